@@ -28,11 +28,22 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use clap::{Parser, Subcommand};
+use camino::Utf8PathBuf;
+use clap::{
+    builder::FalseyValueParser, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser,
+    Subcommand,
+};
 
 #[cfg(feature = "self-update")]
 use crate::update;
-use crate::{doctor, import, pantry, recipe, report, search, seed, server, shopping_list};
+use crate::{
+    backup, bench, collection, completions, config, cookware, doctor, edit,
+    error::ErrorFormat,
+    export, hooks, import, lint, man, migrate, notify, pantry, publish, random, recipe, report,
+    restore, schema, search, seed, server, shopping_list, stats, sync, tag, timer, tui,
+    util::{locale::FractionStyle, temperature::TemperatureUnit, ParserExtension},
+    watch,
+};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -46,10 +57,181 @@ pub struct CliArgs {
     #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, global = true)]
     pub verbosity: u8,
 
+    /// Only log errors, not warnings
+    #[arg(short, long, global = true, conflicts_with = "verbosity")]
+    pub quiet: bool,
+
+    /// Write log messages to a file instead of the terminal
+    ///
+    /// Messages are appended, so the file can collect logs of many runs.
+    #[arg(long, global = true, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+    pub log_file: Option<Utf8PathBuf>,
+
+    /// When to use colors: auto, always or never
+    ///
+    /// auto (the default) uses colors only when writing to a terminal and
+    /// the NO_COLOR environment variable isn't set. Output written to files
+    /// or pipes never contains color codes, unless this is always.
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        hide_possible_values = true
+    )]
+    pub color: Option<clap::ColorChoice>,
+
+    /// Use DIR instead of the global configuration directory
+    ///
+    /// Holds the global cook.toml, aisle.conf, pantry.conf and other files,
+    /// and caches in DIR/cache. Can also be set with the COOK_CONFIG_DIR
+    /// environment variable. Useful to keep all state in one place in
+    /// containers and CI.
+    #[arg(long, global = true, value_name = "DIR", value_hint = clap::ValueHint::DirPath)]
+    pub config_dir: Option<Utf8PathBuf>,
+
+    /// Cooklang syntax extensions to enable, separated by commas
+    ///
+    /// One or more of: modifiers, alias, advanced-units, modes,
+    /// inline-quantities, range-values, timer-requires-time,
+    /// intermediate-preparations, or all. Overrides `parser.extensions`
+    /// in cook.toml. By default no extensions are enabled.
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "NAMES",
+        hide_possible_values = true
+    )]
+    pub extensions: Option<Vec<ParserExtension>>,
+
+    /// Number of threads for parsing recipes, by default one per CPU
+    ///
+    /// Commands that go through the whole collection, like stats, doctor
+    /// and publish, parse recipes in parallel.
+    #[arg(
+        short,
+        long,
+        global = true,
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub jobs: Option<usize>,
+
+    /// Convert temperatures in recipes to celsius or fahrenheit
+    ///
+    /// Temperatures in the steps, like "350°F" or "350 degrees F", are
+    /// converted and rounded to 5 degrees. Overrides `output.temperature`
+    /// in cook.toml.
+    #[arg(long, global = true, value_enum, value_name = "UNIT")]
+    pub temperature: Option<TemperatureUnit>,
+
+    /// Write quantities as fractions: unicode (1½), ascii (1 1/2) or off
+    ///
+    /// Applies to the terminal, Markdown and HTML output, while JSON and
+    /// YAML keep exact decimals. Overrides `output.fractions` and
+    /// `output.fraction_style` in cook.toml.
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "STYLE",
+        hide_possible_values = true
+    )]
+    pub fractions: Option<FractionStyle>,
+
+    /// How to print errors: human or json
+    ///
+    /// json prints one object with the kind of failure, its exit code, the
+    /// message and its causes to stderr, for scripts. Whatever the format,
+    /// cook exits with 3 for recipes with errors, 4 when a recipe or file
+    /// doesn't exist, 5 for invalid configuration and 6 for network errors.
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "FMT",
+        hide_possible_values = true
+    )]
+    pub error_format: Option<ErrorFormat>,
+
+    /// Directory of the recipe collection the command works on: its
+    /// `-b/--base-path`, or the directory given to `cook server`
+    #[arg(skip)]
+    pub base_path: Option<Utf8PathBuf>,
+
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// Prefix of the environment variables that set options
+pub const ENV_PREFIX: &str = "COOK";
+
+impl CliArgs {
+    /// Parse the command line, with environment variables for options that
+    /// aren't given
+    ///
+    /// Every option can be set with `COOK_<COMMAND>_<OPTION>`, like
+    /// `COOK_SERVER_PORT` for `cook server --port` or
+    /// `COOK_SHOPPING_LIST_FORMAT` for `cook shopping-list --format`. Global
+    /// options drop the command (`COOK_COLOR`) and the recipe collection is
+    /// always `COOK_BASE_PATH`.
+    pub fn parse_with_env() -> Self {
+        let matches = with_env(CliArgs::command(), ENV_PREFIX).get_matches();
+        let mut args = CliArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        args.base_path = base_path(&matches);
+        args
+    }
+}
+
+/// The collection given to the innermost command that takes one
+fn base_path(matches: &ArgMatches) -> Option<Utf8PathBuf> {
+    if let Some(path) = matches.subcommand().and_then(|(_, sub)| base_path(sub)) {
+        return Some(path);
+    }
+    // `cook search` calls it base_dir
+    ["base_path", "base_dir"]
+        .into_iter()
+        .find_map(|id| matches.try_get_one::<Utf8PathBuf>(id).ok().flatten())
+        .cloned()
+}
+
+fn with_env(mut command: clap::Command, prefix: &str) -> clap::Command {
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in subcommands {
+        let prefix = format!("{prefix}_{}", env_name(&name));
+        command = command.mut_subcommand(&name, |sub| with_env(sub, &prefix));
+    }
+
+    command.mut_args(|arg| {
+        if arg.get_env().is_some() {
+            return arg;
+        }
+        let name = match arg.get_id().as_str() {
+            "base_path" => format!("{ENV_PREFIX}_BASE_PATH"),
+            _ if arg.is_positional() => return arg,
+            id => format!("{prefix}_{}", env_name(arg.get_long().unwrap_or(id))),
+        };
+        // Listed in the docs, showing them would double the length of --help
+        let arg = match arg.get_action() {
+            ArgAction::Set | ArgAction::Append => arg.env(name),
+            // So COOK_SERVER_OPEN=1 works as well as =true
+            ArgAction::SetTrue => arg.env(name).value_parser(FalseyValueParser::new()),
+            _ => return arg,
+        };
+        arg.hide_env(true)
+    })
+}
+
+/// `shopping-list` becomes `SHOPPING_LIST`
+fn env_name(name: &str) -> String {
+    name.replace('-', "_").to_uppercase()
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// Parse, validate and display recipe files in various formats
@@ -102,6 +284,19 @@ pub enum Command {
     )]
     ShoppingList(shopping_list::ShoppingListArgs),
 
+    /// List the cookware needed for one or more recipes
+    ///
+    /// Gathers the cookware of the recipes, or of all recipes in a menu, into
+    /// a checklist with how many of each item are needed to prepare them
+    /// side by side.
+    ///
+    /// Examples:
+    ///   cook cookware Pizza Salad         # Checklist for two recipes
+    ///   cook cookware --plan week.menu    # Everything a menu needs
+    ///   cook cookware Pizza -f markdown   # As a Markdown task list
+    #[command(long_about = "Gather the cookware of several recipes into a checklist with counts")]
+    Cookware(cookware::CookwareArgs),
+
     /// Initialize a directory with example Cooklang recipes
     ///
     /// Creates a set of sample recipes to help you get started with Cooklang.
@@ -113,6 +308,17 @@ pub enum Command {
     #[command(long_about = "Populate a directory with example Cooklang recipes to get started")]
     Seed(seed::SeedArgs),
 
+    /// Set up and manage a recipe collection
+    ///
+    /// `init` creates the folders, configuration and optionally a git
+    /// repository and example recipes of a new collection in one step.
+    ///
+    /// Examples:
+    ///   cook collection init ~/recipes         # Folders and configuration
+    ///   cook collection init --git --seed      # With git and example recipes
+    #[command(long_about = "Set up a new recipe collection")]
+    Collection(collection::CollectionArgs),
+
     /// Search through your recipe collection for matching text
     ///
     /// Performs a full-text search across all recipe files in the specified directory.
@@ -139,6 +345,7 @@ pub enum Command {
     /// Examples:
     ///   cook import https://example.com/recipe       # Import and convert
     ///   cook import URL --skip-conversion            # Import without converting
+    ///   cook import inbox                            # Import what's dropped in inbox/
     #[command(
         alias = "i",
         long_about = "Import recipes from websites and automatically convert them to Cooklang format"
@@ -152,10 +359,12 @@ pub enum Command {
     /// cards, or any custom format you need.
     ///
     /// The template receives the full recipe data including ingredients,
-    /// steps, metadata, and calculated values.
+    /// steps, metadata, and calculated values. Without a recipe it
+    /// receives the whole collection instead.
     ///
     /// Examples:
     ///   cook report -t card.j2 recipe.cook           # Generate recipe card
+    ///   cook report -t index.j2                      # Index of the collection
     ///   cook report -t nutrition.j2 recipe.cook@2    # Nutrition for 2x recipe
     ///   cook report -t plan.j2 recipe.cook -o out.md # Output to file
     #[command(
@@ -193,6 +402,284 @@ pub enum Command {
     )]
     Pantry(pantry::PantryArgs),
 
+    /// Show statistics about your recipe collection
+    ///
+    /// Summarizes the collection: recipes per directory and tag, the most
+    /// used ingredients and cookware, average cooking time, and recipes
+    /// missing an image or servings metadata.
+    ///
+    /// Examples:
+    ///   cook stats                      # Summary of the current directory
+    ///   cook stats -f json              # Machine readable output
+    ///   cook stats -n 20                # Show the top 20 ingredients
+    #[command(long_about = "Summarize your recipe collection and spot gaps in its metadata")]
+    Stats(stats::StatsArgs),
+
+    /// Pick a random recipe from your collection
+    ///
+    /// Chooses a random recipe, optionally limited to recipes with given
+    /// tags or that can be made within a time limit, and displays it.
+    ///
+    /// Examples:
+    ///   cook random                          # Any recipe
+    ///   cook random --tag dinner --max-time 1h
+    ///   cook random -t vegetarian --path-only
+    #[command(long_about = "Pick a random recipe matching optional tag and time filters")]
+    Random(random::RandomArgs),
+
+    /// Time the main operations on your recipe collection
+    ///
+    /// Times scanning and parsing the whole collection, building the
+    /// metadata index, searching it and generating a shopping list of every
+    /// recipe, and reports how long each phase took and how many recipes
+    /// per second it handled.
+    ///
+    /// Examples:
+    ///   cook bench                      # Time the current directory
+    ///   cook bench -r 10                # Best of 10 runs
+    ///   cook bench -f json              # Numbers to paste in an issue
+    #[command(
+        long_about = "Time parsing, indexing, searching and shopping lists on your collection"
+    )]
+    Bench(bench::BenchArgs),
+
+    /// Browse your recipe collection in an interactive terminal UI
+    ///
+    /// Navigate the recipe tree, fuzzy-search by name or path, preview
+    /// recipes, collect them on the shopping list and open them in your
+    /// editor ($VISUAL or $EDITOR), without starting the web server.
+    ///
+    /// The shopping list is the same one the web interface uses.
+    ///
+    /// Examples:
+    ///   cook tui                        # Browse the current directory
+    ///   cook tui -b ~/recipes           # Browse a specific directory
+    #[command(long_about = "Browse, search and preview recipes in an interactive terminal UI")]
+    Tui(tui::TuiArgs),
+
+    /// Run countdown timers in the terminal
+    ///
+    /// Starts one or more timers with a progress display and rings the
+    /// bell (and sends a desktop notification) when each one finishes.
+    /// Timers can be typed in or taken from a recipe step.
+    ///
+    /// Examples:
+    ///   cook timer "25 min pizza"
+    ///   cook timer "10 min pasta" "4 min sauce"
+    ///   cook timer --from-recipe pizza.cook --step 4
+    #[command(long_about = "Run countdown timers typed in or taken from a recipe step")]
+    Timer(timer::TimerArgs),
+
+    /// Sync your recipe collection with a shared git repository
+    ///
+    /// Commits all changes in the collection with a generated message,
+    /// pulls changes from others, and pushes yours, so a shared collection
+    /// stays up to date with a single command. Conflicts in files cook
+    /// generates, like the shopping list, are resolved automatically.
+    ///
+    /// Examples:
+    ///   cook sync                       # Sync the current directory
+    ///   cook sync -m "Grandma's recipes"
+    ///   cook sync --no-push             # Commit and pull only
+    #[command(long_about = "Commit, pull and push a recipe collection kept in git")]
+    Sync(sync::SyncArgs),
+
+    /// Back up your recipe collection to a single archive
+    ///
+    /// Writes the recipes, images, configuration (aisle, pantry, nutrition)
+    /// and server state like the shopping list into a compressed tar
+    /// archive, together with the global configuration directory.
+    ///
+    /// Examples:
+    ///   cook backup                          # cookbook-<date>.tar.zst
+    ///   cook backup --out ~/cookbook.tar.zst
+    ///   cook backup -b ~/recipes
+    #[command(
+        long_about = "Back up recipes, images, configuration and server state to an archive"
+    )]
+    Backup(backup::BackupArgs),
+
+    /// Restore a recipe collection from a backup archive
+    ///
+    /// Unpacks an archive created by `cook backup`. Existing files that
+    /// differ from the backup are only overwritten with --force, and
+    /// --dry-run shows what would change without writing anything.
+    ///
+    /// Examples:
+    ///   cook restore cookbook-2024-05-01.tar.zst --dry-run
+    ///   cook restore cookbook-2024-05-01.tar.zst -b ~/recipes
+    ///   cook restore cookbook-2024-05-01.tar.zst --force
+    #[command(long_about = "Restore recipes and configuration from a `cook backup` archive")]
+    Restore(restore::RestoreArgs),
+
+    /// Publish your recipe collection as a static website
+    ///
+    /// Exports the collection to plain HTML pages and uploads them to
+    /// GitHub Pages, an SSH server (rsync) or an S3 bucket. Only files
+    /// that changed since the last publish are uploaded.
+    ///
+    /// Examples:
+    ///   cook publish --to gh-pages              # Push to the gh-pages branch
+    ///   cook publish --to me@host:/var/www/food # Upload with rsync
+    ///   cook publish --to s3://my-cookbook      # Sync to an S3 bucket
+    ///   cook publish --out-dir site             # Only export the site
+    #[command(long_about = "Export the collection as a static website and deploy it")]
+    Publish(publish::PublishArgs),
+
+    /// Export recipes to other applications
+    ///
+    /// Pushes recipes, and optionally their ingredients as shopping list
+    /// items, to another application through its API.
+    ///
+    /// Examples:
+    ///   cook export grocy --url https://grocy.local --api-key KEY Pizza
+    ///   cook export grocy --all --shopping-list
+    #[command(long_about = "Export recipes and shopping list items to other applications")]
+    Export(export::ExportArgs),
+
+    /// Generate shell completions
+    ///
+    /// Prints a completion script for the given shell. Besides commands
+    /// and options, it completes recipe paths for `recipe` and
+    /// `shopping-list`, and tags for `search`, from the collection in
+    /// the current directory.
+    ///
+    /// Examples:
+    ///   source <(cook completions bash)         # In ~/.bashrc
+    ///   source <(cook completions zsh)          # In ~/.zshrc
+    ///   cook completions fish | source          # In ~/.config/fish/config.fish
+    #[command(long_about = "Generate shell completions with recipe and tag completion")]
+    Completions(completions::CompletionsArgs),
+
+    /// Re-run a cook command whenever recipes change
+    ///
+    /// Watches the collection for changes to .cook and .menu files and
+    /// the config directory, and runs the command again after every
+    /// change. Handy for a live preview in another terminal pane.
+    ///
+    /// Examples:
+    ///   cook watch -- recipe read pizza.cook --format markdown
+    ///   cook watch --clear -- shopping-list pizza.cook salad.cook
+    ///   cook watch --lint                # Validate the collection
+    ///   cook watch --validate --notify   # Report broken recipes as they're saved
+    #[command(long_about = "Watch recipes and re-run a command on every change")]
+    Watch(watch::WatchArgs),
+
+    /// Email the week's meal plan and shopping list
+    ///
+    /// Collects the meals planned in the menus for the coming days and the
+    /// shopping list for their recipes, without what's in the pantry, and
+    /// sends them as an email through the SMTP server set in the [email]
+    /// section of cook.toml. Meant to be run from cron.
+    ///
+    /// Examples:
+    ///   cook notify --email                     # To email.to from cook.toml
+    ///   cook notify --email --to me@example.com
+    ///   cook notify --dry-run --days 3          # Print it instead
+    #[command(long_about = "Send the coming week's meal plan and shopping list by email")]
+    Notify(notify::NotifyArgs),
+
+    /// Move old style `>> key: value` metadata into YAML front matter
+    ///
+    /// Only metadata lines are migrated, other syntax is left as it is.
+    /// Shows a diff of the changes unless --write is given.
+    ///
+    /// Examples:
+    ///   cook migrate                    # Preview the changes
+    ///   cook migrate --write            # Rewrite the recipes
+    ///   cook migrate -b ~/recipes --write
+    #[command(long_about = "Move old style metadata of recipes into front matter")]
+    Migrate(migrate::MigrateArgs),
+
+    /// Check recipes against the collection's own rules
+    ///
+    /// Rules are turned on in the [lint] section of cook.toml, like
+    /// requiring servings, a tag or an image on every recipe, or steps of
+    /// at most a few sentences. Exits with an error when a recipe breaks
+    /// one, for CI. Long steps are split and missing tags taken from the
+    /// recipe's directory with --fix.
+    ///
+    /// Examples:
+    ///   cook lint                       # Report recipes breaking a rule
+    ///   cook lint --fix                 # Fix what can be fixed
+    ///   cook lint -f json               # For scripts
+    ///   cook lint --staged              # Only what's staged in git
+    #[command(long_about = "Check recipes against the collection's lint rules")]
+    Lint(lint::LintArgs),
+
+    /// Install git hooks for a collection kept in git
+    ///
+    /// Adds a pre-commit hook running `cook lint --staged`, so commits
+    /// with recipes that don't parse, or that break the collection's lint
+    /// rules, are stopped before they reach a shared collection.
+    ///
+    /// Examples:
+    ///   cook hooks install              # Check recipes on every commit
+    ///   cook hooks uninstall
+    #[command(long_about = "Install a git pre-commit hook that checks recipes")]
+    Hooks(hooks::HooksArgs),
+
+    /// Print the JSON Schema of recipe metadata
+    ///
+    /// Lists the front matter keys cook understands, like servings, tags,
+    /// times, the source and nutrition, with the values they take. Point
+    /// an editor at it to check and complete metadata while writing.
+    ///
+    /// Examples:
+    ///   cook schema                              # Print the schema
+    ///   cook schema -o .vscode/cook.schema.json
+    #[command(long_about = "Print the JSON Schema of the recipe front matter")]
+    Schema(schema::SchemaArgs),
+
+    /// Add, remove and rename tags across many recipes
+    ///
+    /// Changes the tags in the front matter of the given recipes, every
+    /// recipe with --all, or those matching --where conditions. Shows a
+    /// diff of the changes unless --write is given.
+    ///
+    /// Examples:
+    ///   cook tag list                               # Tags and their counts
+    ///   cook tag add weeknight --where 'time<=30m'
+    ///   cook tag rename vegeterian vegetarian --all --write
+    ///   cook tag remove quick Pancakes.cook --write
+    #[command(long_about = "Add, remove and rename tags across the collection")]
+    Tag(tag::TagArgs),
+
+    /// Show and change settings in cook.toml
+    ///
+    /// Reads and writes the layered configuration files, the global
+    /// cook.toml and the collection's config/cook.toml, so you don't have
+    /// to know where they live. Settings are named section.name.
+    ///
+    /// Examples:
+    ///   cook config list --show-origin  # Every setting and its file
+    ///   cook config get server.port
+    ///   cook config set recipe.scale 2
+    ///   cook config set ai.model claude-sonnet-4-5 --global
+    ///   cook config unset recipe.scale
+    #[command(long_about = "Show and change settings in the layered cook.toml files")]
+    Config(config::ConfigArgs),
+
+    /// Open a recipe in your editor and check it when you're done
+    ///
+    /// Finds the recipe by path or by words in its path, letting you pick
+    /// when several recipes match, and opens it in $VISUAL or $EDITOR.
+    /// After the editor closes the recipe is parsed again, and if it has
+    /// errors you can go straight back to fix them.
+    ///
+    /// Examples:
+    ///   cook edit pizza                 # Finds Neapolitan Pizza.cook
+    ///   cook edit Breakfast/pancakes.cook
+    ///   EDITOR="code --wait" cook edit risotto
+    #[command(long_about = "Edit a recipe in $VISUAL/$EDITOR and validate it after saving")]
+    Edit(edit::EditArgs),
+
+    /// Generate man pages for cook and all its commands
+    ///
+    /// Meant for packagers, who can ship the pages with the binary.
+    #[command(hide = true)]
+    Man(man::ManArgs),
+
     /// Update CookCLI to the latest version
     ///
     /// Checks for new releases on GitHub and automatically downloads and
@@ -210,4 +697,8 @@ pub enum Command {
     )]
     #[cfg(feature = "self-update")]
     Update(update::UpdateArgs),
+
+    /// Run a `cook-<name>` plugin found on PATH
+    #[command(external_subcommand)]
+    External(Vec<String>),
 }
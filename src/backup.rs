@@ -0,0 +1,200 @@
+use anyhow::{Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use crate::{
+    util::{resolve_to_absolute_path, walk::WalkPolicy},
+    Context,
+};
+
+/// First entry of every backup archive
+pub(crate) const MANIFEST: &str = "manifest.json";
+/// Archive directory holding the recipe collection
+pub(crate) const COLLECTION_DIR: &str = "collection";
+/// Archive directory holding the global configuration directory
+pub(crate) const GLOBAL_CONFIG_DIR: &str = "global-config";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    pub cook_version: String,
+    pub created: String,
+    /// Where the collection was backed up from
+    pub collection: String,
+    pub files: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct BackupArgs {
+    /// Archive to write
+    ///
+    /// Defaults to cookbook-<date>.tar.zst in the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    out: Option<Utf8PathBuf>,
+
+    /// Directory of the recipe collection
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// zstd compression level, from 1 (fastest) to 19 (smallest)
+    #[arg(short, long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(1..=19))]
+    level: i32,
+}
+
+pub fn run(ctx: &Context, args: BackupArgs) -> Result<()> {
+    let base_path = resolve_to_absolute_path(args.base_path.as_ref().unwrap_or(ctx.base_path()))?;
+    let base_path = &base_path;
+    let out = args.out.unwrap_or_else(|| {
+        Utf8PathBuf::from(format!(
+            "cookbook-{}.tar.zst",
+            chrono::Local::now().format("%Y-%m-%d")
+        ))
+    });
+
+    let (collection_files, global_files) = write_backup(ctx, base_path, &out, args.level)?;
+
+    println!(
+        "✓ Backed up {collection_files} files from {base_path}{} to {out}",
+        if global_files == 0 {
+            String::new()
+        } else {
+            format!(" and {global_files} global config files")
+        }
+    );
+    Ok(())
+}
+
+/// Write a backup of the collection to `out`
+///
+/// Returns the number of files backed up from the collection and from the
+/// global configuration directory.
+pub(crate) fn write_backup(
+    ctx: &Context,
+    base_path: &Utf8Path,
+    out: &Utf8Path,
+    level: i32,
+) -> Result<(usize, usize)> {
+    // Don't back up the archive into itself
+    let skip = resolve_to_absolute_path(out).ok();
+
+    let walk = Walk {
+        skip: skip.as_deref(),
+        follow_symlinks: WalkPolicy::current().follow_symlinks,
+    };
+    let mut files = Vec::new();
+    walk.collect_files(base_path, base_path, &mut HashSet::new(), &mut files)?;
+    let collection_files = files.len();
+
+    let mut global_files = Vec::new();
+    if let Some(global) = ctx.global_config_dir().filter(|d| d.is_dir()) {
+        walk.collect_files(&global, &global, &mut HashSet::new(), &mut global_files)?;
+    }
+
+    let manifest = Manifest {
+        cook_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: chrono::Local::now().to_rfc3339(),
+        collection: base_path.to_string(),
+        files: files.len() + global_files.len(),
+    };
+
+    let file = File::create(out).with_context(|| format!("Failed to create {out}"))?;
+    let encoder = zstd::Encoder::new(file, level)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST, manifest_json.as_slice())?;
+
+    for (relative, path) in &files {
+        builder
+            .append_path_with_name(path, Utf8Path::new(COLLECTION_DIR).join(relative))
+            .with_context(|| format!("Failed to add {path} to the backup"))?;
+    }
+    for (relative, path) in &global_files {
+        builder
+            .append_path_with_name(path, Utf8Path::new(GLOBAL_CONFIG_DIR).join(relative))
+            .with_context(|| format!("Failed to add {path} to the backup"))?;
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok((collection_files, global_files.len()))
+}
+
+/// How the collection is walked for files to back up
+///
+/// Hidden files are always backed up, they may be settings of the collection.
+struct Walk<'a> {
+    skip: Option<&'a Utf8Path>,
+    follow_symlinks: bool,
+}
+
+impl Walk<'_> {
+    /// Collect every file under `dir` as (path relative to `root`, full
+    /// path), sorted so archives list them in a stable order
+    ///
+    /// `visited` holds the directories walked so far, so links back into
+    /// them aren't followed forever.
+    fn collect_files(
+        &self,
+        root: &Utf8Path,
+        dir: &Utf8Path,
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<(Utf8PathBuf, Utf8PathBuf)>,
+    ) -> Result<()> {
+        if let Ok(real) = fs::canonicalize(dir) {
+            visited.insert(real);
+        }
+        let mut entries: Vec<_> = dir
+            .read_dir_utf8()
+            .with_context(|| format!("Failed to read {dir}"))?
+            .filter_map(|entry| {
+                // Names that aren't UTF-8 can't be put in the archive
+                entry
+                    .inspect_err(|e| tracing::warn!("Skipping a file in {dir}: {e}"))
+                    .ok()
+            })
+            .collect();
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+
+        for entry in entries {
+            let path = entry.path();
+            // Version control data isn't part of the collection
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            if self.skip.is_some_and(|skip| skip == path) {
+                continue;
+            }
+            let is_link = entry.file_type()?.is_symlink();
+            if is_link && !self.follow_symlinks {
+                continue;
+            }
+
+            // Follows symlinks, so linked files are backed up by content
+            let Ok(metadata) = fs::metadata(path) else {
+                tracing::warn!("Skipping {path}: broken link or unreadable");
+                continue;
+            };
+            if metadata.is_dir() {
+                if is_link && fs::canonicalize(path).is_ok_and(|real| visited.contains(&real)) {
+                    tracing::warn!("Skipping {path}: links to a directory already backed up");
+                    continue;
+                }
+                self.collect_files(root, path, visited, files)?;
+            } else if metadata.is_file() {
+                let relative = path.strip_prefix(root).unwrap_or(path);
+                files.push((relative.to_path_buf(), path.to_path_buf()));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::{Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::{
+    shopping_list::collect_ingredients,
+    util::{
+        metadata_cache::collection_metadata,
+        parse_entries, resolve_to_absolute_path, tree_entries,
+        walk::{build_tree, search},
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct BenchArgs {
+    /// Directory of the recipe collection to time
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// How many times to run every phase; the fastest run is reported
+    #[arg(short, long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+
+    /// Text to search for in the search phase
+    #[arg(long, default_value = "salt")]
+    query: String,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "human")]
+    format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    version: &'static str,
+    threads: usize,
+    runs: u32,
+    recipes: usize,
+    /// Size of all recipe files
+    bytes: u64,
+    phases: Vec<Phase>,
+}
+
+#[derive(Debug, Serialize)]
+struct Phase {
+    name: &'static str,
+    /// Fastest run in seconds
+    seconds: f64,
+    /// Recipes handled per second
+    recipes_per_second: f64,
+}
+
+impl Phase {
+    fn new(name: &'static str, time: Duration, recipes: usize) -> Self {
+        let seconds = time.as_secs_f64();
+        Self {
+            name,
+            seconds,
+            recipes_per_second: if seconds > 0.0 {
+                recipes as f64 / seconds
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+pub fn run(ctx: &Context, args: BenchArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+
+    let scan = fastest(args.runs, || Ok(build_tree(base_path)?))?;
+    let (recipes, menus): (Vec<_>, Vec<_>) = tree_entries(&scan.1)
+        .into_iter()
+        .partition(|entry| !entry.is_menu());
+    let count = recipes.len() + menus.len();
+    let bytes = recipes
+        .iter()
+        .chain(&menus)
+        .filter_map(|entry| entry.path())
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum();
+
+    let parse = fastest(args.runs, || Ok(parse_entries(recipes.clone(), 1.0)))?;
+    // Broken recipes would stop the shopping list, leave them out
+    let valid: Vec<String> = parse
+        .1
+        .iter()
+        .filter(|(_, recipe)| recipe.is_ok())
+        .filter_map(|(entry, _)| entry.path())
+        .filter_map(|path| resolve_to_absolute_path(path).ok())
+        .map(|path| path.to_string())
+        .collect();
+
+    let index = fastest(args.runs, || {
+        Ok(collection_metadata(base_path, recipes.clone()))
+    })?;
+    let searched = fastest(args.runs, || Ok(search(base_path, &args.query)?))?;
+    let shopping = fastest(args.runs, || {
+        collect_ingredients(ctx, &valid, false).context("Failed to build the shopping list")
+    })?;
+
+    let report = Report {
+        version: env!("CARGO_PKG_VERSION"),
+        threads: rayon::current_num_threads(),
+        runs: args.runs,
+        recipes: count,
+        bytes,
+        phases: vec![
+            Phase::new("scan", scan.0, count),
+            Phase::new("parse", parse.0, recipes.len()),
+            Phase::new("index", index.0, recipes.len()),
+            Phase::new("search", searched.0, count),
+            Phase::new("shopping list", shopping.0, valid.len()),
+        ],
+    };
+
+    match args.format {
+        OutputFormat::Human => print_human(&report, parse.1.len() - valid.len()),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    Ok(())
+}
+
+/// Time `f` `runs` times, returning the fastest time and the last result
+fn fastest<T>(runs: u32, mut f: impl FnMut() -> Result<T>) -> Result<(Duration, T)> {
+    let mut best = Duration::MAX;
+    let mut result = None;
+    for _ in 0..runs {
+        let start = Instant::now();
+        let value = f()?;
+        best = best.min(start.elapsed());
+        result = Some(value);
+    }
+    Ok((best, result.expect("there is at least one run")))
+}
+
+fn print_human(report: &Report, invalid: usize) {
+    println!(
+        "cook {} with {} threads, fastest of {} runs",
+        report.version, report.threads, report.runs
+    );
+    println!(
+        "{} recipes, {:.1} KiB",
+        report.recipes,
+        report.bytes as f64 / 1024.0
+    );
+    if invalid > 0 {
+        println!("{invalid} recipes failed to parse and were left out of the shopping list");
+    }
+    println!();
+
+    let mut table = tabular::Table::new("{:<}  {:>}  {:>}");
+    table.add_row(
+        tabular::Row::new()
+            .with_cell("Phase")
+            .with_cell("Time")
+            .with_cell("Recipes/s"),
+    );
+    for phase in &report.phases {
+        table.add_row(
+            tabular::Row::new()
+                .with_cell(phase.name)
+                .with_cell(format!("{:.1} ms", phase.seconds * 1000.0))
+                .with_cell(format!("{:.0}", phase.recipes_per_second)),
+        );
+    }
+    print!("{table}");
+}
@@ -0,0 +1,186 @@
+use std::fs;
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+
+use crate::{
+    seed::{self, Seed},
+    util::git::{git, git_ok},
+    Context,
+};
+
+/// Folders a new collection starts with
+const DEFAULT_CATEGORIES: &str = "Breakfast,Mains,Sides,Desserts,Drinks,Shared";
+
+/// What a collection kept in git shouldn't commit
+const GITIGNORE: &str = "\
+# Archives from `cook backup`
+cookbook-*.tar.zst
+
+# Editor and system files
+*.swp
+*~
+.DS_Store
+Thumbs.db
+";
+
+#[derive(Debug, Args)]
+pub struct CollectionArgs {
+    #[command(subcommand)]
+    command: CollectionCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum CollectionCommand {
+    /// Set up a new recipe collection
+    ///
+    /// Creates a folder for each category and a config directory with
+    /// aisle.conf, pantry.conf and a commented cook.toml. Files that
+    /// already exist are kept, so it's safe to run in an existing
+    /// collection.
+    ///
+    /// Examples:
+    ///   cook collection init ~/recipes
+    ///   cook collection init --git --seed
+    ///   cook collection init --categories Soups,Bread,Cakes
+    Init(InitArgs),
+}
+
+#[derive(Debug, Args)]
+struct InitArgs {
+    /// Directory of the new collection, the current directory by default
+    ///
+    /// Created if it doesn't exist.
+    #[arg(value_name = "DIR", value_hint = clap::ValueHint::DirPath)]
+    dir: Option<Utf8PathBuf>,
+
+    /// Folders to create, separated by commas
+    ///
+    /// Pass an empty string to create none.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "NAMES",
+        default_value = DEFAULT_CATEGORIES
+    )]
+    categories: Vec<String>,
+
+    /// Start a git repository with a .gitignore, ready for `cook sync`
+    #[arg(long)]
+    git: bool,
+
+    /// Add the example recipes from `cook seed`
+    #[arg(long)]
+    seed: bool,
+
+    /// Add a recipe pack from a git repository or a .zip URL, like `cook
+    /// seed --from`
+    #[arg(long, value_name = "URL", conflicts_with = "seed")]
+    from: Option<String>,
+
+    /// Print what would be created without creating anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub fn run(ctx: &Context, args: CollectionArgs) -> Result<()> {
+    match args.command {
+        CollectionCommand::Init(args) => init(ctx, args),
+    }
+}
+
+fn init(ctx: &Context, args: InitArgs) -> Result<()> {
+    let path = args.dir.as_ref().unwrap_or(ctx.base_path());
+    if path.is_file() {
+        bail!("{path} is not a directory");
+    }
+
+    let categories: Vec<&str> = args
+        .categories
+        .iter()
+        .map(|name| name.trim().trim_matches('/'))
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let mut files = seed::config_templates()?;
+    if args.git {
+        files.push(Seed {
+            path: ".gitignore".to_string(),
+            content: GITIGNORE.as_bytes().into(),
+        });
+    }
+    if let Some(url) = &args.from {
+        files.extend(seed::fetch_pack(url)?);
+    } else if args.seed {
+        // Without the configuration files that are there already
+        let config: Vec<String> = files.iter().map(|file| file.path.clone()).collect();
+        files.extend(
+            seed::embedded(&[], false)?
+                .into_iter()
+                .filter(|seed| !config.contains(&seed.path)),
+        );
+    }
+
+    let in_repo = path.is_dir()
+        && git(path, &["rev-parse", "--is-inside-work-tree"])
+            .is_ok_and(|output| output.status.success());
+
+    if args.dry_run {
+        for category in &categories {
+            let dir = path.join(category);
+            let action = if dir.is_dir() { "exists" } else { "create" };
+            println!("{action:<11}{dir}/");
+        }
+        for file in &files {
+            let file = path.join(&file.path);
+            let action = if file.exists() { "exists" } else { "create" };
+            println!("{action:<11}{file}");
+        }
+        if args.git && !in_repo {
+            println!("git init   {path}");
+        }
+        return Ok(());
+    }
+
+    fs::create_dir_all(path).with_context(|| format!("Failed to create {path}"))?;
+
+    let mut created = 0;
+    let mut kept = 0;
+    for category in &categories {
+        let dir = path.join(category);
+        if dir.is_dir() {
+            continue;
+        }
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {dir}"))?;
+        created += 1;
+    }
+    for file in &files {
+        let target = path.join(&file.path);
+        if target.exists() {
+            kept += 1;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {parent}"))?;
+        }
+        fs::write(&target, &file.content).with_context(|| format!("Failed to write {target}"))?;
+        created += 1;
+    }
+
+    if args.git && !in_repo {
+        git_ok(path, &["init", "--quiet"]).context("Failed to start a git repository")?;
+    }
+
+    println!("✓ Initialized a recipe collection in {path}");
+    println!("  {created} folders and files created, {kept} existing files kept");
+    if args.git {
+        if in_repo {
+            println!("  Already in a git repository, left it as it is");
+        } else {
+            println!("  Started a git repository, commit with `cook sync`");
+        }
+    }
+    println!("\nAdd recipes to the folders, then browse them with `cook server {path}`");
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use clap::Args;
+use clap_complete::{
+    engine::{ArgValueCandidates, ArgValueCompleter, CompletionCandidate, ValueCompleter},
+    env::Shells,
+    PathCompleter, Shell,
+};
+use cooklang_find::{RecipeEntry, RecipeTree};
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+
+use crate::{util::walk::build_tree, Context};
+
+/// Environment variable the completion scripts set to ask cook for candidates
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate completions for
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+pub fn run(_ctx: &Context, args: CompletionsArgs) -> Result<()> {
+    let name = args.shell.to_string();
+    let shells = Shells::builtins();
+    let shell = shells
+        .completer(&name)
+        .with_context(|| format!("Completions are not supported for {name}"))?;
+
+    // The script calls back into this executable for candidates, so recipe
+    // names always match the collection in the current directory
+    let completer = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.to_str().map(str::to_string))
+        .unwrap_or_else(|| "cook".to_string());
+
+    let mut stdout = std::io::stdout().lock();
+    shell.write_registration(COMPLETE_VAR, "cook", "cook", &completer, &mut stdout)?;
+    Ok(())
+}
+
+/// Completes recipe files of the collection in the current directory
+///
+/// Falls back to plain path completion when no recipe matches, so recipes
+/// outside the collection can still be completed.
+pub fn recipes() -> ArgValueCompleter {
+    ArgValueCompleter::new(|current: &OsStr| {
+        let prefix = current.to_string_lossy();
+        let candidates: Vec<CompletionCandidate> = collection_recipes()
+            .into_iter()
+            .filter(|path| path.starts_with(prefix.as_ref()))
+            .map(CompletionCandidate::new)
+            .collect();
+        if candidates.is_empty() {
+            PathCompleter::any().complete(current)
+        } else {
+            candidates
+        }
+    })
+}
+
+/// Completes the tags used in the collection in the current directory
+pub fn tags() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        let mut tags = BTreeSet::new();
+        for entry in collection_entries() {
+            tags.extend(entry.tags());
+        }
+        tags.into_iter().map(CompletionCandidate::new).collect()
+    })
+}
+
+/// Paths of the recipes and menus in the current directory, relative to it
+fn collection_recipes() -> Vec<String> {
+    let base = Utf8Path::new(".");
+    collection_entries()
+        .iter()
+        .filter_map(|entry| entry.path())
+        .map(|path| path.strip_prefix(base).unwrap_or(path).to_string())
+        .collect()
+}
+
+fn collection_entries() -> Vec<RecipeEntry> {
+    fn collect(tree: &RecipeTree, out: &mut Vec<RecipeEntry>) {
+        if let Some(entry) = &tree.recipe {
+            out.push(entry.clone());
+        }
+        for child in tree.children.values() {
+            collect(child, out);
+        }
+    }
+
+    // Errors can't be reported while completing, there's just nothing to offer
+    let Ok(tree) = build_tree(Utf8Path::new(".")) else {
+        return Vec::new();
+    };
+    let mut entries = Vec::new();
+    collect(&tree, &mut entries);
+    entries
+}
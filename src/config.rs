@@ -0,0 +1,863 @@
+//! Layered `cook.toml` configuration
+//!
+//! Settings are read from `cook.toml` in the global configuration directory,
+//! then from `config/cook.toml` in the recipe collection, which overrides
+//! the global values. `COOK_<SECTION>_<NAME>` environment variables, like
+//! `COOK_AI_PROVIDER`, override the files, and command line flags override
+//! everything.
+//!
+//! `cook config` reads and changes the files without editing them by hand.
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use toml_edit::DocumentMut;
+
+use crate::{
+    global_file_path,
+    server::WebDavMode,
+    util::{
+        images::ImageFormat,
+        lint::LintRules,
+        locale::{FractionStyle, Fractions, DEFAULT_FRACTION_PRECISION},
+        rounding::Rounding,
+        seasons::Hemisphere,
+        temperature::TemperatureUnit,
+        walk::WalkPolicy,
+        ParserExtension,
+    },
+    Context, LOCAL_CONFIG_DIR,
+};
+
+/// Name of the configuration file
+pub const CONFIG_FILE: &str = "cook.toml";
+
+/// Every setting, as section.name
+const SETTINGS: &[&str] = &[
+    "recipe.scale",
+    "recipe.format",
+    "recipe.default_servings",
+    "files.aisle",
+    "files.pantry",
+    "files.nutrition",
+    "files.units",
+    "files.substitutions",
+    "files.seasons",
+    "server.port",
+    "server.host",
+    "server.open",
+    "server.track_views",
+    "server.webdav",
+    "server.inbox",
+    "server.database",
+    "server.public_url",
+    "ai.provider",
+    "ai.model",
+    "ai.api_key_env",
+    "email.smtp_host",
+    "email.smtp_port",
+    "email.smtp_user",
+    "email.password_env",
+    "email.tls",
+    "email.from",
+    "email.to",
+    "output.pretty",
+    "output.locale",
+    "output.fractions",
+    "output.fraction_style",
+    "output.fraction_precision",
+    "output.temperature",
+    "parser.extensions",
+    "collection.follow_symlinks",
+    "collection.hidden",
+    "rounding.enabled",
+    "rounding.unitless",
+    "lint.require_servings",
+    "lint.require_tags",
+    "lint.require_image",
+    "lint.max_step_sentences",
+    "inbox.dir",
+    "inbox.output_dir",
+    "inbox.ai",
+    "inbox.webhook",
+    "images.format",
+    "images.max_size",
+    "images.quality",
+    "seasons.hemisphere",
+];
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub recipe: RecipeConfig,
+    pub files: FilesConfig,
+    pub server: ServerConfig,
+    pub ai: AiConfig,
+    pub email: EmailConfig,
+    pub output: OutputConfig,
+    pub parser: ParserConfig,
+    pub collection: CollectionConfig,
+    pub rounding: RoundingConfig,
+    pub lint: LintConfig,
+    pub inbox: InboxConfig,
+    pub images: ImagesConfig,
+    pub seasons: SeasonsConfig,
+    /// `[aliases]`: other names of ingredients, from alias to the name used
+    /// instead, like `scallion = "green onion"`
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// `[recipe]`: defaults for the recipe commands
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecipeConfig {
+    /// Scaling factor used when none is given
+    pub scale: Option<f64>,
+    /// Output format of `cook recipe`, like "markdown"
+    pub format: Option<String>,
+    /// Servings recipes are scaled to when no scaling factor is given
+    pub default_servings: Option<f64>,
+}
+
+/// `[files]`: configuration files to use instead of the ones found in the
+/// `config` directories
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    pub aisle: Option<Utf8PathBuf>,
+    pub pantry: Option<Utf8PathBuf>,
+    pub nutrition: Option<Utf8PathBuf>,
+    pub units: Option<Utf8PathBuf>,
+    pub substitutions: Option<Utf8PathBuf>,
+    pub seasons: Option<Utf8PathBuf>,
+}
+
+/// `[server]`: defaults for `cook server`
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub port: Option<u16>,
+    pub host: Option<bool>,
+    pub open: Option<bool>,
+    /// Count recipe views, same as `cook server --track-views`
+    pub track_views: Option<bool>,
+    /// Share the collection over WebDAV, same as `cook server --webdav`
+    pub webdav: Option<WebDavMode>,
+    /// Import recipes from the inbox, same as `cook server --inbox`
+    pub inbox: Option<bool>,
+    /// The server's database, relative to the collection
+    pub database: Option<Utf8PathBuf>,
+    /// Address the server is reached at, like `https://recipes.example.com`,
+    /// for links that leave the web UI
+    pub public_url: Option<String>,
+    /// `[[server.schedule]]`: tasks the server runs on a schedule
+    pub schedule: Option<Vec<ScheduledTask>>,
+}
+
+/// A task `cook server` runs on a schedule
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduledTask {
+    /// When to run, as a cron expression like "0 3 * * *"
+    pub cron: String,
+    pub task: TaskKind,
+    /// Where `backup` writes its archives, relative to the collection
+    pub dir: Option<Utf8PathBuf>,
+    /// Number of archives `backup` keeps, the oldest are removed
+    pub keep: Option<usize>,
+    /// Address `webhook` pings
+    pub url: Option<String>,
+    /// Recipients of `digest`, instead of email.to
+    pub to: Option<Vec<String>>,
+    /// Number of days `digest` covers
+    pub days: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskKind {
+    /// Rebuild the recipe index from scratch
+    Reindex,
+    /// Back up the collection like `cook backup`
+    Backup,
+    /// Send a request to a URL, like a health check service
+    Webhook,
+    /// Email the meal plan like `cook notify --email`
+    Digest,
+}
+
+/// `[ai]`: the provider used by AI-assisted features
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AiConfig {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    /// Environment variable holding the API key
+    pub api_key_env: Option<String>,
+}
+
+/// `[email]`: the SMTP server `cook notify --email` sends through
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmailConfig {
+    pub smtp_host: Option<String>,
+    /// 587 for STARTTLS and 465 for TLS by default
+    pub smtp_port: Option<u16>,
+    pub smtp_user: Option<String>,
+    /// Environment variable holding the SMTP password
+    pub password_env: Option<String>,
+    pub tls: Option<SmtpTls>,
+    /// Sender, like "Cook <cook@example.com>"
+    pub from: Option<String>,
+    /// Recipients of the digest
+    pub to: Option<Vec<String>>,
+}
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Upgrade a plain connection
+    #[default]
+    Starttls,
+    /// TLS from the start
+    Tls,
+    /// No encryption, for a relay on the same machine
+    None,
+}
+
+/// `[output]`: output preferences shared by all commands
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Indent JSON and YAML output
+    pub pretty: Option<bool>,
+    /// Language quantities are written in, like "de" for "1,5 EL"
+    pub locale: Option<String>,
+    /// Write common fractions as ½, ⅓ and so on
+    pub fractions: Option<bool>,
+    /// "unicode" for 1½, "ascii" for 1 1/2, or "off"
+    pub fraction_style: Option<FractionStyle>,
+    /// Largest denominator of fractions, 8 by default
+    pub fraction_precision: Option<u32>,
+    /// Convert temperatures in recipes to this scale
+    pub temperature: Option<TemperatureUnit>,
+}
+
+impl OutputConfig {
+    /// How fractions are written, from `fractions` and `fraction_style`
+    pub fn fraction_style(&self) -> Option<FractionStyle> {
+        match (self.fractions, self.fraction_style) {
+            (Some(false), _) => Some(FractionStyle::Off),
+            (Some(true), style) => Some(style.unwrap_or(FractionStyle::Unicode)),
+            (None, style) => style,
+        }
+    }
+
+    /// Fractions in `style`, with the configured precision
+    pub fn fractions(&self, style: FractionStyle) -> Option<Fractions> {
+        (style != FractionStyle::Off).then(|| Fractions {
+            style,
+            precision: self
+                .fraction_precision
+                .unwrap_or(DEFAULT_FRACTION_PRECISION),
+        })
+    }
+}
+
+/// `[parser]`: how recipes are parsed
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParserConfig {
+    /// Cooklang syntax extensions to enable, like ["modifiers", "alias"]
+    pub extensions: Option<Vec<ParserExtension>>,
+}
+
+/// `[collection]`: which files in the collection are recipes
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectionConfig {
+    /// Include recipes in symlinked files and directories, true by default
+    pub follow_symlinks: Option<bool>,
+    /// Include hidden files and directories, true by default
+    pub hidden: Option<bool>,
+}
+
+impl CollectionConfig {
+    pub fn walk_policy(&self) -> WalkPolicy {
+        let default = WalkPolicy::default();
+        WalkPolicy {
+            follow_symlinks: self.follow_symlinks.unwrap_or(default.follow_symlinks),
+            hidden: self.hidden.unwrap_or(default.hidden),
+            skip: default.skip,
+        }
+    }
+}
+
+/// `[rounding]`: steps scaled quantities are rounded to
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoundingConfig {
+    /// Round scaled quantities, true by default
+    pub enabled: Option<bool>,
+    /// Step for quantities without a unit, like eggs
+    pub unitless: Option<f64>,
+    /// Steps by unit, added to the defaults; 0 keeps the unit exact
+    pub units: Option<BTreeMap<String, f64>>,
+}
+
+impl RoundingConfig {
+    /// The rounding rules, `None` if rounding is turned off
+    pub fn rules(&self) -> Option<Rounding> {
+        if !self.enabled.unwrap_or(true) {
+            return None;
+        }
+        Some(Rounding::new(
+            &self.units.clone().unwrap_or_default(),
+            self.unitless,
+        ))
+    }
+}
+
+/// `[lint]`: rules `cook lint` checks recipes against, all off by default
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Every recipe says how many it serves
+    pub require_servings: Option<bool>,
+    /// Every recipe has at least one tag
+    pub require_tags: Option<bool>,
+    /// Every recipe has an image
+    pub require_image: Option<bool>,
+    /// Steps have at most this many sentences
+    pub max_step_sentences: Option<usize>,
+}
+
+impl LintConfig {
+    pub fn rules(&self) -> LintRules {
+        LintRules {
+            require_servings: self.require_servings.unwrap_or(false),
+            require_tags: self.require_tags.unwrap_or(false),
+            require_image: self.require_image.unwrap_or(false),
+            max_step_sentences: self.max_step_sentences.filter(|max| *max > 0),
+        }
+    }
+}
+
+/// `[inbox]`: the folder `cook import inbox` and `cook server --inbox`
+/// import recipes from
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InboxConfig {
+    /// Directory recipes are dropped into, relative to the collection,
+    /// `inbox` by default
+    pub dir: Option<Utf8PathBuf>,
+    /// Directory of the collection imported recipes go to
+    pub output_dir: Option<Utf8PathBuf>,
+    /// Convert photos and text without an ingredient list with the AI
+    /// provider
+    pub ai: Option<bool>,
+    /// URL every import is posted to
+    pub webhook: Option<String>,
+}
+
+impl InboxConfig {
+    /// The inbox, relative to the collection unless absolute
+    pub fn dir(&self) -> &Utf8Path {
+        self.dir.as_deref().unwrap_or(Utf8Path::new("inbox"))
+    }
+}
+
+/// `[images]`: how photos added to recipes are stored
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImagesConfig {
+    /// "jpeg" or "webp"
+    pub format: Option<ImageFormat>,
+    /// Longest side in pixels, larger photos are scaled down
+    pub max_size: Option<u32>,
+    /// Encoding quality, 1 to 100
+    pub quality: Option<u8>,
+}
+
+/// `[seasons]`: which calendar says when produce is in season
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeasonsConfig {
+    /// "north" or "south", the bundled calendar is moved by six months in
+    /// the south
+    pub hemisphere: Option<Hemisphere>,
+}
+
+impl Config {
+    /// Load the global configuration overridden by the collection's
+    ///
+    /// Files that can't be read are skipped with a warning, `cook doctor
+    /// config` reports the details.
+    pub fn load(base_path: &Utf8Path) -> Self {
+        let mut config = Config::default();
+        for path in Self::paths(base_path) {
+            match Self::read(&path) {
+                Ok(Some(layer)) => config = config.merge(layer),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Ignoring {path}: {e:#}"),
+            }
+        }
+        config.merge(Self::from_env())
+    }
+
+    /// Settings from environment variables
+    fn from_env() -> Self {
+        let mut table = toml::Table::new();
+        for (key, value, _) in env_values() {
+            insert(&mut table, &key, value);
+        }
+        // Every value was checked on its own
+        toml::Value::Table(table).try_into().unwrap_or_default()
+    }
+
+    /// The configuration files, from lowest to highest precedence
+    pub fn paths(base_path: &Utf8Path) -> Vec<Utf8PathBuf> {
+        let mut paths = Vec::new();
+        if let Ok(global) = Self::global_path() {
+            paths.push(global);
+        }
+        paths.push(Self::local_path(base_path));
+        paths
+    }
+
+    /// `cook.toml` in the global configuration directory
+    pub fn global_path() -> Result<Utf8PathBuf> {
+        global_file_path(CONFIG_FILE)
+    }
+
+    /// `cook.toml` in the collection's config directory
+    pub fn local_path(base_path: &Utf8Path) -> Utf8PathBuf {
+        base_path.join(LOCAL_CONFIG_DIR).join(CONFIG_FILE)
+    }
+
+    /// Read a single configuration file, `None` if it doesn't exist
+    ///
+    /// Relative paths in `[files]` are resolved against the file's directory.
+    pub fn read(path: &Utf8Path) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let mut config: Config =
+            toml::from_str(&content).with_context(|| format!("Invalid configuration in {path}"))?;
+
+        let dir = path.parent().unwrap_or(Utf8Path::new("."));
+        for file in [
+            &mut config.files.aisle,
+            &mut config.files.pantry,
+            &mut config.files.nutrition,
+            &mut config.files.units,
+            &mut config.files.substitutions,
+            &mut config.files.seasons,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if file.is_relative() {
+                *file = dir.join(&*file);
+            }
+        }
+        Ok(Some(config))
+    }
+
+    /// Values set in `over` replace the ones in `self`
+    fn merge(self, over: Config) -> Config {
+        Config {
+            recipe: RecipeConfig {
+                scale: over.recipe.scale.or(self.recipe.scale),
+                format: over.recipe.format.or(self.recipe.format),
+                default_servings: over
+                    .recipe
+                    .default_servings
+                    .or(self.recipe.default_servings),
+            },
+            files: FilesConfig {
+                aisle: over.files.aisle.or(self.files.aisle),
+                pantry: over.files.pantry.or(self.files.pantry),
+                nutrition: over.files.nutrition.or(self.files.nutrition),
+                units: over.files.units.or(self.files.units),
+                substitutions: over.files.substitutions.or(self.files.substitutions),
+                seasons: over.files.seasons.or(self.files.seasons),
+            },
+            server: ServerConfig {
+                port: over.server.port.or(self.server.port),
+                host: over.server.host.or(self.server.host),
+                open: over.server.open.or(self.server.open),
+                track_views: over.server.track_views.or(self.server.track_views),
+                webdav: over.server.webdav.or(self.server.webdav),
+                inbox: over.server.inbox.or(self.server.inbox),
+                database: over.server.database.or(self.server.database),
+                public_url: over.server.public_url.or(self.server.public_url),
+                schedule: over.server.schedule.or(self.server.schedule),
+            },
+            ai: AiConfig {
+                provider: over.ai.provider.or(self.ai.provider),
+                model: over.ai.model.or(self.ai.model),
+                api_key_env: over.ai.api_key_env.or(self.ai.api_key_env),
+            },
+            email: EmailConfig {
+                smtp_host: over.email.smtp_host.or(self.email.smtp_host),
+                smtp_port: over.email.smtp_port.or(self.email.smtp_port),
+                smtp_user: over.email.smtp_user.or(self.email.smtp_user),
+                password_env: over.email.password_env.or(self.email.password_env),
+                tls: over.email.tls.or(self.email.tls),
+                from: over.email.from.or(self.email.from),
+                to: over.email.to.or(self.email.to),
+            },
+            output: OutputConfig {
+                pretty: over.output.pretty.or(self.output.pretty),
+                locale: over.output.locale.or(self.output.locale),
+                fractions: over.output.fractions.or(self.output.fractions),
+                fraction_style: over.output.fraction_style.or(self.output.fraction_style),
+                fraction_precision: over
+                    .output
+                    .fraction_precision
+                    .or(self.output.fraction_precision),
+                temperature: over.output.temperature.or(self.output.temperature),
+            },
+            parser: ParserConfig {
+                extensions: over.parser.extensions.or(self.parser.extensions),
+            },
+            collection: CollectionConfig {
+                follow_symlinks: over
+                    .collection
+                    .follow_symlinks
+                    .or(self.collection.follow_symlinks),
+                hidden: over.collection.hidden.or(self.collection.hidden),
+            },
+            rounding: RoundingConfig {
+                enabled: over.rounding.enabled.or(self.rounding.enabled),
+                unitless: over.rounding.unitless.or(self.rounding.unitless),
+                // Steps for different units add up
+                units: match (self.rounding.units, over.rounding.units) {
+                    (Some(mut units), Some(over)) => {
+                        units.extend(over);
+                        Some(units)
+                    }
+                    (units, over) => over.or(units),
+                },
+            },
+            lint: LintConfig {
+                require_servings: over.lint.require_servings.or(self.lint.require_servings),
+                require_tags: over.lint.require_tags.or(self.lint.require_tags),
+                require_image: over.lint.require_image.or(self.lint.require_image),
+                max_step_sentences: over
+                    .lint
+                    .max_step_sentences
+                    .or(self.lint.max_step_sentences),
+            },
+            inbox: InboxConfig {
+                dir: over.inbox.dir.or(self.inbox.dir),
+                output_dir: over.inbox.output_dir.or(self.inbox.output_dir),
+                ai: over.inbox.ai.or(self.inbox.ai),
+                webhook: over.inbox.webhook.or(self.inbox.webhook),
+            },
+            images: ImagesConfig {
+                format: over.images.format.or(self.images.format),
+                max_size: over.images.max_size.or(self.images.max_size),
+                quality: over.images.quality.or(self.images.quality),
+            },
+            seasons: SeasonsConfig {
+                hemisphere: over.seasons.hemisphere.or(self.seasons.hemisphere),
+            },
+            // Aliases of both files add up
+            aliases: {
+                let mut aliases = self.aliases;
+                aliases.extend(over.aliases);
+                aliases
+            },
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
+
+    /// Directory of the recipe collection
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, global = true, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Show every setting that is set, with the value that applies
+    #[command(alias = "ls")]
+    List(ListArgs),
+
+    /// Print the value that applies for a setting
+    ///
+    /// Exits with an error when the setting isn't set anywhere.
+    ///
+    /// Example:
+    ///   cook config get server.port
+    Get(GetArgs),
+
+    /// Change a setting in the collection's cook.toml
+    ///
+    /// The value is read as TOML, so 8080 is a number and true a boolean.
+    /// Anything that isn't a TOML value is stored as a string; quote it to
+    /// force a string, like '"2"'. Comments and formatting in the file are
+    /// kept.
+    ///
+    /// Examples:
+    ///   cook config set recipe.scale 2
+    ///   cook config set ai.provider anthropic --global
+    Set(SetArgs),
+
+    /// Remove a setting from the collection's cook.toml
+    ///
+    /// Example:
+    ///   cook config unset recipe.format
+    Unset(UnsetArgs),
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    /// Show which file or environment variable each value comes from
+    #[arg(long)]
+    show_origin: bool,
+}
+
+#[derive(Debug, Args)]
+struct GetArgs {
+    /// Setting to print, as section.name
+    key: String,
+}
+
+#[derive(Debug, Args)]
+struct SetArgs {
+    /// Setting to change, as section.name
+    key: String,
+
+    /// New value
+    value: String,
+
+    /// Change the global cook.toml instead of the collection's
+    #[arg(short, long)]
+    global: bool,
+}
+
+#[derive(Debug, Args)]
+struct UnsetArgs {
+    /// Setting to remove, as section.name
+    key: String,
+
+    /// Change the global cook.toml instead of the collection's
+    #[arg(short, long)]
+    global: bool,
+}
+
+pub fn run(ctx: &Context, args: ConfigArgs) -> Result<()> {
+    let base_path = args.base_path.as_deref().unwrap_or(ctx.base_path());
+
+    match args.command {
+        ConfigCommand::List(list) => run_list(base_path, list),
+        ConfigCommand::Get(get) => run_get(base_path, get),
+        ConfigCommand::Set(set) => {
+            let path = target_path(base_path, set.global)?;
+            let value = set
+                .value
+                .parse::<toml_edit::Value>()
+                .unwrap_or_else(|_| toml_edit::Value::from(set.value.as_str()));
+            edit(&path, &set.key, Some(value))?;
+            println!("✓ Set {} in {path}", set.key);
+            Ok(())
+        }
+        ConfigCommand::Unset(unset) => {
+            let path = target_path(base_path, unset.global)?;
+            edit(&path, &unset.key, None)?;
+            println!("✓ Removed {} from {path}", unset.key);
+            Ok(())
+        }
+    }
+}
+
+fn run_list(base_path: &Utf8Path, args: ListArgs) -> Result<()> {
+    for (key, (value, origin)) in effective_values(base_path)? {
+        if args.show_origin {
+            println!("{origin}\t{key} = {value}");
+        } else {
+            println!("{key} = {value}");
+        }
+    }
+    Ok(())
+}
+
+fn run_get(base_path: &Utf8Path, args: GetArgs) -> Result<()> {
+    let values = effective_values(base_path)?;
+    let Some((value, _)) = values.get(&args.key) else {
+        bail!("{} is not set", args.key);
+    };
+    // Strings are printed without quotes so the output can be used in scripts
+    match value {
+        toml::Value::String(s) => println!("{s}"),
+        other => println!("{other}"),
+    }
+    Ok(())
+}
+
+fn target_path(base_path: &Utf8Path, global: bool) -> Result<Utf8PathBuf> {
+    if global {
+        Config::global_path()
+    } else {
+        Ok(Config::local_path(base_path))
+    }
+}
+
+/// Every setting with its value and the file or environment variable it
+/// comes from, with later files and then the environment overriding earlier
+/// files
+fn effective_values(base_path: &Utf8Path) -> Result<BTreeMap<String, (toml::Value, String)>> {
+    fn flatten(
+        prefix: &str,
+        table: toml::Table,
+        origin: &Utf8Path,
+        out: &mut BTreeMap<String, (toml::Value, String)>,
+    ) {
+        for (name, value) in table {
+            let key = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}.{name}")
+            };
+            match value {
+                toml::Value::Table(table) => flatten(&key, table, origin, out),
+                value => {
+                    out.insert(key, (value, origin.to_string()));
+                }
+            }
+        }
+    }
+
+    let mut values = BTreeMap::new();
+    for path in Config::paths(base_path) {
+        if !path.is_file() {
+            continue;
+        }
+        let content =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path}"))?;
+        let table: toml::Table =
+            toml::from_str(&content).with_context(|| format!("Invalid TOML in {path}"))?;
+        flatten("", table, &path, &mut values);
+    }
+    for (key, value, var) in env_values() {
+        values.insert(key, (value, format!("${var}")));
+    }
+    Ok(values)
+}
+
+/// Settings set with `COOK_<SECTION>_<NAME>` environment variables, like
+/// `COOK_SERVER_PORT` for `server.port`, as (setting, value, variable)
+///
+/// Values are read like `cook config set` reads them, and lists are
+/// separated by commas. Invalid values are skipped with a warning.
+fn env_values() -> Vec<(String, toml::Value, String)> {
+    let mut values = Vec::new();
+    for key in SETTINGS {
+        let var = format!("COOK_{}", key.replace('.', "_").to_uppercase());
+        let Ok(raw) = std::env::var(&var) else {
+            continue;
+        };
+
+        let candidates = [
+            toml::from_str::<toml::Table>(&format!("value = {raw}"))
+                .ok()
+                .and_then(|mut table| table.remove("value")),
+            Some(toml::Value::String(raw.clone())),
+            Some(toml::Value::Array(
+                raw.split(',')
+                    .map(|item| toml::Value::String(item.trim().to_string()))
+                    .collect(),
+            )),
+        ];
+        let valid = candidates.into_iter().flatten().find(|value| {
+            let mut table = toml::Table::new();
+            insert(&mut table, key, value.clone());
+            toml::Value::Table(table).try_into::<Config>().is_ok()
+        });
+        match valid {
+            Some(value) => values.push((key.to_string(), value, var)),
+            None => tracing::warn!("Ignoring {var}: '{raw}' is not a valid {key}"),
+        }
+    }
+    values
+}
+
+/// Set section.name in a table
+fn insert(table: &mut toml::Table, key: &str, value: toml::Value) {
+    let (section, name) = key.split_once('.').expect("settings are section.name");
+    if let toml::Value::Table(section) = table
+        .entry(section)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+    {
+        section.insert(name.to_string(), value);
+    }
+}
+
+/// Set (or with `None` remove) a setting in a file, keeping its formatting
+///
+/// The file is only written if the result is still a valid configuration.
+fn edit(path: &Utf8Path, key: &str, value: Option<toml_edit::Value>) -> Result<()> {
+    let content = if path.is_file() {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?
+    } else {
+        String::new()
+    };
+    let mut doc: DocumentMut = content
+        .parse()
+        .with_context(|| format!("Invalid TOML in {path}"))?;
+
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        bail!("Invalid setting name '{key}', expected section.name like server.port");
+    }
+    let (name, sections) = parts.split_last().expect("split returns at least one part");
+
+    let mut table = doc.as_table_mut();
+    for section in sections {
+        let item = if value.is_some() {
+            table.entry(section).or_insert_with(toml_edit::table)
+        } else {
+            match table.get_mut(section) {
+                Some(item) => item,
+                None => bail!("{key} is not set in {path}"),
+            }
+        };
+        table = item
+            .as_table_mut()
+            .with_context(|| format!("{section} is not a section in {path}"))?;
+    }
+
+    match value {
+        Some(value) => {
+            table.insert(name, toml_edit::value(value));
+        }
+        None => {
+            if table.remove(name).is_none() {
+                bail!("{key} is not set in {path}");
+            }
+        }
+    }
+
+    let new_content = doc.to_string();
+    toml::from_str::<Config>(&new_content).with_context(|| format!("Can't set {key}"))?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir}"))?;
+    }
+    std::fs::write(path, new_content).with_context(|| format!("Failed to write {path}"))?;
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+use yansi::Paint;
+
+use crate::{
+    shopping_list::resolve_entries,
+    util::{
+        cookware::{collect_cookware, CookwareItem, CookwareList},
+        write_to_output,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct CookwareArgs {
+    /// Recipes to gather the cookware of
+    ///
+    /// Recipes can be given by name or path, and directories include all
+    /// their recipes. Scaling factors like "Pizza:2" are accepted, but
+    /// cookware doesn't scale.
+    #[arg(add = crate::completions::recipes())]
+    recipes: Vec<String>,
+
+    /// A menu to gather the cookware of all its recipes
+    ///
+    /// Like passing the menu as one of the recipes.
+    #[arg(long, value_name = "MENU", value_hint = clap::ValueHint::FilePath)]
+    plan: Option<String>,
+
+    /// Output file path (stdout if not specified)
+    ///
+    /// The output format can be inferred from the file extension
+    /// (.json, .yaml, .md)
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    output: Option<Utf8PathBuf>,
+
+    /// Output format for the checklist
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Pretty output format, if available
+    #[arg(long)]
+    pretty: bool,
+
+    /// Don't include the cookware of referenced recipes
+    #[arg(short, long)]
+    ignore_references: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+    Yaml,
+    #[value(alias("md"))]
+    Markdown,
+}
+
+pub fn run(ctx: &Context, args: CookwareArgs) -> Result<()> {
+    let mut entries = args.recipes;
+    entries.extend(args.plan);
+    if entries.is_empty() {
+        bail!("Give the recipes to gather the cookware of, or a menu with --plan");
+    }
+
+    let mut list = CookwareList::new();
+    for (entry, base_path) in resolve_entries(ctx, &entries)? {
+        collect_cookware(
+            &entry,
+            &mut list,
+            &mut Vec::new(),
+            &base_path,
+            args.ignore_references,
+        )?;
+    }
+    let items = list.into_items();
+
+    let format =
+        args.format
+            .unwrap_or_else(|| match args.output.as_ref().and_then(|p| p.extension()) {
+                Some("json") => OutputFormat::Json,
+                Some("yaml" | "yml") => OutputFormat::Yaml,
+                Some("md") => OutputFormat::Markdown,
+                _ => OutputFormat::Human,
+            });
+
+    write_to_output(args.output.as_deref(), |mut w| {
+        match format {
+            OutputFormat::Human => write_human(&items, &mut w)?,
+            OutputFormat::Json if args.pretty => serde_json::to_writer_pretty(w, &items)?,
+            OutputFormat::Json => serde_json::to_writer(w, &items)?,
+            OutputFormat::Yaml => serde_yaml::to_writer(w, &items)?,
+            OutputFormat::Markdown => write_markdown(&items, &mut w)?,
+        }
+        Ok(())
+    })
+}
+
+fn write_human(items: &[CookwareItem], w: &mut impl std::io::Write) -> Result<()> {
+    if items.is_empty() {
+        writeln!(w, "No cookware needed")?;
+        return Ok(());
+    }
+    let width = items
+        .iter()
+        .map(|item| label(item).chars().count())
+        .max()
+        .unwrap_or(0);
+    for item in items {
+        writeln!(
+            w,
+            "[ ] {:<width$}  {}",
+            label(item),
+            item.recipes.join(", ").dim()
+        )?;
+    }
+    Ok(())
+}
+
+fn write_markdown(items: &[CookwareItem], w: &mut impl std::io::Write) -> Result<()> {
+    for item in items {
+        writeln!(w, "- [ ] {}", label(item))?;
+    }
+    Ok(())
+}
+
+/// Like "3 × bowl (optional)"
+fn label(item: &CookwareItem) -> String {
+    let mut label = format!("{} × {}", item.count, item.name);
+    if item.optional {
+        label.push_str(" (optional)");
+    }
+    label
+}
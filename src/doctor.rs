@@ -1,12 +1,24 @@
-use anyhow::Result;
-use camino::Utf8PathBuf;
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::{Args, Subcommand};
-use cooklang_find::build_tree;
+use rayon::prelude::*;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use tracing::warn;
 
-use crate::{util::parse_recipe_from_entry, Context};
+use crate::{
+    config::Config,
+    util::{
+        diagnostics::Diagnostic,
+        get_recipe,
+        images::IMAGE_EXTENSIONS,
+        metadata_cache::collection_metadata,
+        names::{closest_matches, sanitize_stem, slug, stem_problems},
+        parse_entries, parse_recipe_from_entry, tree_entries,
+        walk::build_tree,
+    },
+    Context,
+};
 
 #[derive(Debug, Args)]
 pub struct DoctorArgs {
@@ -56,6 +68,55 @@ enum DoctorCommand {
     ///   cook doctor validate -b ~/recipes # Validate specific directory
     ///   cook doctor validate --strict  # Exit with error code if issues found
     Validate(ValidateArgs),
+
+    /// Check that images referenced in recipe metadata exist
+    ///
+    /// Looks at the `image` (or `images`) metadata of every recipe and
+    /// reports local files that can't be found. URLs are not fetched.
+    ///
+    /// Example:
+    ///   cook doctor images             # Check current directory
+    Images(ImagesArgs),
+
+    /// Check that every link between files resolves
+    ///
+    /// Checks the recipe references (@./Shared/Pizza Dough{}) of recipes
+    /// and menus, and the images in recipe metadata. Each broken link is
+    /// reported with the closest existing names.
+    ///
+    /// Example:
+    ///   cook doctor links              # Check current directory
+    ///   cook doctor links --strict     # Fail if any link is broken
+    Links(LinksArgs),
+
+    /// Check for recipe names that clash or won't work everywhere
+    ///
+    /// Reports recipes whose titles read the same, files in one directory
+    /// whose names only differ in case or punctuation, and file names with
+    /// characters that aren't allowed on some systems or break URLs.
+    ///
+    /// Example:
+    ///   cook doctor names              # Show problems and suggested renames
+    ///   cook doctor names --fix        # Rename the files
+    Names(NamesArgs),
+
+    /// Check that configuration files can be read and parsed
+    ///
+    /// Checks the cook.toml, aisle.conf, pantry.conf, nutrition.csv,
+    /// units.toml, substitutions.toml and seasons.toml files that would be
+    /// used, from the collection's config directory or the global config
+    /// directory.
+    ///
+    /// Example:
+    ///   cook doctor config
+    Config,
+
+    /// Check that the web server port is available
+    ///
+    /// Example:
+    ///   cook doctor port               # Check the port `cook server` uses
+    ///   cook doctor port --port 8080
+    Port(PortArgs),
 }
 
 #[derive(Debug, Args)]
@@ -98,11 +159,63 @@ struct ValidateArgs {
     strict: bool,
 }
 
+#[derive(Debug, Args)]
+struct ImagesArgs {
+    /// Directory to scan for recipe files
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct LinksArgs {
+    /// Directory to scan for recipe files
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// Exit with error code if any link is broken
+    #[arg(long)]
+    strict: bool,
+}
+
+#[derive(Debug, Args)]
+struct NamesArgs {
+    /// Directory to scan for recipe files
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// Rename the files instead of only suggesting names
+    ///
+    /// Images named after a recipe are renamed with it. References from
+    /// other recipes are not updated.
+    #[arg(long)]
+    fix: bool,
+}
+
+#[derive(Debug, Args)]
+struct PortArgs {
+    /// Port to check
+    ///
+    /// Defaults to the port `cook server` would use.
+    #[arg(short, long)]
+    port: Option<u16>,
+}
+
 pub fn run(ctx: &Context, args: DoctorArgs) -> Result<()> {
     match args.command {
         Some(DoctorCommand::Aisle(aisle_args)) => run_aisle(ctx, aisle_args),
         Some(DoctorCommand::Pantry(pantry_args)) => run_pantry(ctx, pantry_args),
         Some(DoctorCommand::Validate(validate_args)) => run_validate(ctx, validate_args),
+        Some(DoctorCommand::Images(images_args)) => run_images(ctx, images_args),
+        Some(DoctorCommand::Links(links_args)) => run_links(ctx, links_args),
+        Some(DoctorCommand::Names(names_args)) => run_names(ctx, names_args),
+        Some(DoctorCommand::Config) => run_config(ctx),
+        Some(DoctorCommand::Port(port_args)) => run_port(ctx, port_args),
         None => {
             // Run all doctor checks
             println!("Running all doctor checks...\n");
@@ -135,6 +248,33 @@ pub fn run(ctx: &Context, args: DoctorArgs) -> Result<()> {
             println!("\n=== Pantry Check ===");
             run_pantry(ctx, PantryArgs { base_path: None })?;
 
+            println!("\n=== Image Check ===");
+            run_images(ctx, ImagesArgs { base_path: None })?;
+
+            println!("\n=== Link Check ===");
+            run_links(
+                ctx,
+                LinksArgs {
+                    base_path: None,
+                    strict: false,
+                },
+            )?;
+
+            println!("\n=== Name Check ===");
+            run_names(
+                ctx,
+                NamesArgs {
+                    base_path: None,
+                    fix: false,
+                },
+            )?;
+
+            println!("\n=== Config Check ===");
+            run_config(ctx)?;
+
+            println!("\n=== Server Port Check ===");
+            run_port(ctx, PortArgs { port: None })?;
+
             Ok(())
         }
     }
@@ -206,72 +346,41 @@ fn run_pantry(ctx: &Context, args: PantryArgs) -> Result<()> {
     // Collect all unique ingredients from all recipes and track which are in pantry
     let mut all_ingredients = BTreeSet::new();
     let mut pantry_ingredients = BTreeSet::new();
-    let mut recipe_count = 0;
+    let entries = tree_entries(&tree);
+    let recipe_count = entries.len();
 
-    // Walk through the tree to find and process all recipes
-    fn process_recipes(
-        tree: &cooklang_find::RecipeTree,
-        all_ingredients: &mut BTreeSet<String>,
-        pantry_ingredients: &mut BTreeSet<String>,
-        pantry: &Option<cooklang::pantry::PantryConf>,
-        recipe_count: &mut usize,
-    ) {
-        // Check if this node has a recipe
-        if let Some(entry) = &tree.recipe {
-            *recipe_count += 1;
-
-            // Parse the recipe
-            let recipe = match parse_recipe_from_entry(entry, 1.0) {
-                Ok(r) => r,
-                Err(e) => {
-                    let name = entry.name().as_deref().unwrap_or("unknown");
-                    warn!("Failed to parse recipe '{name}': {e}");
-                    return;
-                }
-            };
+    for (entry, recipe) in parse_entries(entries, 1.0) {
+        let recipe = match recipe {
+            Ok(r) => r,
+            Err(e) => {
+                let name = entry.name().as_deref().unwrap_or("unknown");
+                warn!("Failed to parse recipe '{name}': {e}");
+                continue;
+            }
+        };
 
-            // Collect ingredients (excluding recipe references)
-            for ingredient in recipe.ingredients.iter() {
-                // Skip recipe references - they shouldn't be in pantry
-                if ingredient.reference.is_some() {
-                    continue;
-                }
+        // Collect ingredients (excluding recipe references)
+        for ingredient in recipe.ingredients.iter() {
+            // Skip recipe references - they shouldn't be in pantry
+            if ingredient.reference.is_some() {
+                continue;
+            }
 
-                if ingredient.modifiers().should_be_listed() {
-                    let name = ingredient.display_name();
-                    let name_str = name.to_string();
-                    all_ingredients.insert(name_str.clone());
+            if ingredient.modifiers().should_be_listed() {
+                let name = ingredient.display_name();
+                let name_str = name.to_string();
+                all_ingredients.insert(name_str.clone());
 
-                    // Check if this ingredient is in pantry
-                    if let Some(pantry_conf) = pantry {
-                        if pantry_conf.has_ingredient(&name_str) {
-                            pantry_ingredients.insert(name_str);
-                        }
+                // Check if this ingredient is in pantry
+                if let Some(pantry_conf) = &pantry {
+                    if pantry_conf.has_ingredient(&name_str) {
+                        pantry_ingredients.insert(name_str);
                     }
                 }
             }
         }
-
-        // Recursively check children
-        for subtree in tree.children.values() {
-            process_recipes(
-                subtree,
-                all_ingredients,
-                pantry_ingredients,
-                pantry,
-                recipe_count,
-            );
-        }
     }
 
-    process_recipes(
-        &tree,
-        &mut all_ingredients,
-        &mut pantry_ingredients,
-        &pantry,
-        &mut recipe_count,
-    );
-
     println!(
         "Scanned {} recipes, found {} unique ingredients",
         recipe_count,
@@ -333,50 +442,33 @@ fn run_aisle(ctx: &Context, args: AisleArgs) -> Result<()> {
 
     // Collect all unique ingredients from all recipes
     let mut all_ingredients = BTreeSet::new();
-    let mut recipe_count = 0;
+    let entries = tree_entries(&tree);
+    let recipe_count = entries.len();
 
-    // Walk through the tree to find and process all recipes
-    fn process_recipes(
-        tree: &cooklang_find::RecipeTree,
-        all_ingredients: &mut BTreeSet<String>,
-        recipe_count: &mut usize,
-    ) {
-        // Check if this node has a recipe
-        if let Some(entry) = &tree.recipe {
-            *recipe_count += 1;
-
-            // Parse the recipe
-            let recipe = match parse_recipe_from_entry(entry, 1.0) {
-                Ok(r) => r,
-                Err(e) => {
-                    let name = entry.name().as_deref().unwrap_or("unknown");
-                    warn!("Failed to parse recipe '{name}': {e}");
-                    return;
-                }
-            };
-
-            // Collect ingredients (excluding recipe references)
-            for ingredient in recipe.ingredients.iter() {
-                // Skip recipe references - they shouldn't be in aisle
-                if ingredient.reference.is_some() {
-                    continue;
-                }
+    for (entry, recipe) in parse_entries(entries, 1.0) {
+        let recipe = match recipe {
+            Ok(r) => r,
+            Err(e) => {
+                let name = entry.name().as_deref().unwrap_or("unknown");
+                warn!("Failed to parse recipe '{name}': {e}");
+                continue;
+            }
+        };
 
-                if ingredient.modifiers().should_be_listed() {
-                    let name = ingredient.display_name();
-                    all_ingredients.insert(name.to_string());
-                }
+        // Collect ingredients (excluding recipe references)
+        for ingredient in recipe.ingredients.iter() {
+            // Skip recipe references - they shouldn't be in aisle
+            if ingredient.reference.is_some() {
+                continue;
             }
-        }
 
-        // Recursively check children
-        for subtree in tree.children.values() {
-            process_recipes(subtree, all_ingredients, recipe_count);
+            if ingredient.modifiers().should_be_listed() {
+                let name = ingredient.display_name();
+                all_ingredients.insert(name.to_string());
+            }
         }
     }
 
-    process_recipes(&tree, &mut all_ingredients, &mut recipe_count);
-
     println!(
         "Scanned {} recipes, found {} unique ingredients",
         recipe_count,
@@ -388,7 +480,8 @@ fn run_aisle(ctx: &Context, args: AisleArgs) -> Result<()> {
         let aisle_info = aisle_conf.ingredients_info();
 
         let missing_ingredients: Vec<_> = all_ingredients
-            .into_iter()
+            .iter()
+            .cloned()
             .filter(|ingredient| {
                 // Check if ingredient is in aisle (case-insensitive)
                 !aisle_info
@@ -410,6 +503,35 @@ fn run_aisle(ctx: &Context, args: AisleArgs) -> Result<()> {
             }
             println!("\nConsider adding these ingredients to your aisle.conf file.");
         }
+
+        // Entries (with all their aliases) that no recipe uses
+        let mut entries: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (name, info) in &aisle_info {
+            let name: &str = name;
+            let common_name: &str = &info.common_name;
+            entries.entry(common_name).or_default().push(name);
+        }
+        let unused: Vec<&str> = entries
+            .into_iter()
+            .filter(|(_, names)| {
+                !names.iter().any(|name| {
+                    all_ingredients
+                        .iter()
+                        .any(|ingredient| ingredient.eq_ignore_ascii_case(name))
+                })
+            })
+            .map(|(common_name, _)| common_name)
+            .collect();
+        if !unused.is_empty() {
+            println!(
+                "\n{} aisle entries are not used by any recipe:",
+                unused.len()
+            );
+            for name in unused {
+                println!("  - {name}");
+            }
+            println!("\nThey may be misspelled, or left over from removed recipes.");
+        }
     } else {
         // No aisle config found - just inform the user
         println!("\nNo aisle configuration found.");
@@ -437,111 +559,96 @@ fn run_validate(ctx: &Context, args: ValidateArgs) -> Result<()> {
     // Track recipe references for validation
     let mut recipe_references = BTreeMap::new();
 
-    // Validate recipes and collect references
-    fn validate_recipes(
-        tree: &cooklang_find::RecipeTree,
-        base_path: &Utf8PathBuf,
-        stats: &mut (usize, usize, usize, usize, usize),
-        recipe_refs: &mut BTreeMap<String, Vec<String>>,
-    ) {
-        if let Some(entry) = &tree.recipe {
-            stats.0 += 1; // total_recipes
-
+    // Read and parse in parallel, then report in tree order
+    let checked: Vec<_> = tree_entries(&tree)
+        .into_par_iter()
+        .map(|entry| {
             let recipe_name = entry.name().as_deref().unwrap_or("unknown");
             let recipe_path = entry
                 .path()
                 .cloned()
                 .unwrap_or_else(|| base_path.join(recipe_name));
-            let relative_path = if let Ok(stripped) = recipe_path.strip_prefix(base_path) {
-                stripped
-            } else {
-                &recipe_path
-            };
+            // Parse with our configured parser to get all errors and warnings
+            let parsed = fs::read_to_string(&recipe_path).map(|content| {
+                let parsed = crate::util::PARSER.parse(&content);
+                (content, parsed)
+            });
+            (recipe_path, parsed)
+        })
+        .collect();
 
-            // Try to read and parse the recipe
-            match fs::read_to_string(&recipe_path) {
-                Ok(content) => {
-                    // Parse with our configured parser to get all errors and warnings
-                    let parsed = crate::util::PARSER.parse(&content);
+    // Validate recipes and collect references
+    for (recipe_path, parsed) in checked {
+        total_recipes += 1;
 
-                    let errors: Vec<_> = parsed.report().errors().collect();
-                    let warnings: Vec<_> = parsed.report().warnings().collect();
+        let relative_path = if let Ok(stripped) = recipe_path.strip_prefix(base_path) {
+            stripped
+        } else {
+            &recipe_path
+        };
 
-                    let has_errors = !errors.is_empty();
-                    let has_warnings = !warnings.is_empty();
+        match parsed {
+            Ok((content, parsed)) => {
+                let (errors, warnings): (Vec<_>, Vec<_>) =
+                    Diagnostic::from_report(parsed.report(), &content)
+                        .into_iter()
+                        .partition(|diag| diag.severity == "error");
 
-                    if has_errors || has_warnings {
-                        println!("\n📄 {relative_path}");
+                let has_errors = !errors.is_empty();
+                let has_warnings = !warnings.is_empty();
 
-                        if has_errors {
-                            stats.1 += 1; // recipes_with_errors
-                            stats.3 += errors.len(); // total_errors
+                if has_errors || has_warnings {
+                    println!("\n📄 {relative_path}");
+
+                    if has_errors {
+                        recipes_with_errors += 1;
+                        total_errors += errors.len();
 
-                            for error in errors {
-                                println!("  ❌ Error: {error}");
-                            }
+                        for error in errors {
+                            println!("  ❌ Error: {}", error.message);
+                            print_snippet(&error, relative_path.as_str(), &content);
                         }
+                    }
 
-                        if has_warnings {
-                            stats.2 += 1; // recipes_with_warnings
-                            stats.4 += warnings.len(); // total_warnings
+                    if has_warnings {
+                        recipes_with_warnings += 1;
+                        total_warnings += warnings.len();
 
-                            for warning in warnings {
-                                println!("  ⚠️  Warning: {warning}");
-                            }
+                        for warning in warnings {
+                            println!("  ⚠️  Warning: {}", warning.message);
+                            print_snippet(&warning, relative_path.as_str(), &content);
                         }
                     }
+                }
 
-                    // Collect recipe references
-                    if let Some(recipe) = parsed.output() {
-                        let mut refs = Vec::new();
-                        for ingredient in &recipe.ingredients {
-                            if let Some(reference) = &ingredient.reference {
-                                // Get the full path of the reference
-                                let ref_path = if reference.components.is_empty() {
-                                    reference.name.clone()
-                                } else {
-                                    reference.path("/")
-                                };
-                                refs.push(ref_path);
-                            }
-                        }
-                        if !refs.is_empty() {
-                            recipe_refs.insert(relative_path.to_string(), refs);
+                // Collect recipe references
+                if let Some(recipe) = parsed.output() {
+                    let mut refs = Vec::new();
+                    for ingredient in &recipe.ingredients {
+                        if let Some(reference) = &ingredient.reference {
+                            // Get the full path of the reference
+                            let ref_path = if reference.components.is_empty() {
+                                reference.name.clone()
+                            } else {
+                                reference.path("/")
+                            };
+                            refs.push(ref_path);
                         }
                     }
-                }
-                Err(e) => {
-                    println!("\n📄 {relative_path}");
-                    println!("  ❌ Error: Failed to read file: {e}");
-                    stats.1 += 1; // recipes_with_errors
-                    stats.3 += 1; // total_errors
+                    if !refs.is_empty() {
+                        recipe_references.insert(relative_path.to_string(), refs);
+                    }
                 }
             }
-        }
-
-        // Recursively check children
-        for subtree in tree.children.values() {
-            validate_recipes(subtree, base_path, stats, recipe_refs);
+            Err(e) => {
+                println!("\n📄 {relative_path}");
+                println!("  ❌ Error: Failed to read file: {e}");
+                recipes_with_errors += 1;
+                total_errors += 1;
+            }
         }
     }
 
-    let mut stats = (
-        total_recipes,
-        recipes_with_errors,
-        recipes_with_warnings,
-        total_errors,
-        total_warnings,
-    );
-    validate_recipes(&tree, base_path, &mut stats, &mut recipe_references);
-    (
-        total_recipes,
-        recipes_with_errors,
-        recipes_with_warnings,
-        total_errors,
-        total_warnings,
-    ) = stats;
-
     // Check recipe references using cooklang_find::get_recipe
     if !recipe_references.is_empty() {
         println!("\n=== Recipe References ===");
@@ -604,3 +711,503 @@ fn run_validate(ctx: &Context, args: ValidateArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// Show where in the recipe a problem is, under its message
+fn print_snippet(diagnostic: &Diagnostic, file: &str, content: &str) {
+    for line in diagnostic.snippet(file, content).lines() {
+        println!("     {line}");
+    }
+}
+
+fn run_images(ctx: &Context, args: ImagesArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let tree = build_tree(base_path)?;
+
+    let mut missing: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut checked = 0;
+
+    fn process_recipes(
+        tree: &cooklang_find::RecipeTree,
+        base_path: &Utf8PathBuf,
+        missing: &mut BTreeMap<String, Vec<String>>,
+        checked: &mut usize,
+    ) {
+        if let Some(entry) = &tree.recipe {
+            if let (Some(path), Ok(recipe)) = (entry.path(), parse_recipe_from_entry(entry, 1.0)) {
+                let dir = path.parent().unwrap_or(base_path);
+                for image in local_images(&recipe) {
+                    *checked += 1;
+                    if !image_exists(image, dir, base_path) {
+                        let relative = path.strip_prefix(base_path).unwrap_or(path);
+                        missing
+                            .entry(relative.to_string())
+                            .or_default()
+                            .push(image.to_string());
+                    }
+                }
+            }
+        }
+
+        for subtree in tree.children.values() {
+            process_recipes(subtree, base_path, missing, checked);
+        }
+    }
+
+    process_recipes(&tree, base_path, &mut missing, &mut checked);
+
+    if missing.is_empty() {
+        println!("✓ All {checked} referenced local images exist");
+        return Ok(());
+    }
+
+    for (recipe, images) in &missing {
+        println!("\n📄 {recipe}");
+        for image in images {
+            println!("  ❌ Missing image: {image}");
+        }
+    }
+    println!("\nAdd the missing files, or fix the paths in the recipes' image metadata.");
+    println!("Paths are relative to the recipe's directory. An image next to the recipe");
+    println!("with the same name (e.g. Pizza.jpg for Pizza.cook) is found without metadata.");
+
+    Ok(())
+}
+
+/// Local images in the metadata of a recipe, URLs are left out
+fn local_images(recipe: &cooklang::Recipe) -> Vec<&str> {
+    ["image", "images", "picture", "pictures"]
+        .iter()
+        .filter_map(|key| recipe.metadata.get(*key))
+        .flat_map(|value| -> Vec<&str> {
+            match value.as_sequence() {
+                Some(seq) => seq.iter().filter_map(|v| v.as_str()).collect(),
+                None => value.as_str().into_iter().collect(),
+            }
+        })
+        .filter(|image| !image.starts_with("http://") && !image.starts_with("https://"))
+        .collect()
+}
+
+/// Images are relative to the recipe's directory, or to the collection
+fn image_exists(image: &str, dir: &Utf8Path, base_path: &Utf8Path) -> bool {
+    let image_path = Utf8Path::new(image);
+    if image_path.is_absolute() {
+        image_path.is_file()
+    } else {
+        dir.join(image_path).is_file() || base_path.join(image_path).is_file()
+    }
+}
+
+fn run_links(ctx: &Context, args: LinksArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let tree = build_tree(base_path)?;
+    let entries = tree_entries(&tree);
+    let relative = |path: &Utf8Path| path.strip_prefix(base_path).unwrap_or(path).to_string();
+
+    // What references can point to, like "Shared/Pizza Dough"
+    let recipe_names: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| entry.path())
+        .map(|path| relative(&path.with_extension("")))
+        .collect();
+
+    let mut broken: BTreeMap<String, Vec<(String, Vec<String>)>> = BTreeMap::new();
+    let mut checked = 0;
+    for (entry, recipe) in parse_entries(entries, 1.0) {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let recipe = match recipe {
+            Ok(recipe) => recipe,
+            Err(e) => {
+                warn!("Failed to parse recipe '{}': {e}", relative(path));
+                continue;
+            }
+        };
+        let dir = path.parent().unwrap_or(base_path).to_path_buf();
+        let mut problems = Vec::new();
+
+        let mut seen = BTreeSet::new();
+        for ingredient in &recipe.ingredients {
+            let Some(reference) = &ingredient.reference else {
+                continue;
+            };
+            let target = if reference.components.is_empty() {
+                reference.name.clone()
+            } else {
+                reference.path("/")
+            };
+            if !seen.insert(target.clone()) {
+                continue;
+            }
+            checked += 1;
+            // Relative to the collection like `cook doctor validate`, or
+            // to the recipe's directory
+            let found = [base_path, &dir]
+                .into_iter()
+                .any(|root| get_recipe(root, &target).is_ok());
+            if !found {
+                let wanted = target.trim_start_matches("./");
+                let suggestions = closest_matches(wanted, &recipe_names);
+                problems.push((format!("Missing recipe: {target}"), suggestions));
+            }
+        }
+
+        for image in local_images(&recipe) {
+            checked += 1;
+            if image_exists(image, &dir, base_path) {
+                continue;
+            }
+            let nearby: Vec<String> = fs::read_dir(&dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter_map(|file| file.file_name().into_string().ok())
+                .filter(|name| {
+                    mime_guess::from_path(name)
+                        .first()
+                        .is_some_and(|mime| mime.type_() == mime_guess::mime::IMAGE)
+                })
+                .collect();
+            let suggestions = closest_matches(image, &nearby);
+            problems.push((format!("Missing image: {image}"), suggestions));
+        }
+
+        if !problems.is_empty() {
+            broken.insert(relative(path), problems);
+        }
+    }
+
+    if broken.is_empty() {
+        println!("✓ All {checked} links resolve");
+        return Ok(());
+    }
+
+    let mut total = 0;
+    for (recipe, problems) in &broken {
+        println!("\n📄 {recipe}");
+        for (problem, suggestions) in problems {
+            total += 1;
+            println!("  ❌ {problem}");
+            if !suggestions.is_empty() {
+                println!("     Did you mean: {}?", suggestions.join(", "));
+            }
+        }
+    }
+    println!(
+        "\n❌ {total} of {checked} links are broken in {} recipe(s)",
+        broken.len()
+    );
+    if args.strict {
+        anyhow::bail!("Found {total} broken links");
+    }
+    Ok(())
+}
+
+/// A recipe file that should have another name
+struct Rename {
+    from: Utf8PathBuf,
+    to: Utf8PathBuf,
+    why: String,
+}
+
+fn run_names(ctx: &Context, args: NamesArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let tree = build_tree(base_path)?;
+    let entries = tree_entries(&tree);
+    let relative = |path: &Utf8Path| path.strip_prefix(base_path).unwrap_or(path).to_string();
+
+    // Titles anywhere in the collection
+    let mut titles: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (entry, metadata) in collection_metadata(base_path, entries.clone()) {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let title = metadata
+            .title
+            .clone()
+            .or_else(|| entry.name().clone())
+            .unwrap_or_default();
+        titles
+            .entry(slug(&title))
+            .or_default()
+            .push(format!("{} ({title})", relative(path)));
+    }
+    titles.retain(|_, recipes| recipes.len() > 1);
+
+    // File names, compared within their directory
+    let mut dirs: BTreeMap<Utf8PathBuf, Vec<Utf8PathBuf>> = BTreeMap::new();
+    for entry in &entries {
+        if let Some(path) = entry.path() {
+            let dir = path.parent().unwrap_or(base_path).to_path_buf();
+            dirs.entry(dir).or_default().push(path.clone());
+        }
+    }
+    let mut renames = Vec::new();
+    for (dir, mut files) in dirs {
+        files.sort();
+        // Slug of every name kept so far, and the file it belongs to
+        let mut taken: BTreeMap<String, Utf8PathBuf> = BTreeMap::new();
+        for path in files {
+            let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+                continue;
+            };
+            let problems = stem_problems(stem);
+            let mut new_stem = if problems.is_empty() {
+                stem.to_string()
+            } else {
+                sanitize_stem(stem)
+            };
+            let mut why = problems.join(", ");
+
+            let key = |stem: &str| format!("{}.{extension}", slug(stem));
+            if let Some(other) = taken.get(&key(&new_stem)) {
+                why = format!("same name as {}", relative(other));
+                let base = new_stem.clone();
+                for n in 2.. {
+                    new_stem = format!("{base} {n}");
+                    let free = !taken.contains_key(&key(&new_stem))
+                        && !dir.join(format!("{new_stem}.{extension}")).exists();
+                    if free {
+                        break;
+                    }
+                }
+            }
+            taken.insert(key(&new_stem), path.clone());
+
+            if new_stem != stem {
+                renames.push(Rename {
+                    to: dir.join(format!("{new_stem}.{extension}")),
+                    from: path,
+                    why,
+                });
+            }
+        }
+    }
+
+    if titles.is_empty() && renames.is_empty() {
+        println!(
+            "✓ All {} recipe names are distinct and portable",
+            entries.len()
+        );
+        return Ok(());
+    }
+
+    if !titles.is_empty() {
+        println!("Recipes with titles that read the same:");
+        for recipes in titles.values() {
+            println!();
+            for recipe in recipes {
+                println!("  ⚠️  {recipe}");
+            }
+        }
+        println!("\nGive them distinct titles so they can be told apart.\n");
+    }
+
+    for rename in &renames {
+        println!("❌ {}: {}", relative(&rename.from), rename.why);
+        println!("   → {}", relative(&rename.to));
+    }
+    if renames.is_empty() {
+        return Ok(());
+    }
+    if !args.fix {
+        println!("\nRun `cook doctor names --fix` to rename the files.");
+        return Ok(());
+    }
+
+    for rename in &renames {
+        rename_with_images(&rename.from, &rename.to)?;
+    }
+    println!("\n✓ Renamed {} recipes", renames.len());
+    println!("References to them from other recipes are not updated,");
+    println!("`cook doctor validate` shows the ones that broke.");
+    Ok(())
+}
+
+/// Rename a recipe and its images, like `Pizza.jpg` and `Pizza.1.jpg` for
+/// `Pizza.cook`
+///
+/// Nothing is renamed when a file would replace another.
+fn rename_with_images(from: &Utf8Path, to: &Utf8Path) -> Result<()> {
+    let (Some(dir), Some(old_stem), Some(new_stem)) =
+        (from.parent(), from.file_stem(), to.file_stem())
+    else {
+        return Ok(());
+    };
+    let mut moves = vec![(from.to_path_buf(), to.to_path_buf())];
+    for entry in dir.read_dir_utf8()?.filter_map(|e| e.ok()) {
+        if let Some(rest) = image_suffix(entry.file_name(), old_stem) {
+            moves.push((
+                entry.path().to_path_buf(),
+                dir.join(format!("{new_stem}.{rest}")),
+            ));
+        }
+    }
+    for (source, target) in &moves {
+        // Only the case changes on case-insensitive file systems
+        let same_file = fs::canonicalize(source).ok() == fs::canonicalize(target).ok();
+        if target.exists() && !same_file {
+            bail!("Can't rename {source} to {target}, it exists already");
+        }
+    }
+    for (source, target) in &moves {
+        fs::rename(source, target)
+            .with_context(|| format!("Failed to rename {source} to {target}"))?;
+    }
+    Ok(())
+}
+
+/// What follows the stem in the name of one of a recipe's images:
+/// "jpg" for `Pizza.jpg`, "1.jpg" for `Pizza.1.jpg`
+///
+/// `Pizza.Napoli.jpg` is the image of another recipe.
+fn image_suffix<'a>(name: &'a str, stem: &str) -> Option<&'a str> {
+    let rest = name.strip_prefix(stem)?.strip_prefix('.')?;
+    let (number, extension) = match rest.split_once('.') {
+        Some((number, extension)) => (Some(number), extension),
+        None => (None, rest),
+    };
+    let numbered = number.map_or(true, |n| {
+        !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())
+    });
+    let image = IMAGE_EXTENSIONS
+        .iter()
+        .any(|ext| ext.eq_ignore_ascii_case(extension));
+    (numbered && image).then_some(rest)
+}
+
+fn run_config(ctx: &Context) -> Result<()> {
+    let mut found = 0;
+    let mut problems = 0;
+
+    for path in Config::paths(ctx.base_path()) {
+        match Config::read(&path) {
+            Ok(Some(_)) => {
+                found += 1;
+                println!("✓ {path}");
+            }
+            Ok(None) => {}
+            Err(e) => {
+                found += 1;
+                problems += 1;
+                println!("❌ {path}: {e:#}");
+                println!(
+                    "   Fix the file; it is ignored until then. See the docs for the settings."
+                );
+            }
+        }
+    }
+
+    let files = [
+        ("aisle", ctx.aisle()),
+        ("pantry", ctx.pantry()),
+        ("nutrition", ctx.nutrition()),
+        ("units", ctx.units()),
+        ("substitutions", ctx.substitutions()),
+        ("seasons", ctx.seasons()),
+    ];
+    for (kind, path) in &files {
+        let Some(path) = path else {
+            continue;
+        };
+        found += 1;
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                problems += 1;
+                println!("❌ {path}: can't be read: {e}");
+                println!("   Check the file's permissions and that it is UTF-8 text.");
+                continue;
+            }
+        };
+
+        let errors: Vec<String> = match *kind {
+            "aisle" => {
+                let result = cooklang::aisle::parse_lenient(&content);
+                let report = result.report();
+                report
+                    .errors()
+                    .chain(report.warnings())
+                    .map(|e| e.to_string())
+                    .collect()
+            }
+            "pantry" => {
+                let result = cooklang::pantry::parse_lenient(&content);
+                let report = result.report();
+                report
+                    .errors()
+                    .chain(report.warnings())
+                    .map(|e| e.to_string())
+                    .collect()
+            }
+            "units" => crate::util::units::CustomUnits::parse(&content)
+                .and_then(|units| units.converter())
+                .err()
+                .map(|e| format!("{e:#}"))
+                .into_iter()
+                .collect(),
+            "substitutions" => crate::util::substitutions::Substitutions::parse(&content)
+                .err()
+                .map(|e| format!("{e:#}"))
+                .into_iter()
+                .collect(),
+            "seasons" => crate::util::seasons::Seasons::parse(&content)
+                .err()
+                .map(|e| format!("{e:#}"))
+                .into_iter()
+                .collect(),
+            _ => crate::util::nutrition::NutritionDb::from_csv(&content)
+                .err()
+                .map(|e| format!("{e:#}"))
+                .into_iter()
+                .collect(),
+        };
+
+        if errors.is_empty() {
+            println!("✓ {path}");
+        } else {
+            problems += 1;
+            println!("❌ {path}:");
+            for error in errors {
+                println!("   - {error}");
+            }
+            println!("   Fix the lines above; see `cook help doctor` and the docs for the format.");
+        }
+    }
+
+    if found == 0 {
+        println!("No configuration files found.");
+        println!("Optional files go in ./config/ or the global config directory:");
+        println!("  - aisle.conf    store sections for shopping lists");
+        println!("  - pantry.conf   pantry inventory");
+        println!("  - nutrition.csv extra nutrition data");
+        println!("  - units.toml    custom units and ingredient densities");
+        println!("  - cook.toml     settings like the default scale and server port");
+    } else if problems == 0 {
+        println!("✓ All {found} configuration files are valid");
+    }
+
+    Ok(())
+}
+
+fn run_port(ctx: &Context, args: PortArgs) -> Result<()> {
+    let port = args
+        .port
+        .or(ctx.config().server.port)
+        .unwrap_or(crate::server::DEFAULT_PORT);
+    match std::net::TcpListener::bind(("127.0.0.1", port)) {
+        Ok(_) => println!("✓ Port {port} is available for `cook server`"),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            println!("❌ Port {port} is already in use");
+            println!(
+                "   Stop the other process (maybe another `cook server`), or pick another port:"
+            );
+            println!("   cook server --port {}", port.wrapping_add(1));
+        }
+        Err(e) => {
+            println!("⚠️  Could not check port {port}: {e}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use cooklang_find::RecipeTree;
+use std::io::{IsTerminal, Write};
+
+use crate::{
+    util::{diagnostics::Diagnostic, fuzzy_score, open_in_editor, walk::build_tree, PARSER},
+    Context,
+};
+
+/// How many matches to offer when the search term is ambiguous
+const MAX_CHOICES: usize = 10;
+
+#[derive(Debug, Args)]
+pub struct EditArgs {
+    /// Recipe to edit: a path, or words to search for in recipe paths
+    #[arg(required = true, add = crate::completions::recipes())]
+    query: Vec<String>,
+
+    /// Directory of the recipe collection
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+}
+
+pub fn run(ctx: &Context, args: EditArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let query = args.query.join(" ");
+    let path = resolve(base_path, &query)?;
+    let relative = path.strip_prefix(base_path).unwrap_or(&path).to_owned();
+
+    loop {
+        open_in_editor(&path)?;
+
+        let content =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path}"))?;
+        let parsed = PARSER.parse(&content);
+        let (errors, warnings): (Vec<_>, Vec<_>) =
+            Diagnostic::from_report(parsed.report(), &content)
+                .into_iter()
+                .partition(|diag| diag.severity == "error");
+
+        let show = |label: &str, diagnostic: &Diagnostic| {
+            println!("  {label}: {}", diagnostic.message);
+            for line in diagnostic.snippet(relative.as_str(), &content).lines() {
+                println!("     {line}");
+            }
+        };
+        for warning in &warnings {
+            show("⚠️  Warning", warning);
+        }
+        if errors.is_empty() {
+            println!("✓ {relative} is valid");
+            return Ok(());
+        }
+        for error in &errors {
+            show("❌ Error", error);
+        }
+
+        if !ask("Edit again to fix the errors? [Y/n] ")? {
+            bail!("{relative} was saved with {} errors", errors.len());
+        }
+    }
+}
+
+/// Find the recipe to edit
+///
+/// A path to an existing file is used as is. Otherwise recipes whose file
+/// name is the search term, then recipes whose path contains it, then fuzzy
+/// matches are tried; when more than one recipe matches, the user picks one.
+fn resolve(base_path: &Utf8Path, query: &str) -> Result<Utf8PathBuf> {
+    for candidate in [Utf8PathBuf::from(query), base_path.join(query)] {
+        if candidate.is_file() {
+            return Ok(candidate);
+        }
+        let with_extension = candidate.with_extension("cook");
+        if with_extension.is_file() {
+            return Ok(with_extension);
+        }
+    }
+
+    let recipes = collection_recipes(base_path)?;
+    let needle = query.to_lowercase();
+
+    let by_name: Vec<&String> = recipes
+        .iter()
+        .filter(|path| {
+            Utf8Path::new(path.as_str())
+                .file_stem()
+                .is_some_and(|stem| stem.to_lowercase() == needle)
+        })
+        .collect();
+    let containing: Vec<&String> = recipes
+        .iter()
+        .filter(|path| path.to_lowercase().contains(&needle))
+        .collect();
+    let mut fuzzy: Vec<(i64, &String)> = recipes
+        .iter()
+        .filter_map(|path| fuzzy_score(query, path).map(|score| (score, path)))
+        .collect();
+    fuzzy.sort_by(|a, b| b.0.cmp(&a.0));
+    let fuzzy: Vec<&String> = fuzzy.into_iter().map(|(_, path)| path).collect();
+
+    let matches = [by_name, containing, fuzzy]
+        .into_iter()
+        .find(|matches| !matches.is_empty())
+        .with_context(|| format!("No recipe matches '{query}'"))?;
+
+    let chosen = match matches.as_slice() {
+        [only] => only,
+        _ => pick(query, &matches[..matches.len().min(MAX_CHOICES)])?,
+    };
+    Ok(base_path.join(chosen))
+}
+
+/// Let the user choose between recipes matching the search term
+fn pick<'a>(query: &str, matches: &[&'a String]) -> Result<&'a String> {
+    let list = matches
+        .iter()
+        .enumerate()
+        .map(|(i, path)| format!("  {}. {path}", i + 1))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if !std::io::stdin().is_terminal() {
+        bail!("'{query}' matches several recipes, be more specific:\n{list}");
+    }
+
+    println!("'{query}' matches several recipes:\n{list}");
+    loop {
+        print!("Which one? [1-{}] ", matches.len());
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            bail!("No recipe chosen");
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            bail!("No recipe chosen");
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=matches.len()).contains(&n) => return Ok(matches[n - 1]),
+            _ => println!("Enter a number between 1 and {}", matches.len()),
+        }
+    }
+}
+
+/// Ask a yes/no question, defaulting to yes; always no without a terminal
+fn ask(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    print!("{question}");
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        return Ok(false);
+    }
+    Ok(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "" | "y" | "yes"
+    ))
+}
+
+/// Paths of all recipes and menus, relative to the collection
+fn collection_recipes(base_path: &Utf8Path) -> Result<Vec<String>> {
+    fn walk(tree: &RecipeTree, base_path: &Utf8Path, out: &mut Vec<String>) {
+        if let Some(path) = tree.recipe.as_ref().and_then(|entry| entry.path()) {
+            out.push(path.strip_prefix(base_path).unwrap_or(path).to_string());
+        }
+        for child in tree.children.values() {
+            walk(child, base_path, out);
+        }
+    }
+
+    let tree = build_tree(base_path)?;
+    let mut recipes = Vec::new();
+    walk(&tree, base_path, &mut recipes);
+    recipes.sort();
+    Ok(recipes)
+}
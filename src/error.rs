@@ -0,0 +1,133 @@
+//! How failures are reported to the shell
+//!
+//! Every command fails with one of a few exit codes, so scripts can tell a
+//! broken recipe from a missing file or a network problem without parsing
+//! messages. With `--error-format json` the error is also printed as JSON.
+//!
+//! | Code | Kind        | Meaning                                         |
+//! |------|-------------|-------------------------------------------------|
+//! | 1    | `other`     | Anything else                                   |
+//! | 2    | `usage`     | Invalid arguments (reported by clap)            |
+//! | 3    | `parse`     | A recipe has syntax errors                      |
+//! | 4    | `not_found` | A recipe or file doesn't exist                  |
+//! | 5    | `config`    | A configuration file or setting is invalid      |
+//! | 6    | `network`   | A server couldn't be reached or answered badly  |
+
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use std::process::ExitCode;
+
+use crate::util::diagnostics::ParseError;
+
+/// What kind of failure an error is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Other,
+    Parse,
+    NotFound,
+    Config,
+    Network,
+}
+
+impl ErrorKind {
+    /// The kind of the first error in the chain that has one
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(Self::classify)
+            .unwrap_or(Self::Other)
+    }
+
+    fn classify(error: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        if error.is::<ParseError>() {
+            return Some(Self::Parse);
+        }
+        if error.is::<NotFound>() {
+            return Some(Self::NotFound);
+        }
+        if error.is::<toml::de::Error>() || error.is::<toml_edit::TomlError>() {
+            return Some(Self::Config);
+        }
+        if error.is::<reqwest::Error>() {
+            return Some(Self::Network);
+        }
+        let io = error.downcast_ref::<std::io::Error>()?;
+        match io.kind() {
+            std::io::ErrorKind::NotFound => Some(Self::NotFound),
+            std::io::ErrorKind::ConnectionRefused
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::TimedOut => Some(Self::Network),
+            _ => None,
+        }
+    }
+
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Other => 1,
+            Self::Parse => 3,
+            Self::NotFound => 4,
+            Self::Config => 5,
+            Self::Network => 6,
+        }
+    }
+}
+
+/// Something the user asked for, like a recipe, doesn't exist
+#[derive(Debug)]
+pub struct NotFound(pub String);
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// How errors are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// The message and its causes
+    #[default]
+    Human,
+    /// One JSON object on a line
+    Json,
+}
+
+#[derive(Serialize)]
+struct JsonError<'a> {
+    kind: ErrorKind,
+    exit_code: u8,
+    message: String,
+    /// The underlying errors, outermost first
+    causes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics: Option<&'a [crate::util::diagnostics::Diagnostic]>,
+}
+
+/// Print a failed command's error to stderr and pick the exit code
+pub fn report(error: &anyhow::Error, format: ErrorFormat) -> ExitCode {
+    let kind = ErrorKind::of(error);
+    match format {
+        ErrorFormat::Human => eprintln!("Error: {error:?}"),
+        ErrorFormat::Json => {
+            let json = JsonError {
+                kind,
+                exit_code: kind.exit_code(),
+                message: error.to_string(),
+                causes: error.chain().skip(1).map(|e| e.to_string()).collect(),
+                diagnostics: error
+                    .chain()
+                    .find_map(|e| e.downcast_ref::<ParseError>())
+                    .map(|e| e.diagnostics.as_slice()),
+            };
+            let json = serde_json::to_string(&serde_json::json!({ "error": json }))
+                .expect("errors serialize");
+            eprintln!("{json}");
+        }
+    }
+    ExitCode::from(kind.exit_code())
+}
@@ -0,0 +1,490 @@
+//! Export to [Grocy](https://grocy.info), a self-hosted grocery and
+//! household manager, through its REST API
+
+use anyhow::{bail, Context as _, Result};
+use clap::Args;
+use cooklang::{
+    model::Item,
+    quantity::{GroupedQuantity, Quantity, Value},
+    Content, Recipe,
+};
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    util::{
+        escape_html,
+        format::{display_grouped, format_quantity},
+        pantry::Amount,
+        tree_entries,
+        walk::build_tree,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct GrocyArgs {
+    /// Recipes to export, by name or path
+    ///
+    /// A scaling factor can follow the name, like "Pizza:2".
+    #[arg(add = crate::completions::recipes(), required_unless_present = "all")]
+    recipes: Vec<String>,
+
+    /// Export every recipe in the collection
+    #[arg(long, conflicts_with = "recipes")]
+    all: bool,
+
+    /// URL of the Grocy instance, like https://grocy.example.com
+    #[arg(long)]
+    url: String,
+
+    /// Grocy API key, created under "Manage API keys"
+    #[arg(long)]
+    api_key: String,
+
+    /// Also put the ingredients on Grocy's shopping list
+    #[arg(long)]
+    shopping_list: bool,
+
+    /// Only show what would be exported, without changing anything in Grocy
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub fn run(ctx: &Context, args: GrocyArgs) -> Result<()> {
+    let queries = if args.all {
+        let base_path = ctx.base_path();
+        tree_entries(&build_tree(base_path)?)
+            .into_iter()
+            .filter(|entry| !entry.is_menu())
+            .filter_map(|entry| {
+                let path = entry.path()?;
+                Some(path.strip_prefix(base_path).unwrap_or(path).to_string())
+            })
+            .collect()
+    } else {
+        args.recipes.clone()
+    };
+
+    tokio::runtime::Runtime::new()?.block_on(export(ctx, &args, &queries))
+}
+
+async fn export(ctx: &Context, args: &GrocyArgs, queries: &[String]) -> Result<()> {
+    let grocy = Grocy::new(&args.url, &args.api_key);
+    let products = Products::new(
+        grocy.get("objects/products").await?,
+        grocy.get("objects/quantity_units").await?,
+    );
+    let existing = grocy.get("objects/recipes").await?;
+
+    let verb = if args.dry_run {
+        "Would export"
+    } else {
+        "Exported"
+    };
+    let mut unmatched = BTreeSet::new();
+    let mut shopping = Vec::new();
+
+    for query in queries {
+        let loaded = ctx.load_recipe(query)?;
+        let ingredients = ctx
+            .shopping_list(&[query], false)
+            .with_context(|| format!("Failed to collect the ingredients of {query}"))?;
+
+        let mut positions = Vec::new();
+        let mut missing = Vec::new();
+        for (name, quantity) in ingredients {
+            match products.find(&name) {
+                Some(product) => positions.push(products.position(product, &quantity)),
+                None => missing.push(name),
+            }
+        }
+
+        if !args.dry_run {
+            let recipe_id = grocy
+                .save_recipe(&loaded.title, &loaded.recipe, &existing)
+                .await?;
+            grocy.replace_positions(recipe_id, &positions).await?;
+        }
+        println!(
+            "{verb} {}: {} ingredients matched, {} not in Grocy",
+            loaded.title,
+            positions.len(),
+            missing.len()
+        );
+
+        unmatched.extend(missing);
+        shopping.extend(positions);
+    }
+
+    if args.shopping_list {
+        if !args.dry_run {
+            for position in &shopping {
+                grocy
+                    .post(
+                        "stock/shoppinglist/add-product",
+                        json!({
+                            "product_id": position.product_id,
+                            "product_amount": position.shopping_amount(),
+                            "note": position.note,
+                        }),
+                    )
+                    .await?;
+            }
+        }
+        let verb = if args.dry_run { "Would add" } else { "Added" };
+        println!("{verb} {} items to the shopping list", shopping.len());
+    }
+
+    if !unmatched.is_empty() {
+        println!(
+            "\nNo Grocy product for: {}\n\
+             Add products with these names in Grocy to include them next time.",
+            unmatched.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+    Ok(())
+}
+
+struct Grocy {
+    client: reqwest::Client,
+    api: String,
+    api_key: String,
+}
+
+impl Grocy {
+    fn new(url: &str, api_key: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api: format!("{}/api", url.trim_end_matches('/')),
+            api_key: api_key.to_string(),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Vec<serde_json::Value>> {
+        let response = self
+            .client
+            .get(format!("{}/{path}", self.api))
+            .header("GROCY-API-KEY", &self.api_key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to Grocy at {}", self.api))?;
+        Self::check(response, path)
+            .await?
+            .json()
+            .await
+            .context("Unexpected Grocy response")
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        path: &str,
+    ) -> Result<serde_json::Value> {
+        let response = request
+            .header("GROCY-API-KEY", &self.api_key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to Grocy at {}", self.api))?;
+        // Some calls answer 204 without a body
+        let text = Self::check(response, path).await?.text().await?;
+        Ok(serde_json::from_str(&text).unwrap_or_default())
+    }
+
+    async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let request = self.client.post(format!("{}/{path}", self.api)).json(&body);
+        self.send(request, path).await
+    }
+
+    async fn put(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let request = self.client.put(format!("{}/{path}", self.api)).json(&body);
+        self.send(request, path).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<serde_json::Value> {
+        let request = self.client.delete(format!("{}/{path}", self.api));
+        self.send(request, path).await
+    }
+
+    async fn check(response: reqwest::Response, path: &str) -> Result<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        let message = body["error_message"].as_str().unwrap_or("unknown error");
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            bail!("Grocy rejected the API key: {message}");
+        }
+        bail!("Grocy returned {status} for {path}: {message}")
+    }
+
+    /// Create the recipe, or update the one with the same name from an
+    /// earlier export, and return its id
+    async fn save_recipe(
+        &self,
+        name: &str,
+        recipe: &Recipe,
+        existing: &[serde_json::Value],
+    ) -> Result<i64> {
+        let servings = recipe
+            .metadata
+            .servings()
+            .and_then(|s| Amount::parse(&s.to_string()))
+            .map(|a| a.value)
+            .filter(|s| *s > 0.0)
+            .unwrap_or(1.0);
+        let body = json!({
+            "name": name,
+            "description": description(recipe),
+            "base_servings": servings,
+            "desired_servings": servings,
+        });
+
+        let found = existing
+            .iter()
+            .find(|r| r["name"].as_str() == Some(name))
+            .and_then(|r| id(&r["id"]));
+        match found {
+            Some(recipe_id) => {
+                self.put(&format!("objects/recipes/{recipe_id}"), body)
+                    .await?;
+                Ok(recipe_id)
+            }
+            None => {
+                let created = self.post("objects/recipes", body).await?;
+                id(&created["created_object_id"]).context("Grocy didn't return the new recipe's id")
+            }
+        }
+    }
+
+    async fn replace_positions(&self, recipe_id: i64, positions: &[Position]) -> Result<()> {
+        for old in self.get("objects/recipes_pos").await? {
+            if id(&old["recipe_id"]) != Some(recipe_id) {
+                continue;
+            }
+            if let Some(pos_id) = id(&old["id"]) {
+                self.delete(&format!("objects/recipes_pos/{pos_id}"))
+                    .await?;
+            }
+        }
+        for position in positions {
+            self.post(
+                "objects/recipes_pos",
+                json!({
+                    "recipe_id": recipe_id,
+                    "product_id": position.product_id,
+                    "amount": position.amount,
+                    "qu_id": position.qu_id,
+                    "note": position.note,
+                    "only_check_single_unit_in_stock": position.single_unit,
+                }),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Ids are numbers in recent Grocy versions and strings in older ones
+fn id(value: &serde_json::Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+struct Product {
+    id: i64,
+    stock_unit: i64,
+}
+
+/// Grocy's products and quantity units, by lowercase name
+struct Products {
+    by_name: HashMap<String, Product>,
+    units: HashMap<String, i64>,
+    unit_names: HashMap<i64, Vec<String>>,
+}
+
+impl Products {
+    fn new(products: Vec<serde_json::Value>, units: Vec<serde_json::Value>) -> Self {
+        let by_name = products
+            .iter()
+            .filter_map(|p| {
+                let product = Product {
+                    id: id(&p["id"])?,
+                    stock_unit: id(&p["qu_id_stock"])?,
+                };
+                Some((p["name"].as_str()?.trim().to_lowercase(), product))
+            })
+            .collect();
+
+        let mut by_unit = HashMap::new();
+        let mut unit_names: HashMap<i64, Vec<String>> = HashMap::new();
+        for unit in &units {
+            let Some(unit_id) = id(&unit["id"]) else {
+                continue;
+            };
+            for key in ["name", "name_plural"] {
+                if let Some(name) = unit[key].as_str().filter(|n| !n.is_empty()) {
+                    let name = name.trim().to_lowercase();
+                    by_unit.insert(name.clone(), unit_id);
+                    unit_names.entry(unit_id).or_default().push(name);
+                }
+            }
+        }
+
+        Self {
+            by_name,
+            units: by_unit,
+            unit_names,
+        }
+    }
+
+    /// The product for an ingredient, tried as written and singular
+    fn find(&self, name: &str) -> Option<&Product> {
+        let name = name.trim().to_lowercase();
+        [
+            Some(name.as_str()),
+            name.strip_suffix("es"),
+            name.strip_suffix('s'),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|candidate| self.by_name.get(candidate))
+    }
+
+    /// A recipe position for the ingredient's quantity
+    ///
+    /// Amounts in the product's stock unit, or without a unit, are used as
+    /// they are. Grocy can't convert other units without conversions set up
+    /// for the product, so those only check the product is in stock and
+    /// keep the quantity in the note.
+    fn position(&self, product: &Product, quantity: &GroupedQuantity) -> Position {
+        let quantities: Vec<&Quantity> = quantity.iter().collect();
+        let single = match quantities.as_slice() {
+            [q] => match q.value() {
+                Value::Number(n) => Some((n.value(), q.unit())),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let in_stock_unit = |unit: Option<&str>| match unit {
+            None => true,
+            Some(unit) => self
+                .unit_names
+                .get(&product.stock_unit)
+                .is_some_and(|names| names.contains(&unit.trim().to_lowercase())),
+        };
+
+        match single {
+            Some((amount, unit)) if in_stock_unit(unit) => Position {
+                product_id: product.id,
+                amount,
+                qu_id: product.stock_unit,
+                note: String::new(),
+                single_unit: false,
+            },
+            _ => Position {
+                product_id: product.id,
+                amount: single.map_or(1.0, |(amount, _)| amount),
+                qu_id: single
+                    .and_then(|(_, unit)| unit)
+                    .and_then(|unit| self.units.get(&unit.trim().to_lowercase()).copied())
+                    .unwrap_or(product.stock_unit),
+                note: display_grouped(quantity),
+                single_unit: true,
+            },
+        }
+    }
+}
+
+struct Position {
+    product_id: i64,
+    /// In the unit `qu_id`
+    amount: f64,
+    qu_id: i64,
+    note: String,
+    /// Grocy only checks one unit is in stock, the amount isn't comparable
+    single_unit: bool,
+}
+
+impl Position {
+    /// The amount to buy, in the product's stock unit
+    fn shopping_amount(&self) -> f64 {
+        if self.single_unit {
+            1.0
+        } else {
+            self.amount
+        }
+    }
+}
+
+/// The steps as HTML, which Grocy shows as the recipe's preparation
+fn description(recipe: &Recipe) -> String {
+    let mut html = String::new();
+    for section in &recipe.sections {
+        if let Some(name) = &section.name {
+            html += &format!("<h3>{}</h3>", escape_html(name));
+        }
+        let mut steps = Vec::new();
+        for content in &section.content {
+            match content {
+                Content::Step(step) => {
+                    let text: String = step
+                        .items
+                        .iter()
+                        .map(|item| item_text(recipe, item))
+                        .collect();
+                    steps.push(format!("<li>{}</li>", escape_html(text.trim())));
+                }
+                Content::Text(text) if text.trim() != "-" => {
+                    html += &format!("<p>{}</p>", escape_html(text.trim()));
+                }
+                Content::Text(_) => {}
+            }
+        }
+        if !steps.is_empty() {
+            html += &format!("<ol>{}</ol>", steps.concat());
+        }
+    }
+    html
+}
+
+fn item_text(recipe: &Recipe, item: &Item) -> String {
+    let quantity_text = |q: &Quantity| {
+        let value = format_quantity(q.value()).unwrap_or_default();
+        match q.unit() {
+            Some(unit) => format!("{value} {unit}").trim().to_string(),
+            None => value,
+        }
+    };
+
+    match item {
+        Item::Text { value } => value.to_string(),
+        &Item::Ingredient { index } => recipe
+            .ingredients
+            .get(index)
+            .map(|i| i.display_name().to_string())
+            .unwrap_or_default(),
+        &Item::Cookware { index } => recipe
+            .cookware
+            .get(index)
+            .map(|c| c.display_name().to_string())
+            .unwrap_or_default(),
+        &Item::Timer { index } => recipe
+            .timers
+            .get(index)
+            .map(|t| match (&t.quantity, &t.name) {
+                (Some(q), _) => quantity_text(q),
+                (None, Some(name)) => name.clone(),
+                (None, None) => String::new(),
+            })
+            .unwrap_or_default(),
+        &Item::InlineQuantity { index } => recipe
+            .inline_quantities
+            .get(index)
+            .map(quantity_text)
+            .unwrap_or_default(),
+    }
+}
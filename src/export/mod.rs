@@ -0,0 +1,46 @@
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+
+use crate::Context;
+
+mod grocy;
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Base path for recipes and configuration files
+    #[arg(short = 'b', long, value_name = "PATH")]
+    pub base_path: Option<Utf8PathBuf>,
+
+    #[command(subcommand)]
+    pub command: ExportCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ExportCommand {
+    /// Push recipes and their ingredients to a Grocy instance
+    ///
+    /// Creates (or updates) a Grocy recipe for each recipe, with the
+    /// ingredients as positions. Ingredients are matched to Grocy products
+    /// by name; the ones without a product are reported and left out.
+    ///
+    /// Examples:
+    ///   cook export grocy --url https://grocy.local --api-key KEY Pizza
+    ///   cook export grocy --all --dry-run
+    ///   cook export grocy "Lasagna:2" --shopping-list
+    Grocy(grocy::GrocyArgs),
+}
+
+pub fn run(ctx: &Context, args: ExportArgs) -> Result<()> {
+    let new_ctx;
+    let ctx = if let Some(base_path) = args.base_path {
+        new_ctx = Context::new(crate::util::resolve_to_absolute_path(&base_path)?);
+        &new_ctx
+    } else {
+        ctx
+    };
+
+    match args.command {
+        ExportCommand::Grocy(args) => grocy::run(ctx, args),
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand};
+
+use crate::{util::git::git_ok, Context};
+
+/// Marks a hook as written by `cook hooks install`, so it can be replaced
+/// and removed
+const MARKER: &str = "# Added by `cook hooks install`";
+
+#[derive(Debug, Args)]
+pub struct HooksArgs {
+    #[command(subcommand)]
+    command: HooksCommand,
+
+    /// Directory of the recipe collection
+    ///
+    /// Defaults to the current directory. It has to be in a git repository.
+    #[arg(short, long, global = true, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum HooksCommand {
+    /// Add a pre-commit hook that checks the staged recipes
+    ///
+    /// The hook runs `cook lint --staged`, so a commit adding a recipe that
+    /// doesn't parse, or breaks a lint rule, is stopped. Skip it once with
+    /// `git commit --no-verify`.
+    ///
+    /// Examples:
+    ///   cook hooks install
+    ///   cook hooks install --force    # Replace another pre-commit hook
+    Install {
+        /// Replace a pre-commit hook that wasn't added by cook
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Remove the pre-commit hook added by `cook hooks install`
+    Uninstall,
+}
+
+pub fn run(ctx: &Context, args: HooksArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let hook = hook_path(base_path)?;
+    match args.command {
+        HooksCommand::Install { force } => install(base_path, &hook, force),
+        HooksCommand::Uninstall => uninstall(&hook),
+    }
+}
+
+/// Where git looks for the pre-commit hook, following core.hooksPath
+fn hook_path(base_path: &Utf8Path) -> Result<Utf8PathBuf> {
+    let hooks = git_ok(base_path, &["rev-parse", "--git-path", "hooks"])
+        .with_context(|| format!("{base_path} isn't in a git repository"))?;
+    Ok(base_path.join(hooks.trim()).join("pre-commit"))
+}
+
+fn install(base_path: &Utf8Path, hook: &Utf8Path, force: bool) -> Result<()> {
+    if let Ok(existing) = fs::read_to_string(hook) {
+        if !existing.contains(MARKER) && !force {
+            bail!(
+                "{hook} already exists\n\
+                 Add `cook lint --staged` to it yourself, or replace it with --force"
+            );
+        }
+    }
+
+    // The collection relative to the repository, like "Recipes/"
+    let prefix = git_ok(base_path, &["rev-parse", "--show-prefix"])?;
+    let script = script(prefix.trim());
+    if let Some(dir) = hook.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir}"))?;
+    }
+    fs::write(hook, script).with_context(|| format!("Failed to write {hook}"))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(hook, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {hook} executable"))?;
+    }
+
+    println!("✓ Installed the pre-commit hook in {hook}");
+    println!("  Commits with recipes that don't parse or break a lint rule are stopped");
+    Ok(())
+}
+
+fn uninstall(hook: &Utf8Path) -> Result<()> {
+    let Ok(existing) = fs::read_to_string(hook) else {
+        println!("No pre-commit hook to remove");
+        return Ok(());
+    };
+    if !existing.contains(MARKER) {
+        bail!("{hook} wasn't added by `cook hooks install`, remove it yourself");
+    }
+    fs::remove_file(hook).with_context(|| format!("Failed to remove {hook}"))?;
+    println!("✓ Removed the pre-commit hook");
+    Ok(())
+}
+
+/// The hook, checking the collection at `prefix` in the repository
+///
+/// When cook isn't installed, like on a machine only editing the recipes
+/// by hand, the commit goes ahead.
+fn script(prefix: &str) -> String {
+    // Single quotes keep any character of the path as it is
+    let prefix = prefix.replace('\'', r"'\''");
+    format!(
+        "#!/bin/sh\n\
+         {MARKER}\n\
+         # Checks the staged recipes, skip with `git commit --no-verify`\n\
+         \n\
+         if ! command -v cook >/dev/null 2>&1; then\n\
+         \x20   echo \"cook isn't installed, not checking the recipes\" >&2\n\
+         \x20   exit 0\n\
+         fi\n\
+         \n\
+         exec cook lint --staged -b \"$(git rev-parse --show-toplevel)\"/'{prefix}'\n"
+    )
+}
@@ -0,0 +1,180 @@
+//! Crouton's `.crumb` files, one recipe as JSON each
+
+use anyhow::{Context as _, Result};
+use base64::Engine as _;
+use serde::Deserialize;
+
+use crate::util::import::{
+    format_amount, ImportedImage, ImportedIngredient, ImportedRecipe, ImportedStep, IngredientLine,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Crumb {
+    name: String,
+    serves: Option<f64>,
+    /// Preparation time in minutes
+    duration: Option<f64>,
+    /// Cooking time in minutes
+    cooking_duration: Option<f64>,
+    web_link: Option<String>,
+    source_name: Option<String>,
+    notes: Option<String>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    /// Base64 encoded
+    #[serde(default)]
+    images: Vec<String>,
+    #[serde(default)]
+    ingredients: Vec<CrumbIngredient>,
+    #[serde(default)]
+    steps: Vec<CrumbStep>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Tag {
+    Name(String),
+    Object { name: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct CrumbIngredient {
+    #[serde(default)]
+    order: i64,
+    ingredient: Named,
+    quantity: Option<CrumbQuantity>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CrumbQuantity {
+    amount: Option<f64>,
+    quantity_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CrumbStep {
+    #[serde(default)]
+    order: i64,
+    step: String,
+    #[serde(default)]
+    is_section: bool,
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Vec<ImportedRecipe>> {
+    let mut crumb: Crumb = serde_json::from_slice(bytes).context("Not a Crouton recipe")?;
+    crumb.ingredients.sort_by_key(|i| i.order);
+    crumb.steps.sort_by_key(|s| s.order);
+
+    let minutes = |m: Option<f64>| {
+        m.filter(|m| *m > 0.0)
+            .map(|m| format!("{} minutes", format_amount(m)))
+    };
+    let tags = crumb
+        .tags
+        .iter()
+        .map(|tag| match tag {
+            Tag::Name(name) | Tag::Object { name } => name.as_str(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let metadata = [
+        (
+            "servings",
+            crumb.serves.filter(|s| *s > 0.0).map(format_amount),
+        ),
+        ("prep time", minutes(crumb.duration)),
+        ("cook time", minutes(crumb.cooking_duration)),
+        ("source", crumb.web_link.clone()),
+        ("author", crumb.source_name.clone()),
+        ("tags", Some(tags)),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| Some((key.to_string(), value?)))
+    .collect();
+
+    let ingredients = crumb
+        .ingredients
+        .iter()
+        .map(|i| {
+            let (quantity, unit) = match &i.quantity {
+                Some(q) => (
+                    q.amount.filter(|a| *a > 0.0).map(format_amount),
+                    q.quantity_type.as_deref().and_then(unit),
+                ),
+                None => (None, None),
+            };
+            IngredientLine::Ingredient(ImportedIngredient {
+                name: i.ingredient.name.trim().to_string(),
+                quantity,
+                unit,
+                note: None,
+            })
+        })
+        .collect();
+
+    let steps = crumb
+        .steps
+        .iter()
+        .map(|s| {
+            if s.is_section {
+                ImportedStep::Section(s.step.clone())
+            } else {
+                ImportedStep::Step(s.step.clone())
+            }
+        })
+        .collect();
+
+    let images = crumb
+        .images
+        .iter()
+        .filter_map(|data| {
+            base64::engine::general_purpose::STANDARD
+                .decode(data.trim())
+                .ok()
+        })
+        .map(ImportedImage::Data)
+        .collect();
+
+    Ok(vec![ImportedRecipe {
+        name: crumb.name.trim().to_string(),
+        metadata,
+        ingredients,
+        steps,
+        notes: crumb.notes.into_iter().collect(),
+        images,
+    }])
+}
+
+/// The unit of one of Crouton's quantity types, like "TABLESPOON"
+fn unit(quantity_type: &str) -> Option<String> {
+    Some(
+        match quantity_type {
+            "ITEM" | "NONE" | "" => return None,
+            "CUP" => "cup",
+            "TABLESPOON" => "tbsp",
+            "TEASPOON" => "tsp",
+            "GRAMS" => "g",
+            "KGS" => "kg",
+            "MILLS" => "ml",
+            "LITRES" => "l",
+            "OUNCE" => "oz",
+            "FLUID_OUNCE" => "fl oz",
+            "POUND" => "lb",
+            "PINCH" => "pinch",
+            "CAN" => "can",
+            "PACKET" => "packet",
+            "BUNCH" => "bunch",
+            "CLOVE" => "cloves",
+            other => return Some(other.to_lowercase().replace('_', " ")),
+        }
+        .to_string(),
+    )
+}
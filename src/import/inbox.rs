@@ -0,0 +1,382 @@
+//! A folder recipes are dropped into, to be imported on their own
+//!
+//! Text and Markdown recipes, lists of URLs, Cooklang files and, with the AI
+//! provider, photos of recipes are converted and moved into the collection.
+//! Files that can't be imported are moved to `failed/` in the inbox, so they
+//! aren't tried again.
+//!
+//! The inbox is polled instead of watched: drop folders are often network
+//! shares or synced folders, where file events don't always arrive.
+
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+
+use super::text;
+use crate::{
+    config::{AiConfig, Config},
+    util::{
+        ai,
+        diagnostics::ParseError,
+        front_matter::{display_value, FrontMatter},
+        import::to_cooklang,
+        names::sanitize_stem,
+        PARSER,
+    },
+};
+
+/// Where files that couldn't be imported go, in the inbox
+pub const FAILED_DIR: &str = "failed";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+const TEXT_PROMPT: &str = "Convert this recipe to Cooklang.\n\
+    Return only the recipe, no other text and no code fences.\n\
+    - Start with front matter between --- lines with the title, and the servings, source \
+    and times when the recipe has them.\n\
+    - Mark ingredients where a step uses them, like @flour{200%g} or @olive oil{2%tbsp}, \
+    cookware like #pan{} and timers like ~{10%minutes}.\n\
+    - Write each step as its own paragraph, separated by a blank line.\n\
+    - Keep the wording of the recipe.";
+
+const IMAGE_PROMPT: &str = "Transcribe the recipe in this photo to Cooklang.\n\
+    Return only the recipe, no other text and no code fences.\n\
+    - Start with front matter between --- lines with the title, and the servings and \
+    times when the recipe has them.\n\
+    - Mark ingredients where a step uses them, like @flour{200%g} or @olive oil{2%tbsp}, \
+    cookware like #pan{} and timers like ~{10%minutes}.\n\
+    - Write each step as its own paragraph, separated by a blank line.\n\
+    - Keep the wording of the recipe, don't add anything that isn't in the photo.";
+
+/// The inbox and where its recipes go
+#[derive(Debug, Clone)]
+pub struct Inbox {
+    /// The collection, recipes in events are relative to it
+    pub base_path: Utf8PathBuf,
+    pub dir: Utf8PathBuf,
+    pub output_dir: Utf8PathBuf,
+    /// Convert photos, and text without headings, with the AI provider
+    pub ai: Option<AiConfig>,
+    /// URL every import is posted to
+    pub webhook: Option<String>,
+}
+
+/// What happened to a file dropped in the inbox
+#[derive(Debug, Clone, Serialize)]
+pub struct InboxEvent {
+    /// The file's name
+    pub file: String,
+    pub status: InboxStatus,
+    /// Recipes written, relative to the collection when they're in it
+    pub recipes: Vec<Utf8PathBuf>,
+    pub error: Option<String>,
+    /// RFC 3339
+    pub time: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InboxStatus {
+    Imported,
+    /// Moved to `failed/` in the inbox
+    Failed,
+}
+
+impl Inbox {
+    /// The inbox from `[inbox]` in cook.toml, `inbox/` in the collection by
+    /// default
+    pub fn from_config(base_path: &Utf8Path, config: &Config) -> Self {
+        let inbox = &config.inbox;
+        Self {
+            base_path: base_path.to_path_buf(),
+            dir: base_path.join(inbox.dir()),
+            output_dir: match &inbox.output_dir {
+                Some(dir) => base_path.join(dir),
+                None => base_path.to_path_buf(),
+            },
+            ai: inbox.ai.unwrap_or(false).then(|| config.ai.clone()),
+            webhook: inbox.webhook.clone(),
+        }
+    }
+
+    /// Files waiting in the inbox
+    ///
+    /// Hidden files, like the partial downloads of sync tools, are left
+    /// alone.
+    pub fn pending(&self) -> Result<Vec<Utf8PathBuf>> {
+        let mut files = Vec::new();
+        let entries = match self.dir.read_dir_utf8() {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.dir)),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let partial = [".part", ".crdownload", ".tmp"]
+                .iter()
+                .any(|suffix| name.ends_with(suffix));
+            if name.starts_with('.') || partial || !entry.file_type()?.is_file() {
+                continue;
+            }
+            files.push(entry.into_path());
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Import a file from the inbox
+    ///
+    /// The file is removed once its recipes are written, or moved to
+    /// `failed/` when it can't be imported. The event is posted to the
+    /// webhook, if there is one.
+    pub async fn process(&self, file: &Utf8Path) -> InboxEvent {
+        let name = file.file_name().unwrap_or(file.as_str()).to_string();
+        let result = match self.import(file).await {
+            Ok(recipes) => std::fs::remove_file(file)
+                .with_context(|| format!("Failed to remove {file}"))
+                .map(|_| recipes),
+            Err(e) => Err(e),
+        };
+        let event = match result {
+            Ok(recipes) => InboxEvent {
+                file: name,
+                status: InboxStatus::Imported,
+                recipes,
+                error: None,
+                time: chrono::Local::now().to_rfc3339(),
+            },
+            Err(e) => {
+                if let Err(e) = self.move_to_failed(file) {
+                    tracing::error!("{e:#}");
+                }
+                InboxEvent {
+                    file: name,
+                    status: InboxStatus::Failed,
+                    recipes: Vec::new(),
+                    error: Some(format!("{e:#}")),
+                    time: chrono::Local::now().to_rfc3339(),
+                }
+            }
+        };
+        if let Some(url) = &self.webhook {
+            if let Err(e) = post(url, &event).await {
+                tracing::warn!(
+                    "Failed to post the import of {} to {url}: {e:#}",
+                    event.file
+                );
+            }
+        }
+        event
+    }
+
+    /// Import new files until the process stops
+    ///
+    /// A file is only imported once its size and modification time stay
+    /// the same between two polls, so files still being copied are left
+    /// alone.
+    pub async fn watch(&self, mut on_event: impl FnMut(&InboxEvent)) {
+        if let Err(e) = std::fs::create_dir_all(&self.dir) {
+            tracing::error!("Failed to create {}: {e}", self.dir);
+        }
+        let mut seen: HashMap<Utf8PathBuf, (u64, Option<std::time::SystemTime>)> = HashMap::new();
+        loop {
+            let files = match self.pending() {
+                Ok(files) => files,
+                Err(e) => {
+                    tracing::error!("{e:#}");
+                    Vec::new()
+                }
+            };
+            seen.retain(|path, _| files.contains(path));
+            for file in files {
+                let Ok(metadata) = file.metadata() else {
+                    continue;
+                };
+                let stamp = (metadata.len(), metadata.modified().ok());
+                if seen.insert(file.clone(), stamp) == Some(stamp) {
+                    seen.remove(&file);
+                    on_event(&self.process(&file).await);
+                }
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Import the recipe at a URL to where the inbox's recipes go
+    pub async fn import_url(&self, url: &str) -> Result<Vec<Utf8PathBuf>> {
+        self.write(vec![import_url(url).await?])
+    }
+
+    /// Convert the file and write its recipes
+    async fn import(&self, file: &Utf8Path) -> Result<Vec<Utf8PathBuf>> {
+        let recipes = self.convert(file).await?;
+        self.write(recipes)
+    }
+
+    /// Write recipes to the output directory, returning their paths
+    ///
+    /// Nothing is written unless every recipe parses.
+    fn write(&self, recipes: Vec<(String, String)>) -> Result<Vec<Utf8PathBuf>> {
+        for (name, content) in &recipes {
+            PARSER
+                .parse(content)
+                .into_result()
+                .map_err(|report| ParseError::new(name, content, &report))
+                .with_context(|| format!("{name} isn't valid Cooklang"))?;
+        }
+
+        std::fs::create_dir_all(&self.output_dir)
+            .with_context(|| format!("Failed to create {}", self.output_dir))?;
+        let mut written = Vec::new();
+        for (name, content) in recipes {
+            let path = unique_path(&self.output_dir, &name);
+            std::fs::write(&path, content).with_context(|| format!("Failed to write {path}"))?;
+            written.push(
+                path.strip_prefix(&self.base_path)
+                    .map(Utf8Path::to_path_buf)
+                    .unwrap_or(path),
+            );
+        }
+        Ok(written)
+    }
+
+    /// The file's recipes, by name
+    async fn convert(&self, file: &Utf8Path) -> Result<Vec<(String, String)>> {
+        let stem = file.file_stem().unwrap_or("Recipe").to_string();
+        let extension = file.extension().map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("cook") => {
+                let content = std::fs::read_to_string(file)
+                    .with_context(|| format!("Failed to read {file}"))?;
+                Ok(vec![(stem, content)])
+            }
+            Some("url" | "urls" | "txt" | "md") => {
+                let content = std::fs::read_to_string(file)
+                    .with_context(|| format!("Failed to read {file}"))?;
+                match urls(&content) {
+                    Some(urls) => {
+                        let mut recipes = Vec::new();
+                        for url in urls {
+                            recipes.push(import_url(url).await?);
+                        }
+                        Ok(recipes)
+                    }
+                    None if matches!(extension.as_deref(), Some("txt" | "md")) => {
+                        self.convert_text(&content, &stem).await
+                    }
+                    None => bail!("No URLs in the file"),
+                }
+            }
+            Some("jpg" | "jpeg" | "png" | "webp") => {
+                let Some(config) = &self.ai else {
+                    bail!("Photos of recipes need the AI provider, turn it on with --ai");
+                };
+                let image = ai::Image {
+                    media_type: mime_guess::from_path(file)
+                        .first_or_octet_stream()
+                        .to_string(),
+                    data: std::fs::read(file).with_context(|| format!("Failed to read {file}"))?,
+                };
+                let reply = ai::complete(config, IMAGE_PROMPT, Some(&image), 4000).await?;
+                Ok(vec![named(ai::strip_code_fence(&reply), &stem)])
+            }
+            _ => bail!("Don't know how to import this kind of file"),
+        }
+    }
+
+    async fn convert_text(&self, content: &str, stem: &str) -> Result<Vec<(String, String)>> {
+        match text::parse(content, stem) {
+            Ok(recipe) => Ok(vec![(recipe.name.clone(), to_cooklang(&recipe))]),
+            Err(e) => {
+                let Some(config) = &self.ai else {
+                    return Err(e.context("Turn on --ai to convert recipes without headings"));
+                };
+                let prompt = format!("{TEXT_PROMPT}\n\nRecipe:\n{content}");
+                // Room for the recipe and its markup, within what the model
+                // can answer
+                let max_tokens = u32::try_from(content.len())
+                    .unwrap_or(u32::MAX)
+                    .saturating_mul(2)
+                    .clamp(2000, ai::MAX_TOKENS);
+                let reply = ai::complete(config, &prompt, None, max_tokens).await?;
+                Ok(vec![named(ai::strip_code_fence(&reply), stem)])
+            }
+        }
+    }
+
+    fn move_to_failed(&self, file: &Utf8Path) -> Result<()> {
+        let failed = self.dir.join(FAILED_DIR);
+        std::fs::create_dir_all(&failed).with_context(|| format!("Failed to create {failed}"))?;
+        let to = failed.join(file.file_name().unwrap_or("file"));
+        std::fs::rename(file, &to).with_context(|| format!("Failed to move {file} to {to}"))
+    }
+}
+
+/// The URLs of a file with nothing else, one per line
+///
+/// Windows `.url` shortcuts have theirs in a `URL=` line.
+fn urls(content: &str) -> Option<Vec<&str>> {
+    let lines: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let shortcut: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("URL="))
+        .collect();
+    let urls = if shortcut.is_empty() { lines } else { shortcut };
+    let is_url = |line: &&str| line.starts_with("http://") || line.starts_with("https://");
+    (!urls.is_empty() && urls.iter().all(is_url)).then_some(urls)
+}
+
+async fn import_url(url: &str) -> Result<(String, String)> {
+    let content = cooklang_import::import_recipe(url)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to import {url}: {e}"))?;
+    let fallback = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or("Recipe")
+        .replace('-', " ");
+    Ok(named(&content, &fallback))
+}
+
+/// The recipe with the name from its title, or `fallback`
+fn named(content: &str, fallback: &str) -> (String, String) {
+    let title = FrontMatter::parse(content)
+        .ok()
+        .and_then(|front_matter| front_matter.get("title").map(display_value))
+        .filter(|title| !title.trim().is_empty());
+    let name = title.unwrap_or_else(|| fallback.to_string());
+    (name, format!("{}\n", content.trim_end()))
+}
+
+/// `<name>.cook` in `dir`, or `<name> 2.cook` and so on when it exists
+fn unique_path(dir: &Utf8Path, name: &str) -> Utf8PathBuf {
+    let stem = sanitize_stem(name);
+    let mut path = dir.join(format!("{stem}.cook"));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{stem} {n}.cook"));
+        n += 1;
+    }
+    path
+}
+
+async fn post(url: &str, event: &InboxEvent) -> Result<()> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .json(event)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {url}"))?;
+    if !response.status().is_success() {
+        bail!("{url} answered {}", response.status());
+    }
+    Ok(())
+}
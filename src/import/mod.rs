@@ -0,0 +1,413 @@
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand, ValueEnum};
+use cooklang_import::{fetch_recipe, generate_frontmatter, import_recipe};
+
+use crate::{
+    seed,
+    util::{
+        images::ImageOptions,
+        import::{save, ImportedRecipe},
+    },
+    Context,
+};
+
+mod crouton;
+pub mod inbox;
+mod table;
+mod text;
+mod whisk;
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum MetadataFormat {
+    /// Include metadata as YAML frontmatter (default for Cooklang output)
+    Frontmatter,
+    /// Output metadata as JSON
+    Json,
+    /// Output metadata as YAML
+    Yaml,
+    /// Don't include metadata
+    None,
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct ImportArgs {
+    /// URL of the recipe webpage to import
+    ///
+    /// The importer supports many popular recipe websites and will
+    /// automatically extract ingredients, instructions, and metadata.
+    /// The recipe will be converted to Cooklang format unless
+    /// --skip-conversion is used.
+    ///
+    /// Example URLs:
+    ///   https://www.allrecipes.com/recipe/...
+    ///   https://www.bbcgoodfood.com/recipes/...
+    ///   https://cooking.nytimes.com/recipes/...
+    #[arg(value_name = "URL", required = true)]
+    url: Option<String>,
+
+    /// Output the original recipe data without converting to Cooklang
+    ///
+    /// By default, imported recipes are converted to Cooklang format.
+    /// Use this flag to get the raw recipe data as extracted from
+    /// the website (useful for debugging or custom processing).
+    #[arg(short, long)]
+    skip_conversion: bool,
+
+    /// How to include metadata in the output
+    ///
+    /// When using --skip-conversion, metadata can be output separately
+    /// in different formats. With Cooklang conversion, metadata is
+    /// automatically included as frontmatter.
+    #[arg(long, value_enum, default_value = "frontmatter")]
+    metadata: MetadataFormat,
+
+    /// Output only the metadata (no recipe content)
+    ///
+    /// Useful for extracting just the metadata from a recipe webpage
+    /// for analysis or processing.
+    #[arg(long)]
+    metadata_only: bool,
+
+    #[command(subcommand)]
+    command: Option<ImportCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportCommand {
+    /// Import recipes exported from Crouton
+    ///
+    /// Reads `.crumb` files, or a `.zip` of them as exported with
+    /// "Export all recipes". Recipe images are kept.
+    ///
+    /// Examples:
+    ///   cook import crouton Lasagna.crumb
+    ///   cook import crouton "Crouton Recipes.zip" -o imported
+    Crouton(FileImportArgs),
+
+    /// Import recipes exported from Whisk or Samsung Food
+    ///
+    /// Reads the recipe `.json` files of the data export, or the `.zip`
+    /// with them. Recipe images are downloaded.
+    ///
+    /// Examples:
+    ///   cook import whisk samsung-food-export.zip
+    ///   cook import whisk recipes/*.json -o imported
+    Whisk(FileImportArgs),
+
+    /// Import recipes from a spreadsheet saved as CSV
+    ///
+    /// Each row is a recipe. --map says which column holds which part of
+    /// the recipe; without it, columns with common names like "Title",
+    /// "Ingredients" and "Directions" are used.
+    ///
+    /// Examples:
+    ///   cook import csv recipes.csv
+    ///   cook import csv data.csv --map 'title=Name,ingredients=Ingredients,steps=Directions'
+    ///   cook import csv data.csv --map 'title=Name,servings=Yield,tags=Category'
+    Csv(MappedImportArgs),
+
+    /// Import recipes from a JSON list of objects
+    ///
+    /// Each object is a recipe, like the rows of `cook import csv`. Fields
+    /// of nested objects are mapped with their path, like `source=link.url`.
+    ///
+    /// Examples:
+    ///   cook import json recipes.json
+    ///   cook import json data.json --map 'title=title,ingredients=items,steps=method'
+    Json(MappedImportArgs),
+
+    /// Import every recipe dropped into a folder
+    ///
+    /// Text and Markdown recipes with "Ingredients" and "Method" headings,
+    /// files listing recipe URLs and Cooklang files are moved into the
+    /// collection once they parse. With --ai, photos of recipes and text
+    /// without headings are converted by the AI provider. Files that can't
+    /// be imported are moved to failed/ in the inbox.
+    ///
+    /// Examples:
+    ///   cook import inbox                    # Watch inbox/ in the collection
+    ///   cook import inbox ~/Dropbox/Recipes --ai
+    ///   cook import inbox --once             # Import what's there and stop
+    Inbox(InboxArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct InboxArgs {
+    /// Folder to import from
+    ///
+    /// Defaults to `dir` in the `[inbox]` section of cook.toml, or inbox/ in
+    /// the collection.
+    #[arg(value_hint = clap::ValueHint::DirPath)]
+    dir: Option<Utf8PathBuf>,
+
+    /// Directory to write the recipes to
+    ///
+    /// Defaults to the collection's directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    output_dir: Option<Utf8PathBuf>,
+
+    /// Convert photos and text without headings with the AI provider
+    #[arg(long)]
+    ai: bool,
+
+    /// URL to post every import to, as JSON
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// Import the files in the inbox and stop instead of watching it
+    #[arg(long)]
+    once: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct MappedImportArgs {
+    #[command(flatten)]
+    files: FileImportArgs,
+
+    /// Columns of the parts of a recipe, like
+    /// 'title=Name,ingredients=Ingredients,steps=Directions'
+    ///
+    /// Besides title, ingredients, steps, notes and image, any part becomes
+    /// metadata, like 'servings=Yield'.
+    #[arg(short, long, value_name = "PART=COLUMN,...")]
+    map: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct FileImportArgs {
+    /// Exported files, or `.zip` archives of them
+    #[arg(required = true, value_hint = clap::ValueHint::FilePath)]
+    files: Vec<Utf8PathBuf>,
+
+    /// Directory to write the recipes to
+    ///
+    /// Defaults to the collection's directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    output_dir: Option<Utf8PathBuf>,
+
+    /// Replace recipes that already exist
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn run(ctx: &Context, args: ImportArgs) -> Result<()> {
+    match args.command {
+        Some(ImportCommand::Crouton(args)) => import_files(ctx, &args, "crumb", crouton::parse),
+        Some(ImportCommand::Whisk(args)) => import_files(ctx, &args, "json", whisk::parse),
+        Some(ImportCommand::Csv(args)) => {
+            let map = args
+                .map
+                .as_deref()
+                .map(table::FieldMap::parse)
+                .transpose()?;
+            import_files(ctx, &args.files, "csv", |bytes| {
+                table::parse_csv(bytes, map.as_ref())
+            })
+        }
+        Some(ImportCommand::Json(args)) => {
+            let map = args
+                .map
+                .as_deref()
+                .map(table::FieldMap::parse)
+                .transpose()?;
+            import_files(ctx, &args.files, "json", |bytes| {
+                table::parse_json(bytes, map.as_ref())
+            })
+        }
+        Some(ImportCommand::Inbox(args)) => run_inbox(ctx, args),
+        None => {
+            let Some(url) = args.url.clone() else {
+                bail!("Give the URL of a recipe to import");
+            };
+            import_url(&url, args)
+        }
+    }
+}
+
+/// Convert every file with the extension, read directly or from `.zip`
+/// archives, and write the recipes
+///
+/// A file that can't be read is reported and the others are still imported.
+fn import_files(
+    ctx: &Context,
+    args: &FileImportArgs,
+    extension: &str,
+    parse: impl Fn(&[u8]) -> Result<Vec<ImportedRecipe>>,
+) -> Result<()> {
+    let out_dir = args.output_dir.as_deref().unwrap_or(ctx.base_path());
+
+    let mut files = Vec::new();
+    for path in &args.files {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+        if path.extension() == Some("zip") {
+            let entries =
+                seed::unzip(&bytes).with_context(|| format!("Failed to unpack {path}"))?;
+            files.extend(
+                entries
+                    .into_iter()
+                    .filter(|entry| Utf8Path::new(&entry.path).extension() == Some(extension))
+                    .map(|entry| (format!("{path}:{}", entry.path), entry.content.into_owned())),
+            );
+        } else {
+            files.push((path.to_string(), bytes));
+        }
+    }
+
+    let images = ImageOptions::from_config(&ctx.config().images);
+    let (mut imported, mut skipped, mut failed) = (0, 0, 0);
+    for (name, bytes) in files {
+        let recipes = match parse(&bytes) {
+            Ok(recipes) => recipes,
+            Err(e) => {
+                eprintln!("Skipping {name}: {e:#}");
+                failed += 1;
+                continue;
+            }
+        };
+        for recipe in recipes {
+            match save(&recipe, out_dir, args.force, &images)? {
+                Some(path) => {
+                    println!("Imported {path}");
+                    imported += 1;
+                }
+                None => {
+                    eprintln!(
+                        "Skipping {}: it already exists, use --force to replace it",
+                        recipe.name
+                    );
+                    skipped += 1;
+                }
+            }
+        }
+    }
+
+    println!("{imported} recipes imported, {skipped} skipped, {failed} files failed");
+    if imported == 0 && failed > 0 {
+        bail!("No recipes could be imported");
+    }
+    Ok(())
+}
+
+fn run_inbox(ctx: &Context, args: InboxArgs) -> Result<()> {
+    let mut inbox = inbox::Inbox::from_config(ctx.base_path(), ctx.config());
+    if let Some(dir) = args.dir {
+        inbox.dir = dir;
+    }
+    if let Some(dir) = args.output_dir {
+        inbox.output_dir = dir;
+    }
+    if args.ai {
+        inbox.ai = Some(ctx.config().ai.clone());
+    }
+    if args.webhook.is_some() {
+        inbox.webhook = args.webhook;
+    }
+
+    let report = |event: &inbox::InboxEvent| match &event.error {
+        None => {
+            let recipes: Vec<_> = event.recipes.iter().map(|path| path.as_str()).collect();
+            println!("✓ Imported {} as {}", event.file, recipes.join(", "));
+        }
+        Some(error) => eprintln!("❌ {}: {error}", event.file),
+    };
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    if args.once {
+        let mut failed = 0;
+        for file in inbox.pending()? {
+            let event = runtime.block_on(inbox.process(&file));
+            report(&event);
+            if event.status == inbox::InboxStatus::Failed {
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            bail!(
+                "{failed} files couldn't be imported, they were moved to {}",
+                inbox.dir.join(inbox::FAILED_DIR)
+            );
+        }
+        return Ok(());
+    }
+
+    println!("Watching {} for recipes, press Ctrl+C to stop", inbox.dir);
+    runtime.block_on(inbox.watch(report));
+    Ok(())
+}
+
+fn import_url(url: &str, args: ImportArgs) -> Result<()> {
+    let output = tokio::runtime::Runtime::new()?.block_on(async {
+        let recipe = fetch_recipe(url)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        // Handle metadata-only output
+        if args.metadata_only {
+            return match args.metadata {
+                MetadataFormat::Json => serde_json::to_string_pretty(&recipe.metadata)
+                    .map_err(|e| anyhow::anyhow!("Failed to serialize metadata to JSON: {}", e)),
+                MetadataFormat::Yaml => serde_yaml::to_string(&recipe.metadata)
+                    .map_err(|e| anyhow::anyhow!("Failed to serialize metadata to YAML: {}", e)),
+                MetadataFormat::Frontmatter => Ok(generate_frontmatter(&recipe.metadata)),
+                MetadataFormat::None => Ok(String::new()),
+            };
+        }
+
+        // Handle full recipe output
+        if args.skip_conversion {
+            let mut output = String::new();
+
+            // Add metadata based on format
+            match args.metadata {
+                MetadataFormat::Frontmatter => {
+                    output.push_str(&generate_frontmatter(&recipe.metadata));
+                }
+                MetadataFormat::Json => {
+                    if !recipe.metadata.is_empty() {
+                        output.push_str(&format!(
+                            "[Metadata]\n{}\n\n",
+                            serde_json::to_string_pretty(&recipe.metadata)?
+                        ));
+                    }
+                }
+                MetadataFormat::Yaml => {
+                    if !recipe.metadata.is_empty() {
+                        output.push_str(&format!(
+                            "[Metadata]\n{}\n\n",
+                            serde_yaml::to_string(&recipe.metadata)?
+                        ));
+                    }
+                }
+                MetadataFormat::None => {}
+            }
+
+            // Add recipe content
+            output.push_str(&format!("{}\n\n", recipe.name));
+
+            if let Some(desc) = &recipe.description {
+                output.push_str(&format!("{desc}\n\n"));
+            }
+
+            output.push_str(&format!(
+                "[Ingredients]\n{}\n\n[Instructions]\n{}",
+                recipe.ingredients, recipe.instructions
+            ));
+
+            if !recipe.image.is_empty() {
+                output.push_str(&format!("\n\n[Images]\n{}", recipe.image.join("\n")));
+            }
+
+            Ok(output)
+        } else {
+            // Convert to Cooklang (includes metadata as frontmatter by default)
+            import_recipe(url)
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))
+        }
+    })?;
+
+    println!("{output}");
+    Ok(())
+}
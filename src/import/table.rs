@@ -0,0 +1,245 @@
+//! Spreadsheet-like exports: a CSV row or a JSON object per recipe, with
+//! columns mapped to the parts of a recipe
+
+use anyhow::{bail, Context as _, Result};
+use serde_json::Value;
+
+use crate::util::{
+    csv,
+    import::{
+        format_amount, ingredient_lines, parse_ingredient_line, step_lines, ImportedImage,
+        ImportedRecipe, ImportedStep,
+    },
+};
+
+/// Columns used without `--map`, by their name in lowercase
+const DEFAULT_COLUMNS: &[(&str, &[&str])] = &[
+    ("title", &["title", "name", "recipe", "recipe name"]),
+    ("ingredients", &["ingredients", "ingredient list"]),
+    (
+        "steps",
+        &[
+            "steps",
+            "directions",
+            "instructions",
+            "method",
+            "preparation",
+        ],
+    ),
+    ("notes", &["notes", "note", "comments"]),
+    ("image", &["image", "image url", "photo", "picture"]),
+    ("servings", &["servings", "serves", "yield"]),
+    ("source", &["source", "url", "link", "source url"]),
+    ("author", &["author"]),
+    ("tags", &["tags", "categories", "category"]),
+    ("description", &["description", "summary"]),
+    ("prep time", &["prep time", "prep_time", "preptime"]),
+    ("cook time", &["cook time", "cook_time", "cooktime"]),
+    ("cuisine", &["cuisine"]),
+    ("course", &["course"]),
+];
+
+/// Parts of the recipe that aren't metadata
+const PARTS: &[&str] = &["title", "ingredients", "steps", "notes", "image"];
+
+/// Which column each part of the recipe comes from
+#[derive(Debug, Clone)]
+pub struct FieldMap(Vec<(String, String)>);
+
+impl FieldMap {
+    /// A mapping like "title=Name,ingredients=Ingredients,steps=Directions"
+    ///
+    /// Parts other than title, ingredients, steps, notes and image become
+    /// metadata, like "servings=Yield".
+    pub fn parse(map: &str) -> Result<Self> {
+        let mut fields = Vec::new();
+        for pair in map.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let Some((part, column)) = pair.split_once('=') else {
+                bail!("Expected PART=COLUMN in --map, got '{pair}'");
+            };
+            let part = part.trim().to_lowercase().replace('_', " ");
+            fields.push((part, column.trim().to_string()));
+        }
+        if !fields.iter().any(|(part, _)| part == "title") {
+            bail!("--map needs the column of the title, like title=Name");
+        }
+        Ok(Self(fields))
+    }
+
+    /// The known columns among `columns`
+    fn detect(columns: &[String]) -> Result<Self> {
+        let mut fields = Vec::new();
+        for (part, names) in DEFAULT_COLUMNS {
+            if let Some(column) = columns
+                .iter()
+                .find(|column| names.contains(&column.trim().to_lowercase().as_str()))
+            {
+                fields.push((part.to_string(), column.clone()));
+            }
+        }
+        if !fields.iter().any(|(part, _)| part == "title") {
+            bail!(
+                "No title column among {}, say which one it is with --map title=COLUMN",
+                columns.join(", ")
+            );
+        }
+        Ok(Self(fields))
+    }
+
+    fn column(&self, part: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(p, _)| p == part)
+            .map(|(_, column)| column.as_str())
+    }
+}
+
+pub fn parse_csv(bytes: &[u8], map: Option<&FieldMap>) -> Result<Vec<ImportedRecipe>> {
+    let content = std::str::from_utf8(bytes).context("The CSV file isn't UTF-8")?;
+    let mut records = csv::parse(content).into_iter();
+    let Some(header) = records.next() else {
+        bail!("The CSV file is empty");
+    };
+    let map = match map {
+        Some(map) => map.clone(),
+        None => FieldMap::detect(&header)?,
+    };
+    for (_, column) in &map.0 {
+        if !header.iter().any(|h| h.trim() == column) {
+            bail!(
+                "No column '{column}', the columns are {}",
+                header.join(", ")
+            );
+        }
+    }
+
+    let header = &header;
+    let rows = records.map(|record| {
+        move |column: &str| {
+            header
+                .iter()
+                .position(|h| h.trim() == column)
+                .and_then(|i| record.get(i))
+                .map(|cell| cell.trim().to_string())
+                .filter(|cell| !cell.is_empty())
+        }
+    });
+    Ok(rows
+        .enumerate()
+        .filter_map(|(i, cell)| recipe(&map, cell, i + 2))
+        .collect())
+}
+
+pub fn parse_json(bytes: &[u8], map: Option<&FieldMap>) -> Result<Vec<ImportedRecipe>> {
+    let json: Value = serde_json::from_slice(bytes).context("Not a JSON file")?;
+    let objects: Vec<&Value> = match &json {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(object) => match object.get("recipes") {
+            Some(Value::Array(items)) => items.iter().collect(),
+            _ => vec![&json],
+        },
+        _ => bail!("Expected a list of recipes"),
+    };
+    let map = match map {
+        Some(map) => map.clone(),
+        None => {
+            let keys: Vec<String> = objects
+                .iter()
+                .filter_map(|object| object.as_object())
+                .flat_map(|object| object.keys().cloned())
+                .collect();
+            FieldMap::detect(&keys)?
+        }
+    };
+
+    Ok(objects
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, object)| {
+            let cell = |path: &str| lookup(object, path).and_then(json_text);
+            recipe(&map, cell, i + 1)
+        })
+        .collect())
+}
+
+/// A value by its path, like "source.url"
+fn lookup<'a>(json: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(json, |value, key| match value {
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => value.get(key),
+    })
+}
+
+/// Lists become one item per line
+fn json_text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.as_f64().map(format_amount)?,
+        Value::Bool(b) => b.to_string(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(json_text)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Object(_) => ["text", "name", "url"]
+            .iter()
+            .find_map(|key| value.get(key).and_then(json_text))?,
+        Value::Null => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// The recipe of one row; rows without a title are left out with a warning
+fn recipe(
+    map: &FieldMap,
+    cell: impl Fn(&str) -> Option<String>,
+    row: usize,
+) -> Option<ImportedRecipe> {
+    let part = |part: &str| map.column(part).and_then(&cell);
+    let Some(name) = part("title") else {
+        tracing::warn!("Skipping row {row}, it has no title");
+        return None;
+    };
+
+    let metadata = map
+        .0
+        .iter()
+        .filter(|(part, _)| !PARTS.contains(&part.as_str()))
+        .filter_map(|(part, column)| {
+            let value = cell(column)?;
+            // Lists of tags can be one per line too
+            let value = match part.as_str() {
+                "tags" => value.lines().collect::<Vec<_>>().join(", "),
+                _ => value.lines().collect::<Vec<_>>().join(" "),
+            };
+            Some((part.clone(), value))
+        })
+        .collect();
+
+    Some(ImportedRecipe {
+        name,
+        metadata,
+        ingredients: part("ingredients")
+            .map(|text| {
+                ingredient_lines(&text)
+                    .iter()
+                    .map(|line| parse_ingredient_line(line))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        steps: part("steps")
+            .map(|text| {
+                step_lines(&text)
+                    .into_iter()
+                    .map(ImportedStep::Step)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        notes: part("notes").into_iter().collect(),
+        images: part("image")
+            .filter(|url| url.starts_with("http"))
+            .map(ImportedImage::Url)
+            .into_iter()
+            .collect(),
+    })
+}
@@ -0,0 +1,106 @@
+//! Recipes written as plain text, like notes or a recipe copied from a
+//! webpage
+//!
+//! The recipe needs a heading before its ingredients and one before its
+//! steps, like "Ingredients" and "Method". The first line is the title.
+
+use anyhow::{bail, Result};
+
+use crate::util::import::{
+    ingredient_lines, parse_ingredient_line, step_lines, ImportedRecipe, ImportedStep,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Part {
+    Intro,
+    Ingredients,
+    Steps,
+    Notes,
+}
+
+pub fn parse(content: &str, fallback_name: &str) -> Result<ImportedRecipe> {
+    let mut lines = content.lines().map(str::trim).skip_while(|l| l.is_empty());
+    let mut name = fallback_name.to_string();
+    let mut part = Part::Intro;
+    match lines.next() {
+        Some(first) => match heading(first) {
+            Some(heading) => part = heading,
+            None => name = first.trim_start_matches('#').trim().to_string(),
+        },
+        None => bail!("The file is empty"),
+    }
+
+    let (mut intro, mut ingredients, mut steps, mut notes) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let (mut has_ingredients, mut has_steps) = (part == Part::Ingredients, part == Part::Steps);
+    for line in lines {
+        if let Some(heading) = heading(line) {
+            has_ingredients |= heading == Part::Ingredients;
+            has_steps |= heading == Part::Steps;
+            part = heading;
+            continue;
+        }
+        match part {
+            Part::Intro => intro.push(line),
+            Part::Ingredients => ingredients.push(line),
+            Part::Steps => steps.push(line),
+            Part::Notes => notes.push(line),
+        }
+    }
+    if !has_ingredients || !has_steps {
+        bail!("No \"Ingredients\" and \"Method\" headings to tell the ingredients from the steps");
+    }
+
+    let mut metadata = Vec::new();
+    let mut description = Vec::new();
+    for line in intro.into_iter().filter(|line| !line.is_empty()) {
+        match servings(line) {
+            Some(servings) => metadata.push(("servings".to_string(), servings.to_string())),
+            None => description.push(line),
+        }
+    }
+    if !description.is_empty() {
+        metadata.push(("description".to_string(), description.join(" ")));
+    }
+
+    Ok(ImportedRecipe {
+        name,
+        metadata,
+        ingredients: ingredient_lines(&ingredients.join("\n"))
+            .iter()
+            .map(|line| parse_ingredient_line(line))
+            .collect(),
+        steps: step_lines(&steps.join("\n"))
+            .into_iter()
+            .map(ImportedStep::Step)
+            .collect(),
+        notes: step_lines(&notes.join("\n")),
+        images: Vec::new(),
+    })
+}
+
+/// The part a heading like "## Ingredients:" starts
+fn heading(line: &str) -> Option<Part> {
+    let text = line
+        .trim_matches(|c: char| c == '#' || c == '*' || c == ':' || c.is_whitespace())
+        .to_lowercase();
+    match text.as_str() {
+        "ingredients" => Some(Part::Ingredients),
+        "instructions" | "method" | "directions" | "steps" | "preparation" => Some(Part::Steps),
+        "notes" => Some(Part::Notes),
+        _ => None,
+    }
+}
+
+/// The number of servings in a line like "Serves 4" or "Servings: 4"
+fn servings(line: &str) -> Option<&str> {
+    let rest = ["servings", "serves", "yield"].iter().find_map(|prefix| {
+        line.get(..prefix.len())
+            .filter(|start| start.eq_ignore_ascii_case(prefix))
+            .map(|_| &line[prefix.len()..])
+    })?;
+    let number = rest.trim_start_matches([':', ' ']).trim();
+    number
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then_some(number)
+}
@@ -0,0 +1,200 @@
+//! Recipes from the data export of Whisk, now Samsung Food
+//!
+//! The export has changed over the years, so fields are looked up by the
+//! names the different versions used, including the schema.org names of
+//! recipes saved from the web.
+
+use anyhow::{bail, Context as _, Result};
+use serde_json::Value;
+
+use crate::util::import::{
+    format_amount, parse_ingredient_line, ImportedImage, ImportedIngredient, ImportedRecipe,
+    ImportedStep, IngredientLine,
+};
+
+pub fn parse(bytes: &[u8]) -> Result<Vec<ImportedRecipe>> {
+    let json: Value = serde_json::from_slice(bytes).context("Not a JSON file")?;
+    let recipes: Vec<ImportedRecipe> = recipes_in(&json).into_iter().filter_map(recipe).collect();
+    if recipes.is_empty() {
+        bail!("No recipes in it");
+    }
+    Ok(recipes)
+}
+
+/// A file has one recipe, a list of them or a list under `recipes`
+fn recipes_in(json: &Value) -> Vec<&Value> {
+    match json {
+        Value::Array(items) => items.iter().flat_map(recipes_in).collect(),
+        Value::Object(object) => {
+            for key in ["recipes", "recipe", "content"] {
+                if let Some(inner) = object.get(key).filter(|v| v.is_array() || v.is_object()) {
+                    return recipes_in(inner);
+                }
+            }
+            vec![json]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The first of the fields that is set
+fn field<'a>(json: &'a Value, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter()
+        .filter_map(|key| json.get(key))
+        .find(|value| !value.is_null())
+}
+
+/// A string, number, or the name or URL of an object, as text
+fn text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.as_f64().map(format_amount)?,
+        Value::Object(_) => {
+            return field(value, &["text", "name", "url", "displayName"]).and_then(text)
+        }
+        Value::Array(items) => return items.first().and_then(text),
+        _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+fn recipe(json: &Value) -> Option<ImportedRecipe> {
+    let name = field(json, &["name", "title"]).and_then(text)?;
+    let durations = json.get("durations").unwrap_or(&Value::Null);
+    let time = |keys: &[&str]| {
+        field(json, keys)
+            .or_else(|| field(durations, keys))
+            .and_then(duration)
+    };
+
+    let tags = json
+        .get("tags")
+        .and_then(Value::as_array)
+        .map(|tags| tags.iter().filter_map(text).collect::<Vec<_>>().join(", "));
+    let metadata = [
+        (
+            "servings",
+            field(json, &["servings", "recipeYield", "yield"]).and_then(text),
+        ),
+        ("prep time", time(&["prepTime", "prep_time"])),
+        ("cook time", time(&["cookTime", "cook_time"])),
+        ("time required", time(&["totalTime", "total_time"])),
+        (
+            "source",
+            field(json, &["source", "sourceUrl", "originalUrl", "url"])
+                .and_then(|source| source.get("url").and_then(text).or_else(|| text(source))),
+        ),
+        ("author", field(json, &["author"]).and_then(text)),
+        ("description", field(json, &["description"]).and_then(text)),
+        ("tags", tags),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| Some((key.to_string(), value?)))
+    .collect();
+
+    let ingredients = field(json, &["ingredients", "recipeIngredient"])
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(ingredient).collect())
+        .unwrap_or_default();
+
+    let mut steps = Vec::new();
+    if let Some(instructions) = field(
+        json,
+        &["instructions", "recipeInstructions", "steps", "directions"],
+    ) {
+        collect_steps(instructions, &mut steps);
+    }
+
+    let images = field(json, &["images", "image"])
+        .map(|images| match images {
+            Value::Array(items) => items.iter().filter_map(text).collect(),
+            other => text(other).into_iter().collect(),
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|url| url.starts_with("http"))
+        .map(ImportedImage::Url)
+        .collect();
+
+    Some(ImportedRecipe {
+        name,
+        metadata,
+        ingredients,
+        steps,
+        notes: field(json, &["notes", "note"])
+            .and_then(text)
+            .into_iter()
+            .collect(),
+        images,
+    })
+}
+
+fn ingredient(json: &Value) -> Option<IngredientLine> {
+    if let Value::String(line) = json {
+        return Some(parse_ingredient_line(line));
+    }
+    let name = json.get("name").and_then(text);
+    let amount = field(json, &["amount", "quantity"]).and_then(text);
+    match (name, amount) {
+        (Some(name), amount) => Some(IngredientLine::Ingredient(ImportedIngredient {
+            name,
+            quantity: amount,
+            unit: json.get("unit").and_then(text),
+            note: field(json, &["comment", "note"]).and_then(text),
+        })),
+        (None, _) => json
+            .get("text")
+            .and_then(text)
+            .map(|line| parse_ingredient_line(&line)),
+    }
+}
+
+/// Steps from a text, a list of steps, or sections with their own steps
+fn collect_steps(json: &Value, steps: &mut Vec<ImportedStep>) {
+    match json {
+        Value::String(text) => steps.extend(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| ImportedStep::Step(line.to_string())),
+        ),
+        Value::Array(items) => items.iter().for_each(|item| collect_steps(item, steps)),
+        Value::Object(object) => {
+            if let Some(inner) = field(json, &["steps", "itemListElement"]) {
+                if let Some(name) = object.get("name").and_then(text) {
+                    steps.push(ImportedStep::Section(name));
+                }
+                collect_steps(inner, steps);
+            } else if let Some(text) = object.get("text").and_then(text) {
+                steps.push(ImportedStep::Step(text));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Minutes, or an ISO 8601 duration like "PT1H30M", as text
+fn duration(value: &Value) -> Option<String> {
+    if let Some(minutes) = value.as_f64() {
+        return (minutes > 0.0).then(|| format!("{} minutes", format_amount(minutes)));
+    }
+    let text = value.as_str()?.trim();
+    let Some(iso) = text.strip_prefix("PT") else {
+        return (!text.is_empty()).then(|| text.to_string());
+    };
+    let mut parts = Vec::new();
+    let mut number = String::new();
+    for c in iso.chars() {
+        match c {
+            'H' => parts.push(format!("{number} hours")),
+            'M' => parts.push(format!("{number} minutes")),
+            'S' => {}
+            _ => {
+                number.push(c);
+                continue;
+            }
+        }
+        number.clear();
+    }
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
@@ -1,52 +1,434 @@
-// Re-export modules for testing
-use camino::Utf8PathBuf;
+// This file includes a substantial portion of code from
+// https://github.com/Zheoni/cooklang-chef
+//
+// The original code is licensed under the MIT License, a copy of which
+// is provided below in addition to our project's license.
+//
+//
 
-// Commands - make them available as public modules
+// MIT License
+
+// Copyright (c) 2023 Francisco J. Sanchez
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Cooklang recipe collections as a library
+//!
+//! This crate holds everything behind the `cook` command line tool. Besides
+//! the command modules, it offers a small API for tools that want to work
+//! with a recipe collection without shelling out to `cook`:
+//!
+//! - [`Context`] is a recipe collection and its configuration
+//! - [`Context::load_recipe`] finds and parses a recipe
+//! - [`render_recipe`] writes a recipe as text, JSON, YAML, Cooklang or Markdown,
+//!   [`render_recipe_parts`] only some of its [`RecipePart`]s
+//! - [`Context::shopping_list`] combines the ingredients of several recipes
+//! - [`Context::search`] finds recipes matching search terms
+//!
+//! ```no_run
+//! use cookcli::{render_recipe, Context, RecipeFormat};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let ctx = Context::new("/home/me/recipes".into());
+//! let pizza = ctx.load_recipe("Neapolitan Pizza:2")?;
+//! render_recipe(&pizza, RecipeFormat::Markdown, &mut std::io::stdout())?;
+//!
+//! let list = ctx.shopping_list(&["Neapolitan Pizza", "Caesar Salad"], false)?;
+//! for (ingredient, _quantity) in list {
+//!     println!("{ingredient}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{ingredient_list::IngredientList, Recipe};
+use cooklang_find::RecipeEntry;
+use std::sync::Arc;
+
+// commands
+pub mod backup;
+pub mod bench;
+pub mod collection;
+pub mod completions;
+pub mod cookware;
 pub mod doctor;
+pub mod edit;
+pub mod export;
+pub mod hooks;
 pub mod import;
+pub mod lint;
+pub mod man;
+pub mod migrate;
+pub mod notify;
 pub mod pantry;
+pub mod plugin;
+pub mod publish;
+pub mod random;
 pub mod recipe;
 pub mod report;
+pub mod restore;
+pub mod schema;
 pub mod search;
 pub mod seed;
 pub mod server;
 pub mod shopping_list;
+pub mod stats;
+pub mod sync;
+pub mod tag;
+pub mod timer;
+pub mod tui;
 #[cfg(feature = "self-update")]
 pub mod update;
+pub mod watch;
 
-// Other modules
+// other modules
 pub mod args;
+pub mod config;
+pub mod error;
 pub mod util;
 
-// Context struct for testing - matches the one in main.rs
+pub use config::Config;
+pub use recipe::read::{render_recipe, render_recipe_parts, OutputFormat as RecipeFormat};
+pub use util::RecipePart;
+
+const LOCAL_CONFIG_DIR: &str = "config";
+const APP_NAME: &str = "cook";
+/// Environment variable replacing the global configuration directory
+pub const CONFIG_DIR_ENV: &str = "COOK_CONFIG_DIR";
+const AUTO_AISLE: &str = "aisle.conf";
+const AUTO_PANTRY: &str = "pantry.conf";
+const AUTO_NUTRITION: &str = "nutrition.csv";
+
+/// A recipe collection and its configuration
+///
+/// Configuration files are looked up in the collection's `config`
+/// directory first, then in the global configuration directory. Files set
+/// in `cook.toml` take precedence over both.
 pub struct Context {
     base_path: Utf8PathBuf,
+    config: Config,
+}
+
+/// A recipe found in a collection, parsed and scaled
+pub struct LoadedRecipe {
+    /// Name of the recipe, from its file name
+    pub title: String,
+    /// Scaling factor the recipe was scaled by
+    pub scale: f64,
+    pub entry: RecipeEntry,
+    pub recipe: Arc<Recipe>,
 }
 
 impl Context {
     pub fn new(base_path: Utf8PathBuf) -> Self {
-        Self { base_path }
+        let config = Config::load(&base_path);
+        if let Some(extensions) = &config.parser.extensions {
+            util::configure_parser(extensions);
+        }
+        let mut walk = config.collection.walk_policy();
+        // What's waiting in the inbox, or failed to import, isn't a recipe yet
+        walk.skip.push(config.inbox.dir().to_path_buf());
+        util::walk::configure_walk(walk);
+        util::rounding::configure_rounding(config.rounding.rules());
+        util::temperature::configure_temperature(config.output.temperature);
+        util::scaling::configure_default_servings(config.recipe.default_servings);
+        util::aliases::configure_aliases(&config.aliases);
+        util::locale::configure_fraction_style(config.output.fraction_style());
+        util::locale::configure_locale(
+            config.output.locale.as_deref(),
+            util::locale::fraction_style().and_then(|style| config.output.fractions(style)),
+        );
+        let ctx = Self { base_path, config };
+        if let Some(path) = ctx.units() {
+            // Errors are reported in full by `cook doctor config`
+            if let Err(e) = util::units::CustomUnits::load(&path).and_then(util::configure_units) {
+                tracing::warn!("Ignoring custom units: {e:#}");
+            }
+        }
+        ctx
+    }
+
+    /// The context a command line runs in
+    ///
+    /// The collection is the one given to the command with `-b` (or the
+    /// directory of `cook server`), the current directory otherwise. Global
+    /// options that change how recipes are read are applied first, so they
+    /// win over `cook.toml`.
+    pub fn from_args(args: &args::CliArgs) -> Result<Self> {
+        if let Some(extensions) = &args.extensions {
+            util::configure_parser(extensions);
+        }
+        if let Some(jobs) = args.jobs {
+            util::configure_jobs(jobs)?;
+        }
+        util::temperature::configure_temperature(args.temperature);
+        util::locale::configure_fraction_style(args.fractions);
+
+        let base_path = args.base_path.as_deref().unwrap_or(Utf8Path::new("."));
+        let base_path = util::resolve_to_absolute_path(base_path)?;
+        // Commands like restore create the directory
+        if base_path.exists() && !base_path.is_dir() {
+            bail!("Base path is not a directory: {base_path}");
+        }
+        Ok(Self::new(base_path))
     }
 
+    /// Settings from the global and collection `cook.toml`
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Scaling factor for recipes loaded without one
+    pub fn default_scale(&self) -> f64 {
+        self.config.recipe.scale.unwrap_or(1.0)
+    }
+
+    /// Scaling factor for a recipe loaded without one
+    ///
+    /// With `recipe.default_servings`, recipes that say how many they serve
+    /// are scaled to that many servings. Others are scaled by
+    /// [`Context::default_scale`].
+    pub fn default_scale_for(&self, entry: &RecipeEntry) -> f64 {
+        util::scaling::default_servings_factor(entry).unwrap_or_else(|| self.default_scale())
+    }
+
+    /// Like [`Context::default_scale_for`], for a recipe by name
+    pub fn default_scale_of(&self, name: &str) -> f64 {
+        match util::get_recipe(&self.base_path, name) {
+            Ok(entry) => self.default_scale_for(&entry),
+            Err(_) => self.default_scale(),
+        }
+    }
+
+    /// The aisle configuration in use, if any
     pub fn aisle(&self) -> Option<Utf8PathBuf> {
-        let local_config = self.base_path.join("config").join("aisle.conf");
-        if local_config.is_file() {
-            Some(local_config)
-        } else {
-            None
+        if let Some(path) = &self.config.files.aisle {
+            return Some(path.clone());
         }
+        let auto = self.base_path.join(LOCAL_CONFIG_DIR).join(AUTO_AISLE);
+
+        tracing::trace!("checking auto aisle file: {auto}");
+
+        auto.is_file().then_some(auto).or_else(|| {
+            let global = global_file_path(AUTO_AISLE).ok()?;
+            tracing::trace!("checking global auto aisle file: {global}");
+            global.is_file().then_some(global)
+        })
     }
 
+    /// The pantry configuration in use, if any
     pub fn pantry(&self) -> Option<Utf8PathBuf> {
-        let local_config = self.base_path.join("config").join("pantry.conf");
-        if local_config.is_file() {
-            Some(local_config)
-        } else {
-            None
+        if let Some(path) = &self.config.files.pantry {
+            return Some(path.clone());
+        }
+        let auto = self.base_path.join(LOCAL_CONFIG_DIR).join(AUTO_PANTRY);
+
+        tracing::trace!("checking auto pantry file: {auto}");
+
+        auto.is_file().then_some(auto).or_else(|| {
+            let global = global_file_path(AUTO_PANTRY).ok()?;
+            tracing::trace!("checking global auto pantry file: {global}");
+            global.is_file().then_some(global)
+        })
+    }
+
+    /// The nutrition database in use, if any
+    pub fn nutrition(&self) -> Option<Utf8PathBuf> {
+        if let Some(path) = &self.config.files.nutrition {
+            return Some(path.clone());
+        }
+        let auto = self.base_path.join(LOCAL_CONFIG_DIR).join(AUTO_NUTRITION);
+
+        tracing::trace!("checking auto nutrition file: {auto}");
+
+        auto.is_file().then_some(auto).or_else(|| {
+            let global = global_file_path(AUTO_NUTRITION).ok()?;
+            tracing::trace!("checking global auto nutrition file: {global}");
+            global.is_file().then_some(global)
+        })
+    }
+
+    /// The custom units file in use, if any
+    pub fn units(&self) -> Option<Utf8PathBuf> {
+        if let Some(path) = &self.config.files.units {
+            return Some(path.clone());
+        }
+        let auto = self
+            .base_path
+            .join(LOCAL_CONFIG_DIR)
+            .join(util::units::UNITS_FILE);
+
+        tracing::trace!("checking auto units file: {auto}");
+
+        auto.is_file().then_some(auto).or_else(|| {
+            let global = global_file_path(util::units::UNITS_FILE).ok()?;
+            tracing::trace!("checking global auto units file: {global}");
+            global.is_file().then_some(global)
+        })
+    }
+
+    /// The substitutions file in use, if any
+    pub fn substitutions(&self) -> Option<Utf8PathBuf> {
+        if let Some(path) = &self.config.files.substitutions {
+            return Some(path.clone());
+        }
+        let auto = self
+            .base_path
+            .join(LOCAL_CONFIG_DIR)
+            .join(util::substitutions::SUBSTITUTIONS_FILE);
+
+        tracing::trace!("checking auto substitutions file: {auto}");
+
+        auto.is_file().then_some(auto).or_else(|| {
+            let global = global_file_path(util::substitutions::SUBSTITUTIONS_FILE).ok()?;
+            tracing::trace!("checking global auto substitutions file: {global}");
+            global.is_file().then_some(global)
+        })
+    }
+
+    /// The seasons file in use, if any
+    pub fn seasons(&self) -> Option<Utf8PathBuf> {
+        if let Some(path) = &self.config.files.seasons {
+            return Some(path.clone());
         }
+        let auto = self
+            .base_path
+            .join(LOCAL_CONFIG_DIR)
+            .join(util::seasons::SEASONS_FILE);
+
+        tracing::trace!("checking auto seasons file: {auto}");
+
+        auto.is_file().then_some(auto).or_else(|| {
+            let global = global_file_path(util::seasons::SEASONS_FILE).ok()?;
+            tracing::trace!("checking global auto seasons file: {global}");
+            global.is_file().then_some(global)
+        })
     }
 
     pub fn base_path(&self) -> &Utf8PathBuf {
         &self.base_path
     }
+
+    pub fn global_config_dir(&self) -> Option<Utf8PathBuf> {
+        global_config_dir().ok()
+    }
+
+    /// Find a recipe by name or path and parse it
+    ///
+    /// The query can end with a scaling factor, like "Pizza:2", or the name
+    /// of one of the recipe's `scalings`, like "Pizza:party". Otherwise the
+    /// recipe is scaled by [`Context::default_scale`].
+    pub fn load_recipe(&self, query: &str) -> Result<LoadedRecipe> {
+        match util::split_recipe_name_and_scaling_factor(query) {
+            Some((name, scale)) => self.load_recipe_named_scale(name, scale),
+            None => {
+                let entry = util::get_recipe(&self.base_path, query)?;
+                let scale = self.default_scale_for(&entry);
+                Self::load_entry(entry, scale)
+            }
+        }
+    }
+
+    /// Find a recipe by name or path and parse it scaled by `scale`
+    pub fn load_recipe_scaled(&self, name: &str, scale: f64) -> Result<LoadedRecipe> {
+        let entry = util::get_recipe(&self.base_path, name)?;
+        Self::load_entry(entry, scale)
+    }
+
+    /// Find a recipe by name or path and parse it scaled by a number or
+    /// one of the recipe's `scalings`, like "party"
+    pub fn load_recipe_named_scale(&self, name: &str, scale: &str) -> Result<LoadedRecipe> {
+        let entry = util::get_recipe(&self.base_path, name)?;
+        let scale = util::scaling::resolve(&entry, scale)?;
+        Self::load_entry(entry, scale)
+    }
+
+    fn load_entry(entry: RecipeEntry, scale: f64) -> Result<LoadedRecipe> {
+        let recipe = util::parse_recipe_from_entry(&entry, scale)?;
+        Ok(LoadedRecipe {
+            title: entry.name().clone().unwrap_or_default(),
+            scale,
+            entry,
+            recipe,
+        })
+    }
+
+    /// Combine the ingredients of several recipes into one list
+    ///
+    /// Recipes are given like for [`Context::load_recipe`], directories add
+    /// all their recipes. Ingredients of referenced recipes are included,
+    /// unless `ignore_references` is set. Ingredients listed under an alias
+    /// from `[aliases]` are merged into their name.
+    pub fn shopping_list(
+        &self,
+        recipes: &[impl AsRef<str>],
+        ignore_references: bool,
+    ) -> Result<IngredientList> {
+        let recipes: Vec<String> = recipes.iter().map(|r| r.as_ref().to_string()).collect();
+        let list = shopping_list::collect_ingredients(self, &recipes, ignore_references)?;
+        Ok(util::aliases::apply(list, util::PARSER.converter()).0)
+    }
+
+    /// Find the recipes matching search terms, best matches first
+    pub fn search(&self, query: &str) -> Result<Vec<RecipeEntry>> {
+        util::walk::search(&self.base_path, query)
+    }
+}
+
+/// The global configuration directory, like `~/.config/cook` on Linux
+///
+/// [`CONFIG_DIR_ENV`] (or `--config-dir`) replaces it, so all state can be
+/// kept in one place, e.g. in containers and CI.
+pub fn global_config_dir() -> Result<Utf8PathBuf> {
+    if let Some(dir) = config_dir_override()? {
+        return Ok(dir);
+    }
+    let dirs = directories::ProjectDirs::from("", "", APP_NAME)
+        .context("Could not determine home directory path")?;
+    util::utf8_path(dirs.config_dir().to_path_buf())
+}
+
+/// The directory for caches, like `~/.cache/cook` on Linux
+///
+/// With [`CONFIG_DIR_ENV`] set, the `cache` directory inside it.
+pub fn global_cache_dir() -> Result<Utf8PathBuf> {
+    if let Some(dir) = config_dir_override()? {
+        return Ok(dir.join("cache"));
+    }
+    let dirs = directories::ProjectDirs::from("", "", APP_NAME)
+        .context("Could not determine home directory path")?;
+    util::utf8_path(dirs.cache_dir().to_path_buf())
+}
+
+fn config_dir_override() -> Result<Option<Utf8PathBuf>> {
+    match std::env::var_os(CONFIG_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        Some(dir) => util::utf8_path(dir.into())
+            .with_context(|| format!("Invalid {CONFIG_DIR_ENV}"))
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Path of a file in the global configuration directory
+pub fn global_file_path(name: &str) -> Result<Utf8PathBuf> {
+    let path = global_config_dir()?.join(name);
+    Ok(path)
 }
@@ -0,0 +1,244 @@
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, ValueEnum};
+use cooklang_find::RecipeTree;
+use serde::Serialize;
+
+use crate::{
+    util::{
+        git::git_ok,
+        lint::{fix, lint, Finding, LintRules},
+        walk::build_tree,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct LintArgs {
+    /// Fix what can be fixed, like splitting long steps
+    #[arg(long)]
+    fix: bool,
+
+    /// Check only the recipes staged in git, as they are staged
+    ///
+    /// For the pre-commit hook of `cook hooks install`. Recipes that don't
+    /// parse are reported even without lint rules.
+    #[arg(long, conflicts_with = "fix")]
+    staged: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+
+    /// Directory of the recipe collection
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// The rules a recipe breaks
+#[derive(Debug, Serialize)]
+pub struct RecipeFindings {
+    pub path: Utf8PathBuf,
+    pub findings: Vec<Finding>,
+}
+
+pub fn run(ctx: &Context, args: LintArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let rules = ctx.config().lint.rules();
+    if rules.is_empty() && !args.staged {
+        bail!(
+            "No lint rules are on\n\
+             Turn some on in the [lint] section of cook.toml, \
+             like `cook config set lint.require_tags true`"
+        );
+    }
+
+    let mut fixed = 0;
+    if args.fix {
+        for (path, relative) in recipe_files(base_path)? {
+            let content =
+                std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path}"))?;
+            match fix(&content, &relative, &rules) {
+                Ok(Some(new_content)) => {
+                    std::fs::write(&path, new_content)
+                        .with_context(|| format!("Failed to write {path}"))?;
+                    eprintln!("Fixed {relative}");
+                    fixed += 1;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Can't fix {relative}: {e:#}"),
+            }
+        }
+    }
+
+    let (checked, results) = if args.staged {
+        lint_staged(base_path, &rules)?
+    } else {
+        lint_collection(base_path, &rules)?
+    };
+    let problems: usize = results.iter().map(|r| r.findings.len()).sum();
+
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&results)?),
+        OutputFormat::Human => {
+            for recipe in &results {
+                println!("\n📄 {}", recipe.path);
+                for finding in &recipe.findings {
+                    let line = finding
+                        .line
+                        .map(|line| format!("line {line}: "))
+                        .unwrap_or_default();
+                    let fixable = if finding.fixable { " (fixable)" } else { "" };
+                    println!(
+                        "  ❌ {}: {line}{}{fixable}",
+                        finding.rule.name(),
+                        finding.message
+                    );
+                }
+            }
+            if args.fix {
+                println!("\nFixed {fixed} recipes");
+            }
+            if problems == 0 && args.staged {
+                println!("✓ All {checked} staged recipes are fine");
+            } else if problems == 0 {
+                println!("✓ All {checked} recipes follow the lint rules");
+            } else {
+                let fixable = results
+                    .iter()
+                    .flat_map(|r| &r.findings)
+                    .filter(|f| f.fixable)
+                    .count();
+                println!(
+                    "\n{problems} problems in {} of {checked} recipes",
+                    results.len()
+                );
+                if fixable > 0 {
+                    println!("{fixable} can be fixed with `cook lint --fix`");
+                }
+            }
+        }
+    }
+
+    if problems > 0 {
+        bail!("{problems} lint problems in {} recipes", results.len());
+    }
+    Ok(())
+}
+
+/// Lint every recipe of a collection
+///
+/// Returns how many recipes were checked and the ones breaking a rule.
+pub fn lint_collection(
+    base_path: &Utf8Path,
+    rules: &LintRules,
+) -> Result<(usize, Vec<RecipeFindings>)> {
+    let tree = build_tree(base_path)?;
+    let mut entries = Vec::new();
+    collect(&tree, &mut entries);
+
+    let mut results = Vec::new();
+    for entry in &entries {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let relative = path.strip_prefix(base_path).unwrap_or(path).to_owned();
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let findings = lint(&content, &relative, entry.title_image().is_some(), rules);
+        if !findings.is_empty() {
+            results.push(RecipeFindings {
+                path: relative,
+                findings,
+            });
+        }
+    }
+    Ok((entries.len(), results))
+}
+
+/// Lint the recipes staged in git, reading them from the index
+///
+/// So what's checked is what gets committed, not what's in the working
+/// tree. Returns how many recipes were checked and the ones breaking a
+/// rule.
+fn lint_staged(base_path: &Utf8Path, rules: &LintRules) -> Result<(usize, Vec<RecipeFindings>)> {
+    let root = git_ok(base_path, &["rev-parse", "--show-toplevel"])
+        .context("The collection isn't in a git repository")?;
+    let root = Utf8PathBuf::from(root.trim());
+    let base_path = base_path
+        .canonicalize_utf8()
+        .with_context(|| format!("Failed to find {base_path}"))?;
+    let staged = git_ok(
+        &root,
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "-z",
+            "--diff-filter=ACMR",
+        ],
+    )?;
+
+    let mut checked = 0;
+    let mut results = Vec::new();
+    for name in staged.split('\0').filter(|name| name.ends_with(".cook")) {
+        let path = root.join(name);
+        let Ok(relative) = path.strip_prefix(&base_path) else {
+            continue;
+        };
+        let content = git_ok(&root, &["show", &format!(":{name}")])?;
+        checked += 1;
+        let findings = lint(&content, relative, has_image(&path), rules);
+        if !findings.is_empty() {
+            results.push(RecipeFindings {
+                path: relative.to_owned(),
+                findings,
+            });
+        }
+    }
+    Ok((checked, results))
+}
+
+/// Whether an image sits next to the recipe, like Pancakes.jpg
+fn has_image(path: &Utf8Path) -> bool {
+    ["jpg", "jpeg", "png", "webp"]
+        .iter()
+        .any(|ext| path.with_extension(ext).is_file())
+}
+
+/// Every recipe file, with its path in the collection
+fn recipe_files(base_path: &Utf8Path) -> Result<Vec<(Utf8PathBuf, Utf8PathBuf)>> {
+    let tree = build_tree(base_path)?;
+    let mut entries = Vec::new();
+    collect(&tree, &mut entries);
+    Ok(entries
+        .iter()
+        .filter_map(|entry| entry.path())
+        .map(|path| {
+            let relative = path.strip_prefix(base_path).unwrap_or(path).to_owned();
+            (path.to_owned(), relative)
+        })
+        .collect())
+}
+
+fn collect<'a>(tree: &'a RecipeTree, entries: &mut Vec<&'a cooklang_find::RecipeEntry>) {
+    if let Some(entry) = &tree.recipe {
+        if entry
+            .path()
+            .is_some_and(|path| path.extension() == Some("cook"))
+        {
+            entries.push(entry);
+        }
+    }
+    for child in tree.children.values() {
+        collect(child, entries);
+    }
+}
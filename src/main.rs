@@ -28,140 +28,140 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::util::resolve_to_absolute_path;
-use anyhow::{bail, Context as AnyhowContext, Result};
-use args::{CliArgs, Command};
-use camino::{Utf8Path, Utf8PathBuf};
-use clap::Parser;
-
-// commands
-mod doctor;
-mod import;
-mod pantry;
-mod recipe;
-mod report;
-mod search;
-mod seed;
-mod server;
-mod shopping_list;
-#[cfg(feature = "self-update")]
-mod update;
+use anyhow::{Context as _, Result};
+use clap::CommandFactory;
+use clap_complete::CompleteEnv;
+use cookcli::{
+    args::{CliArgs, Command},
+    backup, bench, collection,
+    completions::{self, COMPLETE_VAR},
+    config, cookware, doctor, edit, error, export, hooks, import, lint, man, migrate, notify,
+    pantry, plugin, publish, random, recipe, report, restore, schema, search, seed, server,
+    shopping_list, stats, sync, tag, timer, tui, watch, Context, CONFIG_DIR_ENV,
+};
+use std::process::ExitCode;
+use tracing_subscriber::EnvFilter;
 
-// other modules
-mod args;
-mod util;
+#[cfg(feature = "self-update")]
+use cookcli::update;
 
-const LOCAL_CONFIG_DIR: &str = "config";
-const APP_NAME: &str = "cook";
-const UTF8_PATH_PANIC: &str = "cook only supports UTF-8 paths.";
-const AUTO_AISLE: &str = "aisle.conf";
-const AUTO_PANTRY: &str = "pantry.conf";
+pub fn main() -> ExitCode {
+    // Answers the completion scripts from `cook completions`
+    CompleteEnv::with_factory(CliArgs::command)
+        .var(COMPLETE_VAR)
+        .complete();
 
-pub fn main() -> Result<()> {
     dotenvy::dotenv().ok();
-    
-    let args = CliArgs::parse();
-    configure_logging(args.verbosity);
 
-    let ctx = configure_context()?;
+    let args = CliArgs::parse_with_env();
+    let error_format = args.error_format.unwrap_or_default();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => error::report(&e, error_format),
+    }
+}
+
+fn run(args: CliArgs) -> Result<()> {
+    if let Some(dir) = &args.config_dir {
+        // Through the environment so plugins and commands cook runs use it too
+        let dir = std::env::current_dir()?.join(dir);
+        std::env::set_var(CONFIG_DIR_ENV, dir);
+    }
+    configure_color(args.color);
+    configure_logging(&args)?;
+    let ctx = Context::from_args(&args)?;
 
     match args.command {
         Command::Recipe(args) => recipe::run(&ctx, args),
         Command::Server(args) => server::run(ctx, args),
         Command::ShoppingList(args) => shopping_list::run(&ctx, args),
+        Command::Cookware(args) => cookware::run(&ctx, args),
         Command::Seed(args) => seed::run(&ctx, args),
+        Command::Collection(args) => collection::run(&ctx, args),
         Command::Search(args) => search::run(&ctx, args),
         Command::Import(args) => import::run(&ctx, args),
         Command::Report(args) => report::run(&ctx, args),
         Command::Doctor(args) => doctor::run(&ctx, args),
         Command::Pantry(args) => pantry::run(&ctx, args),
+        Command::Stats(args) => stats::run(&ctx, args),
+        Command::Random(args) => random::run(&ctx, args),
+        Command::Bench(args) => bench::run(&ctx, args),
+        Command::Tui(args) => tui::run(&ctx, args),
+        Command::Timer(args) => timer::run(&ctx, args),
+        Command::Sync(args) => sync::run(&ctx, args),
+        Command::Tag(args) => tag::run(&ctx, args),
+        Command::Backup(args) => backup::run(&ctx, args),
+        Command::Restore(args) => restore::run(&ctx, args),
+        Command::Publish(args) => publish::run(&ctx, args),
+        Command::Export(args) => export::run(&ctx, args),
+        Command::Completions(args) => completions::run(&ctx, args),
+        Command::Watch(args) => watch::run(&ctx, args),
+        Command::Notify(args) => notify::run(&ctx, args),
+        Command::Migrate(args) => migrate::run(&ctx, args),
+        Command::Lint(args) => lint::run(&ctx, args),
+        Command::Hooks(args) => hooks::run(&ctx, args),
+        Command::Schema(args) => schema::run(&ctx, args),
+        Command::Config(args) => config::run(&ctx, args),
+        Command::Edit(args) => edit::run(&ctx, args),
+        Command::Man(args) => man::run(&ctx, args),
         #[cfg(feature = "self-update")]
         Command::Update(args) => update::run(args),
+        Command::External(args) => plugin::run(&ctx, args),
     }
 }
 
-pub struct Context {
-    base_path: Utf8PathBuf,
-}
-
-impl Context {
-    pub fn new(base_path: Utf8PathBuf) -> Self {
-        Self { base_path }
-    }
-
-    pub fn aisle(&self) -> Option<Utf8PathBuf> {
-        let auto = self.base_path.join(LOCAL_CONFIG_DIR).join(AUTO_AISLE);
-
-        tracing::trace!("checking auto aisle file: {auto}");
-
-        auto.is_file().then_some(auto).or_else(|| {
-            let global = global_file_path(AUTO_AISLE).ok()?;
-            tracing::trace!("checking global auto aisle file: {global}");
-            global.is_file().then_some(global)
-        })
-    }
-
-    pub fn pantry(&self) -> Option<Utf8PathBuf> {
-        let auto = self.base_path.join(LOCAL_CONFIG_DIR).join(AUTO_PANTRY);
-
-        tracing::trace!("checking auto pantry file: {auto}");
-
-        auto.is_file().then_some(auto).or_else(|| {
-            let global = global_file_path(AUTO_PANTRY).ok()?;
-            tracing::trace!("checking global auto pantry file: {global}");
-            global.is_file().then_some(global)
-        })
-    }
-
-    pub fn base_path(&self) -> &Utf8PathBuf {
-        &self.base_path
-    }
-}
-
-fn configure_context() -> Result<Context> {
-    let args = CliArgs::parse();
-    let base_path = match args.command {
-        Command::Server(ref server_args) => server_args
-            .get_base_path()
-            .unwrap_or_else(|| Utf8PathBuf::from(".")),
-        Command::ShoppingList(ref shopping_list_args) => shopping_list_args
-            .get_base_path()
-            .unwrap_or_else(|| Utf8PathBuf::from(".")),
-        _ => Utf8PathBuf::from("."),
+/// All colored output goes through anstream, which strips the colors
+/// depending on this choice, NO_COLOR and whether the output is a terminal
+fn configure_color(color: Option<clap::ColorChoice>) {
+    let choice = match color {
+        None | Some(clap::ColorChoice::Auto) => return,
+        Some(clap::ColorChoice::Always) => anstream::ColorChoice::Always,
+        Some(clap::ColorChoice::Never) => anstream::ColorChoice::Never,
     };
-
-    let absolute_base_path = resolve_to_absolute_path(&base_path)?;
-
-    if !absolute_base_path.is_dir() {
-        bail!("Base path is not a directory: {}", absolute_base_path);
-    }
-
-    Ok(Context {
-        base_path: absolute_base_path,
-    })
+    choice.write_global();
 }
 
-fn configure_logging(verbosity: u8) {
-    let env_filter = match verbosity {
-        0 => "warn,cook=warn",   // Default: warnings and errors only
-        1 => "info,cook=info",   // -v: info level
-        2 => "debug,cook=debug", // -vv: debug level
-        _ => "trace,cook=trace", // -vvv or more: trace level
+fn configure_logging(args: &CliArgs) -> Result<()> {
+    let level = if args.quiet {
+        "error" // -q: errors only
+    } else {
+        match args.verbosity {
+            0 => "warn",  // Default: warnings and errors only
+            1 => "info",  // -v: info level
+            2 => "debug", // -vv: debug level
+            _ => "trace", // -vvv or more: trace level
+        }
     };
+    // RUST_LOG takes precedence, for filtering by module
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
 
-    tracing_subscriber::fmt()
+    let builder = tracing_subscriber::fmt()
         .with_env_filter(env_filter)
-        .without_time()
         .with_target(false)
-        .compact()
-        .with_writer(std::io::stderr)
-        .init();
-}
-
-pub fn global_file_path(name: &str) -> Result<Utf8PathBuf> {
-    let dirs = directories::ProjectDirs::from("", "", APP_NAME)
-        .context("Could not determine home directory path")?;
-    let config = Utf8Path::from_path(dirs.config_dir()).expect(UTF8_PATH_PANIC);
-    let path = config.join(name);
-    Ok(path)
+        .compact();
+
+    match &args.log_file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {path}"))?;
+            // Timestamps help telling runs apart in a file
+            builder
+                .with_ansi(false)
+                .with_writer(std::sync::Mutex::new(file))
+                .init();
+        }
+        None => {
+            let ansi =
+                anstream::AutoStream::choice(&std::io::stderr()) != anstream::ColorChoice::Never;
+            builder
+                .without_time()
+                .with_ansi(ansi)
+                .with_writer(std::io::stderr)
+                .init()
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,30 @@
+use anyhow::{Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, CommandFactory};
+
+use crate::{args::CliArgs, Context};
+
+#[derive(Debug, Args)]
+pub struct ManArgs {
+    /// Directory to write the man pages to
+    #[arg(short, long, default_value = "man", value_hint = clap::ValueHint::DirPath)]
+    out_dir: Utf8PathBuf,
+}
+
+pub fn run(_ctx: &Context, args: ManArgs) -> Result<()> {
+    std::fs::create_dir_all(&args.out_dir)
+        .with_context(|| format!("Failed to create {}", args.out_dir))?;
+
+    // One page per command, like cook.1, cook-recipe.1 and cook-recipe-read.1
+    clap_mangen::generate_to(CliArgs::command(), &args.out_dir)
+        .with_context(|| format!("Failed to write man pages to {}", args.out_dir))?;
+
+    let pages = args
+        .out_dir
+        .read_dir_utf8()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().ends_with(".1"))
+        .count();
+    println!("✓ Wrote {pages} man pages to {}", args.out_dir);
+    Ok(())
+}
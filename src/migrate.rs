@@ -0,0 +1,115 @@
+use anyhow::{Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use cooklang_find::RecipeTree;
+use similar::TextDiff;
+use std::io::Write;
+use yansi::Paint;
+
+use crate::{
+    util::{migrate::migrate_recipe, walk::build_tree},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    /// Rewrite the recipes instead of showing what would change
+    #[arg(short, long)]
+    write: bool,
+
+    /// Directory of the recipe collection
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+}
+
+pub fn run(ctx: &Context, args: MigrateArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let tree = build_tree(base_path)?;
+    let mut files = Vec::new();
+    collect(&tree, &mut files);
+
+    let mut out = anstream::stdout().lock();
+    let mut migrated = 0;
+    let mut failed = 0;
+    for path in &files {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let new_content = match migrate_recipe(&content) {
+            Ok(Some(new_content)) => new_content,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("Skipping {path}: {e:#}");
+                failed += 1;
+                continue;
+            }
+        };
+        migrated += 1;
+
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        if args.write {
+            std::fs::write(path, new_content).with_context(|| format!("Failed to write {path}"))?;
+            writeln!(out, "Migrated {relative}")?;
+        } else {
+            print_diff(&mut out, relative, &content, &new_content)?;
+        }
+    }
+
+    if migrated == 0 {
+        writeln!(out, "✓ All {} recipes are up to date", files.len())?;
+    } else if args.write {
+        writeln!(out, "✓ Migrated {migrated} of {} recipes", files.len())?;
+    } else {
+        writeln!(
+            out,
+            "{migrated} of {} recipes use old style metadata. Run with --write to migrate them.",
+            files.len()
+        )?;
+    }
+    if failed > 0 {
+        writeln!(
+            out,
+            "{failed} recipes could not be migrated, see the warnings above"
+        )?;
+    }
+    Ok(())
+}
+
+fn collect(tree: &RecipeTree, files: &mut Vec<Utf8PathBuf>) {
+    if let Some(path) = tree.recipe.as_ref().and_then(|entry| entry.path()) {
+        files.push(path.to_path_buf());
+    }
+    for child in tree.children.values() {
+        collect(child, files);
+    }
+}
+
+pub(crate) fn print_diff(
+    out: &mut impl Write,
+    path: &Utf8Path,
+    old: &str,
+    new: &str,
+) -> Result<()> {
+    let diff = TextDiff::from_lines(old, new);
+    let diff = diff
+        .unified_diff()
+        .header(&format!("a/{path}"), &format!("b/{path}"))
+        .to_string();
+    for (i, line) in diff.lines().enumerate() {
+        // The first two lines are the file header
+        if i < 2 {
+            writeln!(out, "{}", line.bold())?;
+        } else if line.starts_with('+') {
+            writeln!(out, "{}", line.green())?;
+        } else if line.starts_with('-') {
+            writeln!(out, "{}", line.red())?;
+        } else if line.starts_with("@@") {
+            writeln!(out, "{}", line.cyan())?;
+        } else {
+            writeln!(out, "{line}")?;
+        }
+    }
+    writeln!(out)?;
+    Ok(())
+}
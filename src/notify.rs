@@ -0,0 +1,59 @@
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use clap::Args;
+
+use crate::{
+    util::digest::{self, Digest},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct NotifyArgs {
+    /// Send the digest by email, through the SMTP server in `[email]`
+    #[arg(long)]
+    email: bool,
+
+    /// Recipient, instead of email.to from cook.toml
+    ///
+    /// Can be given more than once.
+    #[arg(long, value_name = "ADDRESS")]
+    to: Vec<String>,
+
+    /// Number of days the digest covers
+    #[arg(short, long, default_value_t = 7)]
+    days: u64,
+
+    /// First day of the digest, like 2024-03-18 (default: today)
+    #[arg(long, value_name = "DATE")]
+    from: Option<NaiveDate>,
+
+    /// Print the digest instead of sending it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub fn run(ctx: &Context, args: NotifyArgs) -> Result<()> {
+    if !args.email && !args.dry_run {
+        bail!("Choose where to send the digest, like --email");
+    }
+    let start = args
+        .from
+        .unwrap_or_else(|| chrono::Local::now().date_naive());
+    let digest = Digest::build(ctx, start, args.days)?;
+
+    if args.dry_run {
+        println!("Subject: {}\n", digest.subject());
+        print!("{}", digest.text());
+        return Ok(());
+    }
+
+    let config = &ctx.config().email;
+    let to = if args.to.is_empty() {
+        config.to.clone().unwrap_or_default()
+    } else {
+        args.to
+    };
+    digest::send(config, &to, &digest)?;
+    println!("Sent the meal plan to {}", to.join(", "));
+    Ok(())
+}
@@ -1,12 +1,25 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use camino::Utf8PathBuf;
 use chrono::prelude::*;
 use clap::{Args, Subcommand, ValueEnum};
-use cooklang::pantry::PantryItem;
-use cooklang_find::build_tree;
-use serde::Serialize;
+use cooklang::pantry::{ItemWithAttributes, PantryConf, PantryItem};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-
-use crate::{util::parse_recipe_from_entry, Context as AppContext};
+use std::io::{Read, Write};
+
+use crate::{
+    util::{
+        ai,
+        pantry::{
+            expiring_items, expiry_threshold, find_item_mut, item_attributes, item_from_attributes,
+            load_pantry, parse_date, parse_within, remove_item, save_pantry, Amount, ExpiringItem,
+        },
+        parse_recipe_from_entry,
+        walk::build_tree,
+        PARSER,
+    },
+    Context as AppContext,
+};
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum OutputFormat {
@@ -45,6 +58,43 @@ pub enum PantryCommand {
     /// List recipes that can be made with items currently in pantry
     #[command(alias = "r")]
     Recipes(RecipesArgs),
+
+    /// Add an item to the pantry, or increase the quantity of an existing one
+    ///
+    /// Quantities of existing items are summed, converting units when
+    /// possible (e.g. adding 500g to 1kg of flour gives 1.5kg).
+    ///
+    /// Examples:
+    ///   cook pantry add flour 2kg
+    ///   cook pantry add milk 1l --section fridge --expire 2025-10-01
+    #[command(alias = "a")]
+    Add(AddArgs),
+
+    /// List all items in the pantry
+    #[command(alias = "ls")]
+    List(ListArgs),
+
+    /// Use up an item, subtracting the given quantity
+    ///
+    /// Without a quantity the item is removed from the pantry.
+    ///
+    /// Examples:
+    ///   cook pantry consume flour 500g
+    ///   cook pantry consume eggs 2
+    ///   cook pantry consume yogurt
+    #[command(alias = "c")]
+    Consume(ConsumeArgs),
+
+    /// Add the items on a grocery receipt to the pantry using AI
+    ///
+    /// Accepts a photo of the receipt (jpg, png, gif, webp) or its text.
+    /// The extracted items are shown for confirmation before anything is
+    /// written. Requires CLAUDE_API_KEY to be set.
+    ///
+    /// Examples:
+    ///   cook pantry import-receipt receipt.jpg
+    ///   pdftotext receipt.pdf - | cook pantry import-receipt -
+    ImportReceipt(ImportReceiptArgs),
 }
 
 #[derive(Debug, Args)]
@@ -56,9 +106,16 @@ pub struct DepletedArgs {
 
 #[derive(Debug, Args)]
 pub struct ExpiringArgs {
-    /// Number of days to look ahead for expiring items (default: 7)
-    #[arg(short = 'd', long, default_value = "7")]
-    pub days: u32,
+    /// How far ahead to look for expiring items, e.g. 7d, 2w or 10 (days)
+    #[arg(
+        short = 'd',
+        long = "within",
+        visible_alias = "days",
+        value_name = "DURATION",
+        default_value = "7d",
+        value_parser = parse_within
+    )]
+    pub within: u32,
 
     /// Include items without expiry dates
     #[arg(long)]
@@ -76,6 +133,74 @@ pub struct RecipesArgs {
     pub threshold: u8,
 }
 
+#[derive(Debug, Args)]
+pub struct AddArgs {
+    /// Name of the item
+    pub name: String,
+
+    /// Quantity to add (e.g. 2kg, 500%ml, 12)
+    pub quantity: Option<String>,
+
+    /// Section to add new items to
+    #[arg(short, long, default_value = "pantry")]
+    pub section: String,
+
+    /// Purchase date (e.g. 2025-09-10)
+    #[arg(long)]
+    pub bought: Option<String>,
+
+    /// Expiry date (e.g. 2025-09-25)
+    #[arg(long)]
+    pub expire: Option<String>,
+
+    /// Low stock threshold (e.g. 500g)
+    #[arg(long)]
+    pub low: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// Only show items from this section
+    #[arg(short, long)]
+    pub section: Option<String>,
+
+    /// Only show items expiring within DURATION (7d if left out) or already
+    /// expired, e.g. --expiring=2w
+    #[arg(
+        long,
+        value_name = "DURATION",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "7d",
+        value_parser = parse_within
+    )]
+    pub expiring: Option<u32>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConsumeArgs {
+    /// Name of the item
+    pub name: String,
+
+    /// Quantity to subtract (e.g. 500g, 2). Removes the item if omitted
+    pub quantity: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportReceiptArgs {
+    /// Receipt photo or text file, or - to read text from stdin
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    pub receipt: Utf8PathBuf,
+
+    /// Section to add new items to
+    #[arg(short, long, default_value = "pantry")]
+    pub section: String,
+
+    /// Add all items without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
 // Output structures for JSON/YAML formats
 #[derive(Debug, Serialize)]
 struct DepletedOutput {
@@ -97,12 +222,18 @@ struct ExpiringOutput {
 }
 
 #[derive(Debug, Serialize)]
-struct ExpiringItem {
+struct ListOutput {
+    items: Vec<ListItem>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListItem {
     name: String,
     section: String,
-    expire_date: Option<String>,
-    days_until_expiry: Option<i64>,
-    status: String,
+    quantity: Option<String>,
+    bought: Option<String>,
+    expire: Option<String>,
+    low: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -135,7 +266,378 @@ pub fn run(ctx: &AppContext, args: PantryArgs) -> Result<()> {
         PantryCommand::Depleted(depleted_args) => run_depleted(ctx, depleted_args, format),
         PantryCommand::Expiring(expiring_args) => run_expiring(ctx, expiring_args, format),
         PantryCommand::Recipes(recipes_args) => run_recipes(ctx, recipes_args, format),
+        PantryCommand::Add(add_args) => run_add(ctx, add_args),
+        PantryCommand::List(list_args) => run_list(ctx, list_args, format),
+        PantryCommand::Consume(consume_args) => run_consume(ctx, consume_args),
+        PantryCommand::ImportReceipt(import_args) => run_import_receipt(ctx, import_args),
+    }
+}
+
+/// Path of the pantry file to modify, defaulting to `./config/pantry.conf`
+/// when none exists yet
+fn pantry_file(ctx: &AppContext) -> Utf8PathBuf {
+    ctx.pantry()
+        .unwrap_or_else(|| ctx.base_path().join("config").join("pantry.conf"))
+}
+
+fn run_add(ctx: &AppContext, args: AddArgs) -> Result<()> {
+    let pantry_path = pantry_file(ctx);
+    let mut pantry_conf = if pantry_path.is_file() {
+        load_pantry(&pantry_path)?
+    } else {
+        PantryConf::default()
+    };
+
+    let message = merge_item(&mut pantry_conf, args)?;
+
+    save_pantry(&pantry_path, &pantry_conf)?;
+    println!("{message}");
+    Ok(())
+}
+
+/// Add an item to the pantry, summing quantities if it already exists
+///
+/// Returns a message describing the change.
+fn merge_item(pantry_conf: &mut PantryConf, args: AddArgs) -> Result<String> {
+    let amount = args
+        .quantity
+        .as_deref()
+        .map(|q| Amount::parse(q).with_context(|| format!("Invalid quantity: {q}")))
+        .transpose()?;
+
+    let message = if let Some((section, item)) = find_item_mut(pantry_conf, &args.name) {
+        let mut attrs = item_attributes(item);
+
+        if let Some(amount) = &amount {
+            let current = attrs.quantity.as_deref().and_then(Amount::parse);
+            let new_amount = match current {
+                Some(current) => current
+                    .try_add(amount, &attrs.name, PARSER.converter())
+                    .with_context(|| {
+                        format!(
+                            "Cannot add {amount} to {current} of {}: incompatible units",
+                            attrs.name
+                        )
+                    })?,
+                None => amount.clone(),
+            };
+            attrs.quantity = Some(new_amount.to_pantry_string());
+        }
+        attrs.bought = args.bought.or(attrs.bought);
+        attrs.expire = args.expire.or(attrs.expire);
+        attrs.low = args.low.map(|l| normalize_quantity(&l)).or(attrs.low);
+
+        let message = format!(
+            "Updated {} in {section}{}",
+            attrs.name,
+            attrs
+                .quantity
+                .as_deref()
+                .map(|q| format!(" (now {q})"))
+                .unwrap_or_default()
+        );
+        *item = item_from_attributes(attrs);
+        message
+    } else {
+        let attrs = ItemWithAttributes {
+            name: args.name.clone(),
+            quantity: amount.map(|a| a.to_pantry_string()),
+            bought: args.bought,
+            expire: args.expire,
+            low: args.low.map(|l| normalize_quantity(&l)),
+        };
+        pantry_conf
+            .sections
+            .entry(args.section.clone())
+            .or_default()
+            .push(item_from_attributes(attrs));
+        format!("Added {} to {}", args.name, args.section)
+    };
+
+    pantry_conf.rebuild_index();
+    Ok(message)
+}
+
+fn run_consume(ctx: &AppContext, args: ConsumeArgs) -> Result<()> {
+    let pantry_path = ctx
+        .pantry()
+        .ok_or_else(|| anyhow::anyhow!("No pantry configuration found"))?;
+    let mut pantry_conf = load_pantry(&pantry_path)?;
+
+    let message = match &args.quantity {
+        None => {
+            if !remove_item(&mut pantry_conf, &args.name) {
+                bail!("Item not found in pantry: {}", args.name);
+            }
+            format!("Removed {} from pantry", args.name)
+        }
+        Some(quantity) => {
+            let amount =
+                Amount::parse(quantity).with_context(|| format!("Invalid quantity: {quantity}"))?;
+            let (_, item) = find_item_mut(&mut pantry_conf, &args.name)
+                .with_context(|| format!("Item not found in pantry: {}", args.name))?;
+            let mut attrs = item_attributes(item);
+
+            let current = attrs
+                .quantity
+                .as_deref()
+                .and_then(Amount::parse)
+                .with_context(|| {
+                    format!("{} has no numeric quantity to consume from", attrs.name)
+                })?;
+            let remaining = current
+                .try_sub(&amount, &attrs.name, PARSER.converter())
+                .with_context(|| {
+                    format!(
+                        "Cannot subtract {amount} from {current} of {}: incompatible units",
+                        attrs.name
+                    )
+                })?;
+
+            attrs.quantity = Some(remaining.to_pantry_string());
+            let message = format!("Consumed {amount} of {} ({remaining} left)", attrs.name);
+            *item = item_from_attributes(attrs);
+            message
+        }
+    };
+
+    pantry_conf.rebuild_index();
+    save_pantry(&pantry_path, &pantry_conf)?;
+    println!("{message}");
+    Ok(())
+}
+
+fn run_list(ctx: &AppContext, args: ListArgs, format: OutputFormat) -> Result<()> {
+    let pantry_path = ctx
+        .pantry()
+        .ok_or_else(|| anyhow::anyhow!("No pantry configuration found"))?;
+    let pantry_conf = load_pantry(&pantry_path)?;
+
+    // Too far ahead for a date means every item with a date qualifies
+    let threshold_date = args
+        .expiring
+        .map(|days| expiry_threshold(Local::now().date_naive(), days).unwrap_or(NaiveDate::MAX));
+
+    let mut list = Vec::new();
+    for (section, items) in &pantry_conf.sections {
+        if let Some(filter) = &args.section {
+            if !section.eq_ignore_ascii_case(filter) {
+                continue;
+            }
+        }
+
+        for item in items {
+            if let Some(threshold_date) = threshold_date {
+                let expiring = item
+                    .expire()
+                    .and_then(parse_date)
+                    .is_some_and(|date| date <= threshold_date);
+                if !expiring {
+                    continue;
+                }
+            }
+
+            list.push(ListItem {
+                name: item.name().to_string(),
+                section: section.clone(),
+                quantity: item.quantity().map(|q| q.to_string()),
+                bought: item.bought().map(|b| b.to_string()),
+                expire: item.expire().map(|e| e.to_string()),
+                low: item.low().map(|l| l.to_string()),
+            });
+        }
+    }
+
+    match format {
+        OutputFormat::Human => {
+            if list.is_empty() {
+                println!("No pantry items found!");
+            }
+
+            let mut current_section = String::new();
+            for item in &list {
+                if item.section != current_section {
+                    if !current_section.is_empty() {
+                        println!();
+                    }
+                    println!("{}:", item.section.to_uppercase());
+                    current_section = item.section.clone();
+                }
+                print!("  • {}", item.name);
+                if let Some(ref qty) = item.quantity {
+                    print!(" ({qty})");
+                }
+                if let Some(ref expire) = item.expire {
+                    print!(" [expires {expire}]");
+                }
+                println!();
+            }
+        }
+        OutputFormat::Json => {
+            let output = ListOutput { items: list };
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        OutputFormat::Yaml => {
+            let output = ListOutput { items: list };
+            println!("{}", serde_yaml::to_string(&output)?);
+        }
     }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct ReceiptItem {
+    name: String,
+    quantity: Option<String>,
+}
+
+const RECEIPT_PROMPT: &str = "Extract the grocery items from this receipt.\n\
+    Return only a JSON array, no other text, where each element is \
+    {\"name\": string, \"quantity\": string or null}.\n\
+    Use short, lowercase, singular ingredient names as they would appear in a recipe \
+    (e.g. \"ORG BNLS CHKN BRST\" becomes \"chicken breast\").\n\
+    Write quantities as a number followed by a unit without a space, e.g. \"500g\", \"1l\" \
+    or \"6\" for counts; multiply by the number of packs bought. Use null if unknown.\n\
+    Skip non-food items, bags, deposits, discounts and totals.";
+
+fn run_import_receipt(ctx: &AppContext, args: ImportReceiptArgs) -> Result<()> {
+    let image = match args
+        .receipt
+        .extension()
+        .map(|e| e.to_lowercase())
+        .as_deref()
+    {
+        Some("jpg" | "jpeg" | "png" | "gif" | "webp") => Some(ai::Image {
+            media_type: mime_guess::from_path(&args.receipt)
+                .first_or_octet_stream()
+                .to_string(),
+            data: std::fs::read(&args.receipt)
+                .with_context(|| format!("Failed to read {}", args.receipt))?,
+        }),
+        _ => None,
+    };
+
+    let prompt = match &image {
+        Some(_) => RECEIPT_PROMPT.to_string(),
+        None => {
+            let text = if args.receipt == "-" {
+                let mut buf = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut buf)
+                    .context("Failed to read stdin")?;
+                buf
+            } else {
+                std::fs::read_to_string(&args.receipt)
+                    .with_context(|| format!("Failed to read {}", args.receipt))?
+            };
+            format!("{RECEIPT_PROMPT}\n\nReceipt:\n{text}")
+        }
+    };
+
+    eprintln!("Reading receipt...");
+    let reply = tokio::runtime::Runtime::new()?.block_on(ai::complete(
+        &ctx.config().ai,
+        &prompt,
+        image.as_ref(),
+        2000,
+    ))?;
+    let items: Vec<ReceiptItem> = serde_json::from_str(ai::json_block(&reply))
+        .with_context(|| format!("Could not understand the AI reply:\n{reply}"))?;
+
+    if items.is_empty() {
+        println!("No grocery items found on the receipt");
+        return Ok(());
+    }
+
+    println!("Found {} items:", items.len());
+    for (i, item) in items.iter().enumerate() {
+        match &item.quantity {
+            Some(qty) => println!("  {:>2}. {} ({qty})", i + 1, item.name),
+            None => println!("  {:>2}. {}", i + 1, item.name),
+        }
+    }
+
+    let skipped = if args.yes {
+        HashSet::new()
+    } else {
+        match confirm_receipt_items(items.len())? {
+            Some(skipped) => skipped,
+            None => {
+                println!("Nothing was added");
+                return Ok(());
+            }
+        }
+    };
+
+    let pantry_path = pantry_file(ctx);
+    let mut pantry_conf = if pantry_path.is_file() {
+        load_pantry(&pantry_path)?
+    } else {
+        PantryConf::default()
+    };
+
+    let today = Local::now().date_naive().format("%Y-%m-%d").to_string();
+    for (i, item) in items.into_iter().enumerate() {
+        if skipped.contains(&(i + 1)) {
+            continue;
+        }
+
+        let name = item.name.clone();
+        let add = AddArgs {
+            name: item.name,
+            quantity: item.quantity.filter(|q| Amount::parse(q).is_some()),
+            section: args.section.clone(),
+            bought: Some(today.clone()),
+            expire: None,
+            low: None,
+        };
+        match merge_item(&mut pantry_conf, add) {
+            Ok(message) => println!("{message}"),
+            Err(e) => eprintln!("Skipping {name}: {e:#}"),
+        }
+    }
+
+    save_pantry(&pantry_path, &pantry_conf)?;
+    Ok(())
+}
+
+/// Ask which receipt items to add
+///
+/// Returns the (1-based) numbers of items to skip, or `None` to cancel.
+fn confirm_receipt_items(count: usize) -> Result<Option<HashSet<usize>>> {
+    loop {
+        print!("Add these items to the pantry? [y]es / [n]o / numbers to skip (e.g. 2,5): ");
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Some(HashSet::new())),
+            "" | "n" | "no" => return Ok(None),
+            numbers => {
+                let skipped: Option<HashSet<usize>> = numbers
+                    .split([',', ' '])
+                    .filter(|n| !n.is_empty())
+                    .map(|n| n.parse().ok().filter(|n| (1..=count).contains(n)))
+                    .collect();
+                match skipped {
+                    Some(skipped) => return Ok(Some(skipped)),
+                    None => println!("Please answer y, n or item numbers between 1 and {count}"),
+                }
+            }
+        }
+    }
+}
+
+/// Normalize a command line quantity (`500g`) to pantry notation (`500%g`)
+fn normalize_quantity(quantity: &str) -> String {
+    Amount::parse(quantity)
+        .map(|a| a.to_pantry_string())
+        .unwrap_or_else(|| quantity.to_string())
 }
 
 fn run_depleted(ctx: &AppContext, args: DepletedArgs, format: OutputFormat) -> Result<()> {
@@ -245,54 +747,16 @@ fn run_expiring(ctx: &AppContext, args: ExpiringArgs, format: OutputFormat) -> R
         )
     })?;
 
-    let today = Local::now().date_naive();
-    let threshold_date = today + chrono::Duration::days(args.days as i64);
-
-    let mut expiring_list = Vec::new();
-
-    for (section, items) in &pantry_conf.sections {
-        for item in items {
-            let expire_date = item.expire().and_then(parse_date);
-
-            if let Some(date) = expire_date {
-                if date <= threshold_date {
-                    let days_until = (date - today).num_days();
-                    let status = if days_until < 0 {
-                        format!("EXPIRED {} days ago", -days_until)
-                    } else if days_until == 0 {
-                        "EXPIRES TODAY".to_string()
-                    } else if days_until == 1 {
-                        "expires tomorrow".to_string()
-                    } else {
-                        format!("expires in {days_until} days")
-                    };
-
-                    expiring_list.push(ExpiringItem {
-                        name: item.name().to_string(),
-                        section: section.clone(),
-                        expire_date: Some(date.format("%Y-%m-%d").to_string()),
-                        days_until_expiry: Some(days_until),
-                        status,
-                    });
-                }
-            } else if args.include_unknown {
-                expiring_list.push(ExpiringItem {
-                    name: item.name().to_string(),
-                    section: section.clone(),
-                    expire_date: None,
-                    days_until_expiry: None,
-                    status: "No expiry date".to_string(),
-                });
-            }
-        }
-    }
-
-    // Sort by days until expiry
-    expiring_list.sort_by_key(|item| item.days_until_expiry.unwrap_or(i64::MAX));
+    let expiring_list = expiring_items(
+        pantry_conf,
+        Local::now().date_naive(),
+        args.within,
+        args.include_unknown,
+    );
 
     match format {
         OutputFormat::Human => {
-            println!("Items Expiring Within {} Days:", args.days);
+            println!("Items Expiring Within {} Days:", args.within);
             println!("================================");
 
             let with_dates: Vec<_> = expiring_list
@@ -541,18 +1005,3 @@ fn is_low_quantity(quantity: &str) -> bool {
         false
     }
 }
-
-fn parse_date(date_str: &str) -> Option<NaiveDate> {
-    // Try multiple date formats
-    let formats = [
-        "%Y-%m-%d", "%d.%m.%Y", "%d/%m/%Y", "%m/%d/%Y", "%Y.%m.%d", "%d-%m-%Y",
-    ];
-
-    for format in &formats {
-        if let Ok(date) = NaiveDate::parse_from_str(date_str, format) {
-            return Some(date);
-        }
-    }
-
-    None
-}
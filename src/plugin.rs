@@ -0,0 +1,60 @@
+use anyhow::{bail, Context as _, Result};
+use std::process::Command;
+
+use crate::Context;
+
+/// Prefix of plugin executables, `cook foo` runs `cook-foo`
+const PLUGIN_PREFIX: &str = "cook-";
+
+/// Run `cook-<name>` from PATH with the remaining arguments, like git does
+/// for its subcommands
+///
+/// The plugin gets the resolved collection and configuration through
+/// environment variables, so it doesn't have to repeat cook's lookup:
+///
+/// - `COOK_BASE_PATH`: the recipe collection
+/// - `COOK_CONFIG_DIR`: the global configuration directory
+/// - `COOK_AISLE`, `COOK_PANTRY`, `COOK_NUTRITION`: the configuration files
+///   in use, only set when found
+/// - `COOK_VERSION`: version of cook
+/// - `COOK`: path of the cook executable, to call back into it
+///
+/// Exits with the plugin's exit code.
+pub fn run(ctx: &Context, args: Vec<String>) -> Result<()> {
+    let Some((name, args)) = args.split_first() else {
+        bail!("Missing plugin name");
+    };
+    let program = format!("{PLUGIN_PREFIX}{name}");
+
+    let mut command = Command::new(&program);
+    command
+        .args(args)
+        .env("COOK_BASE_PATH", ctx.base_path())
+        .env("COOK_VERSION", env!("CARGO_PKG_VERSION"));
+    if let Some(dir) = ctx.global_config_dir() {
+        command.env("COOK_CONFIG_DIR", dir);
+    }
+    for (var, path) in [
+        ("COOK_AISLE", ctx.aisle()),
+        ("COOK_PANTRY", ctx.pantry()),
+        ("COOK_NUTRITION", ctx.nutrition()),
+    ] {
+        if let Some(path) = path {
+            command.env(var, path);
+        }
+    }
+    if let Ok(exe) = std::env::current_exe() {
+        command.env("COOK", exe);
+    }
+
+    let status = match command.status() {
+        Ok(status) => status,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => bail!(
+            "'{name}' is not a cook command, and no {program} plugin was found on PATH\n\
+             Run `cook help` to see the available commands"
+        ),
+        Err(e) => return Err(e).with_context(|| format!("Failed to run {program}")),
+    };
+
+    std::process::exit(status.code().unwrap_or(1));
+}
@@ -0,0 +1,213 @@
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use std::hash::{Hash, Hasher};
+use std::process::Command;
+
+use crate::{
+    global_cache_dir,
+    util::{
+        git::{git, git_ok},
+        resolve_to_absolute_path,
+    },
+    Context,
+};
+
+pub mod site;
+
+/// Environment variable with the default publish target
+const TARGET_ENV: &str = "COOK_PUBLISH_TO";
+
+#[derive(Debug, Args)]
+pub struct PublishArgs {
+    /// Where to publish the site
+    ///
+    /// One of:
+    ///   gh-pages[:BRANCH]       push to a branch (default gh-pages) of the
+    ///                           collection's origin remote, for GitHub Pages
+    ///   s3://BUCKET[/PREFIX]    sync to an S3 bucket with the aws CLI
+    ///   [USER@]HOST:PATH        copy over SSH with rsync
+    ///   PATH                    copy to a local directory with rsync
+    ///
+    /// Defaults to the COOK_PUBLISH_TO environment variable (a .env file
+    /// works too).
+    #[arg(short, long, value_name = "TARGET", verbatim_doc_comment)]
+    to: Option<String>,
+
+    /// Directory to export the site into
+    ///
+    /// Defaults to a directory in the user cache. Without a target, the
+    /// site is only exported here.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    out_dir: Option<Utf8PathBuf>,
+
+    /// Directory of the recipe collection
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// Title shown on every page of the site
+    #[arg(long, default_value = "Cookbook")]
+    title: String,
+}
+
+#[derive(Debug)]
+enum Target {
+    GitHubPages { branch: String },
+    S3(String),
+    Rsync(String),
+}
+
+impl Target {
+    fn parse(s: &str) -> Self {
+        if s == "gh-pages" {
+            Target::GitHubPages {
+                branch: "gh-pages".to_string(),
+            }
+        } else if let Some(branch) = s.strip_prefix("gh-pages:") {
+            Target::GitHubPages {
+                branch: branch.to_string(),
+            }
+        } else if s.starts_with("s3://") {
+            Target::S3(s.to_string())
+        } else {
+            Target::Rsync(s.to_string())
+        }
+    }
+}
+
+pub fn run(ctx: &Context, args: PublishArgs) -> Result<()> {
+    let base_path = resolve_to_absolute_path(args.base_path.as_ref().unwrap_or(ctx.base_path()))?;
+    let target = args
+        .to
+        .or_else(|| std::env::var(TARGET_ENV).ok())
+        .filter(|t| !t.is_empty())
+        .map(|t| Target::parse(&t));
+
+    let out_dir = match (args.out_dir, &target) {
+        (Some(dir), _) => dir,
+        (None, Some(_)) => default_out_dir(&base_path)?,
+        (None, None) => bail!(
+            "Nothing to publish to\n\
+             Pass --to (or set {TARGET_ENV}) to publish, or --out-dir to only export the site"
+        ),
+    };
+
+    let summary = site::build(&base_path, &out_dir, &args.title)?;
+    println!(
+        "Exported {} recipes and {} images to {out_dir}: {} files changed, {} removed",
+        summary.pages, summary.images, summary.written, summary.removed
+    );
+
+    let Some(target) = target else {
+        return Ok(());
+    };
+    match &target {
+        Target::GitHubPages { branch } => publish_github_pages(&base_path, &out_dir, branch)?,
+        Target::S3(url) => publish_s3(&out_dir, url)?,
+        Target::Rsync(dest) => publish_rsync(&out_dir, dest)?,
+    }
+
+    println!("✓ Published");
+    Ok(())
+}
+
+/// Export directory in the user cache, one per collection
+pub fn default_out_dir(base_path: &Utf8Path) -> Result<Utf8PathBuf> {
+    let cache =
+        global_cache_dir().context("Could not determine the cache directory, pass --out-dir")?;
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    base_path.hash(&mut hasher);
+    let name = format!(
+        "{}-{:016x}",
+        base_path.file_name().unwrap_or("collection"),
+        hasher.finish()
+    );
+    Ok(cache.join("publish").join(name))
+}
+
+/// Commit the site to a branch of the collection's origin remote
+///
+/// The export directory keeps its own repository, so only changed files
+/// are committed and pushed.
+fn publish_github_pages(base_path: &Utf8Path, site: &Utf8Path, branch: &str) -> Result<()> {
+    let url = git_ok(base_path, &["remote", "get-url", "origin"])
+        .context("Publishing to GitHub Pages needs the collection to be a git repository with an origin remote")?;
+    let url = url.trim();
+    println!("Publishing to the {branch} branch of {url}...");
+
+    if !site.join(".git").exists() {
+        git_ok(site, &["init", "--quiet"])?;
+        git_ok(site, &["remote", "add", "origin", url])?;
+    } else {
+        git_ok(site, &["remote", "set-url", "origin", url])?;
+    }
+
+    // Build on top of what's published; the branch may not exist yet
+    if git(site, &["fetch", "--quiet", "--depth=1", "origin", branch])?
+        .status
+        .success()
+    {
+        git_ok(site, &["reset", "--quiet", "--soft", "FETCH_HEAD"])?;
+    }
+
+    git_ok(site, &["add", "--all"])?;
+    if git_ok(site, &["status", "--porcelain"])?.trim().is_empty() {
+        println!("The published site is already up to date");
+        return Ok(());
+    }
+    git_ok(site, &["commit", "--quiet", "-m", "Publish cookbook"])?;
+    git_ok(
+        site,
+        &[
+            "push",
+            "--quiet",
+            "origin",
+            &format!("HEAD:refs/heads/{branch}"),
+        ],
+    )?;
+    Ok(())
+}
+
+fn publish_s3(site: &Utf8Path, url: &str) -> Result<()> {
+    println!("Publishing to {url}...");
+    // Unchanged files keep their modification time, so sync skips them
+    run_tool(
+        Command::new("aws")
+            .args(["s3", "sync", "--delete", "--exclude", ".git/*"])
+            .args(["--exclude", site::MANIFEST])
+            .arg(site)
+            .arg(url),
+        "aws",
+    )
+}
+
+fn publish_rsync(site: &Utf8Path, dest: &str) -> Result<()> {
+    println!("Publishing to {dest}...");
+    run_tool(
+        Command::new("rsync")
+            .args([
+                "--recursive",
+                "--times",
+                "--compress",
+                "--delete",
+                "--exclude=.git",
+            ])
+            .arg(format!("--exclude={}", site::MANIFEST))
+            .arg(format!("{site}/"))
+            .arg(dest),
+        "rsync",
+    )
+}
+
+fn run_tool(command: &mut Command, name: &str) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to run {name}, is it installed?"))?;
+    if !status.success() {
+        bail!("{name} exited with {status}");
+    }
+    Ok(())
+}
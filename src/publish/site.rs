@@ -0,0 +1,530 @@
+//! Static HTML export of a recipe collection
+//!
+//! Pages only use relative links, so the site works from any URL prefix
+//! (like a GitHub Pages project site) and straight from disk.
+
+use anyhow::{bail, Context as _, Result};
+use askama::Template;
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{
+    model::{Ingredient, Item},
+    quantity::Quantity,
+    Content, Recipe,
+};
+use cooklang_find::{RecipeEntry, RecipeTree};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::sync::Arc;
+
+use crate::{
+    server::templates::StepItem,
+    util::{
+        canonical_path,
+        format::{format_quantity, format_unit},
+        parse_entries,
+        source::Source,
+        tree_entries,
+        walk::build_tree,
+    },
+};
+
+/// Lists the files an export wrote, in the output directory, so the next
+/// one only removes those
+pub const MANIFEST: &str = ".cook-site";
+
+#[derive(Debug, Default)]
+pub struct SiteSummary {
+    pub pages: usize,
+    pub images: usize,
+    /// Files that were new or changed
+    pub written: usize,
+    /// Stale files that were removed
+    pub removed: usize,
+}
+
+struct Link {
+    name: String,
+    href: String,
+}
+
+struct RecipeCard {
+    name: String,
+    href: String,
+    description: Option<String>,
+    image: Option<String>,
+    tags: Vec<String>,
+}
+
+struct IngredientLine {
+    name: String,
+    amount: Option<String>,
+    href: Option<String>,
+}
+
+struct Section {
+    name: Option<String>,
+    notes: Vec<String>,
+    first_step: usize,
+    steps: Vec<Vec<StepItem>>,
+}
+
+#[derive(Template)]
+#[template(path = "static/index.html")]
+struct IndexPage<'a> {
+    site_title: &'a str,
+    version: &'a str,
+    root: String,
+    breadcrumbs: Vec<Link>,
+    title: String,
+    dirs: Vec<Link>,
+    recipes: Vec<RecipeCard>,
+}
+
+#[derive(Template)]
+#[template(path = "static/recipe.html")]
+struct RecipePage<'a> {
+    site_title: &'a str,
+    version: &'a str,
+    root: String,
+    breadcrumbs: Vec<Link>,
+    title: String,
+    image: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    facts: Vec<(String, String)>,
+    ingredients: Vec<IngredientLine>,
+    cookware: Vec<String>,
+    sections: Vec<Section>,
+    attribution: Option<Attribution>,
+}
+
+/// Credit to where the recipe is from, linked when it's a website
+struct Attribution {
+    citation: String,
+    url: Option<String>,
+}
+
+struct SiteBuilder<'a> {
+    base_path: &'a Utf8Path,
+    out: &'a Utf8Path,
+    site_title: &'a str,
+    /// Every recipe, parsed up front in parallel
+    parsed: HashMap<Utf8PathBuf, Result<Arc<Recipe>>>,
+    written: BTreeSet<Utf8PathBuf>,
+    summary: SiteSummary,
+    /// Told of each recipe page, stops the export when it fails
+    progress: &'a dyn Fn(usize, usize) -> Result<()>,
+    total: usize,
+}
+
+/// Render the collection at `base_path` into `out`
+///
+/// Files whose content didn't change are left untouched, and files the
+/// previous export wrote that are no longer part of the site are removed,
+/// so syncing the directory only transfers what changed. Other files in
+/// `out` are left alone.
+pub fn build(base_path: &Utf8Path, out: &Utf8Path, site_title: &str) -> Result<SiteSummary> {
+    build_with_progress(base_path, out, site_title, &|_, _| Ok(()))
+}
+
+/// Like [`build`], calling `progress` with the recipes rendered and their
+/// total after each one
+///
+/// The export stops with the error `progress` returns.
+pub fn build_with_progress(
+    base_path: &Utf8Path,
+    out: &Utf8Path,
+    site_title: &str,
+    progress: &dyn Fn(usize, usize) -> Result<()>,
+) -> Result<SiteSummary> {
+    check_out_dir(base_path, out)?;
+    let tree = build_tree(base_path)?;
+    fs::create_dir_all(out).with_context(|| format!("Failed to create {out}"))?;
+
+    let parsed: HashMap<_, _> = parse_entries(tree_entries(&tree), 1.0)
+        .into_iter()
+        .filter_map(|(entry, recipe)| Some((entry.path()?.clone(), recipe)))
+        .collect();
+    let mut builder = SiteBuilder {
+        base_path,
+        out,
+        site_title,
+        total: parsed.len(),
+        parsed,
+        written: BTreeSet::new(),
+        summary: SiteSummary::default(),
+        progress,
+    };
+    builder.directory(&tree, Utf8Path::new(""))?;
+    // GitHub Pages would otherwise run the site through Jekyll
+    builder.write(Utf8Path::new(".nojekyll"), b"")?;
+    builder.remove_stale()?;
+
+    Ok(builder.summary)
+}
+
+/// Fail when exporting into `out` could overwrite the collection: when it's
+/// the collection's directory or one of its parents
+///
+/// A directory inside the collection is fine, the export only writes HTML
+/// and images.
+pub fn check_out_dir(base_path: &Utf8Path, out: &Utf8Path) -> Result<()> {
+    let base = canonical_path(base_path)?;
+    let out = canonical_path(out)?;
+    if base.starts_with(&out) {
+        bail!("Can't export into {out}, it holds the recipes. Pick another directory");
+    }
+    Ok(())
+}
+
+impl SiteBuilder<'_> {
+    /// Render a directory's index page and everything below it
+    fn directory(&mut self, tree: &RecipeTree, dir: &Utf8Path) -> Result<()> {
+        let root = root_prefix(dir);
+        let mut dirs = Vec::new();
+        let mut recipes = Vec::new();
+
+        for (name, child) in &tree.children {
+            if let Some(entry) = &child.recipe {
+                if let Some(card) = self.recipe(entry, dir)? {
+                    recipes.push(card);
+                }
+            } else {
+                dirs.push(Link {
+                    name: name.clone(),
+                    href: format!("{}/index.html", encode(name)),
+                });
+                self.directory(child, &dir.join(name))?;
+            }
+        }
+
+        let page = IndexPage {
+            site_title: self.site_title,
+            version: env!("CARGO_PKG_VERSION"),
+            breadcrumbs: breadcrumbs(dir, &root),
+            root,
+            title: dir.file_name().unwrap_or(self.site_title).to_string(),
+            dirs,
+            recipes,
+        };
+        self.write(&dir.join("index.html"), page.render()?.as_bytes())
+    }
+
+    /// Render a recipe page, returning its card for the directory index
+    fn recipe(&mut self, entry: &RecipeEntry, dir: &Utf8Path) -> Result<Option<RecipeCard>> {
+        let Some(path) = entry.path() else {
+            return Ok(None);
+        };
+        let recipe = self.parsed.remove(path);
+        (self.progress)(self.total - self.parsed.len(), self.total)?;
+        let recipe = match recipe {
+            Some(Ok(recipe)) => recipe,
+            None => return Ok(None),
+            Some(Err(e)) => {
+                tracing::warn!("Skipping {path}: {e}");
+                return Ok(None);
+            }
+        };
+
+        let name = entry.name().clone().unwrap_or_default();
+        let stem = path.file_stem().unwrap_or(name.as_str());
+        let page_path = dir.join(format!("{stem}.html"));
+        let root = root_prefix(dir);
+
+        let image = match entry.title_image() {
+            Some(image) if image.starts_with("http://") || image.starts_with("https://") => {
+                Some(image.to_string())
+            }
+            Some(image) => self
+                .copy_image(Utf8Path::new(image.as_str()))?
+                .map(|relative| format!("{root}{}", encode(relative.as_str()))),
+            None => None,
+        };
+
+        let description = metadata_str(&recipe, "description");
+        let tags = entry.tags();
+
+        let mut crumbs = breadcrumbs(dir, &root);
+        crumbs.push(Link {
+            name: name.clone(),
+            href: encode(&format!("{stem}.html")),
+        });
+
+        let page = RecipePage {
+            site_title: self.site_title,
+            version: env!("CARGO_PKG_VERSION"),
+            breadcrumbs: crumbs,
+            title: name.clone(),
+            image: image.clone(),
+            description: description.clone(),
+            tags: tags.clone(),
+            facts: facts(&recipe),
+            ingredients: recipe
+                .group_ingredients(crate::util::PARSER.converter())
+                .into_iter()
+                .filter(|g| g.ingredient.modifiers().should_be_listed())
+                .map(|g| IngredientLine {
+                    name: g.ingredient.display_name().to_string(),
+                    amount: (!g.quantity.is_empty()).then(|| g.quantity.to_string()),
+                    href: reference_href(g.ingredient, &root),
+                })
+                .collect(),
+            cookware: recipe
+                .group_cookware(crate::util::PARSER.converter())
+                .into_iter()
+                .filter(|g| g.cookware.modifiers().should_be_listed())
+                .map(|g| g.cookware.display_name().to_string())
+                .collect(),
+            sections: sections(&recipe, &root),
+            attribution: Source::of(&recipe).map(|source| Attribution {
+                citation: source.citation(),
+                url: source.url,
+            }),
+            root,
+        };
+        self.write(&page_path, page.render()?.as_bytes())?;
+        self.summary.pages += 1;
+
+        Ok(Some(RecipeCard {
+            name,
+            href: encode(&format!("{stem}.html")),
+            description,
+            image,
+            tags,
+        }))
+    }
+
+    /// Copy a local image into the site at the same place as in the
+    /// collection, returning its path in the site
+    fn copy_image(&mut self, image: &Utf8Path) -> Result<Option<Utf8PathBuf>> {
+        let image = if image.is_absolute() {
+            image.to_path_buf()
+        } else {
+            self.base_path.join(image)
+        };
+        let Ok(relative) = image.strip_prefix(self.base_path) else {
+            tracing::warn!("Skipping image outside the collection: {image}");
+            return Ok(None);
+        };
+        let Ok(content) = fs::read(&image) else {
+            tracing::warn!("Skipping unreadable image: {image}");
+            return Ok(None);
+        };
+        let relative = relative.to_path_buf();
+        self.write(&relative, &content)?;
+        self.summary.images += 1;
+        Ok(Some(relative))
+    }
+
+    /// Write a file of the site, only touching it if the content changed
+    fn write(&mut self, relative: &Utf8Path, content: &[u8]) -> Result<()> {
+        let path = self.out.join(relative);
+        self.written.insert(path.clone());
+        if fs::read(&path).is_ok_and(|existing| existing == content) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {parent}"))?;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write {path}"))?;
+        self.summary.written += 1;
+        Ok(())
+    }
+
+    /// Remove the files the previous export wrote that weren't written this
+    /// time, and the directories they leave empty, then list this export's
+    /// files for the next one
+    fn remove_stale(&mut self) -> Result<()> {
+        let manifest = self.out.join(MANIFEST);
+        let previous = fs::read_to_string(&manifest).unwrap_or_default();
+        for relative in previous.lines().map(Utf8Path::new) {
+            // Only what an export could have written
+            if relative.as_str().is_empty()
+                || !relative
+                    .components()
+                    .all(|c| matches!(c, camino::Utf8Component::Normal(_)))
+            {
+                continue;
+            }
+            let path = self.out.join(relative);
+            if self.written.contains(&path) || !path.is_file() {
+                continue;
+            }
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {path}"))?;
+            self.summary.removed += 1;
+            let mut dir = path.parent();
+            while let Some(parent) = dir.filter(|dir| *dir != self.out) {
+                // Stops at the first directory that isn't empty
+                if fs::remove_dir(parent).is_err() {
+                    break;
+                }
+                dir = parent.parent();
+            }
+        }
+
+        let listed: String = self
+            .written
+            .iter()
+            .filter_map(|path| path.strip_prefix(self.out).ok())
+            .map(|relative| format!("{relative}\n"))
+            .collect();
+        if listed != previous {
+            fs::write(&manifest, listed).with_context(|| format!("Failed to write {manifest}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// "../" for every level `dir` is below the site root
+fn root_prefix(dir: &Utf8Path) -> String {
+    "../".repeat(dir.components().count())
+}
+
+fn breadcrumbs(dir: &Utf8Path, root: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut href = root.to_string();
+    for component in dir.components() {
+        href.push_str(&encode(component.as_str()));
+        href.push('/');
+        links.push(Link {
+            name: component.to_string(),
+            href: format!("{href}index.html"),
+        });
+    }
+    links
+}
+
+/// Percent-encode the characters that break a relative URL path
+fn encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => encoded.push_str("%20"),
+            '#' => encoded.push_str("%23"),
+            '?' => encoded.push_str("%3F"),
+            '%' => encoded.push_str("%25"),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+fn metadata_str(recipe: &Recipe, key: &str) -> Option<String> {
+    recipe
+        .metadata
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn facts(recipe: &Recipe) -> Vec<(String, String)> {
+    let mut facts = Vec::new();
+    if let Some(servings) = recipe.metadata.servings() {
+        facts.push(("Servings".to_string(), servings.to_string()));
+    }
+    if let Some(time) = recipe.metadata.time(crate::util::PARSER.converter()) {
+        let minutes = time.total() as u64;
+        facts.push((
+            "Time".to_string(),
+            humantime::format_duration(std::time::Duration::from_secs(minutes * 60)).to_string(),
+        ));
+    }
+    for key in ["difficulty", "cuisine", "course", "author"] {
+        if let Some(value) = metadata_str(recipe, key) {
+            let mut label = key.to_string();
+            label[..1].make_ascii_uppercase();
+            facts.push((label, value));
+        }
+    }
+    facts
+}
+
+/// Link to a referenced recipe's page
+fn reference_href(ingredient: &Ingredient, root: &str) -> Option<String> {
+    let reference = ingredient.reference.as_ref()?;
+    let components: Vec<&str> = reference
+        .components
+        .iter()
+        .map(|c| c.as_str())
+        .filter(|c| *c != ".")
+        .chain(std::iter::once(reference.name.as_str()))
+        .collect();
+    Some(format!("{root}{}.html", encode(&components.join("/"))))
+}
+
+fn sections(recipe: &Recipe, root: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut step_number = 1;
+
+    for section in &recipe.sections {
+        let mut notes = Vec::new();
+        let mut steps = Vec::new();
+
+        for content in &section.content {
+            match content {
+                Content::Step(step) => {
+                    let items = step
+                        .items
+                        .iter()
+                        .filter_map(|item| step_item(recipe, item, root))
+                        .collect();
+                    steps.push(items);
+                }
+                Content::Text(text) => {
+                    if text.trim() != "-" {
+                        notes.push(text.trim().to_string());
+                    }
+                }
+            }
+        }
+
+        if steps.is_empty() && notes.is_empty() {
+            continue;
+        }
+        sections.push(Section {
+            name: section.name.clone(),
+            notes,
+            first_step: step_number,
+            steps,
+        });
+        step_number += sections.last().map_or(0, |s| s.steps.len());
+    }
+    sections
+}
+
+fn step_item(recipe: &Recipe, item: &Item, root: &str) -> Option<StepItem> {
+    let quantity_text = |q: &Quantity| {
+        let value = format_quantity(q.value()).unwrap_or_default();
+        match q.unit() {
+            Some(unit) if value.is_empty() => format_unit(unit).to_string(),
+            Some(unit) => format!("{value} {}", format_unit(unit)),
+            None => value,
+        }
+    };
+
+    Some(match item {
+        Item::Text { value } => StepItem::Text(value.to_string()),
+        &Item::Ingredient { index } => {
+            let ingredient = recipe.ingredients.get(index)?;
+            StepItem::Ingredient {
+                name: ingredient.display_name().to_string(),
+                reference_path: reference_href(ingredient, root),
+            }
+        }
+        &Item::Cookware { index } => {
+            StepItem::Cookware(recipe.cookware.get(index)?.display_name().to_string())
+        }
+        &Item::Timer { index } => {
+            let timer = recipe.timers.get(index)?;
+            let text = match (&timer.quantity, &timer.name) {
+                (Some(q), _) => quantity_text(q),
+                (None, Some(name)) => name.clone(),
+                (None, None) => "timer".to_string(),
+            };
+            StepItem::Timer(text)
+        }
+        &Item::InlineQuantity { index } => {
+            StepItem::Quantity(quantity_text(recipe.inline_quantities.get(index)?))
+        }
+    })
+}
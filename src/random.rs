@@ -0,0 +1,70 @@
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+
+use crate::{
+    util::{
+        parse_recipe_from_entry,
+        random::{parse_minutes, pick_random, RandomFilter},
+        write_to_output, PARSER,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct RandomArgs {
+    /// Only pick recipes with this tag (can be repeated)
+    #[arg(short, long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// Only pick recipes that take at most this long
+    ///
+    /// Accepts durations like "45m", "1h" or "1h 30m", or a number of
+    /// minutes. Recipes without a time are skipped.
+    #[arg(short = 'm', long, value_name = "DURATION", value_parser = parse_minutes)]
+    max_time: Option<u32>,
+
+    /// Directory to pick recipes from
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// Only print the path of the picked recipe
+    #[arg(long)]
+    path_only: bool,
+}
+
+pub fn run(ctx: &Context, args: RandomArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let filter = RandomFilter {
+        tags: args.tags,
+        max_time: args.max_time,
+    };
+
+    let Some(entry) = pick_random(base_path, &filter)? else {
+        bail!("No recipes match the given filters");
+    };
+
+    if args.path_only {
+        let path = entry
+            .path()
+            .map(|p| p.strip_prefix(base_path).unwrap_or(p).to_string())
+            .unwrap_or_default();
+        println!("{path}");
+        return Ok(());
+    }
+
+    let recipe = parse_recipe_from_entry(&entry, 1.0)?;
+    let title = entry.name().clone().unwrap_or_default();
+    write_to_output(None, |writer| {
+        crate::util::cooklang_to_human::print_human(
+            &recipe,
+            &title,
+            1.0,
+            PARSER.converter(),
+            writer,
+        )?;
+        Ok(())
+    })
+}
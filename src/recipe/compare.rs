@@ -0,0 +1,154 @@
+use anyhow::{Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::{
+    util::{
+        compare::{compare, ComparedIngredient},
+        format::format_decimal,
+        get_recipe,
+        nutrition::servings,
+        parse_recipe_from_entry, PARSER,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    /// First recipe, its units are used for the comparison
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
+    a: Utf8PathBuf,
+
+    /// Second recipe
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
+    b: Utf8PathBuf,
+
+    /// Compare the amounts per serving
+    ///
+    /// Both recipes need servings in their metadata.
+    #[arg(long)]
+    normalize_servings: bool,
+
+    /// Only list the ingredients that differ
+    #[arg(long)]
+    differences: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "human")]
+    format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// Differences below this many percent count as the same amount
+const SAME: f64 = 1.0;
+
+#[derive(Debug, Serialize)]
+struct Comparison {
+    a: String,
+    b: String,
+    /// Whether the amounts are per serving
+    per_serving: bool,
+    ingredients: Vec<ComparedIngredient>,
+}
+
+pub fn run(ctx: &Context, args: CompareArgs) -> Result<()> {
+    let load = |name: &Utf8PathBuf| -> Result<(String, _)> {
+        let entry = get_recipe(ctx.base_path(), name.as_str())?;
+        let title = entry.name().clone().unwrap_or_else(|| name.to_string());
+        let mut recipe = parse_recipe_from_entry(&entry, 1.0)?;
+        if args.normalize_servings {
+            let servings = servings(&recipe).with_context(|| {
+                format!("{title} has no servings, they're needed for --normalize-servings")
+            })?;
+            recipe = parse_recipe_from_entry(&entry, 1.0 / servings)?;
+        }
+        Ok((title, recipe))
+    };
+    let (a_title, a) = load(&args.a)?;
+    let (b_title, b) = load(&args.b)?;
+
+    let mut ingredients = compare(&a, &b, PARSER.converter());
+    if args.differences {
+        ingredients.retain(|igr| !is_same(igr));
+    }
+    let comparison = Comparison {
+        a: a_title,
+        b: b_title,
+        per_serving: args.normalize_servings,
+        ingredients,
+    };
+
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&comparison)?),
+        OutputFormat::Human => print_human(&comparison),
+    }
+    Ok(())
+}
+
+fn is_same(igr: &ComparedIngredient) -> bool {
+    match igr.difference {
+        Some(difference) => difference.abs() < SAME,
+        None => igr.a.is_some() && igr.a == igr.b,
+    }
+}
+
+fn print_human(comparison: &Comparison) {
+    if comparison.per_serving {
+        println!("{} vs {}, per serving", comparison.a, comparison.b);
+    } else {
+        println!("{} vs {}", comparison.a, comparison.b);
+    }
+    println!();
+    if comparison.ingredients.is_empty() {
+        println!("  The ingredients are the same");
+        return;
+    }
+
+    let mut table = tabular::Table::new("  {:<}  {:>}  {:>}  {:<}");
+    table.add_row(
+        tabular::Row::new()
+            .with_cell("Ingredient")
+            .with_cell(&comparison.a)
+            .with_cell(&comparison.b)
+            .with_cell(""),
+    );
+    for igr in &comparison.ingredients {
+        let note = match (&igr.a, &igr.b, igr.difference) {
+            (None, _, _) => "only in the second".to_string(),
+            (_, None, _) => "only in the first".to_string(),
+            _ if is_same(igr) => "same".to_string(),
+            (_, _, Some(difference)) => {
+                let sign = if difference > 0.0 { "+" } else { "" };
+                format!("{sign}{}%", format_decimal(difference.round()))
+            }
+            _ => "different units".to_string(),
+        };
+        let amount = |side: &Option<String>| match side.as_deref() {
+            None => "—",
+            Some("") => "some",
+            Some(text) => text,
+        };
+        table.add_row(
+            tabular::Row::new()
+                .with_cell(&igr.name)
+                .with_cell(amount(&igr.a))
+                .with_cell(amount(&igr.b))
+                .with_cell(note),
+        );
+    }
+    print!("{table}");
+}
@@ -0,0 +1,345 @@
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang::{Content, Item, Recipe};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+use crate::{
+    error::NotFound,
+    util::{
+        split_recipe_name_and_scaling_factor,
+        timer::{format_countdown, notify, recipe_timer, TimerSpec},
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct CookArgs {
+    /// Recipe to cook
+    ///
+    /// Accepts the same forms as `cook recipe read`, including inline
+    /// scaling like "Pasta:2".
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
+    recipe: Utf8PathBuf,
+
+    /// Scaling factor for ingredient quantities
+    ///
+    /// Defaults to `recipe.scale` from cook.toml, or 1.
+    #[arg(short, long)]
+    scale: Option<f64>,
+}
+
+pub fn run(ctx: &Context, args: CookArgs) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        bail!("Cooking mode needs an interactive terminal, use `cook recipe read` instead");
+    }
+
+    let (name, scale) = match split_recipe_name_and_scaling_factor(args.recipe.as_str()) {
+        Some((name, scale)) => (
+            name,
+            scale
+                .parse::<f64>()
+                .map_err(|e| anyhow::anyhow!("Invalid scaling factor for '{name}': {e}"))?,
+        ),
+        None => (
+            args.recipe.as_str(),
+            args.scale
+                .unwrap_or_else(|| ctx.default_scale_of(args.recipe.as_str())),
+        ),
+    };
+
+    let entry = cooklang_find::get_recipe(vec![ctx.base_path().clone()], name.into())
+        .map_err(|e| NotFound(format!("Recipe not found: {e}")))?;
+    let recipe = crate::util::parse_recipe_from_entry(&entry, scale)?;
+    let title = entry.name().clone().unwrap_or_default();
+
+    let steps = cook_steps(&recipe);
+    if steps.is_empty() {
+        bail!("'{title}' has no steps to cook");
+    }
+
+    let mut mode = CookMode {
+        title,
+        steps,
+        current: 0,
+        timers: Vec::new(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = mode.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+/// A step prepared for display
+struct CookStep {
+    section: Option<String>,
+    text: Line<'static>,
+    ingredients: Vec<String>,
+    timers: Vec<TimerSpec>,
+}
+
+struct RunningTimer {
+    label: String,
+    step: usize,
+    ends_at: Instant,
+    total: Duration,
+    notified: bool,
+}
+
+struct CookMode {
+    title: String,
+    steps: Vec<CookStep>,
+    current: usize,
+    timers: Vec<RunningTimer>,
+}
+
+fn cook_steps(recipe: &Recipe) -> Vec<CookStep> {
+    let ingredient = Style::new().fg(Color::Green).add_modifier(Modifier::BOLD);
+    let cookware = Style::new().fg(Color::Yellow);
+    let timer = Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let quantity = Style::new().fg(Color::Magenta);
+
+    let mut steps = Vec::new();
+    for section in &recipe.sections {
+        for content in &section.content {
+            let Content::Step(step) = content else {
+                continue;
+            };
+
+            let mut spans = Vec::new();
+            let mut ingredients = Vec::new();
+            let mut timers = Vec::new();
+            for item in &step.items {
+                match item {
+                    Item::Text { value } => spans.push(Span::raw(value.clone())),
+                    &Item::Ingredient { index } => {
+                        let igr = &recipe.ingredients[index];
+                        spans.push(Span::styled(igr.display_name().to_string(), ingredient));
+                        let mut line = igr.display_name().to_string();
+                        if let Some(q) = &igr.quantity {
+                            line = format!("{line}: {q}");
+                        }
+                        if igr.modifiers().is_optional() {
+                            line.push_str(" (optional)");
+                        }
+                        ingredients.push(line);
+                    }
+                    &Item::Cookware { index } => {
+                        let item = &recipe.cookware[index];
+                        spans.push(Span::styled(item.display_name().to_string(), cookware));
+                    }
+                    &Item::Timer { index } => {
+                        let spec = recipe_timer(recipe, index);
+                        spans.push(Span::styled(spec.label.clone(), timer));
+                        timers.push(spec);
+                    }
+                    &Item::InlineQuantity { index } => {
+                        let q = &recipe.inline_quantities[index];
+                        spans.push(Span::styled(q.to_string(), quantity));
+                    }
+                }
+            }
+
+            steps.push(CookStep {
+                section: section.name.clone(),
+                text: Line::from(spans),
+                ingredients,
+                timers,
+            });
+        }
+    }
+    steps
+}
+
+impl CookMode {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            self.check_timers()?;
+
+            if !event::poll(Duration::from_millis(250))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('n') | KeyCode::Char(' ') => {
+                    self.current = (self.current + 1).min(self.steps.len() - 1);
+                }
+                KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('p') => {
+                    self.current = self.current.saturating_sub(1);
+                }
+                KeyCode::Home | KeyCode::Char('g') => self.current = 0,
+                KeyCode::End | KeyCode::Char('G') => self.current = self.steps.len() - 1,
+                KeyCode::Char('t') => self.start_timer(0),
+                KeyCode::Char(c @ '1'..='9') => self.start_timer(c as usize - '1' as usize),
+                KeyCode::Char('c') => self.timers.retain(|t| !t.notified),
+                KeyCode::Char('x') => self.timers.clear(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Start (or restart) the n-th timer of the current step
+    fn start_timer(&mut self, n: usize) {
+        let Some(spec) = self.steps[self.current].timers.get(n) else {
+            return;
+        };
+        let Some(duration) = spec.duration else {
+            return;
+        };
+        let step = self.current;
+        let label = spec.label.clone();
+        self.timers
+            .retain(|t| !(t.step == step && t.label == label));
+        self.timers.push(RunningTimer {
+            label,
+            step,
+            ends_at: Instant::now() + duration,
+            total: duration,
+            notified: false,
+        });
+    }
+
+    /// Ring the bell and send a desktop notification for finished timers
+    fn check_timers(&mut self) -> Result<()> {
+        let now = Instant::now();
+        for timer in &mut self.timers {
+            if timer.notified || timer.ends_at > now {
+                continue;
+            }
+            timer.notified = true;
+            notify(&format!("{}: {} timer is done", self.title, timer.label))?;
+        }
+        Ok(())
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let step = &self.steps[self.current];
+
+        let timer_lines = self.timer_lines();
+        let timer_rows = timer_lines.len() as u16 + 2;
+        let ingredient_rows = step.ingredients.len().max(1) as u16 + 2;
+        let [header, body, ingredients, timers, footer] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Min(5),
+            Constraint::Length(ingredient_rows),
+            Constraint::Length(timer_rows),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let mut title = vec![
+            Span::styled(
+                self.title.clone(),
+                Style::new().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!("  step {}/{}", self.current + 1, self.steps.len())),
+        ];
+        if let Some(section) = &step.section {
+            title.push(Span::styled(
+                format!("  § {section}"),
+                Style::new().fg(Color::DarkGray),
+            ));
+        }
+        frame.render_widget(Paragraph::new(Line::from(title)), header);
+
+        frame.render_widget(
+            Paragraph::new(Text::from(step.text.clone()))
+                .block(Block::bordered().title(format!(" Step {} ", self.current + 1)))
+                .wrap(Wrap { trim: true }),
+            body,
+        );
+
+        let lines: Vec<Line> = if step.ingredients.is_empty() {
+            vec![Line::styled("-", Style::new().fg(Color::DarkGray))]
+        } else {
+            step.ingredients
+                .iter()
+                .map(|i| Line::from(format!("• {i}")))
+                .collect()
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Ingredients ")),
+            ingredients,
+        );
+
+        frame.render_widget(
+            Paragraph::new(timer_lines).block(Block::bordered().title(" Timers ")),
+            timers,
+        );
+
+        frame.render_widget(
+            Paragraph::new(
+                "←/→ step  t/1-9 start timer  c clear finished  x cancel timers  q quit",
+            )
+            .style(Style::new().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+
+    fn timer_lines(&self) -> Vec<Line<'static>> {
+        let step = &self.steps[self.current];
+        let mut lines = Vec::new();
+        let now = Instant::now();
+
+        for timer in &self.timers {
+            let remaining = timer.ends_at.saturating_duration_since(now);
+            let line = if remaining.is_zero() {
+                Line::styled(
+                    format!("⏰ {} (step {}) done!", timer.label, timer.step + 1),
+                    Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Line::from(format!(
+                    "⏳ {} (step {}) {} of {}",
+                    timer.label,
+                    timer.step + 1,
+                    format_countdown(remaining),
+                    format_countdown(timer.total)
+                ))
+            };
+            lines.push(line);
+        }
+
+        // Offer the timers of this step that aren't running yet
+        for (i, spec) in step.timers.iter().enumerate() {
+            let running = self
+                .timers
+                .iter()
+                .any(|t| t.step == self.current && t.label == spec.label);
+            if running || spec.duration.is_none() {
+                continue;
+            }
+            lines.push(Line::styled(
+                format!("[{}] start {}", i + 1, spec.label),
+                Style::new().fg(Color::DarkGray),
+            ));
+        }
+
+        if lines.is_empty() {
+            lines.push(Line::styled("-", Style::new().fg(Color::DarkGray)));
+        }
+        lines
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use clap::Args;
+
+use crate::{
+    util::{cooked::mark_cooked, split_recipe_name_and_scaling_factor},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct CookedArgs {
+    /// Recipe that was cooked
+    ///
+    /// Accepts the same forms as `cook recipe read`, including inline
+    /// scaling like "Pasta:2".
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
+    recipe: Utf8PathBuf,
+
+    /// Scaling factor the recipe was cooked at
+    ///
+    /// Defaults to `recipe.scale` from cook.toml, or 1.
+    #[arg(short, long)]
+    scale: Option<f64>,
+
+    /// Only record the cooked event, don't touch the pantry
+    #[arg(long)]
+    no_pantry: bool,
+}
+
+pub fn run(ctx: &Context, args: CookedArgs) -> Result<()> {
+    let (name, scale) = match split_recipe_name_and_scaling_factor(args.recipe.as_str()) {
+        Some((name, scale)) => (
+            name,
+            scale
+                .parse::<f64>()
+                .map_err(|e| anyhow::anyhow!("Invalid scaling factor for '{name}': {e}"))?,
+        ),
+        None => (
+            args.recipe.as_str(),
+            args.scale
+                .unwrap_or_else(|| ctx.default_scale_of(args.recipe.as_str())),
+        ),
+    };
+
+    let pantry_path = if args.no_pantry { None } else { ctx.pantry() };
+    let report = mark_cooked(ctx.base_path(), pantry_path.as_deref(), None, name, scale)?;
+
+    if scale == 1.0 {
+        println!("Marked {} as cooked", report.event.recipe);
+    } else {
+        println!("Marked {} as cooked (scale {scale})", report.event.recipe);
+    }
+
+    if report.pantry.is_empty() {
+        if pantry_path.is_some() {
+            println!("No pantry items were used");
+        }
+        return Ok(());
+    }
+
+    println!("\nPantry updates:");
+    for depletion in &report.pantry {
+        print!("  • {}", depletion.ingredient);
+        if !depletion.used.is_empty() {
+            print!(": used {}", depletion.used.join(" + "));
+        }
+        if let Some(remaining) = &depletion.remaining {
+            print!(", {remaining} left");
+        }
+        if let Some(note) = &depletion.note {
+            print!(" ({note})");
+        }
+        println!();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+
+use crate::{
+    util::{
+        duplicate::{duplicate, duplicate_path},
+        get_recipe,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct DuplicateArgs {
+    /// Recipe to copy
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
+    recipe: Utf8PathBuf,
+
+    /// Title of the copy, also used for its file name
+    #[arg(long = "as", value_name = "TITLE")]
+    title: String,
+
+    /// Remove the source and author from the copy
+    ///
+    /// For variations that aren't the published recipe anymore.
+    #[arg(long)]
+    clear_source: bool,
+
+    /// File to write instead of <TITLE>.cook next to the original
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    output: Option<Utf8PathBuf>,
+
+    /// Replace the file if it exists
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn run(ctx: &Context, args: DuplicateArgs) -> Result<()> {
+    let entry = get_recipe(ctx.base_path(), args.recipe.as_str())?;
+    let path = entry
+        .path()
+        .context("Only recipe files can be duplicated")?
+        .clone();
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path}"))?;
+    let copy = duplicate(&content, &args.title, args.clear_source)
+        .with_context(|| format!("Failed to duplicate {path}"))?;
+
+    let out = args
+        .output
+        .unwrap_or_else(|| duplicate_path(&path, &args.title));
+    if out.exists() && !args.force {
+        bail!("{out} already exists, use --force to replace it");
+    }
+    std::fs::write(&out, copy).with_context(|| format!("Failed to write {out}"))?;
+    println!("✓ Wrote {out}");
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use cooklang::Content;
+use cooklang_find::RecipeEntry;
+
+use crate::{
+    util::{
+        download, get_recipe,
+        images::{self, ImageFormat, ImageOptions, IMAGE_EXTENSIONS},
+        parse_recipe_from_entry,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct ImageArgs {
+    /// Recipe the image belongs to
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
+    recipe: Utf8PathBuf,
+
+    /// Image file or http(s) URL to attach
+    ///
+    /// JPEG, PNG, WebP, GIF and HEIC images are read, turned upright,
+    /// stripped of their metadata and stored next to the recipe, named so
+    /// it's found as the recipe's image.
+    #[arg(long, value_name = "PATH|URL", conflicts_with = "remove")]
+    from: Option<String>,
+
+    /// Remove the image instead
+    #[arg(long)]
+    remove: bool,
+
+    /// The image of a step, counting from 1, instead of the title image
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    step: Option<u32>,
+
+    /// Longest side of the stored image in pixels, larger images are
+    /// scaled down
+    ///
+    /// Defaults to `images.max_size` in cook.toml, or 1600.
+    #[arg(long, value_name = "PIXELS")]
+    max_size: Option<u32>,
+
+    /// Format the image is stored in
+    ///
+    /// Defaults to `images.format` in cook.toml, or JPEG.
+    #[arg(long, value_enum)]
+    format: Option<ImageFormat>,
+
+    /// Replace an existing image
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn run(ctx: &Context, args: ImageArgs) -> Result<()> {
+    let entry = get_recipe(ctx.base_path(), args.recipe.as_str())?;
+    let path = entry
+        .path()
+        .context("Only recipe files can have images")?
+        .clone();
+    let relative = |p: &Utf8Path| p.strip_prefix(ctx.base_path()).unwrap_or(p).to_string();
+
+    if let Some(step) = args.step {
+        let steps = step_count(&entry)?;
+        if step > steps {
+            bail!(
+                "{} has {steps} steps, there's no step {step}",
+                relative(&path)
+            );
+        }
+    }
+    // Step images are numbered from 0, like Pizza.0.jpg for the first step
+    let slot = args.step.map(|step| step - 1);
+
+    if args.remove {
+        let existing = images(&path, slot);
+        if existing.is_empty() {
+            bail!("{} has no image to remove", relative(&path));
+        }
+        for image in existing {
+            std::fs::remove_file(&image).with_context(|| format!("Failed to remove {image}"))?;
+            println!("Removed {}", relative(&image));
+        }
+        return Ok(());
+    }
+
+    let Some(from) = &args.from else {
+        let mut found = images(&path, None);
+        for slot in 0..step_count(&entry)? {
+            found.extend(images(&path, Some(slot)));
+        }
+        if found.is_empty() {
+            println!("{} has no images", relative(&path));
+        }
+        for image in found {
+            println!("{}", relative(&image));
+        }
+        return Ok(());
+    };
+
+    let existing = images(&path, slot);
+    if !existing.is_empty() && !args.force {
+        bail!(
+            "{} already has an image at {}, use --force to replace it",
+            relative(&path),
+            relative(&existing[0])
+        );
+    }
+
+    let bytes = if from.starts_with("http://") || from.starts_with("https://") {
+        download(from)?
+    } else {
+        std::fs::read(from).with_context(|| format!("Failed to read {from}"))?
+    };
+    let mut options = ImageOptions::from_config(&ctx.config().images);
+    options.max_size = args.max_size.unwrap_or(options.max_size);
+    options.format = args.format.unwrap_or(options.format);
+    let image =
+        images::prepare(&bytes, &options).with_context(|| format!("Failed to convert {from}"))?;
+
+    let target = image_path(&path, slot, options.format.extension());
+    std::fs::write(&target, image).with_context(|| format!("Failed to write {target}"))?;
+    // Only one image per slot, or it isn't clear which one is used
+    for old in existing.iter().filter(|old| **old != target) {
+        std::fs::remove_file(old).with_context(|| format!("Failed to remove {old}"))?;
+    }
+    println!("✓ Saved {}", relative(&target));
+    Ok(())
+}
+
+/// Steps of all sections in a row, like the cooking mode numbers them
+fn step_count(entry: &RecipeEntry) -> Result<u32> {
+    let recipe = parse_recipe_from_entry(entry, 1.0)?;
+    let steps = recipe
+        .sections
+        .iter()
+        .flat_map(|section| &section.content)
+        .filter(|content| matches!(content, Content::Step(_)))
+        .count();
+    Ok(steps as u32)
+}
+
+/// Where the image of a recipe, or of one of its steps, goes: `Pizza.jpg`
+/// or `Pizza.0.jpg` next to `Pizza.cook`
+fn image_path(recipe: &Utf8Path, slot: Option<u32>, extension: &str) -> Utf8PathBuf {
+    let stem = recipe.file_stem().unwrap_or("recipe");
+    match slot {
+        Some(slot) => recipe.with_file_name(format!("{stem}.{slot}.{extension}")),
+        None => recipe.with_file_name(format!("{stem}.{extension}")),
+    }
+}
+
+/// The existing images for a slot, in any of the known formats
+fn images(recipe: &Utf8Path, slot: Option<u32>) -> Vec<Utf8PathBuf> {
+    IMAGE_EXTENSIONS
+        .iter()
+        .map(|extension| image_path(recipe, slot, extension))
+        .filter(|path| path.is_file())
+        .collect()
+}
@@ -0,0 +1,199 @@
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+
+use crate::{
+    util::{
+        front_matter::{display_value, FrontMatter},
+        get_recipe, tree_entries,
+        walk::build_tree,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct MetaArgs {
+    /// Recipe to read or change
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes(),
+        required_unless_present = "all"
+    )]
+    recipe: Option<Utf8PathBuf>,
+
+    /// Work on every recipe in the collection
+    #[arg(long, conflicts_with = "recipe")]
+    all: bool,
+
+    /// With --all, only recipes where KEY is VALUE, like tags=dessert
+    ///
+    /// For lists like tags, one of the items has to match. Can be repeated,
+    /// then every filter has to match.
+    #[arg(long, value_name = "KEY=VALUE", requires = "all")]
+    filter: Vec<MetaFilter>,
+
+    /// Print the recipes that would change without writing them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// What to do, all the metadata is printed without one
+    #[command(subcommand)]
+    action: Option<MetaAction>,
+}
+
+#[derive(Debug, Subcommand)]
+enum MetaAction {
+    /// Print the value of a key
+    ///
+    /// Lists like tags are printed separated by commas. Fails if the key
+    /// isn't set, unless used with --all.
+    Get { key: String },
+
+    /// Set a key, adding the front matter if the recipe has none
+    ///
+    /// Numbers and true/false are kept as such, anything else is text.
+    /// Values for tags are split at commas into a list.
+    Set { key: String, value: String },
+
+    /// Remove a key
+    Unset { key: String },
+}
+
+/// A `--filter` like "tags=dessert"
+#[derive(Debug, Clone)]
+struct MetaFilter {
+    key: String,
+    value: String,
+}
+
+impl std::str::FromStr for MetaFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(Self {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => Err("expected KEY=VALUE, like \"tags=dessert\"".to_string()),
+        }
+    }
+}
+
+impl MetaFilter {
+    fn matches(&self, front_matter: &FrontMatter) -> bool {
+        match front_matter.get(&self.key) {
+            Some(serde_yaml::Value::Sequence(items)) => items
+                .iter()
+                .any(|item| display_value(item).eq_ignore_ascii_case(&self.value)),
+            Some(value) => display_value(value).eq_ignore_ascii_case(&self.value),
+            None => false,
+        }
+    }
+}
+
+pub fn run(ctx: &Context, args: MetaArgs) -> Result<()> {
+    let base_path = ctx.base_path();
+    let files = match &args.recipe {
+        Some(recipe) => {
+            let entry = get_recipe(base_path, recipe.as_str())?;
+            let path = entry
+                .path()
+                .context("Only metadata of recipe files can be edited")?
+                .clone();
+            vec![path]
+        }
+        None => {
+            let mut files: Vec<Utf8PathBuf> = tree_entries(&build_tree(base_path)?)
+                .iter()
+                .filter_map(|entry| entry.path().cloned())
+                .collect();
+            files.sort();
+            files
+        }
+    };
+
+    let mut matched = 0;
+    let mut changed = 0;
+    let mut failed = 0;
+    for path in &files {
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let mut front_matter = match FrontMatter::parse(&content) {
+            Ok(front_matter) => front_matter,
+            Err(e) if args.all => {
+                tracing::warn!("Skipping {relative}: {e:#}");
+                failed += 1;
+                continue;
+            }
+            Err(e) => return Err(e.context(format!("Failed to read the metadata of {path}"))),
+        };
+        if !args
+            .filter
+            .iter()
+            .all(|filter| filter.matches(&front_matter))
+        {
+            continue;
+        }
+        matched += 1;
+
+        let updated = match &args.action {
+            None => {
+                for (key, value) in &front_matter.metadata {
+                    let key = display_value(key);
+                    let value = display_value(value);
+                    if args.all {
+                        println!("{relative}: {key}: {value}");
+                    } else {
+                        println!("{key}: {value}");
+                    }
+                }
+                false
+            }
+            Some(MetaAction::Get { key }) => {
+                match front_matter.get(key) {
+                    Some(value) if args.all => println!("{relative}: {}", display_value(value)),
+                    Some(value) => println!("{}", display_value(value)),
+                    None if args.all => {}
+                    None => bail!("{key} isn't set in {relative}"),
+                }
+                false
+            }
+            Some(MetaAction::Set { key, value }) => front_matter.set(key, value),
+            Some(MetaAction::Unset { key }) => front_matter.unset(key),
+        };
+        if !updated {
+            continue;
+        }
+        changed += 1;
+        if args.dry_run {
+            println!("Would update {relative}");
+        } else {
+            std::fs::write(path, front_matter.render()?)
+                .with_context(|| format!("Failed to write {path}"))?;
+            println!("Updated {relative}");
+        }
+    }
+
+    if matches!(
+        args.action,
+        Some(MetaAction::Set { .. } | MetaAction::Unset { .. })
+    ) {
+        let verb = if args.dry_run {
+            "would change"
+        } else {
+            "changed"
+        };
+        if args.all {
+            println!("✓ {changed} of {matched} recipes {verb}");
+        } else if changed == 0 {
+            println!("✓ Nothing to change");
+        }
+    }
+    if failed > 0 {
+        println!("{failed} recipes were skipped, see the warnings above");
+    }
+    Ok(())
+}
@@ -34,7 +34,15 @@ use clap::{Args, Subcommand};
 
 use crate::Context;
 
-mod read;
+mod compare;
+mod cook;
+mod cooked;
+mod duplicate;
+mod image;
+mod meta;
+mod nutrition;
+pub mod read;
+mod translate;
 
 #[derive(Debug, Args)]
 #[command(args_conflicts_with_subcommands = true)]
@@ -51,6 +59,105 @@ enum RecipeCommand {
     /// Parse and print a Cooklang recipe file
     #[command(alias = "r")]
     Read(read::ReadArgs),
+
+    /// Cook a recipe step by step in an interactive terminal view
+    ///
+    /// Shows one step per screen with its ingredients highlighted. Timers
+    /// from the step (~{10%minutes}) can be started with a key press and
+    /// ring the terminal bell when they finish.
+    ///
+    /// Examples:
+    ///   cook recipe cook "Pasta Carbonara"
+    ///   cook recipe cook pancakes.cook:2
+    Cook(cook::CookArgs),
+
+    /// Mark a recipe as cooked and use up its ingredients from the pantry
+    ///
+    /// Records the cooked event in config/cooked.jsonl and subtracts the
+    /// (scaled) ingredient quantities from pantry.conf, if there is one.
+    ///
+    /// Examples:
+    ///   cook recipe cooked "Pasta Carbonara"
+    ///   cook recipe cooked pasta.cook --scale 2
+    Cooked(cooked::CookedArgs),
+
+    /// Calculate calories and macronutrients of a recipe
+    ///
+    /// Ingredients are matched against a bundled nutrition database,
+    /// extended by config/nutrition.csv if present. Ingredients that can't
+    /// be matched are listed so you can add them.
+    ///
+    /// Examples:
+    ///   cook recipe nutrition "Pasta Carbonara"
+    ///   cook recipe nutrition pancakes.cook:2 -f json
+    ///   cook recipe nutrition --import-fdc ~/Downloads/FoodData_Central_csv
+    #[command(alias = "n")]
+    Nutrition(nutrition::NutritionArgs),
+
+    /// Translate a recipe to another language with the AI provider
+    ///
+    /// Names and text are translated, the markup and quantities are kept.
+    /// The translation is only written if it parses and has the same
+    /// ingredients, cookware, timers and quantities as the original.
+    /// Requires CLAUDE_API_KEY (or the variable set in the [ai] section
+    /// of cook.toml).
+    ///
+    /// Examples:
+    ///   cook recipe translate "Pasta Carbonara" --to fr
+    ///   cook recipe translate pancakes.cook --to de -o Pfannkuchen.cook
+    Translate(translate::TranslateArgs),
+
+    /// Read and change the metadata in a recipe's front matter
+    ///
+    /// Only the front matter is rewritten, the rest of the file is kept
+    /// exactly as it is. With --all, every recipe in the collection is
+    /// changed, or those matching --filter.
+    ///
+    /// Examples:
+    ///   cook recipe meta Pancakes                      # Print all metadata
+    ///   cook recipe meta Pancakes get servings
+    ///   cook recipe meta Pancakes set tags "vegan,quick"
+    ///   cook recipe meta Pancakes unset source
+    ///   cook recipe meta --all --filter tags=dessert set course dessert
+    Meta(meta::MetaArgs),
+
+    /// Attach, list and remove the images of a recipe
+    ///
+    /// Images are copied or downloaded, scaled down and stored as a JPEG
+    /// next to the recipe, named so they are found as its title image
+    /// (Pizza.jpg) or the image of a step (Pizza.0.jpg for the first).
+    ///
+    /// Examples:
+    ///   cook recipe image Pizza                           # List the images
+    ///   cook recipe image Pizza --from ~/Photos/pizza.png
+    ///   cook recipe image Pizza --from https://example.com/pizza.png --force
+    ///   cook recipe image Pizza --step 3 --from dough.jpg
+    ///   cook recipe image Pizza --remove
+    Image(image::ImageArgs),
+
+    /// Copy a recipe under a new title, to start a variation
+    ///
+    /// The copy is written next to the original, named after the title,
+    /// with the title in its front matter. The steps are copied as they
+    /// are.
+    ///
+    /// Examples:
+    ///   cook recipe duplicate Souffle --as "Chocolate Souffle"
+    ///   cook recipe duplicate Souffle.cook --as "Cheese Souffle" --clear-source
+    Duplicate(duplicate::DuplicateArgs),
+
+    /// Compare the ingredients of two recipes side by side
+    ///
+    /// Ingredients are matched by name and their amounts compared in the
+    /// units of the first recipe, like two sources of the same dish. With
+    /// --normalize-servings the amounts are compared per serving, so a
+    /// recipe for 4 and one for 6 can be compared.
+    ///
+    /// Examples:
+    ///   cook recipe compare Brownies.cook "Fudgy Brownies.cook"
+    ///   cook recipe compare Brownies "Fudgy Brownies" --normalize-servings
+    ///   cook recipe compare Brownies "Fudgy Brownies" --differences -f json
+    Compare(compare::CompareArgs),
 }
 
 pub fn run(ctx: &Context, args: RecipeArgs) -> Result<()> {
@@ -58,6 +165,14 @@ pub fn run(ctx: &Context, args: RecipeArgs) -> Result<()> {
 
     match command {
         RecipeCommand::Read(args) => read::run(ctx, args),
+        RecipeCommand::Cook(args) => cook::run(ctx, args),
+        RecipeCommand::Cooked(args) => cooked::run(ctx, args),
+        RecipeCommand::Nutrition(args) => nutrition::run(ctx, args),
+        RecipeCommand::Translate(args) => translate::run(ctx, args),
+        RecipeCommand::Meta(args) => meta::run(ctx, args),
+        RecipeCommand::Image(args) => image::run(ctx, args),
+        RecipeCommand::Duplicate(args) => duplicate::run(ctx, args),
+        RecipeCommand::Compare(args) => compare::run(ctx, args),
     }
 }
 
@@ -69,20 +184,27 @@ struct RecipeInputArgs {
     ///   - Full path: /path/to/recipe.cook
     ///   - Relative path: recipes/pasta.cook
     ///   - Recipe name: "Pasta Carbonara" (searches in recipe directory)
-    ///   - With scaling: recipe.cook:2 or "Pasta:3" (scales by factor)
+    ///   - With scaling: recipe.cook:2 or "Pasta:3" (scales by factor),
+    ///     "Pasta:party" (by one of the recipe's scalings)
     ///   - Stdin: omit to read from standard input
     ///
     /// The .cook extension is optional and will be added automatically.
     /// When using recipe names (not paths), the tool searches in the
     /// current directory and configured recipe directories.
-    #[arg(value_hint = clap::ValueHint::FilePath, value_name = "RECIPE")]
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
     recipe: Option<Utf8PathBuf>,
 
     /// Scaling factor for ingredient quantities
     ///
-    /// Multiplies all ingredient quantities by this factor.
+    /// Multiplies all ingredient quantities by this factor, or by the
+    /// factor of one of the recipe's `scalings`, like "party".
     /// Can also be specified inline with : syntax (e.g., recipe:2).
-    /// The inline syntax takes precedence over this flag.
-    #[arg(short, long, default_value_t = 1.0)]
-    scale: f64,
+    /// The inline syntax takes precedence over this flag. Defaults to
+    /// `recipe.scale` from cook.toml, or 1.
+    #[arg(short, long, value_name = "FACTOR|NAME")]
+    scale: Option<String>,
 }
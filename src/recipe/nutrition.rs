@@ -0,0 +1,259 @@
+use anyhow::{Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+
+use crate::{
+    error::NotFound,
+    util::{
+        format::format_decimal,
+        nutrition::{
+            analyze_menu, import_fdc, MenuNutritionReport, Nutrients, NutritionDb, NutritionReport,
+        },
+        split_recipe_name_and_scaling_factor, PARSER,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct NutritionArgs {
+    /// Recipe or menu to analyze
+    ///
+    /// Accepts the same forms as `cook recipe read`, including inline
+    /// scaling like "Pasta:2". For a menu (meal plan) the totals are
+    /// reported per day.
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        required_unless_present = "import_fdc",
+        add = crate::completions::recipes()
+    )]
+    recipe: Option<Utf8PathBuf>,
+
+    /// Scaling factor for the totals
+    ///
+    /// Defaults to `recipe.scale` from cook.toml, or 1.
+    #[arg(short, long)]
+    scale: Option<f64>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// Daily calorie target per person, used to flag days of a menu
+    #[arg(long, value_name = "KCAL")]
+    target_calories: Option<f64>,
+
+    /// How far a day may stray from --target-calories, in percent
+    #[arg(long, value_name = "PERCENT", default_value_t = 15.0)]
+    tolerance: f64,
+
+    /// Nutrition database to use in addition to the bundled one
+    ///
+    /// Defaults to nutrition.csv in the config directory.
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    database: Option<Utf8PathBuf>,
+
+    /// Import a USDA FoodData Central CSV download into the nutrition database
+    ///
+    /// Point this at the extracted directory containing food.csv and
+    /// food_nutrient.csv. The result is written to --database, or to
+    /// config/nutrition.csv.
+    #[arg(long, value_name = "DIR", conflicts_with = "recipe")]
+    import_fdc: Option<Utf8PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+    #[value(alias("yml"))]
+    Yaml,
+}
+
+pub fn run(ctx: &Context, args: NutritionArgs) -> Result<()> {
+    if let Some(dir) = &args.import_fdc {
+        let db = import_fdc(dir)?;
+        let path = args
+            .database
+            .unwrap_or_else(|| ctx.base_path().join("config").join("nutrition.csv"));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {parent}"))?;
+        }
+        std::fs::write(&path, db.to_csv())
+            .with_context(|| format!("Failed to write nutrition database at {path}"))?;
+        println!("Imported {} foods into {path}", db.len());
+        return Ok(());
+    }
+
+    let query = args
+        .recipe
+        .expect("recipe is required without --import-fdc");
+    let (name, scale) = match split_recipe_name_and_scaling_factor(query.as_str()) {
+        Some((name, scale)) => (
+            name,
+            scale
+                .parse::<f64>()
+                .map_err(|e| anyhow::anyhow!("Invalid scaling factor for '{name}': {e}"))?,
+        ),
+        None => (
+            query.as_str(),
+            args.scale
+                .unwrap_or_else(|| ctx.default_scale_of(query.as_str())),
+        ),
+    };
+
+    let db = NutritionDb::load(args.database.or_else(|| ctx.nutrition()).as_deref())?;
+
+    let entry = cooklang_find::get_recipe(vec![ctx.base_path().clone()], name.into())
+        .map_err(|e| NotFound(format!("Recipe not found: {e}")))?;
+    let recipe = crate::util::parse_recipe_from_entry(&entry, 1.0)?;
+    let title = entry.name().clone().unwrap_or_default();
+
+    if entry.is_menu() {
+        let report = analyze_menu(
+            &recipe,
+            ctx.base_path(),
+            &db,
+            PARSER.converter(),
+            scale,
+            args.target_calories,
+            args.tolerance / 100.0,
+        );
+        match args.format {
+            OutputFormat::Human => print_menu_human(&title, scale, &report),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&report)?),
+        }
+        return Ok(());
+    }
+
+    let report = crate::util::nutrition::analyze(&recipe, &db, PARSER.converter(), scale);
+
+    match args.format {
+        OutputFormat::Human => print_human(&title, scale, &report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&report)?),
+    }
+
+    Ok(())
+}
+
+fn print_human(title: &str, scale: f64, report: &NutritionReport) {
+    if scale == 1.0 {
+        println!("Nutrition for {title}");
+    } else {
+        println!("Nutrition for {title} (scaled x{})", format_decimal(scale));
+    }
+    println!();
+
+    let mut table = tabular::Table::new("  {:<}  {:>}  {:>}  {:>}  {:>}  {:>}");
+    table.add_row(
+        tabular::Row::new()
+            .with_cell("Ingredient")
+            .with_cell("Amount")
+            .with_cell("kcal")
+            .with_cell("Protein")
+            .with_cell("Fat")
+            .with_cell("Carbs"),
+    );
+    for igr in &report.ingredients {
+        table.add_row(nutrients_row(
+            &igr.name,
+            &format!("{} g", format_decimal(igr.grams.round())),
+            &igr.nutrients,
+        ));
+    }
+    table.add_row(nutrients_row("Total", "", &report.total));
+    if let Some(per_serving) = &report.per_serving {
+        let servings = report.servings.map(format_decimal).unwrap_or_default();
+        table.add_row(nutrients_row(
+            &format!("Per serving (of {servings})"),
+            "",
+            per_serving,
+        ));
+    }
+    print!("{table}");
+    if report.overridden {
+        println!("  Values from the recipe's nutrition metadata are used where set");
+    }
+
+    if !report.unmatched.is_empty() {
+        println!();
+        println!("Not included:");
+        for unmatched in &report.unmatched {
+            println!("  • {} ({})", unmatched.name, unmatched.reason);
+        }
+    }
+}
+
+fn print_menu_human(title: &str, scale: f64, report: &MenuNutritionReport) {
+    if scale == 1.0 {
+        println!("Daily nutrition for {title}");
+    } else {
+        println!(
+            "Daily nutrition for {title} (scaled x{})",
+            format_decimal(scale)
+        );
+    }
+    if let Some(target) = report.target_calories {
+        println!("Target: {} kcal per person", format_decimal(target.round()));
+    }
+    println!();
+
+    let per_person = report.servings.is_some();
+    let mut table = tabular::Table::new("  {:<}  {:>}  {:>}  {:>}  {:>}  {:<}");
+    table.add_row(
+        tabular::Row::new()
+            .with_cell(if per_person {
+                "Day (per person)"
+            } else {
+                "Day"
+            })
+            .with_cell("kcal")
+            .with_cell("Protein")
+            .with_cell("Fat")
+            .with_cell("Carbs")
+            .with_cell(""),
+    );
+    for day in &report.days {
+        let nutrients = day.per_serving.as_ref().unwrap_or(&day.total);
+        let flag = match day.calorie_deviation {
+            Some(d) if day.off_target => format!("{:+.0}% off target", d * 100.0),
+            _ => String::new(),
+        };
+        table.add_row(
+            tabular::Row::new()
+                .with_cell(&day.name)
+                .with_cell(format!("{:.0}", nutrients.calories))
+                .with_cell(format!("{:.1} g", nutrients.protein))
+                .with_cell(format!("{:.1} g", nutrients.fat))
+                .with_cell(format!("{:.1} g", nutrients.carbohydrates))
+                .with_cell(flag),
+        );
+    }
+    print!("{table}");
+
+    let unmatched: Vec<_> = report
+        .days
+        .iter()
+        .flat_map(|day| day.unmatched.iter().map(move |u| (&day.name, u)))
+        .collect();
+    if !unmatched.is_empty() {
+        println!();
+        println!("Not included:");
+        for (day, u) in unmatched {
+            println!("  • {day}: {} ({})", u.name, u.reason);
+        }
+    }
+}
+
+fn nutrients_row(name: &str, amount: &str, nutrients: &Nutrients) -> tabular::Row {
+    tabular::Row::new()
+        .with_cell(name)
+        .with_cell(amount)
+        .with_cell(format!("{:.0}", nutrients.calories))
+        .with_cell(format!("{:.1} g", nutrients.protein))
+        .with_cell(format!("{:.1} g", nutrients.fat))
+        .with_cell(format!("{:.1} g", nutrients.carbohydrates))
+}
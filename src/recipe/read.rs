@@ -28,15 +28,20 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use anyhow::{Context as _, Result};
-use clap::{Args, CommandFactory, ValueEnum};
+use anyhow::{bail, Context as _, Result};
+use clap::{Args, ValueEnum};
 use std::io::Read;
+use std::sync::Arc;
 
 use camino::Utf8PathBuf;
 
 use crate::{
-    util::{split_recipe_name_and_scaling_factor, write_to_output, PARSER},
-    Context,
+    util::{
+        scaling, select_parts, split_recipe_name_and_scaling_factor,
+        substitutions::{substitute, SubstitutionArg, Substitutions},
+        write_to_output, RecipePart, PARSER,
+    },
+    Context, LoadedRecipe,
 };
 use cooklang_find::RecipeEntry;
 
@@ -71,46 +76,62 @@ pub struct ReadArgs {
     /// Has no effect on human, cooklang, or markdown formats.
     #[arg(long)]
     pretty: bool,
+
+    /// Replace an ingredient, like "butter=margarine"
+    ///
+    /// Quantities are converted with the ratio from the substitutions
+    /// database (config/substitutions.toml and the bundled defaults), and
+    /// the ingredient is noted as replacing the original. Can be repeated.
+    #[arg(long, value_name = "INGREDIENT=SUBSTITUTE")]
+    substitute: Vec<SubstitutionArg>,
+
+    /// Only output these parts of the recipe, without the title
+    ///
+    /// Separate several parts with commas, like --only ingredients,steps.
+    /// In JSON and YAML, steps refer to ingredients, cookware and timers by
+    /// their index. Not available for cooklang output.
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "PART")]
+    only: Vec<RecipePart>,
+
+    /// Show the scaled amounts of the ingredients each step uses
+    ///
+    /// Markdown lists them under every step, JSON and YAML add them as
+    /// `step_quantities`. Human output always shows them, and Cooklang has
+    /// them in the steps already.
+    #[arg(long)]
+    step_quantities: bool,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum OutputFormat {
+/// Format to render a recipe in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
     Human,
+    /// JSON representation of the recipe data
     Json,
+    /// YAML representation of the recipe data
     #[value(alias("yml"))]
     Yaml,
+    /// Regenerated Cooklang
     #[value(alias("cook"))]
     Cooklang,
+    /// Markdown
     #[value(alias("md"))]
     Markdown,
+    /// SSML for text-to-speech, a document for every step
+    Ssml,
 }
 
 pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
-    let mut scale = args.input.scale;
-
-    let (recipe, title) = if let Some(query) = args.input.recipe {
-        let (name, scaling_factor) = split_recipe_name_and_scaling_factor(query.as_str())
-            .map(|(name, scaling_factor)| {
-                let target = scaling_factor.parse::<f64>().unwrap_or_else(|err| {
-                    let mut cmd = crate::args::CliArgs::command();
-                    cmd.error(
-                        clap::error::ErrorKind::InvalidValue,
-                        format!("Invalid scaling target for '{name}': {err}. Use a number value after : to specify a scaling factor."),
-                    )
-                    .exit()
-                });
-                (name, Some(target))
-            })
-            .unwrap_or((query.as_str(), None));
-
-        if let Some(scaling_factor) = scaling_factor {
-            scale = scaling_factor;
+    let mut loaded = if let Some(query) = args.input.recipe {
+        // The inline scale takes precedence over --scale
+        match split_recipe_name_and_scaling_factor(query.as_str()) {
+            Some((name, scale)) => ctx.load_recipe_named_scale(name, scale)?,
+            None => match &args.input.scale {
+                Some(scale) => ctx.load_recipe_named_scale(query.as_str(), scale)?,
+                None => ctx.load_recipe(query.as_str())?,
+            },
         }
-
-        let recipe_entry = cooklang_find::get_recipe(vec![ctx.base_path().clone()], name.into())
-            .map_err(|e| anyhow::anyhow!("Recipe not found: {}", e))?;
-        let recipe = crate::util::parse_recipe_from_entry(&recipe_entry, scale)?;
-        (recipe, recipe_entry.name().clone().unwrap_or(String::new()))
     } else {
         // Read from stdin and create a RecipeEntry
         let mut buf = String::new();
@@ -122,54 +143,190 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
         let recipe_entry = RecipeEntry::from_content(buf, Some("stdin".to_string()))
             .context("Failed to create recipe entry from stdin")?;
 
+        let scale = match &args.input.scale {
+            Some(scale) => scaling::resolve(&recipe_entry, scale)?,
+            None => ctx.default_scale_for(&recipe_entry),
+        };
+
         // Use the same parsing function as for file-based recipes
         let recipe = crate::util::parse_recipe_from_entry(&recipe_entry, scale)?;
-        (recipe, recipe_entry.name().clone().unwrap_or(String::new()))
+        LoadedRecipe {
+            title: recipe_entry.name().clone().unwrap_or_default(),
+            scale,
+            entry: recipe_entry,
+            recipe,
+        }
     };
 
-    let format = args.format.unwrap_or_else(|| match &args.output {
-        Some(p) => match p.extension() {
-            Some("json") => OutputFormat::Json,
-            Some("cook") => OutputFormat::Cooklang,
-            Some("md") => OutputFormat::Markdown,
-            Some("yaml") => OutputFormat::Yaml,
-            Some("yml") => OutputFormat::Yaml,
-            _ => OutputFormat::Human,
-        },
-        None => OutputFormat::Human,
-    });
-
-    write_to_output(args.output.as_deref(), |writer| {
-        match format {
-            OutputFormat::Human => crate::util::cooklang_to_human::print_human(
-                &recipe,
-                &title,
-                scale,
-                PARSER.converter(),
-                writer,
-            )?,
-            OutputFormat::Json => {
-                if args.pretty {
-                    serde_json::to_writer_pretty(writer, &recipe)?;
-                } else {
-                    serde_json::to_writer(writer, &recipe)?;
+    if !args.substitute.is_empty() {
+        let substitutions = Substitutions::load(ctx.substitutions().as_deref())?;
+        let recipe = Arc::make_mut(&mut loaded.recipe);
+        for arg in &args.substitute {
+            let Some(found) = substitutions.find(&arg.ingredient, &arg.substitute) else {
+                let known: Vec<_> = substitutions
+                    .get(&arg.ingredient)
+                    .iter()
+                    .map(|s| s.name.as_str())
+                    .collect();
+                if known.is_empty() {
+                    bail!(
+                        "No substitutes for {} are known, add some to config/substitutions.toml",
+                        arg.ingredient
+                    );
                 }
+                bail!(
+                    "{} isn't a known substitute for {}, try one of: {}. Others can be added to config/substitutions.toml",
+                    arg.substitute,
+                    arg.ingredient,
+                    known.join(", ")
+                );
+            };
+            if substitute(recipe, &arg.ingredient, found) == 0 {
+                bail!("{} has no {}", loaded.title, arg.ingredient);
             }
-            OutputFormat::Cooklang => {
-                crate::util::cooklang_to_cooklang::print_cooklang(&recipe, writer)?
-            }
-            OutputFormat::Yaml => serde_yaml::to_writer(writer, &recipe)?,
-            OutputFormat::Markdown => crate::util::cooklang_to_md::print_md(
-                &recipe,
-                &title,
-                scale,
-                PARSER.converter(),
-                writer,
-            )?,
         }
+    }
+
+    let format = args
+        .format
+        .or_else(|| match args.output.as_ref()?.extension()? {
+            "json" => Some(OutputFormat::Json),
+            "cook" => Some(OutputFormat::Cooklang),
+            "md" => Some(OutputFormat::Markdown),
+            "yaml" | "yml" => Some(OutputFormat::Yaml),
+            "ssml" => Some(OutputFormat::Ssml),
+            _ => None,
+        })
+        .or_else(|| configured_format(ctx))
+        .unwrap_or(OutputFormat::Human);
+    let pretty = args.pretty || ctx.config().output.pretty.unwrap_or(false);
+    if format == OutputFormat::Cooklang && !args.only.is_empty() {
+        bail!("--only can't be used with cooklang output, the recipe has to be complete");
+    }
 
-        Ok(())
+    write_to_output(args.output.as_deref(), |writer| {
+        if format == OutputFormat::Json && pretty {
+            let value = recipe_value(&loaded, &args.only, args.step_quantities)?;
+            serde_json::to_writer_pretty(writer, &value)?;
+            return Ok(());
+        }
+        render(&loaded, format, &args.only, args.step_quantities, writer)
     })?;
 
     Ok(())
 }
+
+/// The `recipe.format` from cook.toml, if it's valid
+fn configured_format(ctx: &Context) -> Option<OutputFormat> {
+    let name = ctx.config().recipe.format.as_deref()?;
+    OutputFormat::from_str(name, true)
+        .map_err(|_| tracing::warn!("Unknown recipe.format '{name}' in cook.toml"))
+        .ok()
+}
+
+/// Write a recipe in the given format
+///
+/// JSON is written compact, YAML as `serde_yaml` writes it.
+pub fn render_recipe(
+    recipe: &LoadedRecipe,
+    format: OutputFormat,
+    writer: &mut dyn std::io::Write,
+) -> Result<()> {
+    render_recipe_parts(recipe, format, &[], writer)
+}
+
+/// Write only some parts of a recipe in the given format, like
+/// [`render_recipe`] does for all of them
+///
+/// Text and Markdown are written without the title. Cooklang can only be
+/// written whole.
+pub fn render_recipe_parts(
+    recipe: &LoadedRecipe,
+    format: OutputFormat,
+    parts: &[RecipePart],
+    writer: &mut dyn std::io::Write,
+) -> Result<()> {
+    render(recipe, format, parts, false, writer)
+}
+
+/// The recipe as JSON, with only the keys of `parts` and, if asked for, the
+/// ingredients of each step
+fn recipe_value(
+    recipe: &LoadedRecipe,
+    parts: &[RecipePart],
+    step_quantities: bool,
+) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(&recipe.recipe)?;
+    if step_quantities {
+        value["step_quantities"] = serde_json::to_value(
+            crate::util::step_quantities::step_quantities(&recipe.recipe, PARSER.converter()),
+        )?;
+    }
+    Ok(select_parts(value, parts))
+}
+
+fn render(
+    loaded: &LoadedRecipe,
+    format: OutputFormat,
+    parts: &[RecipePart],
+    step_quantities: bool,
+    writer: &mut dyn std::io::Write,
+) -> Result<()> {
+    let LoadedRecipe {
+        title,
+        scale,
+        recipe,
+        ..
+    } = loaded;
+    match format {
+        OutputFormat::Human => crate::util::cooklang_to_human::print_human_parts(
+            recipe,
+            title,
+            *scale,
+            parts,
+            PARSER.converter(),
+            writer,
+        )?,
+        OutputFormat::Json if parts.is_empty() && !step_quantities => {
+            serde_json::to_writer(writer, recipe)?
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer(writer, &recipe_value(loaded, parts, step_quantities)?)?
+        }
+        OutputFormat::Cooklang if !parts.is_empty() => {
+            bail!("Only whole recipes can be written as Cooklang")
+        }
+        OutputFormat::Cooklang => {
+            crate::util::cooklang_to_cooklang::print_cooklang(recipe, writer)?
+        }
+        OutputFormat::Yaml if parts.is_empty() && !step_quantities => {
+            serde_yaml::to_writer(writer, recipe)?
+        }
+        OutputFormat::Yaml => {
+            serde_yaml::to_writer(writer, &recipe_value(loaded, parts, step_quantities)?)?
+        }
+        OutputFormat::Ssml => crate::util::cooklang_to_ssml::print_ssml_parts(
+            recipe,
+            title,
+            parts,
+            PARSER.converter(),
+            writer,
+        )?,
+        OutputFormat::Markdown => {
+            let opts = crate::util::cooklang_to_md::Options {
+                step_quantities,
+                ..Default::default()
+            };
+            crate::util::cooklang_to_md::print_md_parts_with_options(
+                recipe,
+                title,
+                *scale,
+                parts,
+                &opts,
+                PARSER.converter(),
+                writer,
+            )?
+        }
+    }
+    Ok(())
+}
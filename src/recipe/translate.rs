@@ -0,0 +1,161 @@
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use cooklang::Recipe;
+
+use crate::{
+    util::{ai, diagnostics::ParseError, get_recipe, PARSER},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct TranslateArgs {
+    /// Recipe to translate
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
+    recipe: Utf8PathBuf,
+
+    /// Language to translate to, like "fr" or "pt-BR"
+    ///
+    /// Also used in the name of the new file.
+    #[arg(long, value_name = "LANG", value_parser = parse_language)]
+    to: String,
+
+    /// File to write instead of <recipe>.<LANG>.cook next to the original
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    output: Option<Utf8PathBuf>,
+
+    /// Replace the file if it exists
+    #[arg(long)]
+    force: bool,
+}
+
+const TRANSLATE_PROMPT: &str = "Translate this Cooklang recipe to the language with the \
+    code \"{lang}\".\n\
+    Return only the translated recipe, no other text and no code fences.\n\
+    Keep the structure exactly as it is:\n\
+    - Keep every @ingredient{...}, #cookware{...} and ~timer{...} in place. Translate the \
+    names, but never change what's inside the braces: quantities and units stay as they are.\n\
+    - Write multi-word names with braces, like @olive oil{}.\n\
+    - Keep the metadata keys between the --- lines in English and translate their text \
+    values, except for URLs, numbers and times.\n\
+    - Keep references to other recipes (@./Folder/Name{}) unchanged.\n\
+    - Keep line breaks, blank lines, section headings (= Name) and comments (-- and [- -]) \
+    where they are, translating the text.";
+
+pub fn run(ctx: &Context, args: TranslateArgs) -> Result<()> {
+    let entry = get_recipe(ctx.base_path(), args.recipe.as_str())?;
+    let path = entry
+        .path()
+        .context("Only recipe files can be translated")?
+        .clone();
+    let source =
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path}"))?;
+    let (original, _) = PARSER
+        .parse(&source)
+        .into_result()
+        .map_err(|report| ParseError::new(path.as_str(), &source, &report))
+        .with_context(|| format!("Fix the errors in {path} before translating it"))?;
+
+    let out = args.output.unwrap_or_else(|| {
+        let stem = path.file_stem().unwrap_or("recipe");
+        path.with_file_name(format!("{stem}.{}.cook", args.to))
+    });
+    if out.exists() && !args.force {
+        bail!("{out} already exists, use --force to replace it");
+    }
+
+    let prompt = format!(
+        "{}\n\nRecipe:\n{source}",
+        TRANSLATE_PROMPT.replace("{lang}", &args.to)
+    );
+    eprintln!("Translating {path} to {}...", args.to);
+    let reply = tokio::runtime::Runtime::new()?.block_on(ai::complete(
+        &ctx.config().ai,
+        &prompt,
+        None,
+        (source.len() as u32).saturating_mul(2).max(2000),
+    ))?;
+    let translated = format!("{}\n", ai::strip_code_fence(&reply).trim_end());
+
+    // Nothing is written unless the translation is still the same recipe
+    let (recipe, _) = PARSER
+        .parse(&translated)
+        .into_result()
+        .map_err(|report| ParseError::new(out.as_str(), &translated, &report))
+        .context("The translation isn't valid Cooklang, nothing was written")?;
+    check_structure(&original, &recipe)
+        .context("The translation changed the recipe, nothing was written")?;
+
+    std::fs::write(&out, translated).with_context(|| format!("Failed to write {out}"))?;
+    println!("✓ Wrote {out}");
+    Ok(())
+}
+
+/// Language codes end up in file names, so only letters, digits and dashes
+fn parse_language(code: &str) -> Result<String, String> {
+    let valid = !code.is_empty()
+        && code.len() <= 16
+        && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if valid {
+        Ok(code.to_string())
+    } else {
+        Err("expected a language code like \"fr\" or \"pt-BR\"".to_string())
+    }
+}
+
+/// Check that only words changed: the same ingredients, cookware and timers
+/// with the same quantities
+fn check_structure(original: &Recipe, translated: &Recipe) -> Result<()> {
+    let counts = |recipe: &Recipe| {
+        (
+            recipe.ingredients.len(),
+            recipe.cookware.len(),
+            recipe.timers.len(),
+        )
+    };
+    let (ingredients, cookware, timers) = counts(original);
+    if counts(original) != counts(translated) {
+        let (new_ingredients, new_cookware, new_timers) = counts(translated);
+        bail!(
+            "Expected {ingredients} ingredients, {cookware} cookware and {timers} timers, \
+             got {new_ingredients}, {new_cookware} and {new_timers}"
+        );
+    }
+
+    let quantities = |recipe: &Recipe| -> Vec<Option<String>> {
+        let ingredients = recipe.ingredients.iter().map(|igr| &igr.quantity);
+        let timers = recipe.timers.iter().map(|timer| &timer.quantity);
+        ingredients
+            .chain(timers)
+            .map(|q| q.as_ref().map(|q| q.to_string()))
+            .collect()
+    };
+    let names = original
+        .ingredients
+        .iter()
+        .map(|igr| igr.name.clone())
+        .chain(
+            original
+                .timers
+                .iter()
+                .map(|timer| timer.name.clone().unwrap_or_else(|| "a timer".to_string())),
+        );
+    for ((before, after), name) in quantities(original)
+        .iter()
+        .zip(&quantities(translated))
+        .zip(names)
+    {
+        if before != after {
+            bail!(
+                "The quantity of {name} changed from {} to {}",
+                before.as_deref().unwrap_or("none"),
+                after.as_deref().unwrap_or("none")
+            );
+        }
+    }
+    Ok(())
+}
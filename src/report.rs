@@ -1,9 +1,19 @@
-use crate::util::split_recipe_name_and_scaling_factor;
+use crate::{
+    server::db::Database,
+    stats::{collection_stats, CollectionStats},
+    util::{
+        cooked::{cooked_history, server_database, CookedHistory},
+        metadata_cache::{collection_metadata, RecipeMetadata},
+        split_recipe_name_and_scaling_factor, tree_entries,
+        walk::build_tree,
+    },
+};
 use anyhow::{Context, Result};
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::{CommandFactory, Parser};
 use cooklang_reports::{config::Config, render_template_with_config};
-use std::{fs, path::PathBuf};
+use serde::Serialize;
+use std::{collections::BTreeMap, fs, path::PathBuf, sync::Arc};
 use tracing::warn;
 
 #[derive(Parser, Debug)]
@@ -29,8 +39,11 @@ pub struct ReportArgs {
     /// Can include an optional scaling factor using the :N syntax
     /// (e.g., "recipe.cook:2" to double the recipe). The scaling
     /// will be applied to all ingredient quantities in the template.
+    ///
+    /// Without a recipe the template reports on the whole collection at
+    /// the base path: it gets `recipes`, `tags`, `ingredients` and `stats`.
     #[arg(value_name = "RECIPE")]
-    recipe: String,
+    recipe: Option<String>,
 
     /// Path to the datastore directory with additional recipe data
     ///
@@ -65,8 +78,16 @@ pub fn run(ctx: &crate::Context, args: ReportArgs) -> Result<()> {
     // Print warning about prototype feature
     warn!("⚠️  The report command is a prototype feature and will change in future versions.");
 
+    let Some(recipe_arg) = &args.recipe else {
+        let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+        let db = server_database(base_path, ctx.config());
+        let report = collection_report(base_path, db.as_ref(), &args.template)?;
+        println!("{report}");
+        return Ok(());
+    };
+
     // Split recipe name and scaling factor
-    let (recipe_name, scaling_factor) = split_recipe_name_and_scaling_factor(&args.recipe)
+    let (recipe_name, scaling_factor) = split_recipe_name_and_scaling_factor(recipe_arg)
         .map(|(name, factor)| {
             let scale = factor.parse::<f64>().unwrap_or_else(|err| {
                 let mut cmd = crate::args::CliArgs::command();
@@ -78,7 +99,7 @@ pub fn run(ctx: &crate::Context, args: ReportArgs) -> Result<()> {
             });
             (name, scale)
         })
-        .unwrap_or((&args.recipe, 1.0));
+        .unwrap_or((recipe_arg, 1.0));
 
     // Read the recipe file
     let recipe = fs::read_to_string(recipe_name)
@@ -160,3 +181,85 @@ pub fn run(ctx: &crate::Context, args: ReportArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// A recipe of the collection, as collection templates see it
+#[derive(Debug, Serialize)]
+struct CollectionRecipe {
+    /// Path relative to the collection
+    path: String,
+    name: String,
+    is_menu: bool,
+    has_image: bool,
+    #[serde(flatten)]
+    metadata: Arc<RecipeMetadata>,
+    /// None if it was never cooked
+    cooked: Option<CookedHistory>,
+}
+
+/// What collection templates are rendered with
+#[derive(Debug, Serialize)]
+struct CollectionContext {
+    base_path: Utf8PathBuf,
+    /// Sorted by path
+    recipes: Vec<CollectionRecipe>,
+    /// Paths of the recipes with each tag
+    tags: BTreeMap<Arc<str>, Vec<String>>,
+    /// Paths of the recipes using each ingredient
+    ingredients: BTreeMap<Arc<str>, Vec<String>>,
+    stats: CollectionStats,
+}
+
+/// Render a template with the whole collection at `base_path`
+fn collection_report(
+    base_path: &Utf8Path,
+    db: Option<&Database>,
+    template: &Utf8Path,
+) -> Result<String> {
+    let source = fs::read_to_string(template)
+        .with_context(|| format!("Failed to read template file: {template}"))?;
+
+    let tree = build_tree(base_path)?;
+    let mut history = cooked_history(base_path, db)?;
+    let mut tags: BTreeMap<Arc<str>, Vec<String>> = BTreeMap::new();
+    let mut ingredients: BTreeMap<Arc<str>, Vec<String>> = BTreeMap::new();
+    let mut recipes = Vec::new();
+    for (entry, metadata) in collection_metadata(base_path, tree_entries(&tree)) {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let path = path.strip_prefix(base_path).unwrap_or(path).to_string();
+        for tag in &metadata.tags {
+            tags.entry(Arc::clone(tag)).or_default().push(path.clone());
+        }
+        for ingredient in &metadata.ingredients {
+            ingredients
+                .entry(Arc::clone(ingredient))
+                .or_default()
+                .push(path.clone());
+        }
+        recipes.push(CollectionRecipe {
+            name: entry.name().clone().unwrap_or_else(|| path.clone()),
+            is_menu: entry.is_menu(),
+            has_image: entry.title_image().is_some(),
+            cooked: history.remove(&path),
+            path,
+            metadata,
+        });
+    }
+    recipes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let context = CollectionContext {
+        base_path: base_path.to_path_buf(),
+        recipes,
+        tags,
+        ingredients,
+        stats: collection_stats(base_path, db, usize::MAX)?,
+    };
+
+    let mut env = minijinja::Environment::new();
+    env.add_template(template.as_str(), &source)
+        .with_context(|| format!("Invalid template {template}"))?;
+    env.get_template(template.as_str())?
+        .render(minijinja::Value::from_serialize(&context))
+        .with_context(|| format!("Failed to render {template}"))
+}
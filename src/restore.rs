@@ -0,0 +1,201 @@
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use clap::Args;
+use std::fs::{self, File};
+use std::io::Read;
+
+use crate::{
+    backup::{Manifest, COLLECTION_DIR, GLOBAL_CONFIG_DIR, MANIFEST},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Backup archive created by `cook backup`
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    archive: Utf8PathBuf,
+
+    /// Directory to restore the recipe collection into
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// Show what would be restored without writing anything
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+
+    /// Overwrite existing files that differ from the backup
+    #[arg(short, long)]
+    force: bool,
+
+    /// Don't restore the global configuration directory
+    #[arg(long)]
+    skip_global_config: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    Create,
+    Overwrite,
+    Unchanged,
+}
+
+pub fn run(ctx: &Context, args: RestoreArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let global_config = if args.skip_global_config {
+        None
+    } else {
+        ctx.global_config_dir()
+    };
+
+    // First pass: work out what would change, so nothing is written when
+    // the restore can't complete
+    let mut manifest = None;
+    let mut plan = Vec::new();
+    for_each_entry(&args.archive, |name, entry| {
+        if name.as_str() == MANIFEST {
+            let mut json = String::new();
+            entry.read_to_string(&mut json)?;
+            manifest = Some(
+                serde_json::from_str::<Manifest>(&json)
+                    .context("The backup's manifest is invalid")?,
+            );
+            return Ok(());
+        }
+
+        let Some(dest) = destination(name, base_path, global_config.as_deref())? else {
+            return Ok(());
+        };
+        let action = if dest.exists() {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            if fs::read(&dest).is_ok_and(|existing| existing == content) {
+                Action::Unchanged
+            } else {
+                Action::Overwrite
+            }
+        } else {
+            Action::Create
+        };
+        plan.push((dest, action));
+        Ok(())
+    })?;
+
+    let Some(manifest) = manifest else {
+        bail!("{} is not a backup created by `cook backup`", args.archive);
+    };
+
+    let count = |action: Action| plan.iter().filter(|(_, a)| *a == action).count();
+    let (create, overwrite, unchanged) = (
+        count(Action::Create),
+        count(Action::Overwrite),
+        count(Action::Unchanged),
+    );
+    let overwritten: Vec<&Utf8PathBuf> = plan
+        .iter()
+        .filter(|(_, a)| *a == Action::Overwrite)
+        .map(|(dest, _)| dest)
+        .collect();
+
+    println!(
+        "Backup of {} from {} (cook {})",
+        manifest.collection, manifest.created, manifest.cook_version
+    );
+
+    if args.dry_run {
+        for (dest, action) in &plan {
+            let label = match action {
+                Action::Create => "create",
+                Action::Overwrite => "overwrite",
+                Action::Unchanged => "unchanged",
+            };
+            println!("  {label:<9}  {dest}");
+        }
+        println!(
+            "Would restore {} files: {create} new, {overwrite} overwritten, {unchanged} unchanged",
+            plan.len()
+        );
+        return Ok(());
+    }
+
+    if !overwritten.is_empty() && !args.force {
+        let list: Vec<String> = overwritten.iter().map(|p| format!("  {p}")).collect();
+        bail!(
+            "{} existing files differ from the backup:\n{}\n\
+             Use --force to overwrite them, or --dry-run to see everything that would change",
+            overwritten.len(),
+            list.join("\n")
+        );
+    }
+
+    // Second pass: write the files
+    for_each_entry(&args.archive, |name, entry| {
+        if name.as_str() == MANIFEST {
+            return Ok(());
+        }
+        let Some(dest) = destination(name, base_path, global_config.as_deref())? else {
+            return Ok(());
+        };
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {parent}"))?;
+        }
+        let mut file = File::create(&dest).with_context(|| format!("Failed to create {dest}"))?;
+        std::io::copy(entry, &mut file).with_context(|| format!("Failed to restore {dest}"))?;
+        Ok(())
+    })?;
+
+    println!(
+        "✓ Restored {} files into {base_path}: {create} new, {overwrite} overwritten, {unchanged} unchanged",
+        plan.len()
+    );
+    Ok(())
+}
+
+/// Call `f` with the path and reader of every file in the archive
+fn for_each_entry(
+    archive: &Utf8Path,
+    mut f: impl FnMut(&Utf8Path, &mut dyn Read) -> Result<()>,
+) -> Result<()> {
+    let file = File::open(archive).with_context(|| format!("Failed to open {archive}"))?;
+    let decoder = zstd::Decoder::new(file)?;
+    let mut archive = tar::Archive::new(decoder);
+
+    for entry in archive.entries().context("Failed to read the backup")? {
+        let mut entry = entry.context("Failed to read the backup")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let Some(name) = Utf8Path::from_path(&path) else {
+            bail!("The backup contains a non UTF-8 path: {}", path.display());
+        };
+        let name = name.to_path_buf();
+        f(&name, &mut entry)?;
+    }
+    Ok(())
+}
+
+/// Where an archive entry is restored to, `None` to skip it
+fn destination(
+    name: &Utf8Path,
+    base_path: &Utf8Path,
+    global_config: Option<&Utf8Path>,
+) -> Result<Option<Utf8PathBuf>> {
+    // Never write outside the target directories
+    if !name
+        .components()
+        .all(|c| matches!(c, Utf8Component::Normal(_)))
+    {
+        bail!("The backup contains an unsafe path: {name}");
+    }
+
+    if let Ok(relative) = name.strip_prefix(COLLECTION_DIR) {
+        return Ok(Some(base_path.join(relative)));
+    }
+    if let Ok(relative) = name.strip_prefix(GLOBAL_CONFIG_DIR) {
+        return Ok(global_config.map(|dir| dir.join(relative)));
+    }
+    tracing::warn!("Skipping unknown entry {name} in the backup");
+    Ok(None)
+}
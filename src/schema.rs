@@ -0,0 +1,25 @@
+use anyhow::{Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+
+use crate::{util::schema::metadata_schema, Context};
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// File to write the schema to instead of printing it
+    #[arg(short, long, value_hint = clap::ValueHint::FilePath)]
+    output: Option<Utf8PathBuf>,
+}
+
+pub fn run(_ctx: &Context, args: SchemaArgs) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&metadata_schema())?;
+    match args.output {
+        Some(path) => {
+            std::fs::write(&path, format!("{schema}\n"))
+                .with_context(|| format!("Failed to write {path}"))?;
+            println!("✓ Wrote {path}");
+        }
+        None => println!("{schema}"),
+    }
+    Ok(())
+}
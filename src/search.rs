@@ -1,9 +1,18 @@
+use crate::{
+    util::{
+        cooked::{cooked_history, server_database},
+        metadata_cache::collection_metadata,
+        nutrition::{recipe_calories, NutritionDb},
+        seasons::{current_month, Seasons},
+        tree_entries,
+        walk::{build_tree, search},
+    },
+    Context,
+};
 use anyhow::Result;
 use camino::Utf8PathBuf;
-use clap::Args;
-use cooklang_find::search;
-
-use crate::Context;
+use clap::{Args, ValueEnum};
+use cooklang_find::RecipeEntry;
 
 #[derive(Debug, Args)]
 pub struct SearchArgs {
@@ -17,7 +26,15 @@ pub struct SearchArgs {
     ///   cook search chicken              # Find recipes with "chicken"
     ///   cook search chicken rice         # Find recipes with both "chicken" and "rice"
     ///   cook search "olive oil"          # Search for exact phrase
-    #[arg(required = true, num_args = 1.., value_name = "TERMS")]
+    ///
+    /// With --in-season the terms can be left out to list every recipe in
+    /// season.
+    #[arg(
+        required_unless_present = "in_season",
+        num_args = 1..,
+        value_name = "TERMS",
+        add = crate::completions::tags()
+    )]
     query: Vec<String>,
 
     /// Directory to search for recipes
@@ -27,6 +44,50 @@ pub struct SearchArgs {
     /// Defaults to the current directory.
     #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
     base_dir: Option<Utf8PathBuf>,
+
+    /// Order of the results
+    ///
+    /// `last-cooked` lists the most recently cooked recipes first and
+    /// `times-cooked` the most often cooked, from `cook recipe cooked`.
+    /// Recipes that were never cooked come last. `calories` lists the
+    /// lightest first, per serving, and recipes without nutrition data
+    /// last.
+    #[arg(long, value_enum, default_value = "relevance")]
+    sort: SortOrder,
+
+    /// Only recipes with at most this many calories per serving
+    ///
+    /// Calories come from the nutrition database, like `cook recipe
+    /// nutrition`. Recipes that don't say how many they serve count as one
+    /// serving, and recipes without any ingredient in the database are
+    /// left out.
+    #[arg(long, value_name = "KCAL")]
+    max_calories: Option<f64>,
+
+    /// Only recipes with produce that's in season, and none that isn't
+    ///
+    /// Seasons come from the bundled calendar, moved for the southern
+    /// hemisphere with `seasons.hemisphere` in cook.toml, and
+    /// seasons.toml in the config directory. Recipes without seasonal
+    /// produce are left out.
+    #[arg(long)]
+    in_season: bool,
+
+    /// Month to check the seasons in, from 1 to 12 (default: this month)
+    #[arg(
+        long,
+        requires = "in_season",
+        value_parser = clap::value_parser!(u32).range(1..=12)
+    )]
+    month: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SortOrder {
+    Relevance,
+    LastCooked,
+    TimesCooked,
+    Calories,
 }
 
 pub fn run(ctx: &Context, args: SearchArgs) -> Result<()> {
@@ -34,14 +95,71 @@ pub fn run(ctx: &Context, args: SearchArgs) -> Result<()> {
 
     // Join multiple search terms with spaces
     let query = args.query.join(" ");
-    let recipes = search(&base_dir, &query)?;
+    let found = if query.is_empty() {
+        tree_entries(&build_tree(&base_dir)?)
+    } else {
+        search(&base_dir, &query)?
+    };
+    let mut recipes: Vec<(RecipeEntry, Option<f64>)> =
+        found.into_iter().map(|recipe| (recipe, None)).collect();
+
+    if args.in_season {
+        let hemisphere = ctx.config().seasons.hemisphere.unwrap_or_default();
+        let seasons = Seasons::load(ctx.seasons().as_deref(), hemisphere)?;
+        let month = args.month.unwrap_or_else(current_month);
+        let entries = recipes.into_iter().map(|(recipe, _)| recipe).collect();
+        recipes = collection_metadata(&base_dir, entries)
+            .into_iter()
+            .filter(|(_, metadata)| {
+                seasons
+                    .check(metadata.ingredients.iter().map(|name| &**name), month)
+                    .is_in_season()
+            })
+            .map(|(recipe, _)| (recipe, None))
+            .collect();
+    }
 
-    for recipe in recipes {
-        if let Some(path) = recipe.path() {
-            let relative_path = path.strip_prefix(&base_dir).unwrap_or(path);
-            println!("\"{relative_path}\"");
+    if args.max_calories.is_some() || matches!(args.sort, SortOrder::Calories) {
+        let db = NutritionDb::load(ctx.nutrition().as_deref())?;
+        for (recipe, calories) in &mut recipes {
+            *calories = recipe_calories(recipe, &db);
+        }
+        if let Some(max) = args.max_calories {
+            recipes.retain(|(_, calories)| calories.is_some_and(|c| c <= max));
+        }
+        if matches!(args.sort, SortOrder::Calories) {
+            // Unknown calories last
+            recipes.sort_by(|(_, a), (_, b)| match (a, b) {
+                (Some(a), Some(b)) => a.total_cmp(b),
+                _ => b.is_some().cmp(&a.is_some()),
+            });
         }
     }
 
+    let mut paths: Vec<_> = recipes
+        .iter()
+        .filter_map(|(recipe, _)| recipe.path())
+        .map(|path| path.strip_prefix(&base_dir).unwrap_or(path).to_string())
+        .collect();
+
+    if matches!(args.sort, SortOrder::LastCooked | SortOrder::TimesCooked) {
+        let db = server_database(&base_dir, ctx.config());
+        let history = cooked_history(&base_dir, db.as_ref())?;
+        // Stable, so recipes cooked equally keep their relevance order
+        paths.sort_by(|a, b| {
+            let (a, b) = (history.get(a), history.get(b));
+            match args.sort {
+                SortOrder::LastCooked => b
+                    .and_then(|h| h.last_cooked_at())
+                    .cmp(&a.and_then(|h| h.last_cooked_at())),
+                _ => b.map(|h| h.times_cooked).cmp(&a.map(|h| h.times_cooked)),
+            }
+        });
+    }
+
+    for relative_path in paths {
+        println!("\"{relative_path}\"");
+    }
+
     Ok(())
 }
@@ -1,18 +1,105 @@
-use std::fs;
+use std::{borrow::Cow, fs, io::Read};
 
-use anyhow::{bail, Result};
-use camino::Utf8PathBuf;
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::Args;
 
 use rust_embed::RustEmbed;
 
-use crate::Context;
+use crate::{
+    util::{
+        diagnostics::ParseError, download_limited, git::git_ok, images::IMAGE_EXTENSIONS, PARSER,
+    },
+    Context,
+};
 
 // TODO check if it's compressed
 #[derive(RustEmbed)]
 #[folder = "./seed/"]
 struct SeedFiles;
 
+/// Seed files that can be installed on their own
+struct SeedGroup {
+    name: &'static str,
+    description: &'static str,
+    /// Files, or directories ending with `/`
+    paths: &'static [&'static str],
+    /// Groups with recipes this group's recipes reference
+    requires: &'static [&'static str],
+}
+
+const GROUPS: &[SeedGroup] = &[
+    SeedGroup {
+        name: "breakfasts",
+        description: "Pancakes and a breakfast burrito",
+        paths: &["Breakfast/"],
+        requires: &["shared"],
+    },
+    SeedGroup {
+        name: "mains",
+        description: "Neapolitan pizza and lamb chops",
+        paths: &[
+            "Neapolitan Pizza.cook",
+            "Sicilian-style Scottadito Lamb Chops.cook",
+        ],
+        requires: &["shared"],
+    },
+    SeedGroup {
+        name: "snacks",
+        description: "Two snack baskets",
+        paths: &["Snack Basket I.cook", "Snack Basket II.cook"],
+        requires: &[],
+    },
+    SeedGroup {
+        name: "shared",
+        description: "Pizza dough, red beans and guacamole, used by other recipes",
+        paths: &["Shared/"],
+        requires: &[],
+    },
+    SeedGroup {
+        name: "menus",
+        description: "A two day meal plan",
+        paths: &["2 Day Plan.menu"],
+        requires: &["breakfasts", "mains"],
+    },
+    SeedGroup {
+        name: "config",
+        description: "Shopping aisles, a pantry and a commented cook.toml",
+        paths: &["config/"],
+        requires: &[],
+    },
+    SeedGroup {
+        name: "extras",
+        description: "Ingredient database and report templates",
+        paths: &["db/", "reports/"],
+        requires: &[],
+    },
+];
+
+/// Largest recipe pack, downloaded or unpacked
+const MAX_PACK_SIZE: u64 = 100 << 20;
+
+/// Installed with every group
+const ALWAYS: &[&str] = &["README.md"];
+
+/// The configuration group, for --with-config
+const CONFIG_GROUP: &str = "config";
+
+/// Only installed with --with-config, or when asked for with --only; the
+/// rest of the configuration comes with every seed
+const STARTER_CONFIG: &[&str] = &["config/cook.toml"];
+
+impl SeedGroup {
+    fn contains(&self, file: &str) -> bool {
+        self.paths.iter().any(|path| match path.strip_suffix('/') {
+            Some(dir) => file
+                .strip_prefix(dir)
+                .is_some_and(|rest| rest.starts_with('/')),
+            None => file == *path,
+        })
+    }
+}
+
 #[derive(Debug, Args)]
 #[command()]
 pub struct SeedArgs {
@@ -26,27 +113,350 @@ pub struct SeedArgs {
     /// The directory will be created if it doesn't exist.
     #[arg(value_name = "DIR", value_hint = clap::ValueHint::DirPath)]
     output: Option<Utf8PathBuf>,
+
+    /// Show the groups of example recipes instead of creating them
+    #[arg(long)]
+    list: bool,
+
+    /// Only create these groups, like breakfasts,snacks
+    ///
+    /// Groups with recipes they reference are created too. See --list for
+    /// the groups.
+    #[arg(long, value_delimiter = ',', value_name = "GROUP")]
+    only: Vec<String>,
+
+    /// Also create a starter cook.toml
+    ///
+    /// config/cook.toml has the common settings commented out. With --only,
+    /// config/aisle.conf and config/pantry.conf are created too, which
+    /// every other seed has already.
+    #[arg(long)]
+    with_config: bool,
+
+    /// Print the files that would be created without creating them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Replace files that already exist
+    ///
+    /// Without it, nothing is created when any of the files exists.
+    #[arg(long)]
+    force: bool,
+
+    /// Create a recipe pack from a git repository or a .zip URL instead
+    ///
+    /// URLs ending in .zip are downloaded and unpacked, anything else is
+    /// cloned with git. Nothing is created unless every recipe in the pack
+    /// is valid Cooklang.
+    #[arg(long, value_name = "URL", conflicts_with_all = ["list", "only", "with_config"])]
+    from: Option<String>,
 }
 
 pub fn run(ctx: &Context, args: SeedArgs) -> Result<()> {
+    if args.list {
+        for group in GROUPS {
+            let files = SeedFiles::iter().filter(|f| group.contains(f)).count();
+            println!("{:<12}{} ({files} files)", group.name, group.description);
+        }
+        return Ok(());
+    }
+
     let path = args.output.as_ref().unwrap_or(&ctx.base_path);
 
     if path.is_file() {
         bail!("{} is not a directory", path);
     }
 
-    fs::create_dir_all(path).expect("Couldn't create output path");
+    let seeds = match &args.from {
+        Some(url) => fetch_pack(url)?,
+        None => {
+            let mut only = args.only;
+            if args.with_config && !only.is_empty() {
+                only.push(CONFIG_GROUP.to_string());
+            }
+            embedded(&only, args.with_config)?
+        }
+    };
 
-    for seed in SeedFiles::iter() {
-        SeedFiles::get(seed.as_ref()).map(|content| {
-            let file = path.join(seed.as_ref());
-            let parent = file.parent().expect("Invalid path");
+    // Check everything first, so a refused seed doesn't leave half the files
+    let existing: Vec<_> = seeds
+        .iter()
+        .filter(|seed| path.join(&seed.path).exists())
+        .map(|seed| seed.path.as_str())
+        .collect();
+
+    if args.dry_run {
+        for seed in &seeds {
+            let file = path.join(&seed.path);
+            if !existing.contains(&seed.path.as_str()) {
+                println!("create     {file}");
+            } else if args.force {
+                println!("overwrite  {file}");
+            } else {
+                println!("exists     {file}");
+            }
+        }
+        if !existing.is_empty() && !args.force {
+            println!(
+                "\n{} files already exist, nothing would be created without --force",
+                existing.len()
+            );
+        }
+        return Ok(());
+    }
 
-            fs::create_dir_all(parent).expect("Failed to create directory");
+    if !existing.is_empty() && !args.force {
+        let mut message = format!(
+            "{} of the example files already exist in {path}:\n",
+            existing.len()
+        );
+        for seed in existing.iter().take(10) {
+            message.push_str(&format!("  {seed}\n"));
+        }
+        if existing.len() > 10 {
+            message.push_str(&format!("  and {} more\n", existing.len() - 10));
+        }
+        message.push_str(
+            "Nothing was created. Use --force to replace them, or seed another directory",
+        );
+        bail!(message);
+    }
+
+    fs::create_dir_all(path).with_context(|| format!("Failed to create {path}"))?;
+
+    for seed in &seeds {
+        let file = path.join(&seed.path);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {parent}"))?;
+        }
+        fs::write(&file, &seed.content).with_context(|| format!("Failed to write {file}"))?;
+    }
+
+    println!("✓ Created {} example files in {path}", seeds.len());
+    Ok(())
+}
 
-            fs::write(file, content.data.as_ref())
+/// A file to create, relative to the seeded directory
+pub(crate) struct Seed {
+    pub(crate) path: String,
+    pub(crate) content: Cow<'static, [u8]>,
+}
+
+/// The files of the starter configuration, under `config/`
+pub(crate) fn config_templates() -> Result<Vec<Seed>> {
+    let mut seeds = embedded(&[CONFIG_GROUP.to_string()], true)?;
+    seeds.retain(|seed| !ALWAYS.contains(&seed.path.as_str()));
+    Ok(seeds)
+}
+
+/// The embedded files of the groups in `only`, or of all groups
+pub(crate) fn embedded(only: &[String], with_config: bool) -> Result<Vec<Seed>> {
+    let groups = selected_groups(only)?;
+
+    SeedFiles::iter()
+        .filter(|seed| match &groups {
+            Some(groups) => {
+                ALWAYS.contains(&seed.as_ref()) || groups.iter().any(|group| group.contains(seed))
+            }
+            None => with_config || !STARTER_CONFIG.contains(&seed.as_ref()),
+        })
+        .map(|seed| {
+            let content = SeedFiles::get(&seed)
+                .with_context(|| format!("The example file {seed} is missing from the build"))?;
+            Ok(Seed {
+                path: seed.into_owned(),
+                content: content.data,
+            })
+        })
+        .collect()
+}
+
+/// The groups named in `--only` and the groups they require, `None` for
+/// everything
+fn selected_groups(names: &[String]) -> Result<Option<Vec<&'static SeedGroup>>> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+    let mut selected: Vec<&SeedGroup> = Vec::new();
+    let mut pending: Vec<&str> = names.iter().map(|name| name.trim()).collect();
+    while let Some(name) = pending.pop() {
+        let Some(group) = GROUPS
+            .iter()
+            .find(|group| group.name.eq_ignore_ascii_case(name))
+        else {
+            let known: Vec<_> = GROUPS.iter().map(|group| group.name).collect();
+            bail!(
+                "There's no seed group called '{name}', try one of: {}",
+                known.join(", ")
+            );
+        };
+        if !selected.iter().any(|s| s.name == group.name) {
+            selected.push(group);
+            pending.extend(group.requires);
+        }
+    }
+    Ok(Some(selected))
+}
+
+/// The files of a recipe pack, checked to be valid recipes
+pub(crate) fn fetch_pack(url: &str) -> Result<Vec<Seed>> {
+    eprintln!("Fetching {url}...");
+    let mut seeds = if url.ends_with(".zip") {
+        unzip_limited(&download_limited(url, MAX_PACK_SIZE)?, MAX_PACK_SIZE)
+            .with_context(|| format!("Failed to unpack {url}"))?
+    } else {
+        clone(url)?
+    };
+    seeds.retain(|seed| {
+        in_pack(&seed.path)
+            && !seed
+                .path
+                .split('/')
+                .any(|part| part.starts_with('.') || part.is_empty())
+    });
+    strip_common_dir(&mut seeds);
+
+    let mut recipes = 0;
+    let mut invalid = Vec::new();
+    for seed in &seeds {
+        if !(seed.path.ends_with(".cook") || seed.path.ends_with(".menu")) {
+            continue;
+        }
+        recipes += 1;
+        let Ok(source) = std::str::from_utf8(&seed.content) else {
+            invalid.push(format!("{}: not UTF-8 text", seed.path));
+            continue;
+        };
+        if let Err(report) = PARSER.parse(source).into_result() {
+            invalid.push(ParseError::new(&seed.path, source, &report).to_string());
+        }
+    }
+    if recipes == 0 {
+        bail!("{url} has no .cook recipes");
+    }
+    if !invalid.is_empty() {
+        for error in &invalid {
+            eprintln!("{error}");
+        }
+        bail!(
+            "{} of the {recipes} recipes in {url} aren't valid Cooklang, nothing was created",
+            invalid.len()
+        );
+    }
+    Ok(seeds)
+}
+
+/// Whether a file of a recipe pack is created: recipes and their images,
+/// nothing that could run or configure anything
+fn in_pack(path: &str) -> bool {
+    let extension = Utf8Path::new(path)
+        .extension()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    ["cook", "menu"].contains(&extension.as_str()) || IMAGE_EXTENSIONS.contains(&extension.as_str())
+}
+
+pub(crate) fn unzip(bytes: &[u8]) -> Result<Vec<Seed>> {
+    unzip_limited(bytes, u64::MAX)
+}
+
+/// Like [`unzip`], failing when the files add up to more than `limit` bytes
+pub(crate) fn unzip_limited(bytes: &[u8], limit: u64) -> Result<Vec<Seed>> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let mut seeds = Vec::new();
+    let mut total = 0;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        // Skips paths leaving the directory, like ../../.bashrc
+        let Some(path) = file
+            .enclosed_name()
+            .and_then(|path| Utf8PathBuf::from_path_buf(path).ok())
+        else {
+            tracing::warn!("Skipping {} in the archive", file.name());
+            continue;
+        };
+        let mut content = Vec::new();
+        // What the archive says the sizes are can't be trusted
+        (&mut file)
+            .take(limit.saturating_sub(total).saturating_add(1))
+            .read_to_end(&mut content)?;
+        total += content.len() as u64;
+        if total > limit {
+            bail!("The archive unpacks to more than {} MB", limit >> 20);
+        }
+        seeds.push(Seed {
+            path: relative_path(&path),
+            content: Cow::Owned(content),
         });
     }
+    Ok(seeds)
+}
 
+fn clone(url: &str) -> Result<Vec<Seed>> {
+    // Removed when dropped
+    let temp = tempfile::tempdir().context("Failed to create a temporary directory")?;
+    let parent =
+        Utf8Path::from_path(temp.path()).context("The temporary directory isn't a UTF-8 path")?;
+    let dir = parent.join("pack");
+    // `--` so a URL starting with a dash isn't read as an option
+    git_ok(
+        parent,
+        &["clone", "--depth", "1", "--quiet", "--", url, dir.as_str()],
+    )?;
+    let mut seeds = Vec::new();
+    read_dir(&dir, &dir, &mut 0, &mut seeds)?;
+    Ok(seeds)
+}
+
+/// The files of the pack in `dir`, adding their sizes to `total`
+fn read_dir(root: &Utf8Path, dir: &Utf8Path, total: &mut u64, seeds: &mut Vec<Seed>) -> Result<()> {
+    for entry in dir.read_dir_utf8()? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_name().starts_with('.') {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            read_dir(root, path, total, seeds)?;
+        } else if file_type.is_file() && in_pack(path.as_str()) {
+            *total += entry.metadata()?.len();
+            if *total > MAX_PACK_SIZE {
+                bail!(
+                    "The pack has more than {} MB of recipes and images",
+                    MAX_PACK_SIZE >> 20
+                );
+            }
+            seeds.push(Seed {
+                path: relative_path(path.strip_prefix(root)?),
+                content: Cow::Owned(fs::read(path)?),
+            });
+        }
+    }
     Ok(())
 }
+
+/// A path with `/` separators, like the embedded files
+fn relative_path(path: &Utf8Path) -> String {
+    path.components()
+        .map(|c| c.as_str())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Remove the directory every file is in, like the `name-main/` of
+/// archives downloaded from GitHub
+pub(crate) fn strip_common_dir(seeds: &mut [Seed]) {
+    let Some((first, _)) = seeds.first().and_then(|seed| seed.path.split_once('/')) else {
+        return;
+    };
+    let prefix = format!("{first}/");
+    if seeds.iter().all(|seed| seed.path.starts_with(&prefix)) {
+        for seed in seeds {
+            seed.path.drain(..prefix.len());
+        }
+    }
+}
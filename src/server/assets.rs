@@ -0,0 +1,150 @@
+//! Caching of the UI's assets and the collection's images
+//!
+//! The stylesheet and other embedded files are linked with their content
+//! hash in the URL (see [`asset_url`]), so browsers can keep them until cook
+//! is updated. Images from the collection can change at any time, they're
+//! sent with an `ETag` and browsers ask whether they changed before using
+//! their copy, which costs a round trip but not the download.
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, Request},
+    http::{header, HeaderMap, HeaderValue, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use rust_embed::RustEmbed;
+use serde::Deserialize;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+// Embed static files at compile time
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct StaticFiles;
+
+/// For assets requested with their current hash
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Use the cached copy only after checking it's still current
+const REVALIDATE: &str = "no-cache";
+
+#[derive(Debug, Deserialize)]
+pub struct AssetQuery {
+    /// Content hash the asset was linked with
+    v: Option<String>,
+}
+
+/// URL of an embedded asset that changes whenever its content does
+///
+/// Used by the templates, e.g. `asset_url("css/output.css")`.
+pub fn asset_url(path: &str) -> String {
+    match StaticFiles::get(path) {
+        Some(file) => format!("/static/{path}?v={}", content_hash(&file)),
+        None => format!("/static/{path}"),
+    }
+}
+
+/// Serve an embedded asset
+pub async fn serve_static(
+    Path(path): Path<String>,
+    Query(query): Query<AssetQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let path = path.trim_start_matches('/');
+
+    let Some(content) = StaticFiles::get(path) else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("404 Not Found"))
+            .unwrap();
+    };
+
+    let hash = content_hash(&content);
+    // An old hash is answered with the current content, which mustn't be
+    // cached under the old URL for good
+    let cache_control = if query.v.as_deref() == Some(hash.as_str()) {
+        IMMUTABLE
+    } else {
+        REVALIDATE
+    };
+    let etag = format!("\"{hash}\"");
+
+    let response = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, cache_control);
+    if etag_matches(&headers, &etag) {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    response
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, mime.as_ref())
+        .body(Body::from(content.data))
+        .unwrap()
+}
+
+/// Add an `ETag` to files from the collection and answer revalidations
+///
+/// The tag is made from the size and modification time the file server
+/// reports, so the file is never read to check it.
+pub async fn revalidate(request: Request, next: Next) -> Response<Body> {
+    let request_headers = request.headers().clone();
+    let mut response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let headers = response.headers();
+    let (Some(length), Some(modified)) = (
+        headers.get(header::CONTENT_LENGTH),
+        headers.get(header::LAST_MODIFIED),
+    ) else {
+        return response;
+    };
+    let mut hasher = DefaultHasher::new();
+    modified.as_bytes().hash(&mut hasher);
+    let etag = format!(
+        "W/\"{}-{:x}\"",
+        length.to_str().unwrap_or_default(),
+        hasher.finish()
+    );
+    let etag_value = HeaderValue::from_str(&etag).expect("the tag is ASCII");
+
+    if etag_matches(&request_headers, &etag) {
+        return Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag_value)
+            .header(header::CACHE_CONTROL, REVALIDATE)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let headers = response.headers_mut();
+    headers.insert(header::ETAG, etag_value);
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(REVALIDATE));
+    response
+}
+
+/// The first 16 hex digits of the file's SHA-256, computed at build time
+fn content_hash(file: &rust_embed::EmbeddedFile) -> String {
+    file.metadata.sha256_hash()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Whether `If-None-Match` lists the tag, compared weakly like RFC 9110 says
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = weak(etag);
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || weak(tag) == etag)
+}
@@ -0,0 +1,203 @@
+//! The server's own state, in an SQLite database
+//!
+//! View counts, shared shopping lists, background jobs, recipes cooked in
+//! the web UI and whatever else the server changes while it runs live here
+//! instead of in files of their own. Recipes and the pantry stay files in
+//! the collection: the command line reads them too, and they're synced with
+//! the collection. The command line reads the cooked history from here as
+//! well as from its own log.
+//!
+//! The database is in the global configuration directory, one per
+//! collection, so it's never synced or backed up with the recipes, and
+//! remote collections can be refreshed without losing it.
+
+use std::{sync::Mutex, time::Duration};
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use rusqlite::Connection;
+
+use crate::{config::Config, util::resolve_to_absolute_path};
+
+/// How long a write waits for another one before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema changes, applied in order
+///
+/// The database's `user_version` is the number of them applied. Never change
+/// one that was released, add another.
+const MIGRATIONS: &[&str] = &[
+    // 1: view counts and shared shopping lists
+    "CREATE TABLE recipe_views (
+        path TEXT PRIMARY KEY,
+        views INTEGER NOT NULL,
+        last_viewed TEXT NOT NULL
+    );
+    CREATE TABLE shares (
+        token TEXT PRIMARY KEY,
+        list TEXT NOT NULL,
+        expires_at INTEGER NOT NULL
+    );",
+    // 2: background jobs
+    "CREATE TABLE jobs (
+        id TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        status TEXT NOT NULL,
+        done INTEGER NOT NULL,
+        total INTEGER,
+        message TEXT,
+        error TEXT,
+        result TEXT,
+        created_at TEXT NOT NULL,
+        finished_at TEXT
+    );",
+    // 3: recipes cooked in the web UI
+    "CREATE TABLE cooked (
+        id INTEGER PRIMARY KEY,
+        recipe TEXT NOT NULL,
+        scale REAL NOT NULL,
+        cooked_at TEXT NOT NULL
+    );",
+];
+
+pub struct Database {
+    path: Utf8PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl Database {
+    /// Open the database, creating it and bringing its tables up to date
+    pub fn open(path: &Utf8Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir}"))?;
+        }
+        let mut conn = Connection::open(path)
+            .with_context(|| format!("Failed to open the database {path}"))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Readers don't wait for writers
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn).with_context(|| format!("Failed to update the database {path}"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// The database of the collection at `base_path`, `server.database`
+    /// or the default one
+    pub fn locate(base_path: &Utf8Path, config: &Config) -> Result<Utf8PathBuf> {
+        match &config.server.database {
+            Some(path) => Ok(base_path.join(path)),
+            None => Self::default_path(base_path),
+        }
+    }
+
+    /// The database of the collection at `base_path`, in the global
+    /// configuration directory
+    ///
+    /// Named after the collection's directory and a hash of its full path,
+    /// so collections in directories of the same name don't share one.
+    pub fn default_path(base_path: &Utf8Path) -> Result<Utf8PathBuf> {
+        let dir = crate::global_config_dir()
+            .context("Could not determine the configuration directory, set server.database")?;
+        let base_path = resolve_to_absolute_path(base_path)?;
+        let name = format!(
+            "{}-{:016x}.db",
+            base_path.file_name().unwrap_or("collection"),
+            fnv1a(base_path.as_str().as_bytes())
+        );
+        Ok(dir.join("server").join(name))
+    }
+
+    /// The collection's database, if the server made one
+    pub fn existing(base_path: &Utf8Path, config: &Config) -> Result<Option<Self>> {
+        let path = Self::locate(base_path, config)?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Self::open(&path).map(Some)
+    }
+
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// Run queries on the connection
+    pub fn with<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        f(&conn).with_context(|| format!("Database error in {}", self.path))
+    }
+}
+
+/// A hash that stays the same between Rust versions, unlike the standard
+/// library's, as it names files that have to be found again
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let version = version as usize;
+    if version > MIGRATIONS.len() {
+        bail!("The database was made by a newer version of cook, update cook to use it");
+    }
+    for (number, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", (number + 1) as u32)?;
+        tx.commit()?;
+        tracing::info!("Updated the database to version {}", number + 1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db(dir: &tempfile::TempDir) -> Utf8PathBuf {
+        Utf8Path::from_path(dir.path()).unwrap().join("server.db")
+    }
+
+    #[test]
+    fn test_migrate() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = temp_db(&dir);
+        let db = Database::open(&path).unwrap();
+        let version: usize = db
+            .with(|conn| conn.query_row("PRAGMA user_version", [], |row| row.get(0)))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        drop(db);
+
+        // Opening it again doesn't apply the migrations twice
+        Database::open(&path).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+        drop(conn);
+        let error = Database::open(&path).err().unwrap();
+        assert!(format!("{error:#}").contains("newer version of cook"));
+    }
+
+    #[test]
+    fn test_default_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = Utf8Path::from_path(dir.path()).unwrap();
+        for name in ["My Recipes", "My-Recipes"] {
+            std::fs::create_dir(base.join(name)).unwrap();
+        }
+        let spaced = Database::default_path(&base.join("My Recipes")).unwrap();
+        let dashed = Database::default_path(&base.join("My-Recipes")).unwrap();
+        assert_ne!(spaced, dashed);
+        assert!(spaced.file_name().unwrap().starts_with("My Recipes-"));
+        // The same collection, however it's written
+        assert_eq!(
+            Database::default_path(&base.join("My-Recipes/../My Recipes")).unwrap(),
+            spaced
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Managing the server: rebuilding the recipe index and reporting on it
+//!
+//! The index is the metadata cache the recipe list, stats and random picks
+//! are served from. The admin endpoints need the token given with
+//! `--admin-token`, as `Authorization: Bearer TOKEN`; the health check is
+//! open.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use camino::Utf8Path;
+use serde::Serialize;
+
+use crate::{
+    server::AppState,
+    util::{metadata_cache, tree_entries, walk::build_tree},
+};
+
+/// How many parse failures are listed, the count has all of them
+const LISTED_FAILURES: usize = 50;
+
+/// The last build of the index
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexBuild {
+    /// When the build finished, RFC 3339
+    pub built_at: String,
+    #[serde(serialize_with = "as_millis")]
+    pub duration: Duration,
+    pub recipes: usize,
+    pub failures: usize,
+    /// Recipes that couldn't be parsed, by path in the collection
+    pub failed: Vec<Failure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    pub path: String,
+    pub error: String,
+}
+
+fn as_millis<S: serde::Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u128(duration.as_millis())
+}
+
+/// Parse the recipes of the collection that changed, or all of them when
+/// `full`, and update the cache
+pub fn build_index(base_path: &Utf8Path, full: bool) -> Result<IndexBuild> {
+    let start = Instant::now();
+    if full {
+        metadata_cache::invalidate(base_path);
+    }
+    let tree = build_tree(base_path)?;
+    let metadata = metadata_cache::collection_metadata(base_path, tree_entries(&tree));
+
+    let failed: Vec<Failure> = metadata
+        .iter()
+        .filter_map(|(entry, metadata)| {
+            let error = metadata.error.as_ref()?;
+            let path = entry.path()?;
+            Some(Failure {
+                path: path.strip_prefix(base_path).unwrap_or(path).to_string(),
+                error: error.clone(),
+            })
+        })
+        .collect();
+    Ok(IndexBuild {
+        built_at: chrono::Local::now().to_rfc3339(),
+        duration: start.elapsed(),
+        recipes: metadata.len(),
+        failures: failed.len(),
+        failed: failed.into_iter().take(LISTED_FAILURES).collect(),
+    })
+}
+
+/// Rebuild the index in the background and keep its report
+pub async fn rebuild(state: Arc<AppState>, full: bool) -> Result<IndexBuild, StatusCode> {
+    let base_path = state.base_path.clone();
+    let build = tokio::task::spawn_blocking(move || build_index(&base_path, full))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to rebuild the index: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map_err(|e| {
+            tracing::error!("Failed to rebuild the index: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    tracing::info!(
+        "Indexed {} recipes in {:?}, {} failed to parse",
+        build.recipes,
+        build.duration,
+        build.failures
+    );
+    *state.index.lock().unwrap_or_else(|e| e.into_inner()) = Some(build.clone());
+    Ok(build)
+}
+
+/// Check the request has the admin token
+///
+/// Without a token set, the admin API is off.
+pub(super) fn authorize(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let Some(token) = &state.admin_token else {
+        tracing::warn!("The admin API is off, start the server with --admin-token to use it");
+        return Err(StatusCode::FORBIDDEN);
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if same(given.trim().as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Compare without leaking how much of the token matched through timing
+pub(crate) fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+pub async fn reindex(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<IndexBuild>, StatusCode> {
+    authorize(&state, &headers)?;
+    Ok(Json(rebuild(state, true).await?))
+}
+
+/// Pick up changed recipes, without parsing the others again
+///
+/// Open like it always was, unless there's an admin token.
+pub async fn reload(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<IndexBuild>, StatusCode> {
+    if state.admin_token.is_some() {
+        authorize(&state, &headers)?;
+    }
+    Ok(Json(rebuild(state, false).await?))
+}
+
+/// Whether the server is up, for `cook server ping` and load balancers
+pub async fn health(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let indexed = state
+        .index
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some();
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "api_version": crate::server::API_VERSION,
+        // False until the index built at start is done
+        "indexed": indexed,
+    }))
+}
+
+pub async fn status(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize(&state, &headers)?;
+    let index = state
+        .index
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let cache_size = metadata_cache::cache_file()
+        .and_then(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len());
+    Ok(Json(serde_json::json!({
+        "base_path": state.base_path,
+        "version": env!("CARGO_PKG_VERSION"),
+        // Bytes of the cache file, shared by all collections
+        "index_size": cache_size,
+        "index": index,
+    })))
+}
@@ -0,0 +1,36 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::{
+    server::AppState,
+    util::calendar::{collection_events, render_ics},
+};
+
+/// The meals planned in the menus as an iCalendar feed
+///
+/// Built from the menu files on every request, so subscribed calendars
+/// follow changes to the plan.
+pub async fn calendar(State(state): State<Arc<AppState>>) -> Response {
+    let events = match collection_events(&state.base_path) {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::error!("Failed to read the menus: {e:#}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // Calendar apps need full links
+    let ics = render_ics("Meal plan", &events, |recipe| {
+        format!("{}/recipe/{}", state.public_url, recipe.replace(' ', "%20"))
+    });
+
+    (
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        ics,
+    )
+        .into_response()
+}
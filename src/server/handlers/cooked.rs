@@ -0,0 +1,45 @@
+use crate::{server::AppState, util::cooked::mark_cooked};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct CookedRequest {
+    recipe: String,
+    scale: Option<f64>,
+    /// Subtract the ingredients from the pantry (default: true)
+    deplete_pantry: Option<bool>,
+}
+
+pub async fn mark_recipe_cooked(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CookedRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let pantry_path = if payload.deplete_pantry.unwrap_or(true) {
+        state.pantry_path.as_deref()
+    } else {
+        None
+    };
+    if let Some(pantry_path) = pantry_path {
+        state.check_writable(pantry_path)?;
+    }
+
+    let report = mark_cooked(
+        &state.base_path,
+        pantry_path,
+        Some(&state.db),
+        &payload.recipe,
+        payload.scale.unwrap_or(1.0),
+    )
+    .map_err(|e| {
+        tracing::error!("Error marking recipe as cooked: {:#}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    if let Some(pantry_path) = pantry_path {
+        state.file_changed(pantry_path);
+    }
+
+    Ok(Json(
+        serde_json::to_value(report).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    ))
+}
@@ -0,0 +1,41 @@
+//! Server-sent events for clients that follow changes to the collection
+//!
+//! Each recipe file dropped into the inbox is sent as an `import` event with
+//! what became of it, as JSON. Clients that fall behind skip the events
+//! they missed.
+
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::Stream;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::{import::inbox::InboxEvent, server::AppState};
+
+/// Keeps proxies from closing an idle connection
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+pub async fn events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = state.inbox_events.subscribe();
+    let stream = futures_util::stream::unfold(rx, |mut rx: Receiver<InboxEvent>| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let event = Event::default()
+                        .event("import")
+                        .json_data(&event)
+                        .unwrap_or_else(|_| Event::default().comment("unserializable event"));
+                    return Some((Ok(event), rx));
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(KEEP_ALIVE))
+}
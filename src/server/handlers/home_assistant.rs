@@ -0,0 +1,219 @@
+//! A small JSON surface for Home Assistant REST sensors
+//!
+//! The responses are kept flat and their fields stable, so sensors and
+//! templates set up once keep working.
+
+use axum::{extract::State, http::StatusCode, Json};
+use cooklang::ingredient_list::IngredientList;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::{
+    server::{
+        shopping_list_store::{ShoppingListItem, ShoppingListStore},
+        AppState,
+    },
+    util::{calendar::collection_events, extract_ingredients, get_recipe, PARSER},
+};
+
+#[derive(Debug, Serialize)]
+struct PlannedMeal {
+    /// Like "Dinner", empty when the menu doesn't name the meal
+    meal: String,
+    /// Like "Lasagna, salad"
+    dishes: String,
+    /// Paths of the planned recipes, for links
+    recipes: Vec<String>,
+}
+
+/// Today's planned meals
+///
+/// `state` is short enough to be a sensor's state, like
+/// "Breakfast: toast; Dinner: Lasagna, salad". `dinner` is the dinner
+/// alone, for "what's for dinner" cards.
+pub async fn today(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let today = chrono::Local::now().date_naive();
+    let events = collection_events(&state.base_path).map_err(|e| {
+        tracing::error!("Failed to read the menus: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let events: Vec<_> = events
+        .into_iter()
+        .filter(|event| event.date == today)
+        .collect();
+
+    let summary = events
+        .iter()
+        .map(|event| event.summary())
+        .collect::<Vec<_>>()
+        .join("; ");
+    let dinner = events
+        .iter()
+        .find(|event| {
+            event
+                .meal
+                .as_deref()
+                .is_some_and(|meal| meal.eq_ignore_ascii_case("dinner"))
+        })
+        .map(|event| dishes(&event.items));
+    let meals: Vec<PlannedMeal> = events
+        .iter()
+        .map(|event| PlannedMeal {
+            meal: event.meal.clone().unwrap_or_default(),
+            dishes: dishes(&event.items),
+            recipes: event
+                .items
+                .iter()
+                .filter_map(|item| item.recipe.clone())
+                .collect(),
+        })
+        .collect();
+
+    // Home Assistant states are at most 255 characters
+    let sensor_state = if summary.is_empty() {
+        "Nothing planned".to_string()
+    } else {
+        truncate(&summary, 255)
+    };
+
+    Ok(Json(serde_json::json!({
+        "date": today.to_string(),
+        "state": sensor_state,
+        "dinner": dinner.unwrap_or_default(),
+        "meals": meals,
+    })))
+}
+
+fn dishes(items: &[crate::util::calendar::MealItem]) -> String {
+    items
+        .iter()
+        .map(|item| item.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max - 1).collect();
+    short.push('…');
+    short
+}
+
+/// Size of the shopping list: the recipes on it, the ingredients they add
+/// up to and the other items
+pub async fn shopping_list(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let store = ShoppingListStore::new(&state.base_path);
+    let recipes = store.load().map_err(|e| {
+        tracing::error!("Failed to load shopping list: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let extra = store.load_extra().map_err(|e| {
+        tracing::error!("Failed to load shopping list: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut list = IngredientList::new();
+    let mut seen = BTreeMap::new();
+    for recipe in &recipes {
+        // A recipe removed since shouldn't break the sensor
+        if let Err(e) = extract_ingredients(
+            &format!("{}:{}", recipe.path, recipe.scale),
+            &mut list,
+            &mut seen,
+            &state.base_path,
+            PARSER.converter(),
+            false,
+        ) {
+            tracing::warn!("Leaving {} out of the count: {e:#}", recipe.path);
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "items": list.into_iter().count() + extra.len(),
+        "recipes": recipes.len(),
+        "names": recipes.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(),
+        "extra": extra.iter().map(|i| i.name.as_str()).collect::<Vec<_>>(),
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddRequest {
+    /// Something to buy, like "milk"
+    item: Option<String>,
+    /// How much of the item, like "2 l"
+    quantity: Option<String>,
+    /// Recipe name or path, like "Pizza" or "Mains/Lasagna.cook"
+    recipe: Option<String>,
+    scale: Option<f64>,
+}
+
+/// Add an item or a recipe to the shopping list, for automations and voice
+/// assistants
+///
+/// Items are whatever the assistant heard, like "milk", and are listed
+/// apart from the recipes' ingredients. Unlike `/api/v1/shopping_list/add`
+/// recipes are looked up by name, so "Pizza" is enough. Answers 400 unless
+/// there's either an item or a recipe, or when the scale isn't above 0, and
+/// 404 when there's no such recipe.
+pub async fn add_to_shopping_list(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<AddRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let store = ShoppingListStore::new(&state.base_path);
+    let recipe = match (
+        payload.item.as_deref().map(str::trim),
+        payload.recipe.as_deref().map(str::trim),
+    ) {
+        (Some(item), None) if !item.is_empty() => {
+            let quantity = payload.quantity.as_deref().unwrap_or_default().trim();
+            store.add_extra(item, quantity).map_err(|e| {
+                tracing::error!("Failed to add to shopping list: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            return Ok(Json(serde_json::json!({
+                "added": item,
+                "quantity": quantity,
+            })));
+        }
+        (None, Some(recipe)) if !recipe.is_empty() => recipe,
+        _ => {
+            tracing::warn!("Add either an item or a recipe to the shopping list");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    };
+
+    let scale = payload.scale.unwrap_or(1.0);
+    if !scale.is_finite() || scale <= 0.0 {
+        tracing::warn!("The scale has to be a number above 0, not {scale}");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let entry = get_recipe(&state.base_path, recipe).map_err(|e| super::lookup_error(recipe, e))?;
+    let path = entry
+        .path()
+        .and_then(|path| path.strip_prefix(&state.base_path).ok())
+        .ok_or(StatusCode::NOT_FOUND)?
+        .to_string();
+    let name = entry.name().clone().unwrap_or_else(|| path.clone());
+
+    let item = ShoppingListItem {
+        path: path.clone(),
+        name: name.clone(),
+        scale,
+    };
+    store.add(item).map_err(|e| {
+        tracing::error!("Failed to add to shopping list: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(serde_json::json!({
+        "added": name,
+        "path": path,
+    })))
+}
@@ -0,0 +1,162 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    server::AppState,
+    util::{
+        metadata_cache::{collection_metadata, RecipeMetadata},
+        tree_entries,
+        walk::build_tree,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct IngredientsQuery {
+    /// Start or part of the name, all ingredients without it
+    #[serde(default)]
+    q: String,
+    /// Number of ingredients to list (default: 20)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct KnownIngredient {
+    name: String,
+    /// Recipes using it
+    count: usize,
+    /// Whether the aisle file has it
+    in_aisle: bool,
+}
+
+/// Ingredient names used in the collection or in the aisle file, for
+/// autocomplete
+///
+/// Names starting with the query come first, then names containing it, the
+/// most used first.
+pub async fn autocomplete(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<IngredientsQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let tree = build_tree(&state.base_path).map_err(|e| {
+        tracing::error!("Failed to build recipe tree: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let recipes = collection_metadata(&state.base_path, tree_entries(&tree));
+    let aisle = state
+        .aisle_path
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default();
+
+    let known = known_ingredients(recipes.iter().map(|(_, metadata)| &**metadata), &aisle);
+    let ingredients = rank(known, &query.q, query.limit.unwrap_or(20));
+    Ok(Json(serde_json::json!({ "ingredients": ingredients })))
+}
+
+/// Ingredients of the recipes and the aisle file, by lowercase name
+fn known_ingredients<'a>(
+    recipes: impl IntoIterator<Item = &'a RecipeMetadata>,
+    aisle: &str,
+) -> BTreeMap<String, KnownIngredient> {
+    let mut known: BTreeMap<String, KnownIngredient> = BTreeMap::new();
+    for metadata in recipes {
+        for name in &metadata.ingredients {
+            known
+                .entry(name.to_string())
+                .or_insert_with(|| KnownIngredient {
+                    name: name.to_string(),
+                    count: 0,
+                    in_aisle: false,
+                })
+                .count += 1;
+        }
+    }
+
+    if let Some(aisle) = cooklang::aisle::parse_lenient(aisle).output() {
+        for (name, _) in aisle.ingredients_info() {
+            known
+                .entry(name.to_lowercase())
+                .or_insert_with(|| KnownIngredient {
+                    name: name.to_string(),
+                    count: 0,
+                    in_aisle: false,
+                })
+                .in_aisle = true;
+        }
+    }
+    known
+}
+
+/// At most `limit` ingredients with `q` in their name
+fn rank(known: BTreeMap<String, KnownIngredient>, q: &str, limit: usize) -> Vec<KnownIngredient> {
+    let q = q.trim().to_lowercase();
+    let mut matches: Vec<(bool, KnownIngredient)> = known
+        .into_iter()
+        .filter(|(key, _)| key.contains(&q))
+        .map(|(key, ingredient)| (key.starts_with(&q), ingredient))
+        .collect();
+    matches.sort_by(|(a_prefix, a), (b_prefix, b)| {
+        b_prefix
+            .cmp(a_prefix)
+            .then(b.count.cmp(&a.count))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    matches
+        .into_iter()
+        .map(|(_, ingredient)| ingredient)
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(ingredients: &[&str]) -> RecipeMetadata {
+        RecipeMetadata {
+            ingredients: ingredients.iter().map(|&name| name.into()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn names(ingredients: &[KnownIngredient]) -> Vec<&str> {
+        ingredients.iter().map(|i| i.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_known_ingredients() {
+        let recipes = [recipe(&["tomato", "basil"]), recipe(&["tomato"])];
+        let known = known_ingredients(&recipes, "[produce]\nTomato\nLeek\n");
+        assert_eq!(known["tomato"].count, 2);
+        assert!(known["tomato"].in_aisle);
+        assert_eq!(known["basil"].count, 1);
+        assert!(!known["basil"].in_aisle);
+        assert_eq!(known["leek"].count, 0);
+        assert_eq!(known["leek"].name, "Leek");
+    }
+
+    #[test]
+    fn test_rank() {
+        let recipes = [
+            recipe(&["cherry tomato", "tomato paste"]),
+            recipe(&["tomato paste", "potato"]),
+            recipe(&["tomato", "cherry tomato", "tomato paste"]),
+        ];
+        let known = known_ingredients(&recipes, "");
+        // Starting with the query first, then the most used
+        assert_eq!(
+            names(&rank(known, " TOM", 10)),
+            ["tomato paste", "tomato", "cherry tomato"]
+        );
+        let known = known_ingredients(&recipes, "");
+        assert_eq!(names(&rank(known, "to", 2)), ["tomato paste", "tomato"]);
+        let known = known_ingredients(&recipes, "");
+        assert_eq!(rank(known, "", 20).len(), 4);
+    }
+}
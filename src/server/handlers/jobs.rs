@@ -0,0 +1,265 @@
+//! Starting background jobs and following them
+//!
+//! Starting a job answers 202 with the job; `/api/v1/jobs/:id` has its
+//! progress. Rebuilding the index and exporting the site need the admin
+//! token. The others, and listing and cancelling jobs, are open unless
+//! there's an admin token, like `/api/v1/reload`.
+
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use camino::{Utf8Component, Utf8PathBuf};
+use serde::Deserialize;
+
+use super::admin::{authorize, rebuild};
+use crate::{
+    publish::site,
+    server::{jobs::Job, AppState},
+};
+
+/// Most URLs one import takes
+const MAX_URLS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct JobsQuery {
+    /// Number of jobs to list (default: 20)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportRequest {
+    /// Directory to export into, inside the one `cook publish` uses by
+    /// default; that one itself when not given
+    out_dir: Option<Utf8PathBuf>,
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportRequest {
+    urls: Vec<String>,
+}
+
+/// Open unless there's an admin token
+fn authorize_open(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    if state.admin_token.is_some() {
+        authorize(state, headers)?;
+    }
+    Ok(())
+}
+
+fn started(job: anyhow::Result<Job>) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    match job {
+        Ok(job) => Ok((StatusCode::ACCEPTED, Json(job))),
+        Err(e) => {
+            tracing::error!("Failed to start a job: {e:#}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// The latest jobs, newest first
+pub async fn list(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<JobsQuery>,
+) -> Result<Json<Vec<Job>>, StatusCode> {
+    authorize_open(&state, &headers)?;
+    state
+        .jobs
+        .list(query.limit.unwrap_or(20))
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("{e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn get(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, StatusCode> {
+    authorize_open(&state, &headers)?;
+    match state.jobs.get(&id) {
+        Ok(Some(job)) => Ok(Json(job)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("{e:#}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Ask a job to stop, 409 when it isn't running
+pub async fn cancel(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    authorize_open(&state, &headers)?;
+    if state.jobs.cancel(&id) {
+        return Ok(StatusCode::ACCEPTED);
+    }
+    match state.jobs.get(&id) {
+        Ok(Some(_)) => Err(StatusCode::CONFLICT),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("{e:#}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Parse every recipe again, like `/api/v1/admin/reindex`
+pub async fn reindex(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    authorize(&state, &headers)?;
+    let job_state = Arc::clone(&state);
+    started(state.jobs.spawn("reindex", |job| async move {
+        job.progress(0, 1, "Parsing the recipes");
+        let build = rebuild(job_state, true)
+            .await
+            .map_err(|_| anyhow::anyhow!("Failed to rebuild the index, see the server log"))?;
+        job.progress(1, 1, "Done");
+        Ok(serde_json::to_value(build)?)
+    }))
+}
+
+/// Export the site, like `cook publish --out-dir`
+pub async fn export(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ExportRequest>,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    authorize(&state, &headers)?;
+    let base_path = state.base_path.clone();
+    let root = crate::publish::default_out_dir(&base_path).map_err(|e| {
+        tracing::error!("{e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let out_dir = match request.out_dir {
+        // No way out of the export directory
+        Some(dir)
+            if dir.as_str().is_empty()
+                || !dir
+                    .components()
+                    .all(|c| matches!(c, Utf8Component::Normal(_))) =>
+        {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Some(dir) => root.join(dir),
+        None => root,
+    };
+    if let Err(e) = site::check_out_dir(&base_path, &out_dir) {
+        tracing::error!("{e:#}");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let title = request.title.unwrap_or_else(|| "Cookbook".to_string());
+    started(state.jobs.spawn("export", |job| async move {
+        let out = out_dir.clone();
+        let summary = tokio::task::spawn_blocking(move || {
+            site::build_with_progress(&base_path, &out, &title, &|done, total| {
+                job.check()?;
+                job.progress(done, total, "Rendering the recipes");
+                Ok(())
+            })
+        })
+        .await
+        .context("The export panicked")??;
+        Ok(serde_json::json!({
+            "out_dir": out_dir,
+            "pages": summary.pages,
+            "images": summary.images,
+            "written": summary.written,
+            "removed": summary.removed,
+        }))
+    }))
+}
+
+/// Import recipes from URLs, to where the inbox's recipes go
+///
+/// A URL that can't be imported is reported in the result, the others are
+/// still imported.
+pub async fn import(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ImportRequest>,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    authorize_open(&state, &headers)?;
+    if request.urls.is_empty() || request.urls.len() > MAX_URLS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    state.check_writable(&state.inbox.output_dir)?;
+    let job_state = Arc::clone(&state);
+    started(state.jobs.spawn("import", |job| async move {
+        let total = request.urls.len();
+        let (mut imported, mut failed) = (Vec::new(), Vec::new());
+        for (i, url) in request.urls.iter().enumerate() {
+            job.check()?;
+            job.progress(i, total, &format!("Importing {url}"));
+            match job_state.inbox.import_url(url).await {
+                Ok(recipes) => {
+                    for recipe in &recipes {
+                        job_state.file_changed(&job_state.base_path.join(recipe));
+                    }
+                    imported.extend(recipes);
+                }
+                Err(e) => failed.push(serde_json::json!({
+                    "url": url,
+                    "error": format!("{e:#}"),
+                })),
+            }
+        }
+        job.progress(total, total, "Done");
+        Ok(serde_json::json!({ "recipes": imported, "failed": failed }))
+    }))
+}
+
+/// Import what's waiting in the inbox, converting it with the AI provider
+/// when `[inbox]` turns that on
+///
+/// 409 when the server already imports the inbox itself.
+pub async fn inbox(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    authorize_open(&state, &headers)?;
+    if state.inbox_watched {
+        return Err(StatusCode::CONFLICT);
+    }
+    state.check_writable(&state.inbox.output_dir)?;
+    let job_state = Arc::clone(&state);
+    started(state.jobs.spawn("inbox", |job| async move {
+        let inbox = &job_state.inbox;
+        let files = inbox.pending()?;
+        if files.is_empty() {
+            bail!("Nothing in {}", inbox.dir);
+        }
+        let total = files.len();
+        let mut events = Vec::new();
+        for (i, file) in files.iter().enumerate() {
+            job.check()?;
+            job.progress(
+                i,
+                total,
+                &format!("Importing {}", file.file_name().unwrap_or("")),
+            );
+            let event = inbox.process(file).await;
+            for recipe in &event.recipes {
+                job_state.file_changed(&job_state.base_path.join(recipe));
+            }
+            // Nobody listening isn't an error
+            let _ = job_state.inbox_events.send(event.clone());
+            events.push(event);
+        }
+        job.progress(total, total, "Done");
+        Ok(serde_json::to_value(events)?)
+    }))
+}
@@ -0,0 +1,140 @@
+//! Every problem in the collection at once, for a "problems" panel
+//!
+//! What the parser reports for each recipe, errors and warnings, and the
+//! rules from `[lint]` in cook.toml the recipe breaks, like `cook lint`.
+//! Broken rules are warnings: the recipe still works, it just doesn't
+//! follow the collection's conventions.
+
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use axum::{extract::State, http::StatusCode, Json};
+use camino::Utf8Path;
+use serde::Serialize;
+
+use crate::{
+    server::AppState,
+    util::{
+        diagnostics::Diagnostic,
+        encode_url,
+        lint::{lint, LintRules, Rule},
+        tree_entries,
+        walk::build_tree,
+        PARSER,
+    },
+};
+
+#[derive(Debug, Serialize)]
+pub struct Problems {
+    /// Recipes checked
+    recipes: usize,
+    errors: usize,
+    warnings: usize,
+    /// Recipes with problems, by path
+    files: Vec<FileProblems>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileProblems {
+    path: String,
+    /// The page editing the recipe
+    edit_url: String,
+    errors: Vec<Problem>,
+    warnings: Vec<Problem>,
+}
+
+#[derive(Debug, Serialize)]
+struct Problem {
+    /// The lint rule, none for what the parser reports
+    rule: Option<Rule>,
+    message: String,
+    /// 1-based, when the problem is about one place
+    line: Option<usize>,
+    column: Option<usize>,
+    hints: Vec<String>,
+}
+
+impl From<Diagnostic> for Problem {
+    fn from(diag: Diagnostic) -> Self {
+        let label = diag.labels.first();
+        Self {
+            rule: None,
+            line: label.map(|label| label.line),
+            column: label.map(|label| label.column),
+            message: diag.message,
+            hints: diag.hints,
+        }
+    }
+}
+
+pub async fn problems(State(state): State<Arc<AppState>>) -> Result<Json<Problems>, StatusCode> {
+    let problems =
+        tokio::task::spawn_blocking(move || collect(&state.base_path, &state.lint_rules))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to lint the collection: {e}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .map_err(|e| {
+                tracing::error!("Failed to lint the collection: {e:#}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    Ok(Json(problems))
+}
+
+fn collect(base_path: &Utf8Path, rules: &LintRules) -> Result<Problems> {
+    let tree = build_tree(base_path)?;
+    let mut problems = Problems {
+        recipes: 0,
+        errors: 0,
+        warnings: 0,
+        files: Vec::new(),
+    };
+
+    for entry in tree_entries(&tree) {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        if path.extension() != Some("cook") {
+            continue;
+        }
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        problems.recipes += 1;
+
+        let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+        for diag in Diagnostic::from_report(PARSER.parse(&content).report(), &content) {
+            match diag.severity {
+                "error" => errors.push(Problem::from(diag)),
+                _ => warnings.push(Problem::from(diag)),
+            }
+        }
+        // The rules are only checked on recipes that parse
+        if errors.is_empty() && !rules.is_empty() {
+            let has_image = entry.title_image().is_some();
+            warnings.extend(lint(&content, relative, has_image, rules).into_iter().map(
+                |finding| Problem {
+                    rule: Some(finding.rule),
+                    message: finding.message,
+                    line: finding.line,
+                    column: None,
+                    hints: Vec::new(),
+                },
+            ));
+        }
+
+        if errors.is_empty() && warnings.is_empty() {
+            continue;
+        }
+        problems.errors += errors.len();
+        problems.warnings += warnings.len();
+        problems.files.push(FileProblems {
+            path: relative.to_string(),
+            edit_url: format!("/recipe/new?edit={}", encode_url(relative.as_str())),
+            errors,
+            warnings,
+        });
+    }
+    Ok(problems)
+}
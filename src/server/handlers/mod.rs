@@ -1,13 +1,72 @@
+pub mod admin;
+pub mod calendar;
+pub mod cooked;
+pub mod events;
+pub mod home_assistant;
+pub mod ingredients;
+pub mod jobs;
+pub mod lint;
+pub mod nutrition;
 pub mod pantry;
 pub mod recipes;
+pub mod schema;
+pub mod seasons;
 pub mod shopping_list;
+pub mod substitutions;
+pub mod units;
+pub mod views;
 
+use crate::error::ErrorKind;
+use crate::util::diagnostics::ParseError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+pub use admin::reload;
+pub use cooked::mark_recipe_cooked;
 pub use pantry::{
-    add_item as add_pantry_item, get_pantry, remove_item as remove_pantry_item,
-    update_item as update_pantry_item,
+    add_item as add_pantry_item, get_expiring as get_expiring_pantry_items, get_pantry,
+    remove_item as remove_pantry_item, update_item as update_pantry_item,
 };
-pub use recipes::{all_recipes, recipe, reload, search};
+pub use recipes::{all_recipes, random, recipe, search};
 pub use shopping_list::{
-    add_to_shopping_list, clear_shopping_list, get_shopping_list_items, remove_from_shopping_list,
-    shopping_list,
+    add_to_shopping_list, clear_shopping_list, get_extra_items, get_shopping_list_items,
+    remove_extra_item, remove_from_shopping_list, share_shopping_list, shopping_list,
 };
+
+/// Status for a recipe that couldn't be looked up
+///
+/// 404 only when there's no such recipe, a recipe that can't be read is a
+/// 500.
+fn lookup_error(path: &str, e: anyhow::Error) -> StatusCode {
+    if ErrorKind::of(&e) == ErrorKind::NotFound {
+        tracing::error!("Recipe not found: {path}");
+        StatusCode::NOT_FOUND
+    } else {
+        tracing::error!("Failed to look up {path}: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Response for a recipe that couldn't be parsed
+///
+/// Parse errors are sent as 422 with the parser's diagnostics, so clients
+/// can point at the problem; anything else is a 500.
+fn recipe_error(path: &str, e: anyhow::Error) -> Response {
+    let Some(error) = e.downcast_ref::<ParseError>() else {
+        tracing::error!("Failed to parse recipe: {e:#}");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    tracing::warn!("{error}");
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(serde_json::json!({
+            "error": error.summary(),
+            "path": path,
+            "diagnostics": error.diagnostics,
+        })),
+    )
+        .into_response()
+}
@@ -0,0 +1,54 @@
+use crate::{
+    server::AppState,
+    util::{
+        nutrition::{analyze, analyze_menu, NutritionDb},
+        PARSER,
+    },
+};
+use axum::{http::StatusCode, Json};
+
+/// Nutrition of a recipe, served at `/api/v1/recipes/{path}/nutrition`
+///
+/// Menus get daily totals instead, with days flagged when they are more than
+/// `tolerance` percent (default 15) away from `target_calories`.
+pub fn recipe_nutrition(
+    path: &str,
+    state: &AppState,
+    scale: Option<f64>,
+    target_calories: Option<f64>,
+    tolerance: Option<f64>,
+) -> axum::response::Result<Json<serde_json::Value>> {
+    let entry = crate::util::get_recipe(&state.base_path, path)
+        .map_err(|e| super::lookup_error(path, e))?;
+
+    let recipe = crate::util::parse_recipe_from_entry(&entry, 1.0)
+        .map_err(|e| super::recipe_error(path, e))?;
+
+    let db = NutritionDb::load(state.nutrition_path.as_deref()).map_err(|e| {
+        tracing::error!("Failed to load nutrition database: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let scale = scale
+        .or_else(|| crate::util::scaling::default_servings_factor(&entry))
+        .unwrap_or(1.0);
+    let value = if entry.is_menu() {
+        serde_json::to_value(analyze_menu(
+            &recipe,
+            &state.base_path,
+            &db,
+            PARSER.converter(),
+            scale,
+            target_calories,
+            tolerance.unwrap_or(15.0) / 100.0,
+        ))
+    } else {
+        serde_json::to_value(analyze(&recipe, &db, PARSER.converter(), scale))
+    }
+    .map_err(|e| {
+        tracing::error!("Failed to serialize nutrition report: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(value))
+}
@@ -1,12 +1,17 @@
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::server::AppState;
+use crate::{
+    server::AppState,
+    util::pantry::{
+        expiring_items, expiry_threshold, parse_within, serialize_pantry, ExpiringItem,
+    },
+};
 
 #[derive(Debug, Deserialize)]
 pub struct AddPantryItem {
@@ -26,6 +31,20 @@ pub struct UpdatePantryItem {
     pub low: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ExpiringQuery {
+    /// Look-ahead window such as `7d` or `2w` (default: 7 days)
+    pub within: Option<String>,
+    #[serde(default)]
+    pub include_unknown: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExpiringResponse {
+    pub within_days: u32,
+    pub items: Vec<ExpiringItem>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ApiResponse {
     pub success: bool,
@@ -37,6 +56,7 @@ pub async fn add_item(
     Json(item): Json<AddPantryItem>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let pantry_path = state.pantry_path.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    state.check_writable(pantry_path)?;
 
     // Read existing pantry configuration
     let content =
@@ -73,10 +93,11 @@ pub async fn add_item(
     pantry_conf.rebuild_index();
 
     // Serialize back to regular TOML format (not array format)
-    let new_content = serialize_pantry_to_regular_toml(&pantry_conf);
+    let new_content = serialize_pantry(&pantry_conf);
 
     // Write back to file
     std::fs::write(pantry_path, new_content).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.file_changed(pantry_path);
 
     Ok(Json(ApiResponse {
         success: true,
@@ -89,6 +110,7 @@ pub async fn remove_item(
     Path((section, name)): Path<(String, String)>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let pantry_path = state.pantry_path.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    state.check_writable(pantry_path)?;
 
     // Read existing pantry configuration
     let content =
@@ -113,10 +135,11 @@ pub async fn remove_item(
     pantry_conf.rebuild_index();
 
     // Serialize back to regular TOML format (not array format)
-    let new_content = serialize_pantry_to_regular_toml(&pantry_conf);
+    let new_content = serialize_pantry(&pantry_conf);
 
     // Write back to file
     std::fs::write(pantry_path, new_content).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.file_changed(pantry_path);
 
     Ok(Json(ApiResponse {
         success: true,
@@ -130,6 +153,7 @@ pub async fn update_item(
     Json(update): Json<UpdatePantryItem>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let pantry_path = state.pantry_path.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    state.check_writable(pantry_path)?;
 
     // Read existing pantry configuration
     let content =
@@ -187,10 +211,11 @@ pub async fn update_item(
     pantry_conf.rebuild_index();
 
     // Serialize back to regular TOML format (not array format)
-    let new_content = serialize_pantry_to_regular_toml(&pantry_conf);
+    let new_content = serialize_pantry(&pantry_conf);
 
     // Write back to file
     std::fs::write(pantry_path, new_content).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.file_changed(pantry_path);
 
     Ok(Json(ApiResponse {
         success: true,
@@ -213,78 +238,29 @@ pub async fn get_pantry(
     Ok(Json(pantry_conf))
 }
 
-/// Serialize PantryConf to regular TOML format (not array format)
-fn serialize_pantry_to_regular_toml(pantry_conf: &cooklang::pantry::PantryConf) -> String {
-    use std::fmt::Write;
-
-    let mut output = String::new();
-
-    // First, handle any top-level items (if they exist)
-    if let Some(general_items) = pantry_conf.sections.get("general") {
-        for item in general_items {
-            write_pantry_item(&mut output, item);
-        }
-        if !general_items.is_empty() {
-            writeln!(&mut output).unwrap();
-        }
-    }
-
-    // Then handle all other sections in alphabetical order
-    for (section_name, items) in &pantry_conf.sections {
-        if section_name == "general" {
-            continue; // Already handled
-        }
-
-        writeln!(&mut output, "[{section_name}]").unwrap();
-
-        for item in items {
-            write_pantry_item(&mut output, item);
-        }
+pub async fn get_expiring(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExpiringQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let pantry_path = state.pantry_path.as_ref().ok_or(StatusCode::NOT_FOUND)?;
 
-        writeln!(&mut output).unwrap();
+    let within_days = match query.within.as_deref() {
+        Some(within) => parse_within(within).map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => 7,
+    };
+    let today = chrono::Local::now().date_naive();
+    if expiry_threshold(today, within_days).is_none() {
+        return Err(StatusCode::BAD_REQUEST);
     }
 
-    output
-}
+    // Read existing pantry configuration
+    let content =
+        std::fs::read_to_string(pantry_path).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-fn write_pantry_item(output: &mut String, item: &cooklang::pantry::PantryItem) {
-    use std::fmt::Write;
+    let result = cooklang::pantry::parse_lenient(&content);
+    let pantry_conf = result.output().ok_or(StatusCode::NOT_FOUND)?;
 
-    match item {
-        cooklang::pantry::PantryItem::Simple(name) => {
-            writeln!(output, "{} = true", toml_escape_key(name)).unwrap();
-        }
-        cooklang::pantry::PantryItem::WithAttributes(attrs) => {
-            // Build the value string
-            let value = if let Some(qty) = &attrs.quantity {
-                format!("\"{}\"", qty.replace('"', "\\\""))
-            } else if attrs.bought.is_some() || attrs.expire.is_some() || attrs.low.is_some() {
-                // If there are other attributes but no quantity, use a table
-                let mut parts = Vec::new();
-                if let Some(bought) = &attrs.bought {
-                    parts.push(format!("bought = \"{}\"", bought.replace('"', "\\\"")));
-                }
-                if let Some(expire) = &attrs.expire {
-                    parts.push(format!("expire = \"{}\"", expire.replace('"', "\\\"")));
-                }
-                if let Some(low) = &attrs.low {
-                    parts.push(format!("low = \"{}\"", low.replace('"', "\\\"")));
-                }
-                format!("{{ {} }}", parts.join(", "))
-            } else {
-                "true".to_string()
-            };
-
-            writeln!(output, "{} = {}", toml_escape_key(&attrs.name), value).unwrap();
-        }
-    }
-}
+    let items = expiring_items(pantry_conf, today, within_days, query.include_unknown);
 
-fn toml_escape_key(key: &str) -> String {
-    // If the key contains special characters or spaces, quote it
-    if key.contains(' ') || key.contains('.') || key.contains('[') || key.contains(']') {
-        format!("\"{}\"", key.replace('"', "\\\""))
-    } else {
-        key.to_string()
-    }
+    Ok(Json(ExpiringResponse { within_days, items }))
 }
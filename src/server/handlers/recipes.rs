@@ -1,23 +1,70 @@
-use crate::{server::AppState, util::PARSER};
+use crate::{
+    server::{stream, AppState},
+    util::{
+        ai,
+        duplicate::{duplicate, duplicate_path},
+        metadata_cache::{collection_metadata, RecipeMetadata},
+        nutrition::{recipe_calories, NutritionDb},
+        scaling, select_parts,
+        source::Source,
+        step_quantities::{step_quantities, StepQuantities},
+        temperature::{self, TemperatureUnit},
+        tree_entries, RecipePart, PARSER,
+    },
+};
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
+    response::Response,
     Json,
 };
 use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
-use cooklang_find;
+use cooklang_find::{self, RecipeEntry, RecipeTree};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
 #[derive(Deserialize)]
 pub struct RecipeQuery {
-    scale: Option<f64>,
+    /// A factor, or the name of one of the recipe's scalings
+    scale: Option<String>,
+    /// Comma separated ingredients scaled on their own, like "salt,yeast:1.5"
+    pin: Option<String>,
+    target_calories: Option<f64>,
+    tolerance: Option<f64>,
+    /// Comma separated parts of the recipe to include, like "ingredients,steps"
+    fields: Option<String>,
+    /// Add the ingredients each step uses, with their amounts
+    #[serde(default)]
+    step_quantities: bool,
+    /// Convert temperatures to celsius or fahrenheit
+    temperature: Option<TemperatureUnit>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct SearchQuery {
     q: String,
+    /// Only recipes with at most this many calories per serving
+    max_calories: Option<f64>,
+    /// `calories` for the lightest first
+    sort: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListingQuery {
+    /// Only recipes with at most this many calories per serving
+    max_calories: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RandomQuery {
+    /// Comma separated tags the recipe must have
+    tag: Option<String>,
+    /// Maximum total time, e.g. "45m" or "1h"
+    max_time: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,6 +73,16 @@ pub struct SaveRecipeRequest {
     content: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DuplicateRecipeRequest {
+    /// Path of the recipe to copy, in the collection
+    recipe: String,
+    title: String,
+    /// Remove the source and author from the copy
+    #[serde(default)]
+    clear_source: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PlainTextRecipeRequest {
     title: Option<String>,
@@ -44,40 +101,199 @@ fn check_path(p: &str) -> Result<(), StatusCode> {
     Ok(())
 }
 
+/// A scale given as a factor or by the name of one of the recipe's scalings
+fn resolve_scale(state: &AppState, path: &str, scale: &str) -> Result<f64, StatusCode> {
+    let entry = crate::util::get_recipe(&state.base_path, path)
+        .map_err(|e| super::lookup_error(path, e))?;
+    scaling::resolve(&entry, scale).map_err(|e| {
+        tracing::error!("{e:#}");
+        StatusCode::BAD_REQUEST
+    })
+}
+
+/// Characters of the description kept for the listing
+const EXCERPT_LENGTH: usize = 160;
+
+/// A directory or recipe of the listing
+///
+/// Like the recipe tree, with what a recipe card shows about each recipe,
+/// so clients don't need to fetch every recipe.
+#[derive(Serialize)]
+struct ListingNode {
+    name: String,
+    path: Utf8PathBuf,
+    recipe: Option<RecipeEntry>,
+    metadata: Option<ListingMetadata>,
+    children: BTreeMap<String, ListingNode>,
+}
+
+#[derive(Serialize)]
+struct ListingMetadata {
+    title: Option<String>,
+    tags: Vec<Arc<str>>,
+    /// Minutes
+    total_time: Option<u32>,
+    servings: Option<String>,
+    has_image: bool,
+    /// The start of the description
+    description: Option<String>,
+    /// Whether the recipe can't be parsed
+    has_error: bool,
+}
+
+impl ListingNode {
+    fn new(tree: RecipeTree, metadata: &mut HashMap<Utf8PathBuf, Arc<RecipeMetadata>>) -> Self {
+        let recipe_metadata = tree.recipe.as_ref().map(|entry| {
+            let cached = entry.path().and_then(|path| metadata.remove(path));
+            let cached = cached.as_deref();
+            ListingMetadata {
+                title: cached.and_then(|m| m.title.clone()),
+                tags: cached.map(|m| m.tags.clone()).unwrap_or_default(),
+                total_time: cached.and_then(|m| m.total_time),
+                servings: cached.and_then(|m| m.servings.clone()),
+                has_image: entry.title_image().is_some(),
+                description: cached.and_then(|m| m.description.as_deref()).map(excerpt),
+                has_error: cached.is_some_and(|m| m.error.is_some()),
+            }
+        });
+        Self {
+            name: tree.name,
+            path: tree.path,
+            recipe: tree.recipe,
+            metadata: recipe_metadata,
+            children: tree
+                .children
+                .into_iter()
+                .map(|(name, child)| (name, ListingNode::new(child, metadata)))
+                .collect(),
+        }
+    }
+}
+
+fn nutrition_db(state: &AppState) -> Result<NutritionDb, StatusCode> {
+    NutritionDb::load(state.nutrition_path.as_deref()).map_err(|e| {
+        tracing::error!("Failed to load nutrition database: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Remove the recipes with more calories per serving than `max`, or none
+/// known, and directories left empty
+fn keep_within_calories(tree: &mut RecipeTree, db: &NutritionDb, max: f64) {
+    if tree
+        .recipe
+        .as_ref()
+        .is_some_and(|entry| !recipe_calories(entry, db).is_some_and(|c| c <= max))
+    {
+        tree.recipe = None;
+    }
+    for child in tree.children.values_mut() {
+        keep_within_calories(child, db, max);
+    }
+    tree.children
+        .retain(|_, child| child.recipe.is_some() || !child.children.is_empty());
+}
+
+/// The first [`EXCERPT_LENGTH`] characters of a description, cut at a word
+fn excerpt(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() <= EXCERPT_LENGTH {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(EXCERPT_LENGTH).collect();
+    let cut = cut
+        .rsplit_once(char::is_whitespace)
+        .map_or(cut.as_str(), |(start, _)| start);
+    format!(
+        "{}…",
+        cut.trim_end_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace())
+    )
+}
+
 pub async fn all_recipes(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let recipes = cooklang_find::build_tree(&state.base_path).map_err(|e| {
+    Query(query): Query<ListingQuery>,
+) -> Result<Response, StatusCode> {
+    let mut recipes = crate::util::walk::build_tree(&state.base_path).map_err(|e| {
         tracing::error!("Failed to build recipe tree: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    if let Some(max) = query.max_calories {
+        let db = nutrition_db(&state)?;
+        keep_within_calories(&mut recipes, &db, max);
+    }
 
-    let recipes = serde_json::to_value(recipes).map_err(|e| {
-        tracing::error!("Failed to serialize recipes: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let mut metadata = collection_metadata(&state.base_path, tree_entries(&recipes))
+        .into_iter()
+        .filter_map(|(entry, metadata)| Some((entry.path()?.to_path_buf(), metadata)))
+        .collect();
+    let listing = ListingNode::new(recipes, &mut metadata);
 
-    Ok(Json(recipes))
+    // Streamed, big collections would otherwise be in memory several times
+    Ok(stream::json(listing))
 }
 
 pub async fn recipe(
     Path(path): Path<String>,
     State(state): State<Arc<AppState>>,
     Query(query): Query<RecipeQuery>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> axum::response::Result<Json<serde_json::Value>> {
     check_path(&path)?;
 
-    let entry = cooklang_find::get_recipe(vec![&state.base_path], &Utf8PathBuf::from(&path))
-        .map_err(|_| {
-            tracing::error!("Recipe not found: {path}");
-            StatusCode::NOT_FOUND
-        })?;
+    // Wildcard routes can't have a suffix, so sub-resources are dispatched here
+    if let Some(recipe_path) = path.strip_suffix("/nutrition") {
+        let scale = match &query.scale {
+            Some(scale) => Some(resolve_scale(&state, recipe_path, scale)?),
+            None => None,
+        };
+        return super::nutrition::recipe_nutrition(
+            recipe_path,
+            &state,
+            scale,
+            query.target_calories,
+            query.tolerance,
+        );
+    }
 
-    let recipe =
-        crate::util::parse_recipe_from_entry(&entry, query.scale.unwrap_or(1.0)).map_err(|e| {
-            tracing::error!("Failed to parse recipe: {e}");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let parts = match &query.fields {
+        Some(fields) => RecipePart::parse_list(fields).map_err(|e| {
+            tracing::error!("Invalid fields for {path}: {e}");
+            StatusCode::BAD_REQUEST
+        })?,
+        None => Vec::new(),
+    };
+
+    let entry = crate::util::get_recipe(&state.base_path, &path)
+        .map_err(|e| super::lookup_error(&path, e))?;
+
+    let scale = match &query.scale {
+        Some(scale) => scaling::resolve(&entry, scale).map_err(|e| {
+            tracing::error!("{e:#}");
+            StatusCode::BAD_REQUEST
+        })?,
+        None => scaling::default_servings_factor(&entry).unwrap_or(1.0),
+    };
+
+    let overrides = match &query.pin {
+        Some(pin) => scaling::parse_overrides(pin).map_err(|e| {
+            tracing::error!("Invalid pin for {path}: {e:#}");
+            StatusCode::BAD_REQUEST
+        })?,
+        None => Vec::new(),
+    };
+
+    let mut recipe = crate::util::parse_recipe_from_entry(&entry, scale)
+        .map_err(|e| super::recipe_error(&path, e))?;
+    let pinned = if overrides.is_empty() {
+        Vec::new()
+    } else {
+        let original = crate::util::parse_recipe_from_entry(&entry, 1.0)
+            .map_err(|e| super::recipe_error(&path, e))?;
+        scaling::apply_overrides(Arc::make_mut(&mut recipe), &original, &overrides)
+    };
+    if let Some(unit) = query.temperature {
+        temperature::convert_recipe(Arc::make_mut(&mut recipe), unit);
+    }
 
     // Get the image path if available
     let image_path = entry.title_image().clone().and_then(|img_path| {
@@ -111,6 +327,8 @@ pub async fn recipe(
         #[serde(flatten)]
         recipe: Arc<cooklang::Recipe>,
         grouped_ingredients: Vec<serde_json::Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        step_quantities: Option<Vec<StepQuantities>>,
     }
 
     let grouped_ingredients = recipe
@@ -119,36 +337,58 @@ pub async fn recipe(
         .map(|entry| {
             serde_json::json!({
                 "index": entry.index,
+                // Formatted for the configured locale, like "1,5 EL"
+                "display": crate::util::format::display_grouped(&entry.quantity),
                 "quantities": entry.quantity.into_vec()
             })
         })
         .collect();
 
+    let scalings = scaling::recipe_presets(&recipe);
+    let source = Source::of(&recipe);
+    let step_quantities = query
+        .step_quantities
+        .then(|| step_quantities(&recipe, PARSER.converter()));
+
     let api_recipe = ApiRecipe {
         recipe,
         grouped_ingredients,
+        step_quantities,
     };
 
+    let api_recipe = serde_json::to_value(api_recipe).map_err(|e| {
+        tracing::error!("Failed to serialize recipe {path}: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // How often it was cooked, null when never
+    let cooked = entry
+        .path()
+        .and_then(|p| p.strip_prefix(&state.base_path).ok())
+        .and_then(|relative| {
+            crate::util::cooked::cooked_history(&state.base_path, Some(&state.db))
+                .map_err(|e| tracing::warn!("Failed to read the cooked log: {e:#}"))
+                .ok()?
+                .remove(relative.as_str())
+        });
+
     let value = serde_json::json!({
-        "recipe": api_recipe,
+        "recipe": select_parts(api_recipe, &parts),
         "image": image_path,
-        "scale": query.scale.unwrap_or(1.0),
+        "scale": scale,
+        // Ingredients scaled by their own factor instead
+        "pinned": pinned,
+        // Named factors from the recipe's `scalings` metadata
+        "scalings": scalings,
+        "cooked": cooked,
+        // `source` metadata, read into a website or book
+        "source": source,
         // TODO: add more metadata if needed
     });
 
     Ok(Json(value))
 }
 
-pub async fn reload() -> Result<Json<serde_json::Value>, StatusCode> {
-    // Since the server reads from disk on each request, there's no cache to clear.
-    // This endpoint just returns success to indicate the reload was processed.
-    tracing::info!("Reload requested - recipes will be refreshed from disk on next request");
-    Ok(Json(serde_json::json!({
-        "status": "success",
-        "message": "Recipes will be refreshed from disk on next request"
-    })))
-}
-
 pub async fn save_recipe(
     State(state): State<Arc<AppState>>,
     Json(request): Json<SaveRecipeRequest>,
@@ -169,12 +409,14 @@ pub async fn save_recipe(
 
     // Create full path
     let filepath = state.base_path.join(&filename);
-    
+    state.check_writable(&filepath)?;
+
     // Save file
     fs::write(&filepath, &request.content).map_err(|e| {
         tracing::error!("Failed to write recipe file: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    state.file_changed(&filepath);
 
     // Return success with file info
     Ok(Json(serde_json::json!({
@@ -184,24 +426,98 @@ pub async fn save_recipe(
     })))
 }
 
+/// Copy a recipe under a new title, next to the original
+///
+/// 409 if a recipe with the name of the copy exists already.
+pub async fn duplicate_recipe(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<DuplicateRecipeRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    check_path(&request.recipe)?;
+    let entry = crate::util::get_recipe(&state.base_path, &request.recipe)
+        .map_err(|e| super::lookup_error(&request.recipe, e))?;
+    let path = entry.path().ok_or(StatusCode::NOT_FOUND)?;
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        tracing::error!("Failed to read {path}: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let copy = duplicate(&content, &request.title, request.clear_source).map_err(|e| {
+        tracing::error!("Failed to duplicate {path}: {e:#}");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let out = duplicate_path(path, &request.title);
+    state.check_writable(&out)?;
+    if out.exists() {
+        tracing::error!("{out} already exists");
+        return Err(StatusCode::CONFLICT);
+    }
+    std::fs::write(&out, copy).map_err(|e| {
+        tracing::error!("Failed to write {out}: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    state.file_changed(&out);
+
+    let relative = out.strip_prefix(&state.base_path).unwrap_or(&out);
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "path": relative,
+            "title": request.title.trim(),
+        })),
+    ))
+}
+
 pub async fn search(
     State(state): State<Arc<AppState>>,
     Query(query): Query<SearchQuery>,
 ) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    let recipes = cooklang_find::search(&state.base_path, &query.q).map_err(|e| {
+    let by_calories = match query.sort.as_deref() {
+        None | Some("relevance") => false,
+        Some("calories") => true,
+        Some(other) => {
+            tracing::error!("Unknown search order: {other}");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    };
+    let recipes = crate::util::walk::search(&state.base_path, &query.q).map_err(|e| {
         tracing::error!("Failed to search recipes: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    let mut recipes: Vec<(RecipeEntry, Option<f64>)> =
+        recipes.into_iter().map(|recipe| (recipe, None)).collect();
+    let with_calories = by_calories || query.max_calories.is_some();
+    if with_calories {
+        let db = nutrition_db(&state)?;
+        for (recipe, calories) in &mut recipes {
+            *calories = recipe_calories(recipe, &db);
+        }
+        if let Some(max) = query.max_calories {
+            recipes.retain(|(_, calories)| calories.is_some_and(|c| c <= max));
+        }
+        if by_calories {
+            // Unknown calories last
+            recipes.sort_by(|(_, a), (_, b)| match (a, b) {
+                (Some(a), Some(b)) => a.total_cmp(b),
+                _ => b.is_some().cmp(&a.is_some()),
+            });
+        }
+    }
+
     let results = recipes
         .into_iter()
-        .filter_map(|recipe| {
+        .filter_map(|(recipe, calories)| {
             recipe.path().map(|path| {
                 let relative_path = path.strip_prefix(&state.base_path).unwrap_or(path);
-                serde_json::json!({
+                let mut result = serde_json::json!({
                     "name": recipe.name(),
                     "path": relative_path.to_string()
-                })
+                });
+                if with_calories {
+                    result["calories"] = serde_json::json!(calories);
+                }
+                result
             })
         })
         .collect();
@@ -209,73 +525,84 @@ pub async fn search(
     Ok(Json(results))
 }
 
+pub async fn random(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RandomQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let max_time = query
+        .max_time
+        .as_deref()
+        .map(crate::util::random::parse_minutes)
+        .transpose()
+        .map_err(|e| {
+            tracing::error!("Invalid max_time: {e}");
+            StatusCode::BAD_REQUEST
+        })?;
+    let filter = crate::util::random::RandomFilter {
+        tags: query
+            .tag
+            .iter()
+            .flat_map(|t| t.split(','))
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        max_time,
+    };
+
+    let entry = crate::util::random::pick_random(&state.base_path, &filter)
+        .map_err(|e| {
+            tracing::error!("Failed to pick a random recipe: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let path = entry
+        .path()
+        .map(|p| p.strip_prefix(&state.base_path).unwrap_or(p).to_string());
+
+    Ok(Json(serde_json::json!({
+        "name": entry.name(),
+        "path": path,
+        "tags": entry.tags(),
+    })))
+}
+
 pub async fn ai_convert(
     State(state): State<Arc<AppState>>,
     Json(request): Json<PlainTextRecipeRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     use std::fs;
 
+    state.check_writable(&state.base_path)?;
     // Validate input
     if request.content.is_empty() {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    // Get Claude API key from environment
-    let api_key = std::env::var("CLAUDE_API_KEY").map_err(|_| {
-        tracing::error!("CLAUDE_API_KEY not set");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    // Create Claude client
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("anthropic-version", "2023-06-01")
-        .header("x-api-key", api_key)
-        .json(&serde_json::json!({
-            "model": "claude-3-sonnet-20240229",
-            "max_tokens": 1500,
-            "temperature": 0.1,
-            "messages": [{
-                "role": "user",
-                "content": format!(
-                    "Convert this recipe to cooklang format (https://cooklang.org/).\n\
-                    Include metadata section with title and servings if available.\n\
-                    Mark ingredients with @ and cookware with #.\n\
-                    Example format:\n\
-                    ---\n\
-                    title: \"Classic Chocolate Chip Cookies\"\n\
-                    servings: \"24 cookies\"\n\
-                    ---\n\
-                    Preheat #oven{{}} to 375°F.\n\
-                    In a #large bowl{{}}, cream together @butter{{1%cup}} and @sugar{{1%cup}}.\n\
-                    \n\
-                    Here's the recipe to convert:\n\
-                    {}\n\
-                    Return only the cooklang recipe text, no other text.",
-                    request.content
-                )
-            }]
-        }))
-        .send()
+    let prompt = format!(
+        "Convert this recipe to cooklang format (https://cooklang.org/).\n\
+        Include metadata section with title and servings if available.\n\
+        Mark ingredients with @ and cookware with #.\n\
+        Example format:\n\
+        ---\n\
+        title: \"Classic Chocolate Chip Cookies\"\n\
+        servings: \"24 cookies\"\n\
+        ---\n\
+        Preheat #oven{{}} to 375°F.\n\
+        In a #large bowl{{}}, cream together @butter{{1%cup}} and @sugar{{1%cup}}.\n\
+        \n\
+        Here's the recipe to convert:\n\
+        {}\n\
+        Return only the cooklang recipe text, no other text.",
+        request.content
+    );
+    let reply = ai::complete(&state.ai, &prompt, None, 1500)
         .await
         .map_err(|e| {
-            tracing::error!("Failed to call Claude API: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    // Parse response
-    let claude_response: serde_json::Value = response.json().await.map_err(|e| {
-        tracing::error!("Failed to parse Claude response: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let cooklang_text = claude_response["content"][0]["text"]
-        .as_str()
-        .ok_or_else(|| {
-            tracing::error!("Invalid Claude response format");
+            tracing::error!("Failed to convert the recipe: {e:#}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+    let cooklang_text = ai::strip_code_fence(&reply);
 
     // Generate safe filename from title or timestamp
     let filename = if let Some(title) = request.title {
@@ -295,6 +622,7 @@ pub async fn ai_convert(
         tracing::error!("Failed to write recipe file: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    state.file_changed(&filepath);
 
     // Return success with file info
     Ok(Json(serde_json::json!({
@@ -0,0 +1,9 @@
+//! Schemas for editors, the same `cook schema` prints
+
+use axum::Json;
+
+use crate::util::schema::metadata_schema;
+
+pub async fn metadata() -> Json<serde_json::Value> {
+    Json(metadata_schema())
+}
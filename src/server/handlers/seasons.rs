@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+    server::AppState,
+    util::{
+        metadata_cache::collection_metadata,
+        seasons::{current_month, Seasonality, Seasons},
+        tree_entries,
+        walk::build_tree,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SeasonalQuery {
+    /// Number of recipes to list (default: 10)
+    limit: Option<usize>,
+    /// Month from 1 to 12 (default: this month)
+    month: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct SeasonalRecipe {
+    path: String,
+    name: String,
+    /// Produce in season less produce out of it
+    score: i64,
+    #[serde(flatten)]
+    seasonality: Seasonality,
+}
+
+/// Recipes using produce that's in season, those using the most first
+///
+/// Produce out of season counts against a recipe. Recipes without any
+/// produce in season are left out.
+pub async fn seasonal(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SeasonalQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let month = query.month.unwrap_or_else(current_month);
+    if !(1..=12).contains(&month) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let seasons = Seasons::load(state.seasons_path.as_deref(), state.hemisphere).map_err(|e| {
+        tracing::error!("Failed to load seasons: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let tree = build_tree(&state.base_path).map_err(|e| {
+        tracing::error!("Failed to build recipe tree: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut recipes: Vec<SeasonalRecipe> =
+        collection_metadata(&state.base_path, tree_entries(&tree))
+            .into_iter()
+            .filter_map(|(entry, metadata)| {
+                let seasonality =
+                    seasons.check(metadata.ingredients.iter().map(|name| &**name), month);
+                if seasonality.in_season.is_empty() {
+                    return None;
+                }
+                let path = entry.path()?;
+                let path = path.strip_prefix(&state.base_path).unwrap_or(path);
+                Some(SeasonalRecipe {
+                    name: path.file_stem().unwrap_or(path.as_str()).to_string(),
+                    path: path.to_string(),
+                    score: seasonality.score(),
+                    seasonality,
+                })
+            })
+            .collect();
+    // Stable, so recipes that score the same stay in the collection's order
+    recipes.sort_by(|a, b| {
+        b.score.cmp(&a.score).then(
+            b.seasonality
+                .in_season
+                .len()
+                .cmp(&a.seasonality.in_season.len()),
+        )
+    });
+    recipes.truncate(query.limit.unwrap_or(10));
+
+    Ok(Json(
+        serde_json::json!({ "month": month, "recipes": recipes }),
+    ))
+}
@@ -1,14 +1,28 @@
 use crate::server::{
-    shopping_list_store::{ShoppingListItem, ShoppingListStore},
+    share_store::{SharedCategory, SharedItem, SharedList},
+    shopping_list_store::{ExtraItem, ShoppingListItem, ShoppingListStore},
     AppState,
 };
-use crate::util::{extract_ingredients, PARSER};
-use axum::{extract::State, http::StatusCode, Json};
-use cooklang::ingredient_list::IngredientList;
+use crate::shopping_list::{GroupBy, ItemOrder, ListSort};
+use crate::util::{
+    aliases::{self, AppliedAlias},
+    cookware::{collect_cookware, CookwareItem, CookwareList},
+    extract_ingredients,
+    format::display_grouped,
+    pantry::apply_aliases,
+    PARSER,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use cooklang::{ingredient_list::IngredientList, quantity::GroupedQuantity};
 use serde::Deserialize;
 use serde_json;
 use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize)]
 pub struct RecipeRequest {
@@ -16,19 +30,82 @@ pub struct RecipeRequest {
     scale: Option<f64>,
 }
 
+impl RecipeRequest {
+    fn with_scale(&self) -> String {
+        match self.scale {
+            Some(scale) => format!("{}:{}", self.recipe, scale),
+            None => self.recipe.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShoppingListQuery {
+    group_by: Option<GroupBy>,
+    /// Order of the items in each category
+    sort: Option<ListSort>,
+}
+
 pub async fn shopping_list(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<ShoppingListQuery>,
     axum::extract::Json(payload): axum::extract::Json<Vec<RecipeRequest>>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
+    if query.group_by == Some(GroupBy::Recipe) {
+        return by_recipe(&state, payload);
+    }
+
+    let cookware = cookware(&state, &payload)?;
+    let (categories, pantry_items, applied_aliases) =
+        categorized_list(&state, &payload, query.sort.unwrap_or_default())?;
+
+    // Build the response
+    let mut shopping_categories = Vec::new();
+
+    for (category, items) in categories {
+        let mut shopping_items = Vec::new();
+
+        for (name, qty) in items {
+            let item_json = serde_json::json!({
+                "name": name,
+                "quantities": qty.into_vec()
+            });
+            shopping_items.push(item_json);
+        }
+
+        if !shopping_items.is_empty() {
+            shopping_categories.push(serde_json::json!({
+                "category": category,
+                "items": shopping_items
+            }));
+        }
+    }
+
+    let json_value = serde_json::json!({
+        "categories": shopping_categories,
+        "pantry_items": pantry_items,
+        "aliases": applied_aliases,
+        "cookware": cookware
+    });
+    Ok(Json(json_value))
+}
+
+/// Ingredients of a shopping list by aisle category
+type Categories = Vec<(String, Vec<(String, GroupedQuantity)>)>;
+
+/// The ingredients of the recipes without what's in the pantry, by aisle
+/// category and sorted within them, the names of the pantry items that were
+/// left out and the aliases that were merged into their name
+fn categorized_list(
+    state: &AppState,
+    payload: &[RecipeRequest],
+    sort: ListSort,
+) -> Result<(Categories, Vec<String>, Vec<AppliedAlias>), StatusCode> {
     let mut list = IngredientList::new();
     let mut seen = BTreeMap::new();
 
     for entry in payload {
-        let recipe_with_scale = if let Some(scale) = entry.scale {
-            format!("{}:{}", entry.recipe, scale)
-        } else {
-            entry.recipe
-        };
+        let recipe_with_scale = entry.with_scale();
 
         extract_ingredients(
             &recipe_with_scale,
@@ -43,6 +120,7 @@ pub async fn shopping_list(
             StatusCode::BAD_REQUEST
         })?;
     }
+    let (list, applied_aliases) = aliases::apply(list, PARSER.converter());
 
     // Load aisle configuration with lenient parsing
     let aisle_content = if let Some(path) = &state.aisle_path {
@@ -86,7 +164,10 @@ pub async fn shopping_list(
                     }
                 }
 
-                result.output().cloned()
+                result.output().cloned().map(|mut pantry| {
+                    apply_aliases(&mut pantry);
+                    pantry
+                })
             }
             Err(e) => {
                 tracing::warn!("Failed to read pantry file from {:?}: {}", path, e);
@@ -130,35 +211,73 @@ pub async fn shopping_list(
         list
     };
 
-    let categories = final_list.categorize(&aisle);
-
-    // Build the response
-    let mut shopping_categories = Vec::new();
+    let entries: Vec<_> = payload
+        .iter()
+        .map(|entry| (entry.with_scale(), state.base_path.clone()))
+        .collect();
+    let categories = ItemOrder::new(sort, &entries).categorize(final_list, &aisle);
+    Ok((categories, pantry_items, applied_aliases))
+}
 
-    for (category, items) in categories {
-        let mut shopping_items = Vec::new();
+/// The cookware checklist of the recipes, like `cook cookware`
+fn cookware(state: &AppState, payload: &[RecipeRequest]) -> Result<Vec<CookwareItem>, StatusCode> {
+    let mut list = CookwareList::new();
+    for entry in payload {
+        collect_cookware(
+            &entry.recipe,
+            &mut list,
+            &mut Vec::new(),
+            &state.base_path,
+            false,
+        )
+        .map_err(|e| {
+            tracing::error!("Error processing recipe: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+    }
+    Ok(list.into_items())
+}
 
-        for (name, qty) in items {
-            let item_json = serde_json::json!({
-                "name": name,
-                "quantities": qty.into_vec()
-            });
-            shopping_items.push(item_json);
-        }
+/// Every recipe with its own scaled ingredients, without the pantry
+fn by_recipe(
+    state: &AppState,
+    payload: Vec<RecipeRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut recipes = Vec::new();
+    for entry in payload {
+        let mut list = IngredientList::new();
+        extract_ingredients(
+            &entry.with_scale(),
+            &mut list,
+            &mut BTreeMap::new(),
+            &state.base_path,
+            PARSER.converter(),
+            false,
+        )
+        .map_err(|e| {
+            tracing::error!("Error processing recipe: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+        let (list, _) = aliases::apply(list, PARSER.converter());
 
-        if !shopping_items.is_empty() {
-            shopping_categories.push(serde_json::json!({
-                "category": category,
-                "items": shopping_items
-            }));
-        }
+        let items: Vec<_> = list
+            .into_iter()
+            .map(|(name, qty)| {
+                serde_json::json!({
+                    "name": name,
+                    "quantities": qty.into_vec()
+                })
+            })
+            .collect();
+        let cookware = cookware(state, std::slice::from_ref(&entry))?;
+        recipes.push(serde_json::json!({
+            "recipe": entry.recipe,
+            "scale": entry.scale.unwrap_or(1.0),
+            "items": items,
+            "cookware": cookware
+        }));
     }
-
-    let json_value = serde_json::json!({
-        "categories": shopping_categories,
-        "pantry_items": pantry_items
-    });
-    Ok(Json(json_value))
+    Ok(Json(serde_json::json!({ "recipes": recipes })))
 }
 
 pub async fn get_shopping_list_items(
@@ -216,6 +335,36 @@ pub async fn remove_from_shopping_list(
     Ok(StatusCode::OK)
 }
 
+/// Items on the shopping list that aren't from a recipe
+pub async fn get_extra_items(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ExtraItem>>, StatusCode> {
+    let store = ShoppingListStore::new(&state.base_path);
+    let items = store.load_extra().map_err(|e| {
+        tracing::error!("Failed to load shopping list: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(items))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoveExtraRequest {
+    pub name: String,
+}
+
+pub async fn remove_extra_item(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RemoveExtraRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let store = ShoppingListStore::new(&state.base_path);
+    store.remove_extra(&payload.name).map_err(|e| {
+        tracing::error!("Failed to remove from shopping list: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(StatusCode::OK)
+}
+
 pub async fn clear_shopping_list(
     State(state): State<Arc<AppState>>,
 ) -> Result<StatusCode, StatusCode> {
@@ -227,3 +376,106 @@ pub async fn clear_shopping_list(
 
     Ok(StatusCode::OK)
 }
+
+/// How long a shared list stays up unless asked otherwise
+const SHARE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The longest a shared list can stay up
+const MAX_SHARE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Deserialize)]
+pub struct ShareQuery {
+    /// How long the link works, like "2h" (default: 24h, at most 7 days)
+    ttl: Option<String>,
+}
+
+/// Share the current shopping list with a public link
+///
+/// The link shows the list as it is now, later changes aren't shared.
+pub async fn share_shopping_list(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ShareQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let ttl = match &query.ttl {
+        Some(ttl) => humantime::parse_duration(ttl).map_err(|e| {
+            tracing::error!("Invalid ttl {ttl}: {e}");
+            StatusCode::BAD_REQUEST
+        })?,
+        None => SHARE_TTL,
+    };
+    if ttl.is_zero() || ttl > MAX_SHARE_TTL {
+        tracing::error!("Shared lists can stay up for at most 7 days");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let store = ShoppingListStore::new(&state.base_path);
+    let (items, extra) = store
+        .load()
+        .and_then(|items| Ok((items, store.load_extra()?)))
+        .map_err(|e| {
+            tracing::error!("Failed to load shopping list: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if items.is_empty() && extra.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let payload: Vec<RecipeRequest> = items
+        .iter()
+        .map(|item| RecipeRequest {
+            recipe: item.path.clone(),
+            scale: Some(item.scale),
+        })
+        .collect();
+    let (categories, _, _) = categorized_list(&state, &payload, ListSort::default())?;
+    let mut categories: Vec<SharedCategory> = categories
+        .into_iter()
+        .filter(|(_, items)| !items.is_empty())
+        .map(|(name, items)| SharedCategory {
+            name,
+            items: items
+                .into_iter()
+                .map(|(name, quantity)| SharedItem {
+                    quantity: if quantity.is_empty() {
+                        String::new()
+                    } else {
+                        display_grouped(&quantity)
+                    },
+                    name,
+                })
+                .collect(),
+        })
+        .collect();
+    if !extra.is_empty() {
+        categories.push(SharedCategory {
+            name: "Other items".to_string(),
+            items: extra
+                .into_iter()
+                .map(|item| SharedItem {
+                    name: item.name,
+                    quantity: item.quantity,
+                })
+                .collect(),
+        });
+    }
+
+    let expires_at = chrono::Local::now()
+        + chrono::Duration::from_std(ttl).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let list = SharedList {
+        recipes: items.into_iter().map(|item| item.name).collect(),
+        categories,
+        expires_at: expires_at.to_rfc3339(),
+    };
+    let token = state.shares.create(list, ttl).map_err(|e| {
+        tracing::error!("Failed to share the shopping list: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let path = format!("/share/{token}");
+    let url = format!("{}{path}", state.public_url);
+    Ok(Json(serde_json::json!({
+        "token": token,
+        "path": path,
+        "url": url,
+        "expires_at": expires_at.to_rfc3339(),
+    })))
+}
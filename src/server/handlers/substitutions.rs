@@ -0,0 +1,26 @@
+use crate::{server::AppState, util::substitutions::Substitutions};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use std::sync::Arc;
+
+/// Substitutes for an ingredient, best first, served at
+/// `/api/v1/substitutions/{ingredient}`
+///
+/// Unknown ingredients have no substitutes rather than being an error.
+pub async fn get_substitutions(
+    State(state): State<Arc<AppState>>,
+    Path(ingredient): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let substitutions = Substitutions::load(state.substitutions_path.as_deref()).map_err(|e| {
+        tracing::error!("Failed to load substitutions: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(serde_json::json!({
+        "ingredient": ingredient,
+        "substitutes": substitutions.get(&ingredient),
+    })))
+}
@@ -0,0 +1,34 @@
+use crate::util::{pantry::Amount, units, PARSER};
+use axum::{extract::Query, http::StatusCode, Json};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ConvertQuery {
+    value: f64,
+    from: String,
+    to: String,
+    /// Lets volumes and weights convert through the ingredient's density
+    ingredient: Option<String>,
+}
+
+/// Convert an amount between units, served at `/api/v1/convert`
+///
+/// Uses the same converter as the rest of cook, so custom units from
+/// `units.toml` work too. Incompatible or unknown units give 422.
+pub async fn convert(
+    Query(query): Query<ConvertQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let amount = Amount::new(query.value, Some(query.from));
+    let value = units::convert(
+        &amount,
+        Some(&query.to),
+        query.ingredient.as_deref().unwrap_or_default(),
+        PARSER.converter(),
+    )
+    .ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    Ok(Json(serde_json::json!({
+        "value": value,
+        "unit": query.to,
+    })))
+}
@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::{view_store::RecipeViews, AppState};
+
+/// Most recipes listed at once
+const MAX_LIMIT: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct ViewsQuery {
+    /// Number of recipes to list (default: 10, at most 100)
+    limit: Option<usize>,
+}
+
+impl ViewsQuery {
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(10).min(MAX_LIMIT)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ViewedRecipe {
+    path: String,
+    name: String,
+    #[serde(flatten)]
+    views: RecipeViews,
+}
+
+/// The most viewed recipes, 404 unless the server tracks views
+pub async fn popular(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ViewsQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let store = state.views.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let limit = query.limit();
+    // Asks for more, recipes that were removed since are left out
+    Ok(recipes(
+        &state,
+        store.popular(limit.saturating_mul(2)),
+        limit,
+    ))
+}
+
+/// The recently viewed recipes, 404 unless the server tracks views
+pub async fn recent(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ViewsQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let store = state.views.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let limit = query.limit();
+    Ok(recipes(
+        &state,
+        store.recent(limit.saturating_mul(2)),
+        limit,
+    ))
+}
+
+fn recipes(
+    state: &AppState,
+    views: Vec<(String, RecipeViews)>,
+    limit: usize,
+) -> Json<serde_json::Value> {
+    let recipes: Vec<ViewedRecipe> = views
+        .into_iter()
+        .filter(|(path, _)| state.base_path.join(path).is_file())
+        .take(limit)
+        .map(|(path, views)| ViewedRecipe {
+            name: camino::Utf8Path::new(&path)
+                .file_stem()
+                .unwrap_or(&path)
+                .to_string(),
+            path,
+            views,
+        })
+        .collect();
+    Json(serde_json::json!({ "recipes": recipes }))
+}
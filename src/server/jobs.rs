@@ -0,0 +1,403 @@
+//! Long-running work the server does in the background
+//!
+//! Requests that would take too long to answer, like rebuilding the index or
+//! exporting the site, start a job and return at once. Jobs run as tokio
+//! tasks and keep their progress in the server's database, so
+//! `/api/v1/jobs` can report on them, and on the ones that finished, after a
+//! restart. Jobs that were running when the server stopped are marked as
+//! interrupted; they aren't started again.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{bail, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use rusqlite::{params, OptionalExtension, Row};
+use serde::Serialize;
+
+use super::db::Database;
+
+/// Finished jobs that are kept, older ones are forgotten
+const KEPT_JOBS: usize = 100;
+
+const COLUMNS: &str =
+    "id, kind, status, done, total, message, error, result, created_at, finished_at";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    /// Like "reindex" or "import"
+    pub kind: String,
+    pub status: JobStatus,
+    /// Steps done, out of `total` when that's known
+    pub done: u64,
+    pub total: Option<u64>,
+    /// What the job is doing
+    pub message: Option<String>,
+    pub error: Option<String>,
+    /// What the job made, once it succeeded
+    pub result: Option<serde_json::Value>,
+    /// RFC 3339
+    pub created_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    /// The server stopped while it was running
+    Interrupted,
+}
+
+impl JobStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Interrupted => "interrupted",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "running" => JobStatus::Running,
+            "succeeded" => JobStatus::Succeeded,
+            "failed" => JobStatus::Failed,
+            "cancelled" => JobStatus::Cancelled,
+            _ => JobStatus::Interrupted,
+        }
+    }
+}
+
+/// What a running job reports its progress with
+pub struct JobHandle {
+    id: String,
+    db: Arc<Database>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    /// Record that `done` of `total` steps are done
+    pub fn progress(&self, done: usize, total: usize, message: &str) {
+        let result = self.db.with(|conn| {
+            conn.execute(
+                "UPDATE jobs SET done = ?2, total = ?3, message = ?4 WHERE id = ?1",
+                params![self.id, done as u64, total as u64, message],
+            )
+        });
+        // Progress is only informative, the job goes on without it
+        if let Err(e) = result {
+            tracing::warn!("{e:#}");
+        }
+    }
+
+    /// Fail when the job was cancelled, jobs call this between steps
+    pub fn check(&self) -> Result<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            bail!("Cancelled");
+        }
+        Ok(())
+    }
+}
+
+/// The server's jobs
+pub struct Jobs {
+    db: Arc<Database>,
+    /// Cancellation flags of the running jobs
+    running: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+impl Jobs {
+    /// The jobs in the database, marking those left running as interrupted
+    pub fn new(db: Arc<Database>) -> Result<Self> {
+        let interrupted = db.with(|conn| {
+            conn.execute(
+                "UPDATE jobs SET status = ?1, finished_at = ?2 WHERE status = ?3",
+                params![
+                    JobStatus::Interrupted.as_str(),
+                    chrono::Local::now().to_rfc3339(),
+                    JobStatus::Running.as_str()
+                ],
+            )
+        })?;
+        if interrupted > 0 {
+            tracing::warn!("{interrupted} jobs were interrupted when the server stopped");
+        }
+        Ok(Self {
+            db,
+            running: Arc::default(),
+        })
+    }
+
+    /// Start a job, returning it as it is when it starts
+    ///
+    /// `work` gets the handle to report progress with and returns what the
+    /// job made, as JSON.
+    pub fn spawn<F, Fut>(&self, kind: &str, work: F) -> Result<Job>
+    where
+        F: FnOnce(JobHandle) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        let id: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(12)
+            .map(char::from)
+            .collect();
+        self.db.with(|conn| {
+            conn.execute(
+                "INSERT INTO jobs (id, kind, status, done, created_at)
+                 VALUES (?1, ?2, ?3, 0, ?4)",
+                params![
+                    id,
+                    kind,
+                    JobStatus::Running.as_str(),
+                    chrono::Local::now().to_rfc3339()
+                ],
+            )?;
+            conn.execute(
+                "DELETE FROM jobs WHERE status != ?1 AND rowid NOT IN
+                 (SELECT rowid FROM jobs ORDER BY rowid DESC LIMIT ?2)",
+                params![JobStatus::Running.as_str(), KEPT_JOBS as i64],
+            )
+        })?;
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.clone(), Arc::clone(&cancelled));
+        let handle = JobHandle {
+            id: id.clone(),
+            db: Arc::clone(&self.db),
+            cancelled: Arc::clone(&cancelled),
+        };
+        let (db, running, job_id, kind) = (
+            Arc::clone(&self.db),
+            Arc::clone(&self.running),
+            id.clone(),
+            kind.to_string(),
+        );
+        tokio::spawn(async move {
+            // On a task of its own, so a job that panics is marked as failed
+            // and not left running
+            let result = match tokio::spawn(async move { work(handle).await }).await {
+                Ok(result) => result,
+                Err(e) => Err(anyhow::Error::new(e).context("The job crashed")),
+            };
+            let (status, error, result) = match result {
+                Ok(value) => (JobStatus::Succeeded, None, Some(value.to_string())),
+                Err(_) if cancelled.load(Ordering::Relaxed) => (JobStatus::Cancelled, None, None),
+                Err(e) => {
+                    tracing::error!("The {kind} job {job_id} failed: {e:#}");
+                    (JobStatus::Failed, Some(format!("{e:#}")), None)
+                }
+            };
+            let saved = db.with(|conn| {
+                conn.execute(
+                    "UPDATE jobs SET status = ?2, error = ?3, result = ?4, finished_at = ?5
+                     WHERE id = ?1",
+                    params![
+                        job_id,
+                        status.as_str(),
+                        error,
+                        result,
+                        chrono::Local::now().to_rfc3339()
+                    ],
+                )
+            });
+            if let Err(e) = saved {
+                tracing::error!("{e:#}");
+            }
+            running
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&job_id);
+        });
+
+        self.get(&id)?
+            .ok_or_else(|| anyhow::anyhow!("The job {id} disappeared"))
+    }
+
+    /// The latest jobs, newest first
+    pub fn list(&self, limit: usize) -> Result<Vec<Job>> {
+        self.db.with(|conn| {
+            let mut statement = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM jobs ORDER BY rowid DESC LIMIT ?1"
+            ))?;
+            let jobs = statement.query_map([limit as i64], job)?;
+            jobs.collect()
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Job>> {
+        self.db.with(|conn| {
+            conn.query_row(
+                &format!("SELECT {COLUMNS} FROM jobs WHERE id = ?1"),
+                [id],
+                job,
+            )
+            .optional()
+        })
+    }
+
+    /// Ask a running job to stop, false when it isn't running
+    ///
+    /// The job stops at its next step, and is marked as cancelled then.
+    pub fn cancel(&self, id: &str) -> bool {
+        match self
+            .running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+        {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn job(row: &Row) -> rusqlite::Result<Job> {
+    let status: String = row.get(2)?;
+    let result: Option<String> = row.get(7)?;
+    Ok(Job {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        status: JobStatus::parse(&status),
+        done: row.get(3)?,
+        total: row.get(4)?,
+        message: row.get(5)?,
+        error: row.get(6)?,
+        result: result.and_then(|result| serde_json::from_str(&result).ok()),
+        created_at: row.get(8)?,
+        finished_at: row.get(9)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino::Utf8Path;
+    use std::time::Duration;
+
+    fn open(dir: &tempfile::TempDir) -> Arc<Database> {
+        let path = Utf8Path::from_path(dir.path()).unwrap().join("server.db");
+        Arc::new(Database::open(&path).unwrap())
+    }
+
+    /// The job once it stopped running
+    async fn finished(jobs: &Jobs, id: &str) -> Job {
+        for _ in 0..500 {
+            let job = jobs.get(id).unwrap().unwrap();
+            if job.status != JobStatus::Running {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("The job {id} is still running");
+    }
+
+    #[tokio::test]
+    async fn test_outcomes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let jobs = Jobs::new(open(&dir)).unwrap();
+
+        let job = jobs
+            .spawn("test", |_| async { Ok(serde_json::json!({"recipes": 2})) })
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Running);
+        let job = finished(&jobs, &job.id).await;
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!(job.result, Some(serde_json::json!({"recipes": 2})));
+        assert!(job.finished_at.is_some());
+
+        let job = jobs
+            .spawn("test", |_| async { bail!("No recipes") })
+            .unwrap();
+        let job = finished(&jobs, &job.id).await;
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.as_deref(), Some("No recipes"));
+
+        let job = jobs
+            .spawn("test", |handle| async move {
+                loop {
+                    handle.check()?;
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+            .unwrap();
+        assert!(jobs.cancel(&job.id));
+        let job = finished(&jobs, &job.id).await;
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert!(!jobs.cancel(&job.id));
+    }
+
+    #[tokio::test]
+    async fn test_panic() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let jobs = Jobs::new(open(&dir)).unwrap();
+        let job = jobs.spawn("test", |_| async { panic!("Broken") }).unwrap();
+        let job = finished(&jobs, &job.id).await;
+        assert_eq!(job.status, JobStatus::Failed);
+        assert!(job.error.unwrap().contains("The job crashed"));
+        assert!(!jobs.cancel(&job.id));
+    }
+
+    #[tokio::test]
+    async fn test_interrupted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = open(&dir);
+        let job = Jobs::new(Arc::clone(&db))
+            .unwrap()
+            .spawn("test", |_| std::future::pending())
+            .unwrap();
+
+        // The server starting again
+        let jobs = Jobs::new(db).unwrap();
+        let job = jobs.get(&job.id).unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Interrupted);
+        assert!(job.finished_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pruning() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = open(&dir);
+        db.with(|conn| {
+            for i in 0..KEPT_JOBS + 10 {
+                conn.execute(
+                    "INSERT INTO jobs (id, kind, status, done, created_at)
+                     VALUES (?1, 'test', 'succeeded', 0, '')",
+                    [format!("old{i}")],
+                )?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        let jobs = Jobs::new(db).unwrap();
+        let job = jobs.spawn("test", |_| std::future::pending()).unwrap();
+
+        let listed = jobs.list(usize::MAX).unwrap();
+        assert_eq!(listed.len(), KEPT_JOBS);
+        assert_eq!(listed[0].id, job.id);
+        assert!(jobs.get("old0").unwrap().is_none());
+        assert!(jobs
+            .get(&format!("old{}", KEPT_JOBS + 9))
+            .unwrap()
+            .is_some());
+    }
+}
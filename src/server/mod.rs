@@ -28,35 +28,76 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use crate::util::resolve_to_absolute_path;
+use crate::config::AiConfig;
+use crate::import::inbox::{Inbox, InboxEvent};
+use crate::util::{
+    lint::LintRules,
+    remote::{self, RemoteStore},
+    resolve_to_absolute_path,
+    seasons::Hemisphere,
+};
 use crate::Context;
 use anyhow::{bail, Context as _, Result};
 use axum::{
-    body::Body,
-    extract::Path,
-    http::{header, HeaderValue, Method, Response, StatusCode},
+    http::{header::HeaderName, HeaderValue, Method, StatusCode, Uri},
+    middleware,
+    response::{IntoResponse, Redirect, Response},
     routing::{get, post},
     Router,
 };
 use camino::Utf8PathBuf;
-use clap::Args;
-use rust_embed::RustEmbed;
-use std::{net::SocketAddr, sync::Arc};
+use clap::{Args, Subcommand};
+use db::Database;
+use jobs::Jobs;
+use share_store::ShareStore;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tower_http::{cors::CorsLayer, services::ServeDir};
 use tracing::{error, info};
+use view_store::ViewStore;
+pub use webdav::WebDavMode;
 
+mod assets;
+pub mod db;
 mod handlers;
-mod shopping_list_store;
+mod jobs;
+mod ping;
+mod scheduler;
+mod share_store;
+pub mod shopping_list_store;
+mod stream;
 mod templates;
 mod ui;
+mod view_store;
+mod webdav;
+
+/// Port the server listens on unless configured otherwise
+pub const DEFAULT_PORT: u16 = 9080;
+
+/// Version of the API served under [`API_PREFIX`]
+///
+/// Within a version endpoints and fields are only added, see "API Versions"
+/// in docs/server.md.
+pub const API_VERSION: u32 = 1;
+
+/// Where the current version of the API is served
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Response header with the API version
+const API_VERSION_HEADER: HeaderName = HeaderName::from_static("cook-api-version");
 
-// Embed static files at compile time
-#[derive(RustEmbed)]
-#[folder = "static/"]
-struct StaticFiles;
+/// Events kept for /api/v1/events clients that are slow to read them
+const EVENTS_BUFFERED: usize = 64;
 
 #[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct ServerArgs {
+    #[command(subcommand)]
+    command: Option<ServerCommand>,
+
     /// Root directory containing your recipe files
     ///
     /// The server will recursively scan this directory for .cook files
@@ -70,23 +111,118 @@ pub struct ServerArgs {
     /// By default, the server only accepts connections from localhost
     /// for security. Use this flag to allow access from other devices
     /// on your network. Be cautious when using this on public networks.
+    /// Can also be turned on with `server.host` in cook.toml.
     #[arg(long)]
     host: bool,
 
     /// Port number for the HTTP server
     ///
     /// The server will listen on this port. Make sure the port is not
-    /// already in use by another application.
-    #[arg(short = 'p', long, default_value_t = 9080)]
-    port: u16,
+    /// already in use by another application. Defaults to `server.port`
+    /// from cook.toml, or 9080.
+    #[arg(short = 'p', long)]
+    port: Option<u16>,
 
     /// Automatically open the web interface in your default browser
     ///
     /// When enabled, the server will launch your default web browser
-    /// and navigate to the server URL after startup.
+    /// and navigate to the server URL after startup. Can also be turned
+    /// on with `server.open` in cook.toml.
     // #[cfg(feature = "ui")]
     #[arg(long, default_value_t = false)]
     open: bool,
+
+    /// Count how often each recipe is viewed in the web interface
+    ///
+    /// The counts are kept in the server's database and served at
+    /// /api/v1/recipes/popular and /api/v1/recipes/recent. Can also be
+    /// turned on with `server.track_views` in cook.toml.
+    #[arg(long)]
+    track_views: bool,
+
+    /// Share the recipe directory over WebDAV at /dav
+    ///
+    /// Text editors and sync clients can then open and change the recipe
+    /// files directly. Files can only be opened unless it's
+    /// --webdav=read-write. Hidden files and the config directory aren't
+    /// shared. Can also be set with `server.webdav` in cook.toml.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "read-only"
+    )]
+    webdav: Option<WebDavMode>,
+
+    /// Password WebDAV clients log in with, under any user name
+    ///
+    /// Defaults to the admin token. A read-write share on other hosts than
+    /// this one needs one. Prefer the COOK_SERVER_WEBDAV_PASSWORD
+    /// environment variable, so the password isn't in the process list.
+    #[arg(long, value_name = "PASSWORD", hide_env_values = true)]
+    webdav_password: Option<String>,
+
+    /// Serve a collection stored in an S3 bucket or a zip archive
+    ///
+    /// s3://BUCKET[/PREFIX] is synced with the aws CLI, which also reads
+    /// the credentials and AWS_ENDPOINT_URL for S3-compatible services.
+    /// An http(s) URL of a .zip archive is downloaded. The server works on
+    /// a copy in a cache directory that's refreshed regularly.
+    #[arg(long, value_name = "URL", conflicts_with = "base_path")]
+    remote: Option<String>,
+
+    /// Upload recipes saved in the web interface to the S3 bucket
+    #[arg(long, requires = "remote")]
+    remote_write: bool,
+
+    /// How often the copy of the remote collection is refreshed, 0 for
+    /// only at start
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "5m",
+        value_parser = humantime::parse_duration
+    )]
+    refresh: Duration,
+
+    /// Directory for the copy of the remote collection
+    ///
+    /// Defaults to a directory in the user cache. Files that aren't in the
+    /// remote collection are removed from it, so it has to be empty the
+    /// first time.
+    #[arg(long, requires = "remote", value_hint = clap::ValueHint::DirPath)]
+    cache_dir: Option<Utf8PathBuf>,
+
+    /// Token for the admin API, sent as `Authorization: Bearer TOKEN`
+    ///
+    /// The admin API at /api/v1/admin rebuilds the recipe index and reports
+    /// on it. Without a token it's off. Prefer the COOK_SERVER_ADMIN_TOKEN
+    /// environment variable, so the token isn't in the process list.
+    #[arg(long, value_name = "TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Import the recipes dropped into the inbox, like `cook import inbox`
+    ///
+    /// Each import is sent to clients of /api/v1/events and to the webhook in
+    /// the `[inbox]` section of cook.toml. Can also be turned on with
+    /// `server.inbox` in cook.toml.
+    #[arg(long)]
+    inbox: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum ServerCommand {
+    /// Check that a running server is healthy
+    ///
+    /// Exits with 0 when the server answers its health check and 1 when it
+    /// doesn't, for container HEALTHCHECKs and service watchdogs.
+    ///
+    /// Examples:
+    ///   cook server ping
+    ///   cook server ping --url http://localhost:8080 --timeout 2s
+    Ping(ping::PingArgs),
 }
 
 impl ServerArgs {
@@ -96,18 +232,30 @@ impl ServerArgs {
 }
 
 #[tokio::main]
-pub async fn run(ctx: Context, args: ServerArgs) -> Result<()> {
-    let addr = if args.host {
-        SocketAddr::from(([0, 0, 0, 0], args.port))
+pub async fn run(ctx: Context, mut args: ServerArgs) -> Result<()> {
+    if let Some(ServerCommand::Ping(ping)) = args.command.take() {
+        return ping::run(ping).await;
+    }
+
+    let (ctx, remote) = match &args.remote {
+        Some(url) => {
+            let (ctx, remote) = open_remote(url, &args).await?;
+            (ctx, Some(remote))
+        }
+        None => (ctx, None),
+    };
+    let config = &ctx.config().server;
+    let port = args.port.or(config.port).unwrap_or(DEFAULT_PORT);
+    let addr = if args.host || config.host.unwrap_or(false) {
+        SocketAddr::from(([0, 0, 0, 0], port))
     } else {
-        SocketAddr::from(([127, 0, 0, 1], args.port))
+        SocketAddr::from(([127, 0, 0, 1], port))
     };
 
     println!("Listening on http://{addr}");
 
     // #[cfg(feature = "ui")]
-    if args.open {
-        let port = args.port;
+    if args.open || config.open.unwrap_or(false) {
         let url = format!("http://localhost:{port}");
         println!("Serving Web UI on {url}");
         tokio::task::spawn(async move {
@@ -118,15 +266,75 @@ pub async fn run(ctx: Context, args: ServerArgs) -> Result<()> {
         });
     }
 
-    let state = build_state(ctx, args)?;
+    let state = build_state(&ctx, &args, remote)?;
 
     println!("Serving recipe files from: {:?}", &state.base_path);
 
-    let app = Router::new()
-        .nest("/api", api(&state)?)
+    // Warm the index, so the first recipe list doesn't parse everything
+    tokio::spawn(handlers::admin::rebuild(state.clone(), false));
+    scheduler::start(state.clone(), config.schedule.clone().unwrap_or_default())?;
+
+    if state.inbox_watched {
+        if state.check_writable(&state.inbox.output_dir).is_err() {
+            bail!("Recipes can't be imported into a remote collection that's read-only");
+        }
+        let inbox = state.inbox.clone();
+        println!("Importing recipes dropped into {}", inbox.dir);
+        let state = state.clone();
+        tokio::spawn(async move {
+            inbox
+                .watch(|event| {
+                    match &event.error {
+                        None => info!("Imported {} from the inbox", event.file),
+                        Some(e) => error!("Failed to import {} from the inbox: {e}", event.file),
+                    }
+                    for recipe in &event.recipes {
+                        state.file_changed(&state.base_path.join(recipe));
+                    }
+                    // Nobody listening isn't an error
+                    let _ = state.inbox_events.send(event.clone());
+                })
+                .await
+        });
+    }
+
+    let mut app = Router::new()
+        .nest(
+            API_PREFIX,
+            api(&state)?.layer(middleware::map_response(add_api_version)),
+        )
         .merge(ui::ui())
-        .route("/static/*file", get(serve_static))
-        .nest_service("/api/static", ServeDir::new(&state.base_path));
+        .route("/calendar.ics", get(handlers::calendar::calendar))
+        .route("/static/*file", get(assets::serve_static))
+        .merge(
+            Router::new()
+                .nest_service("/api/static", ServeDir::new(&state.base_path))
+                .layer(middleware::from_fn(assets::revalidate)),
+        )
+        .fallback(unversioned_api);
+
+    if let Some(mode) = args.webdav.or(config.webdav) {
+        let password = args
+            .webdav_password
+            .clone()
+            .or_else(|| state.admin_token.clone())
+            .filter(|password| !password.is_empty());
+        if password.is_none() && !addr.ip().is_loopback() {
+            if mode == WebDavMode::ReadWrite {
+                bail!(
+                    "A read-write WebDAV share on the network needs a password, \
+                     set --webdav-password"
+                );
+            }
+            tracing::warn!("WebDAV has no password, anyone on the network can read the recipes");
+        }
+        app = app.merge(webdav::router(&state.base_path, mode, password));
+        let access = match mode {
+            WebDavMode::ReadWrite => "read-write",
+            WebDavMode::ReadOnly => "read-only",
+        };
+        println!("WebDAV ({access}) at http://{addr}{}/", webdav::PREFIX);
+    }
 
     let app = app.with_state(state).layer(
         CorsLayer::new()
@@ -157,30 +365,114 @@ pub async fn run(ctx: Context, args: ServerArgs) -> Result<()> {
     Ok(())
 }
 
-fn build_state(ctx: Context, args: ServerArgs) -> Result<Arc<AppState>> {
-    let Context { base_path } = ctx;
+/// Fetch the remote collection into the cache and keep it up to date
+///
+/// The context is the one of the copy, so its `cook.toml` and `config`
+/// directory are used.
+async fn open_remote(url: &str, args: &ServerArgs) -> Result<(Context, Remote)> {
+    let store: Arc<dyn RemoteStore> = remote::open(url, args.remote_write)?.into();
+    let cache = match &args.cache_dir {
+        Some(dir) => {
+            remote::prepare_cache(dir, false)?;
+            dir.clone()
+        }
+        None => {
+            let dir = remote::default_cache_dir(url)?;
+            remote::prepare_cache(&dir, true)?;
+            dir
+        }
+    };
+    let cache = resolve_to_absolute_path(&cache)?;
 
-    let path = args.base_path.as_ref().unwrap_or(&base_path);
-    let absolute_path = resolve_to_absolute_path(path)?;
+    println!("Fetching {url} into {cache}...");
+    let pull = {
+        let (store, cache) = (Arc::clone(&store), cache.clone());
+        move || store.pull(&cache)
+    };
+    tokio::task::spawn_blocking(pull.clone())
+        .await?
+        .with_context(|| format!("Failed to fetch {url}"))?;
+
+    if !args.refresh.is_zero() {
+        let (interval, url) = (args.refresh, url.to_string());
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match tokio::task::spawn_blocking(pull.clone()).await {
+                    Ok(Ok(())) => tracing::debug!("Refreshed {url}"),
+                    Ok(Err(e)) => tracing::warn!("Failed to refresh {url}: {e:#}"),
+                    Err(e) => tracing::warn!("Failed to refresh {url}: {e}"),
+                }
+            }
+        });
+    }
+
+    Ok((Context::new(cache.clone()), Remote { store, cache }))
+}
 
-    if absolute_path.is_file() {
-        bail!("Base path {} is not a directory", absolute_path);
+fn build_state(ctx: &Context, args: &ServerArgs, remote: Option<Remote>) -> Result<Arc<AppState>> {
+    let path = args.base_path.as_deref().unwrap_or(ctx.base_path());
+    let base_path = resolve_to_absolute_path(path)?;
+    if !base_path.is_dir() {
+        bail!("Base path {} is not a directory", base_path);
     }
 
-    tracing::info!("Using absolute base path: {:?}", absolute_path);
+    tracing::info!("Using absolute base path: {:?}", base_path);
 
-    // Create a new Context with the actual base path to properly search for config files
-    let server_ctx = Context::new(absolute_path.clone());
-    let aisle_path = server_ctx.aisle();
-    let pantry_path = server_ctx.pantry();
+    let aisle_path = ctx.aisle();
+    let pantry_path = ctx.pantry();
+    let nutrition_path = ctx.nutrition();
+    let substitutions_path = ctx.substitutions();
+    let seasons_path = ctx.seasons();
 
     tracing::info!("Aisle configuration: {:?}", aisle_path);
     tracing::info!("Pantry configuration: {:?}", pantry_path);
+    tracing::info!("Nutrition database: {:?}", nutrition_path);
+    tracing::info!("Substitutions: {:?}", substitutions_path);
+    tracing::info!("Seasons: {:?}", seasons_path);
+
+    let db = Arc::new(Database::open(&Database::locate(
+        &base_path,
+        ctx.config(),
+    )?)?);
+    tracing::info!("Database: {}", db.path());
+
+    let views = (args.track_views || ctx.config().server.track_views.unwrap_or(false))
+        .then(|| ViewStore::new(Arc::clone(&db), &base_path));
+    let inbox = Inbox::from_config(&base_path, ctx.config());
+    // Not taken from the Host header, which clients can set to anything
+    let public_url = match &ctx.config().server.public_url {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => {
+            let port = args
+                .port
+                .or(ctx.config().server.port)
+                .unwrap_or(DEFAULT_PORT);
+            format!("http://localhost:{port}")
+        }
+    };
 
     Ok(Arc::new(AppState {
-        base_path: absolute_path,
+        base_path,
         aisle_path,
         pantry_path,
+        nutrition_path,
+        substitutions_path,
+        seasons_path,
+        hemisphere: ctx.config().seasons.hemisphere.unwrap_or_default(),
+        views,
+        remote,
+        admin_token: args.admin_token.clone().filter(|token| !token.is_empty()),
+        index: Mutex::new(None),
+        shares: ShareStore::new(Arc::clone(&db)),
+        jobs: Jobs::new(Arc::clone(&db))?,
+        db,
+        inbox,
+        inbox_watched: args.inbox || ctx.config().server.inbox.unwrap_or(false),
+        inbox_events: tokio::sync::broadcast::channel(EVENTS_BUFFERED).0,
+        ai: ctx.config().ai.clone(),
+        public_url,
+        lint_rules: ctx.config().lint.rules(),
     }))
 }
 
@@ -214,6 +506,113 @@ pub struct AppState {
     pub base_path: Utf8PathBuf,
     pub aisle_path: Option<Utf8PathBuf>,
     pub pantry_path: Option<Utf8PathBuf>,
+    pub nutrition_path: Option<Utf8PathBuf>,
+    pub substitutions_path: Option<Utf8PathBuf>,
+    pub seasons_path: Option<Utf8PathBuf>,
+    /// Whose seasons the bundled calendar is moved to
+    pub hemisphere: Hemisphere,
+    /// Recipe views, when the server tracks them
+    pub views: Option<ViewStore>,
+    /// Where the collection is stored, when it isn't served from a local
+    /// directory
+    pub remote: Option<Remote>,
+    /// Token the admin API requires, it's off without one
+    pub admin_token: Option<String>,
+    /// The last build of the recipe index
+    pub index: Mutex<Option<handlers::admin::IndexBuild>>,
+    /// Shopping lists shared with a public link
+    pub shares: ShareStore,
+    /// Background work, listed at /api/v1/jobs
+    pub jobs: Jobs,
+    /// The server's own state, for what has no store of its own
+    pub db: Arc<Database>,
+    /// Where imported recipes come from and go
+    pub inbox: Inbox,
+    /// Whether the server imports what's dropped into the inbox itself
+    pub inbox_watched: bool,
+    /// Recipes imported from the inbox, sent to /api/v1/events
+    pub inbox_events: tokio::sync::broadcast::Sender<InboxEvent>,
+    /// The AI provider, for converting plain text recipes
+    pub ai: AiConfig,
+    /// Address the server is reached at, without a trailing `/`, for full
+    /// links in calendar feeds and shared lists
+    pub public_url: String,
+    /// The `[lint]` rules of the collection, for /api/lint
+    pub lint_rules: LintRules,
+}
+
+/// A remote collection and its local copy
+pub struct Remote {
+    store: Arc<dyn RemoteStore>,
+    cache: Utf8PathBuf,
+}
+
+impl AppState {
+    /// 403 when `path` is in the copy of a remote collection that can't be
+    /// written
+    ///
+    /// The copy is replaced by the remote's files on every refresh, what's
+    /// only written to it would be lost.
+    pub fn check_writable(&self, path: &camino::Utf8Path) -> Result<(), StatusCode> {
+        match &self.remote {
+            Some(remote) if !remote.store.writable() && path.starts_with(&remote.cache) => {
+                tracing::warn!("{path} can't be changed, the remote collection is read-only");
+                Err(StatusCode::FORBIDDEN)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Upload a file changed in the collection, when it's stored remotely
+    ///
+    /// The upload happens in the background, a failure is only logged.
+    pub fn file_changed(&self, path: &camino::Utf8Path) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        // Like a pantry file from the global configuration
+        let Ok(relative) = path.strip_prefix(&remote.cache) else {
+            return;
+        };
+        let relative = relative.to_path_buf();
+        if !remote.store.writable() {
+            tracing::warn!(
+                "{relative} is only saved in the local copy, the remote collection is read-only"
+            );
+            return;
+        }
+        let (store, cache) = (Arc::clone(&remote.store), remote.cache.clone());
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = store.push(&cache, &relative) {
+                tracing::error!("Failed to upload {relative}: {e:#}");
+            }
+        });
+    }
+}
+
+async fn add_api_version(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert(API_VERSION_HEADER, HeaderValue::from(API_VERSION));
+    response
+}
+
+/// Redirect requests to the API without a version to the current one
+///
+/// Clients from before the API was versioned keep working while they move
+/// over. The redirect is temporary (307), so POST requests are sent again
+/// with their body, and marked deprecated.
+async fn unversioned_api(uri: Uri) -> Response {
+    let rest = match uri.path().strip_prefix("/api/") {
+        Some(rest) if rest != "v1" && !rest.starts_with("v1/") => rest,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let query = uri.query().map(|q| format!("?{q}")).unwrap_or_default();
+    let mut response = Redirect::temporary(&format!("{API_PREFIX}/{rest}{query}")).into_response();
+    response
+        .headers_mut()
+        .insert("deprecation", HeaderValue::from_static("true"));
+    response
 }
 
 fn api(_state: &AppState) -> Result<Router<Arc<AppState>>> {
@@ -228,9 +627,35 @@ fn api(_state: &AppState) -> Result<Router<Arc<AppState>>> {
             "/shopping_list/remove",
             post(handlers::remove_from_shopping_list),
         )
+        .route("/shopping_list/extra", get(handlers::get_extra_items))
+        .route(
+            "/shopping_list/extra/remove",
+            post(handlers::remove_extra_item),
+        )
         .route("/shopping_list/clear", post(handlers::clear_shopping_list))
+        .route("/shopping_list/share", post(handlers::share_shopping_list))
+        .route("/cooked", post(handlers::mark_recipe_cooked))
+        .route("/homeassistant/today", get(handlers::home_assistant::today))
+        .route(
+            "/homeassistant/shopping_list",
+            get(handlers::home_assistant::shopping_list),
+        )
+        .route(
+            "/homeassistant/shopping_list/add",
+            post(handlers::home_assistant::add_to_shopping_list),
+        )
+        .route("/convert", get(handlers::units::convert))
+        .route("/lint", get(handlers::lint::problems))
+        .route("/events", get(handlers::events::events))
+        .route("/schema/metadata", get(handlers::schema::metadata))
+        .route("/ingredients", get(handlers::ingredients::autocomplete))
+        .route(
+            "/substitutions/:ingredient",
+            get(handlers::substitutions::get_substitutions),
+        )
         .route("/pantry", get(handlers::get_pantry))
         .route("/pantry/add", post(handlers::add_pantry_item))
+        .route("/pantry/expiring", get(handlers::get_expiring_pantry_items))
         .route(
             "/pantry/:section/:name",
             axum::routing::delete(handlers::remove_pantry_item),
@@ -241,29 +666,58 @@ fn api(_state: &AppState) -> Result<Router<Arc<AppState>>> {
         )
         .route("/recipes", get(handlers::all_recipes))
         .route("/recipes/save", post(handlers::recipes::save_recipe))
+        .route(
+            "/recipes/duplicate",
+            post(handlers::recipes::duplicate_recipe),
+        )
+        .route("/recipes/popular", get(handlers::views::popular))
+        .route("/recipes/recent", get(handlers::views::recent))
+        .route("/recipes/seasonal", get(handlers::seasons::seasonal))
         .route("/recipes/*path", get(handlers::recipe))
         .route("/search", get(handlers::search))
-        .route("/reload", get(handlers::reload).post(handlers::reload));
+        .route("/random", get(handlers::random))
+        .route("/reload", get(handlers::reload).post(handlers::reload))
+        .route("/admin/reindex", post(handlers::admin::reindex))
+        .route("/admin/status", get(handlers::admin::status))
+        .route("/jobs", get(handlers::jobs::list))
+        .route("/jobs/reindex", post(handlers::jobs::reindex))
+        .route("/jobs/export", post(handlers::jobs::export))
+        .route("/jobs/import", post(handlers::jobs::import))
+        .route("/jobs/inbox", post(handlers::jobs::inbox))
+        .route("/jobs/:id", get(handlers::jobs::get))
+        .route("/jobs/:id/cancel", post(handlers::jobs::cancel))
+        .route("/health", get(handlers::admin::health));
 
     Ok(router)
 }
 
-async fn serve_static(Path(path): Path<String>) -> impl axum::response::IntoResponse {
-    let path = path.trim_start_matches('/');
-
-    StaticFiles::get(path)
-        .map(|content| {
-            let mime = mime_guess::from_path(path).first_or_octet_stream();
-            Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, mime.as_ref())
-                .body(Body::from(content.data))
-                .unwrap()
-        })
-        .unwrap_or_else(|| {
-            Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Body::from("404 Not Found"))
-                .unwrap()
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_api_version_header() {
+        let response = add_api_version(StatusCode::OK.into_response()).await;
+        assert_eq!(
+            response.headers()[API_VERSION_HEADER],
+            API_VERSION.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_api() {
+        let response = unversioned_api("/api/recipes/Soup.cook?scale=2".parse().unwrap()).await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            response.headers()[axum::http::header::LOCATION],
+            "/api/v1/recipes/Soup.cook?scale=2"
+        );
+        assert_eq!(response.headers()["deprecation"], "true");
+
+        for uri in ["/api/v1/nothing", "/api/v1", "/recipes", "/apis/recipes"] {
+            let response = unversioned_api(uri.parse().unwrap()).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+            assert!(!response.headers().contains_key("deprecation"), "{uri}");
+        }
+    }
 }
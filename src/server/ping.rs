@@ -0,0 +1,69 @@
+//! `cook server ping`: check a running server is healthy
+//!
+//! For container HEALTHCHECKs and service watchdogs, so images don't need
+//! curl. The exit code is all they look at: 0 when healthy, 1 otherwise,
+//! as Docker expects.
+
+use anyhow::{bail, Context as _, Result};
+use clap::Args;
+use std::time::Duration;
+
+use super::API_PREFIX;
+
+#[derive(Debug, Args)]
+pub struct PingArgs {
+    /// Address of the server
+    #[arg(long, default_value = "http://localhost:9080")]
+    url: String,
+
+    /// How long to wait for an answer
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "5s",
+        value_parser = humantime::parse_duration
+    )]
+    timeout: Duration,
+
+    /// Print nothing when the server is healthy
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+pub async fn run(args: PingArgs) -> Result<()> {
+    match check(&args).await {
+        Ok(version) => {
+            if !args.quiet {
+                println!("{} is healthy (cook {version})", args.url);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The version of the server when it answers healthy
+async fn check(args: &PingArgs) -> Result<String> {
+    let url = format!("{}{API_PREFIX}/health", args.url.trim_end_matches('/'));
+    let response = reqwest::Client::builder()
+        .timeout(args.timeout)
+        .build()?
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("No answer from {url}"))?;
+    if !response.status().is_success() {
+        bail!("{url} answered {}", response.status());
+    }
+    let health: serde_json::Value = response
+        .json()
+        .await
+        .with_context(|| format!("{url} isn't a cook server"))?;
+    if health["status"] != "ok" {
+        bail!("{url} reports {}", health["status"]);
+    }
+    Ok(health["version"].as_str().unwrap_or("unknown").to_string())
+}
@@ -0,0 +1,159 @@
+//! Tasks the server runs on a schedule, from `[[server.schedule]]`
+//!
+//! So index rebuilds, backups and the weekly digest happen where cron
+//! isn't available, like in a container or on a NAS. Times are local, and
+//! a task is skipped when the server isn't running at its time.
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use std::sync::Arc;
+
+use super::{handlers::admin, AppState};
+use crate::{
+    backup::write_backup,
+    config::{ScheduledTask, TaskKind},
+    util::{
+        canonical_path,
+        cron::CronSchedule,
+        digest::{self, Digest},
+    },
+    Context,
+};
+
+/// Archives `backup` keeps unless configured otherwise
+const DEFAULT_KEEP: usize = 7;
+/// Days `digest` covers unless configured otherwise
+const DEFAULT_DAYS: u64 = 7;
+
+/// Check every task and run each on its schedule in the background
+///
+/// Fails when a task is configured wrong, so it's noticed at start and not
+/// when it's due.
+pub fn start(state: Arc<AppState>, tasks: Vec<ScheduledTask>) -> Result<()> {
+    let mut scheduled = Vec::new();
+    for task in tasks {
+        let schedule: CronSchedule = task.cron.parse()?;
+        match task.task {
+            TaskKind::Webhook if task.url.is_none() => {
+                bail!("The webhook task at '{schedule}' needs a url")
+            }
+            TaskKind::Backup => {
+                let dir = task
+                    .dir
+                    .as_ref()
+                    .with_context(|| format!("The backup task at '{schedule}' needs a dir"))?;
+                // Each archive would have the ones before in it
+                if canonical_path(&state.base_path.join(dir))?.starts_with(&state.base_path) {
+                    bail!(
+                        "The backups of the task at '{schedule}' have to go outside \
+                         the collection, {dir} is in it"
+                    );
+                }
+            }
+            _ => {}
+        }
+        scheduled.push((schedule, task));
+    }
+
+    for (schedule, task) in scheduled {
+        println!("Scheduled {} at '{schedule}'", name(task.task));
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            loop {
+                let now = chrono::Local::now().naive_local();
+                let Some(next) = schedule.next_after(now) else {
+                    tracing::warn!(
+                        "'{schedule}' never happens, not running {}",
+                        name(task.task)
+                    );
+                    return;
+                };
+                tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+                tracing::info!("Running scheduled {}", name(task.task));
+                match run(&state, &task).await {
+                    Ok(()) => tracing::info!("Scheduled {} done", name(task.task)),
+                    Err(e) => tracing::error!("Scheduled {} failed: {e:#}", name(task.task)),
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+fn name(kind: TaskKind) -> &'static str {
+    match kind {
+        TaskKind::Reindex => "reindex",
+        TaskKind::Backup => "backup",
+        TaskKind::Webhook => "webhook",
+        TaskKind::Digest => "digest",
+    }
+}
+
+async fn run(state: &Arc<AppState>, task: &ScheduledTask) -> Result<()> {
+    match task.task {
+        TaskKind::Reindex => {
+            admin::rebuild(Arc::clone(state), true)
+                .await
+                .map_err(|status| anyhow::anyhow!("Index rebuild failed with {status}"))?;
+        }
+        TaskKind::Backup => {
+            let base_path = state.base_path.clone();
+            let dir = base_path.join(task.dir.as_ref().context("No backup dir")?);
+            let keep = task.keep.unwrap_or(DEFAULT_KEEP);
+            tokio::task::spawn_blocking(move || backup(base_path, dir, keep)).await??;
+        }
+        TaskKind::Webhook => {
+            let url = task.url.as_deref().context("No webhook url")?;
+            let response = reqwest::Client::new()
+                .get(url)
+                .send()
+                .await
+                .with_context(|| format!("Failed to reach {url}"))?;
+            if !response.status().is_success() {
+                bail!("{url} answered {}", response.status());
+            }
+        }
+        TaskKind::Digest => {
+            let base_path = state.base_path.clone();
+            let (to, days) = (task.to.clone(), task.days.unwrap_or(DEFAULT_DAYS));
+            tokio::task::spawn_blocking(move || -> Result<()> {
+                let ctx = Context::new(base_path);
+                let digest = Digest::build(&ctx, chrono::Local::now().date_naive(), days)?;
+                let config = &ctx.config().email;
+                let to = to.or_else(|| config.to.clone()).unwrap_or_default();
+                digest::send(config, &to, &digest)
+            })
+            .await??;
+        }
+    }
+    Ok(())
+}
+
+/// Write a dated backup to `dir` and remove the oldest beyond `keep`
+fn backup(base_path: Utf8PathBuf, dir: Utf8PathBuf, keep: usize) -> Result<()> {
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {dir}"))?;
+    let out = dir.join(format!(
+        "cookbook-{}.tar.zst",
+        chrono::Local::now().format("%Y-%m-%d-%H%M")
+    ));
+    let ctx = Context::new(base_path.clone());
+    write_backup(&ctx, &base_path, &out, 3)?;
+
+    // The names sort by date
+    let mut archives: Vec<Utf8PathBuf> = dir
+        .read_dir_utf8()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.starts_with("cookbook-") && name.ends_with(".tar.zst"))
+        })
+        .collect();
+    archives.sort();
+    let excess = archives.len().saturating_sub(keep.max(1));
+    for old in &archives[..excess] {
+        std::fs::remove_file(old).with_context(|| format!("Failed to remove {old}"))?;
+    }
+    Ok(())
+}
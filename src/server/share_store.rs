@@ -0,0 +1,130 @@
+use anyhow::Result;
+use rand::{distributions::Alphanumeric, Rng};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+
+use super::db::Database;
+
+/// Characters of a share token, about 130 bits
+const TOKEN_LENGTH: usize = 22;
+
+/// A shopping list as it was when it was shared
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedList {
+    /// Names of the recipes it's for
+    pub recipes: Vec<String>,
+    pub categories: Vec<SharedCategory>,
+    /// RFC 3339 timestamp
+    pub expires_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedCategory {
+    pub name: String,
+    pub items: Vec<SharedItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedItem {
+    pub name: String,
+    /// Like "200 g, 1 cup"
+    pub quantity: String,
+}
+
+/// Shopping lists shared with a public link, until they expire
+///
+/// Shares are kept in the server's database, so links keep working after a
+/// restart.
+pub struct ShareStore {
+    db: Arc<Database>,
+}
+
+impl ShareStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Share a list for `ttl`, returns the token of its link
+    pub fn create(&self, list: SharedList, ttl: Duration) -> Result<String> {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        let now = chrono::Utc::now().timestamp();
+        let expires_at = now.saturating_add(ttl.as_secs().try_into().unwrap_or(i64::MAX));
+        let list = serde_json::to_string(&list)?;
+        self.db.with(|conn| {
+            conn.execute("DELETE FROM shares WHERE expires_at <= ?1", [now])?;
+            conn.execute(
+                "INSERT INTO shares (token, list, expires_at) VALUES (?1, ?2, ?3)",
+                params![token, list, expires_at],
+            )
+        })?;
+        Ok(token)
+    }
+
+    /// The list shared with `token`, unless it expired
+    pub fn get(&self, token: &str) -> Option<SharedList> {
+        let list = self.db.with(|conn| {
+            conn.query_row(
+                "SELECT list FROM shares WHERE token = ?1 AND expires_at > ?2",
+                params![token, chrono::Utc::now().timestamp()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+        });
+        match list {
+            Ok(list) => list.and_then(|list| match serde_json::from_str(&list) {
+                Ok(list) => Some(list),
+                Err(e) => {
+                    tracing::error!("Ignoring the unreadable shared list {token}: {e}");
+                    None
+                }
+            }),
+            Err(e) => {
+                tracing::error!("{e:#}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino::Utf8Path;
+
+    fn list() -> SharedList {
+        SharedList {
+            recipes: vec!["Pancakes".to_string()],
+            categories: vec![SharedCategory {
+                name: "dairy".to_string(),
+                items: vec![SharedItem {
+                    name: "milk".to_string(),
+                    quantity: "300 ml".to_string(),
+                }],
+            }],
+            expires_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_survives_restart() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = Utf8Path::from_path(dir.path()).unwrap().join("server.db");
+
+        let store = ShareStore::new(Arc::new(Database::open(&path).unwrap()));
+        let token = store.create(list(), Duration::from_secs(3600)).unwrap();
+        let expired = store.create(list(), Duration::ZERO).unwrap();
+        drop(store);
+
+        let store = ShareStore::new(Arc::new(Database::open(&path).unwrap()));
+        let shared = store.get(&token).unwrap();
+        assert_eq!(shared.recipes, ["Pancakes"]);
+        assert_eq!(shared.categories[0].items[0].quantity, "300 ml");
+        assert!(store.get(&expired).is_none());
+        assert!(store.get("unknown").is_none());
+    }
+}
@@ -10,14 +10,27 @@ pub struct ShoppingListItem {
     pub scale: f64,
 }
 
+/// Something to buy that isn't from a recipe, like "milk"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtraItem {
+    pub name: String,
+    /// As written, like "2 l", empty when not given
+    pub quantity: String,
+}
+
 pub struct ShoppingListStore {
     file_path: Utf8PathBuf,
+    extra_path: Utf8PathBuf,
 }
 
 impl ShoppingListStore {
     pub fn new(base_path: &Utf8PathBuf) -> Self {
         let file_path = base_path.join(".shopping_list.txt");
-        Self { file_path }
+        let extra_path = base_path.join(".shopping_list_extra.txt");
+        Self {
+            file_path,
+            extra_path,
+        }
     }
 
     pub fn load(&self) -> Result<Vec<ShoppingListItem>> {
@@ -81,6 +94,94 @@ impl ShoppingListStore {
 
     pub fn clear(&self) -> Result<()> {
         self.save(&[])?;
+        self.save_extra(&[])?;
         Ok(())
     }
+
+    pub fn load_extra(&self) -> Result<Vec<ExtraItem>> {
+        if !self.extra_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.extra_path)?;
+        Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (name, quantity) = line.split_once('\t').unwrap_or((line, ""));
+                ExtraItem {
+                    name: name.to_string(),
+                    quantity: quantity.to_string(),
+                }
+            })
+            .collect())
+    }
+
+    pub fn save_extra(&self, items: &[ExtraItem]) -> Result<()> {
+        let mut content = String::from("# Other items on the shopping list\n");
+        content.push_str("# Format: name<TAB>quantity\n\n");
+
+        for item in items {
+            content.push_str(&format!("{}\t{}\n", item.name, item.quantity));
+        }
+
+        fs::write(&self.extra_path, content)?;
+        Ok(())
+    }
+
+    /// Add an item, tabs and line breaks in it become spaces
+    pub fn add_extra(&self, name: &str, quantity: &str) -> Result<()> {
+        let clean = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut items = self.load_extra()?;
+        items.push(ExtraItem {
+            name: clean(name),
+            quantity: clean(quantity),
+        });
+        self.save_extra(&items)
+    }
+
+    pub fn remove_extra(&self, name: &str) -> Result<()> {
+        let mut items = self.load_extra()?;
+        if let Some(pos) = items.iter().position(|i| i.name == name) {
+            items.remove(pos);
+        }
+        self.save_extra(&items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = camino::Utf8Path::from_path(dir.path())
+            .unwrap()
+            .to_path_buf();
+        let store = ShoppingListStore::new(&base);
+        assert!(store.load_extra().unwrap().is_empty());
+
+        store.add_extra("milk", "2 l").unwrap();
+        store.add_extra("bin\tbags", "").unwrap();
+        assert_eq!(
+            store.load_extra().unwrap(),
+            [
+                ExtraItem {
+                    name: "milk".to_string(),
+                    quantity: "2 l".to_string(),
+                },
+                ExtraItem {
+                    name: "bin bags".to_string(),
+                    quantity: String::new(),
+                },
+            ]
+        );
+
+        store.remove_extra("milk").unwrap();
+        assert_eq!(store.load_extra().unwrap().len(), 1);
+        store.clear().unwrap();
+        assert!(store.load_extra().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,82 @@
+//! JSON responses that are sent while they're serialized
+//!
+//! `Json` serializes the whole response into memory first, which for a
+//! collection with thousands of recipes means holding the tree, its JSON and
+//! often an intermediate `serde_json::Value` at once. Here the value is
+//! serialized on a blocking thread into a small bounded channel, so only a
+//! few chunks exist at a time and a slow client slows the serializer down.
+
+use axum::{
+    body::{Body, Bytes},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::io::{self, Write};
+use tokio::sync::mpsc;
+
+/// Bytes sent per chunk
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks serialized ahead of what the client received
+const CHUNKS_AHEAD: usize = 4;
+
+/// Respond with `value` as JSON, streamed in chunks
+pub fn json<T>(value: T) -> Response
+where
+    T: Serialize + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(CHUNKS_AHEAD);
+
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChannelWriter {
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            tx,
+        };
+        let result = serde_json::to_writer(&mut writer, &value)
+            .map_err(io::Error::from)
+            .and_then(|()| writer.flush());
+        if let Err(e) = result {
+            // Nothing to tell when the client went away
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                tracing::error!("Failed to serialize response: {e}");
+                // Ends the body with an error, so the client sees it's cut off
+                let _ = writer.tx.blocking_send(Err(e));
+            }
+        }
+    });
+
+    let chunks = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+struct ChannelWriter {
+    buf: Vec<u8>,
+    tx: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        self.tx
+            .blocking_send(Ok(Bytes::from(chunk)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The client went away"))
+    }
+}
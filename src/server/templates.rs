@@ -44,6 +44,14 @@ pub struct ShoppingListTemplate {
     pub active: String,
 }
 
+/// A shared shopping list, on its own page without the app around it
+#[derive(Template)]
+#[template(path = "shared_list.html")]
+pub struct SharedListTemplate {
+    pub token: String,
+    pub list: crate::server::share_store::SharedList,
+}
+
 #[derive(Template)]
 #[template(path = "recipe_form.html")]
 pub struct RecipeFormTemplate {
@@ -65,6 +73,7 @@ pub struct PreferencesTemplate {
 pub struct PantryTemplate {
     pub active: String,
     pub sections: Vec<PantrySection>,
+    pub expiring: Vec<crate::util::pantry::ExpiringItem>,
 }
 
 #[derive(Debug, Clone, Serialize)]
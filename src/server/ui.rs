@@ -1,13 +1,16 @@
 use crate::server::{templates::*, AppState};
+use crate::util::format::format_unit;
+use crate::util::metadata_cache::collection_metadata;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Json},
     routing::{get, post},
-    Router, Form,
+    Form, Router,
 };
 use camino::Utf8PathBuf;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub fn ui() -> Router<Arc<AppState>> {
@@ -17,6 +20,7 @@ pub fn ui() -> Router<Arc<AppState>> {
         .route("/recipe/new", get(recipe_form_page))
         .route("/recipe/*path", get(recipe_page))
         .route("/shopping-list", get(shopping_list_page))
+        .route("/share/:token", get(shared_list_page))
         .route("/pantry", get(pantry_page))
         .route("/preferences", get(preferences_page))
         .route("/api/import-url", post(import_recipe_url))
@@ -48,11 +52,24 @@ async fn recipes_handler(
         base.clone()
     };
 
-    let tree = cooklang_find::build_tree(&search_path).map_err(|e| {
+    let tree = crate::util::walk::build_tree(&search_path).map_err(|e| {
         tracing::error!("Failed to build recipe tree: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    // From the metadata cache, so the page doesn't parse every recipe
+    let entries = tree
+        .children
+        .values()
+        .filter_map(|child| child.recipe.clone())
+        .collect();
+    let descriptions: HashMap<String, String> = collection_metadata(base, entries)
+        .into_iter()
+        .filter_map(|(entry, metadata)| {
+            Some((entry.path()?.to_string(), metadata.description.clone()?))
+        })
+        .collect();
+
     let mut items = Vec::new();
 
     for (name, child) in &tree.children {
@@ -97,7 +114,11 @@ async fn recipes_handler(
             } else {
                 None
             },
-            description: None,
+            description: child
+                .recipe
+                .as_ref()
+                .and_then(|recipe| recipe.path())
+                .and_then(|path| descriptions.get(path.as_str()).cloned()),
             tags,
             image_path,
             is_menu,
@@ -167,8 +188,6 @@ async fn recipe_page(
     Query(query): Query<RecipeQuery>,
     State(state): State<Arc<AppState>>,
 ) -> Result<axum::response::Response, StatusCode> {
-    let scale = query.scale.unwrap_or(1.0);
-
     let recipe_path = Utf8PathBuf::from(&path);
     tracing::info!(
         "Looking for recipe at path: {}, extension: {:?}",
@@ -180,6 +199,20 @@ async fn recipe_page(
         tracing::error!("Recipe not found: {path}");
         StatusCode::NOT_FOUND
     })?;
+    // Scaled to `recipe.default_servings` unless the page asks otherwise
+    let scale = query
+        .scale
+        .or_else(|| crate::util::scaling::default_servings_factor(&entry))
+        .unwrap_or(1.0);
+
+    if let (Some(views), Some(viewed)) = (&state.views, entry.path()) {
+        views.record(
+            viewed
+                .strip_prefix(&state.base_path)
+                .unwrap_or(viewed)
+                .as_str(),
+        );
+    }
 
     // Check if this is a menu file
     let actual_path = entry.path();
@@ -254,7 +287,7 @@ async fn recipe_page(
             unit: ingredient
                 .quantity
                 .as_ref()
-                .and_then(|q| q.unit().as_ref().map(|u| u.to_string())),
+                .and_then(|q| q.unit().map(|u| format_unit(u).to_string())),
             reference_path,
         });
     }
@@ -308,10 +341,9 @@ async fn recipe_page(
                                         quantity: ing.quantity.as_ref().and_then(|q| {
                                             crate::util::format::format_quantity(q.value())
                                         }),
-                                        unit: ing
-                                            .quantity
-                                            .as_ref()
-                                            .and_then(|q| q.unit().as_ref().map(|u| u.to_string())),
+                                        unit: ing.quantity.as_ref().and_then(|q| {
+                                            q.unit().map(|u| format_unit(u).to_string())
+                                        }),
                                     });
                                 }
                             }
@@ -335,7 +367,7 @@ async fn recipe_page(
                                             if !timer_text.is_empty() {
                                                 timer_text.push(' ');
                                             }
-                                            timer_text.push_str(unit);
+                                            timer_text.push_str(format_unit(unit));
                                         }
                                     }
 
@@ -353,9 +385,9 @@ async fn recipe_page(
                                         .unwrap_or_default();
                                     if let Some(unit) = q.unit() {
                                         if !qty.is_empty() {
-                                            qty.push_str(&format!(" {unit}"));
+                                            qty.push_str(&format!(" {}", format_unit(unit)));
                                         } else {
-                                            qty = unit.to_string();
+                                            qty = format_unit(unit).to_string();
                                         }
                                     }
                                     step_items.push(StepItem::Quantity(qty));
@@ -402,7 +434,7 @@ async fn recipe_page(
                         unit: ingredient
                             .quantity
                             .as_ref()
-                            .and_then(|q| q.unit().as_ref().map(|u| u.to_string())),
+                            .and_then(|q| q.unit().map(|u| format_unit(u).to_string())),
                         reference_path,
                     });
                 }
@@ -604,7 +636,7 @@ async fn menu_page_handler(
                                     let unit = ing
                                         .quantity
                                         .as_ref()
-                                        .and_then(|q| q.unit().as_ref().map(|u| u.to_string()));
+                                        .and_then(|q| q.unit().map(|u| format_unit(u).to_string()));
 
                                     step_items.push(MenuSectionItem::Ingredient {
                                         name: ing.name.to_string(),
@@ -710,6 +742,15 @@ async fn shopping_list_page() -> impl askama_axum::IntoResponse {
     }
 }
 
+/// A shopping list shared with a public link
+async fn shared_list_page(
+    Path(token): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl askama_axum::IntoResponse, StatusCode> {
+    let list = state.shares.get(&token).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(SharedListTemplate { token, list })
+}
+
 async fn pantry_page(
     State(state): State<Arc<AppState>>,
 ) -> Result<impl askama_axum::IntoResponse, StatusCode> {
@@ -717,12 +758,21 @@ async fn pantry_page(
     let pantry_path = state.pantry_path.as_ref();
 
     let mut sections = Vec::new();
+    let mut expiring = Vec::new();
 
     if let Some(path) = pantry_path {
         if let Ok(content) = std::fs::read_to_string(path) {
             let result = cooklang::pantry::parse_lenient(&content);
 
             if let Some(pantry_conf) = result.output() {
+                // Items to use up soon, shown as a warning banner
+                expiring = crate::util::pantry::expiring_items(
+                    pantry_conf,
+                    chrono::Local::now().date_naive(),
+                    3,
+                    false,
+                );
+
                 // Convert pantry data to template format
                 for (section_name, items) in &pantry_conf.sections {
                     let mut pantry_items = Vec::new();
@@ -749,6 +799,7 @@ async fn pantry_page(
     Ok(PantryTemplate {
         active: "pantry".to_string(),
         sections,
+        expiring,
     })
 }
 
@@ -783,7 +834,7 @@ async fn recipe_form_page(
     // If editing, we could pre-populate form data here in the future
     // For now, we'll handle it on the frontend via JavaScript
     let _editing_recipe = query.edit.as_deref();
-    
+
     Ok(RecipeFormTemplate {
         active: "create".to_string(),
     })
@@ -808,7 +859,9 @@ struct ImportResponse {
     image_url: Option<String>,
 }
 
-async fn import_recipe_url(Json(request): Json<ImportRequest>) -> Result<Json<ImportResponse>, StatusCode> {
+async fn import_recipe_url(
+    Json(request): Json<ImportRequest>,
+) -> Result<Json<ImportResponse>, StatusCode> {
     tracing::info!("Importing recipe from URL: {}", request.url);
 
     // First get the raw recipe data for images and metadata
@@ -816,7 +869,7 @@ async fn import_recipe_url(Json(request): Json<ImportRequest>) -> Result<Json<Im
         Ok(r) => {
             tracing::info!("Successfully fetched raw recipe data");
             r
-        },
+        }
         Err(e) => {
             tracing::error!("Failed to fetch recipe from {}: {}", request.url, e);
             return Err(StatusCode::BAD_REQUEST);
@@ -828,16 +881,20 @@ async fn import_recipe_url(Json(request): Json<ImportRequest>) -> Result<Json<Im
         Ok(r) => {
             tracing::info!("Successfully imported and converted recipe to cooklang");
             r
-        },
+        }
         Err(e) => {
-            tracing::error!("Failed to convert recipe to cooklang from {}: {}", request.url, e);
+            tracing::error!(
+                "Failed to convert recipe to cooklang from {}: {}",
+                request.url,
+                e
+            );
             return Err(StatusCode::BAD_REQUEST);
         }
     };
 
     // Parse the cooklang content to extract structured data
     let parts: Vec<&str> = cooklang_recipe.split("---").collect();
-    
+
     let (metadata_yaml, recipe_content) = if parts.len() >= 3 {
         // YAML frontmatter exists
         (parts[1], parts[2])
@@ -931,7 +988,7 @@ async fn get_recipe_data(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<RecipeDataResponse>, StatusCode> {
     let recipe_path = Utf8PathBuf::from(&path);
-    
+
     let entry = cooklang_find::get_recipe(vec![&state.base_path], &recipe_path).map_err(|_| {
         tracing::error!("Recipe not found for editing: {path}");
         StatusCode::NOT_FOUND
@@ -944,7 +1001,7 @@ async fn get_recipe_data(
 
     // Parse the recipe content to extract metadata and content
     let parts: Vec<&str> = recipe_content.split("---").collect();
-    
+
     let (metadata_yaml, recipe_body) = if parts.len() >= 3 {
         (parts[1], parts[2])
     } else {
@@ -959,7 +1016,10 @@ async fn get_recipe_data(
     };
 
     let get_metadata = |key: &str| -> Option<String> {
-        metadata.get(key).and_then(|v| v.as_str()).map(|s| s.to_string())
+        metadata
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
     };
 
     let get_tags = || -> Vec<String> {
@@ -1028,7 +1088,7 @@ struct SaveRecipeForm {
 
 async fn save_recipe(
     State(state): State<Arc<AppState>>,
-    Form(form): Form<SaveRecipeForm>
+    Form(form): Form<SaveRecipeForm>,
 ) -> Result<impl IntoResponse, StatusCode> {
     tracing::info!("Saving recipe: {}", form.name);
 
@@ -1049,8 +1109,9 @@ async fn save_recipe(
         .replace('>', "_")     // Illegal on Windows
         .replace('|', "_")     // Illegal on Windows
         + ".cook";
-    
+
     let recipe_path = state.base_path.join(&filename);
+    state.check_writable(&recipe_path)?;
 
     // Build the recipe content in Cooklang format
     let mut content = String::new();
@@ -1058,34 +1119,38 @@ async fn save_recipe(
     // Add YAML frontmatter with metadata
     content.push_str("---\n");
     content.push_str(&format!("title: \"{}\"\n", form.name));
-    
+
     if let Some(desc) = &form.description {
         if !desc.trim().is_empty() {
             content.push_str(&format!("description: \"{}\"\n", desc.trim()));
         }
     }
-    
+
     if let Some(servings) = &form.servings {
         if !servings.trim().is_empty() {
             content.push_str(&format!("servings: {}\n", servings.trim()));
         }
     }
-    
+
     if let Some(prep) = &form.prep_time {
         if !prep.trim().is_empty() {
             content.push_str(&format!("prep time: \"{}\"\n", prep.trim()));
         }
     }
-    
+
     if let Some(cook) = &form.cook_time {
         if !cook.trim().is_empty() {
             content.push_str(&format!("cook time: \"{}\"\n", cook.trim()));
         }
     }
-    
+
     if let Some(tags) = &form.tags {
         if !tags.trim().is_empty() {
-            let tag_list: Vec<&str> = tags.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+            let tag_list: Vec<&str> = tags
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .collect();
             if !tag_list.is_empty() {
                 content.push_str("tags:\n");
                 for tag in tag_list {
@@ -1094,13 +1159,13 @@ async fn save_recipe(
             }
         }
     }
-    
+
     if let Some(image) = &form.image_url {
         if !image.trim().is_empty() {
             content.push_str(&format!("image: \"{}\"\n", image.trim()));
         }
     }
-    
+
     content.push_str("---\n\n");
 
     // Add ingredients section
@@ -1118,7 +1183,7 @@ async fn save_recipe(
     // Add instructions section - each line becomes a separate step
     if !form.instructions.trim().is_empty() {
         let mut first_step = true;
-        
+
         for line in form.instructions.lines() {
             let line = line.trim();
             if !line.is_empty() {
@@ -1131,7 +1196,7 @@ async fn save_recipe(
                 first_step = false;
             }
         }
-        
+
         // Add final newline if we added any steps
         if !first_step {
             content.push('\n');
@@ -1147,7 +1212,7 @@ async fn save_recipe(
         }
         // Still save the file but log warnings - let users save invalid recipes for editing
     }
-    
+
     if validation_result.report().has_warnings() {
         tracing::warn!("Recipe has warnings:");
         for warning in validation_result.report().warnings() {
@@ -1159,6 +1224,7 @@ async fn save_recipe(
     match std::fs::write(&recipe_path, content) {
         Ok(()) => {
             tracing::info!("Recipe saved to: {}", recipe_path);
+            state.file_changed(&recipe_path);
             // Redirect to the new recipe page (filename without extension)
             let redirect_path = format!("/recipe/{}", filename.replace(".cook", ""));
             Ok(axum::response::Redirect::to(&redirect_path).into_response())
@@ -0,0 +1,147 @@
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, sync::Arc};
+
+use super::db::Database;
+
+/// Where views were counted before the server had a database
+const LEGACY_FILE: &str = ".recipe_views.json";
+
+/// How often and when a recipe was opened in the web UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeViews {
+    pub views: u64,
+    /// RFC 3339 timestamp
+    pub last_viewed: String,
+}
+
+/// Views per recipe, kept in the server's database so they survive restarts
+pub struct ViewStore {
+    db: Arc<Database>,
+}
+
+impl ViewStore {
+    /// The view counts, taking over those of `.recipe_views.json` in the
+    /// collection from older versions
+    pub fn new(db: Arc<Database>, base_path: &Utf8Path) -> Self {
+        let store = Self { db };
+        let legacy = base_path.join(LEGACY_FILE);
+        if legacy.is_file() {
+            match store.import(&legacy) {
+                Ok(()) => tracing::info!("Moved the view counts of {legacy} to the database"),
+                Err(e) => tracing::warn!("{e:#}"),
+            }
+        }
+        store
+    }
+
+    /// Count a view of a recipe, by its path relative to the collection
+    pub fn record(&self, path: &str) {
+        let now = chrono::Local::now().to_rfc3339();
+        let result = self.db.with(|conn| {
+            conn.execute(
+                "INSERT INTO recipe_views (path, views, last_viewed) VALUES (?1, 1, ?2)
+                 ON CONFLICT (path) DO UPDATE
+                 SET views = views + 1, last_viewed = excluded.last_viewed",
+                params![path, now],
+            )
+        });
+        // Views are nice to have, failing to save them isn't worth an error page
+        if let Err(e) = result {
+            tracing::warn!("{e:#}");
+        }
+    }
+
+    /// The most viewed recipes, most viewed first
+    pub fn popular(&self, limit: usize) -> Vec<(String, RecipeViews)> {
+        self.query(
+            "SELECT path, views, last_viewed FROM recipe_views
+             ORDER BY views DESC, last_viewed DESC LIMIT ?1",
+            limit,
+        )
+    }
+
+    /// The recently viewed recipes, latest first
+    pub fn recent(&self, limit: usize) -> Vec<(String, RecipeViews)> {
+        self.query(
+            "SELECT path, views, last_viewed FROM recipe_views
+             ORDER BY last_viewed DESC LIMIT ?1",
+            limit,
+        )
+    }
+
+    fn query(&self, sql: &str, limit: usize) -> Vec<(String, RecipeViews)> {
+        let result = self.db.with(|conn| {
+            let mut statement = conn.prepare(sql)?;
+            let rows = statement.query_map([limit as i64], |row| {
+                Ok((
+                    row.get(0)?,
+                    RecipeViews {
+                        views: row.get(1)?,
+                        last_viewed: row.get(2)?,
+                    },
+                ))
+            })?;
+            rows.collect()
+        });
+        result.unwrap_or_else(|e| {
+            tracing::warn!("{e:#}");
+            Vec::new()
+        })
+    }
+
+    /// Add the counts of the JSON file and rename it, so they're only
+    /// added once
+    fn import(&self, file: &Utf8Path) -> Result<()> {
+        let content = fs::read_to_string(file).with_context(|| format!("Failed to read {file}"))?;
+        let views: BTreeMap<String, RecipeViews> = serde_json::from_str(&content)
+            .with_context(|| format!("Ignoring invalid view counts in {file}"))?;
+        self.db.with(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            for (path, views) in &views {
+                tx.execute(
+                    "INSERT INTO recipe_views (path, views, last_viewed) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (path) DO UPDATE
+                     SET views = views + excluded.views,
+                         last_viewed = max(last_viewed, excluded.last_viewed)",
+                    params![path, views.views, views.last_viewed],
+                )?;
+            }
+            tx.commit()
+        })?;
+        let moved = file.with_extension("json.imported");
+        fs::rename(file, &moved).with_context(|| format!("Failed to rename {file} to {moved}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_import() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = Utf8Path::from_path(dir.path()).unwrap();
+        fs::write(
+            base.join(LEGACY_FILE),
+            r#"{"Pancakes.cook": {"views": 3, "last_viewed": "2024-03-17T19:02:11+01:00"}}"#,
+        )
+        .unwrap();
+        let db = Arc::new(Database::open(&base.join("server.db")).unwrap());
+
+        let store = ViewStore::new(Arc::clone(&db), base);
+        store.record("Pancakes.cook");
+        let popular = store.popular(10);
+        assert_eq!(popular.len(), 1);
+        assert_eq!(popular[0].0, "Pancakes.cook");
+        assert_eq!(popular[0].1.views, 4);
+        assert!(!base.join(LEGACY_FILE).exists());
+        assert!(base.join(".recipe_views.json.imported").exists());
+
+        // Only imported once
+        let store = ViewStore::new(db, base);
+        assert_eq!(store.popular(10)[0].1.views, 4);
+    }
+}
@@ -0,0 +1,248 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::any,
+    Router,
+};
+use base64::Engine;
+use camino::Utf8Path;
+use clap::ValueEnum;
+use dav_server::{
+    davpath::DavPath,
+    fakels::FakeLs,
+    fs::{
+        DavDirEntry, DavFile, DavFileSystem, DavMetaData, FsError, FsFuture, FsStream, OpenOptions,
+        ReadDirMeta,
+    },
+    localfs::LocalFs,
+    DavHandler, DavMethodSet,
+};
+use futures_util::{future, FutureExt, StreamExt};
+use serde::Deserialize;
+use std::{
+    path::{Component, Path},
+    sync::Arc,
+};
+
+use super::{handlers::admin::same, AppState};
+use crate::LOCAL_CONFIG_DIR;
+
+/// Where the collection is mounted
+pub const PREFIX: &str = "/dav";
+
+/// What WebDAV clients may do with the files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebDavMode {
+    /// Open, change, add and remove files
+    ReadWrite,
+    /// Only open files
+    ReadOnly,
+}
+
+/// The recipe directory as a WebDAV share
+///
+/// Files are served from disk on every request, so changes made over
+/// WebDAV show up in the rest of the server right away. Hidden files, like
+/// `.git`, and the `config` directory aren't shared. With a password,
+/// clients log in with HTTP basic auth, under any user name.
+pub fn router(
+    base_path: &Utf8Path,
+    mode: WebDavMode,
+    password: Option<String>,
+) -> Router<Arc<AppState>> {
+    let methods = match mode {
+        WebDavMode::ReadWrite => DavMethodSet::WEBDAV_RW,
+        WebDavMode::ReadOnly => DavMethodSet::WEBDAV_RO,
+    };
+    let dav = DavHandler::builder()
+        .filesystem(Box::new(Shared {
+            fs: LocalFs::new(base_path, false, false, false),
+        }))
+        // Editors lock files while they're open, nothing else writes them
+        .locksystem(FakeLs::new())
+        .strip_prefix(PREFIX)
+        .methods(methods)
+        .build_handler();
+
+    let password = Arc::new(password);
+    let handler = move |request: Request| {
+        let dav = dav.clone();
+        let password = Arc::clone(&password);
+        async move {
+            if let Some(password) = password.as_deref() {
+                if !logged_in(request.headers(), password) {
+                    return (
+                        StatusCode::UNAUTHORIZED,
+                        [(header::WWW_AUTHENTICATE, "Basic realm=\"cook\"")],
+                    )
+                        .into_response();
+                }
+            }
+            dav.handle(request).await.map(Body::new).into_response()
+        }
+    };
+    Router::new()
+        .route(PREFIX, any(handler.clone()))
+        .route(&format!("{PREFIX}/*path"), any(handler))
+}
+
+/// Whether the request has basic auth with the password
+fn logged_in(headers: &HeaderMap, password: &str) -> bool {
+    let Some(encoded) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+    else {
+        return false;
+    };
+    let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(encoded.trim()) else {
+        return false;
+    };
+    // user:password, the user name doesn't matter
+    match decoded.iter().position(|&b| b == b':') {
+        Some(colon) => same(&decoded[colon + 1..], password.as_bytes()),
+        None => false,
+    }
+}
+
+/// Whether a path in the collection isn't shared
+fn hidden(path: &Path) -> bool {
+    path.components()
+        .enumerate()
+        .any(|(i, component)| match component {
+            Component::Normal(name) => hidden_name(name.as_encoded_bytes(), i == 0),
+            _ => true,
+        })
+}
+
+fn hidden_name(name: &[u8], top_level: bool) -> bool {
+    name.starts_with(b".") || (top_level && name == LOCAL_CONFIG_DIR.as_bytes())
+}
+
+/// The collection's directory without what [`hidden`] leaves out
+#[derive(Clone)]
+struct Shared {
+    fs: Box<LocalFs>,
+}
+
+fn not_found<'a, T: Send + 'a>() -> FsFuture<'a, T> {
+    future::ready(Err(FsError::NotFound)).boxed()
+}
+
+impl DavFileSystem for Shared {
+    fn open<'a>(
+        &'a self,
+        path: &'a DavPath,
+        options: OpenOptions,
+    ) -> FsFuture<'a, Box<dyn DavFile>> {
+        if hidden(path.as_rel_ospath()) {
+            return not_found();
+        }
+        self.fs.open(path, options)
+    }
+
+    fn read_dir<'a>(
+        &'a self,
+        path: &'a DavPath,
+        meta: ReadDirMeta,
+    ) -> FsFuture<'a, FsStream<Box<dyn DavDirEntry>>> {
+        let relative = path.as_rel_ospath();
+        if hidden(relative) {
+            return not_found();
+        }
+        let top_level = relative.as_os_str().is_empty();
+        async move {
+            let entries = self.fs.read_dir(path, meta).await?;
+            let shared = entries.filter(move |entry| {
+                future::ready(match entry {
+                    Ok(entry) => !hidden_name(&entry.name(), top_level),
+                    Err(_) => true,
+                })
+            });
+            Ok(Box::pin(shared) as FsStream<Box<dyn DavDirEntry>>)
+        }
+        .boxed()
+    }
+
+    fn metadata<'a>(&'a self, path: &'a DavPath) -> FsFuture<'a, Box<dyn DavMetaData>> {
+        if hidden(path.as_rel_ospath()) {
+            return not_found();
+        }
+        self.fs.metadata(path)
+    }
+
+    fn symlink_metadata<'a>(&'a self, path: &'a DavPath) -> FsFuture<'a, Box<dyn DavMetaData>> {
+        if hidden(path.as_rel_ospath()) {
+            return not_found();
+        }
+        self.fs.symlink_metadata(path)
+    }
+
+    fn create_dir<'a>(&'a self, path: &'a DavPath) -> FsFuture<'a, ()> {
+        if hidden(path.as_rel_ospath()) {
+            return not_found();
+        }
+        self.fs.create_dir(path)
+    }
+
+    fn remove_dir<'a>(&'a self, path: &'a DavPath) -> FsFuture<'a, ()> {
+        if hidden(path.as_rel_ospath()) {
+            return not_found();
+        }
+        self.fs.remove_dir(path)
+    }
+
+    fn remove_file<'a>(&'a self, path: &'a DavPath) -> FsFuture<'a, ()> {
+        if hidden(path.as_rel_ospath()) {
+            return not_found();
+        }
+        self.fs.remove_file(path)
+    }
+
+    fn rename<'a>(&'a self, from: &'a DavPath, to: &'a DavPath) -> FsFuture<'a, ()> {
+        if hidden(from.as_rel_ospath()) || hidden(to.as_rel_ospath()) {
+            return not_found();
+        }
+        self.fs.rename(from, to)
+    }
+
+    fn copy<'a>(&'a self, from: &'a DavPath, to: &'a DavPath) -> FsFuture<'a, ()> {
+        if hidden(from.as_rel_ospath()) || hidden(to.as_rel_ospath()) {
+            return not_found();
+        }
+        self.fs.copy(from, to)
+    }
+
+    fn get_quota(&self) -> FsFuture<(u64, Option<u64>)> {
+        self.fs.get_quota()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hidden() {
+        assert!(hidden(Path::new(".git/config")));
+        assert!(hidden(Path::new("config/cook.toml")));
+        assert!(hidden(Path::new("Dinners/.secret.cook")));
+        assert!(hidden(Path::new("../etc/passwd")));
+        assert!(!hidden(Path::new("Dinners/config/Pasta.cook")));
+        assert!(!hidden(Path::new("Pasta.cook")));
+        assert!(!hidden(Path::new("")));
+    }
+
+    #[test]
+    fn test_logged_in() {
+        let mut headers = HeaderMap::new();
+        assert!(!logged_in(&headers, "secret"));
+        // me:secret
+        headers.insert(header::AUTHORIZATION, "Basic bWU6c2VjcmV0".parse().unwrap());
+        assert!(logged_in(&headers, "secret"));
+        assert!(!logged_in(&headers, "other"));
+    }
+}
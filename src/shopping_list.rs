@@ -38,12 +38,22 @@ use yansi::Paint;
 use cooklang::{
     aisle::AisleConf,
     ingredient_list::IngredientList,
-    quantity::{GroupedQuantity, Quantity, Value},
+    quantity::{GroupedQuantity, Value},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    util::{extract_ingredients, write_to_output, PARSER},
+    util::{
+        aliases::{self, AppliedAlias},
+        extract_ingredients,
+        format::{display_grouped, display_quantity},
+        get_recipe,
+        pantry::apply_aliases,
+        parse_recipe_from_entry,
+        vtodo::{self, render_todos, CalDav, Todo},
+        walk::WalkPolicy,
+        write_to_output, PARSER,
+    },
     Context,
 };
 
@@ -61,6 +71,7 @@ pub struct ShoppingListArgs {
     ///   "Pasta.cook:3"          # Triple the pasta recipe
     ///   recipe1.cook recipe2.cook  # Multiple recipes
     ///   desserts/*.cook         # All recipes in desserts folder
+    #[arg(add = crate::completions::recipes())]
     recipes: Vec<String>,
 
     /// Base directory to search for recipe files
@@ -86,8 +97,10 @@ pub struct ShoppingListArgs {
 
     /// Output format for the shopping list
     ///
-    /// Available formats: human (default), json, yaml, markdown
+    /// Available formats: human (default), json, yaml, vtodo
     /// If not specified, format is inferred from output file extension.
+    /// vtodo is an iCalendar file with a to-do for every item, categorized
+    /// by aisle, for reminder and task apps.
     #[arg(short, long, value_enum)]
     format: Option<OutputFormat>,
 
@@ -106,6 +119,44 @@ pub struct ShoppingListArgs {
     /// Display only ingredient names, one per line, without amounts
     #[arg(long)]
     ingredients_only: bool,
+
+    /// How to group the ingredients
+    ///
+    /// By recipe, every recipe is listed with its own scaled ingredients,
+    /// for portioning when preparing several dishes. The pantry isn't
+    /// subtracted then.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = GroupBy::Aisle,
+        conflicts_with_all = ["plain", "ingredients_only"]
+    )]
+    group_by: GroupBy,
+
+    /// How to order the items
+    ///
+    /// aisle keeps the order of the aisle file, alpha sorts them by name
+    /// and recipe-order lists them as they first appear in the recipes.
+    /// Items stay under their aisle category unless --plain is given.
+    #[arg(long, value_enum, default_value_t = ListSort::Aisle, conflicts_with = "group_by")]
+    sort: ListSort,
+
+    /// Put the items on a CalDAV task list instead of printing them
+    ///
+    /// The URL of the list, like a Nextcloud Tasks or iCloud Reminders
+    /// list. Every item becomes a to-do, categorized by aisle (or recipe
+    /// with --group-by recipe).
+    #[arg(long, value_name = "URL")]
+    caldav: Option<String>,
+
+    /// User name for the CalDAV server
+    #[arg(long, requires = "caldav")]
+    caldav_user: Option<String>,
+
+    /// Password for the CalDAV server, an app password where there are
+    /// those
+    #[arg(long, requires = "caldav")]
+    caldav_password: Option<String>,
 }
 
 impl ShoppingListArgs {
@@ -119,12 +170,170 @@ enum OutputFormat {
     Human,
     Json,
     Yaml,
+    #[value(alias("ics"))]
+    Vtodo,
 }
 
-pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
+/// How ingredients are grouped in a shopping list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// Merged and grouped by aisle category
+    Aisle,
+    /// Listed under each recipe
+    Recipe,
+}
+
+/// How the items of a shopping list are ordered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListSort {
+    /// In the order of the aisle file
+    #[default]
+    Aisle,
+    /// By name
+    Alpha,
+    /// In the order they first appear in the recipes
+    RecipeOrder,
+}
+
+/// Puts the items of a shopping list in order
+#[derive(Debug, Default)]
+pub struct ItemOrder {
+    sort: ListSort,
+    /// Ingredient names as they first appear, for [`ListSort::RecipeOrder`]
+    recipe_order: Vec<String>,
+}
+
+impl ItemOrder {
+    /// Order for the recipes of `entries`, like [`resolve_entries`] gives
+    ///
+    /// Recipes are only read for [`ListSort::RecipeOrder`].
+    pub fn new(sort: ListSort, entries: &[(String, Utf8PathBuf)]) -> Self {
+        let mut recipe_order = Vec::new();
+        if sort == ListSort::RecipeOrder {
+            for (entry, base_path) in entries {
+                let name = crate::util::split_recipe_name_and_scaling_factor(entry)
+                    .map_or(entry.as_str(), |(name, _)| name);
+                // Recipes that can't be read already failed to list
+                let Ok(recipe) = get_recipe(base_path, name)
+                    .and_then(|entry| parse_recipe_from_entry(&entry, 1.0))
+                else {
+                    continue;
+                };
+                for ingredient in &recipe.ingredients {
+                    let name = ingredient.display_name().to_string();
+                    if !recipe_order.contains(&name) {
+                        recipe_order.push(name);
+                    }
+                }
+            }
+        }
+        Self { sort, recipe_order }
+    }
+
+    /// The items of a list in order
+    pub fn plain(&self, list: IngredientList) -> Vec<(String, GroupedQuantity)> {
+        let mut items: Vec<_> = list.into_iter().collect();
+        self.sort(&mut items);
+        items
+    }
+
+    /// The items of a list by aisle category, in order within each
+    pub fn categorize(
+        &self,
+        list: IngredientList,
+        aisle: &AisleConf,
+    ) -> Vec<(String, Vec<(String, GroupedQuantity)>)> {
+        list.categorize(aisle)
+            .into_iter()
+            .map(|(category, items)| {
+                let mut items: Vec<_> = items.into_iter().collect();
+                self.sort(&mut items);
+                (category.to_string(), items)
+            })
+            .collect()
+    }
+
+    fn sort(&self, items: &mut [(String, GroupedQuantity)]) {
+        match self.sort {
+            ListSort::Aisle => {}
+            ListSort::Alpha => items.sort_by_key(|(name, _)| name.to_lowercase()),
+            // Ingredients of referenced recipes come last
+            ListSort::RecipeOrder => items.sort_by_key(|(name, _)| {
+                self.recipe_order
+                    .iter()
+                    .position(|seen| aliases::same_ingredient(seen, name))
+                    .unwrap_or(usize::MAX)
+            }),
+        }
+    }
+}
+
+/// Scale and merge the ingredients of recipes into one list
+///
+/// Entries are recipe names or paths with an optional `:scale`, and
+/// directories, which add every recipe in them.
+pub fn collect_ingredients(
+    ctx: &Context,
+    recipes: &[String],
+    ignore_references: bool,
+) -> Result<IngredientList> {
+    // retrieve, scale and merge ingredients
+    let mut list = IngredientList::new();
+    let mut seen = BTreeMap::new();
+
+    for (entry, base_path) in resolve_entries(ctx, recipes)? {
+        extract_ingredients(
+            &entry,
+            &mut list,
+            &mut seen,
+            &base_path,
+            PARSER.converter(),
+            ignore_references,
+        )?;
+    }
+
+    Ok(list)
+}
+
+/// Scale the ingredients of each recipe, without merging recipes
+///
+/// Entries are like for [`collect_ingredients`]; directories add one list
+/// for each of their recipes. Lists are named by their entry.
+pub fn collect_by_recipe(
+    ctx: &Context,
+    recipes: &[String],
+    ignore_references: bool,
+) -> Result<Vec<(String, IngredientList)>> {
+    let mut lists = Vec::new();
+    for (entry, base_path) in resolve_entries(ctx, recipes)? {
+        let mut list = IngredientList::new();
+        extract_ingredients(
+            &entry,
+            &mut list,
+            &mut BTreeMap::new(),
+            &base_path,
+            PARSER.converter(),
+            ignore_references,
+        )?;
+        lists.push((entry, list));
+    }
+    Ok(lists)
+}
+
+/// Each entry as looked up in a directory, with that directory
+///
+/// Directories are expanded to their recipes, and paths to files become the
+/// file name in the file's directory, so references resolve from there.
+pub(crate) fn resolve_entries(
+    ctx: &Context,
+    recipes: &[String],
+) -> Result<Vec<(String, Utf8PathBuf)>> {
     // Expand directories to .cook files
+    let policy = WalkPolicy::current();
     let mut expanded_recipes = Vec::new();
-    for entry in &args.recipes {
+    for entry in recipes {
         let path = if entry.contains(':') {
             // Handle recipe:scaling syntax
             let (recipe_path, _) = entry.split_once(':').unwrap();
@@ -141,14 +350,22 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
                 let file_path = dir_entry.path();
                 if let Some(ext) = file_path.extension() {
                     if ext == "cook" {
-                        if let Ok(utf8_path) = Utf8PathBuf::from_path_buf(file_path) {
-                            // Preserve the scaling factor if it was specified
-                            if entry.contains(':') {
-                                let scaling = entry.split_once(':').unwrap().1;
-                                expanded_recipes.push(format!("{utf8_path}:{scaling}"));
-                            } else {
-                                expanded_recipes.push(utf8_path.to_string());
-                            }
+                        let Ok(utf8_path) = Utf8PathBuf::from_path_buf(file_path) else {
+                            tracing::warn!(
+                                "Skipping {}: not a UTF-8 path",
+                                dir_entry.path().display()
+                            );
+                            continue;
+                        };
+                        if !policy.allows(&path, &utf8_path) {
+                            continue;
+                        }
+                        // Preserve the scaling factor if it was specified
+                        if entry.contains(':') {
+                            let scaling = entry.split_once(':').unwrap().1;
+                            expanded_recipes.push(format!("{utf8_path}:{scaling}"));
+                        } else {
+                            expanded_recipes.push(utf8_path.to_string());
                         }
                     }
                 }
@@ -160,10 +377,68 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
     }
 
     // If no recipes were expanded (empty directory or no directories), use original list
-    if expanded_recipes.is_empty() && !args.recipes.is_empty() {
-        expanded_recipes = args.recipes.clone();
+    if expanded_recipes.is_empty() && !recipes.is_empty() {
+        expanded_recipes = recipes.to_vec();
     }
 
+    let mut resolved = Vec::new();
+    for entry in expanded_recipes {
+        // Determine the base path for this entry
+        // If the entry is an absolute path or relative path to a file,
+        // use its parent directory as the base for resolving references
+        let entry_without_scaling = entry.split(':').next().unwrap_or(&entry);
+        let entry_path = Utf8PathBuf::from(entry_without_scaling);
+
+        // Check if this is a file path (contains '/' or starts with './')
+        let (actual_entry, base_path) = if entry_without_scaling.contains('/') {
+            // This looks like a file path, not just a recipe name
+            let full_path = if entry_path.is_absolute() {
+                entry_path.clone()
+            } else {
+                // Clean up the path by removing ./ prefix if present
+                let clean_entry = entry_without_scaling
+                    .strip_prefix("./")
+                    .unwrap_or(entry_without_scaling);
+                ctx.base_path().join(clean_entry)
+            };
+
+            if full_path.exists() && full_path.is_file() {
+                // File exists, use its parent directory as base
+                let base = full_path
+                    .parent()
+                    .map(|p| p.to_path_buf())
+                    .unwrap_or_else(|| ctx.base_path().clone());
+
+                // Convert to just the filename for the recipe lookup
+                let filename = full_path
+                    .file_name()
+                    .map(|n| n.to_string())
+                    .unwrap_or_else(|| entry_without_scaling.to_string());
+
+                // Preserve scaling if present
+                let actual = if entry.contains(':') {
+                    format!("{}:{}", filename, entry.split(':').nth(1).unwrap())
+                } else {
+                    filename
+                };
+
+                (actual, base)
+            } else {
+                // File doesn't exist, but still treat as path
+                // This will fail with a better error message
+                (entry.to_string(), ctx.base_path().clone())
+            }
+        } else {
+            // This is just a recipe name, use as-is
+            (entry.to_string(), ctx.base_path().clone())
+        };
+        resolved.push((actual_entry, base_path));
+    }
+
+    Ok(resolved)
+}
+
+pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
     let aile_path = args
         .aisle
         .or_else(|| ctx.aisle())
@@ -212,6 +487,7 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
                 let mut pantry_conf = result.output().cloned();
                 if let Some(ref mut pantry) = pantry_conf {
                     pantry.rebuild_index();
+                    apply_aliases(pantry);
                     tracing::debug!(
                         "Pantry loaded successfully with {} sections",
                         pantry.sections.len()
@@ -234,97 +510,83 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
     let format = args.format.unwrap_or_else(|| match &args.output {
         Some(p) => match p.extension() {
             Some("json") => OutputFormat::Json,
+            Some("ics") => OutputFormat::Vtodo,
             _ => OutputFormat::Human,
         },
         None => OutputFormat::Human,
     });
+    let today = chrono::Local::now().date_naive();
 
-    // retrieve, scale and merge ingredients
-    let mut list = IngredientList::new();
-    let mut seen = BTreeMap::new();
+    let pretty = args.pretty || ctx.config().output.pretty.unwrap_or(false);
 
-    let ignore_references = args.ignore_references;
-
-    for entry in expanded_recipes {
-        // Determine the base path for this entry
-        // If the entry is an absolute path or relative path to a file,
-        // use its parent directory as the base for resolving references
-        let entry_without_scaling = entry.split(':').next().unwrap_or(&entry);
-        let entry_path = Utf8PathBuf::from(entry_without_scaling);
-
-        // Check if this is a file path (contains '/' or starts with './')
-        let (actual_entry, base_path) = if entry_without_scaling.contains('/') {
-            // This looks like a file path, not just a recipe name
-            let full_path = if entry_path.is_absolute() {
-                entry_path.clone()
-            } else {
-                // Clean up the path by removing ./ prefix if present
-                let clean_entry = entry_without_scaling
-                    .strip_prefix("./")
-                    .unwrap_or(entry_without_scaling);
-                ctx.base_path().join(clean_entry)
-            };
-
-            if full_path.exists() && full_path.is_file() {
-                // File exists, use its parent directory as base
-                let base = full_path
-                    .parent()
-                    .map(|p| p.to_path_buf())
-                    .unwrap_or_else(|| ctx.base_path().clone());
-
-                // Convert to just the filename for the recipe lookup
-                let filename = full_path
-                    .file_name()
-                    .map(|n| n.to_string())
-                    .unwrap_or_else(|| entry_without_scaling.to_string());
-
-                // Preserve scaling if present
-                let actual = if entry.contains(':') {
-                    format!("{}:{}", filename, entry.split(':').nth(1).unwrap())
-                } else {
-                    filename
-                };
-
-                (actual, base)
-            } else {
-                // File doesn't exist, but still treat as path
-                // This will fail with a better error message
-                (entry.to_string(), ctx.base_path().clone())
+    if args.group_by == GroupBy::Recipe {
+        let mut applied = Vec::new();
+        let lists: Vec<_> = collect_by_recipe(ctx, &args.recipes, args.ignore_references)?
+            .into_iter()
+            .map(|(recipe, list)| {
+                let (list, recipe_aliases) = aliases::apply(list, PARSER.converter());
+                applied.extend(recipe_aliases);
+                (recipe, list)
+            })
+            .collect();
+        applied.sort();
+        applied.dedup();
+        report_aliases(&applied);
+        if let Some(caldav) = caldav(&args) {
+            return upload_todos(&caldav, &recipe_todos(lists), today);
+        }
+        return write_to_output(args.output.as_deref(), |w| {
+            match format {
+                OutputFormat::Human => write!(w, "{}", build_recipe_table(lists))?,
+                OutputFormat::Vtodo => write!(w, "{}", render_todos(&recipe_todos(lists), today))?,
+                OutputFormat::Json => {
+                    let value = build_recipe_value(lists, &aisle);
+                    if pretty {
+                        serde_json::to_writer_pretty(w, &value)?;
+                    } else {
+                        serde_json::to_writer(w, &value)?;
+                    }
+                }
+                OutputFormat::Yaml => {
+                    serde_yaml::to_writer(w, &build_recipe_value(lists, &aisle))?;
+                }
             }
-        } else {
-            // This is just a recipe name, use as-is
-            (entry.to_string(), ctx.base_path().clone())
-        };
-
-        extract_ingredients(
-            &actual_entry,
-            &mut list,
-            &mut seen,
-            &base_path,
-            PARSER.converter(),
-            ignore_references,
-        )?;
+            Ok(())
+        });
     }
 
+    let list = collect_ingredients(ctx, &args.recipes, args.ignore_references)?;
+    let order = ItemOrder::new(args.sort, &resolve_entries(ctx, &args.recipes)?);
+    let (mut list, applied) = aliases::apply(list, PARSER.converter());
+    report_aliases(&applied);
+
     // Subtract pantry quantities from shopping list
     if let Some(pantry_conf) = &pantry {
         list = list.subtract_pantry(pantry_conf, PARSER.converter());
     }
 
+    if let Some(caldav) = caldav(&args) {
+        let todos = aisle_todos(list, &aisle, &order, !args.ingredients_only);
+        return upload_todos(&caldav, &todos, today);
+    }
+
     write_to_output(args.output.as_deref(), |w| {
         if args.ingredients_only {
             match format {
                 OutputFormat::Human => {
                     // Simple output: one ingredient per line, no amounts
-                    for (ingredient, _quantity) in list {
+                    for (ingredient, _quantity) in order.plain(list) {
                         writeln!(w, "{ingredient}")?;
                     }
                 }
                 OutputFormat::Json => {
                     // Output as a JSON array of strings
-                    let ingredients: Vec<String> =
-                        list.into_iter().map(|(ingredient, _)| ingredient).collect();
-                    if args.pretty {
+                    let ingredients: Vec<String> = order
+                        .plain(list)
+                        .into_iter()
+                        .map(|(ingredient, _)| ingredient)
+                        .collect();
+                    if pretty {
                         serde_json::to_writer_pretty(w, &ingredients)?;
                     } else {
                         serde_json::to_writer(w, &ingredients)?;
@@ -332,30 +594,47 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
                 }
                 OutputFormat::Yaml => {
                     // Output as a YAML array of strings
-                    let ingredients: Vec<String> =
-                        list.into_iter().map(|(ingredient, _)| ingredient).collect();
+                    let ingredients: Vec<String> = order
+                        .plain(list)
+                        .into_iter()
+                        .map(|(ingredient, _)| ingredient)
+                        .collect();
                     serde_yaml::to_writer(w, &ingredients)?;
                 }
+                OutputFormat::Vtodo => {
+                    write!(
+                        w,
+                        "{}",
+                        render_todos(&aisle_todos(list, &aisle, &order, false), today)
+                    )?;
+                }
             }
         } else {
             match format {
                 OutputFormat::Human => {
-                    let table = build_human_table(list, &aisle, args.plain);
+                    let table = build_human_table(list, &aisle, args.plain, &order);
                     write!(w, "{table}")?;
                 }
                 OutputFormat::Json => {
-                    let value = build_json_value(list, &aisle, args.plain);
-                    if args.pretty {
+                    let value = build_json_value(list, &aisle, args.plain, &order);
+                    if pretty {
                         serde_json::to_writer_pretty(w, &value)?;
                     } else {
                         serde_json::to_writer(w, &value)?;
                     }
                 }
                 OutputFormat::Yaml => {
-                    let value = build_yaml_value(list, &aisle);
+                    let value = build_yaml_value(list, &aisle, &order);
 
                     serde_yaml::to_writer(w, &value)?;
                 }
+                OutputFormat::Vtodo => {
+                    write!(
+                        w,
+                        "{}",
+                        render_todos(&aisle_todos(list, &aisle, &order, true), today)
+                    )?;
+                }
             }
         }
         Ok(())
@@ -365,30 +644,84 @@ pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
 fn total_quantity_fmt(qty: &GroupedQuantity, row: &mut tabular::Row) {
     let content = qty
         .iter()
-        .map(quantity_fmt)
+        .map(display_quantity)
         .reduce(|s, q| format!("{s}, {q}"))
         .unwrap_or_default();
     row.add_ansi_cell(content);
 }
 
-fn quantity_fmt(qty: &Quantity) -> String {
-    if let Some(unit) = qty.unit() {
-        format!("{} {}", qty.value(), unit)
-    } else {
-        format!("{}", qty.value())
+/// Tell which ingredients were listed under another name
+fn report_aliases(applied: &[AppliedAlias]) {
+    for alias in applied {
+        eprintln!("Listed {} as {}", alias.alias, alias.name);
+    }
+}
+
+fn caldav(args: &ShoppingListArgs) -> Option<CalDav> {
+    Some(CalDav {
+        url: args.caldav.clone()?,
+        user: args.caldav_user.clone(),
+        password: args.caldav_password.clone(),
+    })
+}
+
+fn upload_todos(caldav: &CalDav, todos: &[Todo], date: chrono::NaiveDate) -> Result<()> {
+    let uploaded = tokio::runtime::Runtime::new()?.block_on(vtodo::upload(caldav, todos, date))?;
+    println!("Added {uploaded} items to {}", caldav.url);
+    Ok(())
+}
+
+/// A to-do for every item, categorized by aisle
+fn aisle_todos(
+    list: IngredientList,
+    aisle: &AisleConf,
+    order: &ItemOrder,
+    with_quantities: bool,
+) -> Vec<Todo> {
+    let mut todos = Vec::new();
+    for (category, items) in order.categorize(list, aisle) {
+        for (name, quantity) in items {
+            todos.push(Todo {
+                name,
+                quantity: (with_quantities && !quantity.is_empty())
+                    .then(|| display_grouped(&quantity)),
+                category: Some(category.clone()),
+            });
+        }
+    }
+    todos
+}
+
+/// A to-do for every ingredient, categorized by recipe
+fn recipe_todos(lists: Vec<(String, IngredientList)>) -> Vec<Todo> {
+    let mut todos = Vec::new();
+    for (recipe, list) in lists {
+        for (name, quantity) in list {
+            todos.push(Todo {
+                name,
+                quantity: (!quantity.is_empty()).then(|| display_grouped(&quantity)),
+                category: Some(recipe.clone()),
+            });
+        }
     }
+    todos
 }
 
-fn build_human_table(list: IngredientList, aisle: &AisleConf, plain: bool) -> tabular::Table {
+fn build_human_table(
+    list: IngredientList,
+    aisle: &AisleConf,
+    plain: bool,
+    order: &ItemOrder,
+) -> tabular::Table {
     let mut table = tabular::Table::new("{:<} {:<}");
     if plain {
-        for (igr, q) in list {
+        for (igr, q) in order.plain(list) {
             let mut row = tabular::Row::new().with_cell(igr);
             total_quantity_fmt(&q, &mut row);
             table.add_row(row);
         }
     } else {
-        let categories = list.categorize(aisle);
+        let categories = order.categorize(list, aisle);
         for (cat, items) in categories {
             table.add_heading(format!("[{}]", cat.green()));
             for (igr, q) in items {
@@ -401,10 +734,42 @@ fn build_human_table(list: IngredientList, aisle: &AisleConf, plain: bool) -> ta
     table
 }
 
+fn build_recipe_table(lists: Vec<(String, IngredientList)>) -> tabular::Table {
+    let mut table = tabular::Table::new("{:<} {:<}");
+    for (recipe, list) in lists {
+        table.add_heading(format!("[{}]", recipe.green()));
+        for (igr, q) in list {
+            let mut row = tabular::Row::new().with_cell(igr);
+            total_quantity_fmt(&q, &mut row);
+            table.add_row(row);
+        }
+    }
+    table
+}
+
+/// Recipes with their ingredients, each like the plain JSON list
+fn build_recipe_value<'a>(
+    lists: Vec<(String, IngredientList)>,
+    aisle: &'a AisleConf<'a>,
+) -> serde_json::Value {
+    serde_json::Value::Array(
+        lists
+            .into_iter()
+            .map(|(recipe, list)| {
+                serde_json::json!({
+                    "recipe": recipe,
+                    "items": build_json_value(list, aisle, true, &ItemOrder::default()),
+                })
+            })
+            .collect(),
+    )
+}
+
 fn build_json_value<'a>(
     list: IngredientList,
     aisle: &'a AisleConf<'a>,
     plain: bool,
+    order: &ItemOrder,
 ) -> serde_json::Value {
     #[derive(Serialize)]
     struct Quantity {
@@ -438,10 +803,18 @@ fn build_json_value<'a>(
     }
 
     if plain {
-        serde_json::to_value(list.into_iter().map(Ingredient::from).collect::<Vec<_>>()).unwrap()
+        serde_json::to_value(
+            order
+                .plain(list)
+                .into_iter()
+                .map(Ingredient::from)
+                .collect::<Vec<_>>(),
+        )
+        .unwrap()
     } else {
         serde_json::to_value(
-            list.categorize(aisle)
+            order
+                .categorize(list, aisle)
                 .into_iter()
                 .map(|(category, items)| Category {
                     category,
@@ -453,7 +826,11 @@ fn build_json_value<'a>(
     }
 }
 
-fn build_yaml_value<'a>(list: IngredientList, aisle: &'a AisleConf<'a>) -> serde_yaml::Value {
+fn build_yaml_value<'a>(
+    list: IngredientList,
+    aisle: &'a AisleConf<'a>,
+    order: &ItemOrder,
+) -> serde_yaml::Value {
     #[derive(Serialize)]
     struct Quantity {
         value: Value,
@@ -487,7 +864,8 @@ fn build_yaml_value<'a>(list: IngredientList, aisle: &'a AisleConf<'a>) -> serde
 
     // Convert to categorized list and serialize to YAML
     serde_yaml::to_value(
-        list.categorize(aisle)
+        order
+            .categorize(list, aisle)
             .into_iter()
             .map(|(category, items)| Category {
                 category,
@@ -0,0 +1,328 @@
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, ValueEnum};
+use cooklang_find::RecipeEntry;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::{
+    server::db::Database,
+    util::{
+        cooked::{cooked_history, server_database, CookedHistory},
+        format::format_decimal,
+        metadata_cache::{collection_metadata, RecipeMetadata},
+        tree_entries,
+        walk::build_tree,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Directory to scan for recipe files
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// Number of ingredients and cookware items to list
+    #[arg(short = 'n', long, default_value_t = 10)]
+    top: usize,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct CollectionStats {
+    recipes: usize,
+    menus: usize,
+    /// Recipes that failed to parse
+    invalid: Vec<String>,
+    by_directory: BTreeMap<String, usize>,
+    by_tag: BTreeMap<Arc<str>, usize>,
+    /// Recipes from each website (by domain) or book
+    by_source: BTreeMap<String, usize>,
+    /// Recipes without a `source`
+    without_source: usize,
+    /// Number of recipes using each ingredient, most used first
+    ingredients: Vec<Usage>,
+    /// Number of recipes using each cookware item, most used first
+    cookware: Vec<Usage>,
+    /// Average total time in minutes of the recipes that have one
+    average_time: Option<f64>,
+    recipes_with_time: usize,
+    missing_image: Vec<String>,
+    missing_servings: Vec<String>,
+    /// Recipes cooked at least once, from the cooked log
+    recipes_cooked: usize,
+    /// Most often cooked recipes, most cooked first
+    most_cooked: Vec<Cooked>,
+}
+
+#[derive(Debug, Serialize)]
+struct Usage {
+    name: Arc<str>,
+    recipes: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Cooked {
+    name: String,
+    #[serde(flatten)]
+    history: CookedHistory,
+}
+
+pub fn run(ctx: &Context, args: StatsArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let db = server_database(base_path, ctx.config());
+    let stats = collection_stats(base_path, db.as_ref(), args.top)?;
+
+    match args.format {
+        OutputFormat::Human => print_human(&stats),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    }
+
+    Ok(())
+}
+
+/// Statistics of the collection at `base_path`, with the `top` most used
+/// ingredients, cookware and cooked recipes
+pub(crate) fn collection_stats(
+    base_path: &Utf8Path,
+    db: Option<&Database>,
+    top: usize,
+) -> Result<CollectionStats> {
+    let tree = build_tree(base_path)?;
+
+    let mut stats = CollectionStats::default();
+    let mut ingredients = BTreeMap::new();
+    let mut cookware = BTreeMap::new();
+    let mut total_time = 0u64;
+
+    let (menus, recipes): (Vec<_>, Vec<_>) = tree_entries(&tree)
+        .into_iter()
+        .partition(|entry| entry.is_menu());
+    stats.menus = menus.len();
+    let names: Vec<String> = recipes
+        .iter()
+        .filter_map(|entry| entry.path())
+        .map(|path| path.strip_prefix(base_path).unwrap_or(path).to_string())
+        .collect();
+    for (entry, metadata) in collection_metadata(base_path, recipes) {
+        add_recipe(
+            &entry,
+            metadata,
+            base_path,
+            &mut stats,
+            &mut ingredients,
+            &mut cookware,
+            &mut total_time,
+        );
+    }
+
+    stats.average_time =
+        (stats.recipes_with_time > 0).then(|| total_time as f64 / stats.recipes_with_time as f64);
+    stats.ingredients = top_usage(ingredients, top);
+    stats.cookware = top_usage(cookware, top);
+    let mut cooked = cooked_recipes(cooked_history(base_path, db)?, &names);
+    stats.recipes_cooked = cooked.len();
+    cooked.truncate(top);
+    stats.most_cooked = cooked;
+
+    Ok(stats)
+}
+
+fn add_recipe(
+    entry: &RecipeEntry,
+    metadata: Arc<RecipeMetadata>,
+    base_path: &Utf8Path,
+    stats: &mut CollectionStats,
+    ingredients: &mut BTreeMap<Arc<str>, usize>,
+    cookware: &mut BTreeMap<Arc<str>, usize>,
+    total_time: &mut u64,
+) {
+    let path = entry
+        .path()
+        .map(|p| p.strip_prefix(base_path).unwrap_or(p).to_path_buf());
+    let name = path
+        .as_ref()
+        .map(|p| p.to_string())
+        .or_else(|| entry.name().clone())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    stats.recipes += 1;
+
+    let dir = path
+        .as_ref()
+        .and_then(|p| p.parent())
+        .map(|p| p.as_str())
+        .filter(|p| !p.is_empty())
+        .unwrap_or(".")
+        .to_string();
+    *stats.by_directory.entry(dir).or_default() += 1;
+
+    match &metadata.error {
+        None => {
+            for tag in &metadata.tags {
+                *stats.by_tag.entry(Arc::clone(tag)).or_default() += 1;
+            }
+            match &metadata.source {
+                Some(source) => *stats.by_source.entry(source.clone()).or_default() += 1,
+                None => stats.without_source += 1,
+            }
+            for igr in &metadata.ingredients {
+                *ingredients.entry(Arc::clone(igr)).or_default() += 1;
+            }
+            for item in &metadata.cookware {
+                *cookware.entry(Arc::clone(item)).or_default() += 1;
+            }
+
+            if let Some(time) = metadata.total_time {
+                stats.recipes_with_time += 1;
+                *total_time += time as u64;
+            }
+
+            if !metadata.has_servings {
+                stats.missing_servings.push(name.clone());
+            }
+        }
+        Some(e) => {
+            warn!("Failed to parse recipe '{name}': {e}");
+            stats.invalid.push(name.clone());
+        }
+    }
+
+    if entry.title_image().is_none() {
+        stats.missing_image.push(name);
+    }
+}
+
+/// Recipes that were cooked, only the ones still in the collection, most
+/// cooked first
+fn cooked_recipes(mut history: HashMap<String, CookedHistory>, recipes: &[String]) -> Vec<Cooked> {
+    let mut cooked: Vec<_> = recipes
+        .iter()
+        .filter_map(|name| {
+            history.remove(name).map(|history| Cooked {
+                name: name.clone(),
+                history,
+            })
+        })
+        .collect();
+    cooked.sort_by(|a, b| {
+        b.history
+            .times_cooked
+            .cmp(&a.history.times_cooked)
+            .then_with(|| b.history.last_cooked_at().cmp(&a.history.last_cooked_at()))
+    });
+    cooked
+}
+
+fn top_usage(counts: BTreeMap<Arc<str>, usize>, n: usize) -> Vec<Usage> {
+    let mut usage: Vec<_> = counts
+        .into_iter()
+        .map(|(name, recipes)| Usage { name, recipes })
+        .collect();
+    // stable sort keeps names alphabetical within the same count
+    usage.sort_by(|a, b| b.recipes.cmp(&a.recipes));
+    usage.truncate(n);
+    usage
+}
+
+fn print_human(stats: &CollectionStats) {
+    println!("Recipes: {}", stats.recipes);
+    if stats.menus > 0 {
+        println!("Menus: {}", stats.menus);
+    }
+    if let Some(avg) = stats.average_time {
+        println!(
+            "Average time: {} min ({} of {} recipes have a time)",
+            format_decimal(avg.round()),
+            stats.recipes_with_time,
+            stats.recipes
+        );
+    }
+
+    print_counts(
+        "By directory",
+        stats.by_directory.iter().map(|(k, v)| (&**k, *v)),
+    );
+
+    let mut tags: Vec<_> = stats.by_tag.iter().collect();
+    tags.sort_by(|a, b| b.1.cmp(a.1));
+    print_counts("By tag", tags.into_iter().map(|(k, v)| (&**k, *v)));
+
+    let mut sources: Vec<_> = stats.by_source.iter().collect();
+    sources.sort_by(|a, b| b.1.cmp(a.1));
+    print_counts(
+        &format!("By source ({} without one)", stats.without_source),
+        sources.into_iter().map(|(k, v)| (&**k, *v)),
+    );
+
+    print_counts(
+        "Most used ingredients",
+        stats.ingredients.iter().map(|u| (&*u.name, u.recipes)),
+    );
+    print_counts(
+        "Most used cookware",
+        stats.cookware.iter().map(|u| (&*u.name, u.recipes)),
+    );
+
+    if !stats.most_cooked.is_empty() {
+        println!();
+        println!(
+            "Most cooked ({} of {} recipes cooked):",
+            stats.recipes_cooked, stats.recipes
+        );
+        let mut table = tabular::Table::new("  {:<}  {:>}  {:<}");
+        for cooked in &stats.most_cooked {
+            table.add_row(
+                tabular::Row::new()
+                    .with_cell(&cooked.name)
+                    .with_cell(format!("{}×", cooked.history.times_cooked))
+                    .with_cell(format!("last {}", cooked.history.last_cooked_day())),
+            );
+        }
+        print!("{table}");
+    }
+
+    print_list("Missing servings", &stats.missing_servings);
+    print_list("Missing image", &stats.missing_image);
+    print_list("Failed to parse", &stats.invalid);
+}
+
+fn print_counts<'a>(title: &str, rows: impl Iterator<Item = (&'a str, usize)>) {
+    let mut table = tabular::Table::new("  {:<}  {:>}");
+    let mut empty = true;
+    for (name, count) in rows {
+        empty = false;
+        table.add_row(tabular::Row::new().with_cell(name).with_cell(count));
+    }
+    if empty {
+        return;
+    }
+    println!();
+    println!("{title}:");
+    print!("{table}");
+}
+
+fn print_list(title: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    println!();
+    println!("{title} ({}):", items.len());
+    for item in items {
+        println!("  • {item}");
+    }
+}
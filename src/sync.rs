@@ -0,0 +1,242 @@
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use std::process::Command;
+
+use crate::{
+    util::git::{git, git_ok},
+    Context,
+};
+
+/// Files cook writes on its own. Conflicts in them are resolved by keeping
+/// this device's version instead of stopping the sync.
+const GENERATED_FILES: &[&str] = &[".shopping_list.txt"];
+
+#[derive(Debug, Args)]
+pub struct SyncArgs {
+    /// Directory of the recipe collection
+    ///
+    /// Must be inside a git repository. Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// Commit message to use instead of the generated one
+    #[arg(short, long)]
+    message: Option<String>,
+
+    /// Commit and pull, but don't push
+    #[arg(long)]
+    no_push: bool,
+}
+
+pub fn run(ctx: &Context, args: SyncArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+
+    let inside = git(base_path, &["rev-parse", "--is-inside-work-tree"])
+        .context("Failed to run git, is it installed?")?;
+    if !inside.status.success() {
+        bail!(
+            "{base_path} is not in a git repository\n\
+             To start syncing it, run:\n  \
+             git init && git remote add origin <url>"
+        );
+    }
+
+    if git(
+        base_path,
+        &["rev-parse", "--verify", "--quiet", "REBASE_HEAD"],
+    )?
+    .status
+    .success()
+    {
+        bail!(
+            "A rebase is in progress in this repository\n\
+             Finish it with `git rebase --continue` or undo it with `git rebase --abort`"
+        );
+    }
+
+    commit_changes(base_path, args.message.as_deref())?;
+
+    let has_upstream = git(
+        base_path,
+        &["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"],
+    )?
+    .status
+    .success();
+
+    if has_upstream {
+        println!("Pulling changes...");
+        pull(base_path)?;
+    }
+
+    if args.no_push {
+        println!("✓ Synced (not pushed)");
+        return Ok(());
+    }
+
+    if has_upstream {
+        println!("Pushing changes...");
+        git_ok(base_path, &["push"])?;
+    } else {
+        let remotes = git_ok(base_path, &["remote"])?;
+        let Some(remote) = remotes.lines().next() else {
+            println!("No remote configured, changes were only committed locally");
+            println!("To share the collection, run:\n  git remote add origin <url>");
+            return Ok(());
+        };
+        println!("Pushing changes to {remote}...");
+        git_ok(base_path, &["push", "--set-upstream", remote, "HEAD"])?;
+    }
+
+    println!("✓ Synced");
+    Ok(())
+}
+
+/// Stage everything in the collection and commit it, if anything changed
+fn commit_changes(base_path: &Utf8Path, message: Option<&str>) -> Result<()> {
+    git_ok(base_path, &["add", "--all", "--", "."])?;
+
+    let changes = git_ok(
+        base_path,
+        &["diff", "--cached", "--name-status", "-z", "--relative"],
+    )?;
+    let changes = parse_name_status(&changes);
+    if changes.is_empty() {
+        println!("No local changes");
+        return Ok(());
+    }
+
+    let message = match message {
+        Some(message) => message.to_string(),
+        None => commit_message(&changes),
+    };
+    git_ok(base_path, &["commit", "--quiet", "-m", &message])?;
+    println!("Committed {} changed files", changes.len());
+    Ok(())
+}
+
+/// Pull with rebase, resolving conflicts in generated files
+fn pull(base_path: &Utf8Path) -> Result<()> {
+    let mut output = git(base_path, &["pull", "--rebase", "--quiet"])?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    // Each replayed commit can stop the rebase again
+    loop {
+        let conflicts = git_ok(base_path, &["diff", "--name-only", "--diff-filter=U"])?;
+        let conflicts: Vec<&str> = conflicts.lines().collect();
+        if conflicts.is_empty() {
+            // Failed for another reason, like a network error
+            let _ = git(base_path, &["rebase", "--abort"]);
+            bail!(
+                "git pull failed:\n{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let (generated, recipes): (Vec<&str>, Vec<&str>) =
+            conflicts.into_iter().partition(|path| is_generated(path));
+        if !recipes.is_empty() {
+            git_ok(base_path, &["rebase", "--abort"])?;
+            bail!(
+                "These files were changed both here and in the shared collection:\n  {}\n\
+                 Nothing was lost, your changes are still committed locally.\n\
+                 Resolve them with `git pull --rebase` and your editor, then run `cook sync` again",
+                recipes.join("\n  ")
+            );
+        }
+
+        for path in generated {
+            // Paths are relative to the repository root, not the collection
+            let pathspec = format!(":(top){path}");
+            // While rebasing, "theirs" is the local commit being replayed
+            git_ok(base_path, &["checkout", "--theirs", "--", &pathspec])?;
+            git_ok(base_path, &["add", "--", &pathspec])?;
+            println!("Kept this device's version of {path}");
+        }
+
+        output = Command::new("git")
+            .args(["rebase", "--continue"])
+            .current_dir(base_path)
+            .env("GIT_EDITOR", "true")
+            .output()?;
+        if output.status.success() {
+            return Ok(());
+        }
+    }
+}
+
+fn is_generated(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    GENERATED_FILES.contains(&name)
+}
+
+/// Parse `git diff --name-status -z` into (status letter, path) pairs
+fn parse_name_status(output: &str) -> Vec<(char, String)> {
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+    let mut changes = Vec::new();
+    while let Some(status) = fields.next() {
+        let kind = status.chars().next().unwrap_or('M');
+        // renames and copies list the old path first
+        if matches!(kind, 'R' | 'C') {
+            fields.next();
+        }
+        if let Some(path) = fields.next() {
+            changes.push((kind, path.to_string()));
+        }
+    }
+    changes
+}
+
+/// A commit message like "Add Pizza; update Pasta, Soup" listing every file
+/// in the body
+fn commit_message(changes: &[(char, String)]) -> String {
+    let mut groups: Vec<(&str, Vec<String>)> = Vec::new();
+    for (kind, path) in changes {
+        let verb = match kind {
+            'A' => "Add",
+            'D' => "Remove",
+            'R' => "Rename",
+            _ => "Update",
+        };
+        let name = Utf8Path::new(path)
+            .file_stem()
+            .unwrap_or(path.as_str())
+            .to_string();
+        match groups.iter_mut().find(|(v, _)| *v == verb) {
+            Some((_, names)) => names.push(name),
+            None => groups.push((verb, vec![name])),
+        }
+    }
+
+    let subject = groups
+        .iter()
+        .enumerate()
+        .map(|(i, (verb, names))| {
+            let verb = if i == 0 {
+                verb.to_string()
+            } else {
+                verb.to_lowercase()
+            };
+            if names.len() > 3 {
+                format!(
+                    "{verb} {} and {} more",
+                    names[..2].join(", "),
+                    names.len() - 2
+                )
+            } else {
+                format!("{verb} {}", names.join(", "))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    let body = changes
+        .iter()
+        .map(|(kind, path)| format!("{kind} {path}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("{subject}\n\n{body}")
+}
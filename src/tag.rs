@@ -0,0 +1,374 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand};
+use cooklang_find::RecipeEntry;
+
+use crate::{
+    migrate::print_diff,
+    util::{
+        front_matter::{display_value, FrontMatter},
+        get_recipe,
+        metadata_cache::collection_metadata,
+        random::parse_minutes,
+        tree_entries,
+        walk::build_tree,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct TagArgs {
+    /// Directory of the recipe collection
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, global = true, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// Change the recipes instead of showing what would change
+    #[arg(short, long, global = true)]
+    write: bool,
+
+    #[command(subcommand)]
+    command: TagCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum TagCommand {
+    /// List the tags of the collection and how many recipes have them
+    #[command(alias = "ls")]
+    List,
+
+    /// Add a tag to recipes
+    Add {
+        tag: String,
+        #[command(flatten)]
+        targets: Targets,
+    },
+
+    /// Remove a tag from recipes
+    #[command(alias = "rm")]
+    Remove {
+        tag: String,
+        #[command(flatten)]
+        targets: Targets,
+    },
+
+    /// Rename a tag, merging it into the new one if recipes have both
+    Rename {
+        old: String,
+        new: String,
+        #[command(flatten)]
+        targets: Targets,
+    },
+}
+
+/// The recipes a tag command changes
+#[derive(Debug, Args)]
+#[group(required = true, multiple = true)]
+struct Targets {
+    /// Recipes to change
+    #[arg(
+        value_hint = clap::ValueHint::FilePath,
+        value_name = "RECIPE",
+        add = crate::completions::recipes()
+    )]
+    recipes: Vec<Utf8PathBuf>,
+
+    /// Every recipe in the collection
+    #[arg(long, conflicts_with = "recipes")]
+    all: bool,
+
+    /// Only recipes matching a condition, like "time<=30m" or
+    /// "tags=dessert" (can be repeated)
+    ///
+    /// Conditions are KEY OP VALUE, with =, !=, <, <=, > or >=. `time`
+    /// (or `max-time`) is the total time of the recipe, given like 30m or
+    /// 1h 30m. `tags=X` matches recipes with the tag X. Other keys are
+    /// compared with the front matter, as numbers when both sides are.
+    /// Without recipes, the whole collection is searched.
+    #[arg(long = "where", value_name = "CONDITION")]
+    conditions: Vec<Condition>,
+}
+
+/// What happens to the tags of each recipe
+enum Change {
+    Add(String),
+    Remove(String),
+    Rename(String, String),
+}
+
+impl Change {
+    fn apply(&self, tags: Vec<String>) -> Vec<String> {
+        let mut changed: Vec<String> = Vec::with_capacity(tags.len() + 1);
+        let mut push = |tag: &str| {
+            if !changed.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                changed.push(tag.to_string());
+            }
+        };
+        for tag in &tags {
+            match self {
+                Self::Remove(removed) if tag.eq_ignore_ascii_case(removed) => {}
+                Self::Rename(old, new) if tag.eq_ignore_ascii_case(old) => push(new),
+                _ => push(tag),
+            }
+        }
+        if let Self::Add(added) = self {
+            push(added);
+        }
+        changed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn compare<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+        }
+    }
+}
+
+/// A `--where` condition
+#[derive(Debug, Clone)]
+enum Condition {
+    /// Total time in minutes
+    Time(Op, u32),
+    /// Has (or with `!=` hasn't) a tag
+    Tag(Op, String),
+    Metadata(String, Op, String),
+}
+
+impl std::str::FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // At the leftmost operator, so values can have any of them, and two
+        // character operators first there, so <= isn't read as <
+        const OPS: &[(&str, Op)] = &[
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("!=", Op::Ne),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+            ("=", Op::Eq),
+        ];
+        let Some((key, op, value)) = s.char_indices().find_map(|(i, _)| {
+            OPS.iter().find_map(|(text, op)| {
+                s[i..]
+                    .strip_prefix(text)
+                    .map(|value| (s[..i].trim(), *op, value.trim()))
+            })
+        }) else {
+            return Err(
+                "expected KEY OP VALUE, like \"time<=30m\" or \"tags=dessert\"".to_string(),
+            );
+        };
+        if key.is_empty() || value.is_empty() {
+            return Err(format!("'{s}' needs a key and a value"));
+        }
+        match key.to_lowercase().as_str() {
+            "time" | "max-time" | "total-time" => {
+                let minutes = parse_minutes(value).map_err(|e| e.to_string())?;
+                Ok(Self::Time(op, minutes))
+            }
+            "tag" | "tags" => match op {
+                Op::Eq | Op::Ne => Ok(Self::Tag(op, value.to_string())),
+                _ => Err("tags can only be compared with = or !=".to_string()),
+            },
+            _ => Ok(Self::Metadata(key.to_string(), op, value.to_string())),
+        }
+    }
+}
+
+impl Condition {
+    fn matches(&self, front_matter: &FrontMatter, total_time: Option<u32>) -> bool {
+        match self {
+            Self::Time(op, minutes) => total_time.is_some_and(|time| op.compare(time, *minutes)),
+            Self::Tag(op, tag) => {
+                let has = front_matter
+                    .tags()
+                    .iter()
+                    .any(|t| t.eq_ignore_ascii_case(tag));
+                has == (*op == Op::Eq)
+            }
+            Self::Metadata(key, op, wanted) => {
+                let Some(value) = front_matter.get(key) else {
+                    return *op == Op::Ne;
+                };
+                let value = display_value(value);
+                match (value.parse::<f64>(), wanted.parse::<f64>()) {
+                    (Ok(left), Ok(right)) => op.compare(left, right),
+                    _ => match op {
+                        Op::Eq => value.eq_ignore_ascii_case(wanted),
+                        Op::Ne => !value.eq_ignore_ascii_case(wanted),
+                        _ => op.compare(value.to_lowercase(), wanted.to_lowercase()),
+                    },
+                }
+            }
+        }
+    }
+}
+
+pub fn run(ctx: &Context, args: TagArgs) -> Result<()> {
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path());
+    let (targets, change) = match args.command {
+        TagCommand::List => return list(base_path),
+        TagCommand::Add { tag, targets } => (targets, Change::Add(tag.trim().to_string())),
+        TagCommand::Remove { tag, targets } => (targets, Change::Remove(tag.trim().to_string())),
+        TagCommand::Rename { old, new, targets } => (
+            targets,
+            Change::Rename(old.trim().to_string(), new.trim().to_string()),
+        ),
+    };
+    apply(base_path, &targets, &change, args.write)
+}
+
+fn list(base_path: &Utf8Path) -> Result<()> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for entry in tree_entries(&build_tree(base_path)?) {
+        for tag in entry.tags() {
+            *counts.entry(tag.to_string()).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    for (tag, count) in counts {
+        println!("{count:>5}  {tag}");
+    }
+    Ok(())
+}
+
+fn apply(base_path: &Utf8Path, targets: &Targets, change: &Change, write: bool) -> Result<()> {
+    let entries: Vec<RecipeEntry> = if targets.recipes.is_empty() {
+        tree_entries(&build_tree(base_path)?)
+    } else {
+        let base_path = base_path.to_path_buf();
+        targets
+            .recipes
+            .iter()
+            .map(|recipe| get_recipe(&base_path, recipe.as_str()))
+            .collect::<Result<_>>()?
+    };
+
+    // Times need the recipes parsed, only done when a condition asks
+    let times: HashMap<Utf8PathBuf, Option<u32>> = if targets
+        .conditions
+        .iter()
+        .any(|c| matches!(c, Condition::Time(..)))
+    {
+        collection_metadata(base_path, entries.clone())
+            .into_iter()
+            .filter_map(|(entry, metadata)| Some((entry.path()?.clone(), metadata.total_time)))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let mut files: Vec<Utf8PathBuf> = entries
+        .iter()
+        .filter_map(|entry| entry.path().cloned())
+        .collect();
+    files.sort();
+    files.dedup();
+    if files.is_empty() {
+        bail!("There are no recipes in {base_path}");
+    }
+
+    let mut out = anstream::stdout().lock();
+    let mut matched = 0;
+    let mut changed = 0;
+    let mut failed = 0;
+    for path in &files {
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        let mut front_matter = match FrontMatter::parse(&content) {
+            Ok(front_matter) => front_matter,
+            Err(e) => {
+                tracing::warn!("Skipping {relative}: {e:#}");
+                failed += 1;
+                continue;
+            }
+        };
+        let total_time = times.get(path).copied().flatten();
+        if !targets
+            .conditions
+            .iter()
+            .all(|c| c.matches(&front_matter, total_time))
+        {
+            continue;
+        }
+        matched += 1;
+
+        if !front_matter.set_tags(change.apply(front_matter.tags())) {
+            continue;
+        }
+        changed += 1;
+        let new_content = front_matter.render()?;
+        if write {
+            std::fs::write(path, new_content).with_context(|| format!("Failed to write {path}"))?;
+            writeln!(out, "Updated {relative}")?;
+        } else {
+            print_diff(&mut out, relative, &content, &new_content)?;
+        }
+    }
+
+    if changed == 0 {
+        writeln!(
+            out,
+            "✓ None of the {matched} matching recipes need a change"
+        )?;
+    } else if write {
+        writeln!(out, "✓ Updated {changed} of {matched} matching recipes")?;
+    } else {
+        writeln!(
+            out,
+            "{changed} of {matched} matching recipes would change. Run with --write to apply."
+        )?;
+    }
+    if failed > 0 {
+        writeln!(out, "{failed} recipes were skipped, see the warnings above")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leftmost_operator() {
+        let Ok(Condition::Metadata(key, op, value)) = "cuisine=a<b".parse() else {
+            panic!("not a metadata condition");
+        };
+        assert_eq!(
+            (key.as_str(), op, value.as_str()),
+            ("cuisine", Op::Eq, "a<b")
+        );
+        let Ok(Condition::Metadata(key, op, value)) = "servings>=4=x".parse() else {
+            panic!("not a metadata condition");
+        };
+        assert_eq!(
+            (key.as_str(), op, value.as_str()),
+            ("servings", Op::Ge, "4=x")
+        );
+        assert!(matches!("tags!=a=b".parse(), Ok(Condition::Tag(Op::Ne, tag)) if tag == "a=b"));
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::Args;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crate::{
+    error::NotFound,
+    util::{
+        parse_recipe_from_entry, split_recipe_name_and_scaling_factor,
+        timer::{format_countdown, notify, parse_timer, step_timers, TimerSpec},
+    },
+    Context,
+};
+
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Args)]
+pub struct TimerArgs {
+    /// Timers to run, like "25 min pizza", "90s" or "1h 30m"
+    ///
+    /// A number without a unit is minutes. Several timers run at the
+    /// same time.
+    #[arg(value_name = "TIMER", required_unless_present = "from_recipe")]
+    timers: Vec<String>,
+
+    /// Take the timers from a recipe instead
+    #[arg(
+        short = 'r',
+        long,
+        value_name = "RECIPE",
+        value_hint = clap::ValueHint::FilePath,
+        conflicts_with = "timers"
+    )]
+    from_recipe: Option<Utf8PathBuf>,
+
+    /// Step of the recipe whose timers to run
+    ///
+    /// Steps are numbered like in `cook recipe cook`, across all sections.
+    /// Without it, the steps with timers are listed.
+    #[arg(short, long, requires = "from_recipe")]
+    step: Option<usize>,
+}
+
+pub fn run(ctx: &Context, args: TimerArgs) -> Result<()> {
+    let timers = match &args.from_recipe {
+        Some(recipe) => recipe_timers(ctx, recipe, args.step)?,
+        None => args
+            .timers
+            .iter()
+            .map(|t| parse_timer(t.as_str()))
+            .collect::<Result<Vec<_>>>()?,
+    };
+
+    let timers: Vec<(String, Duration)> = timers
+        .into_iter()
+        .filter_map(|t| Some((t.label, t.duration?)))
+        .collect();
+    if timers.is_empty() {
+        bail!("No timers with a duration to run");
+    }
+
+    run_countdowns(&timers)
+}
+
+fn recipe_timers(
+    ctx: &Context,
+    query: &Utf8PathBuf,
+    step: Option<usize>,
+) -> Result<Vec<TimerSpec>> {
+    let (name, scale) = match split_recipe_name_and_scaling_factor(query.as_str()) {
+        Some((name, scale)) => (
+            name,
+            scale
+                .parse::<f64>()
+                .map_err(|e| anyhow::anyhow!("Invalid scaling factor for '{name}': {e}"))?,
+        ),
+        None => (query.as_str(), 1.0),
+    };
+
+    let entry = cooklang_find::get_recipe(vec![ctx.base_path().clone()], name.into())
+        .map_err(|e| NotFound(format!("Recipe not found: {e}")))?;
+    let recipe = parse_recipe_from_entry(&entry, scale)?;
+    let steps = step_timers(&recipe);
+
+    let Some(step) = step else {
+        let with_timers: Vec<String> = steps
+            .iter()
+            .enumerate()
+            .filter(|(_, timers)| !timers.is_empty())
+            .map(|(i, timers)| {
+                let labels: Vec<&str> = timers.iter().map(|t| t.label.as_str()).collect();
+                format!("  step {}: {}", i + 1, labels.join(", "))
+            })
+            .collect();
+        if with_timers.is_empty() {
+            bail!("{name} has no timers");
+        }
+        bail!(
+            "Pick a step with --step. Steps with timers:\n{}",
+            with_timers.join("\n")
+        );
+    };
+
+    let Some(timers) = step.checked_sub(1).and_then(|i| steps.get(i)) else {
+        bail!("{name} has {} steps, there is no step {step}", steps.len());
+    };
+    if timers.is_empty() {
+        bail!("Step {step} of {name} has no timers");
+    }
+    Ok(timers.clone())
+}
+
+fn run_countdowns(timers: &[(String, Duration)]) -> Result<()> {
+    let start = Instant::now();
+    let mut done = vec![false; timers.len()];
+    let interactive = std::io::stdout().is_terminal();
+    let label_width = timers
+        .iter()
+        .map(|(l, _)| l.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    if !interactive {
+        for (label, duration) in timers {
+            println!("Started {label} ({})", format_countdown(*duration));
+        }
+    }
+
+    let mut first = true;
+    loop {
+        let elapsed = start.elapsed();
+
+        if interactive {
+            let mut stdout = std::io::stdout().lock();
+            if !first {
+                // redraw in place
+                write!(stdout, "\x1b[{}A", timers.len())?;
+            }
+            for (label, duration) in timers {
+                let remaining = duration.saturating_sub(elapsed);
+                let filled = if duration.is_zero() {
+                    BAR_WIDTH
+                } else {
+                    ((elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.0) * BAR_WIDTH as f64)
+                        as usize
+                };
+                let status = if remaining.is_zero() {
+                    "done!".to_string()
+                } else {
+                    format_countdown(remaining)
+                };
+                writeln!(
+                    stdout,
+                    "\x1b[2K{label:<label_width$}  [{}{}]  {status}",
+                    "█".repeat(filled),
+                    "░".repeat(BAR_WIDTH - filled),
+                )?;
+            }
+            stdout.flush()?;
+            first = false;
+        }
+
+        for (i, (label, duration)) in timers.iter().enumerate() {
+            if !done[i] && elapsed >= *duration {
+                done[i] = true;
+                if interactive {
+                    notify(&format!("{label} timer is done"))?;
+                } else {
+                    println!("⏰ {label} is done");
+                }
+            }
+        }
+
+        if done.iter().all(|d| *d) {
+            return Ok(());
+        }
+
+        let next_end = timers
+            .iter()
+            .zip(&done)
+            .filter(|(_, d)| !**d)
+            .map(|((_, duration), _)| duration.saturating_sub(elapsed))
+            .min()
+            .unwrap_or_default();
+        let tick = if interactive {
+            Duration::from_millis(250).min(next_end)
+        } else {
+            next_end
+        };
+        std::thread::sleep(tick);
+    }
+}
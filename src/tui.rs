@@ -0,0 +1,505 @@
+use anyhow::{bail, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use cooklang_find::{RecipeEntry, RecipeTree};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::io::IsTerminal;
+
+use crate::{
+    server::shopping_list_store::{ShoppingListItem, ShoppingListStore},
+    util::{fuzzy_score, open_in_editor, parse_recipe_from_entry, walk::build_tree, PARSER},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct TuiArgs {
+    /// Directory with your recipes
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+}
+
+pub fn run(ctx: &Context, args: TuiArgs) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        bail!("The TUI needs an interactive terminal");
+    }
+
+    let base_path = args.base_path.as_ref().unwrap_or(ctx.base_path()).clone();
+    let mut app = Browser::new(base_path)?;
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+struct RecipeItem {
+    /// Path relative to the collection, with extension
+    path: String,
+    /// Directory relative to the collection, empty for the root
+    dir: String,
+    name: String,
+    entry: RecipeEntry,
+}
+
+enum Row {
+    Dir(String),
+    Recipe(usize),
+}
+
+#[derive(PartialEq)]
+enum Focus {
+    Recipes,
+    Search,
+    Basket,
+}
+
+struct Browser {
+    base_path: Utf8PathBuf,
+    recipes: Vec<RecipeItem>,
+    dir: String,
+    query: String,
+    rows: Vec<Row>,
+    list: ListState,
+    focus: Focus,
+    previews: HashMap<String, String>,
+    preview_scroll: u16,
+    basket: ShoppingListStore,
+    basket_items: Vec<ShoppingListItem>,
+    basket_list: ListState,
+    status: String,
+}
+
+fn collect_recipes(base_path: &Utf8Path) -> Result<Vec<RecipeItem>> {
+    fn walk(tree: &RecipeTree, base_path: &Utf8Path, out: &mut Vec<RecipeItem>) {
+        if let Some(entry) = &tree.recipe {
+            if let Some(path) = entry.path() {
+                let relative = path.strip_prefix(base_path).unwrap_or(path);
+                out.push(RecipeItem {
+                    path: relative.to_string(),
+                    dir: relative.parent().map(|p| p.to_string()).unwrap_or_default(),
+                    name: entry.name().clone().unwrap_or_else(|| relative.to_string()),
+                    entry: entry.clone(),
+                });
+            }
+        }
+        for child in tree.children.values() {
+            walk(child, base_path, out);
+        }
+    }
+
+    let tree = build_tree(base_path)?;
+    let mut recipes = Vec::new();
+    walk(&tree, base_path, &mut recipes);
+    recipes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(recipes)
+}
+
+impl Browser {
+    fn new(base_path: Utf8PathBuf) -> Result<Self> {
+        let recipes = collect_recipes(&base_path)?;
+        let basket = ShoppingListStore::new(&base_path);
+        let basket_items = basket.load()?;
+
+        let mut browser = Self {
+            base_path,
+            recipes,
+            dir: String::new(),
+            query: String::new(),
+            rows: Vec::new(),
+            list: ListState::default(),
+            focus: Focus::Recipes,
+            previews: HashMap::new(),
+            preview_scroll: 0,
+            basket,
+            basket_items,
+            basket_list: ListState::default(),
+            status: String::new(),
+        };
+        browser.refresh_rows();
+        Ok(browser)
+    }
+
+    fn refresh_rows(&mut self) {
+        self.rows = if self.query.is_empty() {
+            let prefix = if self.dir.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", self.dir)
+            };
+            let dirs: BTreeSet<&str> = self
+                .recipes
+                .iter()
+                .filter_map(|r| r.dir.strip_prefix(&prefix))
+                .filter_map(|rest| rest.split('/').next())
+                .filter(|d| !d.is_empty())
+                .collect();
+            dirs.into_iter()
+                .map(|d| Row::Dir(d.to_string()))
+                .chain(
+                    self.recipes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, r)| r.dir == self.dir)
+                        .map(|(i, _)| Row::Recipe(i)),
+                )
+                .collect()
+        } else {
+            let mut matches: Vec<_> = self
+                .recipes
+                .iter()
+                .enumerate()
+                .filter_map(|(i, r)| fuzzy_score(&self.query, &r.path).map(|s| (s, i)))
+                .collect();
+            matches.sort_by(|a, b| b.0.cmp(&a.0));
+            matches.into_iter().map(|(_, i)| Row::Recipe(i)).collect()
+        };
+
+        self.list.select((!self.rows.is_empty()).then_some(0));
+        self.preview_scroll = 0;
+    }
+
+    fn selected_recipe(&self) -> Option<&RecipeItem> {
+        match self.rows.get(self.list.selected()?)? {
+            Row::Recipe(i) => self.recipes.get(*i),
+            Row::Dir(_) => None,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            let quit = match self.focus {
+                Focus::Search => {
+                    self.search_key(key);
+                    false
+                }
+                Focus::Basket => self.basket_key(key)?,
+                Focus::Recipes => self.recipes_key(key, terminal)?,
+            };
+            if quit {
+                return Ok(());
+            }
+        }
+    }
+
+    fn search_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.query.clear();
+                self.focus = Focus::Recipes;
+                self.refresh_rows();
+            }
+            KeyCode::Enter => self.focus = Focus::Recipes,
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refresh_rows();
+            }
+            KeyCode::Up => self.list.select_previous(),
+            KeyCode::Down => self.list.select_next(),
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.refresh_rows();
+            }
+            _ => {}
+        }
+    }
+
+    fn recipes_key(&mut self, key: KeyEvent, terminal: &mut DefaultTerminal) -> Result<bool> {
+        match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc if !self.query.is_empty() => {
+                self.query.clear();
+                self.refresh_rows();
+            }
+            KeyCode::Esc => return Ok(true),
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.list.select_previous();
+                self.preview_scroll = 0;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.list.select_next();
+                self.preview_scroll = 0;
+            }
+            KeyCode::PageDown | KeyCode::Char('J') => {
+                self.preview_scroll = self.preview_scroll.saturating_add(10)
+            }
+            KeyCode::PageUp | KeyCode::Char('K') => {
+                self.preview_scroll = self.preview_scroll.saturating_sub(10)
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                let selected = self.list.selected().and_then(|i| self.rows.get(i));
+                if let Some(Row::Dir(name)) = selected {
+                    self.dir = if self.dir.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}/{name}", self.dir)
+                    };
+                    self.refresh_rows();
+                }
+            }
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                if self.query.is_empty() && !self.dir.is_empty() {
+                    self.dir = self
+                        .dir
+                        .rsplit_once('/')
+                        .map(|(parent, _)| parent.to_string())
+                        .unwrap_or_default();
+                    self.refresh_rows();
+                }
+            }
+            KeyCode::Char('/') => self.focus = Focus::Search,
+            KeyCode::Char('a') => self.add_to_basket()?,
+            KeyCode::Char('b') | KeyCode::Tab => {
+                self.focus = Focus::Basket;
+                self.basket_list
+                    .select((!self.basket_items.is_empty()).then_some(0));
+            }
+            KeyCode::Char('e') => self.edit_selected(terminal)?,
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn basket_key(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('b') | KeyCode::Tab => self.focus = Focus::Recipes,
+            KeyCode::Up | KeyCode::Char('k') => self.basket_list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.basket_list.select_next(),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(i) = self.basket_list.selected() {
+                    if i < self.basket_items.len() {
+                        let item = self.basket_items.remove(i);
+                        self.basket.save(&self.basket_items)?;
+                        self.status = format!("Removed {} from the shopping list", item.name);
+                    }
+                }
+            }
+            KeyCode::Char('C') => {
+                self.basket_items.clear();
+                self.basket.clear()?;
+                self.status = "Cleared the shopping list".to_string();
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn add_to_basket(&mut self) -> Result<()> {
+        let Some(recipe) = self.selected_recipe() else {
+            return Ok(());
+        };
+        let item = ShoppingListItem {
+            path: recipe.path.clone(),
+            name: recipe.name.clone(),
+            scale: 1.0,
+        };
+        self.status = format!("Added {} to the shopping list", item.name);
+        self.basket.add(item.clone())?;
+        self.basket_items.push(item);
+        Ok(())
+    }
+
+    fn edit_selected(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let Some(recipe) = self.selected_recipe() else {
+            return Ok(());
+        };
+        let path = self.base_path.join(&recipe.path);
+        let key = recipe.path.clone();
+
+        ratatui::restore();
+        let result = open_in_editor(&path);
+        *terminal = ratatui::init();
+
+        match result {
+            Ok(()) => {
+                // the recipe changed on disk, reload it
+                self.previews.remove(&key);
+                self.recipes = collect_recipes(&self.base_path)?;
+                let selected = self.list.selected();
+                // the rows point into the recipes, so they're rebuilt too
+                self.refresh_rows();
+                let row = self
+                    .rows
+                    .iter()
+                    .position(|row| matches!(row, Row::Recipe(i) if self.recipes[*i].path == key));
+                let last = self.rows.len().checked_sub(1);
+                self.list
+                    .select(row.or_else(|| selected.zip(last).map(|(i, last)| i.min(last))));
+                self.status = format!("Edited {key}");
+            }
+            Err(e) => self.status = format!("{e:#}"),
+        }
+        Ok(())
+    }
+
+    fn preview(&mut self) -> String {
+        let Some(recipe) = self.selected_recipe() else {
+            return String::new();
+        };
+        let key = recipe.path.clone();
+        if let Some(preview) = self.previews.get(&key) {
+            return preview.clone();
+        }
+
+        let preview = match parse_recipe_from_entry(&recipe.entry, 1.0) {
+            Ok(parsed) => {
+                let mut out = Vec::new();
+                match crate::util::cooklang_to_human::print_human(
+                    &parsed,
+                    &recipe.name,
+                    1.0,
+                    PARSER.converter(),
+                    &mut out,
+                ) {
+                    Ok(()) => {
+                        anstream::adapter::strip_str(&String::from_utf8_lossy(&out)).to_string()
+                    }
+                    Err(e) => format!("Failed to render recipe: {e}"),
+                }
+            }
+            Err(e) => format!("Failed to parse recipe: {e:#}"),
+        };
+        self.previews.insert(key, preview.clone());
+        preview
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, main, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+        let basket_height = if self.focus == Focus::Basket {
+            self.basket_items.len() as u16 + 2
+        } else {
+            3
+        };
+        let [list_area, basket_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(basket_height.min(12)),
+        ])
+        .areas(left);
+
+        let header_text = if self.focus == Focus::Search || !self.query.is_empty() {
+            Line::from(vec![
+                Span::styled("Search: ", Style::new().add_modifier(Modifier::BOLD)),
+                Span::raw(self.query.clone()),
+                Span::styled(
+                    if self.focus == Focus::Search {
+                        "▏"
+                    } else {
+                        ""
+                    },
+                    Style::new().fg(Color::Gray),
+                ),
+            ])
+        } else {
+            Line::from(vec![
+                Span::styled("Recipes", Style::new().add_modifier(Modifier::BOLD)),
+                Span::raw(format!("  /{}", self.dir)),
+            ])
+        };
+        frame.render_widget(Paragraph::new(header_text), header);
+
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                Row::Dir(name) => ListItem::new(format!("📁 {name}/")),
+                Row::Recipe(i) => {
+                    let recipe = &self.recipes[*i];
+                    let icon = if recipe.entry.is_menu() {
+                        "📋"
+                    } else {
+                        "🍽️"
+                    };
+                    if self.query.is_empty() {
+                        ListItem::new(format!("{icon} {}", recipe.name))
+                    } else {
+                        ListItem::new(format!("{icon} {}", recipe.path))
+                    }
+                }
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(format!(" {} ", self.rows.len())))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let basket_title = format!(" Shopping list ({}) ", self.basket_items.len());
+        if self.focus == Focus::Basket {
+            let items: Vec<ListItem> = self
+                .basket_items
+                .iter()
+                .map(|item| {
+                    if item.scale == 1.0 {
+                        ListItem::new(item.name.clone())
+                    } else {
+                        ListItem::new(format!("{} ×{}", item.name, item.scale))
+                    }
+                })
+                .collect();
+            let list = List::new(items)
+                .block(
+                    Block::bordered()
+                        .title(basket_title)
+                        .border_style(Style::new().fg(Color::Yellow)),
+                )
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(list, basket_area, &mut self.basket_list);
+        } else {
+            let names: Vec<&str> = self.basket_items.iter().map(|i| i.name.as_str()).collect();
+            frame.render_widget(
+                Paragraph::new(names.join(", ")).block(Block::bordered().title(basket_title)),
+                basket_area,
+            );
+        }
+
+        let preview = self.preview();
+        frame.render_widget(
+            Paragraph::new(preview)
+                .block(Block::bordered())
+                .wrap(Wrap { trim: false })
+                .scroll((self.preview_scroll, 0)),
+            right,
+        );
+
+        let help = match self.focus {
+            Focus::Search => "type to search  ↑/↓ move  Enter done  Esc clear".to_string(),
+            Focus::Basket => "↑/↓ move  d remove  C clear  b back  q quit".to_string(),
+            Focus::Recipes if !self.status.is_empty() => self.status.clone(),
+            Focus::Recipes => {
+                "↑/↓ move  Enter open  ← up  / search  a add to list  b list  e edit  J/K scroll  q quit"
+                    .to_string()
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(help).style(Style::new().fg(Color::DarkGray)),
+            footer,
+        );
+        self.status.clear();
+    }
+}
@@ -0,0 +1,109 @@
+//! Client for the AI provider used by AI-assisted features
+//!
+//! Only Anthropic's Claude is supported for now. The API key is read from the
+//! `CLAUDE_API_KEY` environment variable (a `.env` file works as well). The
+//! `[ai]` section of cook.toml can change the model and the variable.
+
+use anyhow::{bail, Context as _, Result};
+use base64::Engine as _;
+
+use crate::config::AiConfig;
+
+pub const API_KEY_ENV: &str = "CLAUDE_API_KEY";
+
+/// The only provider implemented
+const PROVIDER: &str = "anthropic";
+
+const API_URL: &str = "https://api.anthropic.com/v1/messages";
+const API_VERSION: &str = "2023-06-01";
+const MODEL: &str = "claude-3-sonnet-20240229";
+/// Longest reply the default model writes, in tokens
+pub const MAX_TOKENS: u32 = 4096;
+
+/// An image sent along with a prompt
+pub struct Image {
+    /// MIME type, e.g. `image/jpeg`
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Send a single prompt (optionally with an image) and return the text reply
+pub async fn complete(
+    config: &AiConfig,
+    prompt: &str,
+    image: Option<&Image>,
+    max_tokens: u32,
+) -> Result<String> {
+    if let Some(provider) = config.provider.as_deref().filter(|p| *p != PROVIDER) {
+        bail!("Unsupported AI provider '{provider}' in cook.toml, only '{PROVIDER}' is supported");
+    }
+    let key_env = config.api_key_env.as_deref().unwrap_or(API_KEY_ENV);
+    let api_key = std::env::var(key_env)
+        .with_context(|| format!("{key_env} is not set; it is required for AI features"))?;
+
+    let content = match image {
+        Some(image) => serde_json::json!([
+            {
+                "type": "image",
+                "source": {
+                    "type": "base64",
+                    "media_type": image.media_type,
+                    "data": base64::engine::general_purpose::STANDARD.encode(&image.data),
+                }
+            },
+            { "type": "text", "text": prompt }
+        ]),
+        None => serde_json::json!(prompt),
+    };
+
+    let response = reqwest::Client::new()
+        .post(API_URL)
+        .header("anthropic-version", API_VERSION)
+        .header("x-api-key", api_key)
+        .json(&serde_json::json!({
+            "model": config.model.as_deref().unwrap_or(MODEL),
+            "max_tokens": max_tokens,
+            "temperature": 0.1,
+            "messages": [{ "role": "user", "content": content }]
+        }))
+        .send()
+        .await
+        .context("Failed to call AI provider")?;
+
+    let status = response.status();
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .context("Failed to parse AI provider response")?;
+    if !status.is_success() {
+        let message = body["error"]["message"].as_str().unwrap_or("unknown error");
+        bail!("AI provider returned {status}: {message}");
+    }
+
+    body["content"][0]["text"]
+        .as_str()
+        .map(|text| text.trim().to_string())
+        .context("Unexpected AI provider response format")
+}
+
+/// Extract the JSON part of a reply, dropping Markdown code fences or any
+/// text the model put around it
+pub fn json_block(reply: &str) -> &str {
+    let start = reply.find(['[', '{']);
+    let end = reply.rfind([']', '}']);
+    match (start, end) {
+        (Some(start), Some(end)) if start <= end => &reply[start..=end],
+        _ => reply,
+    }
+}
+
+/// The reply without Markdown code fences the model may have added anyway
+pub fn strip_code_fence(reply: &str) -> &str {
+    let reply = reply.trim();
+    let Some(rest) = reply.strip_prefix("```") else {
+        return reply;
+    };
+    // Skip the language after the opening fence
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body)
+}
@@ -0,0 +1,213 @@
+//! Ingredient aliases from the `[aliases]` table of `cook.toml`
+//!
+//! Regional names for the same ingredient, like scallion and green onion,
+//! are merged into one entry on shopping lists, match the same pantry item
+//! and find the same recipes. Each alias maps to the name that is used
+//! instead; aliases of aliases are not followed.
+
+use cooklang::{ingredient_list::IngredientList, Converter};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::Setting;
+
+static ALIASES: Setting<Aliases> = Setting::new();
+
+/// Most spellings of a search query that are looked for
+const MAX_VARIANTS: usize = 16;
+
+/// An ingredient on a list that was merged into the name it's an alias of
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct AppliedAlias {
+    /// The name in the recipe
+    pub alias: String,
+    /// The name it was listed under
+    pub name: String,
+}
+
+/// Aliases in lowercase, mapped to the name used instead
+#[derive(Debug, Default)]
+pub struct Aliases(BTreeMap<String, String>);
+
+impl Aliases {
+    pub fn new(aliases: &BTreeMap<String, String>) -> Self {
+        Self(
+            aliases
+                .iter()
+                .filter(|(alias, name)| !alias.eq_ignore_ascii_case(name))
+                .map(|(alias, name)| (alias.trim().to_lowercase(), name.trim().to_string()))
+                .collect(),
+        )
+    }
+
+    /// The name an ingredient is an alias of, if it is one
+    pub fn alias_of(&self, ingredient: &str) -> Option<&str> {
+        self.0
+            .get(&ingredient.trim().to_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Whether two names are the same ingredient, ignoring case
+    pub fn same_ingredient(&self, a: &str, b: &str) -> bool {
+        let key = |name: &str| self.alias_of(name).unwrap_or(name).trim().to_lowercase();
+        a.eq_ignore_ascii_case(b) || key(a) == key(b)
+    }
+
+    /// The query with every other spelling of the ingredients in it
+    ///
+    /// The query itself comes first. Only whole words are replaced, so
+    /// "leek" doesn't change "sleek", and at most [`MAX_VARIANTS`] are
+    /// returned.
+    pub fn query_variants(&self, query: &str) -> Vec<String> {
+        let mut variants = vec![query.to_string()];
+        for (alias, name) in &self.0 {
+            let name = name.to_lowercase();
+            for i in 0..variants.len() {
+                for (from, to) in [
+                    (alias.as_str(), name.as_str()),
+                    (name.as_str(), alias.as_str()),
+                ] {
+                    let Some(variant) = replace_words(&variants[i].to_lowercase(), from, to) else {
+                        continue;
+                    };
+                    if !variants.contains(&variant) {
+                        variants.push(variant);
+                        if variants.len() == MAX_VARIANTS {
+                            return variants;
+                        }
+                    }
+                }
+            }
+        }
+        variants
+    }
+}
+
+/// The text with `from` replaced by `to` where it's whole words, `None`
+/// when it isn't in the text
+fn replace_words(text: &str, from: &str, to: &str) -> Option<String> {
+    if from.is_empty() {
+        return None;
+    }
+    let word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let mut replaced = String::new();
+    let mut rest = 0;
+    for (start, _) in text.match_indices(from) {
+        let end = start + from.len();
+        if word_char(text[..start].chars().next_back()) || word_char(text[end..].chars().next()) {
+            continue;
+        }
+        replaced.push_str(&text[rest..start]);
+        replaced.push_str(to);
+        rest = end;
+    }
+    if rest == 0 {
+        return None;
+    }
+    replaced.push_str(&text[rest..]);
+    Some(replaced)
+}
+
+/// Use these aliases, from alias to name
+pub fn configure_aliases(aliases: &BTreeMap<String, String>) {
+    ALIASES.set(Aliases::new(aliases));
+}
+
+fn aliases() -> &'static Aliases {
+    static EMPTY: Aliases = Aliases(BTreeMap::new());
+    ALIASES.get().unwrap_or(&EMPTY)
+}
+
+/// The name an ingredient is an alias of, if it is one
+pub fn alias_of(ingredient: &str) -> Option<&'static str> {
+    aliases().alias_of(ingredient)
+}
+
+/// Whether two names are the same ingredient, ignoring case
+pub fn same_ingredient(a: &str, b: &str) -> bool {
+    aliases().same_ingredient(a, b)
+}
+
+/// Merge the ingredients listed under an alias into their name
+///
+/// Returns the merged list and the aliases that were replaced.
+pub fn apply(list: IngredientList, converter: &Converter) -> (IngredientList, Vec<AppliedAlias>) {
+    if aliases().0.is_empty() {
+        return (list, Vec::new());
+    }
+    let mut merged = IngredientList::new();
+    let mut applied = BTreeSet::new();
+    for (ingredient, quantity) in list {
+        let name = match alias_of(&ingredient) {
+            Some(name) => {
+                applied.insert(AppliedAlias {
+                    alias: ingredient,
+                    name: name.to_string(),
+                });
+                name.to_string()
+            }
+            None => ingredient,
+        };
+        merged.add_ingredient(name, &quantity, converter);
+    }
+    (merged, applied.into_iter().collect())
+}
+
+/// The query with every other spelling of the ingredients in it
+///
+/// A query mentioning "scallion" also gives the query with "green onion"
+/// instead, and the other way around.
+pub fn query_variants(query: &str) -> Vec<String> {
+    aliases().query_variants(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases() {
+        let aliases = Aliases::new(&BTreeMap::from([
+            ("Scallion".to_string(), "green onion".to_string()),
+            ("cilantro".to_string(), "coriander".to_string()),
+        ]));
+
+        assert_eq!(aliases.alias_of("scallion"), Some("green onion"));
+        assert_eq!(aliases.alias_of("green onion"), None);
+        assert!(aliases.same_ingredient("Cilantro", "coriander"));
+        assert!(!aliases.same_ingredient("cilantro", "parsley"));
+        assert_eq!(
+            aliases.query_variants("scallion pancakes"),
+            ["scallion pancakes", "green onion pancakes"]
+        );
+        assert_eq!(
+            aliases.query_variants("coriander"),
+            ["coriander", "cilantro"]
+        );
+    }
+
+    #[test]
+    fn test_whole_words() {
+        let aliases = Aliases::new(&BTreeMap::from([("leek".to_string(), "onion".to_string())]));
+        assert_eq!(aliases.query_variants("sleek onions"), ["sleek onions"]);
+        assert_eq!(
+            aliases.query_variants("leek and onion"),
+            ["leek and onion", "onion and onion", "leek and leek"]
+        );
+        assert_eq!(
+            replace_words("leek, leek", "leek", "onion").unwrap(),
+            "onion, onion"
+        );
+    }
+
+    #[test]
+    fn test_max_variants() {
+        let aliases: BTreeMap<_, _> = (0..10)
+            .map(|i| (format!("alias{i}"), format!("name{i}")))
+            .collect();
+        let query = (0..10).map(|i| format!("alias{i}")).collect::<Vec<_>>();
+        let variants = Aliases::new(&aliases).query_variants(&query.join(" "));
+        assert_eq!(variants.len(), MAX_VARIANTS);
+        assert_eq!(variants[0], query.join(" "));
+    }
+}
@@ -0,0 +1,290 @@
+//! Calendar events for the meals planned in menus
+//!
+//! A menu section is a day when its name has a date, like
+//! `== Monday 2024-03-18 ==`, or when the menu has a `start` date in its
+//! metadata, then the sections are the days from it on. Each step in a day
+//! is a meal, named by the text before a colon, like `Dinner:`.
+
+use anyhow::Result;
+use camino::Utf8Path;
+use chrono::{Days, NaiveDate};
+use cooklang::{Content, Item, Recipe};
+
+use super::{parse_entries, tree_entries, walk::build_tree};
+
+/// A planned meal, as an all day event
+#[derive(Debug, Clone, PartialEq)]
+pub struct MealEvent {
+    pub date: NaiveDate,
+    /// Like "Dinner", when the menu names the meal
+    pub meal: Option<String>,
+    /// Recipes and ingredients of the meal
+    pub items: Vec<MealItem>,
+    /// Menu the meal is planned in, relative to the collection
+    pub menu: String,
+    /// Position of the meal in its day, to tell meals apart
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MealItem {
+    pub name: String,
+    /// Path of a referenced recipe, like "Mains/Lasagna"
+    pub recipe: Option<String>,
+}
+
+impl MealEvent {
+    pub fn summary(&self) -> String {
+        let items = self
+            .items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &self.meal {
+            Some(meal) => format!("{meal}: {items}"),
+            None => items,
+        }
+    }
+}
+
+/// The meals of a menu that has dates, none if it has none
+pub fn menu_events(menu: &Recipe, menu_path: &str) -> Vec<MealEvent> {
+    let start = menu
+        .metadata
+        .get("start")
+        .and_then(|value| value.as_str())
+        .and_then(|start| NaiveDate::parse_from_str(start.trim(), "%Y-%m-%d").ok());
+
+    let mut events = Vec::new();
+    for (i, section) in menu.sections.iter().enumerate() {
+        let date = section
+            .name
+            .as_deref()
+            .and_then(date_in)
+            .or_else(|| start.and_then(|start| start.checked_add_days(Days::new(i as u64))));
+        let Some(date) = date else {
+            continue;
+        };
+
+        let steps = section.content.iter().filter_map(|content| match content {
+            Content::Step(step) => Some(step),
+            _ => None,
+        });
+        for step in steps {
+            let mut meal = None;
+            let mut items = Vec::new();
+            for item in &step.items {
+                match item {
+                    Item::Text { value } if items.is_empty() && meal.is_none() => {
+                        meal = value
+                            .split_once(':')
+                            .map(|(name, _)| name.trim().to_string())
+                            .filter(|name| !name.is_empty());
+                    }
+                    Item::Ingredient { index } => {
+                        let Some(ingredient) = menu.ingredients.get(*index) else {
+                            continue;
+                        };
+                        items.push(match &ingredient.reference {
+                            Some(reference) => MealItem {
+                                name: reference.name.clone(),
+                                recipe: Some(if reference.components.is_empty() {
+                                    reference.name.clone()
+                                } else {
+                                    reference.path("/")
+                                }),
+                            },
+                            None => MealItem {
+                                name: ingredient.display_name().to_string(),
+                                recipe: None,
+                            },
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            if items.is_empty() {
+                continue;
+            }
+            events.push(MealEvent {
+                date,
+                meal,
+                items,
+                menu: menu_path.to_string(),
+                index: events.iter().filter(|e| e.date == date).count(),
+            });
+        }
+    }
+    events
+}
+
+/// The meals of every menu in the collection, by date
+///
+/// Menus that fail to parse are left out with a warning.
+pub fn collection_events(base_path: &Utf8Path) -> Result<Vec<MealEvent>> {
+    let menus = tree_entries(&build_tree(base_path)?)
+        .into_iter()
+        .filter(|entry| entry.is_menu())
+        .collect();
+
+    let mut events = Vec::new();
+    for (entry, menu) in parse_entries(menus, 1.0) {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        match menu {
+            Ok(menu) => events.extend(menu_events(&menu, relative.as_str())),
+            Err(e) => tracing::warn!("Leaving {relative} out of the meal plan: {e:#}"),
+        }
+    }
+    events.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.index.cmp(&b.index)));
+    Ok(events)
+}
+
+/// A date like 2024-03-18 in a section name
+fn date_in(name: &str) -> Option<NaiveDate> {
+    name.split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .find_map(|word| NaiveDate::parse_from_str(word, "%Y-%m-%d").ok())
+}
+
+/// An iCalendar feed with the events
+///
+/// `recipe_url` turns the path of a referenced recipe into a link, used as
+/// the event's URL and in its description.
+pub fn render_ics(name: &str, events: &[MealEvent], recipe_url: impl Fn(&str) -> String) -> String {
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//cooklang//cookcli//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for event in events {
+        let end = event
+            .date
+            .checked_add_days(Days::new(1))
+            .unwrap_or(event.date);
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:{}-{}-{}@cookcli",
+            event.date.format("%Y%m%d"),
+            event.index,
+            super::names::slug(&event.menu)
+        ));
+        lines.push(format!("DTSTAMP:{stamp}"));
+        lines.push(format!(
+            "DTSTART;VALUE=DATE:{}",
+            event.date.format("%Y%m%d")
+        ));
+        lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape(&event.summary())));
+
+        let description = event
+            .items
+            .iter()
+            .map(|item| match &item.recipe {
+                Some(recipe) => format!("{} – {}", item.name, recipe_url(recipe)),
+                None => item.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        lines.push(format!("DESCRIPTION:{}", escape(&description)));
+        if let Some(recipe) = event.items.iter().find_map(|item| item.recipe.as_deref()) {
+            lines.push(format!("URL:{}", recipe_url(recipe)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in &lines {
+        fold(line, &mut ics);
+    }
+    ics
+}
+
+/// Text values escape backslashes, separators and line breaks
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Lines are at most 75 bytes, longer ones continue on the next line after
+/// a space
+pub(crate) fn fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PARSER;
+
+    fn parse(menu: &str) -> Recipe {
+        PARSER.parse(menu).into_result().unwrap().0
+    }
+
+    #[test]
+    fn test_dated_sections() {
+        let menu = parse(
+            "== Monday 2024-03-18 ==\n\nBreakfast:\n- @toast{2}\n\nDinner:\n- @./Mains/Lasagna{}\n- @salad{}\n\n== Tuesday ==\n\nDinner:\n- @./Soup{}\n",
+        );
+        let events = menu_events(&menu, "Week.menu");
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].date,
+            NaiveDate::from_ymd_opt(2024, 3, 18).unwrap()
+        );
+        assert_eq!(events[0].summary(), "Breakfast: toast");
+        assert_eq!(events[1].summary(), "Dinner: Lasagna, salad");
+        assert_eq!(events[1].items[0].recipe.as_deref(), Some("Mains/Lasagna"));
+        assert_eq!(events[1].index, 1);
+    }
+
+    #[test]
+    fn test_start_date() {
+        let menu = parse(
+            "---\nstart: 2024-02-28\n---\n\n== Day 1 ==\n\nDinner:\n- @./Soup{}\n\n== Day 2 ==\n\nDinner:\n- @./Stew{}\n\n== Day 3 ==\n\nDinner:\n- @./Pie{}\n",
+        );
+        let dates: Vec<_> = menu_events(&menu, "Plan.menu")
+            .iter()
+            .map(|event| event.date.to_string())
+            .collect();
+        assert_eq!(dates, ["2024-02-28", "2024-02-29", "2024-03-01"]);
+    }
+
+    #[test]
+    fn test_no_dates() {
+        let menu = parse("== Day 1 ==\n\nDinner:\n- @./Soup{}\n");
+        assert!(menu_events(&menu, "Plan.menu").is_empty());
+    }
+
+    #[test]
+    fn test_render_ics() {
+        let menu = parse("== 2024-03-18 ==\n\nDinner:\n- @./Mains/Lasagna{}, @salad{}\n");
+        let ics = render_ics("Meals", &menu_events(&menu, "Week.menu"), |recipe| {
+            format!("http://localhost:9080/recipe/{recipe}")
+        });
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240318\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20240319\r\n"));
+        assert!(ics.contains("SUMMARY:Dinner: Lasagna\\, salad\r\n"));
+        assert!(ics.contains("UID:20240318-0-week-menu@cookcli\r\n"));
+        assert!(ics.lines().all(|line| line.len() <= 76));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+}
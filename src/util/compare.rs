@@ -0,0 +1,143 @@
+//! Comparing the ingredients of two recipes, like two sources of the same
+//! dish
+//!
+//! Ingredients are matched by name, aliases included, and their amounts
+//! compared in the unit of the first recipe, converting through densities
+//! from `units.toml` when one uses cups and the other grams.
+
+use cooklang::{quantity::Value, Converter, Recipe};
+use serde::Serialize;
+
+use super::{aliases::same_ingredient, format::display_quantity, pantry::Amount, units};
+
+/// An ingredient in one or both recipes
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedIngredient {
+    pub name: String,
+    /// What the first recipe uses, none when it doesn't have it
+    pub a: Option<String>,
+    pub b: Option<String>,
+    /// How much more (or less, when negative) the second recipe uses, in
+    /// percent
+    ///
+    /// Only when both have one amount in units that can be converted.
+    pub difference: Option<f64>,
+}
+
+/// An ingredient's amounts in a recipe
+struct Side {
+    name: String,
+    text: String,
+    /// The amount, when it's a single number
+    amount: Option<Amount>,
+}
+
+/// The ingredients of both recipes, those of `a` first in its order, then
+/// those only `b` has
+pub fn compare(a: &Recipe, b: &Recipe, converter: &Converter) -> Vec<ComparedIngredient> {
+    let a_sides = sides(a, converter);
+    let mut b_sides: Vec<Option<Side>> = sides(b, converter).into_iter().map(Some).collect();
+
+    let mut compared = Vec::new();
+    for a_side in a_sides {
+        let b_side = b_sides
+            .iter_mut()
+            .find(|side| {
+                side.as_ref()
+                    .is_some_and(|side| same_ingredient(&side.name, &a_side.name))
+            })
+            .and_then(Option::take);
+        let difference = match (
+            &a_side.amount,
+            b_side.as_ref().and_then(|b| b.amount.as_ref()),
+        ) {
+            (Some(a_amount), Some(b_amount)) if a_amount.value > 0.0 => {
+                units::convert(b_amount, a_amount.unit.as_deref(), &a_side.name, converter)
+                    .map(|b_value| (b_value - a_amount.value) / a_amount.value * 100.0)
+            }
+            _ => None,
+        };
+        compared.push(ComparedIngredient {
+            name: a_side.name,
+            a: Some(a_side.text),
+            b: b_side.map(|side| side.text),
+            difference,
+        });
+    }
+    compared.extend(
+        b_sides
+            .into_iter()
+            .flatten()
+            .map(|side| ComparedIngredient {
+                name: side.name,
+                a: None,
+                b: Some(side.text),
+                difference: None,
+            }),
+    );
+    compared
+}
+
+fn sides(recipe: &Recipe, converter: &Converter) -> Vec<Side> {
+    recipe
+        .group_ingredients(converter)
+        .into_iter()
+        .map(|entry| {
+            let quantities: Vec<_> = entry.quantity.iter().collect();
+            let amount = match quantities[..] {
+                [quantity] => match quantity.value() {
+                    Value::Number(n) => Some(Amount::new(
+                        n.value(),
+                        quantity.unit().map(|unit| unit.to_string()),
+                    )),
+                    _ => None,
+                },
+                _ => None,
+            };
+            Side {
+                name: entry.ingredient.display_name().to_string(),
+                text: quantities
+                    .iter()
+                    .map(|quantity| display_quantity(quantity))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                amount,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PARSER;
+
+    #[test]
+    fn test_compare() {
+        let parse = |source: &str| PARSER.parse(source).into_result().unwrap().0;
+        let a = parse("Mix @flour{200%g}, @sugar{100%g} and @salt{}.\n");
+        let b = parse("Mix @Flour{0.3%kg}, @sugar{100%g} and @butter{50%g}.\n");
+        let compared = compare(&a, &b, PARSER.converter());
+
+        let rows: Vec<_> = compared
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.a.is_some(),
+                    c.b.is_some(),
+                    c.difference.map(f64::round),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("flour", true, true, Some(50.0)),
+                ("sugar", true, true, Some(0.0)),
+                ("salt", true, false, None),
+                ("butter", false, true, None),
+            ]
+        );
+    }
+}
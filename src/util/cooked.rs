@@ -0,0 +1,249 @@
+//! Recording cooked recipes and using up their ingredients from the pantry
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::ingredient_list::IngredientList;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use crate::{config::Config, server::db::Database};
+
+use super::{
+    extract_ingredients, get_recipe,
+    pantry::{deplete_pantry, load_pantry, save_pantry, Depletion},
+    split_recipe_name_and_scaling_factor, PARSER, RECIPE_SCALING_DELIMITER,
+};
+
+/// File in the collection's config directory that cooked events are appended to
+pub const COOKED_LOG: &str = "cooked.jsonl";
+
+/// A single "I cooked this" event, stored as one JSON line in the cooked log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CookedEvent {
+    /// Recipe path relative to the collection root
+    pub recipe: String,
+    pub scale: f64,
+    /// RFC 3339 timestamp
+    pub cooked_at: String,
+}
+
+/// What happened when a recipe was marked as cooked
+#[derive(Debug, Clone, Serialize)]
+pub struct CookedReport {
+    pub event: CookedEvent,
+    pub pantry: Vec<Depletion>,
+}
+
+/// How often and when a recipe was cooked, summed up from the cooked log
+#[derive(Debug, Clone, Serialize)]
+pub struct CookedHistory {
+    pub times_cooked: usize,
+    /// RFC 3339 timestamp of the latest event
+    pub last_cooked: String,
+}
+
+impl CookedHistory {
+    pub fn last_cooked_at(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        chrono::DateTime::parse_from_rfc3339(&self.last_cooked).ok()
+    }
+
+    /// The day of the latest event, like "2024-03-17"
+    pub fn last_cooked_day(&self) -> &str {
+        self.last_cooked.get(..10).unwrap_or(&self.last_cooked)
+    }
+}
+
+pub fn cooked_log_path(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join("config").join(COOKED_LOG)
+}
+
+/// Append an event to the cooked log, creating it if needed
+pub fn record_cooked(base_path: &Utf8Path, event: &CookedEvent) -> Result<()> {
+    let path = cooked_log_path(base_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {parent}"))?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open cooked log at {path}"))?;
+    writeln!(file, "{}", serde_json::to_string(event)?)
+        .with_context(|| format!("Failed to write cooked log at {path}"))
+}
+
+/// Read the cooked log, and the recipes cooked in the web UI when the
+/// server's database is given, keyed by recipe path relative to the
+/// collection root
+///
+/// Nothing has been cooked when there's no log yet. Lines that can't be
+/// read are skipped with a warning, so one bad edit doesn't lose the rest.
+pub fn cooked_history(
+    base_path: &Utf8Path,
+    db: Option<&Database>,
+) -> Result<HashMap<String, CookedHistory>> {
+    let mut history: HashMap<String, CookedHistory> = HashMap::new();
+
+    let path = cooked_log_path(base_path);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read cooked log at {path}")),
+    };
+    for (number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = match serde_json::from_str::<CookedEvent>(line) {
+            Ok(event) => event,
+            Err(e) => {
+                tracing::warn!("Skipping line {} of {path}: {e}", number + 1);
+                continue;
+            }
+        };
+        if !add_event(&mut history, event.clone()) {
+            tracing::warn!(
+                "Skipping line {} of {path}: invalid time '{}'",
+                number + 1,
+                event.cooked_at
+            );
+        }
+    }
+
+    if let Some(db) = db {
+        for event in db_events(db)? {
+            add_event(&mut history, event);
+        }
+    }
+    Ok(history)
+}
+
+/// Count an event in the history, `false` when its time can't be read
+fn add_event(history: &mut HashMap<String, CookedHistory>, event: CookedEvent) -> bool {
+    let Ok(cooked_at) = chrono::DateTime::parse_from_rfc3339(&event.cooked_at) else {
+        return false;
+    };
+    match history.get_mut(&event.recipe) {
+        Some(entry) => {
+            entry.times_cooked += 1;
+            // Events are appended, but the log may have been merged by hand
+            if entry.last_cooked_at().is_none_or(|last| cooked_at > last) {
+                entry.last_cooked = event.cooked_at;
+            }
+        }
+        None => {
+            history.insert(
+                event.recipe,
+                CookedHistory {
+                    times_cooked: 1,
+                    last_cooked: event.cooked_at,
+                },
+            );
+        }
+    }
+    true
+}
+
+fn db_events(db: &Database) -> Result<Vec<CookedEvent>> {
+    db.with(|conn| {
+        let mut statement = conn.prepare("SELECT recipe, scale, cooked_at FROM cooked")?;
+        let events = statement.query_map([], |row| {
+            Ok(CookedEvent {
+                recipe: row.get(0)?,
+                scale: row.get(1)?,
+                cooked_at: row.get(2)?,
+            })
+        })?;
+        events.collect()
+    })
+}
+
+/// The server's database of a collection, for the recipes cooked in the
+/// web UI, if there is one
+///
+/// Without it the history is that of the cooked log only, so a database
+/// that can't be opened is a warning.
+pub fn server_database(base_path: &Utf8Path, config: &Config) -> Option<Database> {
+    Database::existing(base_path, config)
+        .map_err(|e| tracing::warn!("Leaving out recipes cooked in the web UI: {e:#}"))
+        .ok()
+        .flatten()
+}
+
+/// Mark a recipe as cooked: record the event and, if a pantry file is given,
+/// subtract the (scaled) ingredients of the recipe and its sub-recipes from it
+///
+/// The event goes to the server's database when one is given, and to the
+/// cooked log otherwise.
+pub fn mark_cooked(
+    base_path: &Utf8PathBuf,
+    pantry_path: Option<&Utf8Path>,
+    db: Option<&Database>,
+    recipe: &str,
+    scale: f64,
+) -> Result<CookedReport> {
+    if !(scale.is_finite() && scale > 0.0) {
+        bail!("The scale has to be a number above 0, not {scale}");
+    }
+    // The scale given wins over one written after the name, like "Pasta:2"
+    let recipe = match split_recipe_name_and_scaling_factor(recipe) {
+        Some((name, factor)) if factor.parse::<f64>().is_ok() => name,
+        _ => recipe,
+    };
+    let entry = get_recipe(base_path, recipe)
+        .with_context(|| format!("Failed to find recipe '{recipe}'"))?;
+    let recipe_name = entry
+        .path()
+        .and_then(|p| p.strip_prefix(base_path).ok())
+        .map(|p| p.to_string())
+        .unwrap_or_else(|| recipe.to_string());
+
+    // Work out what to take from the pantry first, so a recipe that doesn't
+    // parse records nothing, but only save it once the event is recorded
+    let mut pantry = Vec::new();
+    let mut depleted = None;
+    if let Some(pantry_path) = pantry_path {
+        let mut list = IngredientList::new();
+        let mut seen = BTreeMap::new();
+        extract_ingredients(
+            &format!("{recipe}{RECIPE_SCALING_DELIMITER}{scale}"),
+            &mut list,
+            &mut seen,
+            base_path,
+            PARSER.converter(),
+            false,
+        )?;
+
+        let mut pantry_conf = load_pantry(pantry_path)?;
+        pantry = deplete_pantry(&mut pantry_conf, list, PARSER.converter());
+        if pantry.iter().any(|d| !d.used.is_empty()) {
+            depleted = Some((pantry_path, pantry_conf));
+        }
+    }
+
+    let event = CookedEvent {
+        recipe: recipe_name,
+        scale,
+        cooked_at: chrono::Local::now().to_rfc3339(),
+    };
+    match db {
+        Some(db) => {
+            db.with(|conn| {
+                conn.execute(
+                    "INSERT INTO cooked (recipe, scale, cooked_at) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![event.recipe, event.scale, event.cooked_at],
+                )
+            })?;
+        }
+        None => record_cooked(base_path, &event)?,
+    }
+
+    if let Some((pantry_path, pantry_conf)) = depleted {
+        save_pantry(pantry_path, &pantry_conf)?;
+    }
+
+    Ok(CookedReport { event, pantry })
+}
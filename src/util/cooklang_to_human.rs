@@ -47,6 +47,11 @@ use std::fmt::Write;
 use tabular::{Row, Table};
 use yansi::Paint;
 
+use super::{
+    format::{display_value, format_unit},
+    RecipePart,
+};
+
 mod style {
     use anstyle::Style;
 
@@ -134,15 +139,40 @@ pub fn print_human(
     name: &str,
     scale: f64,
     converter: &Converter,
+    writer: impl std::io::Write,
+) -> Result {
+    print_human_parts(recipe, name, scale, &[], converter, writer)
+}
+
+/// Like [`print_human`], but only the given parts, without the title
+///
+/// No parts prints the whole recipe.
+pub fn print_human_parts(
+    recipe: &Recipe,
+    name: &str,
+    scale: f64,
+    parts: &[RecipePart],
+    converter: &Converter,
     mut writer: impl std::io::Write,
 ) -> Result {
     let w = &mut writer;
+    let show = |part| parts.is_empty() || parts.contains(&part);
 
-    header(w, recipe, name, scale)?;
-    metadata(w, recipe, converter)?;
-    ingredients(w, recipe, converter)?;
-    cookware(w, recipe, converter)?;
-    steps(w, recipe)?;
+    if parts.is_empty() {
+        header(w, recipe, name, scale)?;
+    }
+    if show(RecipePart::Metadata) {
+        metadata(w, recipe, converter)?;
+    }
+    if show(RecipePart::Ingredients) {
+        ingredients(w, recipe, converter)?;
+    }
+    if show(RecipePart::Cookware) {
+        cookware(w, recipe, converter)?;
+    }
+    if show(RecipePart::Steps) {
+        steps(w, recipe)?;
+    }
 
     Ok(())
 }
@@ -567,9 +597,13 @@ fn write_igr_count(
 
 fn quantity_fmt(qty: &Quantity) -> String {
     if let Some(unit) = qty.unit() {
-        format!("{} {}", qty.value(), unit.italic())
+        format!(
+            "{} {}",
+            display_value(qty.value()),
+            format_unit(unit).italic()
+        )
     } else {
-        format!("{}", qty.value())
+        display_value(qty.value())
     }
 }
 
@@ -41,6 +41,12 @@ use cooklang::{
 };
 use serde::{Deserialize, Serialize};
 
+use super::{
+    format::{display_grouped, display_quantity},
+    source::Source,
+    RecipePart,
+};
+
 /// Options for [`print_md_with_options`]
 ///
 /// This implements [`Serialize`] and [`Deserialize`], so you can embed it in
@@ -88,6 +94,8 @@ pub struct Options {
     pub heading: Headings,
     /// Text to write when an ingredient or cookware item is optional
     pub optional_marker: String,
+    /// List the ingredients each step uses, with their amounts, under it
+    pub step_quantities: bool,
 }
 
 impl Default for Options {
@@ -100,6 +108,7 @@ impl Default for Options {
             front_matter_name: FrontMatterName::default(),
             heading: Headings::default(),
             optional_marker: "(optional)".to_string(),
+            step_quantities: false,
         }
     }
 }
@@ -209,6 +218,43 @@ pub fn print_md(
     print_md_with_options(recipe, name, scale, &Options::default(), converter, writer)
 }
 
+/// Writes only the given parts of a recipe in Markdown format, without the
+/// title
+///
+/// The metadata is written as YAML front-matter. No parts writes the whole
+/// recipe, like [`print_md`].
+pub fn print_md_parts(
+    recipe: &Recipe,
+    name: &str,
+    scale: f64,
+    parts: &[RecipePart],
+    converter: &Converter,
+    writer: impl io::Write,
+) -> Result<()> {
+    print_md_parts_with_options(
+        recipe,
+        name,
+        scale,
+        parts,
+        &Options::default(),
+        converter,
+        writer,
+    )
+}
+
+/// Like [`print_md_parts`], with [`Options`]
+pub fn print_md_parts_with_options(
+    recipe: &Recipe,
+    name: &str,
+    scale: f64,
+    parts: &[RecipePart],
+    opts: &Options,
+    converter: &Converter,
+    writer: impl io::Write,
+) -> Result<()> {
+    write_md(recipe, name, scale, parts, opts, converter, writer)
+}
+
 /// Writes a recipe in Markdown format
 ///
 /// The metadata of the recipe will be in a YAML front-matter. Some special keys
@@ -223,10 +269,29 @@ pub fn print_md_with_options(
     scale: f64,
     opts: &Options,
     converter: &Converter,
+    writer: impl io::Write,
+) -> Result<()> {
+    write_md(recipe, name, scale, &[], opts, converter, writer)
+}
+
+fn write_md(
+    recipe: &Recipe,
+    name: &str,
+    scale: f64,
+    parts: &[RecipePart],
+    opts: &Options,
+    converter: &Converter,
     mut writer: impl io::Write,
 ) -> Result<()> {
-    frontmatter(&mut writer, &recipe.metadata, name, opts)
-        .context("Failed to write frontmatter")?;
+    let show = |part| parts.is_empty() || parts.contains(&part);
+
+    if show(RecipePart::Metadata) {
+        frontmatter(&mut writer, &recipe.metadata, name, opts)
+            .context("Failed to write frontmatter")?;
+    }
+    if !parts.is_empty() {
+        return write_md_body(recipe, parts, opts, converter, writer);
+    }
 
     writeln!(
         writer,
@@ -271,9 +336,38 @@ pub fn print_md_with_options(
         }
     }
 
-    ingredients(&mut writer, recipe, converter, opts).context("Failed to write ingredients")?;
-    cookware(&mut writer, recipe, opts, converter).context("Failed to write cookware")?;
-    sections(&mut writer, recipe, opts).context("Failed to write sections")?;
+    write_md_body(recipe, parts, opts, converter, &mut writer)?;
+
+    if let Some(source) = Source::of(recipe) {
+        let citation = source.citation();
+        match &source.url {
+            Some(url) => writeln!(writer, "Source: [{citation}]({url})"),
+            None => writeln!(writer, "Source: {citation}"),
+        }
+        .context("Failed to write source")?;
+    }
+    Ok(())
+}
+
+/// The ingredients, cookware and steps, if in `parts`
+fn write_md_body(
+    recipe: &Recipe,
+    parts: &[RecipePart],
+    opts: &Options,
+    converter: &Converter,
+    mut writer: impl io::Write,
+) -> Result<()> {
+    let show = |part| parts.is_empty() || parts.contains(&part);
+
+    if show(RecipePart::Ingredients) {
+        ingredients(&mut writer, recipe, converter, opts).context("Failed to write ingredients")?;
+    }
+    if show(RecipePart::Cookware) {
+        cookware(&mut writer, recipe, opts, converter).context("Failed to write cookware")?;
+    }
+    if show(RecipePart::Steps) {
+        sections(&mut writer, recipe, opts, converter).context("Failed to write sections")?;
+    }
 
     Ok(())
 }
@@ -324,11 +418,11 @@ fn ingredients(
 
         write!(w, "- ").context("Failed to write ingredient bullet")?;
         if !entry.quantity.is_empty() {
+            let quantity = display_grouped(&entry.quantity);
             if opts.italic_amounts {
-                write!(w, "*{}* ", entry.quantity)
-                    .context("Failed to write italicized quantity")?;
+                write!(w, "*{quantity}* ").context("Failed to write italicized quantity")?;
             } else {
-                write!(w, "{} ", entry.quantity).context("Failed to write quantity")?;
+                write!(w, "{quantity} ").context("Failed to write quantity")?;
             }
         }
 
@@ -398,10 +492,15 @@ fn cookware(
     Ok(())
 }
 
-fn sections(w: &mut impl io::Write, recipe: &Recipe, opts: &Options) -> Result<()> {
+fn sections(
+    w: &mut impl io::Write,
+    recipe: &Recipe,
+    opts: &Options,
+    converter: &Converter,
+) -> Result<()> {
     writeln!(w, "## {}\n", opts.heading.steps).context("Failed to write steps header")?;
     for (idx, section) in recipe.sections.iter().enumerate() {
-        w_section(w, section, recipe, idx + 1, opts)
+        w_section(w, section, recipe, idx + 1, opts, converter)
             .context(format!("Failed to write section {}", idx + 1))?;
     }
     Ok(())
@@ -413,6 +512,7 @@ fn w_section(
     recipe: &Recipe,
     num: usize,
     opts: &Options,
+    converter: &Converter,
 ) -> Result<()> {
     if section.name.is_some() || recipe.sections.len() > 1 {
         if let Some(name) = &section.name {
@@ -425,7 +525,11 @@ fn w_section(
     for content in &section.content {
         match content {
             cooklang::Content::Step(step) => {
-                w_step(w, step, recipe, opts).context("Failed to write step")?
+                w_step(w, step, recipe, opts).context("Failed to write step")?;
+                if opts.step_quantities {
+                    w_step_quantities(w, step, recipe, opts, converter)
+                        .context("Failed to write step quantities")?
+                }
             }
             cooklang::Content::Text(text) => {
                 // Check if this is a list bullet item
@@ -476,12 +580,12 @@ fn w_step(w: &mut impl io::Write, step: &Step, recipe: &Recipe, opts: &Options)
                     write!(&mut step_str, "({name})").context("Failed to write timer name")?;
                 }
                 if let Some(quantity) = &t.quantity {
-                    write!(&mut step_str, "{quantity}")
+                    write!(&mut step_str, "{}", display_quantity(quantity))
                         .context("Failed to write timer quantity")?;
                 }
             }
             &Item::InlineQuantity { index } => {
-                let q = &recipe.inline_quantities[index];
+                let q = display_quantity(&recipe.inline_quantities[index]);
                 if opts.italic_amounts {
                     write!(&mut step_str, "*{q}*")
                         .context("Failed to write italicized inline quantity")?;
@@ -495,6 +599,31 @@ fn w_step(w: &mut impl io::Write, step: &Step, recipe: &Recipe, opts: &Options)
     Ok(())
 }
 
+/// The ingredients of the step as a list nested in it
+fn w_step_quantities(
+    w: &mut impl io::Write,
+    step: &Step,
+    recipe: &Recipe,
+    opts: &Options,
+    converter: &Converter,
+) -> Result<()> {
+    let ingredients = super::step_quantities::step_ingredients(recipe, step, converter);
+    if ingredients.is_empty() {
+        return Ok(());
+    }
+    writeln!(w)?;
+    for ingredient in ingredients {
+        write!(w, "   - ")?;
+        match ingredient.display {
+            Some(quantity) if opts.italic_amounts => write!(w, "*{quantity}* ")?,
+            Some(quantity) => write!(w, "{quantity} ")?,
+            None => {}
+        }
+        writeln!(w, "{}", ingredient.name)?;
+    }
+    Ok(())
+}
+
 fn print_wrapped(w: &mut impl io::Write, text: &str) -> Result<()> {
     print_wrapped_with_options(w, text, |o| o)
 }
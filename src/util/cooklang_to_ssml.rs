@@ -0,0 +1,180 @@
+//! Format a recipe as SSML, for text-to-speech and smart speakers
+//!
+//! Every step is a `<speak>` document of its own, so a voice assistant can
+//! read one step at a time. The first document introduces the recipe with
+//! its ingredients and cookware. Timers are followed by a pause.
+
+use std::io;
+
+use anyhow::Result;
+use cooklang::{
+    convert::Converter,
+    model::{Item, Step},
+    Content, Recipe,
+};
+
+use super::{
+    escape_html,
+    format::{display_grouped, display_quantity},
+    RecipePart,
+};
+
+/// The pause after a timer, time to start it
+const TIMER_PAUSE: &str = "2s";
+
+/// Write the recipe as SSML documents separated by blank lines
+///
+/// No parts writes the whole recipe.
+pub fn print_ssml_parts(
+    recipe: &Recipe,
+    name: &str,
+    parts: &[RecipePart],
+    converter: &Converter,
+    mut w: impl io::Write,
+) -> Result<()> {
+    let show = |part| parts.is_empty() || parts.contains(&part);
+
+    let mut intro = Vec::new();
+    if parts.is_empty() {
+        intro.push(format!("<s>{}.</s>", escape_html(name)));
+    }
+    if show(RecipePart::Ingredients) {
+        let ingredients: Vec<String> = recipe
+            .group_ingredients(converter)
+            .into_iter()
+            .filter(|entry| entry.ingredient.modifiers().should_be_listed())
+            .map(|entry| {
+                let name = entry.ingredient.display_name();
+                if entry.quantity.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{} {name}", display_grouped(&entry.quantity))
+                }
+            })
+            .collect();
+        if !ingredients.is_empty() {
+            intro.push(format!(
+                "<s>You need {}.</s>",
+                escape_html(&spoken_list(&ingredients))
+            ));
+        }
+    }
+    if show(RecipePart::Cookware) {
+        let cookware: Vec<String> = recipe
+            .group_cookware(converter)
+            .into_iter()
+            .filter(|entry| entry.cookware.modifiers().should_be_listed())
+            .map(|entry| entry.cookware.display_name().to_string())
+            .collect();
+        if !cookware.is_empty() {
+            intro.push(format!(
+                "<s>Get out {}.</s>",
+                escape_html(&spoken_list(&cookware))
+            ));
+        }
+    }
+    if !intro.is_empty() {
+        write_speak(&mut w, &intro.join(""))?;
+    }
+
+    if !show(RecipePart::Steps) {
+        return Ok(());
+    }
+    for section in &recipe.sections {
+        let mut heading = section
+            .name
+            .as_ref()
+            .map(|name| format!("<s>{}.</s>", escape_html(name)));
+        for content in &section.content {
+            let body = match content {
+                Content::Step(step) => format!(
+                    "<s>Step {}.</s><p>{}</p>",
+                    step.number,
+                    step_ssml(recipe, step)
+                ),
+                Content::Text(text) => format!("<p>{}</p>", escape_html(text.trim())),
+            };
+            let heading = heading.take().unwrap_or_default();
+            write_speak(&mut w, &format!("{heading}{body}"))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_speak(w: &mut impl io::Write, body: &str) -> Result<()> {
+    writeln!(w, "<speak>{body}</speak>")?;
+    writeln!(w)?;
+    Ok(())
+}
+
+/// The text of a step with the amounts spoken where they're used
+fn step_ssml(recipe: &Recipe, step: &Step) -> String {
+    let mut ssml = String::new();
+    for item in &step.items {
+        match item {
+            Item::Text { value } => ssml += &escape_html(value),
+            &Item::Ingredient { index } => {
+                let ingredient = &recipe.ingredients[index];
+                if let Some(quantity) = &ingredient.quantity {
+                    ssml += &escape_html(&display_quantity(quantity));
+                    ssml.push(' ');
+                }
+                ssml += &escape_html(ingredient.display_name().as_ref());
+            }
+            &Item::Cookware { index } => {
+                ssml += &escape_html(recipe.cookware[index].display_name().as_ref())
+            }
+            &Item::Timer { index } => {
+                let timer = &recipe.timers[index];
+                match (&timer.quantity, &timer.name) {
+                    (Some(quantity), _) => ssml += &escape_html(&display_quantity(quantity)),
+                    (None, Some(name)) => ssml += &escape_html(name),
+                    (None, None) => {}
+                }
+                ssml += &format!("<break time=\"{TIMER_PAUSE}\"/>");
+            }
+            &Item::InlineQuantity { index } => {
+                ssml += &escape_html(&display_quantity(&recipe.inline_quantities[index]))
+            }
+        }
+    }
+    ssml.trim().to_string()
+}
+
+/// "a, b and c"
+fn spoken_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [one] => one.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PARSER;
+
+    #[test]
+    fn test_print_ssml() {
+        let recipe = PARSER
+            .parse("Mix @flour{200%g} & @water in a #bowl.\n\nRest ~{10%minutes}, then bake.\n")
+            .into_result()
+            .unwrap()
+            .0;
+        let mut out = Vec::new();
+        print_ssml_parts(&recipe, "Bread", &[], PARSER.converter(), &mut out).unwrap();
+        let ssml = String::from_utf8(out).unwrap();
+        let blocks: Vec<_> = ssml.split("\n\n").filter(|b| !b.is_empty()).collect();
+        assert_eq!(blocks.len(), 3);
+        assert!(blocks[0].starts_with("<speak><s>Bread.</s><s>You need 200 g flour and water.</s>"));
+        assert_eq!(
+            blocks[1],
+            "<speak><s>Step 1.</s><p>Mix 200 g flour &amp; water in a bowl.</p></speak>"
+        );
+        assert_eq!(
+            blocks[2],
+            "<speak><s>Step 2.</s><p>Rest 10 minutes<break time=\"2s\"/>, then bake.</p></speak>"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! The cookware needed for several recipes, as a checklist
+//!
+//! A recipe needs the most of an item it names at once, like `#bowl{3}`,
+//! or one. Recipes are prepared side by side, so their counts add up.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context as _, Result};
+use camino::Utf8PathBuf;
+use cooklang::{quantity::Value, Recipe};
+use serde::Serialize;
+
+use super::{get_recipe, parse_recipe_from_entry, split_recipe_name_and_scaling_factor};
+
+/// An item of cookware and how many of it are needed
+#[derive(Debug, Clone, Serialize)]
+pub struct CookwareItem {
+    pub name: String,
+    pub count: u32,
+    /// Whether every recipe using it marks it optional
+    pub optional: bool,
+    /// Titles of the recipes using it
+    pub recipes: Vec<String>,
+}
+
+/// Cookware of several recipes, by name
+#[derive(Debug, Default)]
+pub struct CookwareList {
+    items: BTreeMap<String, CookwareItem>,
+}
+
+impl CookwareList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the cookware of a recipe, without the recipes it references
+    pub fn add_recipe(&mut self, recipe: &Recipe, title: &str) {
+        let mut counts: Vec<(String, u32, bool)> = Vec::new();
+        for cookware in recipe
+            .cookware
+            .iter()
+            .filter(|cw| cw.modifiers().should_be_listed())
+        {
+            let count = match cookware.quantity.as_ref().map(|q| q.value()) {
+                Some(Value::Number(n)) if n.value() >= 1.0 => n.value().ceil() as u32,
+                _ => 1,
+            };
+            let name = cookware.display_name().to_string();
+            let optional = cookware.modifiers().is_optional();
+            match counts
+                .iter_mut()
+                .find(|(n, _, _)| n.eq_ignore_ascii_case(&name))
+            {
+                Some(found) => {
+                    found.1 = found.1.max(count);
+                    found.2 &= optional;
+                }
+                None => counts.push((name, count, optional)),
+            }
+        }
+
+        for (name, count, optional) in counts {
+            let item = self
+                .items
+                .entry(name.to_lowercase())
+                .or_insert_with(|| CookwareItem {
+                    name,
+                    count: 0,
+                    optional: true,
+                    recipes: Vec::new(),
+                });
+            item.count += count;
+            item.optional &= optional;
+            if !item.recipes.iter().any(|r| r == title) {
+                item.recipes.push(title.to_string());
+            }
+        }
+    }
+
+    /// The items sorted by name
+    pub fn into_items(self) -> Vec<CookwareItem> {
+        self.items.into_values().collect()
+    }
+}
+
+/// Add the cookware of a recipe entry, like "Pizza" or "Pizza:2", and of
+/// the recipes it references, like a menu does
+///
+/// Scaling factors are accepted, but cookware doesn't scale.
+pub fn collect_cookware(
+    entry: &str,
+    list: &mut CookwareList,
+    seen: &mut Vec<String>,
+    base_path: &Utf8PathBuf,
+    ignore_references: bool,
+) -> Result<()> {
+    let name = split_recipe_name_and_scaling_factor(entry)
+        .map(|(name, _)| name)
+        .unwrap_or(entry);
+    if seen.iter().any(|s| s == name) {
+        anyhow::bail!("Circular dependency found: {} -> {name}", seen.join(" -> "));
+    }
+
+    let recipe_entry =
+        get_recipe(base_path, name).with_context(|| format!("Failed to find recipe '{name}'"))?;
+    let recipe = parse_recipe_from_entry(&recipe_entry, 1.0)?;
+    let title = recipe_entry
+        .name()
+        .clone()
+        .unwrap_or_else(|| name.to_string());
+    list.add_recipe(&recipe, &title);
+
+    if ignore_references {
+        return Ok(());
+    }
+    let recipe_dir = recipe_entry
+        .path()
+        .and_then(|p| p.parent())
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| base_path.clone());
+
+    seen.push(name.to_string());
+    for ingredient in &recipe.ingredients {
+        let Some(reference) = &ingredient.reference else {
+            continue;
+        };
+        let path = if reference.components.is_empty() {
+            reference.name.clone()
+        } else {
+            format!("{}/{}", reference.components.join("/"), reference.name)
+        };
+        // References are relative to the recipe, or to the collection
+        let base = if get_recipe(&recipe_dir, &path).is_ok() {
+            &recipe_dir
+        } else {
+            base_path
+        };
+        collect_cookware(&path, list, seen, base, ignore_references)?;
+    }
+    seen.pop();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PARSER;
+
+    #[test]
+    fn test_add_recipe() {
+        let parse = |content: &str| PARSER.parse(content).into_result().unwrap().0;
+        let mut list = CookwareList::new();
+        list.add_recipe(
+            &parse("Mix in a #bowl{2}, then rest in the #bowl. Bake on a #sheet pan{}.\n"),
+            "Cookies",
+        );
+        list.add_recipe(&parse("Toss in a #Bowl. Serve with #?tongs{}.\n"), "Salad");
+
+        let items = list.into_items();
+        let summary: Vec<_> = items
+            .iter()
+            .map(|item| (item.name.as_str(), item.count, item.optional))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("bowl", 3, false),
+                ("sheet pan", 1, false),
+                ("tongs", 1, true)
+            ]
+        );
+        assert_eq!(items[0].recipes, ["Cookies", "Salad"]);
+    }
+}
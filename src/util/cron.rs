@@ -0,0 +1,242 @@
+//! Cron expressions, for the tasks `cook server` runs on a schedule
+//!
+//! The usual five fields, minute, hour, day of the month, month and day of
+//! the week, each `*`, a number, a range like `1-5`, a step like `*/15` or
+//! `8-18/2`, or a list of those. Months and days can be named, like `jan`
+//! or `mon`; Sunday is 0 or 7. `@hourly`, `@daily`, `@weekly`, `@monthly`
+//! and `@yearly` are shortcuts. Like in cron, when both days are
+//! restricted a time matches either of them.
+
+use anyhow::{bail, Context as _, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use std::{fmt, str::FromStr};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const NO_NAMES: &[&str] = &[];
+
+/// How far ahead to look for the next time, enough for "0 0 29 2 *"
+const MAX_DAYS: i64 = 8 * 366;
+
+/// When a scheduled task runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    /// Bit n set when minute n matches
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    /// Bit 0 is Sunday
+    weekdays: u8,
+    /// Whether the days of the month and of the week aren't `*`
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    /// The first time after `after` that matches, to the minute
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(MAX_DAYS);
+        let mut time = start;
+        while time < limit {
+            if !has(self.months as u64, time.month()) {
+                // The first day of the next month
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_time(NaiveTime::MIN);
+            } else if !self.day_matches(time.date()) {
+                time = time.date().succ_opt()?.and_time(NaiveTime::MIN);
+            } else if !has(self.hours as u64, time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if !has(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = has(self.days as u64, date.day());
+        let weekday = has(self.weekdays as u64, date.weekday().num_days_from_sunday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            bail!("'{expression}' should have five fields: minute hour day month weekday");
+        };
+
+        let parse = |field: &str, name: &str, min: u32, max: u32, names: &[&str]| {
+            parse_field(field, min, max, names)
+                .with_context(|| format!("Invalid {name} '{field}' in '{expression}'"))
+        };
+        let mut weekday_bits = parse(weekdays, "day of the week", 0, 7, &WEEKDAYS[..])?;
+        // 7 is Sunday too
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits |= 1;
+        }
+        Ok(Self {
+            expression: expression.trim().to_string(),
+            minutes: parse(minutes, "minute", 0, 59, NO_NAMES)?,
+            hours: parse(hours, "hour", 0, 23, NO_NAMES)? as u32,
+            days: parse(days, "day of the month", 1, 31, NO_NAMES)? as u32,
+            months: parse(months, "month", 1, 12, &MONTHS[..])? as u16,
+            weekdays: (weekday_bits & 0x7f) as u8,
+            days_restricted: days != "*",
+            weekdays_restricted: weekdays != "*",
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+fn has(bits: u64, n: u32) -> bool {
+    bits & (1 << n) != 0
+}
+
+/// The values a field matches, as bits
+///
+/// Names are numbered from `min`, so "jan" is 1 and "sun" 0.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let value = |text: &str| -> Result<u32> {
+        let lowercase = text.to_lowercase();
+        let n = match names.iter().position(|name| *name == lowercase) {
+            Some(i) => i as u32 + min,
+            None => text
+                .parse()
+                .with_context(|| format!("'{text}' isn't a number"))?,
+        };
+        if !(min..=max).contains(&n) {
+            bail!("{n} isn't between {min} and {max}");
+        }
+        Ok(n)
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .with_context(|| format!("'{step}' isn't a step"))?;
+                if step == 0 {
+                    bail!("The step can't be 0");
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((first, last)) => (value(first)?, value(last)?),
+                // Like "5/10", from 5 to the end
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let n = value(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if first > last {
+            bail!("The range {first}-{last} is backwards");
+        }
+        for n in (first..=last).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> NaiveDateTime {
+        expression
+            .parse::<CronSchedule>()
+            .unwrap()
+            .next_after(at(after))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(
+            next("*/15 * * * *", "2024-03-18 10:07"),
+            at("2024-03-18 10:15")
+        );
+        assert_eq!(
+            next("0 3 * * *", "2024-03-18 03:00"),
+            at("2024-03-19 03:00")
+        );
+        // Sunday evening
+        assert_eq!(
+            next("0 18 * * sun", "2024-03-18 10:00"),
+            at("2024-03-24 18:00")
+        );
+        assert_eq!(
+            next("0 18 * * 7", "2024-03-18 10:00"),
+            at("2024-03-24 18:00")
+        );
+        assert_eq!(next("@monthly", "2024-12-15 00:00"), at("2025-01-01 00:00"));
+        assert_eq!(
+            next("30 8-18/2 * jan-mar mon-fri", "2024-03-29 19:00"),
+            at("2025-01-01 08:30")
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2024-03-01 00:00"),
+            at("2028-02-29 00:00")
+        );
+        // Either day matches
+        assert_eq!(
+            next("0 0 1 * mon", "2024-03-19 00:00"),
+            at("2024-03-25 00:00")
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "0 0 * foo *",
+        ] {
+            assert!(expression.parse::<CronSchedule>().is_err(), "{expression}");
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! The CSV reader used for nutrition databases and imported tables
+//!
+//! Fields may be quoted with `"`, quoted fields may span lines and `""` in
+//! them is a quote. Records are read a line at a time, so large files, like
+//! the USDA downloads, aren't read into memory at once.
+
+use std::io::{self, BufRead};
+
+/// Records of CSV read from `reader`
+pub fn records<R: BufRead>(reader: R) -> Records<R> {
+    Records {
+        lines: reader.lines(),
+        line: 0,
+        start: 0,
+        comments: false,
+    }
+}
+
+/// The records of CSV text, without blank ones
+pub fn parse(content: &str) -> Vec<Vec<String>> {
+    records(content.trim_start_matches('\u{feff}').as_bytes())
+        .map_while(Result::ok)
+        .filter(|record| record.iter().any(|field| !field.trim().is_empty()))
+        .collect()
+}
+
+pub struct Records<R> {
+    lines: io::Lines<R>,
+    /// Lines read so far
+    line: usize,
+    /// Line the last record started on, from 1
+    start: usize,
+    comments: bool,
+}
+
+impl<R> Records<R> {
+    /// Skip lines starting with `#` between records
+    pub fn skip_comments(mut self) -> Self {
+        self.comments = true;
+        self
+    }
+
+    /// Line the last record started on, from 1
+    pub fn line(&self) -> usize {
+        self.start
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        loop {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e)),
+                // A quote left open takes the rest of the file
+                None if in_quotes => break,
+                None => return None,
+            };
+            self.line += 1;
+            if !in_quotes {
+                if self.comments && line.starts_with('#') {
+                    continue;
+                }
+                self.start = self.line;
+            } else {
+                field.push('\n');
+            }
+
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if in_quotes && chars.peek() == Some(&'"') => {
+                        field.push('"');
+                        chars.next();
+                    }
+                    '"' => in_quotes = !in_quotes,
+                    ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+                    _ => field.push(c),
+                }
+            }
+            if !in_quotes {
+                break;
+            }
+        }
+        record.push(field);
+        Some(Ok(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let records =
+            parse("Name,Ingredients\r\nPancakes,\"2 eggs\n200 g flour\"\n\nTea,\"say \"\"hi\"\"\"");
+        assert_eq!(
+            records,
+            [
+                vec!["Name", "Ingredients"],
+                vec!["Pancakes", "2 eggs\n200 g flour"],
+                vec!["Tea", "say \"hi\""],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_records() {
+        let mut records = records("# name\na,\"b\n# c\"\nd,e\n".as_bytes()).skip_comments();
+        assert_eq!(records.next().unwrap().unwrap(), ["a", "b\n# c"]);
+        assert_eq!(records.line(), 2);
+        assert_eq!(records.next().unwrap().unwrap(), ["d", "e"]);
+        assert_eq!(records.line(), 4);
+        assert!(records.next().is_none());
+    }
+}
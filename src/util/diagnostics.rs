@@ -0,0 +1,236 @@
+//! Parse errors that point at the source
+//!
+//! The parser reports where in the recipe each problem is. Instead of a
+//! bare "Failed to parse recipe", errors show the offending lines with the
+//! span underlined and the parser's hints, and the server sends the same
+//! information as JSON so editors can highlight it.
+
+use cooklang::error::{SourceDiag, SourceReport};
+use serde::Serialize;
+use std::fmt::{self, Write as _};
+
+/// A problem found by the parser, with positions resolved to lines
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    /// "error" or "warning"
+    pub severity: &'static str,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub hints: Vec<String>,
+}
+
+/// A span of the recipe a diagnostic is about
+#[derive(Debug, Clone, Serialize)]
+pub struct Label {
+    /// Byte offsets in the file
+    pub start: usize,
+    pub end: usize,
+    /// 1-based line and column (in characters) of the start
+    pub line: usize,
+    pub column: usize,
+    pub message: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(severity: &'static str, diag: &SourceDiag, source: &str) -> Self {
+        Self {
+            severity,
+            message: diag.to_string(),
+            labels: diag
+                .labels
+                .iter()
+                .map(|(span, message)| {
+                    let (line, column) = position(source, span.start());
+                    Label {
+                        start: span.start(),
+                        end: span.end(),
+                        line,
+                        column,
+                        message: message.as_ref().map(|m| m.to_string()),
+                    }
+                })
+                .collect(),
+            hints: diag.hints.iter().map(|h| h.to_string()).collect(),
+        }
+    }
+
+    /// Errors then warnings of a report
+    pub fn from_report(report: &SourceReport, source: &str) -> Vec<Self> {
+        report
+            .errors()
+            .map(|diag| Self::new("error", diag, source))
+            .chain(
+                report
+                    .warnings()
+                    .map(|diag| Self::new("warning", diag, source)),
+            )
+            .collect()
+    }
+
+    /// Show the diagnostic with the lines it's about, like:
+    ///
+    /// ```text
+    /// error: Invalid quantity
+    ///  --> Pancakes.cook:3:11
+    ///   |
+    /// 3 | Add @flour{2%}.
+    ///   |           ^^^^ expected a unit
+    ///   = help: remove the '%' or add a unit
+    /// ```
+    pub fn render(&self, file: &str, source: &str) -> String {
+        format!(
+            "{}: {}\n{}",
+            self.severity,
+            self.message,
+            self.snippet(file, source)
+        )
+    }
+
+    /// The lines the diagnostic is about and its hints, without the message
+    pub fn snippet(&self, file: &str, source: &str) -> String {
+        let mut out = String::new();
+        let width = self
+            .labels
+            .iter()
+            .map(|label| label.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+
+        if let Some(first) = self.labels.first() {
+            let _ = writeln!(out, "{gutter}--> {file}:{}:{}", first.line, first.column);
+            let _ = writeln!(out, "{gutter} |");
+        }
+        for label in &self.labels {
+            let text = source.lines().nth(label.line - 1).unwrap_or_default();
+            let line_start = source[..label.start.min(source.len())]
+                .rfind('\n')
+                .map_or(0, |i| i + 1);
+            // Spans over several lines are underlined to the end of the first
+            let end = label.end.min(line_start + text.len()).max(label.start);
+            let underline = source
+                .get(label.start..end)
+                .map_or(1, |s| s.chars().count())
+                .max(1);
+            let _ = writeln!(out, "{:>width$} | {text}", label.line);
+            let _ = write!(
+                out,
+                "{gutter} | {}{}",
+                " ".repeat(label.column - 1),
+                "^".repeat(underline)
+            );
+            match &label.message {
+                Some(message) => {
+                    let _ = writeln!(out, " {message}");
+                }
+                None => out.push('\n'),
+            }
+        }
+        for hint in &self.hints {
+            let _ = writeln!(out, "{gutter} = help: {hint}");
+        }
+        out
+    }
+}
+
+/// A recipe that failed to parse, with everything the parser found
+///
+/// Displays as the rendered diagnostics. Handlers downcast errors to this to
+/// send the diagnostics to clients.
+#[derive(Debug)]
+pub struct ParseError {
+    pub file: String,
+    pub diagnostics: Vec<Diagnostic>,
+    rendered: String,
+}
+
+impl ParseError {
+    pub fn new(file: &str, source: &str, report: &SourceReport) -> Self {
+        let diagnostics = Diagnostic::from_report(report, source);
+        let rendered = diagnostics
+            .iter()
+            .map(|diag| diag.render(file, source))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self {
+            file: file.to_string(),
+            diagnostics,
+            rendered,
+        }
+    }
+
+    /// Message of the first error
+    pub fn summary(&self) -> &str {
+        self.diagnostics
+            .iter()
+            .find(|diag| diag.severity == "error")
+            .map_or("Failed to parse recipe", |diag| &diag.message)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to parse {}", self.file)?;
+        if !self.rendered.is_empty() {
+            write!(f, "\n\n{}", self.rendered.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// 1-based line and column (in characters) of a byte offset
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let source = "---\ntitle: Tea\n---\nBoil @wäter{1%l}.\n";
+        assert_eq!(position(source, 0), (1, 1));
+        let at = source.find("{1").unwrap();
+        // 'ä' is two bytes but one column
+        assert_eq!(position(source, at), (4, 12));
+        assert_eq!(position(source, source.len()), (5, 1));
+    }
+
+    #[test]
+    fn test_render() {
+        let source = "Mix @flour{2%} and @eggs{2}.\n";
+        let start = source.find("{2%}").unwrap();
+        let diagnostic = Diagnostic {
+            severity: "error",
+            message: "Invalid quantity".to_string(),
+            labels: vec![Label {
+                start,
+                end: start + 4,
+                line: 1,
+                column: start + 1,
+                message: Some("expected a unit".to_string()),
+            }],
+            hints: vec!["add a unit after '%'".to_string()],
+        };
+
+        assert_eq!(
+            diagnostic.render("Bread.cook", source),
+            "error: Invalid quantity\n \
+             --> Bread.cook:1:11\n  \
+             |\n\
+             1 | Mix @flour{2%} and @eggs{2}.\n  \
+             |           ^^^^ expected a unit\n  \
+             = help: add a unit after '%'\n"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! The weekly digest: the meals planned in the menus for the coming days
+//! and the shopping list for them, as an email
+//!
+//! Built by `cook notify --email`, meant to be run from cron.
+
+use anyhow::{bail, Context as _, Result};
+use chrono::{Days, NaiveDate};
+use cooklang::ingredient_list::IngredientList;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use super::{
+    aliases,
+    calendar::{collection_events, MealEvent},
+    escape_html, extract_ingredients,
+    format::display_grouped,
+    pantry::{apply_aliases, load_pantry},
+    PARSER,
+};
+use crate::{
+    config::{EmailConfig, SmtpTls},
+    Context,
+};
+
+/// Environment variable holding the SMTP password, unless `[email]` names
+/// another one
+pub const PASSWORD_ENV: &str = "COOK_SMTP_PASSWORD";
+
+/// The plan and shopping list for some days
+#[derive(Debug)]
+pub struct Digest {
+    pub start: NaiveDate,
+    /// The last day, included
+    pub end: NaiveDate,
+    pub meals: Vec<MealEvent>,
+    /// Aisle categories with their items and quantities
+    pub shopping_list: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Digest {
+    /// The meals planned for `days` days from `start`, and what to buy for
+    /// the recipes in them
+    ///
+    /// Planned recipes that can't be found are left off the shopping list
+    /// with a warning. The pantry is subtracted like in `cook shopping-list`.
+    pub fn build(ctx: &Context, start: NaiveDate, days: u64) -> Result<Self> {
+        let end = start
+            .checked_add_days(Days::new(days.saturating_sub(1)))
+            .context("Too many days")?;
+        let meals: Vec<MealEvent> = collection_events(ctx.base_path())?
+            .into_iter()
+            .filter(|event| (start..=end).contains(&event.date))
+            .collect();
+
+        let mut list = IngredientList::new();
+        for recipe in meals
+            .iter()
+            .flat_map(|meal| &meal.items)
+            .filter_map(|item| item.recipe.as_deref())
+        {
+            // Every time it's planned, so a dish cooked twice is bought twice
+            if let Err(e) = extract_ingredients(
+                recipe,
+                &mut list,
+                &mut BTreeMap::new(),
+                ctx.base_path(),
+                PARSER.converter(),
+                false,
+            ) {
+                tracing::warn!("Leaving {recipe} off the shopping list: {e:#}");
+            }
+        }
+        let (mut list, _) = aliases::apply(list, PARSER.converter());
+        if let Some(path) = ctx.pantry() {
+            let mut pantry = load_pantry(&path)?;
+            apply_aliases(&mut pantry);
+            list = list.subtract_pantry(&pantry, PARSER.converter());
+        }
+
+        let aisle_content = match ctx.aisle() {
+            Some(path) => std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read aisle file {path}"))?,
+            None => String::new(),
+        };
+        let aisle = cooklang::aisle::parse_lenient(&aisle_content)
+            .output()
+            .cloned()
+            .unwrap_or_default();
+        let shopping_list = list
+            .categorize(&aisle)
+            .into_iter()
+            .map(|(category, items)| {
+                let items = items
+                    .into_iter()
+                    .map(|(name, quantity)| {
+                        let quantity = if quantity.is_empty() {
+                            String::new()
+                        } else {
+                            display_grouped(&quantity)
+                        };
+                        (name, quantity)
+                    })
+                    .collect();
+                (category.to_string(), items)
+            })
+            .collect();
+
+        Ok(Self {
+            start,
+            end,
+            meals,
+            shopping_list,
+        })
+    }
+
+    pub fn subject(&self) -> String {
+        format!("Meal plan for the week of {}", self.start.format("%-d %B"))
+    }
+
+    /// The digest as plain text
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "Meal plan, {} to {}\n",
+            self.start.format("%A %-d %B"),
+            self.end.format("%A %-d %B")
+        );
+        if self.meals.is_empty() {
+            text += "\nNothing planned.\n";
+        }
+        for (date, meals) in self.days() {
+            let _ = writeln!(text, "\n{}", date.format("%A %-d %B"));
+            for meal in meals {
+                let _ = writeln!(text, "  {}", meal.summary());
+            }
+        }
+
+        if !self.shopping_list.is_empty() {
+            text += "\nShopping list\n";
+            for (category, items) in &self.shopping_list {
+                let _ = writeln!(text, "\n[{category}]");
+                for (name, quantity) in items {
+                    let _ = writeln!(text, "  {name}  {quantity}");
+                }
+            }
+        }
+        text
+    }
+
+    /// The digest as an HTML email body
+    pub fn html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html><body style=\"font-family: sans-serif; color: #1f2937;\">\n",
+        );
+        let _ = writeln!(
+            html,
+            "<h1 style=\"color: #ea580c;\">Meal plan</h1>\n<p>{} to {}</p>",
+            self.start.format("%A %-d %B"),
+            self.end.format("%A %-d %B")
+        );
+        if self.meals.is_empty() {
+            html += "<p>Nothing planned.</p>\n";
+        }
+        for (date, meals) in self.days() {
+            let _ = writeln!(html, "<h3>{}</h3>\n<ul>", date.format("%A %-d %B"));
+            for meal in meals {
+                let _ = writeln!(html, "<li>{}</li>", escape_html(&meal.summary()));
+            }
+            html += "</ul>\n";
+        }
+
+        if !self.shopping_list.is_empty() {
+            html += "<h2 style=\"color: #ea580c;\">Shopping list</h2>\n";
+            for (category, items) in &self.shopping_list {
+                let _ = writeln!(html, "<h3>{}</h3>\n<ul>", escape_html(category));
+                for (name, quantity) in items {
+                    let _ = writeln!(
+                        html,
+                        "<li>{} <span style=\"color: #6b7280;\">{}</span></li>",
+                        escape_html(name),
+                        escape_html(quantity)
+                    );
+                }
+                html += "</ul>\n";
+            }
+        }
+        html += "</body></html>\n";
+        html
+    }
+
+    /// Meals by day, in order
+    fn days(&self) -> Vec<(NaiveDate, Vec<&MealEvent>)> {
+        let mut days: Vec<(NaiveDate, Vec<&MealEvent>)> = Vec::new();
+        for meal in &self.meals {
+            match days.last_mut() {
+                Some((date, meals)) if *date == meal.date => meals.push(meal),
+                _ => days.push((meal.date, vec![meal])),
+            }
+        }
+        days
+    }
+}
+
+/// Send the digest through the SMTP server in `[email]`
+pub fn send(config: &EmailConfig, to: &[String], digest: &Digest) -> Result<()> {
+    let Some(host) = config.smtp_host.as_deref() else {
+        bail!("Set email.smtp_host in cook.toml to send email");
+    };
+    let Some(from) = config.from.as_deref() else {
+        bail!("Set email.from in cook.toml to send email");
+    };
+    if to.is_empty() {
+        bail!("No one to send the digest to, set email.to in cook.toml or use --to");
+    }
+
+    let mut message = Message::builder()
+        .from(
+            from.parse::<Mailbox>()
+                .with_context(|| format!("Invalid sender '{from}'"))?,
+        )
+        .subject(digest.subject());
+    for recipient in to {
+        message = message.to(recipient
+            .parse::<Mailbox>()
+            .with_context(|| format!("Invalid recipient '{recipient}'"))?);
+    }
+    let message = message.multipart(MultiPart::alternative_plain_html(
+        digest.text(),
+        digest.html(),
+    ))?;
+
+    let tls = config.tls.unwrap_or_default();
+    let mut transport = match tls {
+        SmtpTls::Starttls => SmtpTransport::starttls_relay(host)?,
+        SmtpTls::Tls => SmtpTransport::relay(host)?,
+        SmtpTls::None => SmtpTransport::builder_dangerous(host),
+    };
+    if let Some(port) = config.smtp_port {
+        transport = transport.port(port);
+    }
+    if let Some(user) = &config.smtp_user {
+        let password_env = config.password_env.as_deref().unwrap_or(PASSWORD_ENV);
+        let password = std::env::var(password_env)
+            .with_context(|| format!("{password_env} is not set, it holds the SMTP password"))?;
+        transport = transport.credentials(Credentials::new(user.clone(), password));
+    }
+    transport
+        .build()
+        .send(&message)
+        .with_context(|| format!("Failed to send the digest through {host}"))?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Copying a recipe as the start of a variation, like a chocolate souffle
+//! from the plain one
+//!
+//! The copy gets its own title and file name. Its source and author can be
+//! cleared, since a variation is no longer the recipe they published.
+
+use anyhow::{bail, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde_yaml::Value;
+
+use super::{front_matter::FrontMatter, names::sanitize_stem};
+
+/// Metadata about where a recipe comes from, dropped with `clear_source`
+const SOURCE_KEYS: &[&str] = &[
+    "source",
+    "source.name",
+    "source.url",
+    "source.author",
+    "author",
+    "author.name",
+    "author.url",
+];
+
+/// The recipe with a new title, and without its source if `clear_source`
+///
+/// Only the front matter changes, the steps are copied as they are.
+pub fn duplicate(content: &str, title: &str, clear_source: bool) -> Result<String> {
+    let title = title.trim();
+    if title.is_empty() {
+        bail!("The new recipe needs a title");
+    }
+    let mut front_matter = FrontMatter::parse(content)?;
+    front_matter.metadata.insert(
+        Value::String("title".to_string()),
+        Value::String(title.to_string()),
+    );
+    if clear_source {
+        for key in SOURCE_KEYS {
+            front_matter.unset(key);
+        }
+    }
+    front_matter.render()
+}
+
+/// Where the copy of `original` titled `title` goes: next to it, named
+/// after the title
+pub fn duplicate_path(original: &Utf8Path, title: &str) -> Utf8PathBuf {
+    let extension = original.extension().unwrap_or("cook");
+    original.with_file_name(format!("{}.{extension}", sanitize_stem(title)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate() {
+        let recipe = "---\ntitle: Souffle\nsource: https://example.com/souffle\n\
+            author: Julia\nservings: 4\n---\n\nWhisk @eggs{4}.\n";
+        assert_eq!(
+            duplicate(recipe, "Chocolate Souffle", true).unwrap(),
+            "---\ntitle: Chocolate Souffle\nservings: 4\n---\n\nWhisk @eggs{4}.\n"
+        );
+        assert!(duplicate(recipe, "Chocolate Souffle", false)
+            .unwrap()
+            .contains("author: Julia"));
+        // Titles that look like numbers stay text
+        assert!(duplicate("Whisk @eggs{4}.\n", "1984", false)
+            .unwrap()
+            .starts_with("---\ntitle: '1984'\n---\n\n"));
+        assert!(duplicate(recipe, " ", false).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_path() {
+        assert_eq!(
+            duplicate_path(Utf8Path::new("Desserts/Souffle.cook"), "Fish: Baked?"),
+            "Desserts/Fish_ Baked_.cook"
+        );
+    }
+}
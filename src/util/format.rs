@@ -1,6 +1,17 @@
+use cooklang::quantity::{GroupedQuantity, Quantity};
+
+use super::locale::locale;
+
 /// Formats a floating-point number as a human-readable string with fractions
 /// Based on the approach from cooklang-rs/bindings/src/lib.rs
+///
+/// With a configured [locale](super::locale), numbers are written the way
+/// the locale says instead.
 pub fn format_number(value: f64) -> String {
+    if let Some(locale) = locale() {
+        return locale.number(value);
+    }
+
     // Round to reasonable precision to handle floating point errors
     // This handles cases like 0.89999999999 -> 0.9
     let rounded = (value * 1000000.0).round() / 1000000.0;
@@ -15,9 +26,14 @@ pub fn format_number(value: f64) -> String {
         return fraction;
     }
 
-    // For decimals, determine appropriate precision
+    format_decimal(rounded)
+}
+
+/// Formats a floating-point number as a plain decimal with at most 3 decimal
+/// places and no trailing zeros, never using fractions
+pub fn format_decimal(value: f64) -> String {
     // Round to at most 3 decimal places, but remove trailing zeros
-    let rounded_to_3 = (rounded * 1000.0).round() / 1000.0;
+    let rounded_to_3 = (value * 1000.0).round() / 1000.0;
 
     // Format with appropriate precision
     let mut result = if (rounded_to_3 * 100.0).fract().abs() < 0.001 {
@@ -88,6 +104,48 @@ pub fn format_quantity(value: &cooklang::Value) -> Option<String> {
     }
 }
 
+/// A unit's name in the configured locale
+pub fn format_unit(unit: &str) -> &str {
+    match locale() {
+        Some(locale) => locale.unit(unit),
+        None => unit,
+    }
+}
+
+/// A quantity's value for text output
+///
+/// Without a configured locale this is the parser's own formatting.
+pub fn display_value(value: &cooklang::Value) -> String {
+    match locale() {
+        Some(_) => format_quantity(value).unwrap_or_default(),
+        None => value.to_string(),
+    }
+}
+
+/// A quantity with its unit for text output, like "1,5 EL"
+pub fn display_quantity(quantity: &Quantity) -> String {
+    if locale().is_none() {
+        return quantity.to_string();
+    }
+    let value = display_value(quantity.value());
+    match quantity.unit() {
+        Some(unit) => format!("{value} {}", format_unit(unit)),
+        None => value,
+    }
+}
+
+/// Quantities of an ingredient that can't be added up, like "2 cups, 1 tbsp"
+pub fn display_grouped(quantity: &GroupedQuantity) -> String {
+    if locale().is_none() {
+        return quantity.to_string();
+    }
+    quantity
+        .iter()
+        .map(display_quantity)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
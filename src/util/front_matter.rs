@@ -0,0 +1,250 @@
+//! Editing the YAML front matter of a recipe
+//!
+//! Only the front matter is rewritten. Everything after its closing `---`
+//! is kept byte for byte, so the steps, comments and line endings of the
+//! recipe don't change.
+
+use anyhow::{bail, Context as _, Result};
+use serde_yaml::{Mapping, Value};
+
+use super::migrate::has_legacy_metadata;
+
+/// Keys holding lists, set from comma separated values
+const LIST_KEYS: &[&str] = &["tags"];
+
+/// A recipe split into its front matter and the rest
+pub struct FrontMatter<'a> {
+    pub metadata: Mapping,
+    body: &'a str,
+    newline: &'static str,
+    /// The recipe had a front matter block
+    existed: bool,
+}
+
+impl<'a> FrontMatter<'a> {
+    pub fn parse(content: &'a str) -> Result<Self> {
+        // The front matter gets the line ending of the first line
+        let newline = match content.split_once('\n') {
+            Some((first, _)) if first.ends_with('\r') => "\r\n",
+            _ => "\n",
+        };
+        let Some((yaml, body)) = split(content) else {
+            if has_legacy_metadata(content) {
+                bail!("The recipe uses `>> key: value` metadata, run `cook migrate --write` first");
+            }
+            return Ok(Self {
+                metadata: Mapping::new(),
+                body: content,
+                newline,
+                existed: false,
+            });
+        };
+        let metadata = if yaml.trim().is_empty() {
+            Mapping::new()
+        } else {
+            serde_yaml::from_str(yaml).context("The front matter is not valid YAML")?
+        };
+        Ok(Self {
+            metadata,
+            body,
+            newline,
+            existed: true,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.metadata.get(key)
+    }
+
+    /// Set a key, returns if the value changed
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        let value = if LIST_KEYS.contains(&key) {
+            Value::Sequence(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| Value::String(item.to_string()))
+                    .collect(),
+            )
+        } else {
+            yaml_value(value)
+        };
+        let key = Value::String(key.to_string());
+        if self.metadata.get(&key) == Some(&value) {
+            return false;
+        }
+        self.metadata.insert(key, value);
+        true
+    }
+
+    /// Remove a key, returns if it was set
+    pub fn unset(&mut self, key: &str) -> bool {
+        self.metadata.shift_remove(key).is_some()
+    }
+
+    /// The tags, whether they are a list or separated by commas
+    pub fn tags(&self) -> Vec<String> {
+        match self.get("tags") {
+            Some(Value::Sequence(items)) => items.iter().map(display_value).collect(),
+            Some(Value::String(tags)) => tags
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Replace the tags, written the way they were before, returns if they
+    /// changed
+    ///
+    /// The key is removed when there are no tags left.
+    pub fn set_tags(&mut self, tags: Vec<String>) -> bool {
+        if tags == self.tags() {
+            return false;
+        }
+        if tags.is_empty() {
+            return self.unset("tags");
+        }
+        let value = match self.get("tags") {
+            Some(Value::String(_)) => Value::String(tags.join(", ")),
+            _ => Value::Sequence(tags.into_iter().map(Value::String).collect()),
+        };
+        self.metadata
+            .insert(Value::String("tags".to_string()), value);
+        true
+    }
+
+    /// The recipe with the new front matter
+    ///
+    /// The front matter is left out when it has no keys left.
+    pub fn render(&self) -> Result<String> {
+        if self.metadata.is_empty() {
+            return Ok(self.body.to_string());
+        }
+        let yaml = serde_yaml::to_string(&self.metadata)?;
+        let nl = self.newline;
+        let mut out = format!("---{nl}");
+        for line in yaml.lines() {
+            out.push_str(line);
+            out.push_str(nl);
+        }
+        out.push_str("---");
+        out.push_str(nl);
+        if !self.existed && !self.body.is_empty() {
+            out.push_str(nl);
+        }
+        out.push_str(self.body);
+        Ok(out)
+    }
+}
+
+/// The YAML between the `---` fences at the start of the recipe and
+/// everything after the closing fence
+fn split(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\r\n")
+        .or_else(|| content.strip_prefix("---\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Keeps numbers and booleans typed, everything else becomes a string
+pub fn yaml_value(value: &str) -> Value {
+    match serde_yaml::from_str::<Value>(value) {
+        Ok(v @ (Value::Number(_) | Value::Bool(_))) => v,
+        _ => Value::String(value.to_string()),
+    }
+}
+
+/// A metadata value as plain text: strings without quotes, lists
+/// separated by commas
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Sequence(items) if items.iter().all(|item| !item.is_mapping()) => items
+            .iter()
+            .map(display_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_keeps_body() {
+        let recipe =
+            "---\ntitle: Pancakes\nservings: 2\n---\n\r\nMix @flour{200%g}.  \n-- a comment\n";
+        let mut front_matter = FrontMatter::parse(recipe).unwrap();
+        assert!(front_matter.set("servings", "4"));
+        assert!(front_matter.set("tags", "vegan, quick"));
+        assert_eq!(
+            front_matter.render().unwrap(),
+            "---\ntitle: Pancakes\nservings: 4\ntags:\n- vegan\n- quick\n---\n\r\nMix @flour{200%g}.  \n-- a comment\n"
+        );
+        assert!(!front_matter.set("servings", "4"));
+    }
+
+    #[test]
+    fn test_set_without_front_matter() {
+        let mut front_matter = FrontMatter::parse("Mix @flour{200%g}.").unwrap();
+        assert!(front_matter.get("source").is_none());
+        front_matter.set("source", "https://example.com");
+        assert_eq!(
+            front_matter.render().unwrap(),
+            "---\nsource: https://example.com\n---\n\nMix @flour{200%g}."
+        );
+    }
+
+    #[test]
+    fn test_unset() {
+        let recipe = "---\r\nsource: Grandma\r\n---\r\nMix @flour{200%g}.\r\n";
+        let mut front_matter = FrontMatter::parse(recipe).unwrap();
+        assert_eq!(front_matter.get("source"), Some(&yaml_value("Grandma")));
+        assert!(front_matter.unset("source"));
+        assert!(!front_matter.unset("source"));
+        assert_eq!(front_matter.render().unwrap(), "Mix @flour{200%g}.\r\n");
+    }
+
+    #[test]
+    fn test_set_tags_keeps_style() {
+        let mut front_matter = FrontMatter::parse("---\ntags: vegan, quick\n---\n").unwrap();
+        assert_eq!(front_matter.tags(), ["vegan", "quick"]);
+        assert!(front_matter.set_tags(vec!["vegan".to_string()]));
+        assert_eq!(front_matter.render().unwrap(), "---\ntags: vegan\n---\n");
+        assert!(front_matter.set_tags(Vec::new()));
+        assert!(front_matter.get("tags").is_none());
+    }
+
+    #[test]
+    fn test_legacy_metadata() {
+        assert!(FrontMatter::parse(">> servings: 2\n\nMix @flour{200%g}.").is_err());
+    }
+
+    #[test]
+    fn test_display_value() {
+        assert_eq!(display_value(&yaml_value("4")), "4");
+        assert_eq!(
+            display_value(&serde_yaml::from_str("[vegan, quick]").unwrap()),
+            "vegan, quick"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+//! Running git, for commands that work with collections kept in git
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use std::process::{Command, Output};
+
+/// Run git in `dir`
+pub fn git(dir: &Utf8Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .with_context(|| format!("Failed to run git {}", args.join(" ")))
+}
+
+/// Run git and return its stdout, failing with its stderr
+pub fn git_ok(dir: &Utf8Path, args: &[&str]) -> Result<String> {
+    let output = git(dir, args)?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed:\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,228 @@
+//! Preparing photos before they're stored with recipes
+//!
+//! Photos are decoded and written again: that turns them upright following
+//! their EXIF orientation and leaves their metadata behind, like the GPS
+//! position phones record. They're scaled down and stored as JPEG, or WebP
+//! with `[images] format = "webp"`.
+//!
+//! HEIC photos, the default of iPhones, are read with `heif-convert` from
+//! libheif, which has to be installed.
+
+use std::{io::Cursor, process::Command};
+
+use anyhow::{Context as _, Result};
+use clap::ValueEnum;
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageDecoder};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+
+use crate::config::ImagesConfig;
+
+/// Extensions of images found next to recipes
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Longest side of stored images, in pixels
+pub const DEFAULT_MAX_SIZE: u32 = 1600;
+
+/// The longest side WebP can store
+const MAX_SIZE: u32 = 16383;
+
+const DEFAULT_QUALITY: u8 = 85;
+
+/// HEIF brands of the `ftyp` box of HEIC photos
+const HEIC_BRANDS: &[&[u8]] = &[
+    b"heic", b"heix", b"heim", b"heis", b"hevc", b"mif1", b"msf1",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+}
+
+/// How photos are stored
+#[derive(Debug, Clone, Copy)]
+pub struct ImageOptions {
+    pub format: ImageFormat,
+    /// Larger photos are scaled down to this on their longest side, at most
+    /// 16383
+    pub max_size: u32,
+    /// 1 to 100
+    pub quality: u8,
+}
+
+impl ImageOptions {
+    pub fn from_config(config: &ImagesConfig) -> Self {
+        Self {
+            format: config.format.unwrap_or_default(),
+            max_size: config
+                .max_size
+                .unwrap_or(DEFAULT_MAX_SIZE)
+                .clamp(1, MAX_SIZE),
+            quality: config.quality.unwrap_or(DEFAULT_QUALITY).clamp(1, 100),
+        }
+    }
+}
+
+/// The photo upright, without its metadata, scaled down and encoded as
+/// `options` say
+pub fn prepare(bytes: &[u8], options: &ImageOptions) -> Result<Vec<u8>> {
+    let image = decode(bytes)?;
+    let image = if image.width() > options.max_size || image.height() > options.max_size {
+        image.resize(options.max_size, options.max_size, FilterType::Lanczos3)
+    } else {
+        image
+    };
+    encode(&image, options)
+}
+
+/// The image, turned the way its EXIF orientation says
+fn decode(bytes: &[u8]) -> Result<DynamicImage> {
+    if is_heic(bytes) {
+        return decode_heic(bytes);
+    }
+    let mut decoder = image::ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_decoder()
+        .context("Not an image, or in a format other than JPEG, PNG, WebP, GIF or HEIC")?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn encode(image: &DynamicImage, options: &ImageOptions) -> Result<Vec<u8>> {
+    match options.format {
+        ImageFormat::Jpeg => {
+            // JPEG has no transparency
+            let image = DynamicImage::ImageRgb8(image.to_rgb8());
+            let mut jpeg = Vec::new();
+            image.write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, options.quality))?;
+            Ok(jpeg)
+        }
+        ImageFormat::Webp => {
+            let quality = options.quality as f32;
+            let encoded = if image.color().has_alpha() {
+                let rgba = image.to_rgba8();
+                webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                    .encode_simple(false, quality)
+            } else {
+                let rgb = image.to_rgb8();
+                webp::Encoder::from_rgb(&rgb, rgb.width(), rgb.height())
+                    .encode_simple(false, quality)
+            };
+            let encoded =
+                encoded.map_err(|e| anyhow::anyhow!("Failed to encode the image: {e:?}"))?;
+            Ok(encoded.to_vec())
+        }
+    }
+}
+
+fn is_heic(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && HEIC_BRANDS.contains(&&bytes[8..12])
+}
+
+/// Convert with `heif-convert`, which applies the orientation itself
+fn decode_heic(bytes: &[u8]) -> Result<DynamicImage> {
+    let name: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect();
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("cook-{name}.heic"));
+    let output = dir.join(format!("cook-{name}.png"));
+    std::fs::write(&input, bytes).context("Failed to write a temporary file")?;
+    let status = Command::new("heif-convert")
+        .arg(&input)
+        .arg(&output)
+        .status();
+    let image = match status {
+        Ok(status) if status.success() => image::open(&output).map_err(anyhow::Error::from),
+        Ok(status) => Err(anyhow::anyhow!("heif-convert exited with {status}")),
+        Err(e) => Err(e).context("HEIC photos need heif-convert from libheif, is it installed?"),
+    };
+    let _ = std::fs::remove_file(&input);
+    let _ = std::fs::remove_file(&output);
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat as Format, RgbImage};
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut Cursor::new(&mut bytes), Format::Jpeg)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_prepare_scales_down() {
+        let options = ImageOptions::from_config(&ImagesConfig {
+            max_size: Some(100),
+            ..Default::default()
+        });
+        let prepared = prepare(&jpeg(400, 200), &options).unwrap();
+        let image = image::load_from_memory(&prepared).unwrap();
+        assert_eq!((image.width(), image.height()), (100, 50));
+    }
+
+    #[test]
+    fn test_prepare_orientation() {
+        // An APP1 segment with EXIF saying the photo is turned by 90°
+        let mut exif = vec![0xff, 0xe1, 0x00, 0x22];
+        exif.extend_from_slice(b"Exif\0\0MM\0\x2a\0\0\0\x08");
+        exif.extend_from_slice(b"\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0");
+        let mut photo = jpeg(40, 20);
+        photo.splice(2..2, exif);
+
+        let prepared = prepare(&photo, &ImageOptions::from_config(&Default::default())).unwrap();
+        let image = image::load_from_memory(&prepared).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 40));
+        assert!(!prepared.windows(4).any(|window| window == b"Exif"));
+    }
+
+    #[test]
+    fn test_max_size() {
+        let options = |max_size| {
+            ImageOptions::from_config(&ImagesConfig {
+                max_size: Some(max_size),
+                ..Default::default()
+            })
+            .max_size
+        };
+        assert_eq!(options(0), 1);
+        assert_eq!(options(100_000), MAX_SIZE);
+    }
+
+    #[test]
+    fn test_prepare_webp() {
+        let options = ImageOptions::from_config(&ImagesConfig {
+            format: Some(ImageFormat::Webp),
+            ..Default::default()
+        });
+        let prepared = prepare(&jpeg(20, 10), &options).unwrap();
+        assert_eq!(image::guess_format(&prepared).unwrap(), Format::WebP);
+    }
+
+    #[test]
+    fn test_is_heic() {
+        assert!(is_heic(b"\0\0\0\x18ftypheic\0\0\0\0"));
+        assert!(!is_heic(&jpeg(1, 1)));
+    }
+}
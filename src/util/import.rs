@@ -0,0 +1,650 @@
+//! Cooklang from the recipes of other apps
+//!
+//! Other apps keep the ingredients in a list apart from the steps. Each
+//! ingredient is marked up where a step first mentions it, the ones no step
+//! mentions are listed before the steps.
+
+use anyhow::{Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+
+use super::{front_matter::yaml_value, images::ImageOptions, names::sanitize_stem};
+
+/// A recipe read from another app, before it's written as Cooklang
+#[derive(Debug, Default)]
+pub struct ImportedRecipe {
+    pub name: String,
+    /// Front matter, in order
+    pub metadata: Vec<(String, String)>,
+    pub ingredients: Vec<IngredientLine>,
+    pub steps: Vec<ImportedStep>,
+    /// Notes about the recipe, shown after the steps
+    pub notes: Vec<String>,
+    /// The first one becomes the recipe's image
+    pub images: Vec<ImportedImage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IngredientLine {
+    Ingredient(ImportedIngredient),
+    /// A line that couldn't be split into quantity and name, kept as written
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportedIngredient {
+    pub name: String,
+    /// Like "2", "1/2" or "2-3"
+    pub quantity: Option<String>,
+    pub unit: Option<String>,
+    /// Like "finely chopped"
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportedStep {
+    Section(String),
+    Step(String),
+}
+
+#[derive(Debug)]
+pub enum ImportedImage {
+    Data(Vec<u8>),
+    Url(String),
+}
+
+impl ImportedIngredient {
+    /// Like `@flour{2%cup}(sifted)`
+    pub fn markup(&self) -> String {
+        let name = clean(&self.name, &['@', '#', '~', '{', '}', '(', ')']);
+        let quantity = self
+            .quantity
+            .as_deref()
+            .map(|q| clean(q, &['{', '}', '%']))
+            .filter(|q| !q.is_empty());
+        let unit = self
+            .unit
+            .as_deref()
+            .map(|u| clean(u, &['{', '}', '%']))
+            .filter(|u| !u.is_empty());
+        let amount = match (quantity, unit) {
+            (Some(quantity), Some(unit)) => format!("{quantity}%{unit}"),
+            (Some(quantity), None) => quantity,
+            // A unit alone isn't valid Cooklang, it goes into the note
+            (None, _) => String::new(),
+        };
+        let note = [
+            self.quantity.is_none().then(|| self.unit.clone()).flatten(),
+            self.note.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|n| clean(&n, &['(', ')']))
+        .filter(|n| !n.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+        let mut markup = format!("@{name}{{{amount}}}");
+        if !note.is_empty() {
+            markup.push_str(&format!("({note})"));
+        }
+        markup
+    }
+}
+
+fn clean(text: &str, remove: &[char]) -> String {
+    let text: String = text.chars().filter(|c| !remove.contains(c)).collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Units recognized after the quantity of an ingredient line, with the
+/// unit they're written as
+const UNITS: &[(&[&str], &str)] = &[
+    (&["cup", "cups", "c"], "cup"),
+    (
+        &[
+            "tablespoon",
+            "tablespoons",
+            "tbsp",
+            "tbsps",
+            "tbs",
+            "tbl",
+            "T",
+        ],
+        "tbsp",
+    ),
+    (&["teaspoon", "teaspoons", "tsp", "tsps", "t"], "tsp"),
+    (&["gram", "grams", "g", "gr"], "g"),
+    (&["kilogram", "kilograms", "kg", "kgs"], "kg"),
+    (
+        &[
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+            "ml",
+        ],
+        "ml",
+    ),
+    (&["liter", "liters", "litre", "litres", "l"], "l"),
+    (&["ounce", "ounces", "oz"], "oz"),
+    (&["pound", "pounds", "lb", "lbs"], "lb"),
+    (&["pinch", "pinches"], "pinch"),
+    (&["clove", "cloves"], "cloves"),
+    (&["can", "cans", "tin", "tins"], "can"),
+    (&["slice", "slices"], "slices"),
+    (&["bunch", "bunches"], "bunch"),
+    (&["handful", "handfuls"], "handful"),
+    (
+        &["package", "packages", "packet", "packets", "pkg"],
+        "package",
+    ),
+    (&["stick", "sticks"], "sticks"),
+    (&["piece", "pieces"], "pieces"),
+];
+
+/// Split an ingredient line like "2 1/2 cups flour, sifted"
+///
+/// Lines without a quantity at the start, like "salt and pepper to taste",
+/// are kept as text.
+pub fn parse_ingredient_line(line: &str) -> IngredientLine {
+    let text = || IngredientLine::Text(line.trim().to_string());
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    // Quantities like "2", "1/2", "1 1/2", "½", "1½", "2-3" or "2 - 3"
+    let mut value = None;
+    let mut used = 0;
+    while let Some(word) = words.get(used) {
+        match (value, parse_number(word)) {
+            (None, Some(n)) => value = Some(Quantity::Number(n)),
+            // "1 1/2", the second number is the fraction
+            (Some(Quantity::Number(whole)), Some(n)) if n < 1.0 && whole.fract() == 0.0 => {
+                value = Some(Quantity::Number(whole + n))
+            }
+            (None, None) => match parse_range(word) {
+                Some(range) => value = Some(range),
+                None => break,
+            },
+            (Some(Quantity::Number(start)), None) if *word == "-" || *word == "to" => {
+                match words.get(used + 1).and_then(|w| parse_number(w)) {
+                    Some(end) => {
+                        value = Some(Quantity::Range(start, end));
+                        used += 1;
+                    }
+                    None => break,
+                }
+            }
+            _ => break,
+        }
+        used += 1;
+    }
+    let Some(value) = value else {
+        return text();
+    };
+
+    let mut rest = &words[used..];
+    let mut unit = None;
+    if let Some(word) = rest.first() {
+        let bare = word.trim_end_matches('.');
+        if let Some((_, name)) = UNITS.iter().find(|(names, _)| {
+            names
+                .iter()
+                .any(|n| *n == bare || (n.len() > 1 && n.eq_ignore_ascii_case(bare)))
+        }) {
+            unit = Some(name.to_string());
+            rest = &rest[1..];
+        }
+    }
+    if rest.first().is_some_and(|w| w.eq_ignore_ascii_case("of")) {
+        rest = &rest[1..];
+    }
+
+    let rest = rest.join(" ");
+    let (name, note) = match rest.find([',', '(']) {
+        Some(i) => (
+            rest[..i].trim(),
+            Some(
+                rest[i..]
+                    .trim_matches(|c: char| c == ',' || c == '(' || c == ')' || c.is_whitespace())
+                    .to_string(),
+            )
+            .filter(|note| !note.is_empty()),
+        ),
+        None => (rest.trim(), None),
+    };
+    if name.is_empty() {
+        return text();
+    }
+
+    IngredientLine::Ingredient(ImportedIngredient {
+        name: name.to_string(),
+        quantity: Some(value.to_string()),
+        unit,
+        note,
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Quantity {
+    Number(f64),
+    Range(f64, f64),
+}
+
+impl std::fmt::Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Quantity::Number(n) => f.write_str(&format_amount(*n)),
+            Quantity::Range(start, end) => {
+                write!(f, "{}-{}", format_amount(*start), format_amount(*end))
+            }
+        }
+    }
+}
+
+fn parse_number(word: &str) -> Option<f64> {
+    const FRACTIONS: &[(char, f64)] = &[
+        ('½', 0.5),
+        ('⅓', 1.0 / 3.0),
+        ('⅔', 2.0 / 3.0),
+        ('¼', 0.25),
+        ('¾', 0.75),
+        ('⅛', 0.125),
+    ];
+    let word = word.trim_end_matches(',');
+    if let Some(&(c, fraction)) = FRACTIONS.iter().find(|(c, _)| word.ends_with(*c)) {
+        let whole = &word[..word.len() - c.len_utf8()];
+        return if whole.is_empty() {
+            Some(fraction)
+        } else {
+            whole.parse::<u32>().ok().map(|w| f64::from(w) + fraction)
+        };
+    }
+    if let Some((numerator, denominator)) = word.split_once('/') {
+        let numerator: f64 = numerator.parse().ok()?;
+        let denominator: f64 = denominator.parse().ok()?;
+        return (denominator != 0.0).then(|| numerator / denominator);
+    }
+    word.replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n >= 0.0)
+}
+
+fn parse_range(word: &str) -> Option<Quantity> {
+    let (start, end) = word.split_once(['-', '–'])?;
+    Some(Quantity::Range(parse_number(start)?, parse_number(end)?))
+}
+
+/// An amount for Cooklang, common fractions written as such
+pub fn format_amount(value: f64) -> String {
+    const FRACTIONS: &[(f64, &str)] = &[
+        (0.5, "1/2"),
+        (1.0 / 3.0, "1/3"),
+        (2.0 / 3.0, "2/3"),
+        (0.25, "1/4"),
+        (0.75, "3/4"),
+        (0.125, "1/8"),
+    ];
+    if value.fract() == 0.0 {
+        return format!("{value}");
+    }
+    if value < 1.0 {
+        if let Some((_, fraction)) = FRACTIONS.iter().find(|(f, _)| (value - f).abs() < 0.001) {
+            return fraction.to_string();
+        }
+    }
+    let rounded = (value * 100.0).round() / 100.0;
+    format!("{rounded}")
+}
+
+/// The recipe as Cooklang
+pub fn to_cooklang(recipe: &ImportedRecipe) -> String {
+    let mut steps: Vec<Option<Vec<Segment>>> = recipe
+        .steps
+        .iter()
+        .map(|step| match step {
+            ImportedStep::Step(text) => Some(vec![Segment::Text(text.trim().to_string())]),
+            ImportedStep::Section(_) => None,
+        })
+        .collect();
+
+    let mut unmentioned = Vec::new();
+    for line in &recipe.ingredients {
+        match line {
+            IngredientLine::Ingredient(ingredient) => {
+                let placed = steps
+                    .iter_mut()
+                    .flatten()
+                    .any(|segments| place(segments, ingredient));
+                if !placed {
+                    unmentioned.push(format!("- {}", ingredient.markup()));
+                }
+            }
+            IngredientLine::Text(text) => unmentioned.push(format!("- {}", text.trim())),
+        }
+    }
+
+    let mut out = front_matter(recipe);
+    if !unmentioned.is_empty() {
+        out.push_str(&unmentioned.join("\n"));
+        out.push_str("\n\n");
+    }
+    for (step, segments) in recipe.steps.iter().zip(&steps) {
+        match (step, segments) {
+            (ImportedStep::Section(name), _) => {
+                out.push_str(&format!("== {} ==\n\n", name.trim()));
+            }
+            (_, Some(segments)) => {
+                let text: String = segments.iter().map(Segment::as_str).collect();
+                // A step is one paragraph, blank lines would split it
+                let text = text
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if !text.is_empty() {
+                    out.push_str(&text);
+                    out.push_str("\n\n");
+                }
+            }
+            _ => {}
+        }
+    }
+    for note in &recipe.notes {
+        for line in note.lines().filter(|line| !line.trim().is_empty()) {
+            out.push_str(&format!("> {}\n", line.trim()));
+        }
+        out.push('\n');
+    }
+    format!("{}\n", out.trim_end())
+}
+
+fn front_matter(recipe: &ImportedRecipe) -> String {
+    let mapping: serde_yaml::Mapping = recipe
+        .metadata
+        .iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(key, value)| {
+            let value = if key == "tags" {
+                serde_yaml::Value::Sequence(
+                    value
+                        .split(',')
+                        .map(|tag| serde_yaml::Value::String(tag.trim().to_string()))
+                        .collect(),
+                )
+            } else {
+                yaml_value(value.trim())
+            };
+            (serde_yaml::Value::String(key.clone()), value)
+        })
+        .collect();
+    if mapping.is_empty() {
+        return String::new();
+    }
+    let yaml = serde_yaml::to_string(&mapping).unwrap_or_default();
+    format!("---\n{yaml}---\n\n")
+}
+
+enum Segment {
+    Text(String),
+    Markup(String),
+}
+
+impl Segment {
+    fn as_str(&self) -> &str {
+        match self {
+            Segment::Text(text) | Segment::Markup(text) => text,
+        }
+    }
+}
+
+/// Mark up the first mention of the ingredient in the step's text
+fn place(segments: &mut Vec<Segment>, ingredient: &ImportedIngredient) -> bool {
+    for i in 0..segments.len() {
+        let Segment::Text(text) = &segments[i] else {
+            continue;
+        };
+        let Some(start) = find_word(text, &ingredient.name) else {
+            continue;
+        };
+        let end = start + ingredient.name.len();
+        let before = text[..start].to_string();
+        let after = text[end..].to_string();
+        segments.splice(
+            i..=i,
+            [
+                Segment::Text(before),
+                Segment::Markup(ingredient.markup()),
+                Segment::Text(after),
+            ],
+        );
+        return true;
+    }
+    false
+}
+
+/// Start of `word` in `text`, ignoring case, as a whole word
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    let word = word.trim();
+    if word.is_empty() {
+        return None;
+    }
+    text.char_indices().map(|(i, _)| i).find(|&start| {
+        let end = start + word.len();
+        text.is_char_boundary(end)
+            && text[start..end].eq_ignore_ascii_case(word)
+            && !text[..start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '@')
+            && !text[end..]
+                .chars()
+                .next()
+                .is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// The lines of an ingredient list, one per line or separated by `;`,
+/// without bullets
+pub fn ingredient_lines(text: &str) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let lines = match lines.as_slice() {
+        [line] => line.split(';').collect(),
+        _ => lines,
+    };
+    lines
+        .into_iter()
+        .map(|line| strip_bullet(line).to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// The steps of a method, one per line, without bullets and numbers like
+/// "1." or "Step 2:"
+pub fn step_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| {
+            let line = strip_bullet(line);
+            let rest = line
+                .strip_prefix("Step ")
+                .or_else(|| line.strip_prefix("step "))
+                .unwrap_or(line);
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            match rest[digits..].strip_prefix(['.', ')', ':']) {
+                Some(step) if digits > 0 => step.trim(),
+                _ => line,
+            }
+            .to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn strip_bullet(line: &str) -> &str {
+    let line = line.trim();
+    line.strip_prefix(['-', '*', '•'])
+        .map(str::trim_start)
+        .unwrap_or(line)
+}
+
+/// Write the recipe and its image into `dir`
+///
+/// Returns the recipe's path, or `None` when it exists and `overwrite`
+/// isn't set. The image is prepared like photos added to recipes; images
+/// that can't be downloaded or read are left out with a warning.
+pub fn save(
+    recipe: &ImportedRecipe,
+    dir: &Utf8Path,
+    overwrite: bool,
+    images: &ImageOptions,
+) -> Result<Option<Utf8PathBuf>> {
+    let stem = sanitize_stem(&recipe.name);
+    let path = dir.join(format!("{stem}.cook"));
+    if path.exists() && !overwrite {
+        return Ok(None);
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir}"))?;
+    std::fs::write(&path, to_cooklang(recipe))
+        .with_context(|| format!("Failed to write {path}"))?;
+
+    if let Some(image) = recipe.images.first() {
+        let bytes = match image {
+            ImportedImage::Data(bytes) => Some(bytes.clone()),
+            ImportedImage::Url(url) => match super::download(url) {
+                Ok(bytes) => Some(bytes),
+                Err(e) => {
+                    tracing::warn!("Leaving out the image of {}: {e:#}", recipe.name);
+                    None
+                }
+            },
+        };
+        let prepared = bytes.and_then(|bytes| match super::images::prepare(&bytes, images) {
+            Ok(prepared) => Some(prepared),
+            Err(e) => {
+                tracing::warn!("Leaving out the image of {}: {e:#}", recipe.name);
+                None
+            }
+        });
+        if let Some(prepared) = prepared {
+            let image_path = dir.join(format!("{stem}.{}", images.format.extension()));
+            std::fs::write(&image_path, prepared)
+                .with_context(|| format!("Failed to write {image_path}"))?;
+        }
+    }
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PARSER;
+
+    fn ingredient(name: &str, quantity: Option<&str>, unit: Option<&str>) -> ImportedIngredient {
+        ImportedIngredient {
+            name: name.to_string(),
+            quantity: quantity.map(str::to_string),
+            unit: unit.map(str::to_string),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_ingredient_line() {
+        let parsed = |line| match parse_ingredient_line(line) {
+            IngredientLine::Ingredient(i) => i,
+            IngredientLine::Text(text) => panic!("{text} wasn't parsed"),
+        };
+        assert_eq!(
+            parsed("2 1/2 cups flour, sifted"),
+            ImportedIngredient {
+                note: Some("sifted".to_string()),
+                ..ingredient("flour", Some("2.5"), Some("cup"))
+            }
+        );
+        assert_eq!(
+            parsed("½ tsp salt"),
+            ingredient("salt", Some("1/2"), Some("tsp"))
+        );
+        assert_eq!(
+            parsed("2-3 cloves garlic"),
+            ingredient("garlic", Some("2-3"), Some("cloves"))
+        );
+        assert_eq!(parsed("3 eggs"), ingredient("eggs", Some("3"), None));
+        assert_eq!(
+            parsed("1 cup of milk (warm)"),
+            ImportedIngredient {
+                note: Some("warm".to_string()),
+                ..ingredient("milk", Some("1"), Some("cup"))
+            }
+        );
+        assert_eq!(
+            parse_ingredient_line("Salt and pepper to taste"),
+            IngredientLine::Text("Salt and pepper to taste".to_string())
+        );
+    }
+
+    #[test]
+    fn test_markup() {
+        assert_eq!(
+            ingredient("olive oil", Some("2"), Some("tbsp")).markup(),
+            "@olive oil{2%tbsp}"
+        );
+        assert_eq!(ingredient("salt", None, None).markup(), "@salt{}");
+        assert_eq!(
+            ingredient("basil", None, Some("bunch")).markup(),
+            "@basil{}(bunch)"
+        );
+    }
+
+    #[test]
+    fn test_list_lines() {
+        assert_eq!(
+            ingredient_lines("- 2 eggs\n\n• 1 cup milk\n"),
+            ["2 eggs", "1 cup milk"]
+        );
+        assert_eq!(
+            ingredient_lines("2 eggs; 1 cup milk"),
+            ["2 eggs", "1 cup milk"]
+        );
+        assert_eq!(
+            step_lines("1. Mix.\n2) Bake 20 minutes.\nStep 3: Eat.\n350 degrees is enough."),
+            ["Mix.", "Bake 20 minutes.", "Eat.", "350 degrees is enough."]
+        );
+    }
+
+    #[test]
+    fn test_to_cooklang() {
+        let recipe = ImportedRecipe {
+            name: "Pancakes".to_string(),
+            metadata: vec![
+                ("servings".to_string(), "4".to_string()),
+                ("tags".to_string(), "breakfast, sweet".to_string()),
+            ],
+            ingredients: vec![
+                IngredientLine::Ingredient(ingredient("flour", Some("200"), Some("g"))),
+                IngredientLine::Ingredient(ingredient("eggs", Some("2"), None)),
+                IngredientLine::Ingredient(ingredient("butter", None, None)),
+                IngredientLine::Text("Maple syrup to serve".to_string()),
+            ],
+            steps: vec![
+                ImportedStep::Section("Batter".to_string()),
+                ImportedStep::Step("Whisk the Flour and eggs.".to_string()),
+                ImportedStep::Step("Fry in a pan.".to_string()),
+            ],
+            notes: vec!["Keeps for a day.".to_string()],
+            images: Vec::new(),
+        };
+        let cook = to_cooklang(&recipe);
+        assert_eq!(
+            cook,
+            "---\nservings: 4\ntags:\n- breakfast\n- sweet\n---\n\n\
+             - @butter{}\n- Maple syrup to serve\n\n\
+             == Batter ==\n\n\
+             Whisk the @flour{200%g} and @eggs{2}.\n\n\
+             Fry in a pan.\n\n\
+             > Keeps for a day.\n"
+        );
+        let parsed = PARSER.parse(&cook).into_result().unwrap().0;
+        assert_eq!(parsed.ingredients.len(), 3);
+    }
+}
@@ -0,0 +1,94 @@
+//! Shared copies of strings that repeat across recipes
+//!
+//! The same ingredient, cookware and tag names show up in most recipes of a
+//! collection. Interned, every name is allocated once and the copies are
+//! `Arc` clones of it. Names no longer used anywhere, like those of a
+//! metadata cache that was rebuilt, are dropped as new ones come in.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeSet, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Strings kept before the first look for unused ones
+const MIN_PRUNE: usize = 1024;
+
+static STRINGS: Lazy<Mutex<Strings>> = Lazy::new(|| {
+    Mutex::new(Strings {
+        set: HashSet::new(),
+        prune_at: MIN_PRUNE,
+    })
+});
+
+struct Strings {
+    set: HashSet<Arc<str>>,
+    /// Size of the set at which unused strings are dropped, twice what was
+    /// left the last time so it's rarely done
+    prune_at: usize,
+}
+
+impl Strings {
+    /// Drop the strings only the set holds
+    fn prune(&mut self) {
+        self.set.retain(|s| Arc::strong_count(s) > 1);
+        self.prune_at = (self.set.len() * 2).max(MIN_PRUNE);
+    }
+}
+
+/// The shared copy of `s`
+pub fn intern(s: &str) -> Arc<str> {
+    let mut strings = STRINGS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(shared) = strings.set.get(s) {
+        return Arc::clone(shared);
+    }
+    if strings.set.len() >= strings.prune_at {
+        strings.prune();
+    }
+    let shared: Arc<str> = Arc::from(s);
+    strings.set.insert(Arc::clone(&shared));
+    shared
+}
+
+/// Deserialize strings as their shared copies, for `#[serde(deserialize_with)]`
+pub fn deserialize_vec<'de, D>(deserializer: D) -> Result<Vec<Arc<str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let strings = Vec::<String>::deserialize(deserializer)?;
+    Ok(strings.iter().map(|s| intern(s)).collect())
+}
+
+/// Like [`deserialize_vec`], for sets
+pub fn deserialize_set<'de, D>(deserializer: D) -> Result<BTreeSet<Arc<str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let strings = Vec::<String>::deserialize(deserializer)?;
+    Ok(strings.iter().map(|s| intern(s)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_shares_strings() {
+        let a = intern("flour");
+        let b = intern(&String::from("flour"));
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &intern("sugar")));
+    }
+
+    #[test]
+    fn test_unused_strings_are_dropped() {
+        let kept = intern("kept by the test");
+        drop(intern("dropped by the test"));
+
+        let mut strings = STRINGS.lock().unwrap();
+        strings.prune();
+        assert!(strings.set.contains("kept by the test"));
+        assert!(!strings.set.contains("dropped by the test"));
+        drop(strings);
+        drop(kept);
+    }
+}
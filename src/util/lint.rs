@@ -0,0 +1,394 @@
+//! Rules a collection sets for its recipes, beyond what the parser checks
+//!
+//! Turned on in `[lint]` of cook.toml, so a shared collection can keep its
+//! recipes consistent, like every recipe having servings and a tag. `cook
+//! lint` reports the recipes breaking them and fixes what it can.
+
+use camino::Utf8Path;
+use serde::Serialize;
+use serde_yaml::Value;
+
+use super::{diagnostics::Diagnostic, front_matter::FrontMatter, PARSER};
+
+/// The rules that are on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintRules {
+    pub require_servings: bool,
+    pub require_tags: bool,
+    pub require_image: bool,
+    pub max_step_sentences: Option<usize>,
+}
+
+impl LintRules {
+    /// Whether no rule is on
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A rule, named like its setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// The recipe has to parse before the other rules are checked
+    Parse,
+    RequireServings,
+    RequireTags,
+    RequireImage,
+    MaxStepSentences,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Parse => "parse",
+            Rule::RequireServings => "require_servings",
+            Rule::RequireTags => "require_tags",
+            Rule::RequireImage => "require_image",
+            Rule::MaxStepSentences => "max_step_sentences",
+        }
+    }
+}
+
+/// A rule a recipe breaks
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub rule: Rule,
+    pub message: String,
+    /// 1-based line the problem starts on, if it's about one place
+    pub line: Option<usize>,
+    /// Whether `--fix` can fix it
+    pub fixable: bool,
+}
+
+/// The rules a recipe breaks
+///
+/// `relative` is the recipe's path in the collection and `has_image`
+/// whether it has an image, in its metadata or next to it.
+pub fn lint(
+    content: &str,
+    relative: &Utf8Path,
+    has_image: bool,
+    rules: &LintRules,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let parsed = PARSER.parse(content);
+    let error = Diagnostic::from_report(parsed.report(), content)
+        .into_iter()
+        .find(|diag| diag.severity == "error");
+    let Some(recipe) = parsed.into_result().ok().map(|(r, _)| r) else {
+        findings.push(Finding {
+            rule: Rule::Parse,
+            message: error.as_ref().map_or_else(
+                || "The recipe doesn't parse, see `cook doctor validate`".to_string(),
+                |error| error.message.clone(),
+            ),
+            line: error.and_then(|error| error.labels.first().map(|label| label.line)),
+            fixable: false,
+        });
+        return findings;
+    };
+
+    if rules.require_servings && recipe.metadata.get("servings").is_none() {
+        findings.push(Finding {
+            rule: Rule::RequireServings,
+            message: "No servings in the metadata".to_string(),
+            line: None,
+            fixable: false,
+        });
+    }
+    if rules.require_tags && !has_tags(recipe.metadata.get("tags")) {
+        findings.push(Finding {
+            rule: Rule::RequireTags,
+            message: "No tags".to_string(),
+            line: None,
+            fixable: directory_tag(relative).is_some(),
+        });
+    }
+    if rules.require_image && !has_image {
+        findings.push(Finding {
+            rule: Rule::RequireImage,
+            message: "No image".to_string(),
+            line: None,
+            fixable: false,
+        });
+    }
+    if let Some(max) = rules.max_step_sentences {
+        for step in steps(content) {
+            let count = sentences(&step.text).len();
+            if count > max {
+                findings.push(Finding {
+                    rule: Rule::MaxStepSentences,
+                    message: format!("The step has {count} sentences, at most {max} are allowed"),
+                    line: Some(step.start + 1),
+                    fixable: !step.commented,
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// The recipe with what can be fixed fixed, `None` if nothing changed
+///
+/// Long steps are split into steps of at most the allowed sentences, and a
+/// recipe without tags is tagged with the name of its directory.
+pub fn fix(
+    content: &str,
+    relative: &Utf8Path,
+    rules: &LintRules,
+) -> anyhow::Result<Option<String>> {
+    let mut fixed = content.to_string();
+    if let Some(max) = rules.max_step_sentences {
+        fixed = split_steps(&fixed, max);
+    }
+    if rules.require_tags {
+        if let Some(tag) = directory_tag(relative) {
+            let mut front_matter = FrontMatter::parse(&fixed)?;
+            if front_matter.tags().is_empty() && front_matter.set_tags(vec![tag]) {
+                fixed = front_matter.render()?;
+            }
+        }
+    }
+    Ok((fixed != content).then_some(fixed))
+}
+
+fn has_tags(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::String(tags)) => tags.split(',').any(|tag| !tag.trim().is_empty()),
+        Some(Value::Sequence(tags)) => !tags.is_empty(),
+        _ => false,
+    }
+}
+
+/// The tag a recipe in a directory gets, like "breakfast" for
+/// Breakfast/Pancakes.cook
+fn directory_tag(relative: &Utf8Path) -> Option<String> {
+    let tag = relative.parent()?.file_name()?.trim().to_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// A paragraph of the recipe that is a step
+struct Step {
+    /// 0-based lines, the end excluded
+    start: usize,
+    end: usize,
+    /// Without its comments
+    text: String,
+    /// Whether it has comments, which splitting it could break
+    commented: bool,
+}
+
+/// The steps of a recipe, from its text
+///
+/// Paragraphs separated by blank lines, leaving out the front matter,
+/// sections, notes, comments and metadata lines.
+fn steps(content: &str) -> Vec<Step> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut first = 0;
+    if lines.first().is_some_and(|line| line.trim_end() == "---") {
+        if let Some(close) = lines.iter().skip(1).position(|l| l.trim_end() == "---") {
+            first = close + 2;
+        }
+    }
+
+    let mut steps = Vec::new();
+    let mut i = first;
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < lines.len() && !lines[i].trim().is_empty() {
+            i += 1;
+        }
+        let head = lines[start].trim_start();
+        if ["=", ">", "--", "[-"].iter().any(|p| head.starts_with(p)) {
+            continue;
+        }
+        let paragraph = &lines[start..i];
+        let commented = paragraph
+            .iter()
+            .any(|line| line.contains("--") || line.contains("[-"));
+        let text = paragraph
+            .iter()
+            .map(|line| without_comments(line).trim().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        steps.push(Step {
+            start,
+            end: i,
+            text,
+            commented,
+        });
+    }
+    steps
+}
+
+/// A line without its `-- comment` and `[- block comments -]`
+fn without_comments(line: &str) -> String {
+    let mut rest = line.split("--").next().unwrap_or_default();
+    let mut text = String::new();
+    while let Some(open) = rest.find("[-") {
+        text.push_str(&rest[..open]);
+        match rest[open..].find("-]") {
+            Some(close) => rest = &rest[open + close + 2..],
+            None => return text,
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// The sentences of a step
+///
+/// A sentence ends with '.', '!' or '?' before a space or the end, outside
+/// of the braces of quantities like `{1.5%cups}`.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '.' | '!' | '?' if depth == 0 => {
+                if chars.peek().is_none_or(|(_, next)| next.is_whitespace()) {
+                    let sentence = text[start..=i].trim();
+                    if !sentence.is_empty() {
+                        sentences.push(sentence);
+                    }
+                    start = i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+/// Split steps longer than `max` sentences into several steps
+///
+/// Steps with comments are left as they are: their lines are joined when
+/// they're split, and a `--` comment would take in what follows it.
+fn split_steps(content: &str, max: usize) -> String {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut next = 0;
+    for step in steps(content) {
+        let sentences = sentences(&step.text);
+        if sentences.len() <= max || step.commented {
+            continue;
+        }
+        out.extend(lines[next..step.start].iter().map(|line| line.to_string()));
+        let chunks: Vec<String> = sentences.chunks(max).map(|chunk| chunk.join(" ")).collect();
+        out.push(chunks.join(&format!("{newline}{newline}")));
+        next = step.end;
+    }
+    if next == 0 {
+        return content.to_string();
+    }
+    out.extend(lines[next..].iter().map(|line| line.to_string()));
+    let mut fixed = out.join(newline);
+    if content.ends_with('\n') {
+        fixed.push_str(newline);
+    }
+    fixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPE: &str = "---\ntitle: Pancakes\n---\n\n\
+        Mix @flour{1.5%cups} and @milk{1%cup}. Add @eggs{2}! Whisk well.\n\
+        Rest for ~{10%minutes}.\n\n\
+        -- Fry them\n\n\
+        Fry in a #pan.\n";
+
+    #[test]
+    fn test_sentences() {
+        assert_eq!(
+            sentences("Mix @flour{1.5%cups}. Add @eggs{2}! Rest"),
+            ["Mix @flour{1.5%cups}.", "Add @eggs{2}!", "Rest"]
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        let rules = LintRules {
+            require_servings: true,
+            require_tags: true,
+            require_image: false,
+            max_step_sentences: Some(2),
+        };
+        let findings = lint(
+            RECIPE,
+            Utf8Path::new("Breakfast/Pancakes.cook"),
+            false,
+            &rules,
+        );
+        let rules: Vec<_> = findings
+            .iter()
+            .map(|f| (f.rule, f.line, f.fixable))
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                (Rule::RequireServings, None, false),
+                (Rule::RequireTags, None, true),
+                (Rule::MaxStepSentences, Some(5), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fix() {
+        let rules = LintRules {
+            require_tags: true,
+            max_step_sentences: Some(2),
+            ..Default::default()
+        };
+        let fixed = fix(RECIPE, Utf8Path::new("Breakfast/Pancakes.cook"), &rules)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            fixed,
+            "---\ntitle: Pancakes\ntags:\n- breakfast\n---\n\n\
+             Mix @flour{1.5%cups} and @milk{1%cup}. Add @eggs{2}!\n\n\
+             Whisk well. Rest for ~{10%minutes}.\n\n\
+             -- Fry them\n\n\
+             Fry in a #pan.\n"
+        );
+        assert!(
+            fix(&fixed, Utf8Path::new("Breakfast/Pancakes.cook"), &rules)
+                .unwrap()
+                .is_none()
+        );
+        // Splitting would move the comment before the eggs
+        let commented = "Mix @flour{1%cup}. -- or more\nAdd @eggs{2}. Whisk. Rest.\n";
+        assert!(fix(commented, Utf8Path::new("Pancakes.cook"), &rules)
+            .unwrap()
+            .is_none());
+        assert!(
+            lint(commented, Utf8Path::new("Pancakes.cook"), true, &rules)
+                .iter()
+                .any(|f| f.rule == Rule::MaxStepSentences && !f.fixable)
+        );
+        // Nothing to tag a recipe at the top with
+        assert!(lint(RECIPE, Utf8Path::new("Pancakes.cook"), true, &rules)
+            .iter()
+            .any(|f| f.rule == Rule::RequireTags && !f.fixable));
+    }
+}
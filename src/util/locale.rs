@@ -0,0 +1,373 @@
+//! How numbers and units read in the configured language
+//!
+//! With `locale` set in `[output]`, quantities in the terminal, Markdown,
+//! exported sites and the web UI use the language's decimal separator and
+//! unit names, like "1,5 EL" instead of "1.5 tbsp" for German. JSON output
+//! keeps exact numbers and the units as written in the recipe.
+//!
+//! The same outputs can write quantities as fractions cooks measure with,
+//! like "1½" or "1 1/2" instead of "1.5".
+
+use serde::Deserialize;
+
+use super::Setting;
+
+static LOCALE: Setting<Locale> = Setting::new();
+static FRACTION_STYLE: Setting<FractionStyle> = Setting::new();
+
+/// Largest denominator of fractions unless `fraction_precision` says
+/// otherwise
+pub const DEFAULT_FRACTION_PRECISION: u32 = 8;
+
+/// Languages writing decimals with a comma
+const DECIMAL_COMMA: &[&str] = &[
+    "de", "fr", "es", "it", "nl", "pt", "ru", "pl", "cs", "sk", "sv", "da", "nb", "nn", "no", "fi",
+    "tr", "el", "hu", "ro", "uk", "bg", "hr", "sl", "id", "ca",
+];
+
+/// Unit names as written in recipes and their translation, by language
+const UNITS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "de",
+        &[
+            ("cup", "Tasse"),
+            ("cups", "Tassen"),
+            ("tbsp", "EL"),
+            ("tsp", "TL"),
+            ("pinch", "Prise"),
+            ("pinches", "Prisen"),
+            ("clove", "Zehe"),
+            ("cloves", "Zehen"),
+            ("can", "Dose"),
+            ("cans", "Dosen"),
+            ("slice", "Scheibe"),
+            ("slices", "Scheiben"),
+            ("piece", "Stück"),
+            ("pieces", "Stück"),
+            ("bunch", "Bund"),
+            ("handful", "Handvoll"),
+            ("sprig", "Zweig"),
+            ("sprigs", "Zweige"),
+            ("minute", "Minute"),
+            ("minutes", "Minuten"),
+            ("min", "Min."),
+            ("hour", "Stunde"),
+            ("hours", "Stunden"),
+            ("h", "Std."),
+        ],
+    ),
+    (
+        "fr",
+        &[
+            ("cup", "tasse"),
+            ("cups", "tasses"),
+            ("tbsp", "c. à s."),
+            ("tsp", "c. à c."),
+            ("pinch", "pincée"),
+            ("pinches", "pincées"),
+            ("clove", "gousse"),
+            ("cloves", "gousses"),
+            ("can", "boîte"),
+            ("cans", "boîtes"),
+            ("slice", "tranche"),
+            ("slices", "tranches"),
+            ("piece", "pièce"),
+            ("pieces", "pièces"),
+            ("bunch", "botte"),
+            ("handful", "poignée"),
+            ("sprig", "brin"),
+            ("sprigs", "brins"),
+            ("hour", "heure"),
+            ("hours", "heures"),
+        ],
+    ),
+    (
+        "es",
+        &[
+            ("cup", "taza"),
+            ("cups", "tazas"),
+            ("tbsp", "cda."),
+            ("tsp", "cdta."),
+            ("pinch", "pizca"),
+            ("clove", "diente"),
+            ("cloves", "dientes"),
+            ("can", "lata"),
+            ("cans", "latas"),
+            ("slice", "rebanada"),
+            ("slices", "rebanadas"),
+            ("piece", "pieza"),
+            ("pieces", "piezas"),
+            ("bunch", "manojo"),
+            ("handful", "puñado"),
+            ("sprig", "ramita"),
+            ("minutes", "minutos"),
+            ("hour", "hora"),
+            ("hours", "horas"),
+        ],
+    ),
+    (
+        "it",
+        &[
+            ("cup", "tazza"),
+            ("cups", "tazze"),
+            ("tbsp", "cucchiaio"),
+            ("tsp", "cucchiaino"),
+            ("pinch", "pizzico"),
+            ("clove", "spicchio"),
+            ("cloves", "spicchi"),
+            ("can", "lattina"),
+            ("slice", "fetta"),
+            ("slices", "fette"),
+            ("piece", "pezzo"),
+            ("pieces", "pezzi"),
+            ("bunch", "mazzo"),
+            ("handful", "manciata"),
+            ("sprig", "rametto"),
+            ("minutes", "minuti"),
+            ("hour", "ora"),
+            ("hours", "ore"),
+        ],
+    ),
+    (
+        "nl",
+        &[
+            ("cup", "kop"),
+            ("cups", "kopjes"),
+            ("tbsp", "el"),
+            ("tsp", "tl"),
+            ("pinch", "snufje"),
+            ("clove", "teen"),
+            ("cloves", "tenen"),
+            ("can", "blik"),
+            ("slice", "plak"),
+            ("slices", "plakken"),
+            ("piece", "stuk"),
+            ("pieces", "stuks"),
+            ("bunch", "bos"),
+            ("handful", "handvol"),
+            ("sprig", "takje"),
+            ("minutes", "minuten"),
+            ("hour", "uur"),
+            ("hours", "uur"),
+        ],
+    ),
+];
+
+/// Denominators measuring cups and spoons come in, smallest first
+const DENOMINATORS: &[u32] = &[2, 3, 4, 6, 8, 16];
+
+/// Vulgar fraction characters by numerator and denominator
+const VULGAR_FRACTIONS: &[((u32, u32), char)] = &[
+    ((1, 2), '½'),
+    ((1, 3), '⅓'),
+    ((2, 3), '⅔'),
+    ((1, 4), '¼'),
+    ((3, 4), '¾'),
+    ((1, 6), '⅙'),
+    ((5, 6), '⅚'),
+    ((1, 8), '⅛'),
+    ((3, 8), '⅜'),
+    ((5, 8), '⅝'),
+    ((7, 8), '⅞'),
+];
+
+/// How fractions are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FractionStyle {
+    /// Decimals, like 1.5
+    Off,
+    /// Fraction characters, like 1½
+    Unicode,
+    /// Mixed fractions in plain text, like 1 1/2
+    Ascii,
+}
+
+/// Fractions quantities are written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fractions {
+    pub style: FractionStyle,
+    /// Largest denominator, so 4 allows halves, thirds and quarters
+    pub precision: u32,
+}
+
+impl Default for Fractions {
+    fn default() -> Self {
+        Self {
+            style: FractionStyle::Unicode,
+            precision: DEFAULT_FRACTION_PRECISION,
+        }
+    }
+}
+
+impl Fractions {
+    /// A number as a whole number and fraction, like "1½" or "1 1/2", if
+    /// its fractional part is close to one with an allowed denominator
+    pub fn format(&self, value: f64) -> Option<String> {
+        const EPSILON: f64 = 0.001;
+        if self.style == FractionStyle::Off || value < 0.0 {
+            return None;
+        }
+        let whole = value.floor();
+        let fract = value - whole;
+        let (numerator, denominator) = DENOMINATORS
+            .iter()
+            .filter(|d| **d <= self.precision)
+            .map(|d| ((fract * *d as f64).round() as u32, *d))
+            .find(|(n, d)| {
+                (1..*d).contains(n) && (fract - *n as f64 / *d as f64).abs() < EPSILON
+            })?;
+
+        let glyph = VULGAR_FRACTIONS
+            .iter()
+            .find(|(fraction, _)| *fraction == (numerator, denominator))
+            .map(|(_, glyph)| *glyph);
+        Some(match (self.style, glyph) {
+            (FractionStyle::Unicode, Some(glyph)) if whole > 0.0 => format!("{whole:.0}{glyph}"),
+            (FractionStyle::Unicode, Some(glyph)) => glyph.to_string(),
+            _ if whole > 0.0 => format!("{whole:.0} {numerator}/{denominator}"),
+            _ => format!("{numerator}/{denominator}"),
+        })
+    }
+}
+
+/// Formatting rules of a language
+#[derive(Debug, Clone)]
+pub struct Locale {
+    decimal_separator: char,
+    units: &'static [(&'static str, &'static str)],
+    fractions: Option<Fractions>,
+}
+
+impl Locale {
+    /// Rules for a language tag, like "de", "de-AT" or "de_AT.UTF-8"
+    ///
+    /// Languages without unit names keep the units as written.
+    pub fn new(tag: &str, fractions: Option<Fractions>) -> Self {
+        let language = tag
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        Self {
+            decimal_separator: if DECIMAL_COMMA.contains(&language.as_str()) {
+                ','
+            } else {
+                '.'
+            },
+            units: UNITS
+                .iter()
+                .find(|(lang, _)| *lang == language)
+                .map(|(_, units)| *units)
+                .unwrap_or_default(),
+            fractions,
+        }
+    }
+
+    /// A number with at most 3 decimals, or a fraction if enabled
+    pub fn number(&self, value: f64) -> String {
+        if let Some(fraction) = self.fractions.and_then(|f| f.format(value)) {
+            return fraction;
+        }
+        let decimal = super::format::format_decimal(value);
+        if self.decimal_separator == '.' {
+            decimal
+        } else {
+            decimal.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+
+    /// The unit's name in the language, or the unit as written
+    pub fn unit<'a>(&self, unit: &'a str) -> &'a str {
+        self.units
+            .iter()
+            .find(|(from, _)| from.eq_ignore_ascii_case(unit))
+            .map_or(unit, |(_, to)| to)
+    }
+}
+
+/// Write quantities for the language `tag`, with `fractions` if any, from
+/// now on
+///
+/// Without a locale or fractions, quantities are written as the parser
+/// formats them.
+pub fn configure_locale(tag: Option<&str>, fractions: Option<Fractions>) {
+    if tag.is_none() && fractions.is_none() {
+        return;
+    }
+    LOCALE.set(Locale::new(tag.unwrap_or("en"), fractions));
+}
+
+/// Write fractions in `style` from now on, `None` to leave it to
+/// `cook.toml`
+pub fn configure_fraction_style(style: Option<FractionStyle>) {
+    if let Some(style) = style {
+        FRACTION_STYLE.set(style);
+    }
+}
+
+/// The configured fraction style, if any
+pub fn fraction_style() -> Option<FractionStyle> {
+    FRACTION_STYLE.get().copied()
+}
+
+/// The configured locale, if any
+pub fn locale() -> Option<&'static Locale> {
+    LOCALE.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimal_separator() {
+        assert_eq!(Locale::new("de-DE", None).number(1.5), "1,5");
+        assert_eq!(Locale::new("en_US.UTF-8", None).number(1.5), "1.5");
+        assert_eq!(Locale::new("fr", None).number(2.0), "2");
+    }
+
+    #[test]
+    fn test_fractions() {
+        let locale = Locale::new("en", Some(Fractions::default()));
+        assert_eq!(locale.number(1.5), "1½");
+        assert_eq!(locale.number(0.333333), "⅓");
+        assert_eq!(locale.number(2.0), "2");
+        assert_eq!(locale.number(1.2), "1.2");
+    }
+
+    #[test]
+    fn test_fraction_styles() {
+        let ascii = Fractions {
+            style: FractionStyle::Ascii,
+            precision: 8,
+        };
+        assert_eq!(ascii.format(1.5).as_deref(), Some("1 1/2"));
+        assert_eq!(ascii.format(0.666667).as_deref(), Some("2/3"));
+        assert_eq!(ascii.format(2.375).as_deref(), Some("2 3/8"));
+        assert_eq!(ascii.format(0.0625), None);
+
+        let sixteenths = Fractions {
+            style: FractionStyle::Unicode,
+            precision: 16,
+        };
+        assert_eq!(sixteenths.format(0.0625).as_deref(), Some("1/16"));
+        assert_eq!(sixteenths.format(1.75).as_deref(), Some("1¾"));
+
+        let quarters = Fractions {
+            style: FractionStyle::Unicode,
+            precision: 4,
+        };
+        assert_eq!(quarters.format(0.125), None);
+        assert_eq!(quarters.format(0.5).as_deref(), Some("½"));
+    }
+
+    #[test]
+    fn test_units() {
+        let german = Locale::new("de", None);
+        assert_eq!(german.unit("tbsp"), "EL");
+        assert_eq!(german.unit("Cups"), "Tassen");
+        assert_eq!(german.unit("g"), "g");
+        assert_eq!(Locale::new("ja", None).unit("tbsp"), "tbsp");
+    }
+}
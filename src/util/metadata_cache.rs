@@ -0,0 +1,381 @@
+//! On-disk cache of recipe metadata
+//!
+//! Commands that look at the whole collection only need a few facts about
+//! each recipe, but getting them means reading and parsing every file, which
+//! is slow on big collections and network file systems. The facts are kept
+//! in `metadata.bin` in cook's cache directory, keyed by the recipe's path,
+//! modification time and size, so only recipes that changed are parsed again.
+//!
+//! Names are [interned](super::intern) and the metadata is shared through
+//! `Arc`s, and the cache stays loaded, so a long running server keeps one
+//! copy of it however often it's asked.
+
+use anyhow::{Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::Recipe;
+use cooklang_find::RecipeEntry;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use super::{
+    intern::{self, intern},
+    parse_entries,
+    source::Source,
+    EXTENSIONS, PARSER,
+};
+use crate::global_cache_dir;
+
+const CACHE_FILE: &str = "metadata.bin";
+
+/// Changed whenever [`RecipeMetadata`] changes, so old caches are rebuilt
+const CACHE_VERSION: u32 = 3;
+
+/// The cache as last read or written by this process, and where it's from
+static LOADED: Lazy<Mutex<Option<(Option<Utf8PathBuf>, CacheFile)>>> = Lazy::new(Default::default);
+
+/// What the collection-wide commands need to know about a recipe
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecipeMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(deserialize_with = "intern::deserialize_vec")]
+    pub tags: Vec<Arc<str>>,
+    /// Total time in minutes
+    pub total_time: Option<u32>,
+    pub has_servings: bool,
+    /// As written, like "4" or "2|4"
+    pub servings: Option<String>,
+    /// Lowercase names of the listed ingredients, without recipe references
+    #[serde(deserialize_with = "intern::deserialize_set")]
+    pub ingredients: BTreeSet<Arc<str>>,
+    /// Lowercase names of the listed cookware
+    #[serde(deserialize_with = "intern::deserialize_set")]
+    pub cookware: BTreeSet<Arc<str>>,
+    /// Where the recipe is from, see [`Source::key`]
+    pub source: Option<String>,
+    /// Why the recipe couldn't be parsed
+    pub error: Option<String>,
+}
+
+impl RecipeMetadata {
+    fn new(entry: &RecipeEntry, recipe: Result<Arc<Recipe>>) -> Self {
+        let tags = entry.tags().iter().map(|tag| intern(tag)).collect();
+        let recipe = match recipe {
+            Ok(recipe) => recipe,
+            Err(e) => {
+                return Self {
+                    tags,
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                }
+            }
+        };
+        let text = |key: &str| {
+            recipe
+                .metadata
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        };
+
+        Self {
+            title: text("title"),
+            description: text("description"),
+            tags,
+            total_time: recipe
+                .metadata
+                .time(PARSER.converter())
+                .map(|time| time.total()),
+            has_servings: recipe.metadata.servings().is_some(),
+            servings: recipe.metadata.servings().map(|s| s.to_string()),
+            ingredients: recipe
+                .ingredients
+                .iter()
+                .filter(|i| i.reference.is_none() && i.modifiers().should_be_listed())
+                .map(|i| intern(&i.display_name().to_lowercase()))
+                .collect(),
+            cookware: recipe
+                .cookware
+                .iter()
+                .filter(|c| c.modifiers().should_be_listed())
+                .map(|c| intern(&c.display_name().to_lowercase()))
+                .collect(),
+            source: Source::of(&recipe).and_then(|source| source.key()),
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Parser extensions the recipes were parsed with
+    extensions: u32,
+    recipes: HashMap<String, CachedRecipe>,
+    /// Holds recipes from [`recipe_metadata`] that aren't written yet
+    #[serde(skip)]
+    unsaved: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedRecipe {
+    stamp: FileStamp,
+    metadata: Arc<RecipeMetadata>,
+}
+
+/// Tells whether a file changed since it was cached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    modified_secs: u64,
+    modified_nanos: u32,
+    size: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Utf8Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            size: meta.len(),
+        })
+    }
+}
+
+/// Metadata of the entries of the collection at `base_path`, in their order
+///
+/// Cached metadata is used for recipes that didn't change, the rest are
+/// parsed (in parallel) and the cache is updated. Recipes of the collection
+/// that were deleted are dropped from the cache. Problems with the cache
+/// file are only logged, it's rebuilt when unreadable.
+pub fn collection_metadata(
+    base_path: &Utf8Path,
+    entries: Vec<RecipeEntry>,
+) -> Vec<(RecipeEntry, Arc<RecipeMetadata>)> {
+    let cache_path = cache_file();
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = load(&mut loaded, &cache_path);
+
+    renew(cache);
+
+    let mut results: Vec<Option<Arc<RecipeMetadata>>> = Vec::with_capacity(entries.len());
+    let mut keys = Vec::with_capacity(entries.len());
+    // Position and stamp of the recipes to parse, and their entries
+    let mut stale = Vec::new();
+    let mut stale_entries = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let key = entry.path().map(|path| absolute(path));
+        let stamp = key.as_deref().and_then(FileStamp::of);
+        let cached = key
+            .as_ref()
+            .and_then(|key| cache.recipes.get(key.as_str()))
+            .filter(|cached| Some(cached.stamp) == stamp);
+        match cached {
+            Some(cached) => results.push(Some(Arc::clone(&cached.metadata))),
+            None => {
+                results.push(None);
+                stale.push((i, stamp));
+                stale_entries.push(entry.clone());
+            }
+        }
+        keys.push(key);
+    }
+
+    let changed = !stale.is_empty();
+    for ((i, stamp), (entry, recipe)) in stale.into_iter().zip(parse_entries(stale_entries, 1.0)) {
+        let metadata = Arc::new(RecipeMetadata::new(&entry, recipe));
+        if let (Some(key), Some(stamp)) = (&keys[i], stamp) {
+            cache.recipes.insert(
+                key.to_string(),
+                CachedRecipe {
+                    stamp,
+                    metadata: Arc::clone(&metadata),
+                },
+            );
+        }
+        results[i] = Some(metadata);
+    }
+
+    // Recipes of this collection that are gone
+    let base = absolute(base_path);
+    let seen: BTreeSet<&str> = keys.iter().flatten().map(|key| key.as_str()).collect();
+    let before = cache.recipes.len();
+    cache.recipes.retain(|key, _| {
+        let path = Utf8Path::new(key);
+        seen.contains(key.as_str()) || !path.starts_with(&base) || path.is_file()
+    });
+    let removed = cache.recipes.len() != before;
+
+    if changed || removed || cache.unsaved {
+        if let Some(path) = &cache_path {
+            match write(path, cache) {
+                Ok(()) => cache.unsaved = false,
+                Err(e) => tracing::warn!("Failed to update the metadata cache: {e:#}"),
+            }
+        }
+    }
+
+    entries
+        .into_iter()
+        .zip(results)
+        .map(|(entry, metadata)| (entry, metadata.expect("every entry has metadata")))
+        .collect()
+}
+
+/// Metadata of one recipe, from the cache unless it changed
+///
+/// Unlike [`collection_metadata`] it leaves the rest of the cache alone, and
+/// it doesn't write the cache file: pages ask for many recipes one by one,
+/// so what's parsed here is written by the next [`collection_metadata`].
+pub fn recipe_metadata(entry: &RecipeEntry) -> Arc<RecipeMetadata> {
+    let cache_path = cache_file();
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = load(&mut loaded, &cache_path);
+    renew(cache);
+
+    let key = entry.path().map(|path| absolute(path));
+    let stamp = key.as_deref().and_then(FileStamp::of);
+    let cached = key
+        .as_ref()
+        .and_then(|key| cache.recipes.get(key.as_str()))
+        .filter(|cached| Some(cached.stamp) == stamp);
+    if let Some(cached) = cached {
+        return Arc::clone(&cached.metadata);
+    }
+
+    let (entry, recipe) = parse_entries(vec![entry.clone()], 1.0)
+        .pop()
+        .expect("one entry is parsed");
+    let metadata = Arc::new(RecipeMetadata::new(&entry, recipe));
+    if let (Some(key), Some(stamp)) = (key, stamp) {
+        cache.recipes.insert(
+            key.to_string(),
+            CachedRecipe {
+                stamp,
+                metadata: Arc::clone(&metadata),
+            },
+        );
+        cache.unsaved = true;
+    }
+    metadata
+}
+
+/// Empty a cache written by another version or with other parser extensions
+fn renew(cache: &mut CacheFile) {
+    let extensions = EXTENSIONS.get().map(|e| e.bits()).unwrap_or(0);
+    if cache.version != CACHE_VERSION || cache.extensions != extensions {
+        *cache = CacheFile {
+            version: CACHE_VERSION,
+            extensions,
+            ..Default::default()
+        };
+    }
+}
+
+/// Where the cache is kept, if there's a cache directory
+pub fn cache_file() -> Option<Utf8PathBuf> {
+    global_cache_dir().ok().map(|dir| dir.join(CACHE_FILE))
+}
+
+/// The cache from `cache_path`
+///
+/// The file is only read once, unless the cache directory changed.
+fn load<'a>(
+    loaded: &'a mut Option<(Option<Utf8PathBuf>, CacheFile)>,
+    cache_path: &Option<Utf8PathBuf>,
+) -> &'a mut CacheFile {
+    if !matches!(&*loaded, Some((path, _)) if path == cache_path) {
+        let cache = cache_path.as_deref().map(read).unwrap_or_default();
+        *loaded = Some((cache_path.clone(), cache));
+    }
+    let (_, cache) = loaded.as_mut().expect("the cache was loaded above");
+    cache
+}
+
+/// Forget the metadata of the recipes of the collection at `base_path`, so
+/// the next [`collection_metadata`] parses all of them again
+pub fn invalidate(base_path: &Utf8Path) {
+    let cache_path = cache_file();
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = load(&mut loaded, &cache_path);
+    let base = absolute(base_path);
+    cache
+        .recipes
+        .retain(|key, _| !Utf8Path::new(key).starts_with(&base));
+}
+
+fn absolute(path: &Utf8Path) -> Utf8PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    match std::env::current_dir() {
+        Ok(dir) => Utf8PathBuf::from_path_buf(dir)
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+fn read(path: &Utf8Path) -> CacheFile {
+    let Ok(bytes) = std::fs::read(path) else {
+        return CacheFile::default();
+    };
+    postcard::from_bytes(&bytes).unwrap_or_else(|e| {
+        tracing::debug!("Rebuilding the metadata cache {path}: {e}");
+        CacheFile::default()
+    })
+}
+
+/// Write through a temporary file, so concurrent runs never read half a cache
+fn write(path: &Utf8Path, cache: &CacheFile) -> Result<()> {
+    let dir = path.parent().unwrap_or(Utf8Path::new("."));
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir}"))?;
+    let bytes = postcard::to_stdvec(cache)?;
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&tmp, bytes).with_context(|| format!("Failed to write {tmp}"))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {path}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_file_round_trip() {
+        let mut cache = CacheFile {
+            version: CACHE_VERSION,
+            ..Default::default()
+        };
+        cache.recipes.insert(
+            "/recipes/Pancakes.cook".to_string(),
+            CachedRecipe {
+                stamp: FileStamp {
+                    modified_secs: 1_700_000_000,
+                    modified_nanos: 5,
+                    size: 120,
+                },
+                metadata: Arc::new(RecipeMetadata {
+                    title: Some("Pancakes".to_string()),
+                    tags: vec![intern("breakfast")],
+                    total_time: Some(25),
+                    ingredients: [intern("flour"), intern("eggs")].into(),
+                    ..Default::default()
+                }),
+            },
+        );
+
+        let bytes = postcard::to_stdvec(&cache).unwrap();
+        let read: CacheFile = postcard::from_bytes(&bytes).unwrap();
+        let cached = &read.recipes["/recipes/Pancakes.cook"];
+        assert_eq!(cached.stamp.size, 120);
+        assert_eq!(cached.metadata.total_time, Some(25));
+        // Read names are shared with the rest of the process
+        let eggs = cached.metadata.ingredients.get("eggs").unwrap();
+        assert!(Arc::ptr_eq(eggs, &intern("eggs")));
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::{Context as _, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_yaml::{Mapping, Value};
+
+use super::front_matter::yaml_value;
+
+/// Old style metadata line, like `>> servings: 4`
+static LEGACY_METADATA: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*>>\s*([^:]+?)\s*:\s*(.*?)\s*$").unwrap());
+
+/// Moves `>> key: value` metadata lines of a recipe into YAML front matter,
+/// `None` if there are none
+///
+/// Keys already in the front matter win over the old style lines. Nothing
+/// else is changed.
+pub fn migrate_recipe(content: &str) -> Result<Option<String>> {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let lines: Vec<&str> = content.lines().collect();
+
+    let (front_matter, body) = split_front_matter(&lines);
+
+    let mut legacy = Vec::new();
+    let mut body_lines = Vec::new();
+    for line in body {
+        match LEGACY_METADATA.captures(line) {
+            Some(caps) => legacy.push((caps[1].to_string(), caps[2].to_string())),
+            None => body_lines.push(*line),
+        }
+    }
+    if legacy.is_empty() {
+        return Ok(None);
+    }
+
+    let existing: Mapping = match front_matter {
+        Some(lines) if !lines.is_empty() => {
+            serde_yaml::from_str(&lines.join("\n")).context("The front matter is not valid YAML")?
+        }
+        _ => Mapping::new(),
+    };
+
+    let mut added = Mapping::new();
+    for (key, value) in legacy {
+        let key = Value::String(key);
+        if existing.contains_key(&key) || added.contains_key(&key) {
+            continue;
+        }
+        added.insert(key, yaml_value(&value));
+    }
+
+    let added = if added.is_empty() {
+        String::new()
+    } else {
+        serde_yaml::to_string(&added)?
+    };
+
+    let mut out = vec!["---"];
+    out.extend(front_matter.unwrap_or_default());
+    out.extend(added.lines());
+    out.push("---");
+    out.push("");
+
+    // Blank lines left where the metadata was at the top
+    let start = body_lines
+        .iter()
+        .position(|line| !line.trim().is_empty())
+        .unwrap_or(body_lines.len());
+    out.extend(&body_lines[start..]);
+
+    let mut migrated = out.join(newline);
+    if content.ends_with('\n') {
+        migrated.push_str(newline);
+    }
+    Ok(Some(migrated))
+}
+
+/// Splits off the lines between `---` fences at the start of the recipe
+fn split_front_matter<'a>(lines: &'a [&'a str]) -> (Option<&'a [&'a str]>, &'a [&'a str]) {
+    if lines.first().map(|l| l.trim_end()) != Some("---") {
+        return (None, lines);
+    }
+    match lines[1..].iter().position(|l| l.trim_end() == "---") {
+        Some(end) => (Some(&lines[1..end + 1]), &lines[end + 2..]),
+        None => (None, lines),
+    }
+}
+
+/// The recipe has old style `>> key: value` metadata lines
+pub fn has_legacy_metadata(content: &str) -> bool {
+    content.lines().any(|line| LEGACY_METADATA.is_match(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_legacy_metadata() {
+        let recipe = ">> title: Pancakes\n>> servings: 4\n>> source: https://example.com/pancakes\n\nMix @flour{200%g} and @eggs{2}.\n";
+        let migrated = migrate_recipe(recipe).unwrap().unwrap();
+        assert_eq!(
+            migrated,
+            "---\ntitle: Pancakes\nservings: 4\nsource: https://example.com/pancakes\n---\n\nMix @flour{200%g} and @eggs{2}.\n"
+        );
+    }
+
+    #[test]
+    fn test_migrate_merges_front_matter() {
+        let recipe = "---\ntitle: Pancakes\n---\n>> title: Old title\n>> tags: breakfast, sweet\n\nMix @flour{200%g}.\n";
+        let migrated = migrate_recipe(recipe).unwrap().unwrap();
+        assert_eq!(
+            migrated,
+            "---\ntitle: Pancakes\ntags: breakfast, sweet\n---\n\nMix @flour{200%g}.\n"
+        );
+    }
+
+    #[test]
+    fn test_migrate_quotes_values() {
+        let recipe = ">> description: Note: best warm\n\nServe.";
+        let migrated = migrate_recipe(recipe).unwrap().unwrap();
+        assert_eq!(
+            migrated,
+            "---\ndescription: 'Note: best warm'\n---\n\nServe."
+        );
+    }
+
+    #[test]
+    fn test_migrate_nothing_to_do() {
+        let recipe = "---\ntitle: Pancakes\n---\n\nMix @flour{200%g}.\n";
+        assert!(migrate_recipe(recipe).unwrap().is_none());
+        assert!(migrate_recipe("Mix @flour{200%g}.").unwrap().is_none());
+    }
+}
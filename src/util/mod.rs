@@ -28,30 +28,222 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+pub mod ai;
+pub mod aliases;
+pub mod calendar;
+pub mod compare;
+pub mod cooked;
 pub mod cooklang_to_cooklang;
 pub mod cooklang_to_human;
 pub mod cooklang_to_md;
+pub mod cooklang_to_ssml;
+pub mod cookware;
+pub mod cron;
+pub mod csv;
+pub mod diagnostics;
+pub mod digest;
+pub mod duplicate;
 pub mod format;
-
+pub mod front_matter;
+pub mod git;
+pub mod images;
+pub mod import;
+pub mod intern;
+pub mod lint;
+pub mod locale;
+pub mod metadata_cache;
+pub mod migrate;
+pub mod names;
+pub mod nutrition;
+pub mod pantry;
+pub mod random;
+pub mod remote;
+pub mod rounding;
+pub mod scaling;
+pub mod schema;
+pub mod seasons;
+pub mod source;
+pub mod step_quantities;
+pub mod substitutions;
+pub mod temperature;
+pub mod timer;
+pub mod units;
+pub mod vtodo;
+pub mod walk;
+
+use crate::error::NotFound;
 use anyhow::{Context as _, Result};
 use camino::{Utf8Path, Utf8PathBuf};
-use clap::CommandFactory;
 use cooklang::{
     ingredient_list::IngredientList, quantity::Value, Converter, CooklangParser, Extensions, Recipe,
 };
-use cooklang_find::RecipeEntry;
-use once_cell::sync::Lazy;
+use cooklang_find::{RecipeEntry, RecipeTree};
+use diagnostics::ParseError;
+use once_cell::sync::{Lazy, OnceCell};
+use rayon::prelude::*;
+use serde::Deserialize;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 use tracing::warn;
 
 pub const RECIPE_SCALING_DELIMITER: char = ':';
 
+static EXTENSIONS: OnceCell<Extensions> = OnceCell::new();
+static CONVERTER: OnceCell<Converter> = OnceCell::new();
+
+/// A setting every command reads the same way, like the temperature unit
+///
+/// Settings are set once, when the [`Context`](crate::Context) is created,
+/// and read wherever recipes are handled, without passing the configuration
+/// down. Only the first value set counts: global options like
+/// `--temperature` set theirs before `cook.toml` is read, so they win.
+/// New settings should be passed as arguments instead where they can.
+pub struct Setting<T>(OnceCell<T>);
+
+impl<T> Setting<T> {
+    pub const fn new() -> Self {
+        Self(OnceCell::new())
+    }
+
+    /// Set the setting, unless it's set already
+    pub fn set(&self, value: T) {
+        let _ = self.0.set(value);
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        self.0.get()
+    }
+}
+
+impl<T> Default for Setting<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub static PARSER: Lazy<CooklangParser> = Lazy::new(|| {
-    // Use no extensions but with default converter for basic unit support
-    CooklangParser::new(Extensions::empty(), Converter::default())
+    // No extensions unless enabled with configure_parser, and the default
+    // converter for basic unit support unless there's a units.toml
+    let extensions = EXTENSIONS.get().copied().unwrap_or_else(Extensions::empty);
+    let converter = CONVERTER.get().cloned().unwrap_or_default();
+    CooklangParser::new(extensions, converter)
 });
 
+/// Cooklang syntax extensions that can be turned on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParserExtension {
+    /// Every extension below
+    All,
+    /// Component modifiers, like @?salt for optional or @-oil for hidden
+    Modifiers,
+    /// Different names in the text and the ingredient list, like @sea salt|salt{}
+    Alias,
+    /// Stricter units, checked and converted between unit systems
+    AdvancedUnits,
+    /// [mode] lines switching between steps, components and text
+    Modes,
+    /// Quantities in the step text, like 180 °C, are detected and converted
+    InlineQuantities,
+    /// Ranges as quantities, like @eggs{2-3}
+    RangeValues,
+    /// Timers must have a duration
+    TimerRequiresTime,
+    /// References to the result of earlier steps, like @&(~1)dough{}
+    IntermediatePreparations,
+}
+
+impl ParserExtension {
+    fn flags(self) -> Extensions {
+        match self {
+            ParserExtension::All => Extensions::all(),
+            ParserExtension::Modifiers => Extensions::COMPONENT_MODIFIERS,
+            ParserExtension::Alias => Extensions::COMPONENT_ALIAS,
+            ParserExtension::AdvancedUnits => Extensions::ADVANCED_UNITS,
+            ParserExtension::Modes => Extensions::MODES,
+            ParserExtension::InlineQuantities => Extensions::INLINE_QUANTITIES,
+            ParserExtension::RangeValues => Extensions::RANGE_VALUES,
+            ParserExtension::TimerRequiresTime => Extensions::TIMER_REQUIRES_TIME,
+            ParserExtension::IntermediatePreparations => Extensions::INTERMEDIATE_PREPARATIONS,
+        }
+    }
+}
+
+/// Choose the extensions PARSER is built with
+///
+/// Only the first call counts, and only if PARSER hasn't been used yet. This
+/// lets `--extensions` win over `cook.toml`.
+pub fn configure_parser(extensions: &[ParserExtension]) {
+    let flags = extensions
+        .iter()
+        .fold(Extensions::empty(), |flags, ext| flags | ext.flags());
+    let _ = EXTENSIONS.set(flags);
+}
+
+/// Add the units and densities of a `units.toml` to PARSER's converter
+///
+/// Like [`configure_parser`], only the first call before PARSER is used
+/// counts.
+pub fn configure_units(mut units: units::CustomUnits) -> Result<()> {
+    units::configure_densities(std::mem::take(&mut units.densities));
+    let _ = CONVERTER.set(units.converter()?);
+    Ok(())
+}
+
+/// Parts of a recipe that can be output on their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RecipePart {
+    /// Description, servings, times and the other metadata
+    Metadata,
+    /// The ingredient list
+    Ingredients,
+    /// The cookware list
+    Cookware,
+    /// The steps, with their sections and timers
+    Steps,
+}
+
+impl RecipePart {
+    /// Keys of the recipe's JSON holding the part
+    ///
+    /// Steps refer to ingredients, cookware and timers by index.
+    fn keys(self) -> &'static [&'static str] {
+        match self {
+            RecipePart::Metadata => &["metadata"],
+            RecipePart::Ingredients => &["ingredients", "grouped_ingredients"],
+            RecipePart::Cookware => &["cookware"],
+            RecipePart::Steps => &["sections", "timers", "inline_quantities", "step_quantities"],
+        }
+    }
+
+    /// Parts separated by commas, like "ingredients,steps"
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                <Self as clap::ValueEnum>::from_str(name, true).map_err(|_| {
+                    format!(
+                        "unknown recipe part '{name}', expected metadata, ingredients, cookware or steps"
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// A serialized recipe with only the keys of `parts`, or all of them if
+/// there are no parts
+pub fn select_parts(mut value: serde_json::Value, parts: &[RecipePart]) -> serde_json::Value {
+    if parts.is_empty() {
+        return value;
+    }
+    if let serde_json::Value::Object(map) = &mut value {
+        map.retain(|key, _| parts.iter().any(|part| part.keys().contains(&key.as_str())));
+    }
+    value
+}
+
 /// Parse a Recipe from a RecipeEntry with the given scaling factor
 pub fn parse_recipe_from_entry(entry: &RecipeEntry, scaling_factor: f64) -> Result<Arc<Recipe>> {
     let content = entry.content().context("Failed to read recipe content")?;
@@ -65,13 +257,112 @@ pub fn parse_recipe_from_entry(entry: &RecipeEntry, scaling_factor: f64) -> Resu
         }
     }
 
-    let (mut recipe, _warnings) = parsed.into_result().context("Failed to parse recipe")?;
+    let (mut recipe, _warnings) = parsed
+        .into_result()
+        .map_err(|report| ParseError::new(&entry_file(entry), &content, &report))?;
 
     // Scale the recipe
-    recipe.scale(scaling_factor, PARSER.converter());
+    rounding::with_rounding(&mut recipe, |recipe| {
+        recipe.scale(scaling_factor, PARSER.converter())
+    });
+    if let Some(unit) = temperature::temperature() {
+        temperature::convert_recipe(&mut recipe, unit);
+    }
     Ok(Arc::new(recipe))
 }
 
+/// How a recipe is called in error messages: its path, or else its name
+fn entry_file(entry: &RecipeEntry) -> String {
+    entry
+        .path()
+        .map(|path| path.to_string())
+        .or_else(|| entry.name().clone())
+        .unwrap_or_else(|| "recipe".to_string())
+}
+
+/// Use `jobs` threads to parse recipes, instead of one per CPU
+pub fn configure_jobs(jobs: usize) -> Result<()> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build_global()
+        .context("Failed to start the parsing threads")
+}
+
+/// Every recipe and menu in a tree, in tree order
+pub fn tree_entries(tree: &RecipeTree) -> Vec<RecipeEntry> {
+    fn collect(tree: &RecipeTree, out: &mut Vec<RecipeEntry>) {
+        if let Some(entry) = &tree.recipe {
+            out.push(entry.clone());
+        }
+        for child in tree.children.values() {
+            collect(child, out);
+        }
+    }
+
+    let mut entries = Vec::new();
+    collect(tree, &mut entries);
+    entries
+}
+
+/// Parse many recipes in parallel, see [`configure_jobs`]
+///
+/// The results are in the order of the entries.
+pub fn parse_entries(
+    entries: Vec<RecipeEntry>,
+    scaling_factor: f64,
+) -> Vec<(RecipeEntry, Result<Arc<Recipe>>)> {
+    entries
+        .into_par_iter()
+        .map(|entry| {
+            let recipe = parse_recipe_from_entry(&entry, scaling_factor);
+            (entry, recipe)
+        })
+        .collect()
+}
+
+/// Download a file, from the command line where there's no runtime yet
+pub fn download(url: &str) -> Result<Vec<u8>> {
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let response = reqwest::get(url)
+            .await
+            .with_context(|| format!("Failed to download {url}"))?
+            .error_for_status()
+            .with_context(|| format!("Failed to download {url}"))?;
+        Ok(response.bytes().await?.to_vec())
+    })
+}
+
+/// Like [`download`], failing when the file is larger than `limit` bytes
+pub fn download_limited(url: &str, limit: u64) -> Result<Vec<u8>> {
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let mut response = reqwest::get(url)
+            .await
+            .with_context(|| format!("Failed to download {url}"))?
+            .error_for_status()
+            .with_context(|| format!("Failed to download {url}"))?;
+        let too_large = || anyhow::anyhow!("{url} is larger than {} MB", limit >> 20);
+        if response
+            .content_length()
+            .is_some_and(|length| length > limit)
+        {
+            return Err(too_large());
+        }
+        // The length isn't always given, or true
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .with_context(|| format!("Failed to download {url}"))?
+        {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() as u64 > limit {
+                return Err(too_large());
+            }
+        }
+        Ok(bytes)
+    })
+}
+
 pub fn write_to_output<F>(output: Option<&Utf8Path>, f: F) -> Result<()>
 where
     F: FnOnce(&mut dyn std::io::Write) -> Result<()>,
@@ -101,30 +392,131 @@ pub fn resolve_to_absolute_path(path: &Utf8Path) -> anyhow::Result<Utf8PathBuf>
     let absolute = if path.is_absolute() {
         path.to_path_buf()
     } else {
-        std::env::current_dir()
-            .map_err(|e| {
-                tracing::error!("Failed to get current directory: {:?}", e);
-                anyhow::anyhow!("Failed to get current directory")
-            })?
-            .join(path)
-            .try_into()
-            .map_err(|e| {
-                tracing::error!("Failed to convert path to UTF-8: {:?}", e);
-                anyhow::anyhow!("Failed to convert path to UTF-8")
-            })?
+        let current_dir = std::env::current_dir().map_err(|e| {
+            tracing::error!("Failed to get current directory: {:?}", e);
+            anyhow::anyhow!("Failed to get current directory")
+        })?;
+        utf8_path(current_dir.join(path))?
     };
 
     // Normalize the path by resolving all components
-    std::fs::canonicalize(&absolute)
-        .map_err(|e| {
-            tracing::error!("Failed to canonicalize path: {:?}", e);
-            anyhow::anyhow!("Failed to canonicalize path")
-        })?
-        .try_into()
-        .map_err(|e| {
-            tracing::error!("Failed to convert canonicalized path to UTF-8: {:?}", e);
-            anyhow::anyhow!("Failed to convert canonicalized path to UTF-8")
-        })
+    let canonical = std::fs::canonicalize(&absolute).map_err(|e| {
+        tracing::error!("Failed to canonicalize path: {:?}", e);
+        anyhow::anyhow!("Failed to canonicalize path")
+    })?;
+    utf8_path(canonical)
+}
+
+/// Like [`resolve_to_absolute_path`], for paths that don't exist yet too
+///
+/// The part that exists is resolved, the rest is added as it is.
+pub fn canonical_path(path: &Utf8Path) -> Result<Utf8PathBuf> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        utf8_path(std::env::current_dir()?)?.join(path)
+    };
+    let mut existing = absolute.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let Some(name) = existing.file_name() else {
+            anyhow::bail!("Invalid path {path}");
+        };
+        missing.push(name);
+        existing = existing
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Invalid path {path}"))?;
+    }
+    let mut canonical = resolve_to_absolute_path(existing)?;
+    canonical.extend(missing.into_iter().rev());
+    Ok(canonical)
+}
+
+/// Convert a path from the OS, failing (instead of panicking) if it isn't
+/// UTF-8, which happens on some network drives with legacy encodings
+pub fn utf8_path(path: std::path::PathBuf) -> Result<Utf8PathBuf> {
+    Utf8PathBuf::from_path_buf(path).map_err(|path| {
+        anyhow::anyhow!(
+            "cook only supports UTF-8 paths, {} isn't one",
+            path.display()
+        )
+    })
+}
+
+/// Open a file in the user's editor ($VISUAL, then $EDITOR) and wait for it
+/// to be closed
+pub fn open_in_editor(path: &Utf8Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        });
+
+    // The variable may include arguments, like "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("Editor command is empty")?;
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{editor}'"))?;
+    if !status.success() {
+        anyhow::bail!("Editor '{editor}' exited with {status}");
+    }
+    Ok(())
+}
+
+/// Text escaped for HTML and XML, in elements and quoted attributes
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Percent-encode text for a URL path or query value, keeping `/`
+pub fn encode_url(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Score a fuzzy (in order, not necessarily adjacent) match of `query` in
+/// `text`, higher is better. `None` if not all query characters are found.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut prev: Option<usize> = None;
+
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = (pos..text.len()).find(|&i| text[i] == q)?;
+        score += 10;
+        // consecutive characters and word starts are better matches
+        if i > 0 && prev == Some(i - 1) {
+            score += 15;
+        }
+        if i == 0 || matches!(text[i - 1], '/' | ' ' | '-' | '_') {
+            score += 10;
+        }
+        prev = Some(i);
+        pos = i + 1;
+    }
+
+    // prefer shorter paths
+    Some(score * 100 - text.len() as i64)
 }
 
 pub fn extract_ingredients(
@@ -146,22 +538,18 @@ pub fn extract_ingredients(
     seen.insert(entry.to_string(), seen.len());
 
     // split into name and servings
-    let (name, scaling_factor) = split_recipe_name_and_scaling_factor(entry)
-        .map(|(name, scaling_factor)| {
-            let target = scaling_factor.parse::<f64>().unwrap_or_else(|err| {
-                let mut cmd = crate::args::CliArgs::command();
-                cmd.error(
-                    clap::error::ErrorKind::InvalidValue,
-                    format!("Invalid scaling target for '{name}': {err}"),
-                )
-                .exit()
-            });
-            (name, target)
-        })
-        .unwrap_or((entry, 1.0));
+    let (name, scale) = match split_recipe_name_and_scaling_factor(entry) {
+        Some((name, scale)) => (name, Some(scale)),
+        None => (entry, None),
+    };
 
     let recipe_entry =
         get_recipe(base_path, name).with_context(|| format!("Failed to find recipe '{name}'"))?;
+    // A number or the name of one of the recipe's scalings
+    let scaling_factor = match scale {
+        Some(scale) => scaling::resolve(&recipe_entry, scale)?,
+        None => scaling::default_servings_factor(&recipe_entry).unwrap_or(1.0),
+    };
     let recipe = parse_recipe_from_entry(&recipe_entry, scaling_factor)?;
     let ref_indices = list.add_recipe(&recipe, converter, ignore_references);
 
@@ -250,10 +638,10 @@ pub fn extract_ingredients(
                     let content = ref_entry
                         .content()
                         .context("Failed to read recipe content")?;
-                    let (mut recipe, _warnings) = PARSER
-                        .parse(&content)
-                        .into_result()
-                        .context("Failed to parse recipe")?;
+                    let (mut recipe, _warnings) =
+                        PARSER.parse(&content).into_result().map_err(|report| {
+                            ParseError::new(&entry_file(&ref_entry), &content, &report)
+                        })?;
 
                     // Use the new scale_to_target function
                     tracing::debug!(
@@ -262,14 +650,15 @@ pub fn extract_ingredients(
                         target_value,
                         quantity.unit().unwrap_or("(no unit)")
                     );
-                    recipe
-                        .scale_to_target(target_value, quantity.unit(), PARSER.converter())
-                        .context(format!(
-                            "Failed to scale recipe '{}' with target {} {}",
-                            ref_path,
-                            target_value,
-                            quantity.unit().unwrap_or("(no unit)")
-                        ))?;
+                    rounding::with_rounding(&mut recipe, |recipe| {
+                        recipe.scale_to_target(target_value, quantity.unit(), PARSER.converter())
+                    })
+                    .context(format!(
+                        "Failed to scale recipe '{}' with target {} {}",
+                        ref_path,
+                        target_value,
+                        quantity.unit().unwrap_or("(no unit)")
+                    ))?;
 
                     // Don't apply additional CLI scaling when using scale_to_target
                     // The target value already accounts for the scaling
@@ -308,10 +697,17 @@ pub fn extract_ingredients(
                     let nested_content = nested_entry_path
                         .content()
                         .context("Failed to read nested recipe")?;
-                    let (nested_recipe, _) = PARSER
-                        .parse(&nested_content)
-                        .into_result()
-                        .context("Failed to parse nested recipe")?;
+                    let (nested_recipe, _) =
+                        PARSER
+                            .parse(&nested_content)
+                            .into_result()
+                            .map_err(|report| {
+                                ParseError::new(
+                                    &entry_file(&nested_entry_path),
+                                    &nested_content,
+                                    &report,
+                                )
+                            })?;
 
                     // For nested references, we need to handle scaling properly based on units
                     if let Some(quantity) = &nested_ingredient.quantity {
@@ -323,9 +719,14 @@ pub fn extract_ingredients(
                                 let mut scaled_nested = nested_recipe;
                                 let target = target_servings.to_string().parse().unwrap_or(1.0);
                                 tracing::debug!("Scaling nested recipe to {} servings", target);
-                                scaled_nested
-                                    .scale_to_target(target, Some("servings"), PARSER.converter())
-                                    .context("Failed to scale nested recipe")?;
+                                rounding::with_rounding(&mut scaled_nested, |recipe| {
+                                    recipe.scale_to_target(
+                                        target,
+                                        Some("servings"),
+                                        PARSER.converter(),
+                                    )
+                                })
+                                .context("Failed to scale nested recipe")?;
 
                                 // Now add this properly scaled nested recipe's ingredients
                                 // Pass false to exclude references - they will be handled recursively
@@ -337,7 +738,9 @@ pub fn extract_ingredients(
                             if let Value::Number(num) = quantity.value() {
                                 let scaling = num.to_string().parse().unwrap_or(1.0);
                                 let mut scaled_nested = nested_recipe;
-                                scaled_nested.scale(scaling, PARSER.converter());
+                                rounding::with_rounding(&mut scaled_nested, |recipe| {
+                                    recipe.scale(scaling, PARSER.converter())
+                                });
                                 list.add_recipe(&Arc::new(scaled_nested), converter, false);
                             }
                         }
@@ -365,8 +768,15 @@ pub fn get_recipe(base_path: &Utf8PathBuf, name: &str) -> Result<RecipeEntry> {
     // The cooklang-find library doesn't expect the ./ prefix
     let clean_name = name.strip_prefix("./").unwrap_or(name);
 
-    Ok(cooklang_find::get_recipe(
-        vec![base_path.clone()],
-        clean_name.into(),
-    )?)
+    cooklang_find::get_recipe(vec![base_path.clone()], clean_name.into()).map_err(|e| {
+        // A recipe that's there but can't be read isn't missing
+        let unreadable = std::iter::successors(Some(&e as &dyn std::error::Error), |e| e.source())
+            .filter_map(|e| e.downcast_ref::<std::io::Error>())
+            .any(|e| e.kind() != std::io::ErrorKind::NotFound);
+        if unreadable {
+            anyhow::Error::new(e).context(format!("Failed to read the recipe {name}"))
+        } else {
+            NotFound(format!("Recipe not found: {e}")).into()
+        }
+    })
 }
@@ -0,0 +1,152 @@
+//! Recipe names that cause trouble
+//!
+//! Recipes are looked up by name, case-insensitively on some systems, and
+//! their names end up in URLs and in files of exported sites. Two recipes
+//! whose names only differ in case or punctuation, or a file name with
+//! characters some systems don't allow, work on one machine and break on
+//! the next.
+
+/// Characters that aren't allowed in file names on Windows, or that have to
+/// be escaped in URLs and shell commands
+const PROBLEM_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|', '#', '%'];
+
+/// Names Windows reserves for devices, whatever the extension
+const RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Lowercase words of a name joined with dashes, like "mac-n-cheese" for
+/// "Mac 'n' Cheese"
+pub fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// What's wrong with a file name (without its extension), if anything
+pub fn stem_problems(stem: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let bad: String = stem
+        .chars()
+        .filter(|c| PROBLEM_CHARS.contains(c) || c.is_control())
+        .collect();
+    if !bad.is_empty() {
+        problems.push(format!("contains {}", describe_chars(&bad)));
+    }
+    if stem != stem.trim() {
+        problems.push("starts or ends with spaces".to_string());
+    }
+    if stem.ends_with('.') {
+        problems.push("ends with a dot".to_string());
+    }
+    if RESERVED.contains(&stem.to_lowercase().as_str()) {
+        problems.push("is a reserved name on Windows".to_string());
+    }
+    problems
+}
+
+/// The file name with the problems of [`stem_problems`] fixed
+///
+/// Problem characters become `_`, like files saved from the web UI.
+pub fn sanitize_stem(stem: &str) -> String {
+    let mut clean: String = stem
+        .trim()
+        .chars()
+        .map(|c| {
+            if PROBLEM_CHARS.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    while clean.ends_with('.') {
+        clean.pop();
+    }
+    if clean.is_empty() || RESERVED.contains(&clean.to_lowercase().as_str()) {
+        clean.push_str(" recipe");
+    }
+    clean
+}
+
+/// Up to three of `candidates` that look like `name`, best first, ignoring
+/// case, for "did you mean" hints
+pub fn closest_matches(name: &str, candidates: &[String]) -> Vec<String> {
+    let lower: Vec<String> = candidates.iter().map(|c| c.to_lowercase()).collect();
+    let possibilities: Vec<&str> = lower.iter().map(String::as_str).collect();
+    similar::get_close_matches(name.to_lowercase().as_str(), &possibilities, 3, 0.6)
+        .into_iter()
+        .filter_map(|found| possibilities.iter().position(|p| *p == found))
+        .map(|i| candidates[i].clone())
+        .collect()
+}
+
+fn describe_chars(chars: &str) -> String {
+    let mut seen = Vec::new();
+    for c in chars.chars() {
+        if !seen.contains(&c) {
+            seen.push(c);
+        }
+    }
+    seen.iter()
+        .map(|c| {
+            if c.is_control() {
+                format!("U+{:04X}", *c as u32)
+            } else {
+                format!("'{c}'")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Mac 'n' Cheese"), "mac-n-cheese");
+        assert_eq!(slug("mac-n-cheese"), "mac-n-cheese");
+        assert_eq!(slug("  Crème Brûlée! "), "crème-brûlée");
+    }
+
+    #[test]
+    fn test_stem_problems() {
+        assert!(stem_problems("Pancakes").is_empty());
+        assert_eq!(stem_problems("Fish: Baked?"), ["contains ':', '?'"]);
+        assert_eq!(stem_problems("con"), ["is a reserved name on Windows"]);
+        assert_eq!(stem_problems("Soup. "), ["starts or ends with spaces"]);
+    }
+
+    #[test]
+    fn test_closest_matches() {
+        let names = [
+            "Shared/Pizza Dough".to_string(),
+            "Shared/Red Beans".to_string(),
+            "Breakfast/Easy Pancakes".to_string(),
+        ];
+        assert_eq!(
+            closest_matches("shared/pizza dogh", &names)[0],
+            "Shared/Pizza Dough"
+        );
+        assert_eq!(
+            closest_matches("Breakfast/Easy Pancake", &names)[0],
+            "Breakfast/Easy Pancakes"
+        );
+        assert!(closest_matches("Lasagne", &names).is_empty());
+    }
+
+    #[test]
+    fn test_sanitize_stem() {
+        assert_eq!(sanitize_stem("Fish: Baked?"), "Fish_ Baked_");
+        assert_eq!(sanitize_stem(" Soup. "), "Soup");
+        assert_eq!(sanitize_stem("NUL"), "NUL recipe");
+        for stem in ["Fish: Baked?", " Soup. ", "NUL", "a#b%c"] {
+            assert!(stem_problems(&sanitize_stem(stem)).is_empty());
+        }
+    }
+}
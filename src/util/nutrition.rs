@@ -0,0 +1,746 @@
+//! Nutrition database and per-recipe nutrition analysis
+//!
+//! The database is a CSV file with values per 100 g. A small database of
+//! common ingredients is bundled; a `nutrition.csv` in the config directory
+//! adds to and overrides it, and can be generated from a USDA FoodData
+//! Central CSV download with [`import_fdc`].
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{
+    quantity::{Quantity, Value},
+    Content, Converter, Item, Recipe,
+};
+use cooklang_find::RecipeEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::ops::{Add, AddAssign};
+
+use super::{csv, pantry::Amount};
+
+const BUNDLED_DB: &str = include_str!("nutrition.csv");
+
+/// Calories (kcal) and macronutrients (g)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Nutrients {
+    pub calories: f64,
+    pub protein: f64,
+    pub fat: f64,
+    pub carbohydrates: f64,
+}
+
+impl Nutrients {
+    pub fn scale(self, factor: f64) -> Self {
+        Self {
+            calories: self.calories * factor,
+            protein: self.protein * factor,
+            fat: self.fat * factor,
+            carbohydrates: self.carbohydrates * factor,
+        }
+    }
+
+    fn field_mut(&mut self, name: &str) -> &mut f64 {
+        match name {
+            "calories" => &mut self.calories,
+            "protein" => &mut self.protein,
+            "fat" => &mut self.fat,
+            _ => &mut self.carbohydrates,
+        }
+    }
+}
+
+impl Add for Nutrients {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            calories: self.calories + other.calories,
+            protein: self.protein + other.protein,
+            fat: self.fat + other.fat,
+            carbohydrates: self.carbohydrates + other.carbohydrates,
+        }
+    }
+}
+
+impl AddAssign for Nutrients {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+/// A food in the nutrition database
+#[derive(Debug, Clone)]
+pub struct Food {
+    pub name: String,
+    pub per_100g: Nutrients,
+    /// Weight of one piece in grams, used for counts like `@eggs{3}`
+    pub unit_weight: Option<f64>,
+    /// Grams per millilitre, used for volume units
+    pub density: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct NutritionDb {
+    foods: Vec<Food>,
+    index: HashMap<String, usize>,
+}
+
+impl NutritionDb {
+    /// The database bundled with cook
+    pub fn bundled() -> Self {
+        Self::from_csv(BUNDLED_DB).expect("bundled nutrition database is valid")
+    }
+
+    /// The bundled database extended with a user supplied one, if given
+    pub fn load(path: Option<&Utf8Path>) -> Result<Self> {
+        let mut db = Self::bundled();
+        if let Some(path) = path {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read nutrition database at {path}"))?;
+            let user_db = Self::from_csv(&content)
+                .with_context(|| format!("Failed to parse nutrition database at {path}"))?;
+            db.extend(user_db);
+        }
+        Ok(db)
+    }
+
+    /// Parse a database with a header row containing at least `name` and
+    /// `calories`; `protein`, `fat`, `carbohydrates`, `unit_weight` and
+    /// `density` are optional. Lines starting with `#` are ignored.
+    pub fn from_csv(content: &str) -> Result<Self> {
+        let mut records = csv::records(content.as_bytes()).skip_comments();
+        let header = loop {
+            match records.next().transpose()? {
+                None => return Ok(Self::default()),
+                Some(record) if record.iter().all(|field| field.trim().is_empty()) => continue,
+                Some(record) => break record,
+            }
+        };
+        let column = |name: &str| header.iter().position(|h| h.trim() == name);
+        let Some(name_col) = column("name") else {
+            bail!("Missing 'name' column");
+        };
+        let Some(calories_col) = column("calories") else {
+            bail!("Missing 'calories' column");
+        };
+        let protein_col = column("protein");
+        let fat_col = column("fat");
+        let carbs_col = column("carbohydrates");
+        let unit_weight_col = column("unit_weight");
+        let density_col = column("density");
+
+        let mut db = Self::default();
+        while let Some(fields) = records.next().transpose()? {
+            let line_no = records.line();
+            let number = |col: Option<usize>| -> Result<Option<f64>> {
+                match col.and_then(|c| fields.get(c)).map(|f| f.trim()) {
+                    None | Some("") => Ok(None),
+                    Some(f) => f
+                        .parse()
+                        .map(Some)
+                        .with_context(|| format!("Invalid number '{f}' on line {line_no}")),
+                }
+            };
+
+            let name = fields.get(name_col).map(|n| n.trim()).unwrap_or_default();
+            if name.is_empty() {
+                continue;
+            }
+            db.insert(Food {
+                name: name.to_string(),
+                per_100g: Nutrients {
+                    calories: number(Some(calories_col))?.unwrap_or_default(),
+                    protein: number(protein_col)?.unwrap_or_default(),
+                    fat: number(fat_col)?.unwrap_or_default(),
+                    carbohydrates: number(carbs_col)?.unwrap_or_default(),
+                },
+                unit_weight: number(unit_weight_col)?,
+                density: number(density_col)?,
+            });
+        }
+
+        Ok(db)
+    }
+
+    /// Serialize the database in the format read by [`NutritionDb::from_csv`]
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("name,calories,protein,fat,carbohydrates,unit_weight,density\n");
+        for food in &self.foods {
+            let optional = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+            let name = if food.name.contains([',', '"']) {
+                format!("\"{}\"", food.name.replace('"', "\"\""))
+            } else {
+                food.name.clone()
+            };
+            writeln!(
+                out,
+                "{name},{},{},{},{},{},{}",
+                food.per_100g.calories,
+                food.per_100g.protein,
+                food.per_100g.fat,
+                food.per_100g.carbohydrates,
+                optional(food.unit_weight),
+                optional(food.density),
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.foods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.foods.is_empty()
+    }
+
+    /// Add a food, replacing any existing food with the same name
+    pub fn insert(&mut self, food: Food) {
+        let key = food.name.to_lowercase();
+        match self.index.get(&key) {
+            Some(&i) => self.foods[i] = food,
+            None => {
+                self.index.insert(key, self.foods.len());
+                self.foods.push(food);
+            }
+        }
+    }
+
+    pub fn extend(&mut self, other: NutritionDb) {
+        for food in other.foods {
+            self.insert(food);
+        }
+    }
+
+    /// Find the food for an ingredient name
+    ///
+    /// Tries an exact (case-insensitive) match, then the singular form, then
+    /// the first part of FoodData Central style names like
+    /// "Flour, wheat, all-purpose".
+    pub fn lookup(&self, name: &str) -> Option<&Food> {
+        let name = name.trim().to_lowercase();
+        let candidates = [
+            Some(name.as_str()),
+            name.strip_suffix("es"),
+            name.strip_suffix('s'),
+        ];
+
+        for candidate in candidates.into_iter().flatten() {
+            if let Some(&i) = self.index.get(candidate) {
+                return Some(&self.foods[i]);
+            }
+        }
+
+        for candidate in candidates.into_iter().flatten() {
+            if let Some(food) = self.foods.iter().find(|f| {
+                f.name
+                    .split(',')
+                    .next()
+                    .is_some_and(|first| first.trim().eq_ignore_ascii_case(candidate))
+            }) {
+                return Some(food);
+            }
+        }
+
+        None
+    }
+}
+
+/// FoodData Central nutrient ids
+const FDC_ENERGY: &str = "1008";
+const FDC_ENERGY_ATWATER: &str = "2047";
+const FDC_PROTEIN: &str = "1003";
+const FDC_FAT: &str = "1004";
+const FDC_CARBOHYDRATES: &str = "1005";
+
+/// Build a database from an extracted USDA FoodData Central CSV download
+/// (the directory containing `food.csv` and `food_nutrient.csv`)
+///
+/// Branded foods are skipped, they are rarely useful for recipes.
+pub fn import_fdc(dir: &Utf8Path) -> Result<NutritionDb> {
+    let open = |name: &str| {
+        let path = dir.join(name);
+        std::fs::File::open(&path)
+            .map(std::io::BufReader::new)
+            .with_context(|| format!("Failed to open {path}"))
+    };
+
+    let mut foods: HashMap<String, (String, Nutrients, bool)> = HashMap::new();
+
+    let mut records = csv::records(open("food.csv")?);
+    let header = records.next().context("food.csv is empty")??;
+    let col = |name: &str| {
+        header
+            .iter()
+            .position(|h| h == name)
+            .with_context(|| format!("Missing '{name}' column"))
+    };
+    let (id_col, type_col, desc_col) = (col("fdc_id")?, col("data_type")?, col("description")?);
+    for fields in records {
+        let fields = fields?;
+        let (Some(id), Some(data_type), Some(description)) = (
+            fields.get(id_col),
+            fields.get(type_col),
+            fields.get(desc_col),
+        ) else {
+            continue;
+        };
+        if data_type == "branded_food" {
+            continue;
+        }
+        foods.insert(
+            id.clone(),
+            (description.to_lowercase(), Nutrients::default(), false),
+        );
+    }
+
+    let mut records = csv::records(open("food_nutrient.csv")?);
+    let header = records.next().context("food_nutrient.csv is empty")??;
+    let col = |name: &str| {
+        header
+            .iter()
+            .position(|h| h == name)
+            .with_context(|| format!("Missing '{name}' column"))
+    };
+    let (id_col, nutrient_col, amount_col) = (col("fdc_id")?, col("nutrient_id")?, col("amount")?);
+    for fields in records {
+        let fields = fields?;
+        let Some((_, nutrients, has_energy)) = fields.get(id_col).and_then(|id| foods.get_mut(id))
+        else {
+            continue;
+        };
+        let Some(amount) = fields.get(amount_col).and_then(|a| a.parse::<f64>().ok()) else {
+            continue;
+        };
+        match fields.get(nutrient_col).map(String::as_str) {
+            Some(FDC_ENERGY) => {
+                nutrients.calories = amount;
+                *has_energy = true;
+            }
+            Some(FDC_ENERGY_ATWATER) if !*has_energy => {
+                nutrients.calories = amount;
+                *has_energy = true;
+            }
+            Some(FDC_PROTEIN) => nutrients.protein = amount,
+            Some(FDC_FAT) => nutrients.fat = amount,
+            Some(FDC_CARBOHYDRATES) => nutrients.carbohydrates = amount,
+            _ => {}
+        }
+    }
+
+    let mut foods: Vec<_> = foods
+        .into_values()
+        .filter(|(_, _, has_energy)| *has_energy)
+        .collect();
+    foods.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut db = NutritionDb::default();
+    for (name, per_100g, _) in foods {
+        db.insert(Food {
+            name,
+            per_100g,
+            unit_weight: None,
+            density: None,
+        });
+    }
+    Ok(db)
+}
+
+/// Nutrition of a single recipe ingredient
+#[derive(Debug, Clone, Serialize)]
+pub struct IngredientNutrition {
+    pub name: String,
+    /// Name of the matched food in the database
+    pub food: String,
+    pub grams: f64,
+    pub nutrients: Nutrients,
+}
+
+/// An ingredient that couldn't be included in the totals
+#[derive(Debug, Clone, Serialize)]
+pub struct Unmatched {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NutritionReport {
+    pub servings: Option<f64>,
+    pub total: Nutrients,
+    pub per_serving: Option<Nutrients>,
+    pub ingredients: Vec<IngredientNutrition>,
+    pub unmatched: Vec<Unmatched>,
+    /// Whether values from the recipe's `nutrition` metadata replaced
+    /// calculated ones
+    pub overridden: bool,
+}
+
+/// Calculate the nutrition of a recipe
+///
+/// The recipe should be parsed unscaled; totals are multiplied by `scale`
+/// while per serving values use the servings from the recipe metadata.
+///
+/// Values in the recipe's `nutrition` metadata, like `nutrition: {calories:
+/// 350}`, are per serving, or for the whole recipe without servings, and
+/// replace the calculated ones.
+pub fn analyze(
+    recipe: &Recipe,
+    db: &NutritionDb,
+    converter: &Converter,
+    scale: f64,
+) -> NutritionReport {
+    let mut total = Nutrients::default();
+    let mut ingredients = Vec::new();
+    let mut unmatched = Vec::new();
+
+    for entry in recipe.group_ingredients(converter) {
+        let igr = entry.ingredient;
+        let name = igr.display_name().to_string();
+
+        if igr.reference.is_some() {
+            unmatched.push(Unmatched {
+                name,
+                reason: "recipe references are not included".to_string(),
+            });
+            continue;
+        }
+
+        match ingredient_nutrition(name, &igr.name, entry.quantity.iter(), db, converter, scale) {
+            Ok(nutrition) => {
+                total += nutrition.nutrients;
+                ingredients.push(nutrition);
+            }
+            Err(u) => unmatched.push(u),
+        }
+    }
+
+    let servings = servings(recipe);
+    let mut per_serving = servings.map(|s| total.scale(1.0 / (s * scale)));
+
+    let overrides = overrides(recipe);
+    for &(field, value) in &overrides {
+        match (per_serving.as_mut(), servings) {
+            (Some(per_serving), Some(servings)) => {
+                *per_serving.field_mut(field) = value;
+                *total.field_mut(field) = value * servings * scale;
+            }
+            _ => *total.field_mut(field) = value * scale,
+        }
+    }
+
+    NutritionReport {
+        servings,
+        total,
+        per_serving,
+        ingredients,
+        unmatched,
+        overridden: !overrides.is_empty(),
+    }
+}
+
+/// The values set in the recipe's `nutrition` metadata, numbers or text
+/// like "350 kcal"
+fn overrides(recipe: &Recipe) -> Vec<(&'static str, f64)> {
+    let Some(nutrition) = recipe.metadata.get("nutrition") else {
+        return Vec::new();
+    };
+    ["calories", "protein", "fat", "carbohydrates"]
+        .into_iter()
+        .filter_map(|field| {
+            let value = match nutrition.get(field)? {
+                serde_yaml::Value::Number(n) => n.as_f64(),
+                serde_yaml::Value::String(s) => Amount::parse(s).map(|a| a.value),
+                _ => None,
+            }?;
+            Some((field, value))
+        })
+        .collect()
+}
+
+/// How many the recipe serves, from its metadata
+pub fn servings(recipe: &Recipe) -> Option<f64> {
+    recipe
+        .metadata
+        .servings()
+        .and_then(|s| Amount::parse(&s.to_string()))
+        .map(|a| a.value)
+        .filter(|s| *s > 0.0)
+}
+
+fn ingredient_nutrition<'a>(
+    name: String,
+    lookup_name: &str,
+    quantities: impl Iterator<Item = &'a Quantity>,
+    db: &NutritionDb,
+    converter: &Converter,
+    scale: f64,
+) -> Result<IngredientNutrition, Unmatched> {
+    let unmatched = |name: String, reason: String| Unmatched { name, reason };
+
+    let Some(food) = db.lookup(lookup_name) else {
+        return Err(unmatched(name, "not in nutrition database".to_string()));
+    };
+
+    let mut grams = 0.0;
+    let mut any = false;
+    for qty in quantities {
+        any = true;
+        let Value::Number(n) = qty.value() else {
+            return Err(unmatched(name, "quantity is not a number".to_string()));
+        };
+        let amount = Amount::new(n.value(), qty.unit().map(|u| u.to_string()));
+        match to_grams(&amount, lookup_name, food, converter) {
+            Some(g) => grams += g,
+            None => return Err(unmatched(name, format!("can't convert {amount} to grams"))),
+        }
+    }
+    if !any {
+        return Err(unmatched(name, "no quantity".to_string()));
+    }
+
+    let grams = grams * scale;
+    Ok(IngredientNutrition {
+        name,
+        food: food.name.clone(),
+        grams,
+        nutrients: food.per_100g.scale(grams / 100.0),
+    })
+}
+
+/// Calories per serving of a recipe entry, or of the whole recipe when it
+/// doesn't say how many it serves
+///
+/// None when the recipe can't be parsed or none of its ingredients are in
+/// the database. Ingredients that aren't are left out, so the calories can
+/// be low.
+pub fn recipe_calories(entry: &RecipeEntry, db: &NutritionDb) -> Option<f64> {
+    let recipe = super::parse_recipe_from_entry(entry, 1.0).ok()?;
+    let report = analyze(&recipe, db, super::PARSER.converter(), 1.0);
+    if report.ingredients.is_empty() && !report.overridden {
+        return None;
+    }
+    Some(report.per_serving.unwrap_or(report.total).calories)
+}
+
+/// Nutrition of one section (usually a day) of a menu
+#[derive(Debug, Clone, Serialize)]
+pub struct DayNutrition {
+    pub name: String,
+    pub total: Nutrients,
+    /// Totals divided by the menu's servings
+    pub per_serving: Option<Nutrients>,
+    /// Relative difference of per serving (or total) calories from the target
+    pub calorie_deviation: Option<f64>,
+    pub off_target: bool,
+    pub unmatched: Vec<Unmatched>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MenuNutritionReport {
+    pub servings: Option<f64>,
+    pub target_calories: Option<f64>,
+    pub days: Vec<DayNutrition>,
+}
+
+/// Calculate daily nutrition totals for a menu (meal plan)
+///
+/// Each section of the menu (e.g. `== Day 1 ==`) is one day. Referenced
+/// recipes are analyzed at the scale given in the menu, plain ingredients
+/// are looked up directly. When `target_calories` is given, days whose per
+/// serving calories deviate more than `tolerance` (a fraction) are flagged.
+pub fn analyze_menu(
+    menu: &Recipe,
+    base_path: &Utf8PathBuf,
+    db: &NutritionDb,
+    converter: &Converter,
+    scale: f64,
+    target_calories: Option<f64>,
+    tolerance: f64,
+) -> MenuNutritionReport {
+    let menu_servings = servings(menu);
+    let mut days = Vec::new();
+
+    for (i, section) in menu.sections.iter().enumerate() {
+        let mut total = Nutrients::default();
+        let mut unmatched = Vec::new();
+        let mut has_items = false;
+
+        for content in &section.content {
+            let Content::Step(step) = content else {
+                continue;
+            };
+            for item in &step.items {
+                let Item::Ingredient { index } = item else {
+                    continue;
+                };
+                let Some(igr) = menu.ingredients.get(*index) else {
+                    continue;
+                };
+                has_items = true;
+
+                let Some(reference) = &igr.reference else {
+                    let name = igr.display_name().to_string();
+                    match ingredient_nutrition(
+                        name,
+                        &igr.name,
+                        igr.quantity.iter(),
+                        db,
+                        converter,
+                        scale,
+                    ) {
+                        Ok(nutrition) => total += nutrition.nutrients,
+                        Err(u) => unmatched.push(u),
+                    }
+                    continue;
+                };
+
+                let path = if reference.components.is_empty() {
+                    reference.name.clone()
+                } else {
+                    format!("{}/{}", reference.components.join("/"), reference.name)
+                };
+                match referenced_recipe_nutrition(
+                    &path,
+                    igr.quantity.as_ref(),
+                    base_path,
+                    db,
+                    converter,
+                ) {
+                    Ok(report) => {
+                        total += report.total.scale(scale);
+                        unmatched.extend(report.unmatched.into_iter().map(|u| Unmatched {
+                            name: format!("{} ({path})", u.name),
+                            reason: u.reason,
+                        }));
+                    }
+                    Err(e) => unmatched.push(Unmatched {
+                        name: path,
+                        reason: format!("{e:#}"),
+                    }),
+                }
+            }
+        }
+
+        if !has_items {
+            continue;
+        }
+
+        let per_serving = menu_servings.map(|s| total.scale(1.0 / (s * scale)));
+        let calorie_deviation = target_calories.filter(|t| *t > 0.0).map(|target| {
+            let calories = per_serving.unwrap_or(total).calories;
+            (calories - target) / target
+        });
+
+        days.push(DayNutrition {
+            name: section
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Section {}", i + 1)),
+            total,
+            per_serving,
+            calorie_deviation,
+            off_target: calorie_deviation.is_some_and(|d| d.abs() > tolerance),
+            unmatched,
+        });
+    }
+
+    MenuNutritionReport {
+        servings: menu_servings,
+        target_calories,
+        days,
+    }
+}
+
+/// Analyze a recipe referenced from a menu, scaled by the reference quantity
+///
+/// `{2}` scales the recipe by 2, `{4%servings}` scales it to 4 servings.
+fn referenced_recipe_nutrition(
+    path: &str,
+    quantity: Option<&Quantity>,
+    base_path: &Utf8PathBuf,
+    db: &NutritionDb,
+    converter: &Converter,
+) -> anyhow::Result<NutritionReport> {
+    let entry = super::get_recipe(base_path, path)?;
+    let recipe = super::parse_recipe_from_entry(&entry, 1.0)?;
+
+    let scale = match quantity.map(|q| (q.value(), q.unit())) {
+        Some((Value::Number(n), Some(unit))) if unit.starts_with("serving") => {
+            servings(&recipe).map_or(n.value(), |s| n.value() / s)
+        }
+        Some((Value::Number(n), _)) => n.value(),
+        _ => 1.0,
+    };
+
+    Ok(analyze(&recipe, db, converter, scale))
+}
+
+/// Convert an ingredient amount to grams
+///
+/// Mass units are converted directly, volume units through the food's density,
+/// the ingredient's density from `units.toml` or water's, and counts or unknown units like "cloves" through the
+/// weight of one piece.
+fn to_grams(amount: &Amount, ingredient: &str, food: &Food, converter: &Converter) -> Option<f64> {
+    if let Some(grams) = amount.value_in(Some("g"), converter) {
+        return Some(grams);
+    }
+    if let Some(ml) = amount.value_in(Some("ml"), converter) {
+        let density = food.density.or_else(|| super::units::density(ingredient));
+        return Some(ml * density.unwrap_or(1.0));
+    }
+    food.unit_weight.map(|w| amount.value * w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PARSER;
+
+    #[test]
+    fn test_bundled_db_parses() {
+        let db = NutritionDb::bundled();
+        assert!(db.len() > 50);
+        assert_eq!(db.lookup("Eggs").unwrap().name, "egg");
+        assert_eq!(db.lookup("tomatoes").unwrap().name, "tomato");
+    }
+
+    #[test]
+    fn test_lookup_fdc_style_names() {
+        let db = NutritionDb::from_csv(
+            "name,calories\n\"flour, wheat, all-purpose\",364\n\"butter, salted\",717\n",
+        )
+        .unwrap();
+        assert_eq!(db.lookup("flour").unwrap().per_100g.calories, 364.0);
+        assert!(db.lookup("sugar").is_none());
+    }
+
+    #[test]
+    fn test_metadata_overrides() {
+        let db = NutritionDb::bundled();
+        let recipe = PARSER
+            .parse(
+                "---\nservings: 2\nnutrition:\n  calories: 350\n  protein: 12 g\n---\n\n\
+                 Whisk @eggs{2} and @flour{100%g}.\n",
+            )
+            .into_result()
+            .unwrap()
+            .0;
+        let report = analyze(&recipe, &db, PARSER.converter(), 2.0);
+        assert!(report.overridden);
+        let per_serving = report.per_serving.unwrap();
+        assert_eq!(per_serving.calories, 350.0);
+        assert_eq!(per_serving.protein, 12.0);
+        assert_eq!(report.total.calories, 1400.0);
+        // Values not set are still calculated
+        assert!(per_serving.fat > 0.0);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let db = NutritionDb::from_csv("name,calories,density\n\"cheese, feta\",264,\n").unwrap();
+        let db = NutritionDb::from_csv(&db.to_csv()).unwrap();
+        assert_eq!(db.lookup("cheese, feta").unwrap().per_100g.calories, 264.0);
+    }
+}
@@ -0,0 +1,587 @@
+//! Shared helpers for reading, writing and updating `pantry.conf`
+//!
+//! Both the `cook pantry` command and the web server modify the pantry file,
+//! so loading, serialization and quantity arithmetic live here.
+
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use chrono::NaiveDate;
+use cooklang::{
+    ingredient_list::IngredientList,
+    pantry::{ItemWithAttributes, PantryConf, PantryItem},
+    quantity::Value,
+    Converter,
+};
+use serde::Serialize;
+use std::fmt::Write;
+use tracing::warn;
+
+use super::{aliases, units};
+
+/// Read and parse a pantry file, logging any configuration warnings
+pub fn load_pantry(path: &Utf8Path) -> Result<PantryConf> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read pantry file at {path}"))?;
+
+    let result = cooklang::pantry::parse_lenient(&content);
+    if result.report().has_warnings() {
+        for warning in result.report().warnings() {
+            warn!("Pantry configuration warning: {}", warning);
+        }
+    }
+
+    let mut pantry_conf = result.output().cloned().ok_or_else(|| {
+        anyhow::anyhow!(
+            "Failed to parse pantry configuration: {:?}",
+            result.report()
+        )
+    })?;
+    pantry_conf.rebuild_index();
+    Ok(pantry_conf)
+}
+
+/// Serialize and write a pantry configuration back to disk
+pub fn save_pantry(path: &Utf8Path, pantry_conf: &PantryConf) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {parent}"))?;
+    }
+    std::fs::write(path, serialize_pantry(pantry_conf))
+        .with_context(|| format!("Failed to write pantry file at {path}"))
+}
+
+/// Serialize PantryConf to regular TOML format (not array format)
+pub fn serialize_pantry(pantry_conf: &PantryConf) -> String {
+    let mut output = String::new();
+
+    // First, handle any top-level items (if they exist)
+    if let Some(general_items) = pantry_conf.sections.get("general") {
+        for item in general_items {
+            write_pantry_item(&mut output, item);
+        }
+        if !general_items.is_empty() {
+            writeln!(&mut output).unwrap();
+        }
+    }
+
+    // Then handle all other sections in alphabetical order
+    for (section_name, items) in &pantry_conf.sections {
+        if section_name == "general" {
+            continue; // Already handled
+        }
+
+        writeln!(&mut output, "[{}]", toml_escape_key(section_name)).unwrap();
+
+        for item in items {
+            write_pantry_item(&mut output, item);
+        }
+
+        writeln!(&mut output).unwrap();
+    }
+
+    output
+}
+
+fn write_pantry_item(output: &mut String, item: &PantryItem) {
+    match item {
+        PantryItem::Simple(name) => {
+            writeln!(output, "{} = true", toml_escape_key(name)).unwrap();
+        }
+        PantryItem::WithAttributes(attrs) => {
+            let has_extra = attrs.bought.is_some() || attrs.expire.is_some() || attrs.low.is_some();
+
+            // Build the value string
+            let value = if !has_extra {
+                // Only a quantity (if any), use the short form
+                attrs
+                    .quantity
+                    .as_deref()
+                    .map(toml_string)
+                    .unwrap_or_else(|| "true".to_string())
+            } else {
+                // Otherwise use an inline table so no attribute gets lost
+                let mut parts = Vec::new();
+                if let Some(qty) = &attrs.quantity {
+                    parts.push(format!("quantity = {}", toml_string(qty)));
+                }
+                if let Some(bought) = &attrs.bought {
+                    parts.push(format!("bought = {}", toml_string(bought)));
+                }
+                if let Some(expire) = &attrs.expire {
+                    parts.push(format!("expire = {}", toml_string(expire)));
+                }
+                if let Some(low) = &attrs.low {
+                    parts.push(format!("low = {}", toml_string(low)));
+                }
+                format!("{{ {} }}", parts.join(", "))
+            };
+
+            writeln!(output, "{} = {}", toml_escape_key(&attrs.name), value).unwrap();
+        }
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04X}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Keys and section names stay bare only if TOML allows it, everything
+/// else (spaces, dots, `crème_fraîche`) gets quoted
+fn toml_escape_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        toml_string(key)
+    }
+}
+
+/// Find an item by name (case-insensitive) in any section
+///
+/// An alias from `[aliases]` finds the item under its name, and the other
+/// way around.
+pub fn find_item_mut<'a>(
+    pantry_conf: &'a mut PantryConf,
+    name: &str,
+) -> Option<(String, &'a mut PantryItem)> {
+    pantry_conf
+        .sections
+        .iter_mut()
+        .find_map(|(section, items)| {
+            items
+                .iter_mut()
+                .find(|item| aliases::same_ingredient(item.name(), name))
+                .map(|item| (section.clone(), item))
+        })
+}
+
+/// Rename items that are an alias from `[aliases]` to the name used
+/// instead, so they match the ingredients of a shopping list
+///
+/// Only for reading, the renamed items shouldn't be saved.
+pub fn apply_aliases(pantry_conf: &mut PantryConf) {
+    let mut renamed = false;
+    for items in pantry_conf.sections.values_mut() {
+        for item in items.iter_mut() {
+            if let Some(name) = aliases::alias_of(item.name()) {
+                let mut attrs = item_attributes(item);
+                attrs.name = name.to_string();
+                *item = item_from_attributes(attrs);
+                renamed = true;
+            }
+        }
+    }
+    if renamed {
+        pantry_conf.rebuild_index();
+    }
+}
+
+/// Remove an item by name (case-insensitive) from any section, dropping the
+/// section if it ends up empty
+pub fn remove_item(pantry_conf: &mut PantryConf, name: &str) -> bool {
+    let mut emptied = Vec::new();
+    let mut removed = false;
+    for (section, items) in pantry_conf.sections.iter_mut() {
+        let before = items.len();
+        items.retain(|item| !item.name().eq_ignore_ascii_case(name));
+        if items.len() != before {
+            removed = true;
+            if items.is_empty() {
+                emptied.push(section.clone());
+            }
+        }
+    }
+    for section in emptied {
+        pantry_conf.sections.remove(&section);
+    }
+    removed
+}
+
+/// Get the attributes of an item, converting simple items
+pub fn item_attributes(item: &PantryItem) -> ItemWithAttributes {
+    match item {
+        PantryItem::Simple(name) => ItemWithAttributes {
+            name: name.clone(),
+            quantity: None,
+            bought: None,
+            expire: None,
+            low: None,
+        },
+        PantryItem::WithAttributes(attrs) => attrs.clone(),
+    }
+}
+
+/// Build an item from attributes, using the simple form when possible
+pub fn item_from_attributes(attrs: ItemWithAttributes) -> PantryItem {
+    if attrs.quantity.is_none()
+        && attrs.bought.is_none()
+        && attrs.expire.is_none()
+        && attrs.low.is_none()
+    {
+        PantryItem::Simple(attrs.name)
+    } else {
+        PantryItem::WithAttributes(attrs)
+    }
+}
+
+/// Parse a date as written in pantry files, trying several common formats
+pub fn parse_date(date_str: &str) -> Option<NaiveDate> {
+    // Try multiple date formats
+    let formats = [
+        "%Y-%m-%d", "%d.%m.%Y", "%d/%m/%Y", "%m/%d/%Y", "%Y.%m.%d", "%d-%m-%Y",
+    ];
+
+    for format in &formats {
+        if let Ok(date) = NaiveDate::parse_from_str(date_str.trim(), format) {
+            return Some(date);
+        }
+    }
+
+    None
+}
+
+/// Parse an expiry window like `7d`, `2w` or `10` (days) into a number of days
+pub fn parse_within(s: &str) -> Result<u32, String> {
+    let s = s.trim();
+    let (number, multiplier) = if let Some(n) = s.strip_suffix('d') {
+        (n, 1)
+    } else if let Some(n) = s.strip_suffix('w') {
+        (n, 7)
+    } else {
+        (s, 1)
+    };
+
+    number
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid duration '{s}', expected e.g. 7d, 2w or 10"))
+}
+
+/// The last day of an expiry window, `None` when it's past the dates that
+/// can be represented
+pub fn expiry_threshold(today: NaiveDate, days: u32) -> Option<NaiveDate> {
+    today.checked_add_signed(chrono::Duration::days(days.into()))
+}
+
+/// A pantry item that is expired or expires soon
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpiringItem {
+    pub name: String,
+    pub section: String,
+    pub expire_date: Option<String>,
+    pub days_until_expiry: Option<i64>,
+    pub status: String,
+}
+
+/// Collect items expiring within `days` of `today` (including already expired
+/// ones), sorted by how soon they expire
+///
+/// With `include_unknown`, items without a (parseable) expiry date are
+/// included at the end.
+pub fn expiring_items(
+    pantry_conf: &PantryConf,
+    today: NaiveDate,
+    days: u32,
+    include_unknown: bool,
+) -> Vec<ExpiringItem> {
+    let threshold_date = expiry_threshold(today, days).unwrap_or(NaiveDate::MAX);
+
+    let mut expiring_list = Vec::new();
+
+    for (section, items) in &pantry_conf.sections {
+        for item in items {
+            let expire_date = item.expire().and_then(parse_date);
+
+            if let Some(date) = expire_date {
+                if date <= threshold_date {
+                    let days_until = (date - today).num_days();
+                    let status = if days_until < 0 {
+                        format!("EXPIRED {} days ago", -days_until)
+                    } else if days_until == 0 {
+                        "EXPIRES TODAY".to_string()
+                    } else if days_until == 1 {
+                        "expires tomorrow".to_string()
+                    } else {
+                        format!("expires in {days_until} days")
+                    };
+
+                    expiring_list.push(ExpiringItem {
+                        name: item.name().to_string(),
+                        section: section.clone(),
+                        expire_date: Some(date.format("%Y-%m-%d").to_string()),
+                        days_until_expiry: Some(days_until),
+                        status,
+                    });
+                }
+            } else if include_unknown {
+                expiring_list.push(ExpiringItem {
+                    name: item.name().to_string(),
+                    section: section.clone(),
+                    expire_date: None,
+                    days_until_expiry: None,
+                    status: "No expiry date".to_string(),
+                });
+            }
+        }
+    }
+
+    // Sort by days until expiry
+    expiring_list.sort_by_key(|item| item.days_until_expiry.unwrap_or(i64::MAX));
+    expiring_list
+}
+
+/// How a single ingredient changed the pantry when depleting it
+#[derive(Debug, Clone, Serialize)]
+pub struct Depletion {
+    pub ingredient: String,
+    pub used: Vec<String>,
+    pub remaining: Option<String>,
+    /// Set when (part of) the quantity could not be subtracted
+    pub note: Option<String>,
+}
+
+/// Subtract the ingredients in `list` from the pantry
+///
+/// Only items that are tracked in the pantry with a numeric quantity are
+/// updated; quantities are converted to the pantry item's unit when possible,
+/// through the ingredient's density from `units.toml` if needed, and never go
+/// below zero.
+pub fn deplete_pantry(
+    pantry_conf: &mut PantryConf,
+    list: IngredientList,
+    converter: &Converter,
+) -> Vec<Depletion> {
+    let mut depletions = Vec::new();
+
+    for (ingredient, quantity) in list {
+        let Some((_, item)) = find_item_mut(pantry_conf, &ingredient) else {
+            continue;
+        };
+        let mut attrs = item_attributes(item);
+        let Some(mut current) = attrs.quantity.as_deref().and_then(Amount::parse) else {
+            continue;
+        };
+
+        let mut used = Vec::new();
+        let mut note = None;
+        for qty in quantity.iter() {
+            let Value::Number(n) = qty.value() else {
+                note = Some("quantity is not a number".to_string());
+                continue;
+            };
+            let amount = Amount::new(n.value(), qty.unit().map(|u| u.to_string()));
+            match current.try_sub(&amount, &ingredient, converter) {
+                Some(remaining) => {
+                    used.push(amount.to_string());
+                    current = remaining;
+                }
+                None => note = Some(format!("cannot subtract {amount} from {current}")),
+            }
+        }
+
+        if !used.is_empty() {
+            attrs.quantity = Some(current.to_pantry_string());
+            *item = item_from_attributes(attrs);
+        }
+
+        depletions.push(Depletion {
+            ingredient,
+            remaining: (!used.is_empty()).then(|| current.to_string()),
+            used,
+            note,
+        });
+    }
+
+    pantry_conf.rebuild_index();
+    depletions
+}
+
+/// A numeric pantry amount like `2%kg`, `500 ml` or `12`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Amount {
+    pub value: f64,
+    pub unit: Option<String>,
+}
+
+impl Amount {
+    pub fn new(value: f64, unit: Option<String>) -> Self {
+        Self { value, unit }
+    }
+
+    /// Parse an amount in pantry (`2%kg`) or command line (`2kg`, `2 kg`) notation
+    pub fn parse(s: &str) -> Option<Self> {
+        static RE: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| {
+            regex::Regex::new(r"^\s*(\d+(?:[.,]\d+)?(?:/\d+)?)\s*%?\s*(.*?)\s*$").unwrap()
+        });
+
+        let captures = RE.captures(s)?;
+        let number = captures.get(1)?.as_str().replace(',', ".");
+        let value = match number.split_once('/') {
+            Some((num, den)) => {
+                let den = den.parse::<f64>().ok()?;
+                if den == 0.0 {
+                    return None;
+                }
+                num.parse::<f64>().ok()? / den
+            }
+            None => number.parse::<f64>().ok()?,
+        };
+        let unit = captures
+            .get(2)
+            .map(|m| m.as_str())
+            .filter(|u| !u.is_empty())
+            .map(|u| u.to_string());
+
+        Some(Self { value, unit })
+    }
+
+    /// Convert this amount to `unit`, returning `None` if the units are
+    /// incompatible or unknown to the converter
+    pub fn value_in(&self, unit: Option<&str>, converter: &Converter) -> Option<f64> {
+        match (self.unit.as_deref(), unit) {
+            (None, None) => Some(self.value),
+            (Some(from), Some(to)) if from.eq_ignore_ascii_case(to) => Some(self.value),
+            (Some(from), Some(to)) => {
+                let from = converter.find_unit(from)?;
+                let to = converter.find_unit(to)?;
+                if from.physical_quantity != to.physical_quantity {
+                    return None;
+                }
+                let base = self.value * from.ratio + from.difference;
+                Some((base - to.difference) / to.ratio)
+            }
+            _ => None,
+        }
+    }
+
+    /// Add `other` to this amount of `ingredient`, keeping this amount's unit
+    pub fn try_add(
+        &self,
+        other: &Amount,
+        ingredient: &str,
+        converter: &Converter,
+    ) -> Option<Amount> {
+        let other = units::convert(other, self.unit.as_deref(), ingredient, converter)?;
+        Some(Amount::new(self.value + other, self.unit.clone()))
+    }
+
+    /// Subtract `other` from this amount of `ingredient`, keeping this
+    /// amount's unit and never going below zero
+    pub fn try_sub(
+        &self,
+        other: &Amount,
+        ingredient: &str,
+        converter: &Converter,
+    ) -> Option<Amount> {
+        let other = units::convert(other, self.unit.as_deref(), ingredient, converter)?;
+        Some(Amount::new(
+            (self.value - other).max(0.0),
+            self.unit.clone(),
+        ))
+    }
+
+    /// Format the amount in pantry file notation (`2%kg`)
+    pub fn to_pantry_string(&self) -> String {
+        let value = crate::util::format::format_decimal(self.value);
+        match &self.unit {
+            Some(unit) => format!("{value}%{unit}"),
+            None => value,
+        }
+    }
+}
+
+impl std::fmt::Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = crate::util::format::format_decimal(self.value);
+        match &self.unit {
+            Some(unit) => write!(f, "{value} {unit}"),
+            None => write!(f, "{value}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(
+            Amount::parse("2kg"),
+            Some(Amount::new(2.0, Some("kg".to_string())))
+        );
+        assert_eq!(
+            Amount::parse("500%ml"),
+            Some(Amount::new(500.0, Some("ml".to_string())))
+        );
+        assert_eq!(Amount::parse("1/2 cup").map(|a| a.value), Some(0.5));
+        assert_eq!(Amount::parse("12"), Some(Amount::new(12.0, None)));
+        assert_eq!(Amount::parse("always available"), None);
+    }
+
+    #[test]
+    fn test_amount_to_pantry_string() {
+        assert_eq!(
+            Amount::new(2.5, Some("kg".into())).to_pantry_string(),
+            "2.5%kg"
+        );
+        assert_eq!(Amount::new(12.0, None).to_pantry_string(), "12");
+    }
+
+    #[test]
+    fn test_toml_escape_key() {
+        assert_eq!(toml_escape_key("olive_oil-2"), "olive_oil-2");
+        assert_eq!(toml_escape_key("crème_fraîche"), "\"crème_fraîche\"");
+        for name in [
+            "crème_fraîche",
+            "olive oil",
+            "a.b",
+            "[x]",
+            "back\\slash",
+            "tab\there",
+            "",
+        ] {
+            let line = format!("{} = {}", toml_escape_key(name), toml_string(name));
+            let table: toml::Table = toml::from_str(&line).expect(&line);
+            assert_eq!(table[name].as_str(), Some(name), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_parse_within() {
+        assert_eq!(parse_within("7d"), Ok(7));
+        assert_eq!(parse_within("2w"), Ok(14));
+        assert_eq!(parse_within("10"), Ok(10));
+        assert!(parse_within("soon").is_err());
+        assert!(parse_within("999999999w").is_err());
+    }
+
+    #[test]
+    fn test_expiry_threshold() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(
+            expiry_threshold(today, 7),
+            NaiveDate::from_ymd_opt(2024, 1, 8)
+        );
+        assert_eq!(expiry_threshold(today, 99999999 * 7), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! Pick a random recipe from the collection, used by `cook random` and
+//! `GET /api/v1/random`.
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use cooklang_find::RecipeEntry;
+use rand::seq::SliceRandom;
+use tracing::warn;
+
+use super::{metadata_cache::collection_metadata, tree_entries, walk::build_tree};
+
+/// Filters a random pick has to match
+#[derive(Debug, Default, Clone)]
+pub struct RandomFilter {
+    /// Tags the recipe must all have (case insensitive)
+    pub tags: Vec<String>,
+    /// Maximum total time in minutes. Recipes without a time don't match.
+    pub max_time: Option<u32>,
+}
+
+impl RandomFilter {
+    fn matches_tags(&self, entry: &RecipeEntry) -> bool {
+        if entry.is_menu() {
+            return false;
+        }
+        let tags = entry.tags();
+        self.tags
+            .iter()
+            .all(|wanted| tags.iter().any(|t| t.eq_ignore_ascii_case(wanted)))
+    }
+}
+
+/// Parse a duration like "1h", "45m", "1h 30m" or a plain number of minutes
+pub fn parse_minutes(s: &str) -> Result<u32> {
+    let s = s.trim();
+    if let Ok(minutes) = s.parse::<u32>() {
+        return Ok(minutes);
+    }
+    let duration = humantime::parse_duration(s)
+        .with_context(|| format!("Invalid duration '{s}', expected something like 45m or 1h"))?;
+    if duration.as_secs() % 60 != 0 {
+        bail!("Invalid duration '{s}', use whole minutes");
+    }
+    Ok((duration.as_secs() / 60) as u32)
+}
+
+/// All recipes under `base_path` matching the filter
+///
+/// Times come from the metadata cache, so only recipes that changed since
+/// the last time are parsed.
+pub fn matching_recipes(base_path: &Utf8Path, filter: &RandomFilter) -> Result<Vec<RecipeEntry>> {
+    let tree = build_tree(base_path)?;
+    let recipes: Vec<RecipeEntry> = tree_entries(&tree)
+        .into_iter()
+        .filter(|entry| filter.matches_tags(entry))
+        .collect();
+
+    let Some(max_time) = filter.max_time else {
+        return Ok(recipes);
+    };
+    let recipes = collection_metadata(base_path, recipes)
+        .into_iter()
+        .filter(|(entry, metadata)| {
+            if let Some(e) = &metadata.error {
+                let name = entry.name().as_deref().unwrap_or("unknown");
+                warn!("Failed to parse recipe '{name}': {e}");
+            }
+            metadata.total_time.is_some_and(|time| time <= max_time)
+        })
+        .map(|(entry, _)| entry)
+        .collect();
+    Ok(recipes)
+}
+
+/// Pick a random recipe matching the filter, `None` if nothing matches
+pub fn pick_random(base_path: &Utf8Path, filter: &RandomFilter) -> Result<Option<RecipeEntry>> {
+    let recipes = matching_recipes(base_path, filter)?;
+    Ok(recipes.choose(&mut rand::thread_rng()).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_minutes() {
+        assert_eq!(parse_minutes("30").unwrap(), 30);
+        assert_eq!(parse_minutes("45m").unwrap(), 45);
+        assert_eq!(parse_minutes("1h").unwrap(), 60);
+        assert_eq!(parse_minutes("1h 30m").unwrap(), 90);
+        assert!(parse_minutes("soon").is_err());
+        assert!(parse_minutes("90s").is_err());
+    }
+}
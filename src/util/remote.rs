@@ -0,0 +1,205 @@
+//! Collections stored somewhere other than a local directory
+//!
+//! Recipes are always read from a local copy in a cache directory, so the
+//! rest of CookCLI doesn't need to know where they come from. A store
+//! brings the copy up to date and, when it can, uploads files changed in it.
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::process::Command;
+
+use crate::{global_cache_dir, seed};
+
+/// Marks a directory as holding the copy of a remote collection, hidden so
+/// pulls keep it
+const CACHE_MARKER: &str = ".cook-remote";
+
+pub trait RemoteStore: Send + Sync {
+    /// Bring the copy in `cache` up to date
+    fn pull(&self, cache: &Utf8Path) -> Result<()>;
+
+    /// Upload `path`, relative to `cache`, after it changed
+    fn push(&self, cache: &Utf8Path, path: &Utf8Path) -> Result<()>;
+
+    /// Whether `push` can work
+    fn writable(&self) -> bool;
+}
+
+/// The store for a URL: `s3://BUCKET[/PREFIX]` or the `http(s)://` URL of
+/// a `.zip` archive
+pub fn open(url: &str, write: bool) -> Result<Box<dyn RemoteStore>> {
+    if url.starts_with("s3://") {
+        Ok(Box::new(S3Store {
+            url: url.trim_end_matches('/').to_string(),
+            write,
+        }))
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        if !url.ends_with(".zip") {
+            bail!("Only .zip archives can be read over HTTP, {url} isn't one");
+        }
+        if write {
+            bail!("A collection in an archive can't be written, leave out --remote-write");
+        }
+        Ok(Box::new(ArchiveStore {
+            url: url.to_string(),
+        }))
+    } else {
+        bail!(
+            "Unknown remote collection {url}\n\
+             Expected s3://BUCKET[/PREFIX] or the http(s) URL of a .zip archive"
+        )
+    }
+}
+
+/// Cache directory for the copy of a remote collection, one per URL
+pub fn default_cache_dir(url: &str) -> Result<Utf8PathBuf> {
+    let cache =
+        global_cache_dir().context("Could not determine the cache directory, pass --cache-dir")?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    url.hash(&mut hasher);
+    Ok(cache
+        .join("remote")
+        .join(format!("{:016x}", hasher.finish())))
+}
+
+/// Make `dir` ready to hold the copy of a remote collection
+///
+/// Pulls remove the files that aren't in the remote, so a directory given
+/// by the user has to be empty, or hold a copy already. `ours` is for cook's
+/// own cache directories, which are used as they are.
+pub fn prepare_cache(dir: &Utf8Path, ours: bool) -> Result<()> {
+    let marker = dir.join(CACHE_MARKER);
+    if marker.is_file() {
+        return Ok(());
+    }
+    if !ours && dir.exists() {
+        let mut entries = dir
+            .read_dir_utf8()
+            .with_context(|| format!("Failed to read {dir}"))?;
+        if entries.next().is_some() {
+            bail!(
+                "{dir} isn't empty and doesn't hold a copy of a remote collection\n\
+                 Its files would be removed, pick an empty directory"
+            );
+        }
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir}"))?;
+    std::fs::write(
+        &marker,
+        "Copy of a remote collection, kept by cook server --remote\n",
+    )
+    .with_context(|| format!("Failed to write {marker}"))
+}
+
+/// An S3 bucket, or a folder in one, synced with the aws CLI
+///
+/// The aws CLI reads the credentials, and `AWS_ENDPOINT_URL` for other
+/// S3-compatible services like MinIO or R2.
+struct S3Store {
+    url: String,
+    write: bool,
+}
+
+impl RemoteStore for S3Store {
+    fn pull(&self, cache: &Utf8Path) -> Result<()> {
+        // Hidden files are the server's own, like the shopping list
+        aws(Command::new("aws")
+            .args(["s3", "sync", "--delete", "--only-show-errors"])
+            .args(["--exclude", ".*", "--exclude", "*/.*"])
+            .arg(&self.url)
+            .arg(cache))
+    }
+
+    fn push(&self, cache: &Utf8Path, path: &Utf8Path) -> Result<()> {
+        if !self.write {
+            bail!("The remote collection is read-only");
+        }
+        aws(Command::new("aws")
+            .args(["s3", "cp", "--only-show-errors"])
+            .arg(cache.join(path))
+            .arg(format!("{}/{path}", self.url)))
+    }
+
+    fn writable(&self) -> bool {
+        self.write
+    }
+}
+
+fn aws(command: &mut Command) -> Result<()> {
+    let status = command
+        .status()
+        .context("Failed to run aws, is the AWS CLI installed?")?;
+    if !status.success() {
+        bail!("aws exited with {status}");
+    }
+    Ok(())
+}
+
+/// A `.zip` archive of the collection on a web server, like the download
+/// of a GitHub repository
+struct ArchiveStore {
+    url: String,
+}
+
+impl RemoteStore for ArchiveStore {
+    fn pull(&self, cache: &Utf8Path) -> Result<()> {
+        std::fs::create_dir_all(cache).with_context(|| format!("Failed to create {cache}"))?;
+        let bytes = super::download(&self.url)?;
+        let mut files =
+            seed::unzip(&bytes).with_context(|| format!("Failed to unpack {}", self.url))?;
+        files.retain(|file| !file.path.split('/').any(|part| part.starts_with('.')));
+        seed::strip_common_dir(&mut files);
+
+        for file in &files {
+            let path = cache.join(&file.path);
+            // Unchanged files keep their time, so caches of parsed recipes stay valid
+            if std::fs::read(&path).is_ok_and(|old| old[..] == file.content[..]) {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {parent}"))?;
+            }
+            std::fs::write(&path, &file.content)
+                .with_context(|| format!("Failed to write {path}"))?;
+        }
+
+        let kept: HashSet<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        remove_others(cache, cache, &kept)
+    }
+
+    fn push(&self, _cache: &Utf8Path, _path: &Utf8Path) -> Result<()> {
+        bail!("A collection in an archive can't be written")
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+}
+
+/// Remove the files that aren't in the archive anymore, except hidden ones
+fn remove_others(root: &Utf8Path, dir: &Utf8Path, kept: &HashSet<&str>) -> Result<()> {
+    for entry in dir.read_dir_utf8()? {
+        let entry = entry?;
+        if entry.file_name().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            remove_others(root, path, kept)?;
+            continue;
+        }
+        let relative = path.strip_prefix(root)?;
+        let relative = relative
+            .components()
+            .map(|c| c.as_str())
+            .collect::<Vec<_>>()
+            .join("/");
+        if !kept.contains(relative.as_str()) {
+            std::fs::remove_file(path).with_context(|| format!("Failed to remove {path}"))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! Rounding of scaled quantities
+//!
+//! Scaling a recipe for 3 servings to 4 gives 2.6667 eggs and 133.333 g of
+//! flour. Quantities changed by scaling are rounded to a step that depends
+//! on their unit, like whole eggs, ¼ tsp and 5 g. Quantities the scaling
+//! didn't change, like fixed ones, are kept as written, and so are
+//! quantities smaller than one step: 2 g of salt doesn't become 0 or 5 g.
+
+use cooklang::{
+    quantity::{Number, Quantity, Value},
+    Recipe,
+};
+use std::collections::BTreeMap;
+
+use super::Setting;
+
+static ROUNDING: Setting<Rounding> = Setting::new();
+
+/// Steps by unit used when `[rounding]` doesn't change them
+const DEFAULT_STEPS: &[(&str, f64)] = &[
+    ("g", 5.0),
+    ("gram", 5.0),
+    ("grams", 5.0),
+    ("ml", 5.0),
+    ("tsp", 0.25),
+    ("teaspoon", 0.25),
+    ("teaspoons", 0.25),
+    ("tbsp", 0.5),
+    ("tablespoon", 0.5),
+    ("tablespoons", 0.5),
+    ("cup", 0.25),
+    ("cups", 0.25),
+];
+
+/// Step for quantities without a unit, like eggs, by default
+const DEFAULT_UNITLESS: f64 = 1.0;
+
+/// Steps scaled quantities are rounded to
+#[derive(Debug, Clone)]
+pub struct Rounding {
+    /// Steps by lowercase unit
+    units: BTreeMap<String, f64>,
+    unitless: f64,
+}
+
+impl Default for Rounding {
+    fn default() -> Self {
+        Self {
+            units: DEFAULT_STEPS
+                .iter()
+                .map(|(unit, step)| (unit.to_string(), *step))
+                .collect(),
+            unitless: DEFAULT_UNITLESS,
+        }
+    }
+}
+
+impl Rounding {
+    /// The default steps changed by `units` and `unitless`
+    ///
+    /// A step of 0 turns rounding off for the unit.
+    pub fn new(units: &BTreeMap<String, f64>, unitless: Option<f64>) -> Self {
+        let mut rounding = Self::default();
+        for (unit, step) in units {
+            rounding.units.insert(unit.to_lowercase(), *step);
+        }
+        if let Some(step) = unitless {
+            rounding.unitless = step;
+        }
+        rounding
+    }
+
+    /// The step for a unit, if quantities in it are rounded
+    pub fn step(&self, unit: Option<&str>) -> Option<f64> {
+        let step = match unit {
+            Some(unit) => *self.units.get(&unit.trim().to_lowercase())?,
+            None => self.unitless,
+        };
+        (step > 0.0).then_some(step)
+    }
+
+    /// A value rounded to the nearest step for its unit
+    pub fn round(&self, value: f64, unit: Option<&str>) -> f64 {
+        let Some(step) = self.step(unit) else {
+            return value;
+        };
+        if value.abs() < step {
+            return value;
+        }
+        let rounded = (value / step).round() * step;
+        // Steps like 0.1 aren't exact in binary
+        (rounded * 1e6).round() / 1e6
+    }
+}
+
+/// Round scaled quantities with `rounding` from now on, `None` to keep them
+/// exact
+pub fn configure_rounding(rounding: Option<Rounding>) {
+    if let Some(rounding) = rounding {
+        ROUNDING.set(rounding);
+    }
+}
+
+/// The configured rounding, if any
+pub fn rounding() -> Option<&'static Rounding> {
+    ROUNDING.get()
+}
+
+/// Run `scale` on a recipe, then round the ingredient quantities it changed
+/// with the configured rules
+pub fn with_rounding<T>(recipe: &mut Recipe, scale: impl FnOnce(&mut Recipe) -> T) -> T {
+    let Some(rounding) = rounding() else {
+        return scale(recipe);
+    };
+    let before: Vec<Option<Value>> = recipe
+        .ingredients
+        .iter()
+        .map(|igr| igr.quantity.as_ref().map(|q| q.value().clone()))
+        .collect();
+    let result = scale(recipe);
+    round_changed(recipe, &before, rounding);
+    result
+}
+
+fn round_changed(recipe: &mut Recipe, before: &[Option<Value>], rounding: &Rounding) {
+    for (igr, before) in recipe.ingredients.iter_mut().zip(before) {
+        let Some(quantity) = &mut igr.quantity else {
+            continue;
+        };
+        if before.as_ref() == Some(quantity.value()) {
+            continue;
+        }
+        let unit = quantity.unit().map(str::to_string);
+        let round = |n: &Number| Number::from(rounding.round(n.value(), unit.as_deref()));
+        let value = match quantity.value() {
+            Value::Number(n) => Value::Number(round(n)),
+            Value::Range { start, end } => Value::Range {
+                start: round(start),
+                end: round(end),
+            },
+            Value::Text(_) => continue,
+        };
+        *quantity = Quantity::new(value, unit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round() {
+        let rounding = Rounding::default();
+        assert_eq!(rounding.round(2.6667, None), 3.0);
+        assert_eq!(rounding.round(133.333, Some("g")), 135.0);
+        assert_eq!(rounding.round(0.6667, Some("tsp")), 0.75);
+        assert_eq!(rounding.round(1.3333, Some("Tbsp")), 1.5);
+        assert_eq!(rounding.round(2.0, Some("g")), 2.0);
+        assert_eq!(rounding.round(0.5, None), 0.5);
+        assert_eq!(rounding.round(1.2345, Some("kg")), 1.2345);
+    }
+
+    #[test]
+    fn test_configured_steps() {
+        let units = BTreeMap::from([("G".to_string(), 10.0), ("tsp".to_string(), 0.0)]);
+        let rounding = Rounding::new(&units, Some(0.5));
+        assert_eq!(rounding.round(133.333, Some("g")), 130.0);
+        assert_eq!(rounding.round(0.6667, Some("tsp")), 0.6667);
+        assert_eq!(rounding.round(2.6667, None), 2.5);
+        assert_eq!(rounding.round(0.3, Some("ml")), 0.3);
+    }
+
+    #[test]
+    fn test_round_changed() {
+        let (mut recipe, _) = crate::util::PARSER
+            .parse("Beat @eggs{2} with @flour{100%g} and @salt{}.\n")
+            .into_result()
+            .unwrap();
+        let before: Vec<_> = recipe
+            .ingredients
+            .iter()
+            .map(|igr| igr.quantity.as_ref().map(|q| q.value().clone()))
+            .collect();
+        recipe.scale(4.0 / 3.0, crate::util::PARSER.converter());
+        round_changed(&mut recipe, &before, &Rounding::default());
+        let quantities: Vec<_> = recipe
+            .ingredients
+            .iter()
+            .map(|igr| igr.quantity.as_ref().map(|q| q.to_string()))
+            .collect();
+        assert_eq!(
+            quantities,
+            [Some("3".to_string()), Some("135 g".to_string()), None]
+        );
+    }
+}
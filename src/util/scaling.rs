@@ -0,0 +1,356 @@
+//! Named scaling factors of a recipe
+//!
+//! A recipe lists them in its `scalings` metadata, like
+//! `scalings: [half, party x3]`, so a batch size used often can be asked
+//! for by name, like `--scale party`, instead of by its factor.
+//!
+//! Scaling is linear, which is wrong for seasonings and leavening, so single
+//! ingredients can be given their own factor with [`apply_overrides`].
+
+use anyhow::{bail, Context as _, Result};
+use cooklang::{
+    quantity::{Number, Quantity, Value as QuantityValue},
+    Recipe,
+};
+use cooklang_find::RecipeEntry;
+use serde::Serialize;
+use serde_yaml::Value;
+
+use super::{
+    aliases, front_matter::FrontMatter, metadata_cache::recipe_metadata, pantry::Amount, rounding,
+    Setting,
+};
+
+/// The metadata key with the presets
+pub const SCALINGS_KEY: &str = "scalings";
+
+static DEFAULT_SERVINGS: Setting<f64> = Setting::new();
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScalingPreset {
+    pub name: String,
+    pub factor: f64,
+}
+
+/// Names that are a factor on their own
+const WORDS: &[(&str, f64)] = &[
+    ("quarter", 0.25),
+    ("third", 1.0 / 3.0),
+    ("half", 0.5),
+    ("single", 1.0),
+    ("double", 2.0),
+    ("triple", 3.0),
+    ("quadruple", 4.0),
+];
+
+/// The presets in a `scalings` value
+///
+/// Items are a name and a factor, like "party x3", "party ×3" or
+/// `party: 3`, or one of the words half, double, triple and so on. Items
+/// without a factor are left out.
+pub fn presets(value: &Value) -> Vec<ScalingPreset> {
+    let mut found = Vec::new();
+    match value {
+        Value::Sequence(items) => {
+            for item in items {
+                found.extend(presets(item));
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (name, factor) in mapping {
+                let factor = match factor {
+                    Value::Number(n) => n.as_f64(),
+                    Value::String(s) => parse_factor(s),
+                    _ => None,
+                };
+                if let (Some(name), Some(factor)) = (name.as_str(), factor) {
+                    found.push(ScalingPreset {
+                        name: name.trim().to_string(),
+                        factor,
+                    });
+                }
+            }
+        }
+        Value::String(item) => found.extend(parse_item(item)),
+        _ => {}
+    }
+    found.retain(|preset| preset.factor > 0.0 && !preset.name.is_empty());
+    found
+}
+
+fn parse_item(item: &str) -> Option<ScalingPreset> {
+    let item = item.trim();
+    if let Some((name, factor)) = item.rsplit_once(char::is_whitespace) {
+        if let Some(factor) = parse_factor(factor) {
+            return Some(ScalingPreset {
+                name: name.trim().trim_end_matches(':').to_string(),
+                factor,
+            });
+        }
+    }
+    WORDS
+        .iter()
+        .find(|(word, _)| word.eq_ignore_ascii_case(item))
+        .map(|&(_, factor)| ScalingPreset {
+            name: item.to_string(),
+            factor,
+        })
+}
+
+/// "3", "x3", "×3" or "x0.5"
+fn parse_factor(factor: &str) -> Option<f64> {
+    let factor = factor.trim();
+    let factor = factor
+        .strip_prefix(['x', 'X', '×', '*'])
+        .unwrap_or(factor)
+        .trim();
+    factor.parse().ok().filter(|f: &f64| f.is_finite())
+}
+
+/// The presets of a parsed recipe
+pub fn recipe_presets(recipe: &Recipe) -> Vec<ScalingPreset> {
+    recipe
+        .metadata
+        .get(SCALINGS_KEY)
+        .map(presets)
+        .unwrap_or_default()
+}
+
+/// A scaling factor above 0, given as a number or by the name of one of the
+/// recipe's presets, case-insensitively
+///
+/// Names are looked up in the front matter, so the recipe doesn't have to
+/// be parsed twice.
+pub fn resolve(entry: &RecipeEntry, scale: &str) -> Result<f64> {
+    let name = entry.name().clone().unwrap_or_default();
+    if let Ok(factor) = scale.trim().parse::<f64>() {
+        if !(factor.is_finite() && factor > 0.0) {
+            bail!("The scale of '{name}' has to be a number above 0, not {scale}");
+        }
+        return Ok(factor);
+    }
+    let content = entry.content().context("Failed to read recipe content")?;
+    let available = FrontMatter::parse(&content)
+        .ok()
+        .and_then(|front_matter| front_matter.get(SCALINGS_KEY).map(presets))
+        .unwrap_or_default();
+
+    if let Some(preset) = available
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(scale.trim()))
+    {
+        return Ok(preset.factor);
+    }
+    if available.is_empty() {
+        bail!(
+            "Invalid scaling factor for '{name}': '{scale}' isn't a number \
+             and the recipe has no scalings"
+        );
+    }
+    let known = available
+        .iter()
+        .map(|preset| format!("{} (x{})", preset.name, preset.factor))
+        .collect::<Vec<_>>()
+        .join(", ");
+    bail!(
+        "Invalid scaling factor for '{name}': '{scale}' isn't a number \
+         or one of its scalings: {known}"
+    )
+}
+
+/// Scale recipes read without a scaling factor to `servings` from now on
+pub fn configure_default_servings(servings: Option<f64>) {
+    if let Some(servings) = servings.filter(|s| *s > 0.0) {
+        DEFAULT_SERVINGS.set(servings);
+    }
+}
+
+/// The factor scaling a recipe from the servings it declares to the
+/// configured default servings
+///
+/// None without default servings, or if the recipe doesn't say how many it
+/// serves. Recipes with several servings, like "2|4", are scaled from the
+/// first.
+pub fn default_servings_factor(entry: &RecipeEntry) -> Option<f64> {
+    let servings = DEFAULT_SERVINGS.get()?;
+    // Servings as cached, so the recipe isn't parsed once more to be scaled
+    let declared = recipe_metadata(entry)
+        .servings
+        .as_deref()
+        .and_then(Amount::parse)
+        .map(|a| a.value)
+        .filter(|s| *s > 0.0)?;
+    Some(servings / declared)
+}
+
+/// An ingredient scaled by its own factor instead of the recipe's, like
+/// salt kept as written when everything else is doubled
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngredientOverride {
+    pub name: String,
+    /// Factor from the quantity as written
+    pub factor: f64,
+}
+
+/// Overrides like "salt, yeast:1.5"
+///
+/// A name alone pins the ingredient to its quantity as written.
+pub fn parse_overrides(list: &str) -> Result<Vec<IngredientOverride>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (name, factor) = match item.rsplit_once(':') {
+                Some((name, factor)) => (
+                    name.trim(),
+                    parse_factor(factor)
+                        .filter(|f| *f >= 0.0)
+                        .with_context(|| format!("Invalid factor for '{name}': '{factor}'"))?,
+                ),
+                None => (item, 1.0),
+            };
+            Ok(IngredientOverride {
+                name: name.to_string(),
+                factor,
+            })
+        })
+        .collect()
+}
+
+/// Give the ingredients named in `overrides` their own factor, from the
+/// same recipe unscaled in `original`
+///
+/// Names match like on the shopping list, ignoring case and through
+/// `[aliases]`. Returns the ingredients that were overridden, by their name
+/// in the recipe; overrides matching nothing are left out.
+pub fn apply_overrides(
+    recipe: &mut Recipe,
+    original: &Recipe,
+    overrides: &[IngredientOverride],
+) -> Vec<IngredientOverride> {
+    let mut pinned: Vec<IngredientOverride> = Vec::new();
+    for (igr, written) in recipe.ingredients.iter_mut().zip(&original.ingredients) {
+        let Some(factor) = overrides
+            .iter()
+            .find(|o| aliases::same_ingredient(&o.name, &igr.name))
+            .map(|o| o.factor)
+        else {
+            continue;
+        };
+        igr.quantity = written
+            .quantity
+            .as_ref()
+            .map(|quantity| scale_quantity(quantity, factor));
+        if !pinned.iter().any(|p| p.name == igr.name) {
+            pinned.push(IngredientOverride {
+                name: igr.name.clone(),
+                factor,
+            });
+        }
+    }
+    pinned
+}
+
+/// A quantity times `factor`, rounded like scaled quantities are
+fn scale_quantity(quantity: &Quantity, factor: f64) -> Quantity {
+    if factor == 1.0 {
+        return quantity.clone();
+    }
+    let unit = quantity.unit().map(str::to_string);
+    let scale = |n: &Number| {
+        let value = n.value() * factor;
+        Number::from(match rounding::rounding() {
+            Some(rounding) => rounding.round(value, unit.as_deref()),
+            None => value,
+        })
+    };
+    let value = match quantity.value() {
+        QuantityValue::Number(n) => QuantityValue::Number(scale(n)),
+        QuantityValue::Range { start, end } => QuantityValue::Range {
+            start: scale(start),
+            end: scale(end),
+        },
+        QuantityValue::Text(_) => return quantity.clone(),
+    };
+    Quantity::new(value, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PARSER;
+
+    fn preset(name: &str, factor: f64) -> ScalingPreset {
+        ScalingPreset {
+            name: name.to_string(),
+            factor,
+        }
+    }
+
+    fn override_(name: &str, factor: f64) -> IngredientOverride {
+        IngredientOverride {
+            name: name.to_string(),
+            factor,
+        }
+    }
+
+    #[test]
+    fn test_presets() {
+        let value: Value =
+            serde_yaml::from_str("[half, party x3, brunch ×1.5, 'crowd: x10', unknown]").unwrap();
+        assert_eq!(
+            presets(&value),
+            [
+                preset("half", 0.5),
+                preset("party", 3.0),
+                preset("brunch", 1.5),
+                preset("crowd", 10.0)
+            ]
+        );
+
+        let value: Value = serde_yaml::from_str("{party: 3, tiny: x0.25}").unwrap();
+        assert_eq!(
+            presets(&value),
+            [preset("party", 3.0), preset("tiny", 0.25)]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let entry = RecipeEntry::from_content(
+            "---\nscalings: [half, party x3]\n---\n\nMix @flour{100%g}.\n".to_string(),
+            Some("Cake".to_string()),
+        )
+        .unwrap();
+        assert_eq!(resolve(&entry, "2").unwrap(), 2.0);
+        assert_eq!(resolve(&entry, "Party").unwrap(), 3.0);
+        for scale in ["0", "-1", "NaN", "inf"] {
+            assert!(resolve(&entry, scale).is_err(), "{scale}");
+        }
+        let error = resolve(&entry, "crowd").unwrap_err().to_string();
+        assert!(error.contains("half (x0.5), party (x3)"), "{error}");
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let overrides = parse_overrides("Salt, yeast:x1.5, pepper").unwrap();
+        assert_eq!(overrides[1], override_("yeast", 1.5));
+        assert!(parse_overrides("salt:lots").is_err());
+
+        let (original, _) = PARSER
+            .parse("Mix @flour{500%g}, @salt{10%g}, @yeast{4%g} and @water{300%ml}.\n")
+            .into_result()
+            .unwrap();
+        let mut recipe = original.clone();
+        recipe.scale(2.0, PARSER.converter());
+        let pinned = apply_overrides(&mut recipe, &original, &overrides);
+        assert_eq!(pinned, [override_("salt", 1.0), override_("yeast", 1.5)]);
+
+        let quantities: Vec<_> = recipe
+            .ingredients
+            .iter()
+            .map(|igr| igr.quantity.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(quantities, ["1000 g", "10 g", "6 g", "600 ml"]);
+    }
+}
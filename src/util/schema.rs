@@ -0,0 +1,228 @@
+//! JSON Schema of the front matter keys cook understands
+//!
+//! Editors validate and complete front matter with it, like VS Code's YAML
+//! extension, and the web editor gets it from `/api/v1/schema/metadata`. Keys
+//! not in the schema are still allowed: recipes can carry their own.
+
+use serde_json::{json, Value};
+
+/// A key with its schema
+struct Key {
+    name: &'static str,
+    description: &'static str,
+    schema: fn() -> Value,
+}
+
+const KEYS: &[Key] = &[
+    Key {
+        name: "title",
+        description: "Title of the recipe, the file name when not set",
+        schema: text,
+    },
+    Key {
+        name: "description",
+        description: "A short description, shown below the title",
+        schema: text,
+    },
+    Key {
+        name: "tags",
+        description: "Tags, as a list or separated by commas",
+        schema: list,
+    },
+    Key {
+        name: "servings",
+        description: "How many the recipe serves, like 4 or \"4 people\"",
+        schema: amount,
+    },
+    Key {
+        name: "time",
+        description: "Total time, in minutes or like \"1h 30min\"",
+        schema: duration,
+    },
+    Key {
+        name: "prep time",
+        description: "Preparation time, in minutes or like \"20 minutes\"",
+        schema: duration,
+    },
+    Key {
+        name: "cook time",
+        description: "Cooking time, in minutes or like \"1 hour\"",
+        schema: duration,
+    },
+    Key {
+        name: "source",
+        description: "Where the recipe comes from, a URL or a name",
+        schema: source,
+    },
+    Key {
+        name: "author",
+        description: "Who wrote the recipe",
+        schema: person,
+    },
+    Key {
+        name: "image",
+        description: "The recipe's image, when it isn't next to the recipe",
+        schema: list,
+    },
+    Key {
+        name: "course",
+        description: "Like \"dinner\" or \"dessert\"",
+        schema: text,
+    },
+    Key {
+        name: "cuisine",
+        description: "Like \"Italian\"",
+        schema: text,
+    },
+    Key {
+        name: "diet",
+        description: "Diets the recipe fits, like \"vegan\"",
+        schema: list,
+    },
+    Key {
+        name: "difficulty",
+        description: "Like \"easy\"",
+        schema: text,
+    },
+    Key {
+        name: "locale",
+        description: "Language of the recipe, like \"en\" or \"pt_BR\"",
+        schema: locale,
+    },
+    Key {
+        name: "emoji",
+        description: "Shown next to the title",
+        schema: text,
+    },
+    Key {
+        name: "nutrition",
+        description: "Nutrition per serving, used instead of the calculated values",
+        schema: nutrition,
+    },
+];
+
+/// The schema of a recipe's front matter
+pub fn metadata_schema() -> Value {
+    let properties: serde_json::Map<String, Value> = KEYS
+        .iter()
+        .map(|key| {
+            let mut schema = (key.schema)();
+            schema["description"] = json!(key.description);
+            (key.name.to_string(), schema)
+        })
+        .collect();
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Cooklang recipe metadata",
+        "description": "The front matter of a .cook file",
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": true,
+    })
+}
+
+fn text() -> Value {
+    json!({ "type": "string" })
+}
+
+fn list() -> Value {
+    json!({
+        "anyOf": [
+            { "type": "string" },
+            { "type": "array", "items": { "type": "string" } },
+        ]
+    })
+}
+
+fn amount() -> Value {
+    json!({
+        "anyOf": [
+            { "type": "number", "exclusiveMinimum": 0 },
+            { "type": "string", "pattern": "^\\s*\\d" },
+        ]
+    })
+}
+
+fn duration() -> Value {
+    json!({
+        "anyOf": [
+            { "type": "number", "minimum": 0 },
+            { "type": "string" },
+        ]
+    })
+}
+
+fn person() -> Value {
+    json!({
+        "anyOf": [
+            { "type": "string" },
+            {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "url": { "type": "string", "format": "uri" },
+                },
+                "additionalProperties": false,
+            },
+        ]
+    })
+}
+
+fn source() -> Value {
+    json!({
+        "anyOf": [
+            { "type": "string" },
+            {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "url": { "type": "string", "format": "uri" },
+                    "author": { "type": "string" },
+                },
+                "additionalProperties": false,
+            },
+        ]
+    })
+}
+
+fn locale() -> Value {
+    json!({ "type": "string", "pattern": "^[a-z]{2,3}([_-][A-Za-z]{2})?$" })
+}
+
+fn nutrition() -> Value {
+    let value = json!({
+        "anyOf": [
+            { "type": "number", "minimum": 0 },
+            { "type": "string", "pattern": "^\\s*\\d" },
+        ]
+    });
+    json!({
+        "type": "object",
+        "properties": {
+            "calories": value,
+            "protein": value,
+            "fat": value,
+            "carbohydrates": value,
+        },
+        "additionalProperties": false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_schema() {
+        let schema = metadata_schema();
+        let properties = schema["properties"].as_object().unwrap();
+        assert_eq!(properties.len(), KEYS.len());
+        assert!(properties
+            .values()
+            .all(|property| property["description"].is_string()));
+        assert_eq!(
+            properties["nutrition"]["properties"]["calories"]["anyOf"][0]["type"],
+            "number"
+        );
+    }
+}
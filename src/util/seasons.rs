@@ -0,0 +1,223 @@
+//! When fruit and vegetables are in season
+//!
+//! A calendar of the produce of temperate climates is bundled. It's written
+//! for the northern hemisphere and moved by six months with `[seasons]
+//! hemisphere = "south"`. A `seasons.toml` in the config directory adds to
+//! it, or replaces the months of produce it lists, for the calendar of the
+//! region you live in; its months are used as written.
+//!
+//! ```toml
+//! asparagus = [4, 5, 6]
+//! "wild garlic" = [3, 4]
+//! ```
+//!
+//! Ingredients are the produce their name ends with, in singular, so
+//! "cherry tomatoes" are tomatoes. Canned, dried and frozen produce is
+//! around all year and isn't seasonal.
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name of the seasons file in the config directories
+pub const SEASONS_FILE: &str = "seasons.toml";
+
+const BUNDLED: &str = include_str!("seasons.toml");
+
+/// Words of ingredients that don't depend on the season
+const PRESERVED: &[&str] = &[
+    "canned",
+    "tinned",
+    "jarred",
+    "dried",
+    "sun-dried",
+    "frozen",
+    "pickled",
+    "preserved",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hemisphere {
+    #[default]
+    North,
+    South,
+}
+
+/// Seasonal produce by name, in singular, with the months it's in season as
+/// bits, January first
+#[derive(Debug, Default)]
+pub struct Seasons(BTreeMap<String, u16>);
+
+impl Seasons {
+    /// The bundled calendar for the hemisphere, extended with a user
+    /// supplied file, if given
+    pub fn load(path: Option<&Utf8Path>, hemisphere: Hemisphere) -> Result<Self> {
+        let mut seasons = Self::parse(BUNDLED).expect("bundled seasons are valid");
+        if hemisphere == Hemisphere::South {
+            for months in seasons.0.values_mut() {
+                *months = ((*months << 6) | (*months >> 6)) & 0xfff;
+            }
+        }
+        if let Some(path) = path {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read seasons at {path}"))?;
+            let user = Self::parse(&content)
+                .with_context(|| format!("Failed to parse seasons at {path}"))?;
+            seasons.0.extend(user.0);
+        }
+        Ok(seasons)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let table: BTreeMap<String, Vec<u32>> = toml::from_str(content)?;
+        let mut seasons = BTreeMap::new();
+        for (name, months) in table {
+            let mut bits = 0;
+            for month in months {
+                if !(1..=12).contains(&month) {
+                    bail!("The months of {name} go from 1 to 12, not {month}");
+                }
+                bits |= 1 << (month - 1);
+            }
+            seasons.insert(words(&name).join(" "), bits);
+        }
+        Ok(Self(seasons))
+    }
+
+    /// Whether an ingredient is in season in a month, from 1 to 12, `None`
+    /// when it isn't seasonal produce
+    pub fn in_season(&self, ingredient: &str, month: u32) -> Option<bool> {
+        let words = words(ingredient);
+        if words.iter().any(|word| PRESERVED.contains(&word.as_str())) {
+            return None;
+        }
+        // The longest name first, "butternut squash" before "squash"
+        (0..words.len())
+            .find_map(|start| self.0.get(&words[start..].join(" ")))
+            .map(|months| months & (1 << (month - 1)) != 0)
+    }
+
+    /// Which of a recipe's ingredients are in season in a month
+    pub fn check<'a>(
+        &self,
+        ingredients: impl IntoIterator<Item = &'a str>,
+        month: u32,
+    ) -> Seasonality {
+        let mut seasonality = Seasonality::default();
+        for ingredient in ingredients {
+            match self.in_season(ingredient, month) {
+                Some(true) => seasonality.in_season.push(ingredient.to_string()),
+                Some(false) => seasonality.out_of_season.push(ingredient.to_string()),
+                None => {}
+            }
+        }
+        seasonality
+    }
+}
+
+/// A recipe's seasonal produce, by whether it's in season
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Seasonality {
+    pub in_season: Vec<String>,
+    pub out_of_season: Vec<String>,
+}
+
+impl Seasonality {
+    /// Some produce is in season and none is out of it
+    pub fn is_in_season(&self) -> bool {
+        !self.in_season.is_empty() && self.out_of_season.is_empty()
+    }
+
+    /// Produce in season counts for the recipe, produce out of it against
+    pub fn score(&self) -> i64 {
+        self.in_season.len() as i64 - self.out_of_season.len() as i64
+    }
+}
+
+/// The month it is, from 1 to 12
+pub fn current_month() -> u32 {
+    chrono::Local::now().month()
+}
+
+/// Lowercase words of a name, in singular
+fn words(name: &str) -> Vec<String> {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .filter(|word| !word.is_empty())
+        .map(singular)
+        .collect()
+}
+
+fn singular(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies").filter(|stem| stem.len() > 1) {
+        return format!("{stem}y");
+    }
+    for suffix in ["oes", "ches", "shes", "sses", "xes"] {
+        if word.ends_with(suffix) {
+            return word[..word.len() - 2].to_string();
+        }
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if !stem.ends_with(['s', 'u']) && stem.len() > 1 => stem.to_string(),
+        _ => word.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled() {
+        let seasons = Seasons::load(None, Hemisphere::North).unwrap();
+        assert_eq!(seasons.in_season("asparagus", 5), Some(true));
+        assert_eq!(seasons.in_season("Asparagus", 12), Some(false));
+        assert_eq!(seasons.in_season("cherry tomatoes", 8), Some(true));
+        assert_eq!(seasons.in_season("strawberries", 1), Some(false));
+        assert_eq!(seasons.in_season("canned tomatoes", 1), None);
+        assert_eq!(seasons.in_season("flour", 5), None);
+        // Not a pea
+        assert_eq!(seasons.in_season("chickpeas", 5), None);
+    }
+
+    #[test]
+    fn test_south() {
+        let seasons = Seasons::load(None, Hemisphere::South).unwrap();
+        assert_eq!(seasons.in_season("asparagus", 11), Some(true));
+        assert_eq!(seasons.in_season("asparagus", 5), Some(false));
+    }
+
+    #[test]
+    fn test_longest_name() {
+        let seasons = Seasons::parse("squash = [1]\n\"butternut squash\" = [2]\n").unwrap();
+        assert_eq!(seasons.in_season("butternut squash", 2), Some(true));
+        assert_eq!(seasons.in_season("acorn squash", 2), Some(false));
+    }
+
+    #[test]
+    fn test_check() {
+        let seasons = Seasons::load(None, Hemisphere::North).unwrap();
+        let seasonality = seasons.check(["asparagus", "butter", "tomato"], 5);
+        assert_eq!(seasonality.in_season, ["asparagus"]);
+        assert_eq!(seasonality.out_of_season, ["tomato"]);
+        assert!(!seasonality.is_in_season());
+        assert_eq!(seasonality.score(), 0);
+    }
+
+    #[test]
+    fn test_invalid_month() {
+        assert!(Seasons::parse("leek = [13]").is_err());
+    }
+
+    #[test]
+    fn test_singular() {
+        assert_eq!(singular("berries"), "berry");
+        assert_eq!(singular("potatoes"), "potato");
+        assert_eq!(singular("peaches"), "peach");
+        assert_eq!(singular("asparagus"), "asparagus");
+        assert_eq!(singular("leeks"), "leek");
+    }
+}
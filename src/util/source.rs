@@ -0,0 +1,240 @@
+//! Where a recipe comes from, from its `source` metadata
+//!
+//! `source` is written many ways: a URL, a book with a page like "The Food
+//! Lab, p. 123", or a map with `name`, `url`, `page` and `author`. They're
+//! all read into a [`Source`], so the API, exports and stats can tell
+//! websites from books and credit them the same way.
+
+use cooklang::Recipe;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_yaml::Value;
+
+use super::front_matter::display_value;
+
+/// A page reference at the end of a book's name, like ", p. 123" or
+/// "(pages 12-14)"
+static PAGE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(.*?)[\s,;]*\(?\b(?:p|pp|pg|page|pages)\.?\s*(\d+(?:\s*[-–]\s*\d+)?)\)?\.?$")
+        .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    /// Has a URL
+    Website,
+    /// Has a page, or is given as a `book`
+    Book,
+    /// Only a name, like "Grandma"
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Source {
+    pub kind: SourceKind,
+    /// The website's or book's name
+    pub name: Option<String>,
+    pub url: Option<String>,
+    /// Like "123" or "12-14"
+    pub page: Option<String>,
+    pub author: Option<String>,
+}
+
+impl Source {
+    /// The recipe's source, with the recipe's `author` when the source has
+    /// none
+    ///
+    /// `source url`, which some importers write, is read too.
+    pub fn of(recipe: &Recipe) -> Option<Self> {
+        let mut source = recipe
+            .metadata
+            .get("source")
+            .and_then(Self::parse)
+            .or_else(|| {
+                ["source url", "source_url"]
+                    .iter()
+                    .find_map(|key| recipe.metadata.get(*key))
+                    .and_then(Self::parse)
+            })?;
+        if source.author.is_none() {
+            source.author = recipe.metadata.get("author").and_then(name_of);
+        }
+        Some(source)
+    }
+
+    /// The source in a metadata value, `None` when it's empty
+    pub fn parse(value: &Value) -> Option<Self> {
+        let source = match value {
+            Value::Mapping(_) => {
+                let field =
+                    |keys: &[&str]| keys.iter().find_map(|key| value.get(*key).and_then(text));
+                let book = field(&["book"]);
+                Source {
+                    kind: SourceKind::Other,
+                    name: field(&["name", "title"]).or_else(|| book.clone()),
+                    // Only web links, they end up in pages
+                    url: field(&["url", "link"]).filter(|url| is_url(url)),
+                    page: field(&["page", "pages"]),
+                    author: value.get("author").and_then(name_of),
+                }
+                .with_kind(book.is_some())
+            }
+            _ => Self::parse_text(&text(value)?),
+        };
+        (source.name.is_some() || source.url.is_some()).then_some(source)
+    }
+
+    fn parse_text(text: &str) -> Self {
+        let mut source = Source {
+            kind: SourceKind::Other,
+            name: None,
+            url: None,
+            page: None,
+            author: None,
+        };
+        // "Serious Eats https://..." has the URL last
+        let (rest, url) = match text.rsplit_once(char::is_whitespace) {
+            Some((name, url)) if is_url(url) => (name.trim(), Some(url)),
+            _ if is_url(text) => ("", Some(text)),
+            _ => (text, None),
+        };
+        source.url = url.map(str::to_string);
+        match PAGE.captures(rest) {
+            Some(captures) if !captures[1].trim().is_empty() => {
+                source.name = Some(captures[1].trim().to_string());
+                source.page = Some(captures[2].to_string());
+            }
+            _ => source.name = (!rest.is_empty()).then(|| rest.to_string()),
+        }
+        source.with_kind(false)
+    }
+
+    fn with_kind(mut self, book: bool) -> Self {
+        self.kind = if self.url.is_some() {
+            SourceKind::Website
+        } else if book || self.page.is_some() {
+            SourceKind::Book
+        } else {
+            SourceKind::Other
+        };
+        self
+    }
+
+    /// The website's host without "www.", like "seriouseats.com"
+    pub fn domain(&self) -> Option<&str> {
+        let url = self.url.as_deref()?;
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let host = rest.split(['/', '?', '#']).next()?;
+        let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+        let host = host.split(':').next()?;
+        Some(host.strip_prefix("www.").unwrap_or(host)).filter(|host| !host.is_empty())
+    }
+
+    /// What recipes from the same place have in common: the domain of
+    /// websites, the name of anything else
+    pub fn key(&self) -> Option<String> {
+        match self.kind {
+            SourceKind::Website => self.domain().map(str::to_lowercase),
+            _ => self.name.clone(),
+        }
+    }
+
+    /// The credit line, like "The Food Lab, page 123, by J. Kenji López-Alt"
+    ///
+    /// Links aren't part of it, websites without a name are credited with
+    /// their domain.
+    pub fn citation(&self) -> String {
+        let mut citation = self
+            .name
+            .clone()
+            .or_else(|| self.domain().map(str::to_string))
+            .unwrap_or_default();
+        if let Some(page) = &self.page {
+            let label = if page.contains(['-', '–']) {
+                "pages"
+            } else {
+                "page"
+            };
+            citation.push_str(&format!(", {label} {page}"));
+        }
+        if let Some(author) = &self.author {
+            citation.push_str(&format!(", by {author}"));
+        }
+        citation
+    }
+}
+
+fn is_url(text: &str) -> bool {
+    (text.starts_with("http://") || text.starts_with("https://")) && !text.contains(' ')
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(_) | Value::Number(_) => {
+            Some(display_value(value).trim().to_string()).filter(|text| !text.is_empty())
+        }
+        _ => None,
+    }
+}
+
+/// An author given as text or as a map with a `name`
+fn name_of(value: &Value) -> Option<String> {
+    match value {
+        Value::Mapping(_) => value.get("name").and_then(text),
+        _ => text(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Option<Source> {
+        Source::parse(&serde_yaml::from_str(yaml).unwrap())
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let source = parse("https://www.seriouseats.com/pancakes").unwrap();
+        assert_eq!(source.kind, SourceKind::Website);
+        assert_eq!(source.name, None);
+        assert_eq!(source.domain(), Some("seriouseats.com"));
+        assert_eq!(source.key().as_deref(), Some("seriouseats.com"));
+        assert_eq!(source.citation(), "seriouseats.com");
+
+        let source = parse("Serious Eats https://www.seriouseats.com/pancakes").unwrap();
+        assert_eq!(source.name.as_deref(), Some("Serious Eats"));
+        assert_eq!(source.kind, SourceKind::Website);
+    }
+
+    #[test]
+    fn test_parse_book() {
+        let source = parse("The Food Lab, p. 123").unwrap();
+        assert_eq!(source.kind, SourceKind::Book);
+        assert_eq!(source.name.as_deref(), Some("The Food Lab"));
+        assert_eq!(source.page.as_deref(), Some("123"));
+        assert_eq!(source.citation(), "The Food Lab, page 123");
+
+        let source = parse("Salt Fat Acid Heat (pages 12-14)").unwrap();
+        assert_eq!(source.name.as_deref(), Some("Salt Fat Acid Heat"));
+        assert_eq!(source.page.as_deref(), Some("12-14"));
+        assert_eq!(source.citation(), "Salt Fat Acid Heat, pages 12-14");
+    }
+
+    #[test]
+    fn test_parse_map() {
+        let source = parse("{book: Jerusalem, page: 88, author: Ottolenghi}").unwrap();
+        assert_eq!(source.kind, SourceKind::Book);
+        assert_eq!(source.name.as_deref(), Some("Jerusalem"));
+        assert_eq!(source.citation(), "Jerusalem, page 88, by Ottolenghi");
+
+        let source = parse("{name: Grandma}").unwrap();
+        assert_eq!(source.kind, SourceKind::Other);
+        assert_eq!(source.key().as_deref(), Some("Grandma"));
+
+        assert!(parse("{page: 3}").is_none());
+        assert!(parse("''").is_none());
+    }
+}
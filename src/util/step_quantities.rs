@@ -0,0 +1,126 @@
+//! The ingredients each step uses, with their quantities
+//!
+//! The ingredient list has the totals of the recipe, these are the amounts
+//! to set out for every step.
+
+use cooklang::{
+    convert::Converter,
+    model::{Item, Step},
+    quantity::{GroupedQuantity, Quantity},
+    Content, Recipe,
+};
+use serde::Serialize;
+
+use super::format::display_grouped;
+
+/// The ingredients of one step
+#[derive(Debug, Serialize)]
+pub struct StepQuantities {
+    /// Index of the section in the recipe
+    pub section: usize,
+    /// Number of the step, like it's displayed
+    pub step: u32,
+    pub ingredients: Vec<StepIngredient>,
+}
+
+/// An ingredient used in a step, with all its amounts in the step added up
+#[derive(Debug, Serialize)]
+pub struct StepIngredient {
+    /// Index of its first use in the step in the recipe's ingredients
+    pub index: usize,
+    pub name: String,
+    /// Formatted for the configured locale, like "1,5 EL"
+    pub display: Option<String>,
+    pub quantities: Vec<Quantity>,
+}
+
+/// The ingredients of every step in the recipe, steps without any are
+/// left out
+pub fn step_quantities(recipe: &Recipe, converter: &Converter) -> Vec<StepQuantities> {
+    recipe
+        .sections
+        .iter()
+        .enumerate()
+        .flat_map(|(section, s)| {
+            s.content.iter().filter_map(move |content| match content {
+                Content::Step(step) => Some((section, step)),
+                Content::Text(_) => None,
+            })
+        })
+        .map(|(section, step)| StepQuantities {
+            section,
+            step: step.number,
+            ingredients: step_ingredients(recipe, step, converter),
+        })
+        .filter(|step| !step.ingredients.is_empty())
+        .collect()
+}
+
+/// The ingredients of a step in the order they're first used
+///
+/// An ingredient used several times in the step, like "@salt some, then
+/// more @&salt{1%tsp}", is listed once.
+pub fn step_ingredients(
+    recipe: &Recipe,
+    step: &Step,
+    converter: &Converter,
+) -> Vec<StepIngredient> {
+    let mut found: Vec<(usize, &str, GroupedQuantity)> = Vec::new();
+    for item in &step.items {
+        let &Item::Ingredient { index } = item else {
+            continue;
+        };
+        let ingredient = &recipe.ingredients[index];
+        let position = match found
+            .iter()
+            .position(|(_, name, _)| *name == ingredient.name)
+        {
+            Some(position) => position,
+            None => {
+                found.push((index, &ingredient.name, GroupedQuantity::default()));
+                found.len() - 1
+            }
+        };
+        if let Some(quantity) = &ingredient.quantity {
+            found[position].2.add(quantity, converter);
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|(index, _, quantity)| StepIngredient {
+            index,
+            name: recipe.ingredients[index].display_name().to_string(),
+            display: (!quantity.is_empty()).then(|| display_grouped(&quantity)),
+            quantities: quantity.into_vec(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PARSER;
+
+    #[test]
+    fn test_step_quantities() {
+        let recipe = PARSER
+            .parse(
+                "Mix @flour{200%g} and @salt{1%tsp}.\n\nAdd @&flour{100%g}, @flour{50%g} and @water.\n\nBake.\n",
+            )
+            .into_result()
+            .unwrap()
+            .0;
+        let steps = step_quantities(&recipe, PARSER.converter());
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[1].step, 2);
+        let names: Vec<_> = steps[1]
+            .ingredients
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect();
+        assert_eq!(names, ["flour", "water"]);
+        assert_eq!(steps[1].ingredients[0].display.as_deref(), Some("150 g"));
+        assert_eq!(steps[1].ingredients[1].display, None);
+    }
+}
@@ -0,0 +1,274 @@
+//! Ingredients that can replace each other
+//!
+//! A small database of common substitutions is bundled; a
+//! `substitutions.toml` in the config directory adds to it. Substitutes for
+//! an ingredient listed in the file come before the bundled ones, and
+//! replace a bundled substitute with the same name.
+//!
+//! ```toml
+//! [[butter]]
+//! name = "olive oil"
+//! ratio = 0.75          # 75 g of oil for 100 g of butter
+//! note = "for cooking"
+//!
+//! [[egg]]
+//! name = "flaxseed meal"
+//! unit = "tbsp"         # 1 egg becomes 1 tbsp
+//! ```
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use cooklang::{
+    quantity::{Number, Quantity, Value},
+    Recipe,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name of the substitutions file in the config directories
+pub const SUBSTITUTIONS_FILE: &str = "substitutions.toml";
+
+const BUNDLED: &str = include_str!("substitutions.toml");
+
+/// Something to use instead of an ingredient
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Substitute {
+    pub name: String,
+    /// Amount of the substitute replacing one of the ingredient
+    #[serde(default = "one")]
+    pub ratio: f64,
+    /// Unit of the substitute, if it's measured differently
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+/// Substitutes by lowercase ingredient name
+#[derive(Debug, Default)]
+pub struct Substitutions(BTreeMap<String, Vec<Substitute>>);
+
+impl Substitutions {
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED).expect("bundled substitutions are valid")
+    }
+
+    /// The bundled substitutions extended with a user supplied file, if given
+    pub fn load(path: Option<&Utf8Path>) -> Result<Self> {
+        let mut substitutions = Self::bundled();
+        if let Some(path) = path {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read substitutions at {path}"))?;
+            let user = Self::parse(&content)
+                .with_context(|| format!("Failed to parse substitutions at {path}"))?;
+            substitutions.extend(user);
+        }
+        Ok(substitutions)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let table: BTreeMap<String, Vec<Substitute>> = toml::from_str(content)?;
+        let mut substitutions = BTreeMap::new();
+        for (ingredient, substitutes) in table {
+            if let Some(bad) = substitutes
+                .iter()
+                .find(|s| !s.ratio.is_finite() || s.ratio <= 0.0)
+            {
+                bail!(
+                    "The ratio of {} for {ingredient} must be a positive number, not {}",
+                    bad.name,
+                    bad.ratio
+                );
+            }
+            substitutions
+                .entry(ingredient.to_lowercase())
+                .or_insert_with(Vec::new)
+                .extend(substitutes);
+        }
+        Ok(Self(substitutions))
+    }
+
+    /// Put the substitutes of `other` first
+    fn extend(&mut self, other: Self) {
+        for (ingredient, mut substitutes) in other.0 {
+            let existing: Vec<_> = self
+                .0
+                .remove(&ingredient)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|old| {
+                    !substitutes
+                        .iter()
+                        .any(|new| same_name(&new.name, &old.name))
+                })
+                .collect();
+            substitutes.extend(existing);
+            self.0.insert(ingredient, substitutes);
+        }
+    }
+
+    /// Substitutes for an ingredient, best first
+    pub fn get(&self, ingredient: &str) -> &[Substitute] {
+        self.0
+            .get(&ingredient.trim().to_lowercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The substitute called `name` for `ingredient`, if known
+    pub fn find(&self, ingredient: &str, name: &str) -> Option<&Substitute> {
+        self.get(ingredient)
+            .iter()
+            .find(|substitute| same_name(&substitute.name, name))
+    }
+}
+
+/// A substitution asked for on the command line, like "butter=margarine"
+#[derive(Debug, Clone)]
+pub struct SubstitutionArg {
+    pub ingredient: String,
+    pub substitute: String,
+}
+
+impl std::str::FromStr for SubstitutionArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((ingredient, substitute))
+                if !ingredient.trim().is_empty() && !substitute.trim().is_empty() =>
+            {
+                Ok(Self {
+                    ingredient: ingredient.trim().to_string(),
+                    substitute: substitute.trim().to_string(),
+                })
+            }
+            _ => Err("expected INGREDIENT=SUBSTITUTE, like \"butter=margarine\"".to_string()),
+        }
+    }
+}
+
+/// Replace an ingredient everywhere in a recipe, converting its quantities
+///
+/// Each replaced ingredient gets a note saying what it replaces. Returns how
+/// many times the ingredient was in the recipe.
+pub fn substitute(recipe: &mut Recipe, ingredient: &str, substitute: &Substitute) -> usize {
+    let mut replaced = 0;
+    for igr in &mut recipe.ingredients {
+        if !same_name(&igr.name, ingredient) {
+            continue;
+        }
+        replaced += 1;
+
+        let note = match &substitute.note {
+            Some(note) => format!("instead of {}, {note}", igr.name),
+            None => format!("instead of {}", igr.name),
+        };
+        igr.note = Some(match igr.note.take() {
+            Some(original) => format!("{original}; {note}"),
+            None => note,
+        });
+        igr.name = substitute.name.clone();
+        igr.alias = None;
+        if let Some(quantity) = &igr.quantity {
+            let unit = substitute
+                .unit
+                .clone()
+                .or_else(|| quantity.unit().map(str::to_string));
+            igr.quantity = Some(Quantity::new(
+                scale_value(quantity.value(), substitute.ratio),
+                unit,
+            ));
+        }
+    }
+    replaced
+}
+
+fn scale_value(value: &Value, ratio: f64) -> Value {
+    let scale = |n: &Number| Number::from(n.value() * ratio);
+    match value {
+        Value::Number(n) => Value::Number(scale(n)),
+        Value::Range { start, end } => Value::Range {
+            start: scale(start),
+            end: scale(end),
+        },
+        Value::Text(text) => Value::Text(text.clone()),
+    }
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_substitutions_come_first() {
+        let mut substitutions = Substitutions::bundled();
+        substitutions.extend(
+            Substitutions::parse(
+                "[[Butter]]\nname = \"ghee\"\n[[butter]]\nname = \"Margarine\"\nratio = 0.9\n",
+            )
+            .unwrap(),
+        );
+        let names: Vec<_> = substitutions
+            .get("butter")
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(names[..2], ["ghee", "Margarine"]);
+        assert_eq!(
+            names
+                .iter()
+                .filter(|n| n.eq_ignore_ascii_case("margarine"))
+                .count(),
+            1
+        );
+        assert_eq!(
+            substitutions.find("BUTTER", "margarine").unwrap().ratio,
+            0.9
+        );
+    }
+
+    #[test]
+    fn test_invalid_ratio() {
+        for ratio in ["0", "-1", "nan", "inf"] {
+            let content = format!("[[butter]]\nname = \"oil\"\nratio = {ratio}\n");
+            assert!(Substitutions::parse(&content).is_err(), "{ratio}");
+        }
+    }
+
+    #[test]
+    fn test_substitute() {
+        let (mut recipe, _) = crate::util::PARSER
+            .parse("Cream @butter{100%g} with @sugar{50%g}.\n")
+            .into_result()
+            .unwrap();
+        let oil = Substitute {
+            name: "olive oil".to_string(),
+            ratio: 0.75,
+            unit: None,
+            note: None,
+        };
+        assert_eq!(substitute(&mut recipe, "Butter", &oil), 1);
+        let igr = &recipe.ingredients[0];
+        assert_eq!(igr.name, "olive oil");
+        assert_eq!(igr.quantity.as_ref().unwrap().to_string(), "75 g");
+        assert_eq!(igr.note.as_deref(), Some("instead of butter"));
+    }
+
+    #[test]
+    fn test_parse_arg() {
+        let arg: SubstitutionArg = " butter = olive oil".parse().unwrap();
+        assert_eq!(arg.ingredient, "butter");
+        assert_eq!(arg.substitute, "olive oil");
+        assert!("butter".parse::<SubstitutionArg>().is_err());
+    }
+}
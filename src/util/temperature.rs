@@ -0,0 +1,187 @@
+//! Temperatures converted between Celsius and Fahrenheit
+//!
+//! Temperatures are found in the step text, like "bake at 350°F" or "350
+//! degrees F", and in inline quantities, when the inline-quantities
+//! extension is on. Converted temperatures are rounded to 5 degrees, like
+//! oven dials.
+
+use std::sync::LazyLock;
+
+use cooklang::{
+    model::Item,
+    quantity::{Number, Quantity, Value},
+    Content, Recipe,
+};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+use super::Setting;
+
+static TEMPERATURE: Setting<TemperatureUnit> = Setting::new();
+
+/// "350°F", "180 ºC", "350 degrees F", "180 degrees Celsius", and ranges
+/// like "350-375°F"
+///
+/// A dash after a number makes a range, so it's a minus sign only where no
+/// digit comes before it.
+static IN_TEXT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?i)(?:(-?\d+(?:[.,]\d+)?)(\s?[-–]\s?))?",
+        r"(-?\d+(?:[.,]\d+)?)(\s?)(?:°|º|degrees?\s+)(c|f|celsius|fahrenheit)\b",
+    ))
+    .unwrap()
+});
+
+/// Scale temperatures are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// The scale of a unit, like "°F", "C" or "celsius"
+    fn of_unit(unit: &str) -> Option<Self> {
+        let unit = unit.trim().trim_start_matches(['°', 'º']).to_lowercase();
+        match unit.as_str() {
+            "c" | "celsius" => Some(TemperatureUnit::Celsius),
+            "f" | "fahrenheit" => Some(TemperatureUnit::Fahrenheit),
+            _ => None,
+        }
+    }
+
+    /// A temperature in `from` in this scale, rounded to 5 degrees
+    fn convert(self, value: f64, from: Self) -> f64 {
+        let converted = match (from, self) {
+            (TemperatureUnit::Fahrenheit, TemperatureUnit::Celsius) => (value - 32.0) * 5.0 / 9.0,
+            (TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit) => value * 9.0 / 5.0 + 32.0,
+            _ => return value,
+        };
+        (converted / 5.0).round() * 5.0
+    }
+}
+
+/// Convert temperatures in every recipe to `unit` from now on, `None` to
+/// keep them as written
+pub fn configure_temperature(unit: Option<TemperatureUnit>) {
+    if let Some(unit) = unit {
+        TEMPERATURE.set(unit);
+    }
+}
+
+/// The configured scale, if any
+pub fn temperature() -> Option<TemperatureUnit> {
+    TEMPERATURE.get().copied()
+}
+
+/// Convert the temperatures in the steps and inline quantities of a recipe
+pub fn convert_recipe(recipe: &mut Recipe, to: TemperatureUnit) {
+    for quantity in &mut recipe.inline_quantities {
+        if let Some(converted) = convert_quantity(quantity, to) {
+            *quantity = converted;
+        }
+    }
+    for section in &mut recipe.sections {
+        for content in &mut section.content {
+            match content {
+                Content::Step(step) => {
+                    for item in &mut step.items {
+                        if let Item::Text { value } = item {
+                            *value = convert_text(value, to);
+                        }
+                    }
+                }
+                Content::Text(text) => *text = convert_text(text, to),
+            }
+        }
+    }
+}
+
+fn convert_quantity(quantity: &Quantity, to: TemperatureUnit) -> Option<Quantity> {
+    let from = TemperatureUnit::of_unit(quantity.unit()?)?;
+    if from == to {
+        return None;
+    }
+    let convert = |n: &Number| Number::from(to.convert(n.value(), from));
+    let value = match quantity.value() {
+        Value::Number(n) => Value::Number(convert(n)),
+        Value::Range { start, end } => Value::Range {
+            start: convert(start),
+            end: convert(end),
+        },
+        Value::Text(_) => return None,
+    };
+    Some(Quantity::new(value, Some(to.symbol().to_string())))
+}
+
+/// Text with the temperatures in it converted
+pub fn convert_text(text: &str, to: TemperatureUnit) -> String {
+    IN_TEXT
+        .replace_all(text, |caps: &Captures| {
+            let parse = |value: &str| value.replace(',', ".").parse::<f64>().ok();
+            let (Some(from), Some(value)) = (TemperatureUnit::of_unit(&caps[5]), parse(&caps[3]))
+            else {
+                return caps[0].to_string();
+            };
+            if from == to {
+                return caps[0].to_string();
+            }
+            let start = match caps.get(1).map(|start| parse(start.as_str())) {
+                Some(Some(start)) => format!("{}{}", to.convert(start, from), &caps[2]),
+                Some(None) => return caps[0].to_string(),
+                None => String::new(),
+            };
+            format!(
+                "{start}{}{}{}",
+                to.convert(value, from),
+                &caps[4],
+                to.symbol()
+            )
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_text() {
+        let to_c = |text| convert_text(text, TemperatureUnit::Celsius);
+        assert_eq!(to_c("Bake at 350°F for 20 min"), "Bake at 175°C for 20 min");
+        assert_eq!(to_c("Heat to 425 degrees F."), "Heat to 220 °C.");
+        assert_eq!(to_c("Cool to 40 ºC"), "Cool to 40 ºC");
+        assert_eq!(
+            convert_text("Preheat to 180 °C", TemperatureUnit::Fahrenheit),
+            "Preheat to 355 °F"
+        );
+        assert_eq!(to_c("Add 2 cups of flour"), "Add 2 cups of flour");
+    }
+
+    #[test]
+    fn test_convert_range() {
+        let to_c = |text| convert_text(text, TemperatureUnit::Celsius);
+        let to_f = |text| convert_text(text, TemperatureUnit::Fahrenheit);
+        assert_eq!(to_c("Bake at 350-375°F"), "Bake at 175-190°C");
+        assert_eq!(to_c("Bake at 350–375°F"), "Bake at 175–190°C");
+        assert_eq!(to_c("Bake at 350 - 375 degrees F"), "Bake at 175 - 190 °C");
+        assert_eq!(to_f("Chill to 2-4°C"), "Chill to 35-40°F");
+        assert_eq!(to_f("Freeze at -20°C"), "Freeze at -5°F");
+        assert_eq!(to_f("Keep at -20-0°C"), "Keep at -5-30°F");
+    }
+
+    #[test]
+    fn test_convert_quantity() {
+        let quantity = Quantity::new(Value::Number(Number::from(400.0)), Some("F".to_string()));
+        let converted = convert_quantity(&quantity, TemperatureUnit::Celsius).unwrap();
+        assert_eq!(converted.to_string(), "205 °C");
+    }
+}
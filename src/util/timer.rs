@@ -0,0 +1,196 @@
+//! Countdown timers from recipe `~{}` syntax or free text like "25 min pizza",
+//! shared by `cook timer` and the cooking mode.
+
+use anyhow::{bail, Result};
+use cooklang::{quantity::Value, Content, Recipe};
+use std::io::Write;
+use std::time::Duration;
+
+use super::{pantry::Amount, PARSER};
+
+/// A timer with its display label and duration (if it has a usable one)
+#[derive(Debug, Clone)]
+pub struct TimerSpec {
+    pub label: String,
+    pub duration: Option<Duration>,
+}
+
+/// Duration of a timer, numbers without a unit are minutes
+pub fn timer_duration(value: f64, unit: Option<&str>) -> Option<Duration> {
+    let seconds = match unit {
+        Some(unit) => {
+            Amount::new(value, Some(unit.to_string())).value_in(Some("s"), PARSER.converter())?
+        }
+        None => value * 60.0,
+    };
+    // Negative, too long or not a number at all isn't usable either
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+}
+
+/// The timer at `index` in the recipe
+pub fn recipe_timer(recipe: &Recipe, index: usize) -> TimerSpec {
+    let t = &recipe.timers[index];
+    let label = match (&t.quantity, &t.name) {
+        (Some(q), Some(name)) => format!("{q} ({name})"),
+        (Some(q), None) => q.to_string(),
+        (None, Some(name)) => name.clone(),
+        (None, None) => String::new(),
+    };
+    let duration = t.quantity.as_ref().and_then(|q| match q.value() {
+        Value::Number(n) => timer_duration(n.value(), q.unit()),
+        _ => None,
+    });
+    TimerSpec { label, duration }
+}
+
+/// Timers of every step of the recipe, numbered like the cooking mode (all
+/// sections in a row, starting at 1)
+pub fn step_timers(recipe: &Recipe) -> Vec<Vec<TimerSpec>> {
+    recipe
+        .sections
+        .iter()
+        .flat_map(|section| &section.content)
+        .filter_map(|content| match content {
+            Content::Step(step) => Some(step),
+            _ => None,
+        })
+        .map(|step| {
+            step.items
+                .iter()
+                .filter_map(|item| match item {
+                    &cooklang::Item::Timer { index } => Some(recipe_timer(recipe, index)),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Parse a timer like "25 min pizza", "1.5h roast", "90s" or "10 eggs"
+///
+/// A number without a unit is minutes. Anything after the amount is the
+/// timer's name. Durations humantime understands, like "1h 30m", work too.
+pub fn parse_timer(s: &str) -> Result<TimerSpec> {
+    let s = s.trim();
+
+    if let Ok(duration) = humantime::parse_duration(s) {
+        return Ok(TimerSpec {
+            label: s.to_string(),
+            duration: Some(duration),
+        });
+    }
+
+    let number_end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ',' || c == '/'))
+        .unwrap_or(s.len());
+    let (number, rest) = s.split_at(number_end);
+    let Some(value) = parse_number(number) else {
+        bail!("Invalid timer '{s}', expected something like \"25 min pizza\"");
+    };
+
+    let rest = rest.trim_start();
+    let (unit, name) = match rest.split_once(char::is_whitespace) {
+        Some((word, name)) => (word, name.trim()),
+        None => (rest, ""),
+    };
+    let (duration, name) = if unit.is_empty() {
+        (timer_duration(value, None), name)
+    } else if let Some(duration) = timer_duration(value, Some(unit)) {
+        (Some(duration), name)
+    } else {
+        // not a unit, so it's the start of the name
+        (timer_duration(value, None), rest)
+    };
+
+    let Some(duration) = duration else {
+        bail!("Invalid timer '{s}', the duration must be positive");
+    };
+    let amount = if name.is_empty() {
+        s
+    } else {
+        s[..s.len() - name.len()].trim_end()
+    };
+    let label = if name.is_empty() {
+        amount.to_string()
+    } else {
+        format!("{amount} ({name})")
+    };
+
+    Ok(TimerSpec {
+        label,
+        duration: Some(duration),
+    })
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    if let Some((n, d)) = s.split_once('/') {
+        let (n, d) = (n.parse::<f64>().ok()?, d.parse::<f64>().ok()?);
+        return (d != 0.0).then(|| n / d);
+    }
+    s.replace(',', ".").parse().ok()
+}
+
+/// Format a remaining duration as m:ss or h:mm:ss
+pub fn format_countdown(d: Duration) -> String {
+    // round up so a timer never shows 0:00 while still running
+    let secs = d.as_secs() + u64::from(d.subsec_nanos() > 0);
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+/// Ring the terminal bell and send a desktop notification
+///
+/// The notification uses the OSC 9 escape sequence, which many terminals
+/// (iTerm2, Windows Terminal, kitty, WezTerm, ...) show as a system
+/// notification. Terminals without support ignore it, and the escape codes
+/// are left out when stdout isn't a terminal.
+pub fn notify(message: &str) -> std::io::Result<()> {
+    let mut stdout = anstream::stdout();
+    write!(stdout, "\x07\x1b]9;{message}\x07")?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timer() {
+        let t = parse_timer("25 min pizza").unwrap();
+        assert_eq!(t.duration, Some(Duration::from_secs(25 * 60)));
+        assert_eq!(t.label, "25 min (pizza)");
+
+        let t = parse_timer("10 eggs").unwrap();
+        assert_eq!(t.duration, Some(Duration::from_secs(10 * 60)));
+        assert_eq!(t.label, "10 (eggs)");
+
+        let t = parse_timer("1h 30m").unwrap();
+        assert_eq!(t.duration, Some(Duration::from_secs(90 * 60)));
+
+        let t = parse_timer("1/2 h").unwrap();
+        assert_eq!(t.duration, Some(Duration::from_secs(30 * 60)));
+
+        assert!(parse_timer("pizza").is_err());
+        assert!(parse_timer("0 min").is_err());
+    }
+
+    #[test]
+    fn test_timer_duration() {
+        assert_eq!(timer_duration(1.5, None), Some(Duration::from_secs(90)));
+        assert_eq!(timer_duration(1e300, Some("h")), None);
+        assert_eq!(timer_duration(f64::NAN, None), None);
+    }
+
+    #[test]
+    fn test_format_countdown() {
+        assert_eq!(format_countdown(Duration::from_secs(65)), "1:05");
+        assert_eq!(format_countdown(Duration::from_millis(500)), "0:01");
+        assert_eq!(format_countdown(Duration::from_secs(3725)), "1:02:05");
+    }
+}
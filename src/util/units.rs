@@ -0,0 +1,128 @@
+//! Custom units from `units.toml`
+//!
+//! The file uses the Cooklang units file format to add units or change
+//! existing ones (like the size of a cup), plus a `[densities]` table in
+//! grams per millilitre so volumes of common ingredients can be converted to
+//! weights and back.
+
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use cooklang::{
+    convert::{ConverterBuilder, UnitsFile},
+    Converter,
+};
+use std::collections::BTreeMap;
+
+use super::{pantry::Amount, Setting};
+
+/// Name of the units file in the config directories
+pub const UNITS_FILE: &str = "units.toml";
+
+static DENSITIES: Setting<BTreeMap<String, f64>> = Setting::new();
+
+/// The contents of a `units.toml`
+#[derive(Debug)]
+pub struct CustomUnits {
+    units: UnitsFile,
+    /// Grams per millilitre by lowercase ingredient name
+    pub densities: BTreeMap<String, f64>,
+}
+
+impl CustomUnits {
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+        Self::parse(&content).with_context(|| format!("Invalid units file {path}"))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(content)?;
+
+        let densities = match table.remove("densities") {
+            Some(densities) => densities
+                .try_into::<BTreeMap<String, f64>>()
+                .context("[densities] must map ingredient names to grams per millilitre")?,
+            None => BTreeMap::new(),
+        };
+        if let Some((name, density)) = densities.iter().find(|(_, d)| !d.is_finite() || **d <= 0.0)
+        {
+            anyhow::bail!("The density of {name} must be a positive number, not {density}");
+        }
+
+        let units = toml::Value::Table(table).try_into::<UnitsFile>()?;
+        Ok(Self {
+            units,
+            densities: densities
+                .into_iter()
+                .map(|(name, density)| (name.to_lowercase(), density))
+                .collect(),
+        })
+    }
+
+    /// The bundled units with these on top
+    pub fn converter(self) -> Result<Converter> {
+        let converter = ConverterBuilder::new()
+            .with_units_file(UnitsFile::bundled())?
+            .with_units_file(self.units)?
+            .finish()?;
+        Ok(converter)
+    }
+}
+
+/// Use the densities when converting ingredient amounts
+pub fn configure_densities(densities: BTreeMap<String, f64>) {
+    DENSITIES.set(densities);
+}
+
+/// Density of an ingredient in grams per millilitre, if configured
+pub fn density(ingredient: &str) -> Option<f64> {
+    DENSITIES.get()?.get(&ingredient.to_lowercase()).copied()
+}
+
+/// Convert an amount of an ingredient to `unit`
+///
+/// Like [`Amount::value_in`], but volumes and weights are converted into
+/// each other when the ingredient has a density.
+pub fn convert(
+    amount: &Amount,
+    unit: Option<&str>,
+    ingredient: &str,
+    converter: &Converter,
+) -> Option<f64> {
+    if let Some(value) = amount.value_in(unit, converter) {
+        return Some(value);
+    }
+    let density = density(ingredient)?;
+    if let Some(ml) = amount.value_in(Some("ml"), converter) {
+        return Amount::new(ml * density, Some("g".to_string())).value_in(unit, converter);
+    }
+    let grams = amount.value_in(Some("g"), converter)?;
+    Amount::new(grams / density, Some("ml".to_string())).value_in(unit, converter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_densities() {
+        let units = CustomUnits::parse("[densities]\nFlour = 0.53\nhoney = 1.42\n").unwrap();
+        assert_eq!(units.densities.get("flour"), Some(&0.53));
+        assert_eq!(units.densities.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_density() {
+        assert!(CustomUnits::parse("[densities]\nflour = 0\n").is_err());
+        assert!(CustomUnits::parse("[densities]\nflour = nan\n").is_err());
+        assert!(CustomUnits::parse("[densities]\nflour = inf\n").is_err());
+        assert!(CustomUnits::parse("[densities]\nflour = \"heavy\"\n").is_err());
+    }
+
+    #[test]
+    fn test_empty_file_keeps_bundled_units() {
+        let converter = CustomUnits::parse("").unwrap().converter().unwrap();
+        let amount = Amount::new(1.0, Some("kg".to_string()));
+        assert_eq!(amount.value_in(Some("g"), &converter), Some(1000.0));
+    }
+}
@@ -0,0 +1,158 @@
+//! Shopping lists as iCalendar to-dos, for reminder and task apps
+//!
+//! Every item is a VTODO with its aisle category, or recipe, as the
+//! category. A list can be written as one calendar file to import, or put
+//! on a CalDAV task list like iOS Reminders or Nextcloud Tasks, where every
+//! to-do is a resource of its own.
+
+use anyhow::{bail, Context as _, Result};
+use chrono::NaiveDate;
+
+use super::{
+    calendar::{escape, fold},
+    names::slug,
+};
+
+/// An item to buy
+#[derive(Debug, Clone)]
+pub struct Todo {
+    pub name: String,
+    /// Like "500 g"
+    pub quantity: Option<String>,
+    pub category: Option<String>,
+}
+
+impl Todo {
+    /// Like "flour (500 g)"
+    pub fn summary(&self) -> String {
+        match &self.quantity {
+            Some(quantity) => format!("{} ({quantity})", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Where and how to upload the to-dos
+#[derive(Debug, Clone)]
+pub struct CalDav {
+    /// The task list, like https://cloud.example.com/remote.php/dav/calendars/me/shopping/
+    pub url: String,
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+/// All the to-dos in one calendar
+pub fn render_todos(todos: &[Todo], date: NaiveDate) -> String {
+    let mut lines = header();
+    for todo in todos {
+        lines.extend(vtodo(todo, date));
+    }
+    lines.push("END:VCALENDAR".to_string());
+    to_ics(&lines)
+}
+
+/// Put every to-do on a CalDAV task list, returns how many were uploaded
+///
+/// To-dos have the date and the item's name in their id, so uploading a
+/// list again on the same day updates the to-dos instead of adding them
+/// twice.
+pub async fn upload(caldav: &CalDav, todos: &[Todo], date: NaiveDate) -> Result<usize> {
+    let client = reqwest::Client::new();
+    let base = caldav.url.trim_end_matches('/');
+    for todo in todos {
+        let mut lines = header();
+        lines.extend(vtodo(todo, date));
+        lines.push("END:VCALENDAR".to_string());
+
+        let url = format!("{base}/{}.ics", uid(todo, date).replace('@', "-"));
+        let mut request = client
+            .put(&url)
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            .body(to_ics(&lines));
+        if let Some(user) = &caldav.user {
+            request = request.basic_auth(user, caldav.password.as_ref());
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload to {base}"))?;
+        if !response.status().is_success() {
+            bail!(
+                "The CalDAV server refused '{}' with {}",
+                todo.name,
+                response.status()
+            );
+        }
+    }
+    Ok(todos.len())
+}
+
+fn header() -> Vec<String> {
+    vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//cooklang//cookcli//EN".to_string(),
+    ]
+}
+
+fn vtodo(todo: &Todo, date: NaiveDate) -> Vec<String> {
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", uid(todo, date)),
+        format!("DTSTAMP:{stamp}"),
+        format!("SUMMARY:{}", escape(&todo.summary())),
+        "STATUS:NEEDS-ACTION".to_string(),
+    ];
+    if let Some(category) = &todo.category {
+        lines.push(format!("CATEGORIES:{}", escape(category)));
+    }
+    lines.push("END:VTODO".to_string());
+    lines
+}
+
+fn uid(todo: &Todo, date: NaiveDate) -> String {
+    format!(
+        "shopping-{}-{}-{}@cookcli",
+        date.format("%Y%m%d"),
+        slug(todo.category.as_deref().unwrap_or("other")),
+        slug(&todo.name)
+    )
+}
+
+fn to_ics(lines: &[String]) -> String {
+    let mut ics = String::new();
+    for line in lines {
+        fold(line, &mut ics);
+    }
+    ics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_todos() {
+        let todos = [
+            Todo {
+                name: "flour".to_string(),
+                quantity: Some("500 g".to_string()),
+                category: Some("baking".to_string()),
+            },
+            Todo {
+                name: "salt, pepper".to_string(),
+                quantity: None,
+                category: None,
+            },
+        ];
+        let ics = render_todos(&todos, NaiveDate::from_ymd_opt(2024, 3, 17).unwrap());
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VTODO").count(), 2);
+        assert!(
+            ics.contains("SUMMARY:flour (500 g)\r\nSTATUS:NEEDS-ACTION\r\nCATEGORIES:baking\r\n")
+        );
+        assert!(ics.contains("SUMMARY:salt\\, pepper\r\n"));
+        assert!(ics.contains("UID:shopping-20240317-baking-flour@cookcli"));
+    }
+}
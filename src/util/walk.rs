@@ -0,0 +1,276 @@
+//! Which files of a collection commands see
+//!
+//! Collections are often put together from shared folders with symlinks,
+//! and carry hidden directories from editors and sync tools. Every command
+//! lists recipes through [`build_tree`] and [`search`] here, so they all
+//! agree on whether links are followed and hidden directories included, as
+//! set in `[collection]` in cook.toml. The inbox isn't part of the
+//! collection either, its files aren't imported yet.
+
+use anyhow::{Context as _, Result};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use cooklang_find::{RecipeEntry, RecipeTree};
+use std::collections::{btree_map::Entry, HashSet};
+use std::path::PathBuf;
+
+use super::{aliases, Setting};
+
+static POLICY: Setting<WalkPolicy> = Setting::new();
+
+/// How directories are walked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkPolicy {
+    /// Include recipes reached through symlinks
+    pub follow_symlinks: bool,
+    /// Include dot files and what's in dot directories
+    pub hidden: bool,
+    /// Directories left out, relative to the collection unless absolute
+    pub skip: Vec<Utf8PathBuf>,
+}
+
+impl Default for WalkPolicy {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: true,
+            hidden: true,
+            skip: Vec::new(),
+        }
+    }
+}
+
+impl WalkPolicy {
+    /// The policy set with [`configure_walk`], or the default
+    pub fn current() -> Self {
+        POLICY.get().cloned().unwrap_or_default()
+    }
+
+    /// Whether a file found under `base_path` is part of the collection
+    pub fn allows(&self, base_path: &Utf8Path, path: &Utf8Path) -> bool {
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        if self
+            .skip
+            .iter()
+            .any(|dir| relative.starts_with(dir) || path.starts_with(dir))
+        {
+            return false;
+        }
+        let names = relative.components().filter_map(|c| match c {
+            Utf8Component::Normal(name) => Some(name),
+            _ => None,
+        });
+
+        let mut current = base_path.to_path_buf();
+        for name in names {
+            if !self.hidden && name.starts_with('.') {
+                return false;
+            }
+            current.push(name);
+            if !self.follow_symlinks && is_symlink(&current) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Walk directories with `policy` from now on
+pub fn configure_walk(policy: WalkPolicy) {
+    POLICY.set(policy);
+}
+
+/// The recipes and menus of a collection, following the walk policy
+///
+/// When links are followed, a directory or recipe reached through several
+/// of them is only listed the first time, and a link back up the
+/// collection isn't walked into again.
+pub fn build_tree(base_path: impl AsRef<Utf8Path>) -> Result<RecipeTree> {
+    let base_path = base_path.as_ref();
+    let mut walk = Walk {
+        base_path,
+        policy: WalkPolicy::current(),
+        dirs: HashSet::new(),
+        files: HashSet::new(),
+    };
+    let name = base_path.file_name().unwrap_or_default().to_string();
+    let mut tree = RecipeTree::new(name, base_path.to_path_buf());
+    walk.dir(base_path, &mut tree)?;
+    Ok(tree)
+}
+
+/// Where a walk has been
+struct Walk<'a> {
+    base_path: &'a Utf8Path,
+    policy: WalkPolicy,
+    /// Directories walked, resolved, so link cycles end
+    dirs: HashSet<PathBuf>,
+    /// Recipes listed, resolved
+    files: HashSet<PathBuf>,
+}
+
+impl Walk<'_> {
+    fn dir(&mut self, dir: &Utf8Path, tree: &mut RecipeTree) -> Result<()> {
+        let entries = match dir.read_dir_utf8() {
+            Ok(entries) => entries,
+            Err(e) if dir == self.base_path => {
+                return Err(e).with_context(|| format!("Failed to read {dir}"));
+            }
+            Err(e) => {
+                tracing::warn!("Skipping {dir}: {e}");
+                return Ok(());
+            }
+        };
+        if !self.dirs.insert(std::fs::canonicalize(dir)?) {
+            return Ok(());
+        }
+        // Links last, so what's reached both ways is listed where it is
+        let mut paths: Vec<(bool, Utf8PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| (is_symlink(entry.path()), entry.into_path()))
+            .collect();
+        paths.sort();
+
+        for (_, path) in paths {
+            if !self.policy.allows(self.base_path, &path) {
+                continue;
+            }
+            if path.is_dir() {
+                let name = path.file_name().unwrap_or_default().to_string();
+                let mut child = RecipeTree::new(name.clone(), path.clone());
+                self.dir(&path, &mut child)?;
+                if child.children.is_empty() {
+                    continue;
+                }
+                // Next to a recipe of the same name, like Pasta.cook
+                match tree.children.entry(name) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(child);
+                    }
+                    Entry::Occupied(mut entry) => entry.get_mut().children.extend(child.children),
+                }
+            } else if matches!(path.extension(), Some("cook" | "menu")) {
+                // The same file twice means links lead to it more than once
+                if let Ok(real) = std::fs::canonicalize(&path) {
+                    if !self.files.insert(real) {
+                        continue;
+                    }
+                }
+                let name = path.file_stem().unwrap_or_default().to_string();
+                let entry = RecipeEntry::from_path(path.clone())?;
+                tree.children
+                    .entry(name.clone())
+                    .or_insert_with(|| RecipeTree::new(name, path))
+                    .recipe = Some(entry);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Recipes matching a search, following the walk policy
+///
+/// Ingredients in the query also find recipes using one of their aliases
+/// from `[aliases]`, or the name an alias stands for.
+pub fn search(base_path: &Utf8Path, query: &str) -> Result<Vec<RecipeEntry>> {
+    let policy = WalkPolicy::current();
+    let mut seen = HashSet::new();
+    // Recipes using another name of an ingredient come after the others
+    let mut found = Vec::new();
+    for query in aliases::query_variants(query) {
+        found.extend(
+            cooklang_find::search(base_path, &query)?
+                .into_iter()
+                .filter(|entry| keep(entry, base_path, &policy, &mut seen)),
+        );
+    }
+    Ok(found)
+}
+
+fn keep(
+    entry: &RecipeEntry,
+    base_path: &Utf8Path,
+    policy: &WalkPolicy,
+    seen: &mut HashSet<PathBuf>,
+) -> bool {
+    let Some(path) = entry.path() else {
+        return true;
+    };
+    if !policy.allows(base_path, path) {
+        return false;
+    }
+    // The same file twice means links lead to it more than once
+    match std::fs::canonicalize(path) {
+        Ok(real) => seen.insert(real),
+        Err(_) => true,
+    }
+}
+
+fn is_symlink(path: &Utf8Path) -> bool {
+    path.symlink_metadata()
+        .is_ok_and(|meta| meta.file_type().is_symlink())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hidden_directories() {
+        let base = Utf8Path::new("/recipes");
+        let path = Utf8Path::new("/recipes/.trash/Soup.cook");
+        assert!(WalkPolicy::default().allows(base, path));
+        let no_hidden = WalkPolicy {
+            hidden: false,
+            ..Default::default()
+        };
+        assert!(!no_hidden.allows(base, path));
+        assert!(no_hidden.allows(base, Utf8Path::new("/recipes/Soups/Leek.cook")));
+    }
+
+    #[test]
+    fn test_skip() {
+        let base = Utf8Path::new("/recipes");
+        let policy = WalkPolicy {
+            skip: vec!["inbox".into(), "/drop".into()],
+            ..Default::default()
+        };
+        assert!(!policy.allows(base, Utf8Path::new("/recipes/inbox/failed/Soup.cook")));
+        assert!(!policy.allows(base, Utf8Path::new("/drop/Soup.cook")));
+        assert!(policy.allows(base, Utf8Path::new("/recipes/Soups/inbox.cook")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = Utf8Path::from_path(dir.path()).unwrap();
+        std::fs::create_dir(base.join("Shared")).unwrap();
+        std::os::unix::fs::symlink(base.join("Shared"), base.join("Linked")).unwrap();
+
+        let linked = base.join("Linked/Soup.cook");
+        assert!(WalkPolicy::default().allows(base, &linked));
+        let no_links = WalkPolicy {
+            follow_symlinks: false,
+            ..Default::default()
+        };
+        assert!(!no_links.allows(base, &linked));
+        assert!(no_links.allows(base, &base.join("Shared/Soup.cook")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_cycle() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = Utf8Path::from_path(dir.path()).unwrap();
+        std::fs::create_dir(base.join("Soups")).unwrap();
+        std::fs::write(base.join("Soups/Leek.cook"), "Boil @leeks{2}.\n").unwrap();
+        // A link back up the collection, and a second way to the soups
+        std::os::unix::fs::symlink(base, base.join("Soups/All")).unwrap();
+        std::os::unix::fs::symlink(base.join("Soups"), base.join("More soups")).unwrap();
+
+        let tree = build_tree(base).unwrap();
+        assert_eq!(tree.children.len(), 1);
+        let soups = &tree.children["Soups"];
+        assert!(soups.children["Leek"].recipe.is_some());
+        assert!(!soups.children.contains_key("All"));
+    }
+}
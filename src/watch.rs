@@ -0,0 +1,219 @@
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::{
+    util::{diagnostics::Diagnostic, resolve_to_absolute_path, timer, PARSER},
+    Context, LOCAL_CONFIG_DIR,
+};
+
+/// How long to wait for more changes before re-running, editors often
+/// write a file in several steps
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Validate the collection on every change, like `cook doctor validate`
+    #[arg(long, conflicts_with = "command")]
+    lint: bool,
+
+    /// Parse every recipe or menu as soon as it's saved and print its errors
+    ///
+    /// Only the files that changed are checked, so problems show up right
+    /// away even in large collections.
+    #[arg(long, conflicts_with_all = ["command", "lint"])]
+    validate: bool,
+
+    /// With --validate, also send a desktop notification for a broken file
+    #[arg(long, requires = "validate")]
+    notify: bool,
+
+    /// Clear the screen before every run
+    #[arg(short, long)]
+    clear: bool,
+
+    /// Directory of the recipe collection to watch
+    ///
+    /// Defaults to the current directory.
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    base_path: Option<Utf8PathBuf>,
+
+    /// The cook command to run, after --
+    #[arg(last = true, value_name = "COMMAND")]
+    command: Vec<String>,
+}
+
+pub fn run(ctx: &Context, args: WatchArgs) -> Result<()> {
+    let base_path = resolve_to_absolute_path(args.base_path.as_ref().unwrap_or(ctx.base_path()))?;
+
+    if args.validate {
+        return validate(&base_path, args.clear, args.notify);
+    }
+
+    let command = if args.lint {
+        vec![
+            "doctor".to_string(),
+            "validate".to_string(),
+            "--base-path".to_string(),
+            base_path.to_string(),
+        ]
+    } else if args.command.is_empty() {
+        bail!(
+            "Nothing to run\n\
+             Pass a command after --, like `cook watch -- recipe read pizza.cook`, \
+             or use --lint or --validate"
+        );
+    } else {
+        args.command
+    };
+    if command.first().is_some_and(|c| c == "watch") {
+        bail!("cook watch can't watch itself");
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).context("Failed to start the file watcher")?;
+    watcher
+        .watch(base_path.as_std_path(), RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {base_path}"))?;
+
+    let cook = std::env::current_exe().context("Failed to find the cook executable")?;
+    let run_command = || {
+        if args.clear {
+            // Left out by anstream when stdout isn't a terminal
+            let mut stdout = anstream::stdout();
+            let _ = write!(stdout, "\x1b[2J\x1b[H");
+            let _ = stdout.flush();
+        }
+        eprintln!(
+            "[{}] cook {}",
+            chrono::Local::now().format("%H:%M:%S"),
+            command.join(" ")
+        );
+        match Command::new(&cook).args(&command).status() {
+            Ok(status) if !status.success() => eprintln!("cook exited with {status}"),
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to run cook: {e}"),
+        }
+        eprintln!("Watching {base_path} for changes, press Ctrl-C to stop");
+    };
+
+    run_command();
+    while let Ok(event) = rx.recv() {
+        if !is_relevant(&event, &base_path) {
+            continue;
+        }
+        // Wait for the burst of events from a single save to settle
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+        run_command();
+    }
+
+    Ok(())
+}
+
+/// Check recipes and menus as they're saved, until interrupted
+///
+/// Remembers which files are broken, so fixing one is reported too.
+fn validate(base_path: &Utf8Path, clear: bool, desktop: bool) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).context("Failed to start the file watcher")?;
+    watcher
+        .watch(base_path.as_std_path(), RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {base_path}"))?;
+
+    let mut broken = BTreeSet::new();
+    eprintln!("Validating {base_path} as files change, press Ctrl-C to stop");
+    while let Ok(event) = rx.recv() {
+        // A save is often several events for the same file
+        let mut changed: BTreeSet<Utf8PathBuf> = changed_files(&event).into_iter().collect();
+        if changed.is_empty() {
+            continue;
+        }
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            changed.extend(changed_files(&event));
+        }
+
+        if clear {
+            let mut stdout = anstream::stdout();
+            let _ = write!(stdout, "\x1b[2J\x1b[H");
+            let _ = stdout.flush();
+        }
+        for path in changed {
+            let relative = path.strip_prefix(base_path).unwrap_or(&path).to_owned();
+            // Deleted, or renamed away
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                broken.remove(&path);
+                continue;
+            };
+            let errors: Vec<Diagnostic> =
+                Diagnostic::from_report(PARSER.parse(&content).report(), &content)
+                    .into_iter()
+                    .filter(|diag| diag.severity == "error")
+                    .collect();
+            let time = chrono::Local::now().format("%H:%M:%S");
+
+            if errors.is_empty() {
+                if broken.remove(&path) {
+                    println!("[{time}] ✓ {relative} is fixed");
+                }
+                continue;
+            }
+            println!("[{time}] ❌ {relative}");
+            for error in &errors {
+                println!("  Error: {}", error.message);
+                for line in error.snippet(relative.as_str(), &content).lines() {
+                    println!("     {line}");
+                }
+            }
+            // Only once until it's fixed, not on every save
+            if broken.insert(path) && desktop {
+                let message = format!("{relative} doesn't parse: {}", errors[0].message);
+                let _ = timer::notify(&message);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The recipe and menu files a change is about
+fn changed_files(event: &notify::Result<notify::Event>) -> Vec<Utf8PathBuf> {
+    let Ok(event) = event else {
+        return Vec::new();
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+        return Vec::new();
+    }
+    event
+        .paths
+        .iter()
+        .filter_map(|path| Utf8Path::from_path(path))
+        .filter(|path| matches!(path.extension(), Some("cook" | "menu")))
+        .map(|path| path.to_owned())
+        .collect()
+}
+
+/// Whether a change affects recipes: recipe and menu files, or configuration
+fn is_relevant(event: &notify::Result<notify::Event>, base_path: &Utf8Path) -> bool {
+    let Ok(event) = event else {
+        return false;
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+        return false;
+    }
+
+    let config_dir = base_path.join(LOCAL_CONFIG_DIR);
+    event.paths.iter().any(|path| {
+        let Some(path) = Utf8Path::from_path(path) else {
+            return false;
+        };
+        matches!(path.extension(), Some("cook" | "menu")) || path.starts_with(&config_dir)
+    })
+}
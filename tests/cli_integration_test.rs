@@ -51,6 +51,26 @@ fn test_cli_shopping_list() {
         .stdout(predicate::str::contains("pasta"));
 }
 
+#[test]
+fn test_cli_cookware() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("cookies.cook"),
+        "Mix in a #bowl{2}. Bake on a #sheet pan{}.\n",
+    )
+    .unwrap();
+    std::fs::write(temp_dir.path().join("salad.cook"), "Toss in a #bowl.\n").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["cookware", "cookies", "salad", "-f", "markdown"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- [ ] 3 × bowl"))
+        .stdout(predicate::str::contains("- [ ] 1 × sheet pan"));
+}
+
 #[ignore]
 #[test]
 fn test_cli_shopping_list_multiple_recipes() {
@@ -110,6 +130,168 @@ fn test_cli_search_multiple_terms() {
         .stdout(predicate::str::contains("sauce.cook"));
 }
 
+#[test]
+fn test_cli_search_hidden_directories() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::create_dir(temp_dir.path().join(".drafts")).unwrap();
+    std::fs::write(
+        temp_dir.path().join(".drafts/zucchini.cook"),
+        "Grill @zucchini{2}.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("search")
+        .arg("zucchini")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("zucchini.cook"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_COLLECTION_HIDDEN", "false")
+        .arg("search")
+        .arg("zucchini")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("zucchini.cook").not());
+}
+
+#[test]
+fn test_cli_search_max_calories() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let dir = temp_dir.path();
+    // 100 g of butter in 2 servings is about 360 kcal each
+    std::fs::write(
+        dir.join("Buttery Toast.cook"),
+        "---\nservings: 2\n---\nSpread @butter{100%g} on the toast.\n",
+    )
+    .unwrap();
+    // 500 g of sugar is about 1935 kcal
+    std::fs::write(
+        dir.join("Sugary Toast.cook"),
+        "Sprinkle @sugar{500%g} on the toast.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(dir)
+        .args(["search", "toast", "--max-calories", "600"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Buttery Toast.cook"))
+        .stdout(predicate::str::contains("Sugary Toast.cook").not());
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(dir)
+        .args(["search", "toast", "--sort", "calories"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.find("Buttery").unwrap() < stdout.find("Sugary").unwrap());
+}
+
+#[test]
+fn test_cli_search_in_season() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let dir = temp_dir.path();
+    std::fs::write(
+        dir.join("Spring Salad.cook"),
+        "Toss @asparagus{200%g} with @olive oil{1%tbsp}.\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("Summer Salad.cook"),
+        "Toss @cherry tomatoes{200%g} with @olive oil{1%tbsp}.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(dir)
+        .args(["search", "--in-season", "--month", "5"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Spring Salad.cook"))
+        .stdout(predicate::str::contains("Summer Salad.cook").not());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(dir)
+        .args(["search", "salad", "--in-season", "--month", "8"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Summer Salad.cook"))
+        .stdout(predicate::str::contains("Spring Salad.cook").not());
+}
+
+#[test]
+fn test_cli_doctor_names() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let dir = temp_dir.path();
+    std::fs::write(dir.join("Simple.cook"), "Boil @water{1%l}.\n").unwrap();
+    std::fs::write(dir.join("Fish: Baked?.cook"), "Bake @fish{1}.\n").unwrap();
+    std::fs::write(dir.join("Fish: Baked?.jpg"), "").unwrap();
+    std::fs::write(dir.join("Fish: Baked?.1.jpg"), "").unwrap();
+    // The image of another recipe
+    std::fs::write(dir.join("Fish: Baked?.Napoli.jpg"), "").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(dir)
+        .arg("doctor")
+        .arg("names")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("same name as Simple.cook"))
+        .stdout(predicate::str::contains("contains ':', '?'"))
+        .stdout(predicate::str::contains("--fix"));
+    assert!(dir.join("simple.cook").exists());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(dir)
+        .arg("doctor")
+        .arg("names")
+        .arg("--fix")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Renamed 2 recipes"));
+    assert!(dir.join("simple 2.cook").exists());
+    assert!(dir.join("Fish_ Baked_.cook").exists());
+    assert!(dir.join("Fish_ Baked_.jpg").exists());
+    assert!(dir.join("Fish_ Baked_.1.jpg").exists());
+    assert!(dir.join("Fish: Baked?.Napoli.jpg").exists());
+    assert!(!dir.join("Fish: Baked?.cook").exists());
+}
+
+#[test]
+fn test_cli_doctor_names_keeps_existing_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    std::fs::write(dir.join("Fish?.cook"), "Bake @fish{1}.\n").unwrap();
+    std::fs::write(dir.join("Fish?.jpg"), "new").unwrap();
+    std::fs::write(dir.join("Fish_.jpg"), "old").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(dir)
+        .args(["doctor", "names", "--fix"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("exists already"));
+    assert!(dir.join("Fish?.cook").exists());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("Fish_.jpg")).unwrap(),
+        "old"
+    );
+}
+
 #[test]
 fn test_cli_doctor_validate() {
     let temp_dir = common::setup_test_recipes().unwrap();
@@ -154,6 +336,87 @@ fn test_cli_doctor_validate_strict() {
         .failure(); // Strict mode fails with errors
 }
 
+#[test]
+fn test_cli_doctor_images_reports_missing() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("pictured.cook"),
+        "---\nimage: missing.jpg\n---\nServe @bread{1%slice}.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("doctor")
+        .arg("images")
+        .arg("-b")
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pictured.cook"))
+        .stdout(predicate::str::contains("Missing image: missing.jpg"));
+}
+
+#[test]
+fn test_cli_doctor_links() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("doctor")
+        .arg("links")
+        .arg("-b")
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("links resolve"));
+
+    std::fs::write(temp_dir.path().join("pizza.jpg"), "").unwrap();
+    std::fs::write(
+        temp_dir.path().join("broken.cook"),
+        "---\nimage: piza.jpg\n---\nMake @./sause{}.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("doctor")
+        .arg("links")
+        .arg("-b")
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("broken.cook"))
+        .stdout(predicate::str::contains("Missing recipe:"))
+        .stdout(predicate::str::contains("Did you mean: sauce?"))
+        .stdout(predicate::str::contains("Missing image: piza.jpg"))
+        .stdout(predicate::str::contains("Did you mean: pizza.jpg?"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("doctor")
+        .arg("links")
+        .arg("--strict")
+        .arg("-b")
+        .arg(temp_dir.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_doctor_config() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("doctor")
+        .arg("config")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("aisle.conf"));
+}
+
 #[test]
 fn test_cli_seed() {
     let temp_dir = TempDir::new().unwrap();
@@ -168,6 +431,11 @@ fn test_cli_seed() {
 
     // Check that seed directory was created
     assert!(temp_dir.path().join("test_seed").exists());
+    let config = temp_dir.path().join("test_seed/config");
+    assert!(config.join("aisle.conf").exists());
+    assert!(config.join("pantry.conf").exists());
+    // Only with --with-config
+    assert!(!config.join("cook.toml").exists());
 }
 
 #[test]
@@ -186,6 +454,93 @@ fn test_cli_seed_custom_path() {
     assert!(temp_dir.path().join("my_recipes").exists());
 }
 
+#[test]
+fn test_cli_seed_from_git() {
+    let temp_dir = TempDir::new().unwrap();
+    let pack = temp_dir.path().join("thai-pack");
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(&pack)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    };
+    std::fs::create_dir_all(pack.join("Curries")).unwrap();
+    std::fs::write(
+        pack.join("Curries/Green Curry.cook"),
+        "Fry @green curry paste{2%tbsp}.\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(pack.join("config")).unwrap();
+    std::fs::write(pack.join("config/cook.toml"), "[server]\nhost = true\n").unwrap();
+    git(&["init", "--quiet"]);
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "Add curry"]);
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["seed", "recipes", "--from", pack.to_str().unwrap()])
+        .assert()
+        .success();
+    let recipes = temp_dir.path().join("recipes");
+    assert!(recipes.join("Curries/Green Curry.cook").exists());
+    assert!(!recipes.join(".git").exists());
+    // Only recipes and images
+    assert!(!recipes.join("config/cook.toml").exists());
+
+    std::fs::write(pack.join("Broken.cook"), "Add @flour{%kg}.\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "Add broken recipe"]);
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["seed", "other", "--from", pack.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Broken.cook"));
+    assert!(!temp_dir.path().join("other").exists());
+}
+
+#[test]
+fn test_cli_seed_existing_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let seed = || {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path()).args(["seed", "recipes"]);
+        cmd
+    };
+
+    seed()
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("create"));
+    assert!(!temp_dir.path().join("recipes").exists());
+
+    seed().assert().success();
+    let readme = temp_dir.path().join("recipes/README.md");
+    std::fs::write(&readme, "My notes").unwrap();
+
+    seed()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+    assert_eq!(std::fs::read_to_string(&readme).unwrap(), "My notes");
+
+    seed()
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("exists"));
+
+    seed().arg("--force").assert().success();
+    assert_ne!(std::fs::read_to_string(&readme).unwrap(), "My notes");
+}
+
 #[test]
 fn test_cli_help() {
     Command::cargo_bin("cook")
@@ -248,3 +603,2364 @@ fn test_cli_recipe_from_subdirectory() {
         .success()
         .stdout(predicate::str::contains("Pancakes"));
 }
+
+#[test]
+fn test_cli_exit_codes_and_json_errors() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(temp_dir.path().join("broken.cook"), "Add @flour{%kg}.\n").unwrap();
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["--error-format", "json", "recipe", "read", "missing.cook"])
+        .assert()
+        .code(4)
+        .get_output()
+        .stderr
+        .clone();
+    let error: serde_json::Value = serde_json::from_slice(&output).expect("Valid JSON error");
+    assert_eq!(error["error"]["kind"], "not_found");
+    assert_eq!(error["error"]["exit_code"], 4);
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_ERROR_FORMAT", "json")
+        .args(["recipe", "read", "broken.cook"])
+        .assert()
+        .code(3)
+        .get_output()
+        .stderr
+        .clone();
+    let error: serde_json::Value = serde_json::from_slice(&output).expect("Valid JSON error");
+    assert_eq!(error["error"]["kind"], "parse");
+    assert_eq!(error["error"]["diagnostics"][0]["severity"], "error");
+
+    // Human errors keep the same codes
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["recipe", "read", "missing.cook"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::starts_with("Error: "));
+}
+
+#[test]
+fn test_cli_recipe_parse_error_points_at_source() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("broken.cook"),
+        "Preheat the oven.\nAdd @flour{%kg} and stir.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("read")
+        .arg("broken.cook")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("broken.cook:2:"))
+        .stderr(predicate::str::contains("2 | Add @flour{%kg} and stir."))
+        .stderr(predicate::str::contains("^"));
+}
+
+#[test]
+fn test_cli_recipe_locale() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("dressing.cook"),
+        "Whisk @oil{1.5%tbsp} with @vinegar{2.25%tsp}.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_OUTPUT_LOCALE", "de")
+        .arg("recipe")
+        .arg("read")
+        .arg("dressing.cook")
+        .arg("--format")
+        .arg("markdown")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1,5 EL"))
+        .stdout(predicate::str::contains("2,25 TL"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_OUTPUT_FRACTIONS", "true")
+        .arg("recipe")
+        .arg("read")
+        .arg("dressing.cook")
+        .arg("--format")
+        .arg("markdown")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1½ tbsp"))
+        .stdout(predicate::str::contains("2¼ tsp"));
+}
+
+#[test]
+fn test_cli_collection_init() {
+    let temp_dir = TempDir::new().unwrap();
+    let collection = temp_dir.path().join("recipes");
+    let init = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .args(["collection", "init", "recipes"])
+            .args(args);
+        cmd
+    };
+
+    init(&["--dry-run", "--git"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("create"))
+        .stdout(predicate::str::contains("git init"));
+    assert!(!collection.exists());
+
+    init(&["--categories", "Soups,Bread", "--git", "--seed"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Initialized a recipe collection"));
+    assert!(collection.join("Soups").is_dir());
+    assert!(collection.join("Bread").is_dir());
+    assert!(!collection.join("Mains").exists());
+    assert!(collection.join("config/aisle.conf").is_file());
+    assert!(collection.join("config/cook.toml").is_file());
+    assert!(collection.join(".gitignore").is_file());
+    assert!(collection.join(".git").exists());
+    assert!(collection.join("Neapolitan Pizza.cook").is_file());
+
+    // Running it again keeps the files the user changed
+    std::fs::write(collection.join("config/aisle.conf"), "[mine]\nflour\n").unwrap();
+    init(&[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("existing files kept"));
+    assert_eq!(
+        std::fs::read_to_string(collection.join("config/aisle.conf")).unwrap(),
+        "[mine]\nflour\n"
+    );
+    assert!(collection.join("Mains").is_dir());
+}
+
+#[test]
+fn test_cli_seed_groups() {
+    let temp_dir = TempDir::new().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["seed", "--list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("breakfasts"))
+        .stdout(predicate::str::contains("snacks"));
+    assert!(!temp_dir.path().join("README.md").exists());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["seed", "--only", "breakfasts"])
+        .assert()
+        .success();
+    let path = temp_dir.path();
+    assert!(path.join("Breakfast/Easy Pancakes.cook").exists());
+    // Referenced by the burrito
+    assert!(path.join("Shared/Red Beans.cook").exists());
+    assert!(path.join("README.md").exists());
+    assert!(!path.join("Neapolitan Pizza.cook").exists());
+    assert!(!path.join("config").exists());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["seed", "starter", "--with-config"])
+        .assert()
+        .success();
+    let starter = path.join("starter");
+    assert!(starter.join("config/aisle.conf").exists());
+    assert!(starter.join("config/pantry.conf").exists());
+    assert!(starter.join("config/cook.toml").exists());
+    // The template is valid, and sets nothing
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(&starter)
+        .env("COOK_CONFIG_DIR", path.join("global"))
+        .args(["config", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["seed", "--only", "desserts"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("breakfasts"));
+}
+
+#[test]
+fn test_cli_shopping_list_group_by_recipe() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args([
+            "shopping-list",
+            "simple.cook:2",
+            "sauce.cook",
+            "--group-by",
+            "recipe",
+            "-f",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let recipes = json.as_array().unwrap();
+    assert_eq!(recipes.len(), 2);
+    assert_eq!(recipes[0]["recipe"], "simple.cook:2");
+    let names: Vec<_> = recipes[0]["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"pasta"));
+    assert!(!names.contains(&"garlic"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args([
+            "shopping-list",
+            "simple.cook",
+            "--group-by",
+            "recipe",
+            "--plain",
+        ])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_shopping_list_sort() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let names = |sort: &str| {
+        let output = Command::cargo_bin("cook")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args(["shopping-list", "simple.cook", "--plain", "-f", "json"])
+            .args(["--sort", sort])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json.as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(names("alpha"), ["pasta", "salt", "water"]);
+    assert_eq!(names("recipe-order"), ["water", "salt", "pasta"]);
+}
+
+#[test]
+fn test_cli_recipe_duplicate() {
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("Souffle.cook"),
+        "---\ntitle: Souffle\nsource: https://example.com\n---\n\nWhisk @eggs{4}.\n",
+    )
+    .unwrap();
+    let duplicate = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .args(["recipe", "duplicate", "Souffle"])
+            .args(args);
+        cmd
+    };
+
+    duplicate(&["--as", "Chocolate Souffle", "--clear-source"])
+        .assert()
+        .success();
+    let copy = std::fs::read_to_string(temp_dir.path().join("Chocolate Souffle.cook")).unwrap();
+    assert_eq!(
+        copy,
+        "---\ntitle: Chocolate Souffle\n---\n\nWhisk @eggs{4}.\n"
+    );
+
+    duplicate(&["--as", "Chocolate Souffle"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+}
+
+#[test]
+fn test_cli_recipe_compare() {
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("Brownies.cook"),
+        "---\nservings: 4\n---\n\nMix @butter{100%g}, @sugar{200%g} and @cocoa{40%g}.\n",
+    )
+    .unwrap();
+    std::fs::write(
+        temp_dir.path().join("Fudgy Brownies.cook"),
+        "---\nservings: 8\n---\n\nMix @butter{0.3%kg}, @sugar{400%g} and @chocolate{150%g}.\n",
+    )
+    .unwrap();
+    let compare = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .args([
+                "recipe",
+                "compare",
+                "Brownies",
+                "Fudgy Brownies",
+                "-f",
+                "json",
+            ])
+            .args(args);
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let comparison = compare(&[]);
+    assert_eq!(comparison["ingredients"][0]["name"], "butter");
+    let difference = comparison["ingredients"][0]["difference"].as_f64().unwrap();
+    assert_eq!(difference.round(), 200.0);
+    assert_eq!(comparison["ingredients"][3]["name"], "chocolate");
+    assert!(comparison["ingredients"][3]["a"].is_null());
+
+    let comparison = compare(&["--normalize-servings", "--differences"]);
+    assert_eq!(comparison["per_serving"], true);
+    let names: Vec<_> = comparison["ingredients"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|igr| igr["name"].as_str().unwrap().to_string())
+        .collect();
+    // Per serving there's as much sugar in both
+    assert_eq!(names, ["butter", "cocoa", "chocolate"]);
+}
+
+#[test]
+fn test_cli_recipe_meta() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let path = temp_dir.path().join("simple.cook");
+    let original = std::fs::read_to_string(&path).unwrap();
+    let body = |content: &str| content.splitn(3, "---").nth(2).unwrap().to_string();
+    let meta = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .args(["recipe", "meta"])
+            .args(args);
+        cmd
+    };
+
+    meta(&["simple", "get", "servings"])
+        .assert()
+        .success()
+        .stdout("2\n");
+
+    meta(&["simple", "set", "tags", "vegan,quick"])
+        .assert()
+        .success();
+    meta(&["simple", "unset", "title"]).assert().success();
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("---\nservings: 2\ntags:\n- vegan\n- quick\n---"));
+    assert_eq!(body(&content), body(&original));
+
+    meta(&["simple", "get", "title"]).assert().failure();
+
+    meta(&["--all", "--filter", "tags=Vegan", "get", "servings"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("simple.cook: 2"))
+        .stdout(predicate::str::contains("with_ref.cook").not());
+
+    meta(&[
+        "--all",
+        "--filter",
+        "tags=vegan",
+        "--dry-run",
+        "set",
+        "servings",
+        "4",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("Would update simple.cook"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+}
+
+#[test]
+fn test_cli_tag() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let pancakes = temp_dir.path().join("Breakfast/pancakes.cook");
+    let original = std::fs::read_to_string(&pancakes).unwrap();
+    let tag = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path()).arg("tag").args(args);
+        cmd
+    };
+
+    tag(&["add", "weeknight", "--where", "servings>=4"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("b/Breakfast/pancakes.cook"))
+        .stdout(predicate::str::contains("+- weeknight"))
+        .stdout(predicate::str::contains("simple.cook").not());
+    assert_eq!(std::fs::read_to_string(&pancakes).unwrap(), original);
+
+    tag(&["add", "weeknight", "--where", "servings>=4", "--write"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Updated 1 of 1"));
+    tag(&["rename", "Weeknight", "quick", "--all", "--write"])
+        .assert()
+        .success();
+    let content = std::fs::read_to_string(&pancakes).unwrap();
+    assert!(content.contains("tags:\n- quick\n"));
+    assert!(content.ends_with(original.splitn(3, "---").nth(2).unwrap()));
+
+    tag(&["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1  quick"));
+    tag(&["add", "quick"]).assert().failure();
+}
+
+#[test]
+fn test_cli_recipe_image() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let photo = temp_dir.path().join("photo.png");
+    image::RgbImage::from_pixel(3000, 1000, image::Rgb([200, 120, 40]))
+        .save(&photo)
+        .unwrap();
+    let recipe_image = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .args(["recipe", "image", "simple"])
+            .args(args);
+        cmd
+    };
+
+    recipe_image(&["--from", photo.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("simple.jpg"));
+    let saved = image::open(temp_dir.path().join("simple.jpg")).unwrap();
+    assert_eq!((saved.width(), saved.height()), (1600, 533));
+
+    recipe_image(&["--from", photo.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+
+    recipe_image(&["--step", "1", "--from", photo.to_str().unwrap()])
+        .assert()
+        .success();
+    assert!(temp_dir.path().join("simple.0.jpg").is_file());
+    recipe_image(&["--step", "2", "--from", photo.to_str().unwrap()])
+        .assert()
+        .failure();
+
+    recipe_image(&[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("simple.jpg"))
+        .stdout(predicate::str::contains("simple.0.jpg"));
+
+    recipe_image(&["--remove"]).assert().success();
+    assert!(!temp_dir.path().join("simple.jpg").exists());
+    assert!(temp_dir.path().join("simple.0.jpg").exists());
+}
+
+#[test]
+fn test_cli_recipe_image_webp() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let photo = temp_dir.path().join("photo.jpg");
+    image::RgbImage::from_pixel(400, 200, image::Rgb([200, 120, 40]))
+        .save(&photo)
+        .unwrap();
+    let config_dir = temp_dir.path().join("config");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        config_dir.join("cook.toml"),
+        "[images]\nformat = \"webp\"\nmax_size = 100\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args([
+            "recipe",
+            "image",
+            "simple",
+            "--from",
+            photo.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("simple.webp"));
+    let saved = image::open(temp_dir.path().join("simple.webp")).unwrap();
+    assert_eq!((saved.width(), saved.height()), (100, 50));
+}
+
+#[test]
+fn test_cli_recipe_only_parts() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["recipe", "read", "simple.cook", "--only", "ingredients"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pasta"))
+        .stdout(predicate::str::contains("Simple Recipe").not())
+        .stdout(predicate::str::contains("pot").not());
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args([
+            "recipe",
+            "read",
+            "simple.cook",
+            "-f",
+            "json",
+            "--only",
+            "metadata,cookware",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let mut keys: Vec<_> = json.as_object().unwrap().keys().collect();
+    keys.sort();
+    assert_eq!(keys, ["cookware", "metadata"]);
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args([
+            "recipe",
+            "read",
+            "simple.cook",
+            "-f",
+            "cooklang",
+            "--only",
+            "steps",
+        ])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_recipe_temperature() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("pie.cook"),
+        "Preheat the oven to 350°F.\n\nBake the @apples{3} for 45 minutes.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args([
+            "--temperature",
+            "celsius",
+            "recipe",
+            "read",
+            "pie",
+            "-f",
+            "markdown",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("175°C"))
+        .stdout(predicate::str::contains("350°F").not());
+}
+
+#[test]
+fn test_cli_recipe_step_quantities() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("dough.cook"),
+        "Mix @flour{250%g} and @water{150%ml}.\n\nKnead in @salt{5%g}.\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("cook").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args([
+            "recipe",
+            "read",
+            "dough:2",
+            "--format",
+            "markdown",
+            "--step-quantities",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("   - *500 g* flour"))
+        .stdout(predicate::str::contains("   - *10 g* salt"));
+
+    let mut cmd = Command::cargo_bin("cook").unwrap();
+    let output = cmd
+        .current_dir(temp_dir.path())
+        .args(["recipe", "read", "dough", "-f", "json", "--step-quantities"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["step_quantities"][1]["step"], 2);
+    assert_eq!(json["step_quantities"][1]["ingredients"][0]["name"], "salt");
+}
+
+#[test]
+fn test_cli_recipe_named_scaling() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("buns.cook"),
+        "---\nscalings: [half, party x3]\n---\n\nMix @flour{200%g}.\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("cook").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args([
+            "recipe", "read", "buns", "--scale", "party", "--format", "markdown",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("600 g"));
+
+    let mut cmd = Command::cargo_bin("cook").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["recipe", "read", "buns:half", "--format", "markdown"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("100 g"));
+
+    let mut cmd = Command::cargo_bin("cook").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["recipe", "read", "buns", "--scale", "crowd"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("party (x3)"));
+}
+
+#[test]
+fn test_cli_lint() {
+    let temp_dir = TempDir::new().unwrap();
+    let recipe = temp_dir.path().join("Breakfast/Eggs.cook");
+    std::fs::create_dir_all(recipe.parent().unwrap()).unwrap();
+    std::fs::write(
+        &recipe,
+        "---\nservings: 2\n---\n\nBoil @eggs{2}. Cool them. Peel them.\n",
+    )
+    .unwrap();
+    let lint = || {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path()).arg("lint");
+        cmd
+    };
+
+    // No rules yet
+    lint()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No lint rules"));
+
+    let config_dir = temp_dir.path().join("config");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        config_dir.join("cook.toml"),
+        "[lint]\nrequire_servings = true\nrequire_tags = true\nmax_step_sentences = 2\n",
+    )
+    .unwrap();
+    lint()
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("require_tags"))
+        .stdout(predicate::str::contains("max_step_sentences"))
+        .stdout(predicate::str::contains("require_servings").not());
+
+    lint()
+        .arg("--fix")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("follow the lint rules"));
+    let fixed = std::fs::read_to_string(&recipe).unwrap();
+    assert!(fixed.contains("- breakfast"), "{fixed}");
+    assert!(
+        fixed.contains("Boil @eggs{2}. Cool them.\n\nPeel them."),
+        "{fixed}"
+    );
+}
+
+#[test]
+fn test_cli_hooks_install() {
+    let temp_dir = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(temp_dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    };
+    let cook = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path()).args(args);
+        cmd
+    };
+    git(&["init", "--quiet"]);
+
+    cook(&["hooks", "install"]).assert().success();
+    let hook = temp_dir.path().join(".git/hooks/pre-commit");
+    let script = std::fs::read_to_string(&hook).unwrap();
+    assert!(script.contains("cook lint --staged"), "{script}");
+    // Installing again replaces our own hook
+    cook(&["hooks", "install"]).assert().success();
+
+    // The staged recipe is checked, not the fixed one in the working tree
+    let recipe = temp_dir.path().join("Eggs.cook");
+    std::fs::write(&recipe, "Add @flour{%kg}.\n").unwrap();
+    git(&["add", "Eggs.cook"]);
+    std::fs::write(&recipe, "Boil @eggs{2}.\n").unwrap();
+    cook(&["lint", "--staged"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Eggs.cook"))
+        .stdout(predicate::str::contains("parse: line 1"));
+    git(&["add", "Eggs.cook"]);
+    cook(&["lint", "--staged"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("staged recipes are fine"));
+
+    cook(&["hooks", "uninstall"]).assert().success();
+    assert!(!hook.exists());
+    std::fs::write(&hook, "#!/bin/sh\nmake test\n").unwrap();
+    cook(&["hooks", "install"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+}
+
+#[test]
+fn test_cli_schema() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("schema")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["type"], "object");
+    assert!(schema["properties"]["servings"].is_object());
+    assert!(schema["properties"]["nutrition"]["properties"]["calories"].is_object());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["schema", "-o", "cook.schema.json"])
+        .assert()
+        .success();
+    assert!(temp_dir.path().join("cook.schema.json").exists());
+}
+
+#[test]
+fn test_cli_recipe_default_servings() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("bread.cook"),
+        "---\nservings: 4\n---\n\nMix @flour{400%g}.\n",
+    )
+    .unwrap();
+    let config_dir = temp_dir.path().join("config");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        config_dir.join("cook.toml"),
+        "[recipe]\ndefault_servings = 2\n",
+    )
+    .unwrap();
+
+    let read = |recipe: &str| {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .args(["recipe", "read", recipe, "--format", "markdown"]);
+        cmd
+    };
+    read("bread")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("200 g"));
+    read("bread:1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("400 g"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["shopping-list", "bread", "--plain"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("200 g"));
+}
+
+#[test]
+fn test_cli_shopping_list_aliases() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("noodles.cook"),
+        "Top with @scallion{2}.\n",
+    )
+    .unwrap();
+    std::fs::write(
+        temp_dir.path().join("pancakes.cook"),
+        "Fold in @green onion{3}.\n",
+    )
+    .unwrap();
+    let config_dir = temp_dir.path().join("config");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        config_dir.join("cook.toml"),
+        "[aliases]\nscallion = \"green onion\"\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["shopping-list", "noodles", "pancakes", "--plain"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("green onion"))
+        .stdout(predicate::str::contains("5"))
+        .stdout(predicate::str::contains("scallion").not())
+        .stderr(predicate::str::contains("Listed scallion as green onion"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["search", "green onion"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("noodles.cook"))
+        .stdout(predicate::str::contains("pancakes.cook"));
+}
+
+#[test]
+fn test_cli_recipe_scaled_rounding() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("omelette.cook"),
+        "Beat @eggs{2} with @flour{100%g}.\n",
+    )
+    .unwrap();
+
+    let read = || {
+        let mut cmd = Command::cargo_bin("cook").unwrap();
+        cmd.current_dir(temp_dir.path()).args([
+            "recipe",
+            "read",
+            "omelette.cook:1.33",
+            "--format",
+            "markdown",
+        ]);
+        cmd
+    };
+
+    read()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- 3 eggs"))
+        .stdout(predicate::str::contains("135 g"))
+        .stdout(predicate::str::contains("133").not());
+
+    let config_dir = temp_dir.path().join("config");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        config_dir.join("cook.toml"),
+        "[rounding]\nenabled = false\n",
+    )
+    .unwrap();
+
+    read()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("133 g"));
+}
+
+#[test]
+fn test_cli_recipe_substitute() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("cake.cook"),
+        "Melt @butter{100%g} with @chocolate{200%g}.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("read")
+        .arg("cake.cook")
+        .arg("--substitute")
+        .arg("butter=coconut oil")
+        .arg("--format")
+        .arg("markdown")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("80 g"))
+        .stdout(predicate::str::contains("coconut oil"))
+        .stdout(predicate::str::contains("instead of butter"));
+
+    // Unknown substitutions list the known ones
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("read")
+        .arg("cake.cook")
+        .arg("--substitute")
+        .arg("butter=lard")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("margarine"));
+}
+
+#[test]
+fn test_cli_recipe_translate_checks_input() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    // Language codes end up in file names
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("translate")
+        .arg("simple.cook")
+        .arg("--to")
+        .arg("../fr")
+        .assert()
+        .code(2);
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env_remove("CLAUDE_API_KEY")
+        .arg("recipe")
+        .arg("translate")
+        .arg("simple.cook")
+        .arg("--to")
+        .arg("fr")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("CLAUDE_API_KEY"));
+    assert!(!temp_dir.path().join("simple.fr.cook").exists());
+}
+
+#[test]
+fn test_cli_recipe_nutrition_json() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("nutrition")
+        .arg("simple.cook")
+        .arg("-f")
+        .arg("json")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let report: serde_json::Value = serde_json::from_slice(&output).expect("Valid JSON output");
+    // 200 g of pasta, water and salt have no calories
+    let calories = report["total"]["calories"].as_f64().unwrap();
+    assert!((calories - 742.0).abs() < 1.0, "calories = {calories}");
+    let per_serving = report["per_serving"]["calories"].as_f64().unwrap();
+    assert!(
+        (per_serving - 371.0).abs() < 1.0,
+        "per serving = {per_serving}"
+    );
+}
+
+#[test]
+fn test_cli_recipe_nutrition_reports_unmatched() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("nutrition")
+        .arg("with_ref.cook")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tomatoes"))
+        .stdout(predicate::str::contains("Not included:"))
+        .stdout(predicate::str::contains(
+            "recipe references are not included",
+        ));
+}
+
+#[test]
+fn test_cli_recipe_nutrition_menu_daily_totals() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("plan.menu"),
+        r#"---
+servings: 1
+---
+
+==Day 1==
+
+Dinner:
+- @./simple{2%servings}
+
+==Day 2==
+
+Dinner:
+- @pasta{100%g}
+"#,
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("nutrition")
+        .arg("plan.menu")
+        .arg("--target-calories")
+        .arg("700")
+        .arg("-f")
+        .arg("json")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let report: serde_json::Value = serde_json::from_slice(&output).expect("Valid JSON output");
+    let days = report["days"].as_array().unwrap();
+    assert_eq!(days.len(), 2);
+    assert_eq!(days[0]["name"], "Day 1");
+    let calories = days[0]["total"]["calories"].as_f64().unwrap();
+    assert!((calories - 742.0).abs() < 1.0, "calories = {calories}");
+    assert_eq!(days[0]["off_target"], false);
+    assert_eq!(days[1]["off_target"], true);
+}
+
+#[test]
+fn test_cli_stats_json() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("stats")
+        .arg("-f")
+        .arg("json")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stats: serde_json::Value = serde_json::from_slice(&output).expect("Valid JSON output");
+    assert!(stats["recipes"].as_u64().unwrap() > 0);
+    assert!(stats["by_directory"]["Breakfast"].as_u64().unwrap() >= 1);
+    let missing_image = stats["missing_image"].as_array().unwrap();
+    assert!(missing_image.iter().any(|r| r == "simple.cook"));
+}
+
+#[test]
+fn test_cli_recipe_source() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("Waffles.cook"),
+        "---\nsource: Serious Eats https://www.seriouseats.com/waffles\n---\nMix @flour{200%g}.\n",
+    )
+    .unwrap();
+    std::fs::write(
+        temp_dir.path().join("Hummus.cook"),
+        "---\nsource: Jerusalem, p. 88\nauthor: Yotam Ottolenghi\n---\nBlend @chickpeas{400%g}.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["recipe", "read", "Waffles.cook", "-f", "markdown"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Source: [Serious Eats](https://www.seriouseats.com/waffles)",
+        ));
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["recipe", "read", "Hummus.cook", "-f", "markdown"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Source: Jerusalem, page 88, by Yotam Ottolenghi",
+        ));
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["stats", "-f", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stats: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(stats["by_source"]["seriouseats.com"], 1);
+    assert_eq!(stats["by_source"]["Jerusalem"], 1);
+}
+
+#[test]
+fn test_cli_report_collection() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let template = temp_dir.path().join("never-cooked.jinja");
+    std::fs::write(
+        &template,
+        "{% for recipe in recipes if not recipe.cooked %}[{{ recipe.path }}]\n{% endfor %}\
+         {{ stats.recipes }} recipes\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("report")
+        .arg("-t")
+        .arg(&template)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[simple.cook]"))
+        .stdout(predicate::str::contains("[Breakfast/"))
+        .stdout(predicate::str::contains(" recipes"));
+}
+
+#[test]
+fn test_cli_stats_human() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("stats")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Recipes:"))
+        .stdout(predicate::str::contains("Most used ingredients:"));
+}
+
+#[test]
+fn test_cli_cooked_history() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::create_dir_all(temp_dir.path().join("config")).unwrap();
+    std::fs::write(
+        temp_dir.path().join("config/cooked.jsonl"),
+        concat!(
+            r#"{"recipe":"sauce.cook","scale":1.0,"cooked_at":"2024-03-01T18:00:00+01:00"}"#,
+            "\n",
+            r#"{"recipe":"sauce.cook","scale":2.0,"cooked_at":"2024-03-02T18:00:00+01:00"}"#,
+            "\nnot json\n",
+            r#"{"recipe":"simple.cook","scale":1.0,"cooked_at":"2024-03-17T19:00:00+01:00"}"#,
+            "\n",
+        ),
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["stats", "-f", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stats: serde_json::Value = serde_json::from_slice(&output).expect("Valid JSON output");
+    assert_eq!(stats["recipes_cooked"], 2);
+    assert_eq!(stats["most_cooked"][0]["name"], "sauce.cook");
+    assert_eq!(stats["most_cooked"][0]["times_cooked"], 2);
+    assert_eq!(
+        stats["most_cooked"][0]["last_cooked"],
+        "2024-03-02T18:00:00+01:00"
+    );
+
+    let search = |sort: &str| {
+        let output = Command::cargo_bin("cook")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args(["search", "add", "--sort", sort])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let output = String::from_utf8(output).unwrap();
+        let simple = output.find("simple.cook").expect("simple.cook is found");
+        let sauce = output.find("sauce.cook").expect("sauce.cook is found");
+        simple < sauce
+    };
+    assert!(search("last-cooked"));
+    assert!(!search("times-cooked"));
+}
+
+#[test]
+fn test_cli_bench_json() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["bench", "-r", "1", "-f", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let report: serde_json::Value = serde_json::from_slice(&output).expect("Valid JSON output");
+    assert!(report["recipes"].as_u64().unwrap() > 0);
+    let phases: Vec<_> = report["phases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|phase| phase["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        phases,
+        ["scan", "parse", "index", "search", "shopping list"]
+    );
+}
+
+#[test]
+fn test_cli_random_with_tag() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("quick.cook"),
+        "---\ntitle: Quick Toast\ntags: [quick, breakfast]\n---\n\nToast @bread{2%slices}.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("random")
+        .arg("--tag")
+        .arg("Quick")
+        .arg("--path-only")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("quick.cook\n"));
+}
+
+#[test]
+fn test_cli_random_no_match() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("random")
+        .arg("--tag")
+        .arg("does-not-exist")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No recipes match"));
+}
+
+#[test]
+fn test_cli_recipe_cook_requires_terminal() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("cook")
+        .arg("simple.cook")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("interactive terminal"));
+}
+
+#[test]
+fn test_cli_tui_requires_terminal() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("tui")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("interactive terminal"));
+}
+
+#[test]
+fn test_cli_timer_runs_to_completion() {
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("timer")
+        .arg("1s")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Started 1s"))
+        .stdout(predicate::str::contains("1s is done"));
+}
+
+#[test]
+fn test_cli_timer_from_recipe_lists_steps() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("timer")
+        .arg("--from-recipe")
+        .arg("simple.cook")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--step"))
+        .stderr(predicate::str::contains("step 1:"));
+}
+
+#[test]
+fn test_cli_sync_requires_git_repo() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not in a git repository"));
+}
+
+#[test]
+fn test_cli_sync_commits_and_pushes() {
+    let temp_dir = TempDir::new().unwrap();
+    let remote = temp_dir.path().join("remote.git");
+    let clone = temp_dir.path().join("recipes");
+    let git = |dir: &std::path::Path, args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    };
+
+    git(
+        temp_dir.path(),
+        &["init", "--bare", "--quiet", "remote.git"],
+    );
+    git(
+        temp_dir.path(),
+        &["clone", "--quiet", "remote.git", "recipes"],
+    );
+    std::fs::write(clone.join("Pancakes.cook"), "Mix @flour{200%g}.\n").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(&clone)
+        .env("GIT_AUTHOR_NAME", "Test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .arg("sync")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Synced"));
+
+    let log = std::process::Command::new("git")
+        .args(["log", "--format=%s", "--all"])
+        .current_dir(&remote)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&log.stdout).trim(), "Add Pancakes");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_cli_non_utf8_paths() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let archive = temp_dir.path().join("backup.tar.zst");
+    std::fs::write(
+        temp_dir.path().join(OsStr::from_bytes(b"caf\xe9.cook")),
+        "Boil @water{1%l}.\n",
+    )
+    .unwrap();
+
+    // The file is skipped with a warning
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("backup")
+        .arg("-b")
+        .arg(temp_dir.path())
+        .arg("--out")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Skipping"));
+
+    // An unusable configuration directory is an error, not a crash
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_CONFIG_DIR", OsStr::from_bytes(b"/tmp/\xff"))
+        .args(["stats"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("panicked").not());
+}
+
+#[test]
+fn test_cli_backup_and_restore() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let archive = temp_dir.path().join("backup.tar.zst");
+    let restored = TempDir::new().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("backup")
+        .arg("-b")
+        .arg(temp_dir.path())
+        .arg("--out")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Backed up"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("restore")
+        .arg(&archive)
+        .arg("-b")
+        .arg(restored.path())
+        .arg("--skip-global-config")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("create"))
+        .stdout(predicate::str::contains("simple.cook"));
+    assert!(!restored.path().join("simple.cook").exists());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("restore")
+        .arg(&archive)
+        .arg("-b")
+        .arg(restored.path())
+        .arg("--skip-global-config")
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(restored.path().join("simple.cook")).unwrap(),
+        std::fs::read_to_string(temp_dir.path().join("simple.cook")).unwrap()
+    );
+    assert!(restored.path().join("config/aisle.conf").exists());
+
+    // Changed files are only overwritten with --force
+    std::fs::write(restored.path().join("simple.cook"), "changed").unwrap();
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("restore")
+        .arg(&archive)
+        .arg("-b")
+        .arg(restored.path())
+        .arg("--skip-global-config")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+}
+
+#[test]
+fn test_cli_publish_exports_site() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let site = TempDir::new().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("publish")
+        .arg("-b")
+        .arg(temp_dir.path())
+        .arg("--out-dir")
+        .arg(site.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Exported"));
+
+    let index = std::fs::read_to_string(site.path().join("index.html")).unwrap();
+    assert!(index.contains("simple.html"));
+    assert!(index.contains("Breakfast/index.html"));
+    assert!(site.path().join("Breakfast/pancakes.html").exists());
+
+    // Nothing changed, so nothing is rewritten
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("publish")
+        .arg("-b")
+        .arg(temp_dir.path())
+        .arg("--out-dir")
+        .arg(site.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0 files changed, 0 removed"));
+}
+
+#[test]
+fn test_cli_publish_keeps_other_files() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let site = TempDir::new().unwrap();
+    std::fs::write(site.path().join("notes.txt"), "mine").unwrap();
+    let extra = temp_dir.path().join("Extra.cook");
+    std::fs::write(&extra, "Boil @water{1%l}.\n").unwrap();
+
+    let publish = || {
+        Command::cargo_bin("cook")
+            .unwrap()
+            .arg("publish")
+            .arg("-b")
+            .arg(temp_dir.path())
+            .arg("--out-dir")
+            .arg(site.path())
+            .assert()
+            .success();
+    };
+    publish();
+    assert!(site.path().join("Extra.html").exists());
+    std::fs::remove_file(&extra).unwrap();
+    publish();
+
+    assert!(!site.path().join("Extra.html").exists());
+    assert!(site.path().join("notes.txt").exists());
+}
+
+#[test]
+fn test_cli_publish_refuses_collection_dir() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["publish", "--out-dir", "."])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("holds the recipes"));
+    assert!(temp_dir.path().join("simple.cook").exists());
+}
+
+#[test]
+fn test_cli_publish_requires_target() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env_remove("COOK_PUBLISH_TO")
+        .arg("publish")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--out-dir"));
+}
+
+#[test]
+fn test_cli_import_crouton() {
+    let temp_dir = TempDir::new().unwrap();
+    let crumb = temp_dir.path().join("Banana Bread.crumb");
+    std::fs::write(
+        &crumb,
+        r#"{
+            "name": "Banana Bread",
+            "serves": 8,
+            "duration": 15,
+            "cookingDuration": 60,
+            "webLink": "https://example.com/banana-bread",
+            "ingredients": [
+                {"order": 1, "ingredient": {"name": "flour"}, "quantity": {"amount": 250, "quantityType": "GRAMS"}},
+                {"order": 0, "ingredient": {"name": "bananas"}, "quantity": {"amount": 3, "quantityType": "ITEM"}},
+                {"order": 2, "ingredient": {"name": "butter"}}
+            ],
+            "steps": [
+                {"order": 0, "step": "Mash the bananas with a fork.", "isSection": false},
+                {"order": 1, "step": "Fold in the flour and bake.", "isSection": false}
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["import", "crouton", "Banana Bread.crumb"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 recipes imported"));
+
+    let recipe = std::fs::read_to_string(temp_dir.path().join("Banana Bread.cook")).unwrap();
+    assert!(recipe.contains("servings: 8"));
+    assert!(recipe.contains("cook time: 60 minutes"));
+    assert!(recipe.contains("- @butter{}"));
+    assert!(recipe.contains("Mash the @bananas{3} with a fork."));
+    assert!(recipe.contains("Fold in the @flour{250%g} and bake."));
+
+    // Existing recipes are kept without --force
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["import", "crouton", "Banana Bread.crumb"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("--force"));
+}
+
+#[test]
+fn test_cli_import_whisk() {
+    let temp_dir = TempDir::new().unwrap();
+    let export = temp_dir.path().join("export.json");
+    std::fs::write(
+        &export,
+        r#"{"recipes": [{
+            "name": "Weeknight Dal",
+            "servings": 4,
+            "durations": {"cook_time": 30},
+            "ingredients": [
+                {"text": "1 cup red lentils, rinsed"},
+                {"name": "onion", "amount": 1},
+                "Salt to taste"
+            ],
+            "instructions": {"steps": [
+                {"text": "Fry the onion."},
+                {"text": "Add the red lentils and simmer."}
+            ]}
+        }]}"#,
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["import", "whisk", "export.json", "-o", "imported"])
+        .assert()
+        .success();
+
+    let recipe =
+        std::fs::read_to_string(temp_dir.path().join("imported/Weeknight Dal.cook")).unwrap();
+    assert!(recipe.contains("cook time: 30 minutes"));
+    assert!(recipe.contains("- Salt to taste"));
+    assert!(recipe.contains("Fry the @onion{1}."));
+    assert!(recipe.contains("Add the @red lentils{1%cup}(rinsed) and simmer."));
+}
+
+#[test]
+fn test_cli_import_csv() {
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("recipes.csv"),
+        "Name,Yield,Ingredients,Directions\n\
+         Pancakes,4,\"2 eggs\n200 g flour\nButter for the pan\",\"1. Whisk the eggs and flour.\n2. Fry in butter.\"\n\
+         ,2,1 apple,Eat.\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["import", "csv", "recipes.csv"])
+        .args([
+            "--map",
+            "title=Name,servings=Yield,ingredients=Ingredients,steps=Directions",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 recipes imported"));
+
+    let recipe = std::fs::read_to_string(temp_dir.path().join("Pancakes.cook")).unwrap();
+    assert!(recipe.contains("servings: 4"));
+    assert!(recipe.contains("- Butter for the pan"));
+    assert!(recipe.contains("Whisk the @eggs{2} and @flour{200%g}."));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["import", "csv", "recipes.csv", "--map", "title=Title"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No column 'Title'"));
+}
+
+#[test]
+fn test_cli_import_inbox() {
+    let temp_dir = TempDir::new().unwrap();
+    let inbox = temp_dir.path().join("inbox");
+    std::fs::create_dir(&inbox).unwrap();
+    std::fs::write(
+        inbox.join("pancakes.txt"),
+        "Pancakes\nFluffy and quick.\nServes 4\n\nIngredients:\n- 200 g flour\n- 2 eggs\n\n\
+         Method:\n1. Whisk the flour and eggs.\n2. Fry in a pan.\n",
+    )
+    .unwrap();
+    std::fs::write(inbox.join("broken.cook"), "Add @flour{%kg}.\n").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["import", "inbox", "--once"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "✓ Imported pancakes.txt as Pancakes.cook",
+        ))
+        .stderr(predicate::str::contains("broken.cook"));
+
+    let recipe = std::fs::read_to_string(temp_dir.path().join("Pancakes.cook")).unwrap();
+    assert!(recipe.contains("servings: 4"));
+    assert!(recipe.contains("Whisk the @flour{200%g} and @eggs{2}."));
+    assert!(!inbox.join("pancakes.txt").exists());
+    // Files that can't be imported are set aside, not written
+    assert!(inbox.join("failed/broken.cook").exists());
+    assert!(!temp_dir.path().join("broken.cook").exists());
+}
+
+#[test]
+fn test_cli_export_grocy_unreachable() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["export", "grocy", "--all", "--dry-run"])
+        .args(["--url", "http://127.0.0.1:1", "--api-key", "secret"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to connect to Grocy"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_external_plugin() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let bin_dir = TempDir::new().unwrap();
+    let plugin = bin_dir.path().join("cook-hello");
+    std::fs::write(
+        &plugin,
+        "#!/bin/sh\necho \"hello $1 from $COOK_BASE_PATH\"\nexit 3\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let path = std::env::join_paths(
+        std::iter::once(bin_dir.path().to_path_buf())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("PATH", path)
+        .arg("hello")
+        .arg("world")
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("hello world from"))
+        .stdout(predicate::str::contains(
+            temp_dir.path().file_name().unwrap().to_str().unwrap(),
+        ));
+}
+
+#[test]
+fn test_cli_unknown_command() {
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("no-such-command")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no cook-no-such-command plugin"));
+}
+
+#[test]
+fn test_cli_completions_script() {
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("completions")
+        .arg("fish")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("complete"))
+        .stdout(predicate::str::contains("COMPLETE=fish"));
+}
+
+#[test]
+fn test_cli_completions_recipe_names() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    // What the fish script runs when completing `cook shopping-list si<TAB>`
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COMPLETE", "fish")
+        .args(["--", "cook", "shopping-list", "si"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("simple.cook"))
+        .stdout(predicate::str::contains("sauce.cook").not());
+}
+
+#[test]
+fn test_cli_man_pages() {
+    let out_dir = TempDir::new().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .arg("man")
+        .arg("--out-dir")
+        .arg(out_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("man pages"));
+
+    for page in [
+        "cook.1",
+        "cook-recipe.1",
+        "cook-recipe-read.1",
+        "cook-shopping-list.1",
+    ] {
+        let path = out_dir.path().join(page);
+        assert!(path.exists(), "{page} was not generated");
+    }
+    let content = std::fs::read_to_string(out_dir.path().join("cook-search.1")).unwrap();
+    assert!(content.contains(".TH"));
+    assert!(!out_dir.path().join("cook-man.1").exists());
+}
+
+#[test]
+fn test_cli_watch_requires_command() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("watch")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Nothing to run"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["watch", "--lint", "--", "recipe", "read", "simple.cook"])
+        .assert()
+        .failure();
+
+    // --notify is only for --validate
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["watch", "--lint", "--notify"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_migrate() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let recipe = temp_dir.path().join("legacy.cook");
+    std::fs::write(&recipe, ">> servings: 4\n\nMix @flour{200%g}.\n").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("migrate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+servings: 4"))
+        .stdout(predicate::str::contains("--write"));
+    assert!(std::fs::read_to_string(&recipe)
+        .unwrap()
+        .starts_with(">> servings"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["migrate", "--write"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Migrated legacy.cook"));
+    assert_eq!(
+        std::fs::read_to_string(&recipe).unwrap(),
+        "---\nservings: 4\n---\n\nMix @flour{200%g}.\n"
+    );
+}
+
+#[test]
+fn test_cli_config_file_defaults() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let config_file = temp_dir.path().join("config").join("cook.toml");
+    std::fs::write(&config_file, "[recipe]\nscale = 2\nformat = \"markdown\"\n").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("simple.cook")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("## Ingredients"))
+        .stdout(predicate::str::contains("4 cups"));
+
+    std::fs::write(&config_file, "[recipe]\nscael = 2\n").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("doctor")
+        .arg("config")
+        .assert()
+        .stdout(predicate::str::contains("❌"))
+        .stdout(predicate::str::contains("unknown field `scael`"));
+}
+
+#[test]
+fn test_cli_config_set_get_list() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let config_file = temp_dir.path().join("config").join("cook.toml");
+    std::fs::write(&config_file, "# Kept by cook config\n[recipe]\nscale = 2\n").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["config", "set", "server.port", "8080"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["config", "get", "server.port"])
+        .assert()
+        .success()
+        .stdout("8080\n");
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["config", "list", "--show-origin"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("recipe.scale = 2"))
+        .stdout(predicate::str::contains("cook.toml\tserver.port = 8080"));
+
+    // Unknown settings and wrong types are refused
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["config", "set", "server.prot", "8080"])
+        .assert()
+        .failure();
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["config", "set", "server.port", "high"])
+        .assert()
+        .failure();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["config", "unset", "recipe.scale"])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(&config_file).unwrap();
+    assert!(content.starts_with("# Kept by cook config"));
+    assert!(!content.contains("scale"));
+    assert!(content.contains("port = 8080"));
+}
+
+#[test]
+fn test_cli_parser_extensions() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["--extensions", "modifiers,alias", "recipe", "simple.cook"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("water"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["--extensions", "sparkles", "recipe", "simple.cook"])
+        .assert()
+        .failure();
+
+    std::fs::write(
+        temp_dir.path().join("config").join("cook.toml"),
+        "[parser]\nextensions = [\"all\"]\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["doctor", "config"])
+        .assert()
+        .stdout(predicate::str::contains("✓"))
+        .stdout(predicate::str::contains("cook.toml"));
+}
+
+#[test]
+fn test_cli_log_file() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let log_file = temp_dir.path().join("cook.log");
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env_remove("RUST_LOG")
+        .arg("-vv")
+        .arg("--log-file")
+        .arg(&log_file)
+        .arg("shopping-list")
+        .arg("simple.cook")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert!(log.contains("DEBUG"));
+}
+
+#[test]
+fn test_cli_quiet_conflicts_with_verbose() {
+    Command::cargo_bin("cook")
+        .unwrap()
+        .args(["-q", "-v", "recipe", "simple.cook"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_color_choice() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    // Piped output has no color codes by default
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env_remove("CLICOLOR_FORCE")
+        .args(["recipe", "simple.cook"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["--color", "always", "recipe", "simple.cook"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b["));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("CLICOLOR_FORCE", "1")
+        .args(["--color", "never", "recipe", "simple.cook"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_edit() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    // `true` stands in for an editor that saves without changes
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env_remove("VISUAL")
+        .env("EDITOR", "true")
+        .args(["edit", "pancake"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "✓ Breakfast/pancakes.cook is valid",
+        ));
+
+    // Without a terminal to pick from, ambiguous terms list the matches
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env_remove("VISUAL")
+        .env("EDITOR", "true")
+        .args(["edit", "with"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("matches several recipes"))
+        .stderr(predicate::str::contains("with_ref.cook"));
+}
+
+#[test]
+fn test_cli_config_dir_override() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let config_dir = TempDir::new().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("--config-dir")
+        .arg(config_dir.path())
+        .args(["config", "set", "--global", "server.port", "8123"])
+        .assert()
+        .success();
+    assert!(std::fs::read_to_string(config_dir.path().join("cook.toml"))
+        .unwrap()
+        .contains("port = 8123"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_CONFIG_DIR", config_dir.path())
+        .args(["config", "list", "--show-origin"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("server.port = 8123"));
+}
+
+#[test]
+fn test_cli_env_overrides() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    // Settings: COOK_<SECTION>_<NAME> over cook.toml
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_AI_PROVIDER", "anthropic")
+        .env("COOK_PARSER_EXTENSIONS", "modifiers,alias")
+        .args(["config", "list", "--show-origin"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "$COOK_AI_PROVIDER\tai.provider = \"anthropic\"",
+        ))
+        .stdout(predicate::str::contains(
+            "parser.extensions = [\"modifiers\", \"alias\"]",
+        ));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_SERVER_PORT", "high")
+        .args(["config", "get", "server.port"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Ignoring COOK_SERVER_PORT"));
+
+    // Options: COOK_<COMMAND>_<OPTION> when the option isn't given
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_SHOPPING_LIST_FORMAT", "json")
+        .args(["shopping-list", "simple.cook"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert!(serde_json::from_slice::<serde_json::Value>(&output).is_ok());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("COOK_SHOPPING_LIST_FORMAT", "json")
+        .args(["shopping-list", "simple.cook", "-f", "markdown"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{").not());
+}
+
+#[test]
+fn test_cli_jobs() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["-j", "2", "doctor", "validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total recipes scanned"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["--jobs", "0", "stats"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_metadata_cache() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let config_dir = TempDir::new().unwrap();
+
+    let stats = || {
+        Command::cargo_bin("cook")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .arg("--config-dir")
+            .arg(config_dir.path())
+            .args(["stats", "--format", "json", "--top", "1000"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+
+    let cold = stats();
+    assert!(config_dir
+        .path()
+        .join("cache")
+        .join("metadata.bin")
+        .is_file());
+    assert_eq!(stats(), cold);
+
+    // Changed recipes are parsed again
+    std::fs::write(
+        temp_dir.path().join("simple.cook"),
+        "Whisk @saffron{1%pinch} into @milk{1%cup}.\n",
+    )
+    .unwrap();
+    let changed = String::from_utf8(stats()).unwrap();
+    assert!(changed.contains("saffron"));
+}
+
+#[test]
+fn test_cli_base_path_sets_context() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let elsewhere = TempDir::new().unwrap();
+
+    // The collection's config/aisle.conf is used, not the current directory's
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(elsewhere.path())
+        .arg("--config-dir")
+        .arg(elsewhere.path())
+        .args(["doctor", "aisle", "-b"])
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("No aisle configuration found").not());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(elsewhere.path())
+        .env("COOK_BASE_PATH", temp_dir.path())
+        .args(["doctor", "aisle"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("No aisle configuration found").not());
+}
+
+#[test]
+fn test_cli_notify_dry_run() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(
+        temp_dir.path().join("week.menu"),
+        "== Monday 2024-03-18 ==\n\nDinner: @./simple{}\n\n== Friday 2024-03-29 ==\n\nDinner: @./simple{}\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["notify", "--dry-run", "--from", "2024-03-18"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Subject: Meal plan for the week of 18 March",
+        ))
+        .stdout(predicate::str::contains("Monday 18 March"))
+        .stdout(predicate::str::contains("Dinner: simple"))
+        .stdout(predicate::str::contains("pasta"))
+        .stdout(predicate::str::contains("Friday 29 March").not());
+
+    // Nowhere to send it
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("notify")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_server_ping_unreachable() {
+    // Nothing listens on port 1
+    Command::cargo_bin("cook")
+        .unwrap()
+        .args([
+            "server",
+            "ping",
+            "--url",
+            "http://127.0.0.1:1",
+            "--timeout",
+            "1s",
+        ])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("No answer from"));
+}
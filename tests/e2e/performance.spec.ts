@@ -306,4 +306,18 @@ test.describe('Performance', () => {
     // CLS should be less than 0.1 (good)
     expect(cls).toBeLessThan(0.1);
   });
+
+  test('should let browsers cache the stylesheet', async ({ page, request }) => {
+    await helpers.navigateTo('/');
+
+    const href = await page.locator('link[rel="stylesheet"]').first().getAttribute('href');
+    expect(href).toMatch(/^\/static\/css\/output\.css\?v=[0-9a-f]+$/);
+
+    const response = await request.get(href!);
+    expect(response.headers()['cache-control']).toContain('immutable');
+
+    const etag = response.headers()['etag'];
+    const revalidated = await request.get(href!, { headers: { 'If-None-Match': etag } });
+    expect(revalidated.status()).toBe(304);
+  });
 });
\ No newline at end of file
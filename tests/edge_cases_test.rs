@@ -170,7 +170,7 @@ fn test_recipe_with_extreme_scaling() {
         .assert()
         .success();
 
-    // Test with zero scaling factor - actually succeeds but with 0 quantities
+    // Test with zero scaling factor - rejected, it would empty the recipe
     Command::cargo_bin("cook")
         .unwrap()
         .current_dir(temp_dir.path())
@@ -178,7 +178,8 @@ fn test_recipe_with_extreme_scaling() {
         .arg("read")
         .arg("normal.cook:0")
         .assert()
-        .success(); // Zero scaling actually works, just shows 0 quantities
+        .failure()
+        .stderr(predicate::str::contains("has to be a number above 0"));
 }
 
 #[test]
@@ -0,0 +1,49 @@
+#[path = "common/mod.rs"]
+mod common;
+
+use cookcli::{render_recipe, RecipeFormat};
+
+#[test]
+fn test_library_load_and_render_recipe() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let ctx = common::create_test_context(temp_dir.path());
+
+    let recipe = ctx.load_recipe("simple:2").unwrap();
+    assert_eq!(recipe.title, "simple");
+    assert_eq!(recipe.scale, 2.0);
+
+    let mut out = Vec::new();
+    render_recipe(&recipe, RecipeFormat::Markdown, &mut out).unwrap();
+    let markdown = String::from_utf8(out).unwrap();
+    assert!(markdown.contains("pasta"));
+
+    assert!(ctx.load_recipe("missing").is_err());
+    assert!(ctx.load_recipe("simple:lots").is_err());
+}
+
+#[test]
+fn test_library_shopping_list() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let ctx = common::create_test_context(temp_dir.path());
+
+    let list = ctx.shopping_list(&["simple", "with_ref"], false).unwrap();
+    let ingredients: Vec<String> = list.into_iter().map(|(name, _)| name).collect();
+    for expected in ["pasta", "tomatoes", "garlic"] {
+        assert!(
+            ingredients.iter().any(|i| i == expected),
+            "{expected} missing from {ingredients:?}"
+        );
+    }
+
+    let list = ctx.shopping_list(&["with_ref"], true).unwrap();
+    assert!(!list.into_iter().any(|(name, _)| name == "garlic"));
+}
+
+#[test]
+fn test_library_search() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    let ctx = common::create_test_context(temp_dir.path());
+
+    let results = ctx.search("garlic").unwrap();
+    assert!(results.iter().any(|r| r.name().as_deref() == Some("sauce")));
+}
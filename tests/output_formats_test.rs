@@ -68,6 +68,50 @@ fn test_recipe_markdown_output() {
         .stdout(predicate::str::contains("## Steps"));
 }
 
+#[test]
+fn test_recipe_fractions() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["--fractions", "ascii", "recipe", "read", "-f", "markdown"])
+        .arg("Breakfast/pancakes.cook")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 1/2 cups"));
+
+    // JSON keeps the exact number
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["--fractions", "ascii", "recipe", "read", "-f", "json"])
+        .arg("Breakfast/pancakes.cook")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1.5"))
+        .stdout(predicate::str::contains("1 1/2").not());
+}
+
+#[test]
+fn test_recipe_ssml_output() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("read")
+        .arg("-f")
+        .arg("ssml")
+        .arg("simple.cook")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("<speak><s>Simple Recipe.</s>"))
+        .stdout(predicate::str::contains("<s>Step 1.</s>"))
+        .stdout(predicate::str::contains("<break time=\"2s\"/>"));
+}
+
 #[test]
 fn test_recipe_cooklang_output() {
     let temp_dir = common::setup_test_recipes().unwrap();
@@ -132,6 +176,25 @@ fn test_shopping_list_yaml_output() {
     assert!(content.contains("ingredients:") || content.contains("- name:"));
 }
 
+#[test]
+fn test_shopping_list_vtodo_output() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("shopping-list")
+        .arg("-f")
+        .arg("vtodo")
+        .arg("simple.cook")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("BEGIN:VCALENDAR\r\n"))
+        .stdout(predicate::str::contains("BEGIN:VTODO"))
+        .stdout(predicate::str::contains("SUMMARY:water"))
+        .stdout(predicate::str::contains("CATEGORIES:"));
+}
+
 #[ignore]
 #[test]
 fn test_shopping_list_human_output_to_file() {
@@ -115,6 +115,45 @@ fn test_pantry_expiring_with_days() {
         .stdout(predicate::str::contains("lettuce"));
 }
 
+#[test]
+fn test_pantry_expiring_within_duration() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("expiring")
+        .arg("--within")
+        .arg("1w")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Items Expiring Within 7 Days"))
+        .stdout(predicate::str::contains("milk"))
+        .stdout(predicate::str::contains("lettuce").not());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("expiring")
+        .arg("--within")
+        .arg("2w")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lettuce"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("expiring")
+        .arg("--within")
+        .arg("soon")
+        .assert()
+        .failure();
+}
+
 #[test]
 fn test_pantry_expiring_expired_items() {
     let temp_dir = common::setup_test_recipes().unwrap();
@@ -355,3 +394,241 @@ fn test_pantry_format_aliases() {
         .success()
         .stdout(predicate::str::contains("Recipes"));
 }
+
+#[test]
+fn test_pantry_add_new_item() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("add")
+        .arg("rice")
+        .arg("2kg")
+        .arg("--expire")
+        .arg("2030-01-01")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added rice to pantry"));
+
+    let content = std::fs::read_to_string(temp_dir.path().join("config/pantry.conf")).unwrap();
+    assert!(content.contains(r#"rice = { quantity = "2%kg", expire = "2030-01-01" }"#));
+    // Existing items keep their attributes
+    assert!(content.contains(r#"flour = { quantity = "5%kg", low = "1%kg" }"#));
+}
+
+#[test]
+fn test_pantry_add_accumulates_with_unit_conversion() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("add")
+        .arg("flour")
+        .arg("500g")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("now 5.5%kg"));
+}
+
+#[test]
+fn test_pantry_consume() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("consume")
+        .arg("eggs")
+        .arg("4")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("8 left"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("consume")
+        .arg("yogurt")
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(temp_dir.path().join("config/pantry.conf")).unwrap();
+    assert!(!content.contains("yogurt"));
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("consume")
+        .arg("caviar")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not found"));
+}
+
+#[test]
+fn test_pantry_list_json_format() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    let output = Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("-f")
+        .arg("json")
+        .arg("list")
+        .arg("--section")
+        .arg("dairy")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let json: Value = serde_json::from_slice(&output).expect("Valid JSON output");
+    let items = json.get("items").unwrap().as_array().unwrap();
+    assert_eq!(items.len(), 4);
+    assert!(items
+        .iter()
+        .all(|item| item.get("section").unwrap().as_str().unwrap() == "dairy"));
+}
+
+#[test]
+fn test_pantry_list_expiring() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("list")
+        .arg("--expiring")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("milk"))
+        .stdout(predicate::str::contains("lettuce").not());
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("pantry")
+        .arg("list")
+        .arg("--expiring=2w")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lettuce"));
+}
+
+#[test]
+fn test_recipe_cooked_depletes_pantry() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("cooked")
+        .arg("simple.cook")
+        .arg("--scale")
+        .arg("2")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Marked simple.cook as cooked (scale 2)",
+        ))
+        .stdout(predicate::str::contains("pasta: used 400 g, 0.6 kg left"));
+
+    let pantry = std::fs::read_to_string(temp_dir.path().join("config/pantry.conf")).unwrap();
+    assert!(pantry.contains(r#"pasta = { quantity = "0.6%kg", low = "200%g" }"#));
+
+    let log = std::fs::read_to_string(temp_dir.path().join("config/cooked.jsonl")).unwrap();
+    let event: Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+    assert_eq!(event["recipe"], "simple.cook");
+    assert_eq!(event["scale"], 2.0);
+}
+
+#[test]
+fn test_recipe_cooked_without_pantry() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .arg("recipe")
+        .arg("cooked")
+        .arg("simple.cook")
+        .arg("--no-pantry")
+        .assert()
+        .success();
+
+    let pantry = std::fs::read_to_string(temp_dir.path().join("config/pantry.conf")).unwrap();
+    assert!(pantry.contains(r#"quantity = "1%kg", low = "200%g""#));
+    assert!(temp_dir.path().join("config/cooked.jsonl").exists());
+}
+
+#[test]
+fn test_recipe_cooked_invalid_scale() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    for scale in ["0", "-1", "NaN", "inf"] {
+        Command::cargo_bin("cook")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args(["recipe", "cooked", "simple.cook"])
+            .arg(format!("--scale={scale}"))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("has to be a number above 0"));
+    }
+    assert!(!temp_dir.path().join("config/cooked.jsonl").exists());
+}
+
+#[test]
+fn test_pantry_import_receipt_requires_api_key() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+    std::fs::write(temp_dir.path().join("receipt.txt"), "MILK 1L  1.29\n").unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env_remove("CLAUDE_API_KEY")
+        .arg("pantry")
+        .arg("import-receipt")
+        .arg("receipt.txt")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("CLAUDE_API_KEY"));
+}
+
+#[test]
+fn test_pantry_consume_with_density() {
+    let temp_dir = common::setup_test_recipes().unwrap();
+
+    // Without a density, volume can't be taken from weight
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["pantry", "consume", "flour", "1000ml"])
+        .assert()
+        .failure();
+
+    std::fs::write(
+        temp_dir.path().join("config/units.toml"),
+        "[densities]\nflour = 0.5\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("cook")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(["pantry", "consume", "flour", "1000ml"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("4.5 kg left"));
+}